@@ -95,8 +95,10 @@ if __name__ == "__main__":
         "risc0-circuit-rv32im-sys": "risc0/risc0/circuit/rv32im-sys",
         "risc0-core": "risc0/risc0/core",
         "risc0-groth16": "risc0/risc0/groth16",
+        "risc0-prove-verification": "risc0/risc0/prove-verification",
         "risc0-r0vm": "risc0/risc0/r0vm",
         "risc0-sys": "risc0/risc0/sys",
+        "risc0-verify": "risc0/risc0/verify",
         "risc0-zkp": "risc0/risc0/zkp",
         "risc0-zkvm": "risc0/risc0/zkvm",
         "risc0-zkvm-platform": "risc0/risc0/zkvm/platform",
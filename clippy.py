@@ -16,9 +16,11 @@ CRATES = [
     ("risc0-circuit-rv32im-sys", "default"),
     ("risc0-core", None),
     ("risc0-groth16", None),
+    ("risc0-prove-verification", None),
     ("risc0-r0vm", None),
     ("risc0-sys", None),
     ("risc0-tools", None),
+    ("risc0-verify", None),
     ("risc0-zkp", "prove"),
     ("risc0-zkvm", "prove"),
     ("risc0-zkvm-platform", None),
@@ -14,7 +14,7 @@
 
 extern crate alloc;
 
-use alloc::collections::BTreeMap;
+use alloc::{collections::BTreeMap, vec::Vec};
 
 use anyhow::{anyhow, bail, Context, Result};
 use elf::{endian::LittleEndian, file::Class, ElfBytes};
@@ -32,7 +32,44 @@ pub struct Program {
 impl Program {
     /// Initialize a RISC Zero Program from an appropriate ELF file
     pub fn load_elf(input: &[u8], max_mem: u32) -> Result<Program> {
+        let elf = ElfSegments::parse(input, max_mem)?;
         let mut image: BTreeMap<u32, u32> = BTreeMap::new();
+        for segment in elf.segments.iter() {
+            for i in (0..segment.mem_len).step_by(WORD_SIZE) {
+                let start = core::cmp::min(i as usize, segment.data.len());
+                let end = core::cmp::min(start + WORD_SIZE, segment.data.len());
+                let mut word = [0u8; WORD_SIZE];
+                word[..end - start].copy_from_slice(&segment.data[start..end]);
+                image.insert(segment.vaddr + i, u32::from_le_bytes(word));
+            }
+        }
+        Ok(Program {
+            entry: elf.entry,
+            image,
+        })
+    }
+}
+
+/// The loadable segments of an ELF file, borrowed from the file rather than copied out word by
+/// word as [Program::load_elf] does.
+pub struct ElfSegments<'a> {
+    /// The entrypoint of the program
+    pub entry: u32,
+
+    segments: Vec<ElfSegment<'a>>,
+}
+
+/// A loadable segment, which is `data` followed by zeros up to `mem_len` bytes from `vaddr`.
+pub(crate) struct ElfSegment<'a> {
+    pub(crate) vaddr: u32,
+    pub(crate) data: &'a [u8],
+    pub(crate) mem_len: u32,
+}
+
+impl<'a> ElfSegments<'a> {
+    /// Parse and validate the loadable segments of an appropriate ELF file, as
+    /// [Program::load_elf] does.
+    pub fn parse(input: &'a [u8], max_mem: u32) -> Result<Self> {
         let elf = ElfBytes::<LittleEndian>::minimal_parse(input)
             .map_err(|err| anyhow!("Elf parse error: {err}"))?;
         if elf.ehdr.class != Class::ELF32 {
@@ -56,6 +93,7 @@ impl Program {
         if segments.len() > 256 {
             bail!("Too many program headers");
         }
+        let mut loadable = Vec::new();
         for segment in segments.iter().filter(|x| x.p_type == elf::abi::PT_LOAD) {
             let file_size: u32 = segment
                 .p_filesz
@@ -82,27 +120,48 @@ impl Program {
                 .p_offset
                 .try_into()
                 .map_err(|err| anyhow!("offset is larger than 32 bits. {err}"))?;
-            for i in (0..mem_size).step_by(WORD_SIZE) {
-                let addr = vaddr.checked_add(i).context("Invalid segment vaddr")?;
-                if addr >= max_mem {
-                    bail!("Address [0x{addr:08x}] exceeds maximum address for guest programs [0x{max_mem:08x}]");
-                }
-                if i >= file_size {
-                    // Past the file size, all zeros.
-                    image.insert(addr, 0);
-                } else {
-                    let mut word = 0;
-                    // Don't read past the end of the file.
-                    let len = core::cmp::min(file_size - i, WORD_SIZE as u32);
-                    for j in 0..len {
-                        let offset = (offset + i + j) as usize;
-                        let byte = input.get(offset).context("Invalid segment offset")?;
-                        word |= (*byte as u32) << (j * 8);
-                    }
-                    image.insert(addr, word);
+
+            // The segment is loaded a word at a time, so a partial last word is padded.
+            let mem_len = mem_size.next_multiple_of(WORD_SIZE as u32);
+            if mem_len > 0 {
+                let last = vaddr
+                    .checked_add(mem_len - WORD_SIZE as u32)
+                    .context("Invalid segment vaddr")?;
+                if last >= max_mem {
+                    bail!("Address [0x{last:08x}] exceeds maximum address for guest programs [0x{max_mem:08x}]");
                 }
             }
+            // Don't read past the end of the file.
+            let start = offset as usize;
+            let len = core::cmp::min(file_size, mem_len) as usize;
+            let data = input
+                .get(start..start + len)
+                .context("Invalid segment offset")?;
+            loadable.push(ElfSegment {
+                vaddr,
+                data,
+                mem_len,
+            });
         }
-        Ok(Program { entry, image })
+        Ok(Self {
+            entry,
+            segments: loadable,
+        })
+    }
+
+    /// The end of the code and data loaded from the ELF, where the program break starts.
+    ///
+    /// This is the entrypoint if the ELF has nothing to load.
+    pub fn end(&self) -> u32 {
+        self.segments
+            .iter()
+            .filter(|segment| segment.mem_len > 0)
+            .map(|segment| segment.vaddr + segment.mem_len)
+            .max()
+            .unwrap_or(self.entry)
+    }
+
+    pub(crate) fn segments(&self) -> &[ElfSegment<'a>] {
+        &self.segments
     }
 }
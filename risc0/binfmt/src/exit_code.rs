@@ -50,8 +50,8 @@ pub enum ExitCode {
 
     /// This indicates that the guest exited upon reaching the session limit set by the host.
     ///
-    /// NOTE: The current version of the RISC Zero zkVM will never exit with an exit code of SessionLimit.
-    /// This is because the system cannot currently prove that the session limit as been reached.
+    /// NOTE: A session that ends with SessionLimit can be inspected, but not proven. This is
+    /// because the system cannot currently prove that the session limit has been reached.
     SessionLimit,
 }
 
@@ -88,6 +88,15 @@ impl ExitCode {
         }
     }
 
+    /// The user part of a [Paused][ExitCode::Paused] exit code, which the guest sets to tell
+    /// the host why it paused. Returns `None` for any other exit code.
+    pub fn pause_code(&self) -> Option<u32> {
+        match self {
+            ExitCode::Paused(user_exit) => Some(*user_exit),
+            _ => None,
+        }
+    }
+
     /// True if the exit code is Halted(0), indicating the program guest exited with an ok status.
     pub fn is_ok(&self) -> bool {
         matches!(self, ExitCode::Halted(0))
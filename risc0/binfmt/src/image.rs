@@ -14,7 +14,12 @@
 
 extern crate alloc;
 
-use alloc::{collections::BTreeMap, vec, vec::Vec};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec,
+    vec::Vec,
+};
+use core::ops::Range;
 
 use anyhow::{anyhow, ensure, Result};
 use risc0_zkp::core::{
@@ -28,7 +33,10 @@ use risc0_zkvm_platform::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{elf::Program, Digestible, SystemState};
+use crate::{
+    elf::{ElfSegments, Program},
+    Digestible, SystemState,
+};
 
 /// An image of a zkVM guest's memory
 ///
@@ -189,6 +197,10 @@ impl MemoryImage {
     /// The result is a MemoryImage with the ELF of `program` loaded (but
     /// execution not yet begun), and with the page table Merkle tree
     /// constructed.
+    ///
+    /// Larger pages make the page table Merkle tree shallower, at the cost of more cycles to page
+    /// in each page. Note that the rv32im circuit currently only supports images with
+    /// [PAGE_SIZE]-byte pages; executing an image with any other page size is rejected.
     pub fn new(program: &Program, page_size: u32) -> Result<Self> {
         // Compute the page table hashes except for the very last root hash.
         let info = PageTableInfo::new(PAGE_TABLE.start() as u32, page_size)?;
@@ -210,6 +222,52 @@ impl MemoryImage {
         Ok(img)
     }
 
+    /// Construct the initial memory image for the loadable segments of an ELF.
+    ///
+    /// This is equivalent to [MemoryImage::new] on the [Program] loaded from the same ELF, but
+    /// copies each segment into the pages of the image directly, rather than through a map of
+    /// every word of the program.
+    pub fn from_elf_segments(elf: &ElfSegments, page_size: u32) -> Result<Self> {
+        let info = PageTableInfo::new(PAGE_TABLE.start() as u32, page_size)?;
+        let mut img = Self {
+            pages: BTreeMap::new(),
+            info,
+            pc: elf.entry,
+        };
+
+        for segment in elf.segments() {
+            let end = segment.vaddr + segment.mem_len;
+            if end as usize > GUEST_MAX_MEM {
+                anyhow::bail!("Invalid Elf Program, address outside GUEST_MAX_MEM");
+            }
+            let mut addr = segment.vaddr;
+            let mut data = segment.data;
+            while addr < end {
+                let page_end = core::cmp::min(round_up(addr + 1, page_size), end);
+                let len = (page_end - addr) as usize;
+                let split = core::cmp::min(len, data.len());
+                let (bytes, rest) = data.split_at(split);
+                if !bytes.is_empty() {
+                    img.store_region_in_page(addr, bytes);
+                }
+                if split < len {
+                    // Past the end of the file data, all zeros.
+                    img.store_region_in_page(addr + split as u32, &vec![0; len - split]);
+                }
+                data = rest;
+                addr = page_end;
+            }
+        }
+
+        img.hash_pages();
+        Ok(img)
+    }
+
+    /// The size of each page of this image, in bytes.
+    pub fn page_size(&self) -> u32 {
+        self.info.page_size
+    }
+
     /// Load a page specified by page_idx. If no page is found, a zero page is
     /// returned.
     pub fn load_page(&self, page_idx: u32) -> Vec<u8> {
@@ -259,7 +317,29 @@ impl MemoryImage {
 
     /// Calculate and update the image merkle tree within this image.
     pub fn hash_pages(&mut self) {
-        self.hash_pages_iter(0..self.info.num_pages)
+        // Most of the page table covers memory that the image does not use, and so consists of
+        // pages that hold identical entries. Hash each distinct page of this kind only once.
+        let mut touched: BTreeSet<u32> = self.pages.keys().copied().collect();
+        let mut untouched: BTreeMap<Vec<u8>, Digest> = BTreeMap::new();
+        for page_idx in 0..self.info.num_pages {
+            let entry_addr = self.info.get_page_entry_addr(page_idx);
+            let digest = match self.pages.get(&page_idx) {
+                _ if touched.contains(&page_idx) => {
+                    touched.insert(self.info.get_page_index(entry_addr));
+                    self.hash_page(page_idx)
+                }
+                Some(page) => match untouched.get(page.as_slice()) {
+                    Some(digest) => *digest,
+                    None => {
+                        let digest = hash_page_bytes(page);
+                        untouched.insert(page.clone(), digest);
+                        digest
+                    }
+                },
+                None => self.info.zero_page_hash,
+            };
+            self.store_region_in_page(entry_addr, digest.as_bytes());
+        }
     }
 
     /// Calculate and update the image merkle tree within this image based on
@@ -344,6 +424,121 @@ impl MemoryImage {
             pc: self.pc,
         }
     }
+
+    /// Produce a [MemoryProof] for the given range of addresses.
+    ///
+    /// The proof contains every page that overlaps the range, along with the page table pages
+    /// on the path from each of those pages to the root. Pages missing from this image are
+    /// assumed to be zero; for a partial image, use [MemoryProof::verify] to check that the
+    /// proof is valid.
+    pub fn prove_memory(&self, range: Range<u32>) -> Result<MemoryProof> {
+        ensure!(!range.is_empty(), "Empty memory range: {range:x?}");
+        ensure!(
+            range.end as usize <= MEM_SIZE,
+            "Memory range out of bounds: {range:x?}"
+        );
+
+        let mut pages = BTreeMap::new();
+        let first = self.info.get_page_index(range.start);
+        let last = self.info.get_page_index(range.end - 1);
+        for mut page_idx in first..=last {
+            loop {
+                if pages.contains_key(&page_idx) {
+                    break;
+                }
+                pages.insert(page_idx, self.load_page(page_idx));
+                if page_idx == self.info.root_idx {
+                    break;
+                }
+                page_idx = self
+                    .info
+                    .get_page_index(self.info.get_page_entry_addr(page_idx));
+            }
+        }
+
+        Ok(MemoryProof { pc: self.pc, pages })
+    }
+}
+
+/// A Merkle proof of the contents of a range of guest memory.
+///
+/// A [MemoryProof] lets external systems check specific values in guest memory against an image
+/// ID, e.g. the pre- or post-state of a segment, without re-executing the guest.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MemoryProof {
+    /// The program counter of the image.
+    pub pc: u32,
+
+    /// The pages needed to check the proof, indexed by page index.
+    pub pages: BTreeMap<u32, Vec<u8>>,
+}
+
+impl MemoryProof {
+    /// Check that every page in this proof is committed to by the image with the given ID.
+    pub fn verify(&self, image_id: &Digest) -> Result<()> {
+        ensure!(
+            self.image_id()? == *image_id,
+            "Memory proof does not match image ID {image_id}"
+        );
+
+        let info = PageTableInfo::default();
+        for (page_idx, page) in &self.pages {
+            ensure!(
+                page.len() == info.page_size as usize,
+                "Invalid size for page 0x{page_idx:05x}"
+            );
+        }
+
+        for (page_idx, page) in &self.pages {
+            if *page_idx == info.root_idx {
+                continue;
+            }
+            let entry_addr = info.get_page_entry_addr(*page_idx);
+            let parent_idx = info.get_page_index(entry_addr);
+            let parent = self.pages.get(&parent_idx).ok_or(anyhow!(
+                "Memory proof is missing parent of page 0x{page_idx:05x}"
+            ))?;
+            let offset = (entry_addr - info.get_page_addr(parent_idx)) as usize;
+            let entry = Digest::try_from(&parent[offset..offset + DIGEST_BYTES]).unwrap();
+            ensure!(
+                hash_page_bytes(page) == entry,
+                "Page 0x{page_idx:05x} does not match its page table entry"
+            );
+        }
+        Ok(())
+    }
+
+    /// Return the ID of the image committed to by the root page of this proof.
+    pub fn image_id(&self) -> Result<Digest> {
+        let info = PageTableInfo::default();
+        let root_page = self
+            .pages
+            .get(&info.root_idx)
+            .ok_or(anyhow!("Memory proof is missing the root page"))?;
+        ensure!(
+            root_page.len() == info.page_size as usize,
+            "Invalid root page size"
+        );
+        let root = hash_page_bytes(&root_page[..(info.root_addr - info.root_page_addr) as usize]);
+        Ok(compute_image_id(&root, self.pc))
+    }
+
+    /// Read the contents of the given range of memory from this proof.
+    ///
+    /// The result is only meaningful once the proof has been checked with [MemoryProof::verify].
+    pub fn read(&self, range: Range<u32>) -> Result<Vec<u8>> {
+        let info = PageTableInfo::default();
+        let mut bytes = Vec::with_capacity(range.len());
+        for addr in range {
+            let page_idx = info.get_page_index(addr);
+            let page = self
+                .pages
+                .get(&page_idx)
+                .ok_or(anyhow!("Memory proof does not cover address 0x{addr:08x}"))?;
+            bytes.push(page[(addr - info.get_page_addr(page_idx)) as usize]);
+        }
+        Ok(bytes)
+    }
 }
 
 fn hash_page_bytes(page: &[u8]) -> Digest {
@@ -363,10 +558,15 @@ mod tests {
     use risc0_zkvm_platform::{
         memory::{GUEST_MAX_MEM, PAGE_TABLE, STACK_TOP, SYSTEM, TEXT_START},
         syscall::DIGEST_BYTES,
+        WORD_SIZE,
     };
     use test_log::test;
 
-    use crate::{elf::Program, image::PageTableInfo, MemoryImage};
+    use crate::{
+        elf::{ElfSegments, Program},
+        image::PageTableInfo,
+        MemoryImage,
+    };
 
     fn page_table_size(max_mem: u32, page_size: u32) -> u32 {
         PageTableInfo::new(max_mem, page_size)
@@ -374,6 +574,62 @@ mod tests {
             ._page_table_size
     }
 
+    #[test]
+    fn memory_proof() {
+        let program = Program::load_elf(MULTI_TEST_ELF, GUEST_MAX_MEM as u32).unwrap();
+        let image = MemoryImage::new(&program, 1024).unwrap();
+        let image_id = image.compute_id();
+
+        let range = TEXT_START..TEXT_START + 3000;
+        let proof = image.prove_memory(range.clone()).unwrap();
+        proof.verify(&image_id).unwrap();
+        assert_eq!(proof.image_id().unwrap(), image_id);
+
+        let mut expected = vec![0u8; range.len()];
+        let mut addr = range.start;
+        while addr < range.end {
+            let page_end = (addr / 1024 + 1) * 1024;
+            let len = (page_end.min(range.end) - addr) as usize;
+            let offset = (addr - range.start) as usize;
+            image
+                .load_region_in_page(addr, &mut expected[offset..offset + len])
+                .unwrap();
+            addr += len as u32;
+        }
+        assert_eq!(proof.read(range).unwrap(), expected);
+        assert!(proof.read(STACK_TOP..STACK_TOP + 4).is_err());
+
+        let mut tampered = proof.clone();
+        let page_idx = TEXT_START / 1024;
+        tampered.pages.get_mut(&page_idx).unwrap()[0] ^= 1;
+        assert!(tampered.verify(&image_id).is_err());
+    }
+
+    #[test]
+    fn hash_pages_matches_full_rehash() {
+        let program = Program::load_elf(MULTI_TEST_ELF, GUEST_MAX_MEM as u32).unwrap();
+        let image = MemoryImage::new(&program, 1024).unwrap();
+
+        let mut expected = image.clone();
+        expected.hash_pages_iter(0..expected.info.num_pages);
+        assert_eq!(image.compute_id(), expected.compute_id());
+        assert_eq!(image.pages, expected.pages);
+    }
+
+    #[test]
+    fn from_elf_segments_matches_program() {
+        let program = Program::load_elf(MULTI_TEST_ELF, GUEST_MAX_MEM as u32).unwrap();
+        let elf = ElfSegments::parse(MULTI_TEST_ELF, GUEST_MAX_MEM as u32).unwrap();
+        let expected = MemoryImage::new(&program, 1024).unwrap();
+        let image = MemoryImage::from_elf_segments(&elf, 1024).unwrap();
+        assert_eq!(image.pc, expected.pc);
+        assert_eq!(image.pages, expected.pages);
+        assert_eq!(image.compute_id(), expected.compute_id());
+
+        let last = *program.image.keys().last().unwrap();
+        assert_eq!(elf.end(), last + WORD_SIZE as u32);
+    }
+
     #[test]
     fn check_integrity() {
         const PAGE_SIZE: u32 = 1024;
@@ -27,9 +27,9 @@ mod image;
 mod sys_state;
 
 #[cfg(not(target_os = "zkvm"))]
-pub use self::image::{MemoryImage, PageTableInfo};
+pub use self::image::{MemoryImage, MemoryProof, PageTableInfo};
 pub use crate::{
-    elf::Program,
+    elf::{ElfSegments, Program},
     exit_code::{ExitCode, InvalidExitCodeError},
     hash::{tagged_iter, tagged_list, tagged_list_cons, tagged_struct, Digestible},
     sys_state::{read_sha_halfs, write_sha_halfs, DecodeError, SystemState},
@@ -40,7 +40,7 @@ pub use crate::{
 pub fn compute_image_id(elf: &[u8]) -> anyhow::Result<risc0_zkp::core::digest::Digest> {
     use risc0_zkvm_platform::{memory::GUEST_MAX_MEM, PAGE_SIZE};
 
-    let program = Program::load_elf(elf, GUEST_MAX_MEM as u32)?;
-    let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
+    let elf = ElfSegments::parse(elf, GUEST_MAX_MEM as u32)?;
+    let image = MemoryImage::from_elf_segments(&elf, PAGE_SIZE as u32)?;
     Ok(image.compute_id())
 }
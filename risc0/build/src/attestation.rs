@@ -0,0 +1,246 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use risc0_binfmt::compute_image_id;
+use risc0_zkp::core::{
+    digest::Digest,
+    hash::sha::{Impl, Sha256},
+};
+use serde::{Deserialize, Serialize};
+
+/// The file name suffix used for attestations written next to each ELF.
+pub const ATTESTATION_SUFFIX: &str = ".attestation.json";
+
+/// A record of how a guest ELF was built by [crate::docker_build_attested], and of the image ID
+/// it produced.
+///
+/// Anyone holding the same sources can rebuild the guest with the recorded toolchain and flags
+/// and check that the result matches the attestation with [BuildAttestation::validate_elf].
+///
+/// The attestation is sealed with a SHA-256 digest over its contents, which detects accidental
+/// or partial modification. It does not identify who built the guest; to establish that, sign
+/// the attestation file with an external tool.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildAttestation {
+    /// The name of the package containing the guest.
+    pub package: String,
+
+    /// The name of the guest binary.
+    pub binary: String,
+
+    /// The digest of the package sources, as computed by [source_digest].
+    pub source_digest: String,
+
+    /// The docker image providing the toolchain used for the build.
+    pub toolchain: String,
+
+    /// The `RUSTFLAGS` used for the build.
+    pub rustflags: String,
+
+    /// The cargo features enabled for the build.
+    pub features: Vec<String>,
+
+    /// The SHA-256 digest of the ELF.
+    pub elf_digest: String,
+
+    /// The image ID of the ELF.
+    pub image_id: String,
+
+    /// The SHA-256 digest of the JSON encoding of all other fields, with this field empty.
+    pub digest: String,
+}
+
+impl BuildAttestation {
+    pub(crate) fn new(
+        package: &str,
+        binary: &str,
+        source_digest: Digest,
+        toolchain: &str,
+        rustflags: &str,
+        features: &[String],
+        elf: &[u8],
+    ) -> Result<Self> {
+        let mut attestation = Self {
+            package: package.to_string(),
+            binary: binary.to_string(),
+            source_digest: source_digest.to_string(),
+            toolchain: toolchain.to_string(),
+            rustflags: rustflags.to_string(),
+            features: features.to_vec(),
+            elf_digest: Impl::hash_bytes(elf).to_string(),
+            image_id: compute_image_id(elf)?.to_string(),
+            digest: String::new(),
+        };
+        attestation.digest = attestation.compute_digest()?.to_string();
+        Ok(attestation)
+    }
+
+    /// Parse an attestation from JSON, and check its digest.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let attestation: Self = serde_json::from_str(json).context("malformed attestation")?;
+        attestation.verify()?;
+        Ok(attestation)
+    }
+
+    /// Encode this attestation as JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Check that the digest of this attestation matches its contents.
+    pub fn verify(&self) -> Result<()> {
+        ensure!(
+            self.digest == self.compute_digest()?.to_string(),
+            "attestation digest does not match its contents"
+        );
+        Ok(())
+    }
+
+    /// Check that `elf` is the ELF described by this attestation, and return its image ID.
+    pub fn validate_elf(&self, elf: &[u8]) -> Result<Digest> {
+        self.verify()?;
+        let elf_digest = Impl::hash_bytes(elf).to_string();
+        if elf_digest != self.elf_digest {
+            bail!(
+                "ELF digest mismatch: expected {}, got {elf_digest}",
+                self.elf_digest
+            );
+        }
+        let image_id = compute_image_id(elf)?;
+        if image_id.to_string() != self.image_id {
+            bail!(
+                "image ID mismatch: expected {}, got {image_id}",
+                self.image_id
+            );
+        }
+        Ok(image_id)
+    }
+
+    fn compute_digest(&self) -> Result<Digest> {
+        let unsealed = Self {
+            digest: String::new(),
+            ..self.clone()
+        };
+        Ok(*Impl::hash_bytes(&serde_json::to_vec(&unsealed)?))
+    }
+}
+
+/// Return the path of the attestation for the ELF at `elf_path`.
+pub fn attestation_path(elf_path: &Path) -> PathBuf {
+    let mut path = elf_path.as_os_str().to_owned();
+    path.push(ATTESTATION_SUFFIX);
+    path.into()
+}
+
+/// Compute a digest over the sources of the package in `dir`.
+///
+/// The digest covers the path and contents of every file under `dir`, except for `target`
+/// directories and hidden files and directories, so that it is independent of build outputs and
+/// version control metadata. Dependencies outside of `dir` are pinned by the `Cargo.lock` file,
+/// which should be checked into the package.
+pub fn source_digest(dir: &Path) -> Result<Digest> {
+    let mut files = Vec::new();
+    collect_files(dir, dir, &mut files)?;
+    files.sort();
+
+    let mut manifest = String::new();
+    for path in files {
+        let contents = fs::read(dir.join(&path))?;
+        writeln!(manifest, "{}\t{}", Impl::hash_bytes(&contents), path).unwrap();
+    }
+    Ok(*Impl::hash_bytes(manifest.as_bytes()))
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<String>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') || name == "target" {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &path, files)?;
+        } else {
+            let rel_path = path.strip_prefix(root)?;
+            let components: Vec<_> = rel_path
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect();
+            files.push(components.join("/"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use risc0_zkp::core::digest::Digest;
+
+    use super::{attestation_path, source_digest, BuildAttestation};
+
+    #[test]
+    fn seal() {
+        let mut attestation = BuildAttestation {
+            package: "methods".to_string(),
+            binary: "guest".to_string(),
+            source_digest: Digest::ZERO.to_string(),
+            toolchain: "risczero/risc0-guest-builder:r0.1.79.0".to_string(),
+            rustflags: String::new(),
+            features: vec![],
+            elf_digest: Digest::ZERO.to_string(),
+            image_id: Digest::ZERO.to_string(),
+            digest: String::new(),
+        };
+        attestation.digest = attestation.compute_digest().unwrap().to_string();
+
+        let json = attestation.to_json().unwrap();
+        assert_eq!(BuildAttestation::from_json(&json).unwrap(), attestation);
+        assert!(attestation.validate_elf(b"not an elf").is_err());
+
+        let tampered = json.replace("guest", "other");
+        assert!(BuildAttestation::from_json(&tampered).is_err());
+        assert_eq!(
+            attestation_path("elf/guest".as_ref()).to_str().unwrap(),
+            "elf/guest.attestation.json"
+        );
+    }
+
+    #[test]
+    fn source_digest_ignores_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[package]").unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        let digest = source_digest(dir.path()).unwrap();
+
+        fs::create_dir_all(dir.path().join("target")).unwrap();
+        fs::write(dir.path().join("target/guest"), "elf").unwrap();
+        fs::write(dir.path().join(".gitignore"), "target").unwrap();
+        assert_eq!(source_digest(dir.path()).unwrap(), digest);
+
+        fs::write(dir.path().join("src/main.rs"), "fn main() { }").unwrap();
+        assert_ne!(source_digest(dir.path()).unwrap(), digest);
+    }
+}
@@ -0,0 +1,282 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use cargo_metadata::{MetadataCommand, Package};
+use risc0_zkp::core::{
+    digest::Digest,
+    hash::sha::{Impl, Sha256},
+};
+
+use crate::{get_env_var, guest_rust_flags, sanitized_cmd, source_digest, GuestListEntry};
+
+/// The environment variable that enables the guest cache, and sets its directory.
+pub const GUEST_CACHE_DIR_ENV: &str = "RISC0_GUEST_CACHE_DIR";
+
+// Environment variables that change the output of a guest build.
+const BUILD_ENV_VARS: &[&str] = &[
+    "RUSTFLAGS",
+    "RISC0_RUST_SRC",
+    "RISC0_BUILD_LOCKED",
+    "CC_riscv32im_risc0_zkvm_elf",
+    "CFLAGS_riscv32im_risc0_zkvm_elf",
+];
+
+/// Whether a guest package was taken from the [GuestCache].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheStatus {
+    /// The cache is not enabled, or cannot be used for this build.
+    Disabled,
+    /// The ELFs were found in the cache, and the guest was not built.
+    Hit,
+    /// The ELFs were not in the cache, so the guest was built and the results added to it.
+    Miss,
+}
+
+/// The result of building a single guest package, or variant of a package.
+#[derive(Clone, Debug)]
+pub struct GuestBuildReport {
+    /// The name of the guest package.
+    pub package: String,
+
+    /// The name of the variant, if this is not the default build of the package.
+    pub variant: Option<String>,
+
+    /// The cache key of the build, if the cache is enabled.
+    pub cache_key: Option<Digest>,
+
+    /// Whether the build was taken from the cache.
+    pub cache_status: CacheStatus,
+}
+
+/// A content-addressed cache of guest ELFs, which may be shared between workspaces.
+///
+/// Builds are keyed by a digest of the sources of the guest package and all of its path
+/// dependencies, its `Cargo.lock`, the toolchain, the rustc flags and the environment variables
+/// that affect the build, the enabled features, and the build profile.
+/// Each entry is a directory named after the key, holding one ELF per binary target.
+pub struct GuestCache {
+    dir: PathBuf,
+}
+
+impl GuestCache {
+    /// Construct a cache stored in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Construct the cache configured by the `RISC0_GUEST_CACHE_DIR` environment variable, if it
+    /// is set.
+    pub fn from_env() -> Option<Self> {
+        let dir = get_env_var(GUEST_CACHE_DIR_ENV);
+        (!dir.is_empty()).then(|| Self::new(dir))
+    }
+
+    /// The directory holding the cache.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Compute the cache key for building `pkg` with the given features and profile.
+    pub(crate) fn key(&self, pkg: &Package, features: &[String], profile: &str) -> Result<Digest> {
+        let meta = MetadataCommand::new()
+            .manifest_path(&pkg.manifest_path)
+            .exec()
+            .context("cargo metadata command failed")?;
+
+        let mut inputs = build_config(&toolchain_version()?, features, profile);
+
+        let lock_path = meta.workspace_root.join("Cargo.lock");
+        if lock_path.exists() {
+            writeln!(inputs, "lock {}", Impl::hash_bytes(&fs::read(lock_path)?)).unwrap();
+        }
+
+        // Registry and git dependencies are pinned by the lock file, but path dependencies must
+        // be hashed along with the package itself.
+        let mut local_pkgs: Vec<_> = meta
+            .packages
+            .iter()
+            .filter(|pkg| pkg.source.is_none())
+            .collect();
+        local_pkgs.sort_by(|a, b| a.id.cmp(&b.id));
+        for local_pkg in local_pkgs {
+            let dir = local_pkg
+                .manifest_path
+                .parent()
+                .context("invalid manifest path")?;
+            writeln!(
+                inputs,
+                "package {} {}",
+                local_pkg.name,
+                source_digest(dir.as_std_path())?
+            )
+            .unwrap();
+        }
+
+        Ok(*Impl::hash_bytes(inputs.as_bytes()))
+    }
+
+    /// Return the methods of `pkg` stored under `key`, if all of them are in the cache.
+    pub(crate) fn get(&self, key: &Digest, pkg: &Package) -> Option<Vec<GuestListEntry>> {
+        self.get_bins(key, bin_targets(pkg))
+    }
+
+    /// Return the methods named `bins` stored under `key`, if all of them are in the cache.
+    fn get_bins<'a>(
+        &self,
+        key: &Digest,
+        bins: impl Iterator<Item = &'a str>,
+    ) -> Option<Vec<GuestListEntry>> {
+        let entry_dir = self.dir.join(key.to_string());
+        bins.map(|name| {
+            let path = entry_dir.join(name);
+            GuestListEntry::build(name, path.to_str()?).ok()
+        })
+        .collect()
+    }
+
+    /// Copy the ELFs of `methods` into the cache under `key`, and return the methods pointing at
+    /// the cached copies.
+    pub(crate) fn insert(
+        &self,
+        key: &Digest,
+        methods: Vec<GuestListEntry>,
+    ) -> Result<Vec<GuestListEntry>> {
+        let entry_dir = self.dir.join(key.to_string());
+        if !entry_dir.exists() {
+            // Populate a temporary directory and rename it into place, so that concurrent builds
+            // never observe a partial entry.
+            fs::create_dir_all(&self.dir)?;
+            let temp_dir = tempfile::tempdir_in(&self.dir)?;
+            for method in methods.iter() {
+                fs::copy(&*method.path, temp_dir.path().join(&*method.name))?;
+            }
+            if let Err(err) = fs::rename(temp_dir.path(), &entry_dir) {
+                if !entry_dir.exists() {
+                    bail!("failed to add entry to guest cache: {err}");
+                }
+            }
+        }
+        methods
+            .into_iter()
+            .map(|method| {
+                let path = entry_dir.join(&*method.name);
+                let path = path.to_str().context("elf path contains invalid unicode")?;
+                GuestListEntry::build(&method.name, path)
+            })
+            .collect()
+    }
+}
+
+/// Describe what determines the output of a guest build, besides the sources of the guest.
+fn build_config(toolchain: &str, features: &[String], profile: &str) -> String {
+    let mut config = String::new();
+    writeln!(config, "risc0-build {}", env!("CARGO_PKG_VERSION")).unwrap();
+    writeln!(config, "toolchain {toolchain}").unwrap();
+    writeln!(config, "rustflags {:?}", guest_rust_flags(&[])).unwrap();
+    for name in BUILD_ENV_VARS {
+        writeln!(config, "env {name} {:?}", get_env_var(name)).unwrap();
+    }
+    writeln!(config, "profile {profile}").unwrap();
+    let mut features = features.to_vec();
+    features.sort();
+    writeln!(config, "features {}", features.join(",")).unwrap();
+    config
+}
+
+fn bin_targets(pkg: &Package) -> impl Iterator<Item = &str> {
+    pkg.targets
+        .iter()
+        .filter(|target| target.kind.iter().any(|kind| kind == "bin"))
+        .map(|target| target.name.as_str())
+}
+
+/// Return the version of the risc0 rust toolchain, including its commit hash.
+fn toolchain_version() -> Result<String> {
+    let rustc = sanitized_cmd("rustup")
+        .args(["+risc0", "which", "rustc"])
+        .output()
+        .context("rustup failed to find risc0 toolchain")?
+        .stdout;
+    let rustc = String::from_utf8(rustc)?;
+    let output = sanitized_cmd(rustc.trim())
+        .arg("-vV")
+        .output()
+        .context("failed to run rustc")?;
+    if !output.status.success() {
+        bail!("`rustc -vV` failed");
+    }
+    Ok(String::from_utf8(output.stdout)?.trim().replace('\n', "; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use risc0_zkp::core::digest::Digest;
+
+    use super::{build_config, GuestCache};
+
+    #[test]
+    fn build_config_changes() {
+        let features = ["b".to_string(), "a".to_string()];
+        let config = build_config("rustc 1.79.0 (abc)", &features, "release");
+        assert_eq!(
+            build_config(
+                "rustc 1.79.0 (abc)",
+                &[features[1].clone(), features[0].clone()],
+                "release"
+            ),
+            config
+        );
+        assert_ne!(
+            build_config("rustc 1.79.0 (def)", &features, "release"),
+            config
+        );
+        assert_ne!(
+            build_config("rustc 1.79.0 (abc)", &features, "debug"),
+            config
+        );
+        assert_ne!(
+            build_config("rustc 1.79.0 (abc)", &features[..1], "release"),
+            config
+        );
+
+        env::set_var("RUSTFLAGS", "-C opt-level=z");
+        let with_rustflags = build_config("rustc 1.79.0 (abc)", &features, "release");
+        env::remove_var("RUSTFLAGS");
+        assert_ne!(with_rustflags, config);
+    }
+
+    #[test]
+    fn incomplete_entries_miss() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = GuestCache::new(dir.path());
+        let key = Digest::ZERO;
+        assert!(cache.get_bins(&key, ["guest"].into_iter()).is_none());
+
+        // An entry holding a file that is not an ELF is not used.
+        let entry_dir = dir.path().join(key.to_string());
+        fs::create_dir_all(&entry_dir).unwrap();
+        fs::write(entry_dir.join("guest"), "not an elf").unwrap();
+        assert!(cache.get_bins(&key, ["guest"].into_iter()).is_none());
+        assert!(cache.get_bins(&key, ["other"].into_iter()).is_none());
+    }
+}
@@ -24,7 +24,13 @@ use risc0_zkvm_platform::{
 };
 use tempfile::tempdir;
 
-use crate::get_env_var;
+use crate::{
+    attestation::{attestation_path, source_digest, BuildAttestation},
+    get_env_var,
+};
+
+/// The docker image providing the toolchain for reproducible builds.
+const BUILDER_IMAGE: &str = "risczero/risc0-guest-builder:r0.1.79.0";
 
 const DOCKER_IGNORE: &str = r#"
 **/Dockerfile
@@ -50,6 +56,30 @@ pub fn docker_build(
     manifest_path: &Path,
     src_dir: &Path,
     features: &[String],
+) -> Result<BuildStatus> {
+    build_with_docker(manifest_path, src_dir, features, false)
+}
+
+/// Build the package in the manifest path using a docker environment, and write a
+/// [BuildAttestation] next to each ELF.
+///
+/// The attestation for each ELF is written to the same directory, with [ATTESTATION_SUFFIX]
+/// appended to the file name.
+///
+/// [ATTESTATION_SUFFIX]: crate::ATTESTATION_SUFFIX
+pub fn docker_build_attested(
+    manifest_path: &Path,
+    src_dir: &Path,
+    features: &[String],
+) -> Result<BuildStatus> {
+    build_with_docker(manifest_path, src_dir, features, true)
+}
+
+fn build_with_docker(
+    manifest_path: &Path,
+    src_dir: &Path,
+    features: &[String],
+    attest: bool,
 ) -> Result<BuildStatus> {
     if !get_env_var("RISC0_SKIP_BUILD").is_empty() {
         eprintln!("Skipping build because RISC0_SKIP_BUILD is set");
@@ -83,6 +113,11 @@ pub fn docker_build(
         eprintln!("{err}");
     }
 
+    // Hash the sources before building, so that the attestation describes the build inputs.
+    let sources = attest
+        .then(|| source_digest(manifest_path.parent().context("invalid manifest path")?))
+        .transpose()?;
+
     let pkg_name = pkg_name.replace('-', "_");
     {
         let temp_dir = tempdir()?;
@@ -99,6 +134,19 @@ pub fn docker_build(
         let image_id = compute_image_id(&elf_path)?;
         let rel_elf_path = Path::new(TARGET_DIR).join(&pkg_name).join(&target.name);
         println!("ImageID: {} - {:?}", image_id, rel_elf_path);
+
+        if let Some(sources) = sources {
+            let attestation = BuildAttestation::new(
+                &root_pkg.name,
+                &target.name,
+                sources,
+                BUILDER_IMAGE,
+                &rustflags(),
+                features,
+                &fs::read(&elf_path)?,
+            )?;
+            fs::write(attestation_path(&elf_path), attestation.to_json()?)?;
+        }
     }
 
     Ok(BuildStatus::Success)
@@ -114,9 +162,7 @@ fn create_dockerfile(
     features: &[String],
 ) -> Result<()> {
     let manifest_env = &[("CARGO_MANIFEST_PATH", manifest_path.to_str().unwrap())];
-    let rustflags = format!(
-        "-C passes=loweratomic -C link-arg=-Ttext=0x{TEXT_START:08X} -C link-arg=--fatal-warnings",
-    );
+    let rustflags = rustflags();
     let rustflags_env = &[("RUSTFLAGS", rustflags.as_str())];
 
     let common_args = vec![
@@ -145,7 +191,7 @@ fn create_dockerfile(
     .join(" ");
 
     let build = DockerFile::new()
-        .from_alias("build", "risczero/risc0-guest-builder:r0.1.79.0")
+        .from_alias("build", BUILDER_IMAGE)
         .workdir("/src")
         .copy(".", ".")
         .env(manifest_env)
@@ -173,6 +219,13 @@ fn create_dockerfile(
     Ok(())
 }
 
+/// The `RUSTFLAGS` used to build guests in docker.
+fn rustflags() -> String {
+    format!(
+        "-C passes=loweratomic -C link-arg=-Ttext=0x{TEXT_START:08X} -C link-arg=--fatal-warnings",
+    )
+}
+
 /// Build the dockerfile and outputs the ELF.
 ///
 /// Overwrites if an ELF with the same name already exists.
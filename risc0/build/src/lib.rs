@@ -17,6 +17,8 @@
 #![deny(rustdoc::broken_intra_doc_links)]
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+mod attestation;
+mod cache;
 mod docker;
 
 use std::{
@@ -37,7 +39,9 @@ use risc0_zkp::core::digest::DIGEST_WORDS;
 use risc0_zkvm_platform::memory;
 use serde::Deserialize;
 
-pub use docker::{docker_build, BuildStatus, TARGET_DIR};
+pub use attestation::{attestation_path, source_digest, BuildAttestation, ATTESTATION_SUFFIX};
+pub use cache::{CacheStatus, GuestBuildReport, GuestCache, GUEST_CACHE_DIR_ENV};
+pub use docker::{docker_build, docker_build_attested, BuildStatus, TARGET_DIR};
 
 /// This const represents a filename that is used in the use to indicate to in
 /// order to indicate to the client and the risc0-build crate that the new rust
@@ -46,6 +50,14 @@ pub use docker::{docker_build, BuildStatus, TARGET_DIR};
 pub const RUST_RZUP_INDICATOR: &str = ".rzup";
 const RUSTUP_TOOLCHAIN_NAME: &str = "risc0";
 
+/// The guest feature that enables compact panics.
+///
+/// Add this to [GuestOptions::features], or pass it to [docker_build], to build a `no_std` guest
+/// that reports panics by location only. Skipping the panic formatting machinery makes the guest
+/// binary smaller and saves the cycles spent formatting the message, while the host still reports
+/// the file, line and column of the panic. The guest must depend on `risc0-zkvm` directly.
+pub const COMPACT_PANIC_FEATURE: &str = "risc0-zkvm/compact-panic";
+
 /// Get the path used by cargo-risczero that stores downloaded toolchains
 pub fn risc0_data() -> Result<PathBuf> {
     risc0_data_new().or_else(|_| risc0_data_compat())
@@ -275,6 +287,26 @@ where
         .collect()
 }
 
+/// Copies the ELFs of the given methods into `dir`, and points the methods at the copies.
+fn relocate_methods(
+    methods: Vec<GuestListEntry>,
+    dir: impl AsRef<Path>,
+) -> Result<Vec<GuestListEntry>> {
+    fs::create_dir_all(dir.as_ref())?;
+    methods
+        .into_iter()
+        .map(|method| {
+            let path = dir.as_ref().join(&*method.name);
+            fs::copy(&*method.path, &path)?;
+            let path = path.to_str().context("elf path contains invalid unicode")?;
+            Ok(GuestListEntry {
+                path: Cow::Owned(path.to_owned()),
+                ..method
+            })
+        })
+        .collect()
+}
+
 fn get_env_var(name: &str) -> String {
     println!("cargo:rerun-if-env-changed={name}");
     env::var(name).unwrap_or_default()
@@ -322,7 +354,23 @@ pub fn cargo_command(subcmd: &str, rust_flags: &[&str]) -> Command {
 
     println!("Building guest package: cargo {}", args.join(" "));
 
-    let rustflags_envvar = [
+    if !cpp_toolchain_override() {
+        let cc_path = risc0_data()
+            .unwrap()
+            .join("cpp/bin/riscv32-unknown-elf-gcc");
+        cmd.env("CC", cc_path)
+            .env("CFLAGS_riscv32im_risc0_zkvm_elf", "-march=rv32im -nostdlib");
+    }
+
+    cmd.env("RUSTC", rustc)
+        .env("CARGO_ENCODED_RUSTFLAGS", guest_rust_flags(rust_flags))
+        .args(args);
+    cmd
+}
+
+/// The flags passed to rustc when building a guest, encoded as for `CARGO_ENCODED_RUSTFLAGS`.
+fn guest_rust_flags(rust_flags: &[&str]) -> String {
+    [
         rust_flags,
         &[
             // Replace atomic ops with nonatomic versions since the guest is single threaded.
@@ -344,20 +392,7 @@ pub fn cargo_command(subcmd: &str, rust_flags: &[&str]) -> Command {
         ],
     ]
     .concat()
-    .join("\x1f");
-
-    if !cpp_toolchain_override() {
-        let cc_path = risc0_data()
-            .unwrap()
-            .join("cpp/bin/riscv32-unknown-elf-gcc");
-        cmd.env("CC", cc_path)
-            .env("CFLAGS_riscv32im_risc0_zkvm_elf", "-march=rv32im -nostdlib");
-    }
-
-    cmd.env("RUSTC", rustc)
-        .env("CARGO_ENCODED_RUSTFLAGS", rustflags_envvar)
-        .args(args);
-    cmd
+    .join("\x1f")
 }
 
 fn cpp_toolchain_override() -> bool {
@@ -443,7 +478,7 @@ fn build_staticlib(guest_pkg: &str, features: &[&str]) -> String {
 fn build_guest_package<P>(
     pkg: &Package,
     target_dir: P,
-    guest_opts: &GuestOptions,
+    features: &[String],
     runtime_lib: Option<&str>,
 ) where
     P: AsRef<Path>,
@@ -460,7 +495,7 @@ fn build_guest_package<P>(
         cargo_command("build", &[])
     };
 
-    let features_str = guest_opts.features.join(",");
+    let features_str = features.join(",");
     if !features_str.is_empty() {
         cmd.args(["--features", &features_str]);
     }
@@ -518,6 +553,42 @@ fn build_guest_package<P>(
     }
 }
 
+// Builds a package like [build_guest_package] and returns its methods, taking the ELFs from the
+// cache if they are present, and adding them to the cache otherwise.
+fn build_guest_package_cached(
+    pkg: &Package,
+    target_dir: &Path,
+    features: &[String],
+    cache: Option<&GuestCache>,
+    report: &mut GuestBuildReport,
+) -> Vec<GuestListEntry> {
+    // The cache cannot be used when skipping the build, since the existing ELFs may be stale.
+    let cache = cache.filter(|_| get_env_var("RISC0_SKIP_BUILD").is_empty());
+    let profile = if is_debug() { "debug" } else { "release" };
+    let key = cache.and_then(|cache| match cache.key(pkg, features, profile) {
+        Ok(key) => Some((cache, key)),
+        Err(err) => {
+            eprintln!("Not using guest cache for {}: {err}", pkg.name);
+            None
+        }
+    });
+    let Some((cache, key)) = key else {
+        build_guest_package(pkg, target_dir, features, None);
+        return guest_methods(pkg, target_dir);
+    };
+
+    report.cache_key = Some(key);
+    if let Some(methods) = cache.get(&key, pkg) {
+        report.cache_status = CacheStatus::Hit;
+        return methods;
+    }
+
+    build_guest_package(pkg, target_dir, features, None);
+    report.cache_status = CacheStatus::Miss;
+    let methods = guest_methods(pkg, target_dir);
+    cache.insert(&key, methods).unwrap()
+}
+
 fn detect_toolchain(name: &str) {
     let result = Command::new("rustup")
         .args(["toolchain", "list", "--verbose"])
@@ -558,6 +629,24 @@ pub struct GuestOptions {
 
     /// Use a docker environment for building.
     pub use_docker: Option<DockerOptions>,
+
+    /// Additional variants of the guest package to build, each with its own set of features.
+    pub variants: Vec<GuestVariant>,
+}
+
+/// A variant of a guest package, built with additional features.
+///
+/// This allows one methods crate to embed the same guest source built in several ways, e.g. with
+/// and without accelerators. Each variant is built into its own target directory, and its methods
+/// are embedded with the variant name appended: a variant named `accel` of the method `my_method`
+/// defines `MY_METHOD_ACCEL_ELF`, `MY_METHOD_ACCEL_ID` and `MY_METHOD_ACCEL_PATH`.
+#[derive(Clone, Debug, Default)]
+pub struct GuestVariant {
+    /// The name of the variant, appended to the names of its methods.
+    pub name: String,
+
+    /// Features for cargo to build the variant with, in addition to [GuestOptions::features].
+    pub features: Vec<String>,
 }
 
 fn get_guest_dir() -> PathBuf {
@@ -580,8 +669,20 @@ fn get_guest_dir() -> PathBuf {
 /// Specify custom options for a guest package by defining its [GuestOptions].
 /// See [embed_methods].
 pub fn embed_methods_with_options(
-    mut guest_pkg_to_options: HashMap<&str, GuestOptions>,
+    guest_pkg_to_options: HashMap<&str, GuestOptions>,
 ) -> Vec<GuestListEntry> {
+    embed_methods_with_report(guest_pkg_to_options).0
+}
+
+/// Embeds methods built for RISC-V for use by host-side dependencies, like
+/// [embed_methods_with_options], and reports how each guest package was built.
+///
+/// Guests built without docker are cached in the [GuestCache] configured by the
+/// `RISC0_GUEST_CACHE_DIR` environment variable, if it is set. The returned reports record
+/// whether each build was taken from the cache, which is useful for diagnosing caching in CI.
+pub fn embed_methods_with_report(
+    mut guest_pkg_to_options: HashMap<&str, GuestOptions>,
+) -> (Vec<GuestListEntry>, Vec<GuestBuildReport>) {
     let out_dir_env = env::var_os("OUT_DIR").unwrap();
     let out_dir = Path::new(&out_dir_env); // $ROOT/target/$profile/build/$crate/out
     let guest_dir = get_guest_dir();
@@ -604,7 +705,9 @@ pub fn embed_methods_with_options(
 
     detect_toolchain(RUSTUP_TOOLCHAIN_NAME);
 
+    let cache = GuestCache::from_env();
     let mut guest_list = vec![];
+    let mut reports = vec![];
     for guest_pkg in guest_packages {
         println!("Building guest package {}.{}", pkg.name, guest_pkg.name);
 
@@ -612,30 +715,67 @@ pub fn embed_methods_with_options(
             .remove(guest_pkg.name.as_str())
             .unwrap_or_default();
 
-        let methods = if let Some(docker_opts) = guest_opts.use_docker {
-            let src_dir = docker_opts
-                .root_dir
-                .unwrap_or_else(|| std::env::current_dir().unwrap());
-            docker_build(
-                guest_pkg.manifest_path.as_std_path(),
-                &src_dir,
-                &guest_opts.features,
-            )
-            .unwrap();
-            guest_methods_docker(&guest_pkg, &guest_dir)
-        } else {
-            build_guest_package(&guest_pkg, &guest_dir, &guest_opts, None);
-            guest_methods(&guest_pkg, &guest_dir)
-        };
-
-        for method in methods {
-            methods_file
-                .write_all(method.codegen_consts().as_bytes())
-                .unwrap();
-
-            #[cfg(feature = "guest-list")]
-            guest_list_codegen.push(method.codegen_list_entry());
-            guest_list.push(method);
+        let mut builds = vec![];
+        for variant in guest_opts.variants.iter() {
+            if !variant
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                panic!("invalid guest variant name: {:?}", variant.name);
+            }
+            let features = [guest_opts.features.as_slice(), &variant.features].concat();
+            builds.push((Some(variant.name.as_str()), features));
+        }
+        // Build the default variant last, so that docker builds of other variants do not
+        // overwrite its ELFs.
+        builds.push((None, guest_opts.features.clone()));
+
+        for (variant, features) in builds {
+            let variant_dir = variant.map(|name| guest_dir.join("variants").join(name));
+            let mut report = GuestBuildReport {
+                package: guest_pkg.name.clone(),
+                variant: variant.map(str::to_string),
+                cache_key: None,
+                cache_status: CacheStatus::Disabled,
+            };
+            let methods = if let Some(docker_opts) = &guest_opts.use_docker {
+                let src_dir = docker_opts
+                    .root_dir
+                    .clone()
+                    .unwrap_or_else(|| std::env::current_dir().unwrap());
+                docker_build(guest_pkg.manifest_path.as_std_path(), &src_dir, &features).unwrap();
+                let methods = guest_methods_docker(&guest_pkg, &guest_dir);
+                match &variant_dir {
+                    // Docker builds always write to the same directory, so move the ELFs of each
+                    // variant out of the way of the next build.
+                    Some(variant_dir) => relocate_methods(methods, variant_dir).unwrap(),
+                    None => methods,
+                }
+            } else {
+                let target_dir = variant_dir.as_ref().unwrap_or(&guest_dir);
+                build_guest_package_cached(
+                    &guest_pkg,
+                    target_dir,
+                    &features,
+                    cache.as_ref(),
+                    &mut report,
+                )
+            };
+            reports.push(report);
+
+            for mut method in methods {
+                if let Some(variant) = variant {
+                    method.name = Cow::Owned(format!("{}_{variant}", method.name));
+                }
+                methods_file
+                    .write_all(method.codegen_consts().as_bytes())
+                    .unwrap();
+
+                #[cfg(feature = "guest-list")]
+                guest_list_codegen.push(method.codegen_list_entry());
+                guest_list.push(method);
+            }
         }
     }
 
@@ -657,7 +797,7 @@ pub fn embed_methods_with_options(
     // Since we generate methods.rs each time we run, it will always
     // be changed.
     println!("cargo:rerun-if-changed={}", methods_path.display());
-    guest_list
+    (guest_list, reports)
 }
 
 /// Embeds methods built for RISC-V for use by host-side dependencies.
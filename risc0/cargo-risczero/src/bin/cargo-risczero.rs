@@ -36,5 +36,7 @@ fn main() -> Result<()> {
         RisczeroCmd::BuildCrate(build) => build.run(BuildSubcommand::Build),
         #[cfg(feature = "experimental")]
         RisczeroCmd::Test(build) => build.run(BuildSubcommand::Test),
+        #[cfg(feature = "experimental")]
+        RisczeroCmd::Cycles(cmd) => cmd.run(),
     }
 }
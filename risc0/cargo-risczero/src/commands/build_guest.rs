@@ -14,9 +14,12 @@
 
 use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use cargo_metadata::MetadataCommand;
 use clap::Parser;
-use risc0_build::BuildStatus;
+use risc0_build::{BuildStatus, COMPACT_PANIC_FEATURE, TARGET_DIR};
+
+use crate::size::SizeReport;
 
 /// `cargo risczero build`
 ///
@@ -32,11 +35,62 @@ pub struct BuildGuest {
     /// Feature flags passed to cargo.
     #[arg(long, value_delimiter = ',')]
     pub features: Vec<String>,
+
+    /// Write a build attestation next to each ELF, recording the source digest, toolchain,
+    /// flags, and resulting image ID.
+    #[arg(long)]
+    pub attest: bool,
+
+    /// Report guest panics by location only, removing the panic formatting machinery from
+    /// `no_std` guests.
+    #[arg(long)]
+    pub compact_panic: bool,
+
+    /// Print a breakdown of the size of each ELF by crate and symbol, along with an estimate of
+    /// the cycles needed to page in its code.
+    #[arg(long)]
+    pub size_report: bool,
+
+    /// Number of crates and symbols to list in the size report.
+    #[arg(long, default_value_t = 20)]
+    pub top: usize,
 }
 
 impl BuildGuest {
     pub fn run(&self) -> Result<()> {
-        build(&self.manifest_path, &self.features)?;
+        let mut features = self.features.clone();
+        if self.compact_panic {
+            features.push(COMPACT_PANIC_FEATURE.to_string());
+        }
+        let status = if self.attest {
+            let src_dir = std::env::current_dir()?;
+            risc0_build::docker_build_attested(&self.manifest_path, &src_dir, &features)?
+        } else {
+            build(&self.manifest_path, &features)?
+        };
+        if self.size_report && matches!(status, BuildStatus::Success) {
+            self.print_size_report()?;
+        }
+        Ok(())
+    }
+
+    fn print_size_report(&self) -> Result<()> {
+        let meta = MetadataCommand::new()
+            .manifest_path(&self.manifest_path)
+            .no_deps()
+            .exec()?;
+        let root_pkg = meta.root_package().context("Failed to parse Cargo.toml")?;
+        let target_dir = std::env::current_dir()?
+            .join(TARGET_DIR)
+            .join(root_pkg.name.replace('-', "_"));
+        for target in root_pkg.targets.iter().filter(|t| t.is_bin()) {
+            let elf_path = target_dir.join(&target.name);
+            let elf = std::fs::read(&elf_path)
+                .with_context(|| format!("failed to read {}", elf_path.display()))?;
+            let report = SizeReport::from_elf(&elf)?;
+            println!("\nSize report for {}:", target.name);
+            print!("{}", report.to_table(self.top));
+        }
         Ok(())
     }
 }
@@ -0,0 +1,391 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+    fs, io,
+    io::Write as _,
+    path::PathBuf,
+    process::Stdio,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use cargo_metadata::{Artifact, Message};
+use clap::Parser;
+use risc0_build::cargo_command;
+use risc0_zkvm::{ExecutorEnv, ExecutorImpl, Symbolizer, TraceEvent};
+
+/// The name used for cycles spent outside of any known function.
+const UNKNOWN_FUNCTION: &str = "[unknown]";
+
+/// `cargo risczero cycles`
+#[derive(Parser)]
+pub struct CyclesCommand {
+    /// Path to the Cargo.toml file for the guest crate.
+    #[arg(long, default_value = "./Cargo.toml")]
+    pub manifest_path: PathBuf,
+
+    /// Output directory for build artifacts.
+    ///
+    /// Determined from package metadata if not supplied.
+    #[arg(long)]
+    pub target_dir: Option<PathBuf>,
+
+    /// Name of the guest binary to execute.
+    #[arg(long)]
+    pub bin: String,
+
+    /// File whose contents are provided to the guest as its input.
+    #[arg(long)]
+    pub input: Option<PathBuf>,
+
+    /// Report the change in cycles relative to a report saved with `--save`.
+    #[arg(long)]
+    pub baseline: Option<PathBuf>,
+
+    /// Save the report, so that it can be used as a baseline later.
+    #[arg(long)]
+    pub save: Option<PathBuf>,
+
+    /// Only show the given number of functions, starting with the most expensive.
+    #[arg(long)]
+    pub top: Option<usize>,
+
+    /// Additional arguments to pass to "cargo build" on the guest
+    pub args: Vec<String>,
+}
+
+impl CyclesCommand {
+    /// Execute this command.
+    pub fn run(&self) -> Result<()> {
+        let elf = fs::read(self.build()?)?;
+        let input = match &self.input {
+            Some(path) => fs::read(path)
+                .with_context(|| format!("failed to read input `{}`", path.display()))?,
+            None => Vec::new(),
+        };
+        let baseline = match &self.baseline {
+            Some(path) => {
+                let contents = fs::read_to_string(path)
+                    .with_context(|| format!("failed to read baseline `{}`", path.display()))?;
+                Some(CycleReport::from_tsv(&contents)?)
+            }
+            None => None,
+        };
+
+        let report = CycleReport::from_elf(&elf, &input)?;
+        if let Some(path) = &self.save {
+            fs::write(path, report.to_tsv())?;
+        }
+        print!("{}", report.to_table(baseline.as_ref(), self.top));
+        Ok(())
+    }
+
+    /// Build the guest binary and return the path to its ELF.
+    fn build(&self) -> Result<PathBuf> {
+        let manifest_path = fs::canonicalize(&self.manifest_path).with_context(|| {
+            format!(
+                "failed to resolve manifest path `{}`",
+                self.manifest_path.display()
+            )
+        })?;
+        let target_dir = self
+            .target_dir
+            .clone()
+            .unwrap_or_else(|| risc0_build::get_target_dir(&manifest_path));
+
+        let mut cmd = cargo_command("build", &[]);
+        cmd.arg("--message-format=json")
+            .arg("--release")
+            .args(["--bin", &self.bin])
+            .arg("--manifest-path")
+            .arg(&manifest_path)
+            .arg("--target-dir")
+            .arg(&target_dir)
+            .args(&self.args);
+
+        let mut child = cmd.stdout(Stdio::piped()).spawn()?;
+        let reader = io::BufReader::new(
+            child
+                .stdout
+                .take()
+                .ok_or(anyhow!("failed to read from cmd stdout"))?,
+        );
+        let mut elf_path = None;
+        for message in Message::parse_stream(reader) {
+            match message? {
+                Message::CompilerArtifact(Artifact {
+                    executable: Some(path),
+                    target,
+                    ..
+                }) if target.name == self.bin => {
+                    elf_path = Some(path.into_std_path_buf());
+                }
+                Message::CompilerMessage(msg) => {
+                    write!(io::stderr(), "{}", msg)?;
+                }
+                _ => (),
+            }
+        }
+
+        let status = child
+            .wait()
+            .with_context(|| "couldn't get cargo's exit status")?;
+        if !status.success() {
+            bail!("failed to build guest")
+        }
+        elf_path.ok_or_else(|| anyhow!("cargo did not produce a binary named `{}`", self.bin))
+    }
+}
+
+/// The cycles spent executing a single guest function.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FunctionCycles {
+    /// The demangled name of the function.
+    pub name: String,
+
+    /// Cycles spent executing instructions in the function.
+    pub user_cycles: u64,
+
+    /// Cycles spent paging memory in and out on behalf of the function.
+    pub paging_cycles: u64,
+}
+
+impl FunctionCycles {
+    /// The total number of cycles attributed to the function.
+    pub fn total_cycles(&self) -> u64 {
+        self.user_cycles + self.paging_cycles
+    }
+}
+
+/// A breakdown of the cycles used by a guest program into the functions that used them.
+///
+/// Cycles are attributed to the function that contains each instruction, according to the ELF
+/// symbol table; cycles spent in an inlined function count towards its caller. The fixed costs
+/// of each segment are not included.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CycleReport {
+    /// The functions that used cycles, most expensive first.
+    pub functions: Vec<FunctionCycles>,
+}
+
+impl CycleReport {
+    /// Execute the guest program in `elf` with the given input and report the cycles used by
+    /// each function.
+    pub fn from_elf(elf: &[u8], input: &[u8]) -> Result<Self> {
+        let symbolizer = Symbolizer::new(elf)?;
+
+        // Cycles by program counter.
+        let mut cycles: HashMap<u32, (u64, u64)> = HashMap::new();
+        let mut last: Option<(u32, u64)> = None;
+        let session = {
+            let env = ExecutorEnv::builder()
+                .write_slice(input)
+                .trace_paging()
+                .trace_callback(|event| {
+                    match event {
+                        TraceEvent::InstructionStart { cycle, pc, .. } => {
+                            if let Some((last_pc, last_cycle)) = last {
+                                cycles.entry(last_pc).or_default().0 += cycle - last_cycle;
+                            }
+                            last = Some((pc, cycle));
+                        }
+                        TraceEvent::Paging { pc, cycles: paging } => {
+                            cycles.entry(pc).or_default().1 += paging;
+                        }
+                        _ => (),
+                    }
+                    Ok(())
+                })
+                .build()?;
+            ExecutorImpl::from_elf(env, elf)?.run()?
+        };
+        if let Some((last_pc, last_cycle)) = last {
+            cycles.entry(last_pc).or_default().0 += session.user_cycles - last_cycle;
+        }
+
+        let mut functions: BTreeMap<&str, FunctionCycles> = BTreeMap::new();
+        for (pc, (user_cycles, paging_cycles)) in cycles {
+            let name = symbolizer.function_name(pc).unwrap_or(UNKNOWN_FUNCTION);
+            let function = functions.entry(name).or_insert_with(|| FunctionCycles {
+                name: name.to_string(),
+                ..Default::default()
+            });
+            function.user_cycles += user_cycles;
+            function.paging_cycles += paging_cycles;
+        }
+        Ok(Self::new(functions.into_values().collect()))
+    }
+
+    fn new(mut functions: Vec<FunctionCycles>) -> Self {
+        functions.sort_by(|a, b| {
+            b.total_cycles()
+                .cmp(&a.total_cycles())
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        Self { functions }
+    }
+
+    /// Parse a report saved with [CycleReport::to_tsv].
+    pub fn from_tsv(contents: &str) -> Result<Self> {
+        let mut functions = Vec::new();
+        for (idx, line) in contents.lines().enumerate().skip(1) {
+            let parse = || -> Option<FunctionCycles> {
+                let (user_cycles, rest) = line.split_once('\t')?;
+                let (paging_cycles, name) = rest.split_once('\t')?;
+                Some(FunctionCycles {
+                    name: name.to_string(),
+                    user_cycles: user_cycles.parse().ok()?,
+                    paging_cycles: paging_cycles.parse().ok()?,
+                })
+            };
+            functions.push(parse().ok_or_else(|| anyhow!("malformed report on line {}", idx + 1))?);
+        }
+        Ok(Self::new(functions))
+    }
+
+    /// Produce a tab-separated form of this report, which can be read with
+    /// [CycleReport::from_tsv].
+    pub fn to_tsv(&self) -> String {
+        let mut tsv = String::from("user\tpaging\tfunction\n");
+        for function in &self.functions {
+            writeln!(
+                tsv,
+                "{}\t{}\t{}",
+                function.user_cycles, function.paging_cycles, function.name
+            )
+            .unwrap();
+        }
+        tsv
+    }
+
+    /// Format this report as a table, showing at most `top` functions.
+    ///
+    /// If a `baseline` is given, the table includes the change in total cycles of each
+    /// function, and lists the functions that no longer use any cycles.
+    pub fn to_table(&self, baseline: Option<&CycleReport>, top: Option<usize>) -> String {
+        let mut rows: Vec<_> = self
+            .functions
+            .iter()
+            .map(|function| (function.clone(), 0))
+            .collect();
+        if let Some(baseline) = baseline {
+            let old: HashMap<_, _> = baseline
+                .functions
+                .iter()
+                .map(|function| (function.name.as_str(), function.total_cycles()))
+                .collect();
+            for (function, old_cycles) in rows.iter_mut() {
+                *old_cycles = old.get(function.name.as_str()).copied().unwrap_or_default();
+            }
+            for function in &baseline.functions {
+                if !self.functions.iter().any(|f| f.name == function.name) {
+                    let removed = FunctionCycles {
+                        name: function.name.clone(),
+                        ..Default::default()
+                    };
+                    rows.push((removed, function.total_cycles()));
+                }
+            }
+        }
+
+        let mut table = String::new();
+        let header = format!("{:>12} {:>12} {:>12}", "user", "paging", "total");
+        match baseline {
+            Some(_) => writeln!(table, "{header} {:>12}  function", "change"),
+            None => writeln!(table, "{header}  function"),
+        }
+        .unwrap();
+        let count = top.unwrap_or(rows.len()).min(rows.len());
+        for (function, old_cycles) in &rows[..count] {
+            let total = function.total_cycles();
+            write!(
+                table,
+                "{:>12} {:>12} {total:>12}",
+                function.user_cycles, function.paging_cycles
+            )
+            .unwrap();
+            if baseline.is_some() {
+                let change = total as i64 - *old_cycles as i64;
+                write!(table, " {:>12}", format!("{change:+}")).unwrap();
+            }
+            writeln!(table, "  {}", function.name).unwrap();
+        }
+        if count < rows.len() {
+            writeln!(table, "... {} more", rows.len() - count).unwrap();
+        }
+
+        let user_cycles: u64 = self.functions.iter().map(|f| f.user_cycles).sum();
+        let paging_cycles: u64 = self.functions.iter().map(|f| f.paging_cycles).sum();
+        write!(
+            table,
+            "{user_cycles:>12} {paging_cycles:>12} {:>12}",
+            user_cycles + paging_cycles
+        )
+        .unwrap();
+        if let Some(baseline) = baseline {
+            let old: u64 = baseline.functions.iter().map(|f| f.total_cycles()).sum();
+            let change = (user_cycles + paging_cycles) as i64 - old as i64;
+            write!(table, " {:>12}", format!("{change:+}")).unwrap();
+        }
+        writeln!(table, "  [total]").unwrap();
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CycleReport, FunctionCycles};
+
+    fn function(name: &str, user_cycles: u64, paging_cycles: u64) -> FunctionCycles {
+        FunctionCycles {
+            name: name.to_string(),
+            user_cycles,
+            paging_cycles,
+        }
+    }
+
+    #[test]
+    fn tsv_round_trip() {
+        let report = CycleReport::new(vec![
+            function("guest::main", 100, 2000),
+            function(
+                "<alloc::vec::Vec<u8> as core::clone::Clone>::clone",
+                5000,
+                0,
+            ),
+        ]);
+        assert_eq!(report.functions[0].user_cycles, 5000);
+        assert_eq!(CycleReport::from_tsv(&report.to_tsv()).unwrap(), report);
+        assert!(CycleReport::from_tsv("user\tpaging\tfunction\n12\tmain\n").is_err());
+    }
+
+    #[test]
+    fn table_with_baseline() {
+        let baseline = CycleReport::new(vec![function("a", 10, 0), function("b", 5, 5)]);
+        let report = CycleReport::new(vec![function("a", 20, 1), function("c", 3, 0)]);
+
+        let table = report.to_table(Some(&baseline), None);
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].ends_with("+11  a"), "{table}");
+        assert!(lines[2].ends_with("+3  c"), "{table}");
+        assert!(lines[3].ends_with("-10  b"), "{table}");
+        assert!(lines[4].ends_with("+4  [total]"), "{table}");
+
+        let table = report.to_table(None, Some(1));
+        assert!(table.contains("... 1 more"), "{table}");
+    }
+}
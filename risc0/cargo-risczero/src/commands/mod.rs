@@ -16,6 +16,8 @@
 pub mod build;
 pub mod build_guest;
 pub mod build_toolchain;
+#[cfg(feature = "experimental")]
+pub mod cycles;
 pub mod deploy;
 pub mod install;
 pub mod new;
@@ -18,16 +18,22 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
 mod commands;
+mod size;
 mod toolchain;
 mod utils;
 
+pub use self::size::{SizeReport, SymbolSize, PAGE_IN_CYCLES};
+
 #[cfg(feature = "experimental")]
-pub use self::commands::build::BuildSubcommand;
+pub use self::commands::{
+    build::BuildSubcommand,
+    cycles::{CycleReport, FunctionCycles},
+};
 
 use clap::{Parser, Subcommand};
 
 #[cfg(feature = "experimental")]
-use self::commands::build::BuildCommand;
+use self::commands::{build::BuildCommand, cycles::CyclesCommand};
 use self::commands::{
     build_guest::BuildGuest, build_toolchain::BuildToolchain, deploy::DeployCommand,
     install::Install, new::NewCommand, verify::VerifyCommand,
@@ -71,6 +77,9 @@ pub enum RisczeroCmd {
     /// Build and test a crate for RISC Zero.
     #[cfg(feature = "experimental")]
     Test(BuildCommand),
+    /// Build and execute a guest, and report the cycles used by each function.
+    #[cfg(feature = "experimental")]
+    Cycles(CyclesCommand),
 }
 
 #[cfg(test)]
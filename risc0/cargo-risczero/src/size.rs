@@ -0,0 +1,229 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reports on the size of guest binaries.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Write as _,
+};
+
+use anyhow::Result;
+use elf::{
+    abi::{SHF_ALLOC, SHF_EXECINSTR, STT_FUNC, STT_OBJECT},
+    endian::LittleEndian,
+    ElfBytes,
+};
+use risc0_zkvm::PAGE_SIZE;
+use rustc_demangle::demangle;
+
+/// The approximate number of cycles needed to page in a single page of memory.
+///
+/// Paging in a page hashes each of its 64-byte blocks with SHA-256, at 68 cycles per block, plus
+/// a few cycles of setup.
+pub const PAGE_IN_CYCLES: u64 = 1 + 5 + 68 * (PAGE_SIZE as u64 / 64);
+
+/// The name used for symbols that cannot be attributed to a crate.
+const UNKNOWN_CRATE: &str = "[unknown]";
+
+/// The size of a single symbol in a guest binary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolSize {
+    /// The demangled name of the symbol.
+    pub name: String,
+
+    /// The name of the crate the symbol most likely belongs to.
+    pub crate_name: String,
+
+    /// The size of the symbol in bytes.
+    pub size: u64,
+
+    /// Whether the symbol is a function, as opposed to data.
+    pub is_function: bool,
+}
+
+/// A breakdown of the size of a guest binary by crate and symbol.
+///
+/// Every page of the binary that the guest touches has to be paged in at least once per segment,
+/// so the size of the code directly affects the number of cycles spent paging.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SizeReport {
+    /// The size of the ELF file in bytes.
+    pub file_size: u64,
+
+    /// The size of the executable sections in bytes.
+    pub text_size: u64,
+
+    /// The size of the non-executable sections that are loaded into memory, in bytes.
+    pub data_size: u64,
+
+    /// The number of pages spanned by the executable sections.
+    pub text_pages: u64,
+
+    /// The symbols in the binary, largest first.
+    pub symbols: Vec<SymbolSize>,
+}
+
+impl SizeReport {
+    /// Analyze the given guest ELF.
+    pub fn from_elf(elf: &[u8]) -> Result<Self> {
+        let file = ElfBytes::<LittleEndian>::minimal_parse(elf)?;
+        let mut report = Self {
+            file_size: elf.len() as u64,
+            ..Default::default()
+        };
+
+        let mut text_pages = BTreeSet::new();
+        if let Some(headers) = file.section_headers() {
+            for header in headers.iter() {
+                if header.sh_flags & SHF_ALLOC as u64 == 0 || header.sh_size == 0 {
+                    continue;
+                }
+                if header.sh_flags & SHF_EXECINSTR as u64 != 0 {
+                    report.text_size += header.sh_size;
+                    let first = header.sh_addr / PAGE_SIZE as u64;
+                    let last = (header.sh_addr + header.sh_size - 1) / PAGE_SIZE as u64;
+                    text_pages.extend(first..=last);
+                } else {
+                    report.data_size += header.sh_size;
+                }
+            }
+        }
+        report.text_pages = text_pages.len() as u64;
+
+        if let Some((symtab, strtab)) = file.symbol_table()? {
+            for sym in symtab {
+                let symtype = sym.st_symtype();
+                if (symtype != STT_FUNC && symtype != STT_OBJECT) || sym.st_size == 0 {
+                    continue;
+                }
+                let name = format!("{:#}", demangle(strtab.get(sym.st_name as usize)?));
+                report.symbols.push(SymbolSize {
+                    crate_name: crate_name(&name).to_string(),
+                    name,
+                    size: sym.st_size,
+                    is_function: symtype == STT_FUNC,
+                });
+            }
+        }
+        report
+            .symbols
+            .sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+        Ok(report)
+    }
+
+    /// Return the total size of the symbols in each crate, largest first.
+    pub fn crates(&self) -> Vec<(String, u64)> {
+        let mut crates: HashMap<&str, u64> = HashMap::new();
+        for symbol in &self.symbols {
+            *crates.entry(symbol.crate_name.as_str()).or_default() += symbol.size;
+        }
+        let mut crates: Vec<_> = crates
+            .into_iter()
+            .map(|(name, size)| (name.to_string(), size))
+            .collect();
+        crates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        crates
+    }
+
+    /// Estimate the number of cycles needed to page in all of the code in each segment.
+    ///
+    /// Guests only pay for the pages they touch, so this is an upper bound on the paging cost of
+    /// the code; it is most useful for comparing the layout of different builds.
+    pub fn text_paging_cycles(&self) -> u64 {
+        self.text_pages * PAGE_IN_CYCLES
+    }
+
+    /// Format this report as a table, listing at most `top` crates and symbols.
+    pub fn to_table(&self, top: usize) -> String {
+        let mut table = String::new();
+        writeln!(table, "File size:  {:>10} bytes", self.file_size).unwrap();
+        writeln!(
+            table,
+            "Text size:  {:>10} bytes ({} pages, ~{} cycles to page in per segment)",
+            self.text_size,
+            self.text_pages,
+            self.text_paging_cycles()
+        )
+        .unwrap();
+        writeln!(table, "Data size:  {:>10} bytes", self.data_size).unwrap();
+
+        let crates = self.crates();
+        writeln!(table, "\n{:>10} {:>6}  crate", "size", "text%").unwrap();
+        for (name, size) in crates.iter().take(top) {
+            writeln!(table, "{size:>10} {:>6}  {name}", self.text_percent(*size)).unwrap();
+        }
+        if crates.len() > top {
+            writeln!(table, "... {} more", crates.len() - top).unwrap();
+        }
+
+        writeln!(table, "\n{:>10} {:>6}  symbol", "size", "text%").unwrap();
+        for symbol in self.symbols.iter().take(top) {
+            writeln!(
+                table,
+                "{:>10} {:>6}  {}",
+                symbol.size,
+                self.text_percent(symbol.size),
+                symbol.name
+            )
+            .unwrap();
+        }
+        if self.symbols.len() > top {
+            writeln!(table, "... {} more", self.symbols.len() - top).unwrap();
+        }
+        table
+    }
+
+    fn text_percent(&self, size: u64) -> String {
+        if self.text_size == 0 {
+            return "-".to_string();
+        }
+        format!("{:.1}%", size as f64 * 100.0 / self.text_size as f64)
+    }
+}
+
+/// Guess the crate a symbol belongs to from its demangled name.
+///
+/// For trait implementations such as `<alloc::vec::Vec<T> as core::clone::Clone>::clone`, this
+/// is the crate of the implementing type.
+fn crate_name(name: &str) -> &str {
+    let name = name.trim_start_matches('<');
+    match name.find("::") {
+        Some(index) if !name[..index].contains(['<', ' ', '>', '[', '&', '*']) => &name[..index],
+        _ => UNKNOWN_CRATE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{crate_name, PAGE_IN_CYCLES};
+
+    #[test]
+    fn crate_names() {
+        assert_eq!(crate_name("guest::main"), "guest");
+        assert_eq!(
+            crate_name("<alloc::vec::Vec<u8> as core::clone::Clone>::clone"),
+            "alloc"
+        );
+        assert_eq!(crate_name("memcpy"), "[unknown]");
+        assert_eq!(crate_name("<&T as core::fmt::Debug>::fmt"), "[unknown]");
+    }
+
+    #[test]
+    fn page_in_cycles() {
+        // Matches the cost of a page-in observed by the executor.
+        assert_eq!(PAGE_IN_CYCLES, 1094);
+    }
+}
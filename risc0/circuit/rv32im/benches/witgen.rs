@@ -0,0 +1,63 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use risc0_binfmt::MemoryImage;
+use risc0_circuit_rv32im::{
+    prove::{
+        emu::{
+            exec::{execute, DEFAULT_SEGMENT_LIMIT_PO2},
+            testutil::{self, NullSyscall, DEFAULT_SESSION_LIMIT},
+        },
+        engine::{loader::Loader, machine::MachineContext},
+    },
+    CIRCUIT,
+};
+use risc0_zkp::{
+    adapter::TapsProvider,
+    field::{baby_bear::BabyBearElem, Elem as _},
+};
+use risc0_zkvm_platform::PAGE_SIZE;
+
+fn benchmark_witgen(c: &mut Criterion) {
+    let image = MemoryImage::new(&testutil::large_text(), PAGE_SIZE as u32).unwrap();
+    let session = execute(
+        image,
+        DEFAULT_SEGMENT_LIMIT_PO2,
+        DEFAULT_SESSION_LIMIT,
+        &NullSyscall::default(),
+        None,
+    )
+    .unwrap();
+    let segment = session.segments.first().unwrap();
+    let steps = 1 << segment.po2;
+    let machine = MachineContext::new(segment.preflight().unwrap());
+    let last_cycle = Loader::new(steps, CIRCUIT.ctrl_size()).load();
+
+    let mut group = c.benchmark_group("witgen");
+    group.bench_function("load", |b| {
+        b.iter(|| Loader::new(steps, CIRCUIT.ctrl_size()).load())
+    });
+    group.bench_function("inject_exec_backs", |b| {
+        b.iter_batched_ref(
+            || vec![BabyBearElem::INVALID; steps * CIRCUIT.data_size()],
+            |data| machine.inject_exec_backs(steps, last_cycle, data),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_witgen);
+criterion_main!(benches);
@@ -19,13 +19,14 @@ pub mod control_id;
 mod info;
 pub mod layout;
 pub mod poly_ext;
-#[cfg(feature = "prove")]
+#[cfg(feature = "execute")]
 pub mod prove;
 mod taps;
 pub mod trace;
 
 use risc0_zkp::{
     adapter::{CircuitCoreDef, TapsProvider},
+    core::digest::Digest,
     field::baby_bear::BabyBear,
     taps::TapSet,
 };
@@ -41,6 +42,13 @@ pub const GLOBAL_MIX: usize = 0;
 pub const GLOBAL_OUT: usize = 1;
 pub const CIRCUIT: CircuitImpl = CircuitImpl::new();
 
+/// The identity of this circuit, which the executor records in each segment so that a prover
+/// built with a different circuit can refuse to prove it.
+///
+/// This is the Poseidon2 control ID of the smallest segment size, which changes with any change
+/// to the circuit, whether or not the crate version does.
+pub const CIRCUIT_ID: Digest = control_id::POSEIDON2_CONTROL_IDS[0];
+
 impl CircuitImpl {
     const fn new() -> Self {
         CircuitImpl
@@ -15,10 +15,11 @@
 use std::{fmt, ops};
 
 use risc0_zkvm_platform::WORD_SIZE;
+use serde::{Deserialize, Serialize};
 
 use super::pager::PAGE_WORDS;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct ByteAddr(pub u32);
 
 #[derive(Clone, Copy, PartialEq)]
@@ -0,0 +1,114 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Comparison of the cycles the executor expects each instruction to take with the cycles the
+//! prover's preflight gives it, to find where the two disagree.
+//!
+//! The executor sizes segments from its own cycle counts, so an instruction whose cycles it
+//! miscounts, e.g. after a change to an accelerator, leads to a segment that overflows or a trace
+//! that fails to prove, far from the cause.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// The cycles taken by one instruction of a segment, excluding paging.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InsnCycles {
+    /// Address of the instruction.
+    pub pc: u32,
+    /// Number of cycles taken by the instruction.
+    pub cycles: usize,
+}
+
+/// The first instruction of a segment whose cycles differ between the executor and the preflight.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CycleMismatch {
+    /// Index of the segment.
+    pub segment: usize,
+    /// Index of the instruction within the segment.
+    pub index: usize,
+    /// The cycle within the segment at which the instruction started.
+    pub cycle: usize,
+    /// The instruction as counted by the executor, if it executed that many instructions.
+    pub executor: Option<InsnCycles>,
+    /// The instruction as counted by the preflight, if it executed that many instructions.
+    pub preflight: Option<InsnCycles>,
+}
+
+impl CycleMismatch {
+    /// Find the first instruction at which `executor` and `preflight` differ in address or cycles.
+    pub fn find(segment: usize, executor: &[InsnCycles], preflight: &[InsnCycles]) -> Option<Self> {
+        let mut cycle = 0;
+        for index in 0..executor.len().max(preflight.len()) {
+            let (executor, preflight) = (executor.get(index), preflight.get(index));
+            if executor != preflight {
+                return Some(Self {
+                    segment,
+                    index,
+                    cycle,
+                    executor: executor.copied(),
+                    preflight: preflight.copied(),
+                });
+            }
+            cycle += executor.map_or(0, |insn| insn.cycles);
+        }
+        None
+    }
+}
+
+impl fmt::Display for CycleMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |insn: Option<InsnCycles>| match insn {
+            Some(insn) => format!("{} cycles at pc=0x{:08x}", insn.cycles, insn.pc),
+            None => "no instruction".to_string(),
+        };
+        write!(
+            f,
+            "segment {} instruction {} (cycle {}): executor counted {}, preflight counted {}",
+            self.segment,
+            self.index,
+            self.cycle,
+            describe(self.executor),
+            describe(self.preflight)
+        )
+    }
+}
+
+impl std::error::Error for CycleMismatch {}
+
+#[cfg(test)]
+mod tests {
+    use super::{CycleMismatch, InsnCycles};
+
+    #[test]
+    fn find() {
+        let insn = |pc, cycles| InsnCycles { pc, cycles };
+        let executor = [insn(0x1000, 1), insn(0x1004, 2), insn(0x1008, 1)];
+        assert_eq!(CycleMismatch::find(0, &executor, &executor), None);
+
+        let preflight = [insn(0x1000, 1), insn(0x1004, 3), insn(0x1008, 1)];
+        let mismatch = CycleMismatch::find(2, &executor, &preflight).unwrap();
+        assert_eq!(mismatch.segment, 2);
+        assert_eq!(mismatch.index, 1);
+        assert_eq!(mismatch.cycle, 1);
+        assert_eq!(mismatch.executor, Some(insn(0x1004, 2)));
+        assert_eq!(mismatch.preflight, Some(insn(0x1004, 3)));
+
+        let mismatch = CycleMismatch::find(0, &executor, &executor[..2]).unwrap();
+        assert_eq!(mismatch.index, 2);
+        assert_eq!(mismatch.cycle, 3);
+        assert_eq!(mismatch.preflight, None);
+    }
+}
@@ -0,0 +1,209 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Translation of a small set of Linux syscalls into zkVM syscalls.
+//!
+//! This lets lightly-modified RISC-V Linux binaries run under the executor for experimentation.
+//! The binaries must be statically linked for rv32im, and must set up their own stack, since the
+//! executor does not provide the initial process stack that Linux does. Sessions executed in this
+//! mode cannot be proven.
+
+use anyhow::{bail, Result};
+use risc0_binfmt::ExitCode;
+use risc0_zkp::core::digest::Digest;
+use risc0_zkvm_platform::{
+    memory::GUEST_MAX_MEM,
+    syscall::{
+        nr::{SYS_READ, SYS_WRITE},
+        reg_abi::{REG_A0, REG_A1, REG_A2, REG_A3, REG_A4, REG_A5, REG_A7},
+        SyscallName,
+    },
+    WORD_SIZE,
+};
+
+use super::{Executor, Syscall, SyscallContext};
+use crate::prove::{
+    emu::{addr::ByteAddr, rv32im::EmuContext},
+    segment::SyscallRecord,
+};
+
+/// Linux syscall numbers for RISC-V.
+pub mod nr {
+    pub const READ: u32 = 63;
+    pub const WRITE: u32 = 64;
+    pub const EXIT: u32 = 93;
+    pub const EXIT_GROUP: u32 = 94;
+    pub const CLOCK_GETTIME: u32 = 113;
+    pub const BRK: u32 = 214;
+    pub const CLOCK_GETTIME64: u32 = 403;
+}
+
+pub(super) struct LinuxState {
+    // The current program break.
+    brk: u32,
+    // The lowest allowed program break, i.e. the end of the program.
+    brk_min: u32,
+}
+
+impl LinuxState {
+    pub(super) fn new(program_end: u32) -> Self {
+        Self {
+            brk: program_end,
+            brk_min: program_end,
+        }
+    }
+}
+
+/// Presents the arguments of a Linux syscall in the registers where the zkVM syscall handlers
+/// expect them.
+struct LinuxArgs<'c> {
+    ctx: &'c mut dyn SyscallContext,
+    args: [u32; 3],
+}
+
+impl<'c> SyscallContext for LinuxArgs<'c> {
+    fn peek_register(&mut self, idx: usize) -> Result<u32> {
+        match idx {
+            REG_A3 => Ok(self.args[0]),
+            REG_A4 => Ok(self.args[1]),
+            REG_A5 => Ok(self.args[2]),
+            _ => self.ctx.peek_register(idx),
+        }
+    }
+
+    fn peek_u32(&mut self, addr: ByteAddr) -> Result<u32> {
+        self.ctx.peek_u32(addr)
+    }
+
+    fn peek_u8(&mut self, addr: ByteAddr) -> Result<u8> {
+        self.ctx.peek_u8(addr)
+    }
+
+    fn peek_page(&mut self, page_idx: u32) -> Result<Vec<u8>> {
+        self.ctx.peek_page(page_idx)
+    }
+
+    fn get_cycle(&self) -> u64 {
+        self.ctx.get_cycle()
+    }
+
+    fn get_pc(&self) -> u32 {
+        self.ctx.get_pc()
+    }
+}
+
+impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
+    pub(super) fn ecall_linux(&mut self) -> Result<bool> {
+        let syscall_nr = self.load_register(REG_A7)?;
+        tracing::debug!("[{}] ecall_linux({syscall_nr})", self.insn_cycles);
+
+        let ret = match syscall_nr {
+            nr::READ => self.linux_read()?,
+            nr::WRITE => self.linux_write()?,
+            nr::EXIT | nr::EXIT_GROUP => {
+                let exit_code = self.load_register(REG_A0)? & 0xff;
+                self.pending.exit_code = Some(ExitCode::Halted(exit_code));
+                self.pending.output_digest = Some(Digest::ZERO);
+                self.pending.pc = self.pc + WORD_SIZE;
+                return Ok(true);
+            }
+            nr::BRK => self.linux_brk()?,
+            nr::CLOCK_GETTIME | nr::CLOCK_GETTIME64 => self.linux_clock_gettime()?,
+            _ => bail!("Unsupported Linux syscall: {syscall_nr}"),
+        };
+        self.store_register(REG_A0, ret)?;
+
+        self.pending.cycles += 1;
+        self.pending.pc = self.pc + WORD_SIZE;
+
+        Ok(true)
+    }
+
+    fn linux_read(&mut self) -> Result<u32> {
+        let fd = self.load_register(REG_A0)?;
+        let buf = ByteAddr(self.load_register(REG_A1)?);
+        let count = self.load_register(REG_A2)?;
+
+        // The zkVM read returns whole words in `to_guest`, and any trailing bytes in a1.
+        let syscall =
+            self.linux_host_syscall(SYS_READ, [fd, count, 0], count as usize / WORD_SIZE)?;
+        let nread = syscall.regs.0 as usize;
+        let mut bytes: Vec<u8> = bytemuck::cast_slice(&syscall.to_guest).to_vec();
+        bytes.extend_from_slice(&syscall.regs.1.to_le_bytes());
+        bytes.truncate(nread);
+        self.store_region_into_guest(buf, &bytes)?;
+        Ok(nread as u32)
+    }
+
+    fn linux_write(&mut self) -> Result<u32> {
+        let fd = self.load_register(REG_A0)?;
+        let buf = self.load_guest_addr_from_register(REG_A1)?;
+        let count = self.load_register(REG_A2)?;
+        Self::check_guest_addr(buf + count)?;
+        self.linux_host_syscall(SYS_WRITE, [fd, buf.0, count], 0)?;
+        Ok(count)
+    }
+
+    fn linux_brk(&mut self) -> Result<u32> {
+        let addr = self.load_register(REG_A0)?;
+        let state = self.linux.as_mut().unwrap();
+        // Like Linux, return the unchanged break if the request cannot be satisfied.
+        if addr >= state.brk_min && addr < GUEST_MAX_MEM as u32 {
+            state.brk = addr;
+        }
+        Ok(state.brk)
+    }
+
+    fn linux_clock_gettime(&mut self) -> Result<u32> {
+        let timespec = self.load_guest_addr_from_register(REG_A1)?;
+        // The zkVM has no clock, so report one nanosecond per user cycle to keep execution
+        // deterministic.
+        let nanos = self.cycles.user as u64;
+        let secs = nanos / 1_000_000_000;
+        let nanos = (nanos % 1_000_000_000) as u32;
+        // On rv32, `struct timespec` has a 64-bit `tv_sec` followed by a 32-bit `tv_nsec` and
+        // padding.
+        let mut bytes = Vec::with_capacity(16);
+        bytes.extend_from_slice(&secs.to_le_bytes());
+        bytes.extend_from_slice(&nanos.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        self.store_region_into_guest(timespec, &bytes)?;
+        Ok(0)
+    }
+
+    /// Invoke a zkVM syscall with the given arguments in a3-a5, or replay its result if the
+    /// current instruction is being executed again after a split.
+    fn linux_host_syscall(
+        &mut self,
+        name: SyscallName,
+        args: [u32; 3],
+        into_guest_len: usize,
+    ) -> Result<SyscallRecord> {
+        if let Some(syscall) = &self.pending.syscall {
+            tracing::debug!("Replay syscall: {syscall:?}");
+            return Ok(syscall.clone());
+        }
+
+        let mut to_guest = vec![0u32; into_guest_len];
+        let syscall_handler = self.syscall_handler;
+        let mut ctx = LinuxArgs { ctx: self, args };
+        let (a0, a1) = syscall_handler.syscall(name.as_str(), &mut ctx, &mut to_guest)?;
+        let syscall = SyscallRecord {
+            to_guest,
+            regs: (a0, a1),
+        };
+        self.pending.syscall = Some(syscall.clone());
+        Ok(syscall)
+    }
+}
@@ -12,12 +12,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod linux;
 #[cfg(test)]
 mod tests;
 
-use std::{array, cell::RefCell, collections::BTreeSet, mem, rc::Rc};
+use std::{
+    array,
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet},
+    mem,
+    ops::{ControlFlow, Range},
+    rc::Rc,
+};
 
-use anyhow::{bail, ensure, Result};
+use anyhow::{bail, ensure, Context as _, Result};
 use crypto_bigint::{CheckedMul as _, Encoding as _, NonZero, U256, U512};
 use risc0_binfmt::{ExitCode, MemoryImage, Program, SystemState};
 use risc0_zkp::{
@@ -34,25 +43,28 @@ use risc0_zkvm_platform::{
     syscall::{
         bigint, ecall, halt,
         reg_abi::{REG_A0, REG_A1, REG_A2, REG_A3, REG_A4, REG_MAX, REG_T0},
-        IO_CHUNK_WORDS,
+        trap, trap_kind, IO_CHUNK_WORDS,
     },
     PAGE_SIZE, WORD_SIZE,
 };
+use serde::{Deserialize, Serialize};
 use sha2::digest::generic_array::GenericArray;
 
+use self::linux::LinuxState;
 use super::{
     addr::{ByteAddr, WordAddr},
     pager::PagedMemory,
-    rv32im::{DecodedInstruction, EmuContext, Emulator, Instruction, TrapCause},
+    rv32im::{DecodedInstruction, EmuContext, Emulator, InsnKind, Instruction, TrapCause},
     BIGINT_CYCLES, SYSTEM_START,
 };
 use crate::{
     prove::{
         emu::sha_cycles,
         engine::loader::{FINI_CYCLES, INIT_CYCLES},
-        segment::{Segment, SyscallRecord},
+        segment::{Segment, SegmentPaging, SyscallRecord},
     },
-    trace::{TraceCallback, TraceEvent},
+    trace::{TraceCallback, TraceEvent, WatchHit, WatchKind},
+    CIRCUIT_ID,
 };
 
 pub const DEFAULT_SEGMENT_LIMIT_PO2: usize = 20;
@@ -112,6 +124,113 @@ pub struct ExecutorResult {
     pub pre_state: SystemState,
     pub post_state: SystemState,
     pub output_digest: Option<Digest>,
+    pub page_faults: BTreeMap<u32, PageFaultCounts>,
+    pub page_accesses: Option<BTreeMap<u32, PageAccessCounts>>,
+    pub paging: PagingStats,
+    pub insn_counts: Option<BTreeMap<InsnKind, u64>>,
+    pub accelerators: BTreeMap<Accelerator, AcceleratorUsage>,
+    pub traps: TrapState,
+}
+
+/// An instruction executed by [Executor::step].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StepOutcome {
+    /// The kind of the instruction, unless it trapped.
+    pub kind: Option<InsnKind>,
+
+    /// The user cycles spent executing the instruction.
+    pub cycles: u64,
+
+    /// The exit code of the guest, if the instruction halted or paused it.
+    pub exit_code: Option<ExitCode>,
+}
+
+/// What the guest executed between two calls of the handler passed to [Executor::monitor].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MonitorSample {
+    /// The user cycles executed since the last call.
+    pub cycles: u64,
+
+    /// The lowest address of the instructions executed since the last call.
+    pub min_pc: u32,
+
+    /// The highest address of the instructions executed since the last call.
+    pub max_pc: u32,
+
+    /// The number of syscalls made since the last call.
+    pub syscalls: u64,
+
+    /// The number of segments the run has ended so far.
+    pub segments: usize,
+
+    /// The total cycles of the segments the run has ended so far.
+    pub total_cycles: u64,
+
+    /// The bytes of guest memory held by the memory image, i.e. the pages that the program or
+    /// the guest has written to.
+    pub resident_bytes: u64,
+}
+
+/// An instruction executed by the guest, as passed to the handler of [Executor::meter].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MeteredOp<'a> {
+    /// An instruction other than an `ecall`.
+    Insn(InsnKind),
+
+    /// An `ecall`, named after the syscall or [Accelerator] it invokes, or `halt` or `input`.
+    Ecall(&'a str),
+}
+
+/// An accelerator invoked by an `ecall`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Accelerator {
+    Sha,
+    BigInt,
+}
+
+impl Accelerator {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sha => "sha",
+            Self::BigInt => "bigint",
+        }
+    }
+}
+
+/// The invocations of an accelerator over a session, and the cycles they took.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AcceleratorUsage {
+    pub invocations: u64,
+    pub cycles: u64,
+}
+
+/// The number of times a page was paged in (read) and paged out (written) over a session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PageFaultCounts {
+    pub reads: u64,
+    pub writes: u64,
+}
+
+/// The number of loads and stores executed by the guest on a page over a session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PageAccessCounts {
+    pub loads: u64,
+    pub stores: u64,
+}
+
+/// The cycles spent paging memory in and out over a session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PagingStats {
+    /// The number of cycles spent paging memory in and out.
+    pub cycles: u64,
+
+    /// The number of paging cycles saved because accesses to pages close to each other, such as
+    /// the pages touched by a large memcpy or a syscall, page in the page table pages that they
+    /// share only once per segment.
+    ///
+    /// This is the difference between the cycles that paging would take if every page accessed
+    /// in a segment paged its whole path to the root in and out on its own, and [Self::cycles].
+    pub saved_cycles: u64,
 }
 
 #[derive(Default)]
@@ -129,11 +248,47 @@ pub struct SimpleSession {
 struct PendingState {
     pc: ByteAddr,
     insn: u32,
+    kind: Option<InsnKind>,
+    accelerator: Option<Accelerator>,
+    // The name of the `ecall` being executed, unless it invokes an accelerator.
+    ecall: Option<Cow<'static, str>>,
     cycles: usize,
     syscall: Option<SyscallRecord>,
     output_digest: Option<Digest>,
     exit_code: Option<ExitCode>,
     events: BTreeSet<TraceEvent>,
+    // The trap handling state after the instruction, if the instruction changes it.
+    traps: Option<TrapState>,
+}
+
+/// The trap handlers registered by the guest with the `TRAP` ecall, and the trap being handled.
+///
+/// This is not part of the memory image, so a guest stopped by a run and continued by another
+/// has to be given the state the first run ended with in [ExecutorResult::traps].
+///
+/// Traps are only supported by the executor: the circuit cannot prove segments that register a
+/// trap handler, enter one, or return from one with `mret`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TrapState {
+    handlers: BTreeMap<u32, ByteAddr>,
+    frame: Option<TrapFrame>,
+}
+
+impl TrapState {
+    /// Whether the guest has not registered any trap handlers.
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty() && self.frame.is_none()
+    }
+}
+
+/// A trap being handled by a handler of the guest.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+struct TrapFrame {
+    kind: u32,
+    // The address `mret` returns to.
+    epc: ByteAddr,
+    // The instruction that trapped, or the number of the unknown ecall.
+    value: u32,
 }
 
 pub struct Executor<'a, 'b, S: Syscall> {
@@ -147,16 +302,109 @@ pub struct Executor<'a, 'b, S: Syscall> {
     output_digest: Option<Digest>,
     pending: PendingState,
     trace: Vec<Rc<RefCell<dyn TraceCallback + 'b>>>,
+    trace_prev_values: bool,
+    trace_paging: bool,
+    linux: Option<LinuxState>,
+    // The po2 of each segment by index, overriding the po2 passed to `run`.
+    segment_po2_schedule: Vec<usize>,
+    // Paging cycles in the current segment, as of the last committed instruction.
+    paging_cycles: usize,
     cycles: SessionCycles,
+    page_faults: BTreeMap<u32, PageFaultCounts>,
+    page_accesses: Option<BTreeMap<u32, PageAccessCounts>>,
+    // The page loaded or stored by the instruction being executed, and whether it was a store, if
+    // page accesses are counted.
+    data_access: Cell<Option<(u32, bool)>>,
+    paging: PagingStats,
+    insn_counts: Option<BTreeMap<InsnKind, u64>>,
+    accelerators: BTreeMap<Accelerator, AcceleratorUsage>,
+    // Addresses below the guest stack that loads and stores may not access.
+    stack_guard: Option<Range<u32>>,
+    // The address in the stack guard accessed by the instruction being executed, if any.
+    guard_fault: Cell<Option<ByteAddr>>,
+    // Decides whether to stop the run at a split, given the number of segments so far.
+    stop_at_split: Option<Box<dyn FnMut(usize) -> bool + 'b>>,
+    breakpoints: Option<Breakpoints<'b>>,
+    // The emulator of the instructions executed by `step`, created by the first of them.
+    step_emu: Option<Emulator>,
+    watchpoints: Vec<(Range<u32>, WatchKind)>,
+    // The address accessed by the instruction being executed, and how, if it is watched.
+    watch_hit: Cell<Option<(ByteAddr, WatchKind)>>,
+    watch_handler: Option<Box<dyn FnMut(WatchHit) -> Result<()> + 'b>>,
+    monitors: Vec<Monitor<'b>>,
+    meter: Option<Meter<'b>>,
+    traps: TrapState,
+}
+
+type Meter<'b> = Box<dyn FnMut(ByteAddr, MeteredOp, u64) -> ControlFlow<()> + 'b>;
+
+/// The addresses to break at, and the handler deciding whether to stop there.
+struct Breakpoints<'b> {
+    addrs: BTreeSet<u32>,
+    handler: Box<dyn FnMut(&mut dyn SyscallContext) -> Result<bool> + 'b>,
+    // The user cycle of the last break, so an instruction replayed after a split is not broken
+    // at twice.
+    last_cycle: Option<usize>,
+}
+
+/// What the guest executed since the monitor handler was last called, and the handler.
+struct Monitor<'b> {
+    interval: usize,
+    // The user cycle the handler was last called at.
+    last_cycle: usize,
+    min_pc: u32,
+    max_pc: u32,
+    syscalls: u64,
+    handler: Box<dyn FnMut(&mut dyn SyscallContext, &MonitorSample) -> Result<()> + 'b>,
+}
+
+impl Monitor<'_> {
+    fn observe(&mut self, pc: u32, syscall: bool) {
+        self.min_pc = self.min_pc.min(pc);
+        self.max_pc = self.max_pc.max(pc);
+        self.syscalls += syscall as u64;
+    }
+
+    /// Take the sample of what was executed up to `cycle`, and start a new one.
+    fn sample(
+        &mut self,
+        cycle: usize,
+        segments: usize,
+        total_cycles: usize,
+        resident_bytes: u64,
+    ) -> MonitorSample {
+        let sample = MonitorSample {
+            cycles: (cycle - self.last_cycle) as u64,
+            min_pc: self.min_pc,
+            max_pc: self.max_pc,
+            syscalls: self.syscalls,
+            segments,
+            total_cycles: total_cycles as u64,
+            resident_bytes,
+        };
+        self.reset(cycle);
+        sample
+    }
+
+    fn reset(&mut self, cycle: usize) {
+        self.last_cycle = cycle;
+        self.min_pc = u32::MAX;
+        self.max_pc = 0;
+        self.syscalls = 0;
+    }
 }
 
 impl PendingState {
     fn reset(&mut self, pc: ByteAddr) {
         self.pc = pc;
+        self.kind = None;
+        self.accelerator = None;
+        self.ecall = None;
         self.cycles = 0;
         self.syscall = None;
         self.output_digest = None;
         self.exit_code = None;
+        self.traps = None;
     }
 }
 
@@ -180,15 +428,221 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
             pending: PendingState {
                 pc,
                 insn: 0,
+                kind: None,
+                accelerator: None,
+                ecall: None,
                 cycles: 0,
                 syscall: None,
                 output_digest: None,
                 exit_code: None,
                 events: BTreeSet::new(),
+                traps: None,
             },
             trace,
+            trace_prev_values: false,
+            trace_paging: false,
+            linux: None,
+            segment_po2_schedule: Vec::new(),
+            paging_cycles: 0,
             cycles: SessionCycles::default(),
+            page_faults: BTreeMap::new(),
+            page_accesses: None,
+            data_access: Cell::new(None),
+            paging: PagingStats::default(),
+            insn_counts: None,
+            accelerators: BTreeMap::new(),
+            stack_guard: None,
+            guard_fault: Cell::new(None),
+            stop_at_split: None,
+            breakpoints: None,
+            step_emu: None,
+            watchpoints: Vec::new(),
+            watch_hit: Cell::new(None),
+            watch_handler: None,
+            monitors: Vec::new(),
+            meter: None,
+            traps: TrapState::default(),
+        }
+    }
+
+    /// Continue a guest with the trap handlers it registered, and the trap it was handling, when
+    /// the run that stopped it ended, as returned in [ExecutorResult::traps].
+    pub fn traps(&mut self, traps: TrapState) {
+        self.traps = traps;
+    }
+
+    /// Record the previous value of registers and memory in [TraceEvent::RegisterPrev] and
+    /// [TraceEvent::MemoryPrev] events, along with their [TraceEvent::RegisterSet] and
+    /// [TraceEvent::MemorySet] events.
+    pub fn trace_prev_values(&mut self, enabled: bool) {
+        self.trace_prev_values = enabled;
+    }
+
+    /// Report the cycles each instruction spends paging in [TraceEvent::Paging] events.
+    pub fn trace_paging(&mut self, enabled: bool) {
+        self.trace_paging = enabled;
+    }
+
+    /// Interpret every `ecall` as a Linux syscall, translating a small set of them into zkVM
+    /// syscalls. See [linux] for details.
+    ///
+    /// `program_end` is the end of the loaded program, which is where the program break starts.
+    pub fn linux_compat(&mut self, program_end: u32) {
+        self.linux = Some(LinuxState::new(program_end));
+    }
+
+    /// Count the number of times each kind of instruction is executed, and return the counts in
+    /// [ExecutorResult::insn_counts].
+    pub fn collect_insn_counts(&mut self, enabled: bool) {
+        self.insn_counts = enabled.then(BTreeMap::new);
+    }
+
+    /// Count the number of loads and stores the guest executes on each page, and return the
+    /// counts in [ExecutorResult::page_accesses].
+    pub fn collect_page_accesses(&mut self, enabled: bool) {
+        self.page_accesses = enabled.then(BTreeMap::new);
+    }
+
+    /// Hold the pages of guest memory in a buffer backed by OS hugepages, if available, to reduce
+    /// TLB misses in guests that touch many pages. This must be called before [Executor::run].
+    pub fn use_hugepages(&mut self, enabled: bool) {
+        if enabled {
+            self.pager.use_hugepages();
+        }
+    }
+
+    /// Fail with a stack overflow error when a load or store accesses an address in `guard`.
+    pub fn stack_guard(&mut self, guard: Range<u32>) {
+        self.stack_guard = Some(guard);
+    }
+
+    /// Stop the run at the end of a split segment if `stop` returns true when called with the
+    /// number of segments so far, making that segment, which exits with [ExitCode::SystemSplit],
+    /// the last of the run.
+    ///
+    /// The post-image of the run can be executed again to resume the guest where it stopped.
+    /// Splits in the middle of a syscall are not stopped at, so that the syscall is not made
+    /// twice.
+    pub fn stop_at_split(&mut self, stop: impl FnMut(usize) -> bool + 'b) {
+        self.stop_at_split = Some(Box::new(stop));
+    }
+
+    /// Call `handler` before executing an instruction at an address in `addrs`, with the executor
+    /// as a [SyscallContext] to inspect the registers and memory of the guest.
+    ///
+    /// If `handler` returns true, the run stops before the instruction, ending with a segment that
+    /// exits with [ExitCode::SystemSplit], as with [Executor::stop_at_split]. The post-image of
+    /// the run can be executed again to resume the guest at the instruction.
+    pub fn breakpoints(
+        &mut self,
+        addrs: BTreeSet<u32>,
+        handler: impl FnMut(&mut dyn SyscallContext) -> Result<bool> + 'b,
+    ) {
+        self.breakpoints = Some(Breakpoints {
+            addrs,
+            handler: Box::new(handler),
+            last_cycle: None,
+        });
+    }
+
+    /// Report loads and stores by the guest of the memory in each range, and writes to it by the
+    /// host, to the handler set with [Executor::on_watch] and, if there are trace callbacks, as
+    /// [TraceEvent::Watchpoint] events. Instructions are matched by the word they access, so a
+    /// load of a byte next to a watched byte in the same word is reported.
+    pub fn watchpoints(&mut self, watchpoints: Vec<(Range<u32>, WatchKind)>) {
+        self.watchpoints = watchpoints;
+    }
+
+    /// Call `handler` with the first watched access of each instruction that accesses memory
+    /// covered by the [watchpoints][Executor::watchpoints], once the instruction completes,
+    /// without tracing every instruction. If `handler` fails, so does the run.
+    pub fn on_watch(&mut self, handler: impl FnMut(WatchHit) -> Result<()> + 'b) {
+        self.watch_handler = Some(Box::new(handler));
+    }
+
+    /// Call `handler` every `interval` user cycles, with the executor as a [SyscallContext] and a
+    /// [MonitorSample] of what the guest executed since the last call, e.g. to detect a guest
+    /// that is stuck in a loop or to report progress. The run fails with the error returned by
+    /// `handler`, if any. This may be called more than once, to add handlers with different
+    /// intervals.
+    pub fn monitor(
+        &mut self,
+        interval: usize,
+        handler: impl FnMut(&mut dyn SyscallContext, &MonitorSample) -> Result<()> + 'b,
+    ) {
+        let mut monitor = Monitor {
+            interval: interval.max(1),
+            last_cycle: 0,
+            min_pc: u32::MAX,
+            max_pc: 0,
+            syscalls: 0,
+            handler: Box::new(handler),
+        };
+        monitor.reset(0);
+        self.monitors.push(monitor);
+    }
+
+    /// Call `handler` with the address, kind, and user cycles of each instruction the guest
+    /// executes, not counting the cycles spent paging memory in and out. If `handler` returns
+    /// [ControlFlow::Break], the run fails at the instruction.
+    pub fn meter(&mut self, handler: impl FnMut(ByteAddr, MeteredOp, u64) -> ControlFlow<()> + 'b) {
+        self.meter = Some(Box::new(handler));
+    }
+
+    /// Split the segment with index `i` at `schedule[i]` po2 rather than at the po2 passed to
+    /// [Executor::run]. Segments past the end of the schedule use the po2 passed to
+    /// [Executor::run].
+    pub fn segment_po2_schedule(&mut self, schedule: Vec<usize>) {
+        self.segment_po2_schedule = schedule;
+    }
+
+    /// Returns the address of the instruction currently being executed.
+    ///
+    /// If [Executor::run] fails, this is the instruction that caused the failure.
+    pub fn pc(&self) -> ByteAddr {
+        self.pc
+    }
+
+    /// Returns the number of user cycles executed by the current run.
+    ///
+    /// If [Executor::run] fails, this is the number of cycles executed before the failure.
+    pub fn user_cycles(&self) -> u64 {
+        self.cycles.user as u64
+    }
+
+    /// Execute the next instruction, without ending a segment.
+    ///
+    /// The executor stays live between steps, so a step costs only its instruction, and no
+    /// segments are produced. Call [Executor::finish_steps] to take the memory image once
+    /// stepping is done. Breakpoints, monitors, and the session and segment limits do not apply.
+    pub fn step(&mut self) -> Result<StepOutcome> {
+        if let Some(exit_code) = self.exit_code {
+            bail!("The guest has already exited with {exit_code:?}");
         }
+        let mut emu = self.step_emu.take().unwrap_or_else(Emulator::new);
+        self.watch_hit.set(None);
+        let result = emu.step(self);
+        self.step_emu = Some(emu);
+        result?;
+
+        let kind = self.pending.kind;
+        let cycles = self.pending.cycles as u64;
+        self.advance()?;
+        Ok(StepOutcome {
+            kind,
+            cycles,
+            exit_code: self.exit_code,
+        })
+    }
+
+    /// The memory image and trap state where the instructions executed by [Executor::step]
+    /// stopped, with the memory they wrote committed to the image.
+    ///
+    /// An instruction that failed part way through is undone, so the image is as it was before it.
+    pub fn finish_steps(mut self) -> (MemoryImage, TrapState) {
+        self.pager.undo();
+        self.pager.commit(self.pc);
+        (self.pager.image, self.traps)
     }
 
     pub fn run<F: FnMut(Segment) -> Result<()>>(
@@ -204,7 +658,20 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
         // leave room for reserved cycles
         const RESERVED_CYCLES: usize =
             INIT_CYCLES + MIN_HALT_CYCLES + PAGE_FINI_CYCLES + FINI_CYCLES + ZK_CYCLES;
-        let segment_limit = (1 << segment_po2) - RESERVED_CYCLES;
+        let schedule = self.segment_po2_schedule.clone();
+        if let Some(po2) = schedule
+            .iter()
+            .find(|po2| !(MIN_CYCLES_PO2..=MAX_CYCLES_PO2).contains(po2))
+        {
+            bail!("Segment po2 {po2} in the schedule is outside of {MIN_CYCLES_PO2}..={MAX_CYCLES_PO2}");
+        }
+        let segment_po2_at = |index: usize| schedule.get(index).copied().unwrap_or(segment_po2);
+
+        let page_size = self.pager.image.page_size();
+        ensure!(
+            page_size as usize == PAGE_SIZE,
+            "Unsupported page size: the circuit requires {PAGE_SIZE}-byte pages, but the memory image uses {page_size}-byte pages"
+        );
 
         self.reset();
 
@@ -219,15 +686,27 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
 
             if let Some(max_cycles) = max_cycles {
                 if self.cycles.user >= max_cycles as usize {
-                    bail!("Session limit exceeded");
+                    tracing::debug!("stopping at session limit {max_cycles}");
+                    self.exit_code = Some(ExitCode::SessionLimit);
+                    break;
                 }
             }
 
+            if self.check_breakpoint()? {
+                tracing::debug!("stopping at breakpoint {:?}", self.pc);
+                self.exit_code = Some(ExitCode::SystemSplit);
+                break;
+            }
+
+            self.watch_hit.set(None);
             emu.step(self)?;
 
+            let segment_po2 = segment_po2_at(segments);
+            let segment_limit = (1 << segment_po2) - RESERVED_CYCLES;
             let segment_cycles = self.insn_cycles + self.pager.cycles + self.pending.cycles;
             if segment_cycles < segment_limit {
                 self.advance()?;
+                self.check_monitors(segments)?;
             } else if self.insn_cycles == 0 {
                 bail!(
                     "segment limit ({segment_limit}) too small for instruction at pc: {:?}",
@@ -245,7 +724,10 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
                 );
 
                 // split
+                let paging = self.count_page_faults();
                 let (pre_state, partial_image, post_state) = self.pager.commit(self.pc);
+                let split_state = post_state.clone();
+                self.flush_trace()?;
                 callback(Segment {
                     partial_image,
                     pre_state,
@@ -257,23 +739,46 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
                     index: segments,
                     input_digest: self.input_digest,
                     output_digest: self.output_digest,
+                    paging,
+                    circuit_id: CIRCUIT_ID,
                 })?;
                 segments += 1;
                 self.cycles.total += 1 << segment_po2;
                 self.pager.clear();
+                self.paging_cycles = 0;
                 self.insn_cycles = 0;
 
+                if self.pending.syscall.is_none()
+                    && self
+                        .stop_at_split
+                        .as_mut()
+                        .is_some_and(|stop| stop(segments))
+                {
+                    tracing::debug!("stopping after {segments} segments");
+                    return self.finish(
+                        segments,
+                        ExitCode::SystemSplit,
+                        initial_state,
+                        split_state,
+                    );
+                }
+
                 // replay the current instruction in a new segment
                 self.pending.pc = self.pc;
                 self.pending.cycles = 0;
+                self.pending.kind = None;
+                self.pending.ecall = None;
+                self.pending.traps = None;
             }
         }
 
+        let paging = self.count_page_faults();
         let (pre_state, partial_image, post_state) = self.pager.commit(self.pc);
         let segment_cycles = self.insn_cycles + self.pager.cycles + RESERVED_CYCLES;
         let po2 = log2_ceil(segment_cycles.next_power_of_two());
         let exit_code = self.exit_code.unwrap();
 
+        self.flush_trace()?;
         callback(Segment {
             partial_image,
             pre_state: pre_state.clone(),
@@ -285,6 +790,8 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
             index: segments,
             input_digest: self.input_digest,
             output_digest: self.output_digest,
+            paging,
+            circuit_id: CIRCUIT_ID,
         })?;
         segments += 1;
         self.cycles.total += 1 << po2;
@@ -298,6 +805,17 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
             _ => post_state,
         };
 
+        self.finish(segments, exit_code, initial_state, post_state)
+    }
+
+    /// The result of a run that ended with `exit_code` after `segments` segments.
+    fn finish(
+        &mut self,
+        segments: usize,
+        exit_code: ExitCode,
+        initial_state: SystemState,
+        post_state: SystemState,
+    ) -> Result<ExecutorResult> {
         Ok(ExecutorResult {
             segments,
             exit_code,
@@ -307,10 +825,118 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
             pre_state: initial_state,
             post_state,
             output_digest: self.output_digest,
+            page_faults: mem::take(&mut self.page_faults),
+            page_accesses: self.page_accesses.as_mut().map(mem::take),
+            paging: mem::take(&mut self.paging),
+            insn_counts: self.insn_counts.as_mut().map(mem::take),
+            accelerators: mem::take(&mut self.accelerators),
+            traps: self.traps.clone(),
         })
     }
 
+    /// Have the trace callbacks pass on the events they hold back, at the end of a segment.
+    fn flush_trace(&self) -> Result<()> {
+        for trace in &self.trace {
+            trace.borrow_mut().flush()?;
+        }
+        Ok(())
+    }
+
+    /// Call the breakpoint handler if the next instruction is at a breakpoint, and return whether
+    /// to stop there.
+    fn check_breakpoint(&mut self) -> Result<bool> {
+        let Some(mut breakpoints) = self.breakpoints.take() else {
+            return Ok(false);
+        };
+        let hit = breakpoints.addrs.contains(&self.pc.0)
+            && breakpoints.last_cycle != Some(self.cycles.user);
+        if hit {
+            breakpoints.last_cycle = Some(self.cycles.user);
+        }
+        let stop = if hit {
+            (breakpoints.handler)(self)
+        } else {
+            Ok(false)
+        };
+        self.breakpoints = Some(breakpoints);
+        stop
+    }
+
+    /// Call each monitor handler whose interval has passed since it was last called, given the
+    /// number of segments the run has ended so far.
+    fn check_monitors(&mut self, segments: usize) -> Result<()> {
+        let due = self
+            .monitors
+            .iter()
+            .any(|monitor| self.cycles.user - monitor.last_cycle >= monitor.interval);
+        if !due {
+            return Ok(());
+        }
+        let mut monitors = mem::take(&mut self.monitors);
+        let image = &self.pager.image;
+        let resident_bytes = image.pages.len() as u64 * image.page_size() as u64;
+        let result = monitors.iter_mut().try_for_each(|monitor| {
+            if self.cycles.user - monitor.last_cycle < monitor.interval {
+                return Ok(());
+            }
+            let sample = monitor.sample(
+                self.cycles.user,
+                segments,
+                self.cycles.total,
+                resident_bytes,
+            );
+            (monitor.handler)(self, &sample)
+        });
+        self.monitors = monitors;
+        result
+    }
+
+    fn count_page_faults(&mut self) -> SegmentPaging {
+        let faults = self.pager.get_faults();
+        for page_idx in faults.reads {
+            self.page_faults.entry(page_idx).or_default().reads += 1;
+        }
+        for page_idx in faults.writes {
+            self.page_faults.entry(page_idx).or_default().writes += 1;
+        }
+        let cycles = self.pager.cycles as u64;
+        self.paging.cycles += cycles;
+        self.paging.saved_cycles += (self.pager.unshared_cycles() as u64).saturating_sub(cycles);
+        self.pager.paging()
+    }
+
     fn advance(&mut self) -> Result<()> {
+        let ecall = self.pending.ecall.take();
+        if let (Some(meter), Some(kind)) = (&mut self.meter, self.pending.kind) {
+            let op = match (&ecall, self.pending.accelerator) {
+                (_, Some(accelerator)) => MeteredOp::Ecall(accelerator.name()),
+                (Some(name), None) => MeteredOp::Ecall(name),
+                (None, None) => MeteredOp::Insn(kind),
+            };
+            if meter(self.pc, op, self.pending.cycles as u64).is_break() {
+                bail!("Execution stopped by the cycle meter at {:?}", self.pc);
+            }
+        }
+        if let Some((addr, kind)) = self.watch_hit.take() {
+            if let Some(handler) = &mut self.watch_handler {
+                handler(WatchHit {
+                    pc: self.pc.0,
+                    addr: addr.0,
+                    kind,
+                })?;
+            }
+            if !self.trace.is_empty() {
+                self.pending.events.insert(TraceEvent::Watchpoint {
+                    pc: self.pc.0,
+                    addr: addr.0,
+                    kind,
+                });
+            }
+        }
+        for monitor in &mut self.monitors {
+            monitor.observe(self.pc.0, self.pending.syscall.is_some());
+        }
+        let paging_cycles = self.pager.cycles - self.paging_cycles;
         for trace in &self.trace {
             trace
                 .borrow_mut()
@@ -323,11 +949,23 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
             for event in &self.pending.events {
                 trace.borrow_mut().trace_callback(event.clone()).unwrap();
             }
+
+            if self.trace_paging && paging_cycles > 0 {
+                trace.borrow_mut().trace_callback(TraceEvent::Paging {
+                    pc: self.pc.0,
+                    cycles: paging_cycles.try_into()?,
+                })?;
+            }
         }
 
         self.pc = self.pending.pc;
         self.insn_cycles += self.pending.cycles;
         self.cycles.user += self.pending.cycles;
+        if let Some(accelerator) = self.pending.accelerator.take() {
+            let usage = self.accelerators.entry(accelerator).or_default();
+            usage.invocations += 1;
+            usage.cycles += self.pending.cycles as u64;
+        }
         self.pending.cycles = 0;
         self.pending.events.clear();
         if let Some(syscall) = self.pending.syscall.take() {
@@ -335,19 +973,51 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
         }
         self.output_digest = self.pending.output_digest.take();
         self.exit_code = self.pending.exit_code.take();
+        if let Some(traps) = self.pending.traps.take() {
+            self.traps = traps;
+        }
+        // Only instructions that complete normally count as a load or store.
+        if let Some((page_idx, store)) = self.data_access.take() {
+            if let (Some(counts), Some(_)) = (&mut self.page_accesses, self.pending.kind) {
+                let counts = counts.entry(page_idx).or_default();
+                if store {
+                    counts.stores += 1;
+                } else {
+                    counts.loads += 1;
+                }
+            }
+        }
+        if let (Some(counts), Some(kind)) = (&mut self.insn_counts, self.pending.kind.take()) {
+            *counts.entry(kind).or_default() += 1;
+        }
         self.pager.commit_step();
+        self.paging_cycles = self.pager.cycles;
 
         Ok(())
     }
 
     fn reset(&mut self) {
         self.pager.clear();
+        self.paging_cycles = 0;
         self.exit_code = None;
         self.syscalls.clear();
         self.output_digest = None;
         self.pending.reset(self.pc);
         self.cycles.user = 0;
         self.cycles.total = 0;
+        self.page_faults.clear();
+        if let Some(counts) = &mut self.page_accesses {
+            counts.clear();
+        }
+        self.data_access.set(None);
+        self.paging = PagingStats::default();
+        if let Some(counts) = &mut self.insn_counts {
+            counts.clear();
+        }
+        self.accelerators.clear();
+        for monitor in &mut self.monitors {
+            monitor.reset(0);
+        }
     }
 }
 
@@ -368,6 +1038,7 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
             _ => bail!("Illegal halt type: {halt_type}"),
         };
         self.pending.output_digest = Some(output.into());
+        self.pending.ecall = Some(Cow::Borrowed("halt"));
         self.pending.pc = self.pc + WORD_SIZE;
 
         Ok(true)
@@ -381,6 +1052,7 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
         self.store_register(REG_A0, word)?;
 
         self.pending.cycles += 1;
+        self.pending.ecall = Some(Cow::Borrowed("input"));
         self.pending.pc = self.pc + WORD_SIZE;
 
         Ok(true)
@@ -433,11 +1105,103 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
         tracing::trace!("{syscall:08x?}");
 
         self.pending.cycles += chunks + 1; // syscallBody + syscallFini
+        self.pending.ecall = Some(Cow::Owned(syscall_name));
         self.pending.pc = self.pc + WORD_SIZE;
 
         Ok(true)
     }
 
+    fn ecall_trap(&mut self) -> Result<bool> {
+        let a0 = self.load_register(REG_A0)?;
+        let op = a0 & 0xff;
+        let kind = (a0 >> 8) & 0xff;
+        tracing::debug!("[{}] ecall_trap({op}, {kind})", self.insn_cycles);
+
+        let mut traps = self.current_traps().clone();
+        match op {
+            trap::SET_HANDLER => {
+                ensure!(
+                    matches!(
+                        kind,
+                        trap_kind::ILLEGAL_INSTRUCTION | trap_kind::UNKNOWN_ECALL
+                    ),
+                    "Illegal trap kind: {kind}"
+                );
+                let handler = ByteAddr(self.load_register(REG_A1)?);
+                if handler.is_null() {
+                    traps.handlers.remove(&kind);
+                } else {
+                    Self::check_guest_addr(handler)?;
+                    traps.handlers.insert(kind, handler);
+                }
+            }
+            trap::INFO => {
+                let frame = traps
+                    .frame
+                    .context("Trap info requested outside of a trap handler")?;
+                self.store_register(REG_A0, frame.kind)?;
+                self.store_register(REG_A1, frame.epc.0)?;
+                self.store_register(REG_A2, frame.value)?;
+            }
+            trap::SET_EPC => {
+                let epc = ByteAddr(self.load_register(REG_A1)?);
+                Self::check_guest_addr(epc)?;
+                traps
+                    .frame
+                    .as_mut()
+                    .context("Trap return address set outside of a trap handler")?
+                    .epc = epc;
+            }
+            _ => bail!("Illegal trap operation: {op}"),
+        }
+
+        self.pending.traps = Some(traps);
+        self.pending.cycles += 1;
+        self.pending.ecall = Some(Cow::Borrowed("trap"));
+        self.pending.pc = self.pc + WORD_SIZE;
+
+        Ok(true)
+    }
+
+    /// The trap handling state as of the instruction being executed.
+    fn current_traps(&self) -> &TrapState {
+        self.pending.traps.as_ref().unwrap_or(&self.traps)
+    }
+
+    /// Continue at the handler the guest registered for traps of `kind`, if any, returning
+    /// whether there is one.
+    fn vector_trap(&mut self, kind: u32, value: u32) -> Result<bool> {
+        let traps = self.current_traps();
+        let Some(&handler) = traps.handlers.get(&kind) else {
+            return Ok(false);
+        };
+        if let Some(frame) = &traps.frame {
+            bail!(
+                "Trap of kind {kind} at pc: {:?} within the handler of a trap of kind {} at pc: {:?}",
+                self.pc,
+                frame.kind,
+                frame.epc
+            );
+        }
+        tracing::debug!(
+            "[{}] trap({kind}, 0x{value:08x}) to {handler:?}",
+            self.insn_cycles
+        );
+
+        let mut traps = traps.clone();
+        traps.frame = Some(TrapFrame {
+            kind,
+            epc: self.pc,
+            value,
+        });
+        self.pending.traps = Some(traps);
+        self.pending.insn = self.pager.peek(self.pc.waddr())?;
+        self.pending.cycles += 1;
+        self.pending.pc = handler;
+
+        Ok(true)
+    }
+
     fn ecall_sha(&mut self) -> Result<bool> {
         tracing::debug!("[{}] ecall_sha", self.insn_cycles);
         let state_out_ptr = self.load_guest_addr_from_register(REG_A0)?;
@@ -484,6 +1248,7 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
         self.store_region_into_guest(state_out_ptr, bytemuck::cast_slice(&state))?;
 
         self.pending.cycles += sha_cycles(count as usize);
+        self.pending.accelerator = Some(Accelerator::Sha);
         self.pending.pc = self.pc + WORD_SIZE;
 
         Ok(true)
@@ -534,11 +1299,48 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
         }
 
         self.pending.cycles += BIGINT_CYCLES;
+        self.pending.accelerator = Some(Accelerator::BigInt);
         self.pending.pc = self.pc + WORD_SIZE;
 
         Ok(true)
     }
 
+    /// Whether `addr` is in the stack guard, recording it for the trap that follows if so.
+    fn in_stack_guard(&self, addr: ByteAddr) -> bool {
+        let hit = self
+            .stack_guard
+            .as_ref()
+            .is_some_and(|guard| guard.contains(&addr.0));
+        if hit {
+            self.guard_fault.set(Some(addr));
+        }
+        hit
+    }
+
+    // Whether any of the `len` bytes from `start` is watched for `access`.
+    fn is_watched(&self, start: u32, len: u32, access: WatchKind) -> bool {
+        let end = start.saturating_add(len);
+        self.watchpoints
+            .iter()
+            .any(|(range, kind)| kind.matches(access) && range.start < end && start < range.end)
+    }
+
+    // Record the first watched access of the instruction being executed.
+    fn watch(&self, addr: ByteAddr, access: WatchKind) {
+        if self.watch_hit.get().is_none()
+            && self.is_watched(addr.waddr().baddr().0, WORD_SIZE as u32, access)
+        {
+            self.watch_hit.set(Some((addr, access)));
+        }
+    }
+
+    fn record_data_access(&self, addr: ByteAddr, store: bool) {
+        if self.page_accesses.is_some() {
+            self.data_access
+                .set(Some((addr.0 / PAGE_SIZE as u32, store)));
+        }
+    }
+
     fn check_guest_addr(addr: ByteAddr) -> Result<ByteAddr> {
         if !is_guest_memory(addr.0) {
             bail!("{addr:?} is an invalid guest address");
@@ -553,6 +1355,7 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
 
     fn load_u32_from_guest(&mut self, addr: ByteAddr) -> Result<u32> {
         Self::check_guest_addr(addr)?;
+        self.watch(addr, WatchKind::Read);
         self.load_memory(addr.waddr())
     }
 
@@ -596,6 +1399,7 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
 
     fn store_u32_into_guest(&mut self, addr: ByteAddr, data: u32) -> Result<()> {
         Self::check_guest_addr(addr)?;
+        self.watch(addr, WatchKind::Write);
         self.store_memory(addr.waddr(), data)
     }
 
@@ -616,7 +1420,19 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
 
     fn store_region(&mut self, addr: ByteAddr, slice: &[u8]) -> Result<()> {
         // tracing::trace!("store_region({addr:?}, {slice:02x?})");
+        if self.watch_hit.get().is_none()
+            && self.is_watched(addr.0, slice.len() as u32, WatchKind::Write)
+        {
+            self.watch_hit.set(Some((addr, WatchKind::Write)));
+        }
         if !self.trace.is_empty() {
+            if self.trace_prev_values {
+                let region = self.peek_region(addr, slice.len() as u32)?;
+                self.pending.events.insert(TraceEvent::MemoryPrev {
+                    addr: addr.0,
+                    region,
+                });
+            }
             self.pending.events.insert(TraceEvent::MemorySet {
                 addr: addr.0,
                 region: slice.into(),
@@ -639,35 +1455,69 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
 
 impl<'a, 'b, S: Syscall> EmuContext for Executor<'a, 'b, S> {
     fn ecall(&mut self) -> Result<bool> {
+        if self.linux.is_some() {
+            return self.ecall_linux();
+        }
         match self.load_register(REG_T0)? {
             ecall::HALT => self.ecall_halt(),
             ecall::INPUT => self.ecall_input(),
             ecall::SOFTWARE => self.ecall_software(),
             ecall::SHA => self.ecall_sha(),
             ecall::BIGINT => self.ecall_bigint(),
-            ecall => bail!("Unknown ecall {ecall:?}"),
+            ecall::TRAP => self.ecall_trap(),
+            ecall => {
+                if self.vector_trap(trap_kind::UNKNOWN_ECALL, ecall)? {
+                    return Ok(false);
+                }
+                bail!("Unknown ecall {ecall:?}")
+            }
         }
     }
 
-    fn mret(&self) -> Result<bool> {
-        unimplemented!()
+    fn mret(&mut self) -> Result<bool> {
+        let mut traps = self.current_traps().clone();
+        let frame = traps
+            .frame
+            .take()
+            .with_context(|| format!("mret outside of a trap handler at pc: {:?}", self.pc))?;
+        tracing::debug!("mret to {:?}", frame.epc);
+        self.pending.traps = Some(traps);
+        self.pending.pc = frame.epc;
+        Ok(true)
     }
 
-    fn trap(&self, cause: TrapCause) -> Result<bool> {
+    fn trap(&mut self, cause: TrapCause) -> Result<bool> {
+        if let Some(addr) = self.guard_fault.take() {
+            bail!(
+                "stack overflow at pc={:?}: access to stack guard page at {addr:?}",
+                self.pc
+            );
+        }
+        if let TrapCause::IllegalInstruction(word) = cause {
+            if self.vector_trap(trap_kind::ILLEGAL_INSTRUCTION, word)? {
+                return Ok(false);
+            }
+        }
         let msg = format!("Trap: {cause:08x?}, pc: {:?}", self.pc);
         tracing::info!("{msg}");
         bail!("{msg}");
     }
 
     fn check_data_load(&self, addr: ByteAddr) -> bool {
-        is_guest_memory(addr.0)
+        self.watch(addr, WatchKind::Read);
+        self.record_data_access(addr, false);
+        is_guest_memory(addr.0) && !self.in_stack_guard(addr)
     }
 
     fn check_data_store(&self, addr: ByteAddr) -> bool {
-        is_guest_memory(addr.0)
+        self.watch(addr, WatchKind::Write);
+        self.record_data_access(addr, true);
+        is_guest_memory(addr.0) && !self.in_stack_guard(addr)
     }
 
     fn check_insn_load(&self, addr: ByteAddr) -> bool {
+        // Every instruction starts here, so an access left by one that trapped is not counted.
+        self.data_access.set(None);
         is_guest_memory(addr.0)
     }
 
@@ -677,6 +1527,7 @@ impl<'a, 'b, S: Syscall> EmuContext for Executor<'a, 'b, S> {
 
     fn on_normal_end(&mut self, insn: &Instruction, decoded: &DecodedInstruction) {
         self.pending.insn = decoded.insn;
+        self.pending.kind = Some(insn.kind);
         self.pending.cycles += insn.cycles;
     }
 
@@ -696,6 +1547,12 @@ impl<'a, 'b, S: Syscall> EmuContext for Executor<'a, 'b, S> {
     fn store_register(&mut self, idx: usize, data: u32) -> Result<()> {
         if idx != 0 {
             // tracing::trace!("store_reg: x{idx} <= 0x{data:08x}");
+            if self.trace_prev_values && !self.trace.is_empty() {
+                self.pending.events.insert(TraceEvent::RegisterPrev {
+                    idx,
+                    value: self.pager.peek(SYSTEM_START + idx)?,
+                });
+            }
             self.pager.store(SYSTEM_START + idx, data)?;
             if !self.trace.is_empty() {
                 self.pending
@@ -715,6 +1572,12 @@ impl<'a, 'b, S: Syscall> EmuContext for Executor<'a, 'b, S> {
     fn store_memory(&mut self, addr: WordAddr, data: u32) -> Result<()> {
         // tracing::trace!("store_mem({:?}, 0x{data:08x})", addr.baddr());
         if !self.trace.is_empty() {
+            if self.trace_prev_values {
+                self.pending.events.insert(TraceEvent::MemoryPrev {
+                    addr: addr.baddr().0,
+                    region: self.pager.peek(addr)?.to_le_bytes().to_vec(),
+                });
+            }
             self.pending.events.insert(TraceEvent::MemorySet {
                 addr: addr.baddr().0,
                 region: data.to_le_bytes().to_vec(),
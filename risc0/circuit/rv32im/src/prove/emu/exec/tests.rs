@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::cell::RefCell;
+use std::{cell::RefCell, ops::ControlFlow};
 
 use anyhow::Result;
 use risc0_binfmt::{Digestible, ExitCode, MemoryImage};
@@ -23,10 +23,11 @@ use risc0_zkvm_platform::{
 };
 use test_log::test;
 
-use super::{Syscall, SyscallContext};
+use super::{Executor, Syscall, SyscallContext};
 use crate::prove::emu::{
     addr::ByteAddr,
-    exec::DEFAULT_SEGMENT_LIMIT_PO2,
+    exec::{PageAccessCounts, DEFAULT_SEGMENT_LIMIT_PO2},
+    rv32im::InsnKind,
     testutil::{self, DEFAULT_SESSION_LIMIT},
 };
 
@@ -129,3 +130,277 @@ fn system_split() {
         segments[0].post_state.digest::<ShaImpl>()
     );
 }
+
+#[test]
+fn segment_po2_schedule() {
+    let program = testutil::simple_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let syscall = BasicSyscall::default();
+
+    // Only the first segment is small enough to split the program.
+    let mut exec = Executor::new(image, &syscall, None, vec![]);
+    exec.segment_po2_schedule(vec![14]);
+    let mut segments = Vec::new();
+    let result = exec
+        .run(
+            DEFAULT_SEGMENT_LIMIT_PO2,
+            DEFAULT_SESSION_LIMIT,
+            |segment| {
+                segments.push(segment);
+                Ok(())
+            },
+        )
+        .unwrap();
+
+    assert_eq!(result.segments, 2);
+    assert_eq!(segments[0].exit_code, ExitCode::SystemSplit);
+    assert_eq!(segments[0].po2, 14);
+    assert_eq!(segments[1].exit_code, ExitCode::Halted(0));
+
+    // A po2 too small to fit the reserved cycles is rejected rather than underflowing.
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &syscall, None, vec![]);
+    exec.segment_po2_schedule(vec![14, 4]);
+    let err = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap_err();
+    assert!(err.to_string().contains("Segment po2 4"));
+}
+
+#[test]
+fn paging_stats() {
+    // The program spans several pages, which share their page table pages.
+    let program = testutil::large_text();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let syscall = BasicSyscall::default();
+
+    let mut exec = Executor::new(image, &syscall, None, vec![]);
+    let mut segments = Vec::new();
+    let result = exec
+        .run(
+            DEFAULT_SEGMENT_LIMIT_PO2,
+            DEFAULT_SESSION_LIMIT,
+            |segment| {
+                segments.push(segment);
+                Ok(())
+            },
+        )
+        .unwrap();
+
+    assert!(result.paging.cycles > 0);
+    assert!(result.paging.saved_cycles > 0);
+    assert!(result.user_cycles + result.paging.cycles <= result.total_cycles);
+
+    let paging = segments[0].paging;
+    assert!(paging.page_reads > 1);
+    assert!(paging.page_writes <= paging.page_reads);
+    assert!(paging.write_cycles <= paging.read_cycles);
+    let segment_cycles: usize = segments
+        .iter()
+        .map(|segment| segment.paging.read_cycles + segment.paging.write_cycles)
+        .sum();
+    assert_eq!(segment_cycles as u64, result.paging.cycles);
+}
+
+#[test]
+fn insn_counts() {
+    let program = testutil::simple_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let syscall = BasicSyscall::default();
+
+    // Split the loop across segments, so that replayed instructions must not be counted twice.
+    let mut exec = Executor::new(image, &syscall, None, vec![]);
+    exec.collect_insn_counts(true);
+    let result = exec.run(14, DEFAULT_SESSION_LIMIT, |_| Ok(())).unwrap();
+
+    let counts = result.insn_counts.unwrap();
+    assert_eq!(counts[&InsnKind::BLT], 100);
+    assert_eq!(counts[&InsnKind::EANY], 1);
+    let total: u64 = counts.values().sum();
+    assert!(total <= result.user_cycles);
+}
+
+#[test]
+fn page_accesses() {
+    let program = testutil::load_store();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let syscall = BasicSyscall::default();
+
+    let mut exec = Executor::new(image, &syscall, None, vec![]);
+    exec.collect_page_accesses(true);
+    let result = exec.run(14, DEFAULT_SESSION_LIMIT, |_| Ok(())).unwrap();
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+
+    // Only the loads and stores of the loop count, not instruction fetches or paging.
+    let counts = result.page_accesses.unwrap();
+    let page_idx = 0x1_0000 / PAGE_SIZE as u32;
+    assert_eq!(
+        counts.into_iter().collect::<Vec<_>>(),
+        vec![(
+            page_idx,
+            PageAccessCounts {
+                loads: 10,
+                stores: 10
+            }
+        )]
+    );
+    assert!(!result.page_faults.is_empty());
+}
+
+#[test]
+fn monitor() {
+    let program = testutil::simple_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let syscall = BasicSyscall::default();
+
+    let samples = RefCell::new(Vec::new());
+    let mut exec = Executor::new(image, &syscall, None, vec![]);
+    exec.monitor(50, |_, sample| {
+        samples.borrow_mut().push(sample.clone());
+        Ok(())
+    });
+    let result = exec.run(14, DEFAULT_SESSION_LIMIT, |_| Ok(())).unwrap();
+
+    let samples = samples.take();
+    assert!(!samples.is_empty());
+    let cycles: u64 = samples.iter().map(|sample| sample.cycles).sum();
+    assert!(cycles <= result.user_cycles);
+    for sample in &samples {
+        assert!(sample.cycles >= 50);
+        assert!(sample.min_pc <= sample.max_pc);
+        assert_eq!(sample.syscalls, 0);
+        assert!(sample.segments < result.segments);
+        assert!(sample.total_cycles < result.total_cycles);
+    }
+
+    // An error from the handler fails the run.
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &syscall, None, vec![]);
+    exec.monitor(50, |_, _| anyhow::bail!("stuck"));
+    let err = exec
+        .run(14, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "stuck");
+}
+
+#[test]
+fn meter() {
+    let program = testutil::simple_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let syscall = BasicSyscall::default();
+
+    // Split the loop across segments, so that replayed instructions must not be metered twice.
+    let ops = RefCell::new(Vec::new());
+    let mut exec = Executor::new(image, &syscall, None, vec![]);
+    exec.meter(|pc, op, cycles| {
+        ops.borrow_mut().push((pc, format!("{op:?}"), cycles));
+        ControlFlow::Continue(())
+    });
+    let result = exec.run(14, DEFAULT_SESSION_LIMIT, |_| Ok(())).unwrap();
+
+    let ops = ops.take();
+    let blts = ops.iter().filter(|(_, op, _)| op == "Insn(BLT)").count();
+    assert_eq!(blts, 100);
+    assert_eq!(ops.last().unwrap().1, "Ecall(\"halt\")");
+    let cycles: u64 = ops.iter().map(|(_, _, cycles)| cycles).sum();
+    assert_eq!(cycles, result.user_cycles);
+
+    // Breaking stops the run at the instruction.
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &syscall, None, vec![]);
+    let mut budget = 50;
+    exec.meter(|_, _, cycles| {
+        budget -= cycles as i64;
+        if budget < 0 {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    });
+    let err = exec
+        .run(14, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("cycle meter"), "{err}");
+}
+
+#[test]
+fn linux_compat() {
+    let program = testutil::linux();
+    let program_end = 0x4000 + 4 * program.image.len() as u32;
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let syscall = BasicSyscall::default();
+
+    let mut exec = Executor::new(image, &syscall, None, vec![]);
+    exec.linux_compat(program_end);
+    let result = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap();
+
+    assert_eq!(result.exit_code, ExitCode::Halted(program_end & 0xff));
+    let state = syscall.state();
+    assert!(state.syscall.ends_with("SYS_WRITE"));
+    assert_eq!(state.from_guest, 0x04000893u32.to_le_bytes());
+}
+
+#[test]
+fn trap_handler() {
+    let mut program = testutil::trap_handler();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let syscall = BasicSyscall::default();
+
+    let session = super::execute(
+        image,
+        DEFAULT_SEGMENT_LIMIT_PO2,
+        DEFAULT_SESSION_LIMIT,
+        &syscall,
+        None,
+    )
+    .unwrap();
+    assert_eq!(session.result.exit_code, ExitCode::Halted(101));
+
+    // Without a handler, the illegal instruction terminates execution.
+    program.image.insert(0x4010, 0x00000013); // nop
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let err = super::execute(
+        image,
+        DEFAULT_SEGMENT_LIMIT_PO2,
+        DEFAULT_SESSION_LIMIT,
+        &syscall,
+        None,
+    )
+    .err()
+    .unwrap();
+    assert!(err.to_string().contains("IllegalInstruction"), "{err}");
+}
+
+#[test]
+fn trap_handler_resumed() {
+    let program = testutil::trap_handler();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let syscall = BasicSyscall::default();
+
+    // Stop after the handlers are registered, before the illegal instruction.
+    let mut exec = Executor::new(image, &syscall, None, vec![]);
+    exec.breakpoints([0x401c].into(), |_| Ok(true));
+    let stopped = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap();
+    assert_eq!(stopped.exit_code, ExitCode::SystemSplit);
+    assert!(!stopped.traps.is_empty());
+
+    // The handlers are not part of the image, so they have to be carried over.
+    let mut exec = Executor::new(stopped.post_image.clone(), &syscall, None, vec![]);
+    exec.traps(stopped.traps);
+    let result = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap();
+    assert_eq!(result.exit_code, ExitCode::Halted(101));
+
+    let err = Executor::new(stopped.post_image, &syscall, None, vec![])
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("IllegalInstruction"), "{err}");
+}
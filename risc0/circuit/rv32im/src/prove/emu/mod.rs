@@ -13,11 +13,15 @@
 // limitations under the License.
 
 pub mod addr;
+pub mod cycle_audit;
 pub mod exec;
 pub mod mux;
 mod pager;
 pub mod preflight;
+mod ram;
 pub mod rv32im;
+#[cfg(feature = "rv64im")]
+pub mod rv64im;
 pub mod testutil;
 
 use risc0_zkvm_platform::{memory::SYSTEM, syscall::DIGEST_WORDS};
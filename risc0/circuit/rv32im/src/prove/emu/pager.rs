@@ -22,7 +22,11 @@ use risc0_binfmt::{MemoryImage, SystemState};
 use risc0_zkp::core::hash::sha::BLOCK_BYTES;
 use risc0_zkvm_platform::{PAGE_SIZE, WORD_SIZE};
 
-use super::addr::{ByteAddr, WordAddr};
+use super::{
+    addr::{ByteAddr, WordAddr},
+    ram::RamBuffer,
+};
+use crate::prove::segment::SegmentPaging;
 
 pub const PAGE_WORDS: usize = PAGE_SIZE / WORD_SIZE;
 
@@ -40,8 +44,6 @@ const fn cycles_per_page(blocks_per_page: usize) -> usize {
     1 + SHA_INIT + (SHA_LOAD + SHA_MAIN) * blocks_per_page
 }
 
-struct Page(Vec<u8>);
-
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
 enum PageState {
     Loaded,
@@ -64,7 +66,7 @@ enum Action {
 pub struct PagedMemory {
     pub image: MemoryImage,
     page_table: Vec<u32>,
-    page_cache: Vec<Page>,
+    page_cache: RamBuffer,
     page_states: BTreeMap<u32, PageState>,
     pub cycles: usize,
     pending_actions: Vec<Action>,
@@ -75,13 +77,20 @@ impl PagedMemory {
         Self {
             image,
             page_table: vec![INVALID_IDX; NUM_PAGES],
-            page_cache: Vec::new(),
+            page_cache: RamBuffer::new(),
             page_states: BTreeMap::new(),
             cycles: 0,
             pending_actions: Vec::new(),
         }
     }
 
+    /// Hold the pages in a buffer backed by hugepages, which reduces TLB misses when the guest
+    /// touches many pages. This must be called before any page is loaded.
+    pub fn use_hugepages(&mut self) {
+        assert!(self.page_table.iter().all(|&idx| idx == INVALID_IDX));
+        self.page_cache = RamBuffer::with_hugepages(NUM_PAGES);
+    }
+
     pub fn pre_peek(&self, addr: WordAddr) -> Result<u32> {
         let mut bytes = [0u8; WORD_SIZE];
         let addr: ByteAddr = addr.into();
@@ -95,7 +104,7 @@ impl PagedMemory {
         if idx == INVALID_IDX {
             self.pre_peek(addr)
         } else {
-            Ok(self.page_cache[idx as usize].load(addr))
+            Ok(load_word(self.page_cache.page(idx as usize), addr))
         }
     }
 
@@ -107,7 +116,7 @@ impl PagedMemory {
             self.load_page(page_idx);
             idx = self.page_table[page_idx as usize];
         }
-        self.page_cache[idx as usize].load(addr)
+        load_word(self.page_cache.page(idx as usize), addr)
     }
 
     pub fn store(&mut self, addr: WordAddr, data: u32) -> Result<()> {
@@ -126,10 +135,10 @@ impl PagedMemory {
         }
 
         let idx = self.page_table[page_idx as usize] as usize;
-        let page = self.page_cache.get_mut(idx).unwrap();
-        let old = page.load(addr);
+        let page = self.page_cache.page_mut(idx);
+        let old = load_word(page, addr);
         self.pending_actions.push(Action::Store(addr, old));
-        page.store(addr, data);
+        store_word(page, addr, data);
 
         Ok(())
     }
@@ -155,8 +164,8 @@ impl PagedMemory {
             // segments.
             if *page_state == PageState::Dirty {
                 let idx = self.page_table[*page_idx as usize] as usize;
-                let page = &self.page_cache[idx];
-                self.image.pages.insert(*page_idx, page.0.clone());
+                let page = self.page_cache.page(idx);
+                self.image.pages.insert(*page_idx, page.to_vec());
             }
         }
 
@@ -192,7 +201,7 @@ impl PagedMemory {
                 }
                 Action::Store(addr, data) => {
                     let idx = self.page_table[addr.page_idx() as usize] as usize;
-                    self.page_cache[idx].store(*addr, *data);
+                    store_word(self.page_cache.page_mut(idx), *addr, *data);
                 }
             }
         }
@@ -221,20 +230,34 @@ impl PagedMemory {
         faults
     }
 
+    /// The pages paged in and out by this segment so far, and the cycles spent on each.
+    pub fn paging(&self) -> SegmentPaging {
+        let mut paging = SegmentPaging::default();
+        for (&page_idx, &page_state) in &self.page_states {
+            let page_cycles = self.page_cycles(page_idx);
+            paging.page_reads += 1;
+            paging.read_cycles += page_cycles;
+            if page_state == PageState::Dirty {
+                paging.page_writes += 1;
+                paging.write_cycles += page_cycles;
+            }
+        }
+        paging
+    }
+
     pub fn peek_page(&self, page_idx: u32) -> Vec<u8> {
         let idx = self.page_table[page_idx as usize];
         if idx == INVALID_IDX {
             self.image.load_page(page_idx)
         } else {
-            self.page_cache[idx as usize].0.clone()
+            self.page_cache.page(idx as usize).to_vec()
         }
     }
 
     fn load_page(&mut self, page_idx: u32) {
         tracing::trace!("load_page: 0x{page_idx:05x}");
         let page = self.image.load_page(page_idx);
-        self.page_table[page_idx as usize] = self.page_cache.len() as u32;
-        self.page_cache.push(Page(page));
+        self.page_table[page_idx as usize] = self.page_cache.push(&page) as u32;
         self.update(page_idx, PageState::Loaded);
         self.page_changed(page_idx, PageState::Loaded);
     }
@@ -247,13 +270,15 @@ impl PagedMemory {
             let parent_idx = info.get_page_index(entry_addr);
 
             if let Some(state) = self.page_states.get(&parent_idx) {
-                if goal > *state {
-                    self.page_changed(parent_idx, goal);
+                if goal <= *state {
+                    // Every ancestor of a page is in at least the state of the page itself, so
+                    // the rest of the path has already been paged in by an earlier access.
+                    break;
                 }
+                self.page_changed(parent_idx, goal);
             } else {
                 let page = self.image.load_page(parent_idx);
-                self.page_table[parent_idx as usize] = self.page_cache.len() as u32;
-                self.page_cache.push(Page(page));
+                self.page_table[parent_idx as usize] = self.page_cache.push(&page) as u32;
                 self.page_changed(parent_idx, goal);
             }
 
@@ -261,15 +286,42 @@ impl PagedMemory {
         }
     }
 
-    fn page_changed(&mut self, page_idx: u32, state: PageState) {
+    /// The number of cycles needed to page the data pages accessed in this segment in and out,
+    /// if each of them paged in its own path to the root rather than sharing page table pages
+    /// with the other accesses.
+    pub fn unshared_cycles(&self) -> usize {
         let info = &self.image.info;
+        let first_table_page = info.get_page_index(info.page_table_addr);
+        let mut cycles = 0;
+        for (&page_idx, &state) in self.page_states.range(..first_table_page) {
+            let passes = match state {
+                PageState::Loaded => 1,
+                PageState::Dirty => 2,
+            };
+            let mut idx = page_idx;
+            loop {
+                cycles += passes * self.page_cycles(idx);
+                if idx == info.root_idx {
+                    break;
+                }
+                idx = info.get_page_index(info.get_page_entry_addr(idx));
+            }
+        }
+        cycles
+    }
 
-        let page_cycles = if page_idx == info.root_idx {
+    fn page_cycles(&self, page_idx: u32) -> usize {
+        let info = &self.image.info;
+        if page_idx == info.root_idx {
             let num_root_entries = info.num_root_entries as usize;
             cycles_per_page(num_root_entries / 2)
         } else {
             cycles_per_page(BLOCKS_PER_PAGE)
-        };
+        }
+    }
+
+    fn page_changed(&mut self, page_idx: u32, state: PageState) {
+        let page_cycles = self.page_cycles(page_idx);
 
         tracing::trace!("page_changed(0x{page_idx:05x}, {state:?}) <= {page_cycles}");
         self.cycles += page_cycles;
@@ -283,21 +335,19 @@ impl PagedMemory {
     }
 }
 
-impl Page {
-    fn load(&self, addr: WordAddr) -> u32 {
-        let word_addr = (addr.0 % PAGE_WORDS as u32) as usize;
-        let byte_addr = word_addr * WORD_SIZE;
-        let mut bytes = [0u8; WORD_SIZE];
-        bytes.clone_from_slice(&self.0[byte_addr..byte_addr + WORD_SIZE]);
-        //let data = u32::from_le_bytes(bytes);
-        // tracing::trace!("load({addr:?}) -> 0x{data:08x}");
-        u32::from_le_bytes(bytes)
-    }
+fn load_word(page: &[u8], addr: WordAddr) -> u32 {
+    let word_addr = (addr.0 % PAGE_WORDS as u32) as usize;
+    let byte_addr = word_addr * WORD_SIZE;
+    let mut bytes = [0u8; WORD_SIZE];
+    bytes.clone_from_slice(&page[byte_addr..byte_addr + WORD_SIZE]);
+    //let data = u32::from_le_bytes(bytes);
+    // tracing::trace!("load({addr:?}) -> 0x{data:08x}");
+    u32::from_le_bytes(bytes)
+}
 
-    fn store(&mut self, addr: WordAddr, data: u32) {
-        let word_addr = (addr.0 % PAGE_WORDS as u32) as usize;
-        let byte_addr = word_addr * WORD_SIZE;
-        // tracing::trace!("store({addr:?}, 0x{data:08x})");
-        self.0[byte_addr..byte_addr + WORD_SIZE].clone_from_slice(&data.to_le_bytes());
-    }
+fn store_word(page: &mut [u8], addr: WordAddr, data: u32) {
+    let word_addr = (addr.0 % PAGE_WORDS as u32) as usize;
+    let byte_addr = word_addr * WORD_SIZE;
+    // tracing::trace!("store({addr:?}, 0x{data:08x})");
+    page[byte_addr..byte_addr + WORD_SIZE].clone_from_slice(&data.to_le_bytes());
 }
@@ -38,6 +38,7 @@ use risc0_zkvm_platform::{
 use sha2::digest::generic_array::GenericArray;
 
 use super::{
+    cycle_audit::InsnCycles,
     mux::{Major, TopMux},
     pager::{PagedMemory, PAGE_WORDS},
     rv32im::{DecodedInstruction, EmuContext, Emulator, InsnKind, Instruction, TrapCause},
@@ -703,15 +704,16 @@ impl EmuContext for Preflight {
             ecall::SOFTWARE => self.ecall_software(),
             ecall::SHA => self.ecall_sha(),
             ecall::BIGINT => self.ecall_bigint(),
+            ecall::TRAP => bail!("Trap handlers are not supported by the circuit"),
             ecall => bail!("Unknown ecall {ecall:?}"),
         }
     }
 
-    fn mret(&self) -> Result<bool> {
-        unimplemented!()
+    fn mret(&mut self) -> Result<bool> {
+        bail!("mret is not supported by the circuit");
     }
 
-    fn trap(&self, cause: TrapCause) -> Result<bool> {
+    fn trap(&mut self, cause: TrapCause) -> Result<bool> {
         bail!("Trap: {cause:08x?}");
     }
 
@@ -787,14 +789,35 @@ impl EmuContext for Preflight {
 impl Segment {
     #[tracing::instrument(skip_all)]
     pub fn preflight(&self) -> Result<PreflightTrace> {
+        self.run_preflight(None)
+    }
+
+    /// Run the preflight of this segment, and return the cycles it gives each instruction.
+    ///
+    /// Compare with the cycles counted by the executor using
+    /// [CycleMismatch::find][super::cycle_audit::CycleMismatch::find].
+    pub fn preflight_insn_cycles(&self) -> Result<Vec<InsnCycles>> {
+        let mut insns = Vec::new();
+        self.run_preflight(Some(&mut insns))?;
+        Ok(insns)
+    }
+
+    fn run_preflight(&self, mut insns: Option<&mut Vec<InsnCycles>>) -> Result<PreflightTrace> {
         tracing::debug!("preflight: {self:#?}");
         let mut preflight = Preflight::new(self);
         let mut emu = Emulator::new();
 
         preflight.pre_steps();
         while preflight.trace.body.cycles.len() < self.insn_cycles && preflight.halted.is_none() {
+            let (pc, start) = (preflight.pc, preflight.trace.body.cycles.len());
             emu.step(&mut preflight)?;
             preflight.pager.commit_step();
+            if let Some(insns) = insns.as_mut() {
+                insns.push(InsnCycles {
+                    pc: pc.0,
+                    cycles: preflight.trace.body.cycles.len() - start,
+                });
+            }
         }
         preflight.post_steps()?;
 
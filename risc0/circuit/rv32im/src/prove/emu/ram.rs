@@ -0,0 +1,241 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The buffer holding the pages of guest memory paged in by the executor.
+
+use risc0_zkvm_platform::PAGE_SIZE;
+
+/// A contiguous buffer of pages, indexed in the order they were pushed.
+pub(crate) struct RamBuffer {
+    backing: Backing,
+    len: usize,
+}
+
+enum Backing {
+    Heap(Vec<u8>),
+    #[cfg(target_os = "linux")]
+    Mapped(mapping::Mapping),
+}
+
+impl RamBuffer {
+    /// A buffer allocated on the heap, growing as pages are pushed.
+    pub(crate) fn new() -> Self {
+        Self {
+            backing: Backing::Heap(Vec::new()),
+            len: 0,
+        }
+    }
+
+    /// A buffer with room for `capacity` pages, mapped with a hint to the OS to back it with
+    /// transparent hugepages.
+    ///
+    /// The mapping is reserved up front but only committed as pages are pushed. Falls back to
+    /// the heap if the OS does not support hugepages.
+    pub(crate) fn with_hugepages(capacity: usize) -> Self {
+        #[cfg(target_os = "linux")]
+        match mapping::Mapping::new(capacity * PAGE_SIZE) {
+            Ok(mapping) => {
+                return Self {
+                    backing: Backing::Mapped(mapping),
+                    len: 0,
+                }
+            }
+            Err(err) => tracing::warn!("hugepages unavailable, using the heap: {err}"),
+        }
+        #[cfg(not(target_os = "linux"))]
+        tracing::warn!("hugepages are only supported on Linux, using the heap");
+        let _ = capacity;
+        Self::new()
+    }
+
+    /// Whether the buffer is backed by hugepages.
+    pub(crate) fn is_hugepages(&self) -> bool {
+        !matches!(self.backing, Backing::Heap(_))
+    }
+
+    /// Append a page, and return its index.
+    pub(crate) fn push(&mut self, page: &[u8]) -> usize {
+        debug_assert_eq!(page.len(), PAGE_SIZE);
+        let idx = self.len;
+        match &mut self.backing {
+            Backing::Heap(buf) => buf.extend_from_slice(page),
+            #[cfg(target_os = "linux")]
+            Backing::Mapped(mapping) => {
+                let offset = idx * PAGE_SIZE;
+                if offset + PAGE_SIZE > mapping.len() {
+                    tracing::warn!("hugepage buffer is full, moving it to the heap");
+                    let mut buf = mapping.as_slice()[..offset].to_vec();
+                    buf.extend_from_slice(page);
+                    self.backing = Backing::Heap(buf);
+                } else {
+                    mapping.as_mut_slice()[offset..offset + PAGE_SIZE].copy_from_slice(page);
+                }
+            }
+        }
+        self.len += 1;
+        idx
+    }
+
+    pub(crate) fn page(&self, idx: usize) -> &[u8] {
+        assert!(idx < self.len);
+        let offset = idx * PAGE_SIZE;
+        &self.as_slice()[offset..offset + PAGE_SIZE]
+    }
+
+    pub(crate) fn page_mut(&mut self, idx: usize) -> &mut [u8] {
+        assert!(idx < self.len);
+        let offset = idx * PAGE_SIZE;
+        &mut self.as_mut_slice()[offset..offset + PAGE_SIZE]
+    }
+
+    /// Remove all pages, keeping the memory backing them for reuse.
+    pub(crate) fn clear(&mut self) {
+        if let Backing::Heap(buf) = &mut self.backing {
+            buf.clear();
+        }
+        self.len = 0;
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        match &self.backing {
+            Backing::Heap(buf) => buf,
+            #[cfg(target_os = "linux")]
+            Backing::Mapped(mapping) => mapping.as_slice(),
+        }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        match &mut self.backing {
+            Backing::Heap(buf) => buf,
+            #[cfg(target_os = "linux")]
+            Backing::Mapped(mapping) => mapping.as_mut_slice(),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod mapping {
+    use std::{io, ptr::null_mut};
+
+    /// The size of a transparent hugepage on the architectures we run on.
+    const HUGEPAGE_SIZE: usize = 2 * 1024 * 1024;
+
+    /// An anonymous mapping, aligned to a hugepage and advised to be backed by hugepages.
+    pub(super) struct Mapping {
+        base: *mut libc::c_void,
+        map_len: usize,
+        offset: usize,
+        len: usize,
+    }
+
+    // SAFETY: the mapping is owned, and only accessed through `&self` and `&mut self`.
+    unsafe impl Send for Mapping {}
+    unsafe impl Sync for Mapping {}
+
+    impl Mapping {
+        pub(super) fn new(len: usize) -> io::Result<Self> {
+            // Map an extra hugepage so that the buffer can start on a hugepage boundary.
+            let map_len = len + HUGEPAGE_SIZE;
+            // SAFETY: a fresh anonymous mapping does not alias any memory.
+            let base = unsafe {
+                libc::mmap(
+                    null_mut(),
+                    map_len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE,
+                    -1,
+                    0,
+                )
+            };
+            if base == libc::MAP_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+            let offset = (base as usize).next_multiple_of(HUGEPAGE_SIZE) - base as usize;
+            let mapping = Self {
+                base,
+                map_len,
+                offset,
+                len,
+            };
+            // SAFETY: the range lies within the mapping.
+            if unsafe { libc::madvise(mapping.ptr().cast(), len, libc::MADV_HUGEPAGE) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(mapping)
+        }
+
+        pub(super) fn len(&self) -> usize {
+            self.len
+        }
+
+        pub(super) fn as_slice(&self) -> &[u8] {
+            // SAFETY: the range lies within the mapping, which is readable and zero-initialized.
+            unsafe { std::slice::from_raw_parts(self.ptr(), self.len) }
+        }
+
+        pub(super) fn as_mut_slice(&mut self) -> &mut [u8] {
+            // SAFETY: as above, and `&mut self` guarantees exclusive access.
+            unsafe { std::slice::from_raw_parts_mut(self.ptr(), self.len) }
+        }
+
+        fn ptr(&self) -> *mut u8 {
+            // SAFETY: the offset is less than a hugepage, which was mapped beyond `len`.
+            unsafe { self.base.cast::<u8>().add(self.offset) }
+        }
+    }
+
+    impl Drop for Mapping {
+        fn drop(&mut self) {
+            // SAFETY: `base` and `map_len` are those of a live mapping made in `new`.
+            unsafe { libc::munmap(self.base, self.map_len) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkvm_platform::PAGE_SIZE;
+
+    use super::RamBuffer;
+
+    fn check(mut buf: RamBuffer) {
+        for i in 0..4u8 {
+            assert_eq!(buf.push(&[i; PAGE_SIZE]), i as usize);
+        }
+        buf.page_mut(1)[0] = 0xff;
+        assert_eq!(buf.page(1)[..2], [0xff, 1]);
+        assert_eq!(buf.page(3), [3; PAGE_SIZE]);
+
+        buf.clear();
+        assert_eq!(buf.push(&[7; PAGE_SIZE]), 0);
+        assert_eq!(buf.page(0), [7; PAGE_SIZE]);
+    }
+
+    #[test]
+    fn heap() {
+        check(RamBuffer::new());
+    }
+
+    #[test]
+    fn hugepages() {
+        check(RamBuffer::with_hugepages(16));
+        // Pushing past the capacity moves the buffer to the heap.
+        let mut buf = RamBuffer::with_hugepages(1);
+        buf.push(&[1; PAGE_SIZE]);
+        buf.push(&[2; PAGE_SIZE]);
+        assert!(!buf.is_hugepages());
+        assert_eq!(buf.page(0), [1; PAGE_SIZE]);
+        assert_eq!(buf.page(1), [2; PAGE_SIZE]);
+    }
+}
@@ -22,10 +22,10 @@ pub trait EmuContext {
     fn ecall(&mut self) -> Result<bool>;
 
     // Handle a machine return
-    fn mret(&self) -> Result<bool>;
+    fn mret(&mut self) -> Result<bool>;
 
     // Handle a trap
-    fn trap(&self, cause: TrapCause) -> Result<bool>;
+    fn trap(&mut self, cause: TrapCause) -> Result<bool>;
 
     // Callback when instructions are decoded
     fn on_insn_decoded(&self, kind: &Instruction, decoded: &DecodedInstruction);
@@ -89,12 +89,12 @@ pub enum TrapCause {
 pub struct DecodedInstruction {
     pub insn: u32,
     top_bit: u32,
-    func7: u32,
-    rs2: u32,
-    rs1: u32,
-    func3: u32,
-    rd: u32,
-    opcode: u32,
+    pub(super) func7: u32,
+    pub(super) rs2: u32,
+    pub(super) rs1: u32,
+    pub(super) func3: u32,
+    pub(super) rd: u32,
+    pub(super) opcode: u32,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -106,7 +106,7 @@ enum InsnCategory {
     Invalid,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum InsnKind {
     INVALID,
     ADD,
@@ -158,6 +158,62 @@ pub enum InsnKind {
     MRET,
 }
 
+impl InsnKind {
+    /// The assembler mnemonic of the instruction, e.g. `addi`, or `ecall` for [InsnKind::EANY].
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            InsnKind::INVALID => "invalid",
+            InsnKind::ADD => "add",
+            InsnKind::SUB => "sub",
+            InsnKind::XOR => "xor",
+            InsnKind::OR => "or",
+            InsnKind::AND => "and",
+            InsnKind::SLL => "sll",
+            InsnKind::SRL => "srl",
+            InsnKind::SRA => "sra",
+            InsnKind::SLT => "slt",
+            InsnKind::SLTU => "sltu",
+            InsnKind::ADDI => "addi",
+            InsnKind::XORI => "xori",
+            InsnKind::ORI => "ori",
+            InsnKind::ANDI => "andi",
+            InsnKind::SLLI => "slli",
+            InsnKind::SRLI => "srli",
+            InsnKind::SRAI => "srai",
+            InsnKind::SLTI => "slti",
+            InsnKind::SLTIU => "sltiu",
+            InsnKind::BEQ => "beq",
+            InsnKind::BNE => "bne",
+            InsnKind::BLT => "blt",
+            InsnKind::BGE => "bge",
+            InsnKind::BLTU => "bltu",
+            InsnKind::BGEU => "bgeu",
+            InsnKind::JAL => "jal",
+            InsnKind::JALR => "jalr",
+            InsnKind::LUI => "lui",
+            InsnKind::AUIPC => "auipc",
+            InsnKind::MUL => "mul",
+            InsnKind::MULH => "mulh",
+            InsnKind::MULHSU => "mulhsu",
+            InsnKind::MULHU => "mulhu",
+            InsnKind::DIV => "div",
+            InsnKind::DIVU => "divu",
+            InsnKind::REM => "rem",
+            InsnKind::REMU => "remu",
+            InsnKind::LB => "lb",
+            InsnKind::LH => "lh",
+            InsnKind::LW => "lw",
+            InsnKind::LBU => "lbu",
+            InsnKind::LHU => "lhu",
+            InsnKind::SB => "sb",
+            InsnKind::SH => "sh",
+            InsnKind::SW => "sw",
+            InsnKind::EANY => "ecall",
+            InsnKind::MRET => "mret",
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Instruction {
     pub kind: InsnKind,
@@ -169,7 +225,7 @@ pub struct Instruction {
 }
 
 impl DecodedInstruction {
-    fn new(insn: u32) -> Self {
+    pub(super) fn new(insn: u32) -> Self {
         Self {
             insn,
             top_bit: (insn & 0x80000000) >> 31,
@@ -182,22 +238,22 @@ impl DecodedInstruction {
         }
     }
 
-    fn imm_b(&self) -> u32 {
+    pub(super) fn imm_b(&self) -> u32 {
         (self.top_bit * 0xfffff000)
             | ((self.rd & 1) << 11)
             | ((self.func7 & 0x3f) << 5)
             | (self.rd & 0x1e)
     }
 
-    fn imm_i(&self) -> u32 {
+    pub(super) fn imm_i(&self) -> u32 {
         (self.top_bit * 0xfffff000) | (self.func7 << 5) | self.rs2
     }
 
-    fn imm_s(&self) -> u32 {
+    pub(super) fn imm_s(&self) -> u32 {
         (self.top_bit * 0xfffff000) | (self.func7 << 5) | self.rd
     }
 
-    fn imm_j(&self) -> u32 {
+    pub(super) fn imm_j(&self) -> u32 {
         (self.top_bit * 0xfff00000)
             | (self.rs1 << 15)
             | (self.func3 << 12)
@@ -206,7 +262,7 @@ impl DecodedInstruction {
             | (self.rs2 & 0x1e)
     }
 
-    fn imm_u(&self) -> u32 {
+    pub(super) fn imm_u(&self) -> u32 {
         self.insn & 0xfffff000
     }
 }
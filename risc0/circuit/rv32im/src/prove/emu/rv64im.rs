@@ -0,0 +1,1049 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An emulator for the RV64IM instruction set.
+//!
+//! This is an execution-only mode for guests whose memory does not fit in a 32-bit address
+//! space. Registers are 64 bits wide and memory is addressed by 64-bit byte addresses, accessed
+//! as doublewords. The rv32im circuit cannot prove the execution of RV64IM programs; support in
+//! the circuit will be gated separately.
+//!
+//! [Rv64Executor] runs an RV64IM ELF, answering its syscalls with the same [Syscall] handlers as
+//! the RV32IM executor.
+
+use std::{array, collections::BTreeMap};
+
+use anyhow::{anyhow, bail, ensure, Context as _, Result};
+use elf::{endian::LittleEndian, file::Class, ElfBytes};
+use risc0_binfmt::ExitCode;
+use risc0_zkp::core::digest::{Digest, DIGEST_BYTES};
+use risc0_zkvm_platform::syscall::{
+    ecall, halt,
+    reg_abi::{REG_A0, REG_A1, REG_A2, REG_MAX, REG_SP, REG_T0},
+};
+
+use super::{
+    addr::ByteAddr,
+    exec::{Syscall, SyscallContext},
+    rv32im::DecodedInstruction,
+};
+
+/// The number of bytes in a doubleword, the unit of memory of the RV64IM emulator.
+pub const DWORD_SIZE: usize = core::mem::size_of::<u64>();
+
+/// The number of bytes in a word of the syscall ABI.
+const WORD_BYTES: usize = core::mem::size_of::<u32>();
+
+/// The number of bytes in an instruction.
+const INSN_SIZE: u64 = 4;
+
+/// The initial stack pointer of an RV64IM guest, above the 32-bit address space.
+pub const STACK_TOP: u64 = 0x40_0000_0000;
+
+/// The most words a syscall may return into the guest.
+const MAX_INTO_GUEST_WORDS: usize = 1 << 24;
+
+/// The longest name of a syscall, in bytes.
+const MAX_SYSCALL_NAME_LEN: usize = 1024;
+
+pub trait Rv64Context {
+    // Handle environment call
+    fn ecall(&mut self) -> Result<bool>;
+
+    // Handle a trap
+    fn trap(&self, cause: TrapCause) -> Result<bool>;
+
+    // Callback when instructions end normally
+    fn on_normal_end(&mut self, _kind: InsnKind) {}
+
+    // Get the program counter
+    fn get_pc(&self) -> u64;
+
+    // Set the program counter
+    fn set_pc(&mut self, addr: u64);
+
+    // Load from a register
+    fn load_register(&mut self, idx: usize) -> Result<u64>;
+
+    // Store to a register
+    fn store_register(&mut self, idx: usize, data: u64) -> Result<()>;
+
+    // Load the doubleword at a doubleword-aligned address
+    fn load_memory(&mut self, addr: u64) -> Result<u64>;
+
+    // Store the doubleword at a doubleword-aligned address
+    fn store_memory(&mut self, addr: u64, data: u64) -> Result<()>;
+
+    // Check access for instruction load
+    fn check_insn_load(&self, _addr: u64) -> bool {
+        true
+    }
+
+    // Check access for data load
+    fn check_data_load(&self, _addr: u64) -> bool {
+        true
+    }
+
+    // Check access for data store
+    fn check_data_store(&self, _addr: u64) -> bool {
+        true
+    }
+}
+
+#[derive(Debug)]
+pub enum TrapCause {
+    InstructionAddressMisaligned,
+    InstructionAccessFault,
+    IllegalInstruction(u32),
+    Breakpoint,
+    LoadAddressMisaligned,
+    LoadAccessFault,
+    StoreAddressMisaligned(u64),
+    StoreAccessFault,
+    EnvironmentCallFromUserMode,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum InsnKind {
+    INVALID,
+    ADD,
+    SUB,
+    XOR,
+    OR,
+    AND,
+    SLL,
+    SRL,
+    SRA,
+    SLT,
+    SLTU,
+    ADDI,
+    XORI,
+    ORI,
+    ANDI,
+    SLLI,
+    SRLI,
+    SRAI,
+    SLTI,
+    SLTIU,
+    ADDW,
+    SUBW,
+    SLLW,
+    SRLW,
+    SRAW,
+    ADDIW,
+    SLLIW,
+    SRLIW,
+    SRAIW,
+    BEQ,
+    BNE,
+    BLT,
+    BGE,
+    BLTU,
+    BGEU,
+    JAL,
+    JALR,
+    LUI,
+    AUIPC,
+    MUL,
+    MULH,
+    MULHSU,
+    MULHU,
+    DIV,
+    DIVU,
+    REM,
+    REMU,
+    MULW,
+    DIVW,
+    DIVUW,
+    REMW,
+    REMUW,
+    LB,
+    LH,
+    LW,
+    LD,
+    LBU,
+    LHU,
+    LWU,
+    SB,
+    SH,
+    SW,
+    SD,
+    FENCE,
+    EANY,
+    MRET,
+}
+
+impl InsnKind {
+    /// Decode the kind of an instruction.
+    ///
+    /// Unlike RV32IM, the immediate shifts of RV64IM take a 6-bit shift amount, which leaves only
+    /// 6 bits of `func7` to select the instruction, so instructions are decoded with a match
+    /// rather than the fast decode table of the RV32IM emulator.
+    pub fn decode(insn: u32) -> Self {
+        let decoded = DecodedInstruction::new(insn);
+        let (func3, func7) = (decoded.func3, decoded.func7);
+        let func6 = func7 >> 1;
+        match (decoded.opcode, func3, func7) {
+            (0x33, 0x0, 0x00) => Self::ADD,
+            (0x33, 0x0, 0x20) => Self::SUB,
+            (0x33, 0x4, 0x00) => Self::XOR,
+            (0x33, 0x6, 0x00) => Self::OR,
+            (0x33, 0x7, 0x00) => Self::AND,
+            (0x33, 0x1, 0x00) => Self::SLL,
+            (0x33, 0x5, 0x00) => Self::SRL,
+            (0x33, 0x5, 0x20) => Self::SRA,
+            (0x33, 0x2, 0x00) => Self::SLT,
+            (0x33, 0x3, 0x00) => Self::SLTU,
+            (0x33, 0x0, 0x01) => Self::MUL,
+            (0x33, 0x1, 0x01) => Self::MULH,
+            (0x33, 0x2, 0x01) => Self::MULHSU,
+            (0x33, 0x3, 0x01) => Self::MULHU,
+            (0x33, 0x4, 0x01) => Self::DIV,
+            (0x33, 0x5, 0x01) => Self::DIVU,
+            (0x33, 0x6, 0x01) => Self::REM,
+            (0x33, 0x7, 0x01) => Self::REMU,
+            (0x3b, 0x0, 0x00) => Self::ADDW,
+            (0x3b, 0x0, 0x20) => Self::SUBW,
+            (0x3b, 0x1, 0x00) => Self::SLLW,
+            (0x3b, 0x5, 0x00) => Self::SRLW,
+            (0x3b, 0x5, 0x20) => Self::SRAW,
+            (0x3b, 0x0, 0x01) => Self::MULW,
+            (0x3b, 0x4, 0x01) => Self::DIVW,
+            (0x3b, 0x5, 0x01) => Self::DIVUW,
+            (0x3b, 0x6, 0x01) => Self::REMW,
+            (0x3b, 0x7, 0x01) => Self::REMUW,
+            (0x13, 0x0, _) => Self::ADDI,
+            (0x13, 0x2, _) => Self::SLTI,
+            (0x13, 0x3, _) => Self::SLTIU,
+            (0x13, 0x4, _) => Self::XORI,
+            (0x13, 0x6, _) => Self::ORI,
+            (0x13, 0x7, _) => Self::ANDI,
+            (0x13, 0x1, _) if func6 == 0x00 => Self::SLLI,
+            (0x13, 0x5, _) if func6 == 0x00 => Self::SRLI,
+            (0x13, 0x5, _) if func6 == 0x10 => Self::SRAI,
+            (0x1b, 0x0, _) => Self::ADDIW,
+            (0x1b, 0x1, 0x00) => Self::SLLIW,
+            (0x1b, 0x5, 0x00) => Self::SRLIW,
+            (0x1b, 0x5, 0x20) => Self::SRAIW,
+            (0x63, 0x0, _) => Self::BEQ,
+            (0x63, 0x1, _) => Self::BNE,
+            (0x63, 0x4, _) => Self::BLT,
+            (0x63, 0x5, _) => Self::BGE,
+            (0x63, 0x6, _) => Self::BLTU,
+            (0x63, 0x7, _) => Self::BGEU,
+            (0x6f, _, _) => Self::JAL,
+            (0x67, 0x0, _) => Self::JALR,
+            (0x37, _, _) => Self::LUI,
+            (0x17, _, _) => Self::AUIPC,
+            (0x03, 0x0, _) => Self::LB,
+            (0x03, 0x1, _) => Self::LH,
+            (0x03, 0x2, _) => Self::LW,
+            (0x03, 0x3, _) => Self::LD,
+            (0x03, 0x4, _) => Self::LBU,
+            (0x03, 0x5, _) => Self::LHU,
+            (0x03, 0x6, _) => Self::LWU,
+            (0x23, 0x0, _) => Self::SB,
+            (0x23, 0x1, _) => Self::SH,
+            (0x23, 0x2, _) => Self::SW,
+            (0x23, 0x3, _) => Self::SD,
+            (0x0f, 0x0, _) => Self::FENCE,
+            (0x73, 0x0, 0x00) => Self::EANY,
+            (0x73, 0x0, 0x18) => Self::MRET,
+            _ => Self::INVALID,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Rv64Emulator;
+
+impl Rv64Emulator {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn step<C: Rv64Context>(&mut self, ctx: &mut C) -> Result<()> {
+        let pc = ctx.get_pc();
+
+        if !ctx.check_insn_load(pc) {
+            ctx.trap(TrapCause::InstructionAccessFault)?;
+            return Ok(());
+        }
+
+        let dword = ctx.load_memory(pc & !(DWORD_SIZE as u64 - 1))?;
+        let word = (dword >> (8 * (pc % DWORD_SIZE as u64))) as u32;
+        if word & 0x03 != 0x03 {
+            ctx.trap(TrapCause::IllegalInstruction(word))?;
+            return Ok(());
+        }
+
+        let decoded = DecodedInstruction::new(word);
+        let kind = InsnKind::decode(word);
+        if match kind {
+            InsnKind::INVALID => ctx.trap(TrapCause::IllegalInstruction(word))?,
+            InsnKind::LB
+            | InsnKind::LH
+            | InsnKind::LW
+            | InsnKind::LD
+            | InsnKind::LBU
+            | InsnKind::LHU
+            | InsnKind::LWU => self.step_load(ctx, kind, &decoded)?,
+            InsnKind::SB | InsnKind::SH | InsnKind::SW | InsnKind::SD => {
+                self.step_store(ctx, kind, &decoded)?
+            }
+            InsnKind::EANY => match decoded.rs2 {
+                0 => ctx.ecall()?,
+                1 => ctx.trap(TrapCause::Breakpoint)?,
+                _ => ctx.trap(TrapCause::IllegalInstruction(word))?,
+            },
+            InsnKind::MRET => ctx.trap(TrapCause::IllegalInstruction(word))?,
+            _ => self.step_compute(ctx, kind, &decoded)?,
+        } {
+            ctx.on_normal_end(kind);
+        }
+
+        Ok(())
+    }
+
+    fn step_compute<M: Rv64Context>(
+        &mut self,
+        ctx: &mut M,
+        kind: InsnKind,
+        decoded: &DecodedInstruction,
+    ) -> Result<bool> {
+        let pc = ctx.get_pc();
+        let mut new_pc = pc.wrapping_add(INSN_SIZE);
+        let mut rd = decoded.rd;
+        let rs1 = ctx.load_register(decoded.rs1 as usize)?;
+        let rs2 = ctx.load_register(decoded.rs2 as usize)?;
+        let imm_i = sign_extend_u32(decoded.imm_i());
+        let mut br_cond = |cond| -> u64 {
+            rd = 0;
+            if cond {
+                new_pc = pc.wrapping_add(sign_extend_u32(decoded.imm_b()));
+            }
+            0
+        };
+        let out = match kind {
+            InsnKind::ADD => rs1.wrapping_add(rs2),
+            InsnKind::SUB => rs1.wrapping_sub(rs2),
+            InsnKind::XOR => rs1 ^ rs2,
+            InsnKind::OR => rs1 | rs2,
+            InsnKind::AND => rs1 & rs2,
+            InsnKind::SLL => rs1 << (rs2 & 0x3f),
+            InsnKind::SRL => rs1 >> (rs2 & 0x3f),
+            InsnKind::SRA => ((rs1 as i64) >> (rs2 & 0x3f)) as u64,
+            InsnKind::SLT => ((rs1 as i64) < (rs2 as i64)) as u64,
+            InsnKind::SLTU => (rs1 < rs2) as u64,
+            InsnKind::ADDI => rs1.wrapping_add(imm_i),
+            InsnKind::XORI => rs1 ^ imm_i,
+            InsnKind::ORI => rs1 | imm_i,
+            InsnKind::ANDI => rs1 & imm_i,
+            InsnKind::SLLI => rs1 << (imm_i & 0x3f),
+            InsnKind::SRLI => rs1 >> (imm_i & 0x3f),
+            InsnKind::SRAI => ((rs1 as i64) >> (imm_i & 0x3f)) as u64,
+            InsnKind::SLTI => ((rs1 as i64) < (imm_i as i64)) as u64,
+            InsnKind::SLTIU => (rs1 < imm_i) as u64,
+            InsnKind::ADDW => sign_extend_u32((rs1 as u32).wrapping_add(rs2 as u32)),
+            InsnKind::SUBW => sign_extend_u32((rs1 as u32).wrapping_sub(rs2 as u32)),
+            InsnKind::SLLW => sign_extend_u32((rs1 as u32) << (rs2 & 0x1f)),
+            InsnKind::SRLW => sign_extend_u32((rs1 as u32) >> (rs2 & 0x1f)),
+            InsnKind::SRAW => ((rs1 as i32) >> (rs2 & 0x1f)) as i64 as u64,
+            InsnKind::ADDIW => sign_extend_u32((rs1 as u32).wrapping_add(imm_i as u32)),
+            InsnKind::SLLIW => sign_extend_u32((rs1 as u32) << (imm_i & 0x1f)),
+            InsnKind::SRLIW => sign_extend_u32((rs1 as u32) >> (imm_i & 0x1f)),
+            InsnKind::SRAIW => ((rs1 as i32) >> (imm_i & 0x1f)) as i64 as u64,
+            InsnKind::BEQ => br_cond(rs1 == rs2),
+            InsnKind::BNE => br_cond(rs1 != rs2),
+            InsnKind::BLT => br_cond((rs1 as i64) < (rs2 as i64)),
+            InsnKind::BGE => br_cond((rs1 as i64) >= (rs2 as i64)),
+            InsnKind::BLTU => br_cond(rs1 < rs2),
+            InsnKind::BGEU => br_cond(rs1 >= rs2),
+            InsnKind::JAL => {
+                new_pc = pc.wrapping_add(sign_extend_u32(decoded.imm_j()));
+                pc.wrapping_add(INSN_SIZE)
+            }
+            InsnKind::JALR => {
+                new_pc = rs1.wrapping_add(imm_i) & !1;
+                pc.wrapping_add(INSN_SIZE)
+            }
+            // Memory accesses are in program order, so a fence has no effect.
+            InsnKind::FENCE => {
+                rd = 0;
+                0
+            }
+            InsnKind::LUI => sign_extend_u32(decoded.imm_u()),
+            InsnKind::AUIPC => pc.wrapping_add(sign_extend_u32(decoded.imm_u())),
+            InsnKind::MUL => rs1.wrapping_mul(rs2),
+            InsnKind::MULH => ((rs1 as i64 as i128).wrapping_mul(rs2 as i64 as i128) >> 64) as u64,
+            InsnKind::MULHSU => ((rs1 as i64 as i128).wrapping_mul(rs2 as i128) >> 64) as u64,
+            InsnKind::MULHU => ((rs1 as u128).wrapping_mul(rs2 as u128) >> 64) as u64,
+            InsnKind::DIV => {
+                if rs2 == 0 {
+                    u64::MAX
+                } else {
+                    (rs1 as i64).wrapping_div(rs2 as i64) as u64
+                }
+            }
+            InsnKind::DIVU => {
+                if rs2 == 0 {
+                    u64::MAX
+                } else {
+                    rs1 / rs2
+                }
+            }
+            InsnKind::REM => {
+                if rs2 == 0 {
+                    rs1
+                } else {
+                    (rs1 as i64).wrapping_rem(rs2 as i64) as u64
+                }
+            }
+            InsnKind::REMU => {
+                if rs2 == 0 {
+                    rs1
+                } else {
+                    rs1 % rs2
+                }
+            }
+            InsnKind::MULW => sign_extend_u32((rs1 as u32).wrapping_mul(rs2 as u32)),
+            InsnKind::DIVW => {
+                if rs2 as u32 == 0 {
+                    u64::MAX
+                } else {
+                    (rs1 as i32).wrapping_div(rs2 as i32) as i64 as u64
+                }
+            }
+            InsnKind::DIVUW => {
+                if rs2 as u32 == 0 {
+                    u64::MAX
+                } else {
+                    sign_extend_u32(rs1 as u32 / rs2 as u32)
+                }
+            }
+            InsnKind::REMW => {
+                if rs2 as u32 == 0 {
+                    sign_extend_u32(rs1 as u32)
+                } else {
+                    (rs1 as i32).wrapping_rem(rs2 as i32) as i64 as u64
+                }
+            }
+            InsnKind::REMUW => {
+                if rs2 as u32 == 0 {
+                    sign_extend_u32(rs1 as u32)
+                } else {
+                    sign_extend_u32(rs1 as u32 % rs2 as u32)
+                }
+            }
+            _ => unreachable!(),
+        };
+        if new_pc % INSN_SIZE != 0 {
+            return ctx.trap(TrapCause::InstructionAddressMisaligned);
+        }
+        ctx.store_register(rd as usize, out)?;
+        ctx.set_pc(new_pc);
+        Ok(true)
+    }
+
+    fn step_load<M: Rv64Context>(
+        &mut self,
+        ctx: &mut M,
+        kind: InsnKind,
+        decoded: &DecodedInstruction,
+    ) -> Result<bool> {
+        let rs1 = ctx.load_register(decoded.rs1 as usize)?;
+        let addr = rs1.wrapping_add(sign_extend_u32(decoded.imm_i()));
+        if !ctx.check_data_load(addr) {
+            return ctx.trap(TrapCause::LoadAccessFault);
+        }
+        let size = access_size(kind);
+        if addr % size != 0 {
+            return ctx.trap(TrapCause::LoadAddressMisaligned);
+        }
+        let data = ctx.load_memory(addr & !(DWORD_SIZE as u64 - 1))?;
+        let data = data >> (8 * (addr % DWORD_SIZE as u64));
+        let out = match kind {
+            InsnKind::LB => data as i8 as i64 as u64,
+            InsnKind::LH => data as i16 as i64 as u64,
+            InsnKind::LW => data as i32 as i64 as u64,
+            InsnKind::LD => data,
+            InsnKind::LBU => data as u8 as u64,
+            InsnKind::LHU => data as u16 as u64,
+            InsnKind::LWU => data as u32 as u64,
+            _ => unreachable!(),
+        };
+        ctx.store_register(decoded.rd as usize, out)?;
+        ctx.set_pc(ctx.get_pc().wrapping_add(INSN_SIZE));
+        Ok(true)
+    }
+
+    fn step_store<M: Rv64Context>(
+        &mut self,
+        ctx: &mut M,
+        kind: InsnKind,
+        decoded: &DecodedInstruction,
+    ) -> Result<bool> {
+        let rs1 = ctx.load_register(decoded.rs1 as usize)?;
+        let rs2 = ctx.load_register(decoded.rs2 as usize)?;
+        let addr = rs1.wrapping_add(sign_extend_u32(decoded.imm_s()));
+        if !ctx.check_data_store(addr) {
+            return ctx.trap(TrapCause::StoreAccessFault);
+        }
+        let size = access_size(kind);
+        if addr % size != 0 {
+            tracing::debug!("Misaligned {kind:?}");
+            return ctx.trap(TrapCause::StoreAddressMisaligned(addr));
+        }
+        let daddr = addr & !(DWORD_SIZE as u64 - 1);
+        let shift = 8 * (addr % DWORD_SIZE as u64);
+        let mask = match size {
+            8 => u64::MAX,
+            _ => ((1u64 << (8 * size)) - 1) << shift,
+        };
+        let data = ctx.load_memory(daddr)?;
+        ctx.store_memory(daddr, (data & !mask) | ((rs2 << shift) & mask))?;
+        ctx.set_pc(ctx.get_pc().wrapping_add(INSN_SIZE));
+        Ok(true)
+    }
+}
+
+/// The number of bytes accessed by a load or store.
+fn access_size(kind: InsnKind) -> u64 {
+    match kind {
+        InsnKind::LB | InsnKind::LBU | InsnKind::SB => 1,
+        InsnKind::LH | InsnKind::LHU | InsnKind::SH => 2,
+        InsnKind::LW | InsnKind::LWU | InsnKind::SW => 4,
+        _ => 8,
+    }
+}
+
+fn sign_extend_u32(x: u32) -> u64 {
+    x as i32 as i64 as u64
+}
+
+/// A sparse 64-bit memory, whose doublewords are zero until they are stored.
+#[derive(Clone, Debug, Default)]
+pub struct SparseMemory {
+    pages: BTreeMap<u64, Box<[u64]>>,
+}
+
+impl SparseMemory {
+    /// The number of bytes in each page of memory that is allocated on first store.
+    pub const PAGE_SIZE: u64 = 4096;
+
+    /// Load the doubleword at the doubleword-aligned address `addr`.
+    pub fn load(&self, addr: u64) -> u64 {
+        let (page, idx) = Self::split(addr);
+        self.pages.get(&page).map_or(0, |page| page[idx])
+    }
+
+    /// Store the doubleword at the doubleword-aligned address `addr`.
+    pub fn store(&mut self, addr: u64, data: u64) {
+        let (page, idx) = Self::split(addr);
+        self.pages
+            .entry(page)
+            .or_insert_with(|| vec![0; Self::PAGE_SIZE as usize / DWORD_SIZE].into_boxed_slice())
+            [idx] = data;
+    }
+
+    /// Load the byte at `addr`.
+    pub fn load_u8(&self, addr: u64) -> u8 {
+        let daddr = addr & !(DWORD_SIZE as u64 - 1);
+        (self.load(daddr) >> (8 * (addr % DWORD_SIZE as u64))) as u8
+    }
+
+    /// Store the byte at `addr`.
+    pub fn store_u8(&mut self, addr: u64, data: u8) {
+        let daddr = addr & !(DWORD_SIZE as u64 - 1);
+        let shift = 8 * (addr % DWORD_SIZE as u64);
+        let dword = self.load(daddr) & !(0xff << shift);
+        self.store(daddr, dword | (data as u64) << shift);
+    }
+
+    /// Store `bytes` at consecutive addresses starting from `addr`.
+    pub fn store_bytes(&mut self, addr: u64, bytes: &[u8]) {
+        for (i, &byte) in bytes.iter().enumerate() {
+            self.store_u8(addr + i as u64, byte);
+        }
+    }
+
+    /// Load the loadable segments of an RV64IM ELF, and return its entrypoint and memory.
+    pub fn load_elf(input: &[u8]) -> Result<(u64, Self)> {
+        let elf = ElfBytes::<LittleEndian>::minimal_parse(input)
+            .map_err(|err| anyhow!("Elf parse error: {err}"))?;
+        if elf.ehdr.class != Class::ELF64 {
+            bail!("Not a 64-bit ELF");
+        }
+        if elf.ehdr.e_machine != elf::abi::EM_RISCV {
+            bail!("Invalid machine type, must be RISC-V");
+        }
+        if elf.ehdr.e_type != elf::abi::ET_EXEC {
+            bail!("Invalid ELF type, must be executable");
+        }
+        let entry = elf.ehdr.e_entry;
+        if entry % INSN_SIZE != 0 {
+            bail!("Invalid entrypoint");
+        }
+        let segments = elf.segments().ok_or(anyhow!("Missing segment table"))?;
+        if segments.len() > 256 {
+            bail!("Too many program headers");
+        }
+        let mut memory = Self::default();
+        for segment in segments.iter().filter(|x| x.p_type == elf::abi::PT_LOAD) {
+            if segment.p_filesz > segment.p_memsz {
+                bail!("Invalid segment file_size");
+            }
+            segment
+                .p_vaddr
+                .checked_add(segment.p_memsz)
+                .context("Invalid segment vaddr")?;
+            // The rest of the segment up to its mem_size is zero, as is all unstored memory.
+            let start = usize::try_from(segment.p_offset)?;
+            let len = usize::try_from(segment.p_filesz)?;
+            let data = input
+                .get(start..start.checked_add(len).context("Invalid segment offset")?)
+                .context("Invalid segment offset")?;
+            memory.store_bytes(segment.p_vaddr, data);
+        }
+        Ok((entry, memory))
+    }
+
+    /// Store `insns` at consecutive addresses starting from `addr`, which must be aligned to an
+    /// instruction.
+    pub fn store_insns(&mut self, addr: u64, insns: &[u32]) {
+        for (i, &insn) in insns.iter().enumerate() {
+            let addr = addr + i as u64 * INSN_SIZE;
+            let daddr = addr & !(DWORD_SIZE as u64 - 1);
+            let shift = 8 * (addr % DWORD_SIZE as u64);
+            let data = self.load(daddr) & !(0xffff_ffff << shift);
+            self.store(daddr, data | (insn as u64) << shift);
+        }
+    }
+
+    /// The number of pages that have been stored to.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    fn split(addr: u64) -> (u64, usize) {
+        debug_assert_eq!(addr % DWORD_SIZE as u64, 0);
+        (
+            addr / Self::PAGE_SIZE,
+            (addr % Self::PAGE_SIZE) as usize / DWORD_SIZE,
+        )
+    }
+}
+
+/// The result of running a guest with [Rv64Executor].
+#[derive(Debug)]
+pub struct Rv64Result {
+    /// The exit code of the guest.
+    pub exit_code: ExitCode,
+
+    /// The output digest that the guest halted with, or zero if it did not halt.
+    pub output_digest: Digest,
+
+    /// The number of instructions the guest executed.
+    pub user_cycles: u64,
+}
+
+/// Runs an RV64IM guest, answering its syscalls with a [Syscall] handler.
+///
+/// Guests use the ecall ABI of RV32IM guests with registers widened to 64 bits: `t0` selects the
+/// `halt` or `software` ecall, and the return values of a syscall are sign-extended into `a0` and
+/// `a1`. Since [SyscallContext] addresses memory with 32 bits, the buffers a guest passes to a
+/// syscall must be in the low 4 GiB of its memory, and syscall handlers see the low 32 bits of
+/// each register.
+pub struct Rv64Executor<'a, S: Syscall> {
+    pc: u64,
+    regs: [u64; REG_MAX],
+    memory: SparseMemory,
+    syscall_handler: &'a S,
+    user_cycles: u64,
+    exit: Option<(ExitCode, Digest)>,
+}
+
+impl<'a, S: Syscall> Rv64Executor<'a, S> {
+    /// Construct an executor for the RV64IM ELF `elf`.
+    pub fn from_elf(elf: &[u8], syscall_handler: &'a S) -> Result<Self> {
+        let (entry, memory) = SparseMemory::load_elf(elf)?;
+        let mut regs = [0; REG_MAX];
+        regs[REG_SP] = STACK_TOP;
+        Ok(Self {
+            pc: entry,
+            regs,
+            memory,
+            syscall_handler,
+            user_cycles: 0,
+            exit: None,
+        })
+    }
+
+    /// Run the guest until it halts, or until it has executed `max_cycles` instructions.
+    pub fn run(&mut self, max_cycles: Option<u64>) -> Result<Rv64Result> {
+        let mut emu = Rv64Emulator::new();
+        loop {
+            if let Some((exit_code, output_digest)) = self.exit.take() {
+                return Ok(Rv64Result {
+                    exit_code,
+                    output_digest,
+                    user_cycles: self.user_cycles,
+                });
+            }
+            if max_cycles.is_some_and(|max_cycles| self.user_cycles >= max_cycles) {
+                return Ok(Rv64Result {
+                    exit_code: ExitCode::SessionLimit,
+                    output_digest: Digest::ZERO,
+                    user_cycles: self.user_cycles,
+                });
+            }
+            emu.step(self)?;
+        }
+    }
+
+    /// The memory of the guest.
+    pub fn memory(&self) -> &SparseMemory {
+        &self.memory
+    }
+
+    fn ecall_halt(&mut self) -> Result<bool> {
+        let a0 = self.regs[REG_A0];
+        let output_ptr = self.regs[REG_A1];
+        let output: [u8; DIGEST_BYTES] =
+            array::from_fn(|i| self.memory.load_u8(output_ptr.wrapping_add(i as u64)));
+
+        let halt_type = (a0 & 0xff) as u32;
+        let user_exit = ((a0 >> 8) & 0xff) as u32;
+
+        tracing::debug!("ecall_halt({halt_type}, {user_exit})");
+
+        let exit_code = match halt_type {
+            halt::TERMINATE => ExitCode::Halted(user_exit),
+            halt::PAUSE => ExitCode::Paused(user_exit),
+            _ => bail!("Illegal halt type: {halt_type}"),
+        };
+        self.exit = Some((exit_code, output.into()));
+        self.pc = self.pc.wrapping_add(INSN_SIZE);
+        Ok(true)
+    }
+
+    fn ecall_software(&mut self) -> Result<bool> {
+        let into_guest_ptr = self.regs[REG_A0];
+        let into_guest_len = self.regs[REG_A1] as usize;
+        ensure!(
+            into_guest_len <= MAX_INTO_GUEST_WORDS,
+            "Syscall returns too many words: {into_guest_len}"
+        );
+        let syscall_name = self.load_string(self.regs[REG_A2])?;
+        tracing::trace!("ecall_software({syscall_name}, into_guest: {into_guest_len})");
+
+        let mut into_guest = vec![0u32; into_guest_len];
+        let syscall_handler = self.syscall_handler;
+        let (a0, a1) = syscall_handler.syscall(&syscall_name, self, &mut into_guest)?;
+
+        // The guest uses a null pointer to indicate that a transfer from host to guest is not
+        // needed.
+        if into_guest_ptr != 0 {
+            for (i, word) in into_guest.iter().enumerate() {
+                let addr = into_guest_ptr.wrapping_add((i * WORD_BYTES) as u64);
+                self.memory.store_bytes(addr, &word.to_le_bytes());
+            }
+        }
+        self.regs[REG_A0] = sign_extend_u32(a0);
+        self.regs[REG_A1] = sign_extend_u32(a1);
+        self.pc = self.pc.wrapping_add(INSN_SIZE);
+        Ok(true)
+    }
+
+    fn load_string(&self, addr: u64) -> Result<String> {
+        let mut buf = Vec::new();
+        loop {
+            let byte = self.memory.load_u8(addr.wrapping_add(buf.len() as u64));
+            if byte == 0 {
+                break;
+            }
+            ensure!(buf.len() < MAX_SYSCALL_NAME_LEN, "Syscall name is too long");
+            buf.push(byte);
+        }
+        Ok(String::from_utf8(buf)?)
+    }
+}
+
+impl<S: Syscall> Rv64Context for Rv64Executor<'_, S> {
+    fn ecall(&mut self) -> Result<bool> {
+        match self.regs[REG_T0] {
+            t0 if t0 == ecall::HALT as u64 => self.ecall_halt(),
+            t0 if t0 == ecall::SOFTWARE as u64 => self.ecall_software(),
+            t0 => bail!("Unsupported ecall in RV64IM mode: {t0}"),
+        }
+    }
+
+    fn trap(&self, cause: TrapCause) -> Result<bool> {
+        bail!("Trap: {cause:08x?}, pc: 0x{:016x}", self.pc)
+    }
+
+    fn on_normal_end(&mut self, _kind: InsnKind) {
+        self.user_cycles += 1;
+    }
+
+    fn get_pc(&self) -> u64 {
+        self.pc
+    }
+
+    fn set_pc(&mut self, addr: u64) {
+        self.pc = addr;
+    }
+
+    fn load_register(&mut self, idx: usize) -> Result<u64> {
+        Ok(self.regs[idx])
+    }
+
+    fn store_register(&mut self, idx: usize, data: u64) -> Result<()> {
+        if idx != 0 {
+            self.regs[idx] = data;
+        }
+        Ok(())
+    }
+
+    fn load_memory(&mut self, addr: u64) -> Result<u64> {
+        Ok(self.memory.load(addr))
+    }
+
+    fn store_memory(&mut self, addr: u64, data: u64) -> Result<()> {
+        self.memory.store(addr, data);
+        Ok(())
+    }
+}
+
+impl<S: Syscall> SyscallContext for Rv64Executor<'_, S> {
+    fn peek_register(&mut self, idx: usize) -> Result<u32> {
+        ensure!(idx < REG_MAX, "invalid register: {idx}");
+        Ok(self.regs[idx] as u32)
+    }
+
+    fn peek_u32(&mut self, addr: ByteAddr) -> Result<u32> {
+        let addr = addr.0 as u64;
+        Ok(u32::from_le_bytes(array::from_fn(|i| {
+            self.memory.load_u8(addr + i as u64)
+        })))
+    }
+
+    fn peek_u8(&mut self, addr: ByteAddr) -> Result<u8> {
+        Ok(self.memory.load_u8(addr.0 as u64))
+    }
+
+    fn peek_page(&mut self, _page_idx: u32) -> Result<Vec<u8>> {
+        bail!("Pages of memory cannot be loaded in RV64IM mode")
+    }
+
+    fn get_cycle(&self) -> u64 {
+        self.user_cycles
+    }
+
+    fn get_pc(&self) -> u32 {
+        self.pc as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use anyhow::{bail, Result};
+    use risc0_binfmt::ExitCode;
+    use risc0_zkvm_platform::syscall::{
+        nr::SYS_WRITE,
+        reg_abi::{REG_A3, REG_A4, REG_A5},
+    };
+
+    use super::{
+        super::{
+            addr::ByteAddr,
+            exec::{Syscall, SyscallContext},
+            testutil,
+        },
+        InsnKind, Rv64Context, Rv64Emulator, Rv64Executor, SparseMemory, TrapCause, STACK_TOP,
+    };
+
+    struct TestContext {
+        pc: u64,
+        regs: [u64; 32],
+        memory: SparseMemory,
+        halted: bool,
+    }
+
+    impl Rv64Context for TestContext {
+        fn ecall(&mut self) -> Result<bool> {
+            self.halted = true;
+            Ok(false)
+        }
+
+        fn trap(&self, cause: TrapCause) -> Result<bool> {
+            bail!("Trap: {cause:?}")
+        }
+
+        fn get_pc(&self) -> u64 {
+            self.pc
+        }
+
+        fn set_pc(&mut self, addr: u64) {
+            self.pc = addr;
+        }
+
+        fn load_register(&mut self, idx: usize) -> Result<u64> {
+            Ok(self.regs[idx])
+        }
+
+        fn store_register(&mut self, idx: usize, data: u64) -> Result<()> {
+            if idx != 0 {
+                self.regs[idx] = data;
+            }
+            Ok(())
+        }
+
+        fn load_memory(&mut self, addr: u64) -> Result<u64> {
+            Ok(self.memory.load(addr))
+        }
+
+        fn store_memory(&mut self, addr: u64, data: u64) -> Result<()> {
+            self.memory.store(addr, data);
+            Ok(())
+        }
+    }
+
+    fn run(insns: &[u32]) -> TestContext {
+        const ENTRY: u64 = 0x1000;
+        let mut ctx = TestContext {
+            pc: ENTRY,
+            regs: [0; 32],
+            memory: SparseMemory::default(),
+            halted: false,
+        };
+        ctx.memory.store_insns(ENTRY, insns);
+        let mut emu = Rv64Emulator::new();
+        while !ctx.halted {
+            emu.step(&mut ctx).unwrap();
+        }
+        ctx
+    }
+
+    #[test]
+    fn decode() {
+        assert_eq!(InsnKind::decode(0x0010059b), InsnKind::ADDIW); // addiw a1, zero, 1
+        assert_eq!(InsnKind::decode(0x02059593), InsnKind::SLLI); // slli a1, a1, 32
+        assert_eq!(InsnKind::decode(0x4205d593), InsnKind::SRAI); // srai a1, a1, 32
+        assert_eq!(InsnKind::decode(0x00b53023), InsnKind::SD); // sd a1, 0(a0)
+        assert_eq!(InsnKind::decode(0x00053603), InsnKind::LD); // ld a2, 0(a0)
+        assert_eq!(InsnKind::decode(0x02c5c6bb), InsnKind::DIVW); // divw a3, a1, a2
+        assert_eq!(InsnKind::decode(0x0ff0000f), InsnKind::FENCE); // fence
+    }
+
+    #[derive(Default)]
+    struct JournalSyscall {
+        journal: RefCell<Vec<u8>>,
+    }
+
+    impl Syscall for JournalSyscall {
+        fn syscall(
+            &self,
+            syscall: &str,
+            ctx: &mut dyn SyscallContext,
+            _into_guest: &mut [u32],
+        ) -> Result<(u32, u32)> {
+            assert_eq!(syscall, SYS_WRITE.as_str());
+            assert_eq!(ctx.peek_register(REG_A3)?, 3);
+            let buf_ptr = ByteAddr(ctx.peek_register(REG_A4)?);
+            let buf_len = ctx.peek_register(REG_A5)?;
+            self.journal
+                .borrow_mut()
+                .extend(ctx.peek_region(buf_ptr, buf_len)?);
+            Ok((0, 0))
+        }
+    }
+
+    #[test]
+    fn execute_elf() {
+        let syscall = JournalSyscall::default();
+        let mut exec = Rv64Executor::from_elf(&testutil::rv64_hello(), &syscall).unwrap();
+        let result = exec.run(None).unwrap();
+        assert_eq!(result.exit_code, ExitCode::Halted(0));
+        assert_eq!(result.user_cycles, 18);
+        assert_eq!(
+            syscall.journal.borrow().as_slice(),
+            testutil::RV64_HELLO_JOURNAL
+        );
+        // The pointer saved on the stack, above 4 GiB.
+        assert_eq!(exec.memory().load(STACK_TOP - 8), 0x11040);
+    }
+
+    #[test]
+    fn session_limit() {
+        let syscall = JournalSyscall::default();
+        let mut exec = Rv64Executor::from_elf(&testutil::rv64_hello(), &syscall).unwrap();
+        let result = exec.run(Some(4)).unwrap();
+        assert_eq!(result.exit_code, ExitCode::SessionLimit);
+        assert_eq!(result.user_cycles, 4);
+        assert!(syscall.journal.borrow().is_empty());
+    }
+
+    #[test]
+    fn reject_elf32() {
+        let elf = testutil::rv64_hello();
+        let mut elf32 = elf.clone();
+        elf32[4] = 1; // ELFCLASS32
+        let syscall = JournalSyscall::default();
+        assert!(Rv64Executor::from_elf(&elf32, &syscall).is_err());
+    }
+
+    #[test]
+    fn wide_addresses() {
+        let ctx = run(&[
+            0x0010051b, // addiw a0, zero, 1
+            0x02451513, // slli a0, a0, 36
+            0xfff0059b, // addiw a1, zero, -1
+            0x02059593, // slli a1, a1, 32
+            0x00b53023, // sd a1, 0(a0)
+            0x00053603, // ld a2, 0(a0)
+            0x00456683, // lwu a3, 4(a0)
+            0x00452703, // lw a4, 4(a0)
+            0x4205d793, // srai a5, a1, 32
+            0x00000073, // ecall
+        ]);
+        assert_eq!(ctx.regs[10], 1 << 36);
+        assert_eq!(ctx.regs[12], 0xffff_ffff_0000_0000);
+        assert_eq!(ctx.regs[13], 0xffff_ffff);
+        assert_eq!(ctx.regs[14], u64::MAX);
+        assert_eq!(ctx.regs[15], u64::MAX);
+        assert_eq!(ctx.memory.load(1 << 36), 0xffff_ffff_0000_0000);
+        // One page of code, and one page of data above 4 GiB.
+        assert_eq!(ctx.memory.page_count(), 2);
+    }
+
+    #[test]
+    fn word_ops() {
+        let ctx = run(&[
+            0x800005b7, // lui a1, 0x80000
+            0x00b585bb, // addw a1, a1, a1
+            0x80000637, // lui a2, 0x80000
+            0xfff0069b, // addiw a3, zero, -1
+            0x02d6473b, // divw a4, a2, a3
+            0x02d657bb, // divuw a5, a2, a3
+            0x00000073, // ecall
+        ]);
+        assert_eq!(ctx.regs[11], 0);
+        assert_eq!(ctx.regs[12], 0xffff_ffff_8000_0000);
+        assert_eq!(ctx.regs[14], 0xffff_ffff_8000_0000);
+        assert_eq!(ctx.regs[15], 0);
+    }
+}
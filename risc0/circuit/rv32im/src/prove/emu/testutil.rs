@@ -64,6 +64,26 @@ pub fn basic() -> Program {
     )
 }
 
+/// A program using Linux syscalls, which writes its first instruction to stdout, and then exits
+/// with the low byte of the initial program break as the exit code.
+pub fn linux() -> Program {
+    program_from_instructions(
+        0x4000,
+        [
+            0x04000893, // li      a7,64
+            0x00100513, // li      a0,1
+            0x000045b7, // lui     a1,0x4
+            0x00400613, // li      a2,4
+            0x00000073, // ecall(write)
+            0x0d600893, // li      a7,214
+            0x00000513, // li      a0,0
+            0x00000073, // ecall(brk)
+            0x05d00893, // li      a7,93
+            0x00000073, // ecall(exit)
+        ],
+    )
+}
+
 pub fn simple_loop() -> Program {
     // loop.asm:
     //
@@ -93,6 +113,119 @@ pub fn simple_loop() -> Program {
     )
 }
 
+pub fn load_store() -> Program {
+    // load_store.asm:
+    //
+    // .global _boot
+    // .text
+    //
+    // _boot:
+    //     lui     a0,0x10
+    //     li      a4,0
+    //     li      a5,10
+    // loop:
+    //     sw      a4,0(a0)
+    //     lw      a6,0(a0)
+    //     addi    a4,a4,1
+    //     blt     a4,a5,loop
+    //     li      a0,0
+    //     lui     a1,0x1000
+    //     ecall
+    //
+    // riscv32-unknown-elf-as load_store.asm -o load_store; riscv32-unknown-elf-objdump -d load_store
+    program_from_instructions(
+        0x4000,
+        [
+            0x00010537, // lui     a0,0x10
+            0x00000713, // li      a4,0
+            0x00a00793, // li      a5,10
+            0x00e52023, // sw      a4,0(a0)
+            0x00052803, // lw      a6,0(a0)
+            0x00170713, // add     a4,a4,1
+            0xfef74ae3, // blt     a4,a5,c <loop>
+            0x00000513, // li      a0,0
+            0x010005b7, // lui     a1,0x1000
+            0x00000073, // ecall
+        ],
+    )
+}
+
+/// A program which fills four pages of memory with consecutive words, one store at a time, as
+/// `memset` would.
+pub fn fill_memory() -> Program {
+    // fill_memory.asm:
+    //
+    // .global _boot
+    // .text
+    //
+    // _boot:
+    //     lui     a0,0x10
+    //     li      a4,0
+    //     li      a5,1024
+    // loop:
+    //     sw      a4,0(a0)
+    //     addi    a0,a0,4
+    //     addi    a4,a4,1
+    //     blt     a4,a5,loop
+    //     li      a0,0
+    //     lui     a1,0x1000
+    //     ecall
+    //
+    // riscv32-unknown-elf-as fill_memory.asm -o fill_memory; riscv32-unknown-elf-objdump -d fill_memory
+    program_from_instructions(
+        0x4000,
+        [
+            0x00010537, // lui     a0,0x10
+            0x00000713, // li      a4,0
+            0x40000793, // li      a5,1024
+            0x00e52023, // sw      a4,0(a0)
+            0x00450513, // add     a0,a0,4
+            0x00170713, // add     a4,a4,1
+            0xfef74ae3, // blt     a4,a5,c <loop>
+            0x00000513, // li      a0,0
+            0x010005b7, // lui     a1,0x1000
+            0x00000073, // ecall
+        ],
+    )
+}
+
+/// A program which registers a handler for illegal instructions and unknown ecalls, triggers one
+/// of each, and then exits with the number of traps handled plus the sum of their values, which
+/// is 2 + (0 + 99).
+pub fn trap_handler() -> Program {
+    program_from_instructions(
+        0x4000,
+        [
+            0x00600293, // li      t0,6
+            0x00000513, // li      a0,0
+            0x000045b7, // lui     a1,0x4
+            0x04058593, // addi    a1,a1,64
+            0x00000073, // ecall(trap: set illegal instruction handler)
+            0x10000513, // li      a0,256
+            0x00000073, // ecall(trap: set unknown ecall handler)
+            0x00000000, // illegal instruction
+            0x06300293, // li      t0,99
+            0x00000073, // ecall(unknown)
+            0x00000293, // li      t0,0
+            0x01248533, // add     a0,s1,s2
+            0x00851513, // slli    a0,a0,8
+            0x000045b7, // lui     a1,0x4
+            0x00000073, // ecall(halt)
+            0x00000013, // nop
+            // handler:
+            0x00148493, // addi    s1,s1,1
+            0x00600293, // li      t0,6
+            0x00100513, // li      a0,1
+            0x00000073, // ecall(trap: info)
+            0x00c90933, // add     s2,s2,a2
+            0x00458593, // addi    a1,a1,4
+            0x00200513, // li      a0,2
+            0x00000073, // ecall(trap: set epc)
+            0x30200073, // mret
+        ],
+    )
+}
+
 pub fn large_text() -> Program {
     let iter = (0..2500).map(|_| {
         0x1234b137 // lui x2, 0x1234b000
@@ -106,3 +239,92 @@ pub fn large_text() -> Program {
 
     program_from_instructions(0x4000, iter)
 }
+
+/// The journal written by [rv64_hello].
+#[cfg(feature = "rv64im")]
+pub const RV64_HELLO_JOURNAL: &[u8] = b"hello";
+
+/// An RV64IM ELF that loads `code` at `entry`, and `data` at `data_addr`.
+#[cfg(feature = "rv64im")]
+pub fn elf64(entry: u64, code: &[u32], data_addr: u64, data: &[u8]) -> Vec<u8> {
+    const EHDR_SIZE: u16 = 64;
+    const PHDR_SIZE: u16 = 56;
+
+    let code: Vec<u8> = code.iter().flat_map(|insn| insn.to_le_bytes()).collect();
+    // Each segment is (vaddr, bytes, flags), with R+X code and R+W data.
+    let segments = [(entry, code.as_slice(), 5u32), (data_addr, data, 6u32)];
+
+    let mut elf = Vec::new();
+    elf.extend_from_slice(b"\x7fELF");
+    elf.extend_from_slice(&[2, 1, 1, 0]); // ELFCLASS64, little endian, version 1, System V ABI
+    elf.extend_from_slice(&[0; 8]);
+    elf.extend_from_slice(&2u16.to_le_bytes()); // ET_EXEC
+    elf.extend_from_slice(&243u16.to_le_bytes()); // EM_RISCV
+    elf.extend_from_slice(&1u32.to_le_bytes()); // e_version
+    elf.extend_from_slice(&entry.to_le_bytes());
+    elf.extend_from_slice(&(EHDR_SIZE as u64).to_le_bytes()); // e_phoff
+    elf.extend_from_slice(&0u64.to_le_bytes()); // e_shoff
+    elf.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+    elf.extend_from_slice(&EHDR_SIZE.to_le_bytes());
+    elf.extend_from_slice(&PHDR_SIZE.to_le_bytes());
+    elf.extend_from_slice(&(segments.len() as u16).to_le_bytes());
+    elf.extend_from_slice(&[0; 6]); // No section headers
+
+    let mut offset = EHDR_SIZE as u64 + PHDR_SIZE as u64 * segments.len() as u64;
+    for (vaddr, bytes, flags) in segments {
+        elf.extend_from_slice(&1u32.to_le_bytes()); // PT_LOAD
+        elf.extend_from_slice(&flags.to_le_bytes());
+        elf.extend_from_slice(&offset.to_le_bytes());
+        elf.extend_from_slice(&vaddr.to_le_bytes()); // p_vaddr
+        elf.extend_from_slice(&vaddr.to_le_bytes()); // p_paddr
+        elf.extend_from_slice(&(bytes.len() as u64).to_le_bytes()); // p_filesz
+        elf.extend_from_slice(&(bytes.len() as u64).to_le_bytes()); // p_memsz
+        elf.extend_from_slice(&8u64.to_le_bytes()); // p_align
+        offset += bytes.len() as u64;
+    }
+    for (_, bytes, _) in segments {
+        elf.extend_from_slice(bytes);
+    }
+    elf
+}
+
+/// An RV64IM ELF that saves a pointer on its stack above 4 GiB, writes [RV64_HELLO_JOURNAL] to the
+/// journal with `sys_write`, and halts with exit code 0.
+#[cfg(feature = "rv64im")]
+pub fn rv64_hello() -> Vec<u8> {
+    use risc0_zkvm_platform::syscall::nr::SYS_WRITE;
+
+    const DATA: u64 = 0x11000;
+
+    // The syscall name at DATA, the journal at DATA + 0x40, and the output digest at DATA + 0x80.
+    let mut data = vec![0; 0xa0];
+    let name = SYS_WRITE.as_str().as_bytes();
+    data[..name.len()].copy_from_slice(name);
+    data[0x40..0x40 + RV64_HELLO_JOURNAL.len()].copy_from_slice(RV64_HELLO_JOURNAL);
+
+    elf64(
+        0x10000,
+        &[
+            0x0ff0000f, // fence
+            0xff010113, // addi sp, sp, -16
+            0x00011737, // lui a4, 0x11
+            0x04070713, // addi a4, a4, 64
+            0x00e13423, // sd a4, 8(sp)
+            0x00813703, // ld a4, 8(sp)
+            0x00000513, // addi a0, zero, 0
+            0x00000593, // addi a1, zero, 0
+            0x00011637, // lui a2, 0x11
+            0x00300693, // addi a3, zero, 3
+            0x00500793, // addi a5, zero, 5
+            0x00200293, // addi t0, zero, 2
+            0x00000073, // ecall(software)
+            0x00000293, // addi t0, zero, 0
+            0x00000513, // addi a0, zero, 0
+            0x000115b7, // lui a1, 0x11
+            0x08058593, // addi a1, a1, 128
+            0x00000073, // ecall(halt)
+        ],
+        DATA,
+        &data,
+    )
+}
@@ -20,18 +20,18 @@ use std::{
 };
 
 use risc0_core::field::{baby_bear::BabyBearElem, Elem};
+#[cfg(feature = "prove")]
 use risc0_zkp::{
-    adapter::TapsProvider,
-    core::{
-        digest::{Digest, DIGEST_WORDS},
-        hash::sha::SHA256_INIT,
-    },
-    hal::Hal,
-    prove::poly_group::PolyGroup,
-    MAX_CYCLES_PO2, MIN_CYCLES_PO2, ZK_CYCLES,
+    adapter::TapsProvider, core::digest::Digest, hal::Hal, prove::poly_group::PolyGroup,
+    MAX_CYCLES_PO2, MIN_CYCLES_PO2,
+};
+use risc0_zkp::{
+    core::{digest::DIGEST_WORDS, hash::sha::SHA256_INIT},
+    ZK_CYCLES,
 };
 use risc0_zkvm_platform::{memory, WORD_SIZE};
 
+#[cfg(feature = "prove")]
 use crate::CIRCUIT;
 
 pub const SHA_K_OFFSET: usize = memory::PRE_LOAD.start();
@@ -365,7 +365,10 @@ impl Loader {
         }
         self.cycle += 1;
     }
+}
 
+#[cfg(feature = "prove")]
+impl Loader {
     // Compute the `ControlId` associated with the given HAL, along with a textual description.
     pub fn compute_control_id_table<H: Hal<Elem = BabyBearElem>>(hal: &H) -> Vec<(String, Digest)> {
         // Make the digest for each level
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use rayon::prelude::*;
 use risc0_circuit_rv32im_sys::ffi::{RawMemoryTransaction, RawPreflightCycle, RawPreflightTrace};
 use risc0_zkp::field::baby_bear::BabyBearElem;
 
@@ -24,10 +25,14 @@ use crate::{
     },
 };
 
-struct Injector<'a> {
-    steps: usize,
-    cycle: usize,
-    data: &'a mut [BabyBearElem],
+/// The rows of the data columns written by a task of [MachineContext::inject_exec_backs].
+const INJECT_BLOCK_ROWS: usize = 1 << 12;
+
+/// Writes the exec back of a cycle to the row of the previous cycle, given the part of each data
+/// column that holds the rows starting at `base`.
+struct Injector<'a, 'b> {
+    row: usize,
+    columns: &'a mut [&'b mut [BabyBearElem]],
 }
 
 pub struct MachineContext {
@@ -38,13 +43,16 @@ pub struct MachineContext {
     _raw_extras: Vec<u32>,
 }
 
-impl<'a> Injector<'a> {
-    fn new(steps: usize, cycle: usize, data: &'a mut [BabyBearElem]) -> Self {
-        Self { steps, cycle, data }
+impl<'a, 'b> Injector<'a, 'b> {
+    fn new(base: usize, cycle: usize, columns: &'a mut [&'b mut [BabyBearElem]]) -> Self {
+        Self {
+            row: cycle - 1 - base,
+            columns,
+        }
     }
 
-    fn get_idx(&self, reg: &DataReg) -> usize {
-        reg.offset * self.steps + self.cycle - 1
+    fn set(&mut self, reg: &DataReg, value: impl Into<BabyBearElem>) {
+        self.columns[reg.offset][self.row] = value.into();
     }
 
     fn set_pc(&mut self, pc: ByteAddr) {
@@ -53,32 +61,32 @@ impl<'a> Injector<'a> {
         let bot2 = bytes[3] & 0b11;
         let top2 = bytes[3] >> 2 & 0b11;
         let pc = LAYOUT.mux.body.pc;
-        self.data[self.get_idx(pc.bytes[0])] = (bytes[0] as u32).into();
-        self.data[self.get_idx(pc.bytes[1])] = (bytes[1] as u32).into();
-        self.data[self.get_idx(pc.bytes[2])] = (bytes[2] as u32).into();
-        self.data[self.get_idx(pc.twits[0])] = (bot2 as u32).into();
-        self.data[self.get_idx(pc.twits[1])] = (top2 as u32).into();
+        self.set(pc.bytes[0], bytes[0] as u32);
+        self.set(pc.bytes[1], bytes[1] as u32);
+        self.set(pc.bytes[2], bytes[2] as u32);
+        self.set(pc.twits[0], bot2 as u32);
+        self.set(pc.twits[1], top2 as u32);
     }
 
     fn set_user_mode(&mut self) {
         let user_mode = LAYOUT.mux.body.user_mode;
-        self.data[self.get_idx(user_mode)] = 0u32.into();
+        self.set(user_mode, 0u32);
     }
 
     fn set_next_major(&mut self, major: Major) {
         let next_major = LAYOUT.mux.body.next_major;
-        self.data[self.get_idx(next_major)] = major.as_u32().into();
+        self.set(next_major, major.as_u32());
     }
 
     fn set_halt(&mut self, sys_exit_code: u8, user_exit_code: u8, write_addr: WordAddr) {
         let major_select = LAYOUT.mux.body.major_select;
         let halt_cycle = LAYOUT.mux.body.major_mux;
-        self.data[self.get_idx(major_select[Major::ECall as usize])] = 0u32.into();
-        self.data[self.get_idx(major_select[Major::PageFault as usize])] = 0u32.into();
-        self.data[self.get_idx(major_select[Major::Halt as usize])] = 1u32.into();
-        self.data[self.get_idx(halt_cycle.sys_exit_code)] = (sys_exit_code as u32).into();
-        self.data[self.get_idx(halt_cycle.user_exit_code)] = (user_exit_code as u32).into();
-        self.data[self.get_idx(halt_cycle.write_addr)] = write_addr.0.into();
+        self.set(major_select[Major::ECall as usize], 0u32);
+        self.set(major_select[Major::PageFault as usize], 0u32);
+        self.set(major_select[Major::Halt as usize], 1u32);
+        self.set(halt_cycle.sys_exit_code, sys_exit_code as u32);
+        self.set(halt_cycle.user_exit_code, user_exit_code as u32);
+        self.set(halt_cycle.write_addr, write_addr.0);
     }
 }
 
@@ -178,45 +186,77 @@ impl MachineContext {
     }
 
     pub fn is_exec_par_safe(&self, cycle: usize) -> bool {
-        self.get_cycle(cycle).back.is_some()
+        get_cycle(&self.trace, cycle).back.is_some()
     }
 
-    pub fn inject_exec_backs(&self, steps: usize, cycle: usize, data: &mut [BabyBearElem]) {
-        let cur_cycle = self.get_cycle(cycle);
-        if let Some(back) = &cur_cycle.back {
-            let mut injector = Injector::new(steps, cycle, data);
-            match back {
-                Back::Null => (),
-                Back::Body { pc } => {
-                    injector.set_pc(*pc);
-                    injector.set_user_mode();
-                    injector.set_next_major(Major::MuxSize);
-                }
-                Back::Halt {
-                    pc,
-                    sys_exit_code,
-                    user_exit_code,
-                    write_addr,
-                } => {
-                    injector.set_pc(*pc);
-                    injector.set_user_mode();
-                    injector.set_halt(*sys_exit_code, *user_exit_code, *write_addr);
-                    injector.set_next_major(Major::Halt);
-                }
+    /// Inject the exec backs of the cycles before `last_cycle` into the column-major `data`.
+    ///
+    /// The exec back of a cycle is written to the row of the previous cycle, and the first cycle
+    /// has none. The rows are split into blocks which are filled in parallel, each from the cycles
+    /// that write to it.
+    pub fn inject_exec_backs(&self, steps: usize, last_cycle: usize, data: &mut [BabyBearElem]) {
+        let mut blocks: Vec<Vec<&mut [BabyBearElem]>> = (0..steps.div_ceil(INJECT_BLOCK_ROWS))
+            .map(|_| Vec::with_capacity(data.len() / steps))
+            .collect();
+        for column in data.chunks_mut(steps) {
+            for (block, rows) in blocks.iter_mut().zip(column.chunks_mut(INJECT_BLOCK_ROWS)) {
+                block.push(rows);
             }
         }
+
+        blocks
+            .into_par_iter()
+            .enumerate()
+            .for_each(|(idx, mut columns)| {
+                let base = idx * INJECT_BLOCK_ROWS;
+                let cycles = (base + 1)..(base + INJECT_BLOCK_ROWS + 1).min(last_cycle);
+                for cycle in cycles {
+                    inject_cycle_exec_backs(&self.trace, base, cycle, &mut columns);
+                }
+            });
     }
+}
 
-    fn get_stage_offset(&self, cycle: usize) -> (&PreflightStage, usize) {
-        if cycle < self.trace.pre.cycles.len() {
-            (&self.trace.pre, 0)
-        } else {
-            (&self.trace.body, self.trace.pre.cycles.len())
+fn inject_cycle_exec_backs(
+    trace: &PreflightTrace,
+    base: usize,
+    cycle: usize,
+    columns: &mut [&mut [BabyBearElem]],
+) {
+    let cur_cycle = get_cycle(trace, cycle);
+    if let Some(back) = &cur_cycle.back {
+        let mut injector = Injector::new(base, cycle, columns);
+        match back {
+            Back::Null => (),
+            Back::Body { pc } => {
+                injector.set_pc(*pc);
+                injector.set_user_mode();
+                injector.set_next_major(Major::MuxSize);
+            }
+            Back::Halt {
+                pc,
+                sys_exit_code,
+                user_exit_code,
+                write_addr,
+            } => {
+                injector.set_pc(*pc);
+                injector.set_user_mode();
+                injector.set_halt(*sys_exit_code, *user_exit_code, *write_addr);
+                injector.set_next_major(Major::Halt);
+            }
         }
     }
+}
 
-    fn get_cycle(&self, cycle: usize) -> &PreflightCycle {
-        let (stage, offset) = self.get_stage_offset(cycle);
-        &stage.cycles[cycle - offset]
+fn get_stage_offset(trace: &PreflightTrace, cycle: usize) -> (&PreflightStage, usize) {
+    if cycle < trace.pre.cycles.len() {
+        (&trace.pre, 0)
+    } else {
+        (&trace.body, trace.pre.cycles.len())
     }
 }
+
+fn get_cycle(trace: &PreflightTrace, cycle: usize) -> &PreflightCycle {
+    let (stage, offset) = get_stage_offset(trace, cycle);
+    &stage.cycles[cycle - offset]
+}
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use rand::thread_rng;
+use rayon::prelude::*;
 use risc0_zkp::{
     adapter::TapsProvider,
     field::{
@@ -56,36 +57,43 @@ where
         mode: StepMode,
     ) -> Self {
         let steps = 1 << po2;
+        let machine = MachineContext::new(trace);
 
-        nvtx::range_push!("load");
-        let mut loader = Loader::new(steps, CIRCUIT.ctrl_size());
-        let last_cycle = loader.load();
-        nvtx::range_pop!();
-        tracing::debug!("last_cycle: {last_cycle}");
+        // The ctrl columns depend only on the segment size, so they are loaded while the data
+        // columns are allocated and the noise is drawn. Injecting the exec backs must wait for
+        // the loader, since it needs the number of cycles in use.
+        let ((loader, last_cycle), mut data) = rayon::join(
+            || {
+                nvtx::range_push!("load");
+                let mut loader = Loader::new(steps, CIRCUIT.ctrl_size());
+                let last_cycle = loader.load();
+                nvtx::range_pop!();
+                (loader, last_cycle)
+            },
+            || {
+                nvtx::range_push!("alloc(data)");
+                let mut data = vec![BabyBearElem::INVALID; steps * CIRCUIT.data_size()];
+                nvtx::range_pop!();
 
-        nvtx::range_push!("alloc(data)");
-        let mut data = vec![BabyBearElem::INVALID; steps * CIRCUIT.data_size()];
-        nvtx::range_pop!();
+                if mode == StepMode::Parallel {
+                    nvtx::range_push!("noise");
+                    // Set data to random for the ZK_CYCLES
+                    data.par_chunks_mut(steps).for_each(|column| {
+                        let mut rng = thread_rng();
+                        for elem in &mut column[steps - ZK_CYCLES..] {
+                            *elem = BabyBearElem::random(&mut rng);
+                        }
+                    });
+                    nvtx::range_pop!();
+                }
+                data
+            },
+        );
+        tracing::debug!("last_cycle: {last_cycle}");
 
-        let machine = MachineContext::new(trace);
         if mode != StepMode::SeqForward {
             nvtx::range_push!("inject_exec_backs");
-            for cycle in 0..last_cycle {
-                machine.inject_exec_backs(steps, cycle, &mut data);
-            }
-            nvtx::range_pop!();
-        }
-
-        if mode == StepMode::Parallel {
-            nvtx::range_push!("noise");
-            let mut rng = thread_rng();
-            for i in 0..ZK_CYCLES {
-                let cycle = steps - ZK_CYCLES + i;
-                // Set data to random for the ZK_CYCLES
-                for j in 0..CIRCUIT.data_size() {
-                    data[j * steps + cycle] = BabyBearElem::random(&mut rng);
-                }
-            }
+            machine.inject_exec_backs(steps, last_cycle, &mut data);
             nvtx::range_pop!();
         }
 
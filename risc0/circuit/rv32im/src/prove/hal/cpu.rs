@@ -25,7 +25,10 @@ use risc0_sys::CppError;
 use risc0_zkp::{
     adapter::PolyFp,
     core::{
-        hash::{poseidon2::Poseidon2HashSuite, sha::Sha256HashSuite},
+        hash::{
+            hash_suite_from_name, poseidon2::Poseidon2HashSuite, registered_hash_suite_names,
+            sha::Sha256HashSuite,
+        },
         log2_ceil,
     },
     field::baby_bear::BabyBear,
@@ -64,30 +67,116 @@ impl CircuitWitnessGenerator<CpuHal<BabyBear>> for CpuCircuitHal {
         io: &CpuBuffer<BabyBearElem>,
         data: &CpuBuffer<BabyBearElem>,
     ) {
-        tracing::debug!("witgen: {steps}, {count}");
-        extern "C" {
-            fn risc0_circuit_rv32im_cpu_witgen(
-                mode: u32,
-                trace: *const RawPreflightTrace,
-                steps: u32,
-                count: u32,
-                ctrl: *const BabyBearElem,
-                io: *const BabyBearElem,
-                data: *const BabyBearElem,
-            ) -> CppError;
-        }
-        unsafe {
-            risc0_circuit_rv32im_cpu_witgen(
-                mode as u32,
-                trace,
-                steps as u32,
-                count as u32,
-                ctrl.as_slice().as_ptr(),
-                io.as_slice().as_ptr(),
-                data.as_slice().as_ptr(),
-            )
-            .unwrap();
-        }
+        cpu_witgen(
+            mode,
+            trace,
+            steps,
+            count,
+            &ctrl.as_slice(),
+            &io.as_slice(),
+            &data.as_slice(),
+        );
+    }
+}
+
+fn cpu_witgen(
+    mode: StepMode,
+    trace: &RawPreflightTrace,
+    steps: usize,
+    count: usize,
+    ctrl: &[BabyBearElem],
+    io: &[BabyBearElem],
+    data: &[BabyBearElem],
+) {
+    tracing::debug!("witgen: {steps}, {count}");
+    extern "C" {
+        fn risc0_circuit_rv32im_cpu_witgen(
+            mode: u32,
+            trace: *const RawPreflightTrace,
+            steps: u32,
+            count: u32,
+            ctrl: *const BabyBearElem,
+            io: *const BabyBearElem,
+            data: *const BabyBearElem,
+        ) -> CppError;
+    }
+    unsafe {
+        risc0_circuit_rv32im_cpu_witgen(
+            mode as u32,
+            trace,
+            steps as u32,
+            count as u32,
+            ctrl.as_ptr(),
+            io.as_ptr(),
+            data.as_ptr(),
+        )
+        .unwrap();
+    }
+}
+
+/// Wraps the [CircuitHal] of a third-party backend, generating the witness on the CPU through
+/// views of the backend's buffers.
+pub(crate) struct HostWitnessGenerator<C> {
+    inner: Rc<C>,
+}
+
+impl<C> HostWitnessGenerator<C> {
+    pub fn new(inner: Rc<C>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<H, C> CircuitWitnessGenerator<H> for HostWitnessGenerator<C>
+where
+    H: Hal<Elem = BabyBearElem>,
+    C: CircuitHal<H>,
+{
+    fn generate_witness(
+        &self,
+        mode: StepMode,
+        trace: &RawPreflightTrace,
+        steps: usize,
+        count: usize,
+        ctrl: &H::Buffer<BabyBearElem>,
+        io: &H::Buffer<BabyBearElem>,
+        data: &H::Buffer<BabyBearElem>,
+    ) {
+        ctrl.view(|ctrl| {
+            io.view_mut(|io| {
+                data.view_mut(|data| cpu_witgen(mode, trace, steps, count, ctrl, io, data))
+            })
+        });
+    }
+}
+
+impl<H, C> CircuitHal<H> for HostWitnessGenerator<C>
+where
+    H: Hal,
+    C: CircuitHal<H>,
+{
+    fn accumulate(
+        &self,
+        ctrl: &H::Buffer<H::Elem>,
+        io: &H::Buffer<H::Elem>,
+        data: &H::Buffer<H::Elem>,
+        mix: &H::Buffer<H::Elem>,
+        accum: &H::Buffer<H::Elem>,
+        steps: usize,
+    ) {
+        self.inner.accumulate(ctrl, io, data, mix, accum, steps)
+    }
+
+    fn eval_check(
+        &self,
+        check: &H::Buffer<H::Elem>,
+        groups: &[&H::Buffer<H::Elem>],
+        globals: &[&H::Buffer<H::Elem>],
+        poly_mix: H::ExtElem,
+        po2: usize,
+        steps: usize,
+    ) {
+        self.inner
+            .eval_check(check, groups, globals, poly_mix, po2, steps)
     }
 }
 
@@ -208,6 +297,12 @@ pub fn segment_prover(hashfn: &str) -> Result<Box<dyn SegmentProver>> {
     let suite = match hashfn {
         "sha-256" => Sha256HashSuite::new_suite(),
         "poseidon2" => Poseidon2HashSuite::new_suite(),
+        _ if registered_hash_suite_names()
+            .iter()
+            .any(|name| name == hashfn) =>
+        {
+            hash_suite_from_name(hashfn).unwrap()
+        }
         _ => bail!("Unsupported hashfn: {hashfn}"),
     };
 
@@ -14,28 +14,11 @@
 
 pub mod emu;
 pub mod engine;
+#[cfg(feature = "prove")]
 pub mod hal;
+#[cfg(feature = "prove")]
+mod prover;
 pub mod segment;
 
-use anyhow::Result;
-use cfg_if::cfg_if;
-
-use self::segment::Segment;
-
-pub type Seal = Vec<u32>;
-
-pub trait SegmentProver {
-    fn prove_segment(&self, segment: &Segment) -> Result<Seal>;
-}
-
-pub fn segment_prover(hashfn: &str) -> Result<Box<dyn SegmentProver>> {
-    cfg_if! {
-        if #[cfg(feature = "cuda")] {
-            self::hal::cuda::segment_prover(hashfn)
-        } else if #[cfg(any(target_os = "macos", target_os = "ios"))] {
-            self::hal::metal::segment_prover(hashfn)
-        } else {
-            self::hal::cpu::segment_prover(hashfn)
-        }
-    }
-}
+#[cfg(feature = "prove")]
+pub use self::prover::*;
@@ -0,0 +1,90 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::HashMap,
+    rc::Rc,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use anyhow::{anyhow, Result};
+use cfg_if::cfg_if;
+use risc0_zkp::{
+    field::baby_bear::{BabyBear, BabyBearElem, BabyBearExtElem},
+    hal::{CircuitHal, Hal},
+};
+
+use super::{engine::SegmentProverImpl, hal::cpu::HostWitnessGenerator, segment::Segment};
+
+pub type Seal = Vec<u32>;
+
+pub trait SegmentProver {
+    fn prove_segment(&self, segment: &Segment) -> Result<Seal>;
+}
+
+/// A prover backend that can be selected by name with [register_backend].
+///
+/// Backends that accelerate the proving primitives, such as an FPGA or a GPU that is not
+/// supported by this crate, implement [Hal] for the commitments, NTTs and hashing, and
+/// [CircuitHal] for the accumulation and constraint checks, and build their segment provers with
+/// [segment_prover_with_hal].
+pub trait ProverBackend: Send + Sync {
+    /// Construct a segment prover that uses the given hash function, e.g. `poseidon2`.
+    fn segment_prover(&self, hashfn: &str) -> Result<Box<dyn SegmentProver>>;
+}
+
+fn backends() -> &'static Mutex<HashMap<String, Arc<dyn ProverBackend>>> {
+    static BACKENDS: OnceLock<Mutex<HashMap<String, Arc<dyn ProverBackend>>>> = OnceLock::new();
+    BACKENDS.get_or_init(Default::default)
+}
+
+/// Register a [ProverBackend] under `name`, replacing any backend already registered under it.
+pub fn register_backend(name: &str, backend: Arc<dyn ProverBackend>) {
+    backends().lock().unwrap().insert(name.to_string(), backend);
+}
+
+/// Construct a segment prover from the [ProverBackend] registered under `name`.
+pub fn backend_segment_prover(name: &str, hashfn: &str) -> Result<Box<dyn SegmentProver>> {
+    let backend = backends()
+        .lock()
+        .unwrap()
+        .get(name)
+        .cloned()
+        .ok_or_else(|| anyhow!("No prover backend registered as {name:?}"))?;
+    backend.segment_prover(hashfn)
+}
+
+/// Construct a segment prover from a [Hal] and [CircuitHal] supplied by a [ProverBackend].
+///
+/// The witness is generated on the CPU and written into the buffers of `hal`.
+pub fn segment_prover_with_hal<H, C>(hal: Rc<H>, circuit_hal: Rc<C>) -> Box<dyn SegmentProver>
+where
+    H: Hal<Field = BabyBear, Elem = BabyBearElem, ExtElem = BabyBearExtElem> + 'static,
+    C: CircuitHal<H> + 'static,
+{
+    let circuit_hal = Rc::new(HostWitnessGenerator::new(circuit_hal));
+    Box::new(SegmentProverImpl::new(hal, circuit_hal))
+}
+
+pub fn segment_prover(hashfn: &str) -> Result<Box<dyn SegmentProver>> {
+    cfg_if! {
+        if #[cfg(feature = "cuda")] {
+            super::hal::cuda::segment_prover(hashfn)
+        } else if #[cfg(any(target_os = "macos", target_os = "ios"))] {
+            super::hal::metal::segment_prover(hashfn)
+        } else {
+            super::hal::cpu::segment_prover(hashfn)
+        }
+    }
+}
@@ -44,6 +44,23 @@ pub struct Segment {
     pub index: usize,
     pub input_digest: Digest,
     pub output_digest: Option<Digest>,
+    #[serde(default)]
+    pub paging: SegmentPaging,
+    /// The [CIRCUIT_ID][crate::CIRCUIT_ID] of the executor that produced this segment.
+    pub circuit_id: Digest,
+}
+
+/// The pages paged in (read) and out (written) by a segment, and the cycles spent doing so.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentPaging {
+    /// The number of pages paged in, including page table pages.
+    pub page_reads: u32,
+    /// The number of pages paged out, including page table pages.
+    pub page_writes: u32,
+    /// The number of cycles spent paging pages in.
+    pub read_cycles: usize,
+    /// The number of cycles spent paging pages out.
+    pub write_cycles: usize,
 }
 
 impl Segment {
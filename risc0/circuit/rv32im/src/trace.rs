@@ -17,10 +17,14 @@ extern crate alloc;
 use alloc::vec::Vec;
 
 use anyhow::Result;
+use risc0_zkvm_platform::PAGE_SIZE;
 use serde::{Deserialize, Serialize};
 
 /// An event traced from the running VM.
+///
+/// New kinds of events may be added, so a `match` on a [TraceEvent] needs a wildcard arm.
 #[derive(Clone, Eq, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum TraceEvent {
     /// An instruction has started at the given program counter
     InstructionStart {
@@ -47,11 +51,104 @@ pub enum TraceEvent {
         /// Data that's been written
         region: Vec<u8>,
     },
+
+    /// A range of memory has been written by one or more stores, as combined by a
+    /// [WriteCombiner]
+    MemoryRegionSet {
+        /// Address of the first byte written
+        addr: u32,
+        /// Data that's been written
+        bytes: Vec<u8>,
+    },
+
+    /// The most recently started instruction paged in pages or marked them to be paged out,
+    /// recorded if the executor is configured to trace paging
+    Paging {
+        /// Program counter of the instruction that touched the pages
+        pc: u32,
+        /// Number of cycles spent paging
+        cycles: u64,
+    },
+
+    /// The most recently started instruction accessed memory covered by a watchpoint
+    Watchpoint {
+        /// Program counter of the instruction that accessed the memory
+        pc: u32,
+        /// Address accessed
+        addr: u32,
+        /// Whether the memory was read or written
+        kind: WatchKind,
+    },
+
+    /// The value of a register before the most recently started instruction set it, recorded
+    /// along with its [TraceEvent::RegisterSet] if the executor is configured to trace previous
+    /// values
+    RegisterPrev {
+        /// Register ID (0-16)
+        idx: usize,
+        /// Previous value in the register
+        value: u32,
+    },
+
+    /// The data in a memory location before it was written, recorded along with its
+    /// [TraceEvent::MemorySet] if the executor is configured to trace previous values
+    MemoryPrev {
+        /// Address of memory that's been written
+        addr: u32,
+        /// Data that was previously in the region
+        region: Vec<u8>,
+    },
+
+    /// The data in a range of memory before it was written, as combined by a [WriteCombiner] from
+    /// the [TraceEvent::MemoryPrev] events of the stores in the [TraceEvent::MemoryRegionSet]
+    /// that follows it
+    MemoryRegionPrev {
+        /// Address of the first byte written
+        addr: u32,
+        /// Data that was previously in the range
+        bytes: Vec<u8>,
+    },
+}
+
+/// An access to memory covered by a watchpoint, by an instruction of the guest or by the host in
+/// answer to a syscall.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct WatchHit {
+    /// Program counter of the instruction that accessed the memory
+    pub pc: u32,
+    /// Address accessed
+    pub addr: u32,
+    /// Whether the memory was read or written
+    pub kind: WatchKind,
+}
+
+/// The accesses to memory that trigger a watchpoint.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum WatchKind {
+    /// Loads by the guest
+    Read,
+    /// Stores by the guest, and writes to guest memory by the host in answer to a syscall
+    Write,
+    /// Both reads and writes; never the kind of a [WatchHit] or [TraceEvent::Watchpoint]
+    Access,
+}
+
+impl WatchKind {
+    /// Whether a watchpoint of this kind is triggered by an `access` of the given kind.
+    pub fn matches(self, access: WatchKind) -> bool {
+        self == WatchKind::Access || self == access
+    }
 }
 
 /// A callback used to collect [TraceEvent]s.
 pub trait TraceCallback {
     fn trace_callback(&mut self, event: TraceEvent) -> Result<()>;
+
+    /// Pass on any events held back by the callback. Called by the executor at the end of each
+    /// segment.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
 
 impl<F: FnMut(TraceEvent) -> Result<()>> TraceCallback for F {
@@ -60,6 +157,95 @@ impl<F: FnMut(TraceEvent) -> Result<()>> TraceCallback for F {
     }
 }
 
+/// A [TraceCallback] that combines the [TraceEvent::MemorySet] events of consecutive stores to
+/// adjacent memory in the same page into one [TraceEvent::MemoryRegionSet], and passes the
+/// events on to another callback.
+///
+/// This cuts the number of events passed on for guests that copy or fill memory, e.g. with
+/// `memcpy`, by an order of magnitude. The stores are held back until a store that does not
+/// follow them, or the end of the segment, so the combined event is passed on after the events
+/// of the instructions that made the stores, which are passed on as they arrive.
+///
+/// If the executor is configured to trace previous values, the [TraceEvent::MemoryPrev] events
+/// of the stores held back are combined in the same way, into a [TraceEvent::MemoryRegionPrev]
+/// passed on just before the [TraceEvent::MemoryRegionSet]. A [TraceEvent::MemoryPrev] that
+/// does not continue the previous values of the stores held back is passed on as it arrives.
+pub struct WriteCombiner<T> {
+    inner: T,
+    // The stores held back.
+    pending: Option<PendingRegion>,
+}
+
+struct PendingRegion {
+    addr: u32,
+    bytes: Vec<u8>,
+    // The previous contents of the first `prev.len()` bytes.
+    prev: Vec<u8>,
+}
+
+impl<T: TraceCallback> WriteCombiner<T> {
+    /// Construct a [WriteCombiner] passing events on to `inner`.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            pending: None,
+        }
+    }
+
+    fn flush_pending(&mut self) -> Result<()> {
+        let Some(PendingRegion { addr, bytes, prev }) = self.pending.take() else {
+            return Ok(());
+        };
+        if !prev.is_empty() {
+            self.inner
+                .trace_callback(TraceEvent::MemoryRegionPrev { addr, bytes: prev })?;
+        }
+        self.inner
+            .trace_callback(TraceEvent::MemoryRegionSet { addr, bytes })
+    }
+}
+
+impl<T: TraceCallback> TraceCallback for WriteCombiner<T> {
+    fn trace_callback(&mut self, event: TraceEvent) -> Result<()> {
+        match event {
+            TraceEvent::MemorySet { addr, region } => {
+                if let Some(pending) = &mut self.pending {
+                    let end = pending.addr.checked_add(pending.bytes.len() as u32);
+                    if end == Some(addr) && addr % PAGE_SIZE as u32 != 0 {
+                        pending.bytes.extend_from_slice(&region);
+                        return Ok(());
+                    }
+                }
+                self.flush_pending()?;
+                self.pending = Some(PendingRegion {
+                    addr,
+                    bytes: region,
+                    prev: Vec::new(),
+                });
+                Ok(())
+            }
+            TraceEvent::MemoryPrev { addr, region } => {
+                if let Some(pending) = &mut self.pending {
+                    let next = pending.addr as usize + pending.prev.len();
+                    let end = pending.addr as usize + pending.bytes.len();
+                    if addr as usize == next && next + region.len() <= end {
+                        pending.prev.extend_from_slice(&region);
+                        return Ok(());
+                    }
+                }
+                self.inner
+                    .trace_callback(TraceEvent::MemoryPrev { addr, region })
+            }
+            event => self.inner.trace_callback(event),
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.flush_pending()?;
+        self.inner.flush()
+    }
+}
+
 impl core::fmt::Debug for TraceEvent {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
@@ -70,6 +256,20 @@ impl core::fmt::Debug for TraceEvent {
             Self::MemorySet { addr, region } => {
                 write!(f, "MemorySet(0x{addr:08X}, {region:#04X?})")
             }
+            Self::MemoryRegionSet { addr, bytes } => {
+                write!(f, "MemoryRegionSet(0x{addr:08X}, {bytes:#04X?})")
+            }
+            Self::Paging { pc, cycles } => write!(f, "Paging(0x{pc:08X}, {cycles})"),
+            Self::Watchpoint { pc, addr, kind } => {
+                write!(f, "Watchpoint(0x{pc:08X}, 0x{addr:08X}, {kind:?})")
+            }
+            Self::RegisterPrev { idx, value } => write!(f, "RegisterPrev({idx}, 0x{value:08X})"),
+            Self::MemoryPrev { addr, region } => {
+                write!(f, "MemoryPrev(0x{addr:08X}, {region:#04X?})")
+            }
+            Self::MemoryRegionPrev { addr, bytes } => {
+                write!(f, "MemoryRegionPrev(0x{addr:08X}, {bytes:#04X?})")
+            }
         }
     }
 }
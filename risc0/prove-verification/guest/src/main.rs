@@ -0,0 +1,34 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_std]
+#![no_main]
+
+use risc0_zkvm::{guest::env, sha::Digestible, ExitCode, ReceiptClaim};
+
+risc0_zkvm::entry!(main);
+
+fn main() {
+    let claim: ReceiptClaim = env::read();
+    // The claim comes from the prover, and the integrity of a paused or faulted execution
+    // verifies as well, so the exit code has to be checked here for the statement to hold.
+    assert_eq!(
+        claim.exit_code,
+        ExitCode::Halted(0),
+        "verified receipt must have halted with exit code 0"
+    );
+    env::verify_integrity(&claim).unwrap();
+    env::commit_slice(claim.pre.digest().as_bytes());
+    env::commit_slice(claim.digest().as_bytes());
+}
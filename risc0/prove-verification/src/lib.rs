@@ -0,0 +1,172 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![doc = include_str!("../README.md")]
+#![deny(missing_docs)]
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+
+use anyhow::{anyhow, ensure, Context as _, Result};
+use risc0_zkvm::{
+    default_prover,
+    sha::{Digest, Digestible, DIGEST_BYTES},
+    ExecutorEnv, ProverOpts, Receipt,
+};
+
+mod methods {
+    include!(concat!(env!("OUT_DIR"), "/methods.rs"));
+}
+
+/// The ELF of the verifier guest.
+pub use methods::RISC0_PROVE_VERIFICATION_GUEST_ELF as VERIFICATION_ELF;
+/// The image ID of the verifier guest.
+pub use methods::RISC0_PROVE_VERIFICATION_GUEST_ID as VERIFICATION_ID;
+
+/// The statement proven by [prove_verification]: a receipt with the given claim digest verifies
+/// for the given image ID, and its guest halted with exit code 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Verification {
+    /// Image ID of the verified receipt.
+    pub image_id: Digest,
+
+    /// Digest of the [ReceiptClaim](risc0_zkvm::ReceiptClaim) of the verified receipt, which
+    /// commits to its journal and exit code.
+    pub claim_digest: Digest,
+}
+
+impl Verification {
+    /// Decode the statement from the journal of a receipt produced by [prove_verification].
+    pub fn decode(journal: &[u8]) -> Result<Self> {
+        ensure!(
+            journal.len() == 2 * DIGEST_BYTES,
+            "verification journal must be {} bytes, got {}",
+            2 * DIGEST_BYTES,
+            journal.len()
+        );
+        let (image_id, claim_digest) = journal.split_at(DIGEST_BYTES);
+        Ok(Self {
+            image_id: Digest::try_from(image_id)?,
+            claim_digest: Digest::try_from(claim_digest)?,
+        })
+    }
+
+    /// Verify a receipt produced by [prove_verification], and decode the statement it proves.
+    pub fn from_receipt(receipt: &Receipt) -> Result<Self> {
+        receipt
+            .verify(VERIFICATION_ID)
+            .context("receipt is not a proof of verification")?;
+        Self::decode(&receipt.journal.bytes)
+    }
+}
+
+/// Prove that `receipt` verifies for `image_id`, producing a receipt of the kind requested by
+/// `opts` for the [Verification] statement.
+///
+/// The verifier guest checks `receipt` with [env::verify_integrity], so `receipt` is added as
+/// an assumption of its execution. A succinct or Groth16 request resolves the assumption, and
+/// the resulting receipt is unconditional. A composite request carries `receipt` along as an
+/// assumption receipt. Returns an error if `receipt` does not verify for `image_id`, which
+/// includes its guest halting with exit code 0, or if its claim has been pruned.
+///
+/// [env::verify_integrity]: risc0_zkvm::guest::env::verify_integrity
+pub fn prove_verification(
+    receipt: &Receipt,
+    image_id: impl Into<Digest>,
+    opts: &ProverOpts,
+) -> Result<Receipt> {
+    let image_id = image_id.into();
+    receipt
+        .verify(image_id)
+        .map_err(|err| anyhow!("receipt does not verify for image {image_id}: {err}"))?;
+    let claim = receipt
+        .claim()?
+        .value()
+        .context("receipt claim must not be pruned")?;
+
+    let env = ExecutorEnv::builder()
+        .write(&claim)?
+        .add_assumption(receipt.clone())
+        .build()?;
+    let proof = default_prover()
+        .prove_with_opts(env, VERIFICATION_ELF, opts)?
+        .receipt;
+
+    let verification = Verification::decode(&proof.journal.bytes)?;
+    ensure!(
+        verification
+            == Verification {
+                image_id,
+                claim_digest: claim.digest(),
+            },
+        "verifier guest committed an unexpected statement"
+    );
+    Ok(proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkvm::{default_prover, sha::Digestible, ExecutorEnv, ProverOpts, Receipt};
+    use risc0_zkvm_methods::{multi_test::MultiTestSpec, MULTI_TEST_ELF, MULTI_TEST_ID};
+
+    use super::{prove_verification, Verification};
+
+    fn prove_nothing() -> Receipt {
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::DoNothing)
+            .unwrap()
+            .build()
+            .unwrap();
+        default_prover()
+            .prove_with_opts(env, MULTI_TEST_ELF, &ProverOpts::composite())
+            .unwrap()
+            .receipt
+    }
+
+    #[test]
+    fn prove_verification_succinct() {
+        let receipt = prove_nothing();
+        let proof = prove_verification(&receipt, MULTI_TEST_ID, &ProverOpts::succinct()).unwrap();
+        proof.inner.succinct().unwrap();
+        assert_eq!(
+            Verification::from_receipt(&proof).unwrap(),
+            Verification {
+                image_id: MULTI_TEST_ID.into(),
+                claim_digest: receipt.claim().unwrap().digest(),
+            }
+        );
+        // The proof of verification is not a proof of the original statement.
+        assert!(Verification::from_receipt(&receipt).is_err());
+    }
+
+    #[test]
+    fn prove_verification_requires_halt() {
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::Halt(1))
+            .unwrap()
+            .build()
+            .unwrap();
+        let receipt = default_prover()
+            .prove_with_opts(env, MULTI_TEST_ELF, &ProverOpts::composite())
+            .unwrap()
+            .receipt;
+        let err = prove_verification(&receipt, MULTI_TEST_ID, &ProverOpts::succinct()).unwrap_err();
+        assert!(err.to_string().contains("does not verify"), "{err}");
+    }
+
+    #[test]
+    fn prove_verification_wrong_image() {
+        let receipt = prove_nothing();
+        let err = prove_verification(&receipt, [0u32; 8], &ProverOpts::succinct()).unwrap_err();
+        assert!(err.to_string().contains("does not verify"), "{err}");
+    }
+}
@@ -14,16 +14,26 @@
 
 use std::{fs, io, path::PathBuf, rc::Rc};
 
-use clap::{Args, Parser, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use memmap2::Mmap;
 use risc0_zkvm::{
-    get_prover_server, ApiServer, ExecutorEnv, ExecutorImpl, ProverOpts, ProverServer,
-    VerifierContext,
+    get_prover_server, inspect::Artifact, ApiServer, ExecutorEnv, ExecutorImpl, ProverOpts,
+    ProverServer, VerifierContext,
 };
 
 /// Runs a RISC-V ELF binary within the RISC Zero ZKVM.
 #[derive(Parser)]
-#[command(about, version, author)]
+#[command(
+    about,
+    version,
+    author,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[command(flatten)]
     mode: Mode,
 
@@ -69,6 +79,15 @@ struct Cli {
     receipt_kind: ReceiptKind,
 }
 
+#[derive(Subcommand)]
+enum Command {
+    /// Print a summary of a receipt, a segment, or a session and verify its integrity.
+    Inspect {
+        /// A receipt or segment file, or a directory of segments written by the executor.
+        path: PathBuf,
+    },
+}
+
 #[derive(Args)]
 #[group(required = true, multiple = false)]
 struct Mode {
@@ -108,6 +127,11 @@ pub fn main() {
         .init();
 
     let args = Cli::parse();
+    if let Some(Command::Inspect { path }) = args.command.as_ref() {
+        let artifact = Artifact::load(path).unwrap();
+        print!("{artifact}");
+        return;
+    }
     if let Some(port) = args.mode.port {
         run_server(port);
         return;
@@ -138,8 +162,13 @@ pub fn main() {
 
     let session = {
         let mut exec = if let Some(ref elf_path) = args.mode.elf {
-            let elf_contents = fs::read(elf_path).unwrap();
-            ExecutorImpl::from_elf(env, &elf_contents).unwrap()
+            // Map the ELF rather than reading it, so that only the parts of it that are used are
+            // loaded from disk.
+            let elf_file = fs::File::open(elf_path).unwrap();
+            // SAFETY: the mapping is read-only, and r0vm does not expect the ELF to be modified
+            // while it is running.
+            let elf_contents = unsafe { Mmap::map(&elf_file) }.unwrap();
+            ExecutorImpl::from_owned_elf(env, elf_contents).unwrap()
         } else if let Some(ref image_path) = args.mode.image {
             let image_contents = fs::read(image_path).unwrap();
             let image = bincode::deserialize(&image_contents).unwrap();
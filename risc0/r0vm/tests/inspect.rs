@@ -0,0 +1,49 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use assert_cmd::Command;
+use assert_fs::{fixture::PathChild, TempDir};
+use risc0_zkvm::{sha::Digest, FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
+
+#[test]
+fn inspect_receipt() {
+    let image_id = Digest::from([7u32; 8]);
+    let journal = b"journal".to_vec();
+    let receipt = Receipt::new(
+        InnerReceipt::Fake(FakeReceipt::new(ReceiptClaim::ok(
+            image_id,
+            journal.clone(),
+        ))),
+        journal,
+    );
+
+    let temp = TempDir::new().unwrap();
+    let receipt_file = temp.child("receipt.bincode");
+    std::fs::write(&receipt_file, bincode::serialize(&receipt).unwrap()).unwrap();
+
+    let output = Command::cargo_bin("r0vm")
+        .unwrap()
+        .arg("inspect")
+        .arg(&*receipt_file)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("Receipt:      fake"));
+    assert!(output.contains("Exit code:    Halted(0)"));
+    assert!(output.contains(&format!("Image ID:     {image_id}")));
+    assert!(output.contains("text:       \"journal\""));
+}
@@ -0,0 +1,31 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![doc = include_str!("../README.md")]
+#![cfg_attr(not(feature = "std"), no_std)]
+#![deny(missing_docs)]
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+
+#[cfg(feature = "std")]
+pub use risc0_zkvm::is_dev_mode;
+pub use risc0_zkvm::{
+    compute_claim_digest, sha,
+    sha::{Digest, Digestible},
+    Assumption, AssumptionReceipt, Assumptions, CompositeReceipt,
+    CompositeReceiptVerifierParameters, ExitCode, FakeReceipt, Groth16Receipt,
+    Groth16ReceiptVerifierParameters, InnerAssumptionReceipt, InnerReceipt, Input, Journal,
+    MaybePruned, Output, PrunedValueError, Receipt, ReceiptClaim, ReceiptMetadata, SegmentReceipt,
+    SegmentReceiptVerifierParameters, SuccinctReceipt, SuccinctReceiptVerifierParameters,
+    SystemState, VerifierContext, VERSION,
+};
@@ -20,7 +20,10 @@ use core::fmt::{Debug, Display, Formatter};
 
 use bytemuck::{Pod, PodCastError, Zeroable};
 use hex::{FromHex, FromHexError};
-use serde::{Deserialize, Serialize};
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
 
 pub use crate::digest;
 pub use hex_literal::hex;
@@ -39,12 +42,18 @@ pub const DIGEST_BYTES: usize = DIGEST_WORDS * WORD_SIZE;
 /// (for example Poseidon's output is actually composed of field elements).  The
 /// storage is in u32's in part to simplify alignment requirements, especially
 /// in the zkVM.
-#[derive(
-    Copy, Clone, Eq, Ord, PartialOrd, PartialEq, Hash, Pod, Zeroable, Serialize, Deserialize,
-)]
+///
+/// A digest is serialized as its 8 words. Human-readable formats such as JSON also accept a hex
+/// string when deserializing a digest.
+#[derive(Copy, Clone, Eq, Ord, PartialOrd, PartialEq, Hash, Pod, Zeroable, Serialize)]
 #[repr(transparent)]
 pub struct Digest([u32; DIGEST_WORDS]);
 
+/// The encoding of a [Digest] in binary formats.
+#[derive(Deserialize)]
+#[serde(rename = "Digest")]
+struct DigestWords([u32; DIGEST_WORDS]);
+
 impl Digest {
     /// Digest of all zeroes.
     pub const ZERO: Self = Self::new([0u32; DIGEST_WORDS]);
@@ -252,6 +261,48 @@ impl Debug for Digest {
     }
 }
 
+impl<'de> Deserialize<'de> for Digest {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(DigestVisitor)
+        } else {
+            Ok(Self(DigestWords::deserialize(deserializer)?.0))
+        }
+    }
+}
+
+/// Accepts a sequence of 8 words, or a hex string.
+struct DigestVisitor;
+
+impl<'de> Visitor<'de> for DigestVisitor {
+    type Value = Digest;
+
+    fn expecting(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.write_str("a digest as 8 words or a hex string")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Digest, D::Error> {
+        Ok(Digest(<[u32; DIGEST_WORDS]>::deserialize(deserializer)?))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Digest, E> {
+        Digest::from_hex(value).map_err(E::custom)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Digest, A::Error> {
+        let mut words = [0u32; DIGEST_WORDS];
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        Ok(Digest(words))
+    }
+}
+
 /// Macro for constructing a Digest from a hex string.
 #[macro_export]
 macro_rules! digest {
@@ -290,4 +341,16 @@ mod tests {
         const HEX: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
         assert_eq!(hex::encode(Digest::from_hex(HEX).unwrap()), HEX);
     }
+
+    #[test]
+    fn test_json() {
+        const HEX: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let digest = Digest::from_hex(HEX).unwrap();
+        let json = serde_json::to_string(&digest).unwrap();
+        assert_eq!(json, serde_json::to_string(digest.as_words()).unwrap());
+        assert_eq!(serde_json::from_str::<Digest>(&json).unwrap(), digest);
+
+        let hex = alloc::format!("\"{HEX}\"");
+        assert_eq!(serde_json::from_str::<Digest>(&hex).unwrap(), digest);
+    }
 }
@@ -89,6 +89,8 @@ impl<F: Field> Clone for HashSuite<F> {
 
 /// Construct a supported hash function given its name. Returns None is the name does not
 /// correspond to a supported hash function.
+///
+/// Hash suites added with [register_hash_suite] are supported in addition to the built-in ones.
 pub fn hash_suite_from_name(name: impl AsRef<str>) -> Option<HashSuite<BabyBear>> {
     match name.as_ref() {
         "sha-256" => Some(sha::Sha256HashSuite::new_suite()),
@@ -96,6 +98,91 @@ pub fn hash_suite_from_name(name: impl AsRef<str>) -> Option<HashSuite<BabyBear>
         "blake2b" => Some(blake2b::Blake2bCpuHashSuite::new_suite()),
         #[cfg(feature = "prove")]
         "poseidon_254" => Some(poseidon_254::Poseidon254HashSuite::new_suite()),
+        #[cfg(feature = "std")]
+        name => registered_hash_suites()
+            .read()
+            .unwrap()
+            .get(name)
+            .map(|new_suite| new_suite()),
+        #[cfg(not(feature = "std"))]
         _ => None,
     }
 }
+
+/// Names of the hash suites that are built into this crate, which cannot be registered again.
+#[cfg(feature = "std")]
+const BUILTIN_HASH_SUITES: &[&str] = &["sha-256", "poseidon2", "blake2b", "poseidon_254"];
+
+#[cfg(feature = "std")]
+type HashSuiteRegistry =
+    std::sync::RwLock<alloc::collections::BTreeMap<String, fn() -> HashSuite<BabyBear>>>;
+
+#[cfg(feature = "std")]
+fn registered_hash_suites() -> &'static HashSuiteRegistry {
+    static REGISTRY: HashSuiteRegistry =
+        std::sync::RwLock::new(alloc::collections::BTreeMap::new());
+    &REGISTRY
+}
+
+/// Register an additional hash suite, e.g. one based on Blake3, under the given name.
+///
+/// Once registered, the suite is returned by [hash_suite_from_name] and included in
+/// [registered_hash_suite_names], so provers can commit with it. Registration does not make
+/// verifiers accept the suite, which must be added to each verifier context that should accept
+/// it. Returns an error if `name` is a built-in suite. Registering a name again replaces the
+/// earlier constructor.
+#[cfg(feature = "std")]
+pub fn register_hash_suite(
+    name: &str,
+    new_suite: fn() -> HashSuite<BabyBear>,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !BUILTIN_HASH_SUITES.contains(&name),
+        "cannot replace the built-in hash suite {name}"
+    );
+    registered_hash_suites()
+        .write()
+        .unwrap()
+        .insert(name.into(), new_suite);
+    Ok(())
+}
+
+/// Return the names of the hash suites added with [register_hash_suite].
+#[cfg(feature = "std")]
+pub fn registered_hash_suite_names() -> alloc::vec::Vec<String> {
+    registered_hash_suites()
+        .read()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+    use super::{
+        hash_suite_from_name, register_hash_suite, registered_hash_suite_names,
+        sha::Sha256HashSuite, HashSuite,
+    };
+    use crate::field::baby_bear::BabyBear;
+
+    fn renamed_sha256() -> HashSuite<BabyBear> {
+        HashSuite {
+            name: "renamed-sha-256".into(),
+            ..Sha256HashSuite::new_suite()
+        }
+    }
+
+    #[test]
+    fn register() {
+        assert!(hash_suite_from_name("renamed-sha-256").is_none());
+        register_hash_suite("renamed-sha-256", renamed_sha256).unwrap();
+        let suite = hash_suite_from_name("renamed-sha-256").unwrap();
+        assert_eq!(suite.name, "renamed-sha-256");
+        assert!(registered_hash_suite_names().contains(&"renamed-sha-256".to_string()));
+
+        assert!(register_hash_suite("poseidon2", renamed_sha256).is_err());
+        assert_eq!(hash_suite_from_name("poseidon2").unwrap().name, "poseidon2");
+    }
+}
@@ -18,6 +18,7 @@ pub mod cpu;
 pub mod guest;
 mod rng;
 pub mod rust_crypto;
+pub mod stream;
 
 // Pick the appropriate implementation of SHA-256 depending on whether we are
 // in the zkVM guest.
@@ -360,7 +361,7 @@ pub mod testutil {
 
     use super::{
         rust_crypto::{self, Digest as _},
-        Sha256,
+        stream, Sha256,
     };
     use crate::core::digest::Digest;
 
@@ -369,6 +370,7 @@ pub mod testutil {
     pub fn test_sha_impl<S: Sha256>() {
         test_hash_pair::<S>();
         test_rust_crypto_wrapper::<S>();
+        test_stream::<S>();
         test_hash_raw_data_slice::<S>();
         test_sha_basics::<S>();
         test_elems::<S>();
@@ -436,6 +438,28 @@ pub mod testutil {
         );
     }
 
+    fn test_stream<S: Sha256>() {
+        let message: Vec<u8> = (0..300u32).map(|i| (i * 7) as u8).collect();
+        for len in [0, 1, 55, 56, 63, 64, 65, 119, 120, 128, 299] {
+            // Hash from an odd offset so that the blocks are not word-aligned.
+            let data = &message[1..len + 1];
+            let expected = *S::hash_bytes(data);
+            for chunk_size in [1, 7, 64, 65, 300] {
+                let mut hasher = stream::Sha256Stream::<S>::new();
+                for chunk in data.chunks(chunk_size) {
+                    // Hand off the state between every chunk.
+                    hasher = stream::Sha256Stream::resume(hasher.midstate());
+                    hasher.update(chunk);
+                }
+                assert_eq!(
+                    hasher.finalize(),
+                    expected,
+                    "len: {len}, chunk: {chunk_size}"
+                );
+            }
+        }
+    }
+
     fn hash_elems<S: Sha256>(len: usize) -> Digest {
         let items: Vec<BabyBearElem> = (0..len as u32).map(BabyBearElem::new).collect();
         *S::hash_raw_data_slice(items.as_slice())
@@ -126,9 +126,8 @@ impl<S: super::Sha256> VariableOutputCore for Sha256VarCore<S> {
 
     #[inline]
     fn finalize_variable_core(&mut self, buffer: &mut Buffer<Self>, out: &mut Output<Self>) {
-        let bit_len =
-            8 * (u32::try_from(buffer.get_pos()).unwrap() + (BLOCK_BYTES as u32) * self.block_len);
-        buffer.len64_padding_be(bit_len as u64, |block| {
+        let bit_len = 8 * (buffer.get_pos() as u64 + (BLOCK_BYTES as u64) * self.block_len as u64);
+        buffer.len64_padding_be(bit_len, |block| {
             // If aligned, reinterpret the u8 array blocks as a u32 array block.
             // If unaligned, the data needs to be copied.
             let current_state = self.state.as_deref().unwrap_or(&SHA256_INIT);
@@ -0,0 +1,165 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Incremental SHA-256 hashing with a resumable state.
+//!
+//! A [Sha256Stream] hashes a message that is supplied in chunks, compressing each block as soon
+//! as it is complete, so that the message never needs to be held in memory at once. In the zkVM
+//! guest, each call to [Sha256Stream::update] passes the current state into the SHA-256
+//! accelerator and takes the new state back out.
+//!
+//! The state of a stream can be saved as a [Sha256Midstate] at any point and used to resume the
+//! hash later, e.g. to hash a stream that is read over several executions of a guest.
+//!
+//! ```rust
+//! use risc0_zkp::core::hash::sha::{cpu, stream::Sha256Stream, Sha256};
+//!
+//! let mut stream = Sha256Stream::<cpu::Impl>::new();
+//! stream.update(b"hello ");
+//! let midstate = stream.midstate();
+//!
+//! let mut stream = Sha256Stream::<cpu::Impl>::resume(midstate);
+//! stream.update(b"world");
+//! assert_eq!(stream.finalize(), *cpu::Impl::hash_bytes(b"hello world"));
+//! ```
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Block, Sha256, BLOCK_BYTES, SHA256_INIT};
+use crate::core::digest::Digest;
+
+/// The saved state of a [Sha256Stream].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sha256Midstate {
+    /// The SHA-256 state after compressing every complete block of the message so far.
+    pub state: Digest,
+
+    /// The number of bytes of the message hashed so far, including the pending bytes.
+    pub len: u64,
+
+    /// The bytes of the message after the last complete block, which is fewer than
+    /// [BLOCK_BYTES].
+    pub pending: Vec<u8>,
+}
+
+/// A SHA-256 hasher that consumes its message incrementally.
+pub struct Sha256Stream<S: Sha256> {
+    state: Digest,
+    len: u64,
+    buffer: Block,
+    phantom: PhantomData<S>,
+}
+
+impl<S: Sha256> Default for Sha256Stream<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Sha256> Sha256Stream<S> {
+    /// Start hashing a new message.
+    pub fn new() -> Self {
+        Self {
+            state: SHA256_INIT,
+            len: 0,
+            buffer: Block::default(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Continue hashing a message from a state saved with [Sha256Stream::midstate].
+    ///
+    /// Panics if the midstate is inconsistent, i.e. if the number of pending bytes does not
+    /// match the length of the message.
+    pub fn resume(midstate: Sha256Midstate) -> Self {
+        assert_eq!(
+            midstate.pending.len(),
+            (midstate.len % BLOCK_BYTES as u64) as usize,
+            "inconsistent SHA-256 midstate"
+        );
+        let mut buffer = Block::default();
+        buffer.as_mut_bytes()[..midstate.pending.len()].copy_from_slice(&midstate.pending);
+        Self {
+            state: midstate.state,
+            len: midstate.len,
+            buffer,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Save the current state, so that hashing can be resumed with [Sha256Stream::resume].
+    pub fn midstate(&self) -> Sha256Midstate {
+        Sha256Midstate {
+            state: self.state,
+            len: self.len,
+            pending: self.buffer.as_bytes()[..self.pending_len()].to_vec(),
+        }
+    }
+
+    /// Append `data` to the message.
+    pub fn update(&mut self, mut data: &[u8]) {
+        let pending_len = self.pending_len();
+        self.len += data.len() as u64;
+
+        if pending_len > 0 {
+            let take = data.len().min(BLOCK_BYTES - pending_len);
+            self.buffer.as_mut_bytes()[pending_len..pending_len + take]
+                .copy_from_slice(&data[..take]);
+            data = &data[take..];
+            if pending_len + take < BLOCK_BYTES {
+                return;
+            }
+            self.state = *S::compress_slice(&self.state, core::slice::from_ref(&self.buffer));
+        }
+
+        let (blocks, rest) = data.split_at(data.len() - data.len() % BLOCK_BYTES);
+        if !blocks.is_empty() {
+            // Compress the blocks in place if they are word-aligned, and one at a time
+            // otherwise, so that an unaligned message is never copied as a whole.
+            match bytemuck::try_cast_slice::<u8, Block>(blocks) {
+                Ok(blocks) => self.state = *S::compress_slice(&self.state, blocks),
+                Err(_) => {
+                    for block in blocks.chunks_exact(BLOCK_BYTES) {
+                        let block: Block = bytemuck::pod_read_unaligned(block);
+                        self.state = *S::compress_slice(&self.state, &[block]);
+                    }
+                }
+            }
+        }
+        self.buffer.as_mut_bytes()[..rest.len()].copy_from_slice(rest);
+    }
+
+    /// Pad the message as specified in FIPS 180-4 and return its hash.
+    pub fn finalize(mut self) -> Digest {
+        let pending_len = self.pending_len();
+        let bit_len = self.len.wrapping_mul(8);
+
+        let bytes = self.buffer.as_mut_bytes();
+        bytes[pending_len] = 0x80;
+        bytes[pending_len + 1..].fill(0);
+        if pending_len + 1 > BLOCK_BYTES - 8 {
+            self.state = *S::compress_slice(&self.state, core::slice::from_ref(&self.buffer));
+            self.buffer = Block::default();
+        }
+        self.buffer.as_mut_bytes()[BLOCK_BYTES - 8..].copy_from_slice(&bit_len.to_be_bytes());
+        *S::compress_slice(&self.state, core::slice::from_ref(&self.buffer))
+    }
+
+    fn pending_len(&self) -> usize {
+        (self.len % BLOCK_BYTES as u64) as usize
+    }
+}
@@ -17,8 +17,9 @@
 mod fri;
 mod merkle;
 mod read_iop;
+pub mod seal;
 
-use alloc::{vec, vec::Vec};
+use alloc::{string::String, vec, vec::Vec};
 use core::{cell::RefCell, fmt, iter::zip};
 
 pub(crate) use merkle::MerkleTreeVerifier;
@@ -54,7 +55,9 @@ pub enum VerificationError {
         received: Digest,
     },
     UnexpectedExitCode,
-    InvalidHashSuite,
+    UnknownHashSuite {
+        name: String,
+    },
     VerifierParametersMissing,
     VerifierParametersMismatch {
         expected: Digest,
@@ -71,6 +74,9 @@ pub enum VerificationError {
     UnresolvedAssumption {
         digest: Digest,
     },
+    ClaimPredicateFailed {
+        predicate: String,
+    },
 }
 
 impl fmt::Debug for VerificationError {
@@ -99,7 +105,9 @@ impl fmt::Display for VerificationError {
                 write!(f, "claim digest does not match the expected digest {received}; expected {expected}")
             }
             VerificationError::UnexpectedExitCode => write!(f, "unexpected exit_code"),
-            VerificationError::InvalidHashSuite => write!(f, "invalid hash suite"),
+            VerificationError::UnknownHashSuite { name } => {
+                write!(f, "hash suite {name} is not known to the verifier context")
+            }
             VerificationError::VerifierParametersMissing => {
                 write!(f, "verifier parameters were not found in verifier context for the given receipt type")
             }
@@ -115,6 +123,9 @@ impl fmt::Display for VerificationError {
             VerificationError::UnresolvedAssumption { digest } => {
                 write!(f, "receipt contains an unresolved assumption: {digest}")
             }
+            VerificationError::ClaimPredicateFailed { predicate } => {
+                write!(f, "receipt claim does not satisfy {predicate}")
+            }
         }
     }
 }
@@ -0,0 +1,285 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed access to the structure of a STARK seal.
+//!
+//! A seal is a flat sequence of words, written by the prover in the order that the verifier reads
+//! them. The layout depends only on the circuit and the `po2` encoded at the start of the seal:
+//!
+//! 1. The globals of the circuit (`OUTPUT_SIZE` field elements), followed by `po2`.
+//! 2. The top rows of the Merkle trees committing to the code, data, accum, and check groups,
+//!    in that order. Each tree has `INV_RATE << po2` leaves.
+//! 3. The coefficients of the polynomials interpolating the taps and the check group, as
+//!    extension field elements.
+//! 4. The top row of the Merkle tree committing to each FRI layer, folding the domain by
+//!    `FRI_FOLD` each time until the degree reaches 256.
+//! 5. The coefficients of the final FRI polynomial, as field elements, one block per component
+//!    of the extension field.
+//! 6. [QUERIES] query openings. Each opens a row of the accum, code, data, and check trees,
+//!    followed by one row of each FRI layer tree. An opening is the row of field elements,
+//!    followed by the sibling hashes on the path from the leaf up to the top row of the tree.
+//!
+//! The query positions and all other challenges are drawn from the Fiat-Shamir transcript, and
+//! so are not part of the seal. [Reader] only splits the seal into its parts; it does not check
+//! any hashes, and a seal that parses successfully may still be invalid.
+
+use alloc::vec::Vec;
+
+use risc0_core::field::{Elem, ExtElem, Field};
+
+use crate::{
+    adapter::{CircuitCoreDef, REGISTER_GROUP_ACCUM, REGISTER_GROUP_CODE, REGISTER_GROUP_DATA},
+    core::digest::{Digest, DIGEST_WORDS},
+    merkle::MerkleTreeParams,
+    verify::VerificationError,
+    FRI_FOLD, FRI_MIN_DEGREE, INV_RATE, MAX_CYCLES_PO2, QUERIES,
+};
+
+/// The top row of a Merkle tree committed to by the seal.
+///
+/// The verifier hashes the top row up to the root once, and checks each query opening against
+/// the top row rather than the root.
+#[derive(Clone, Copy, Debug)]
+pub struct MerkleCommitment<'a> {
+    /// The number of leaves in the tree.
+    pub rows: usize,
+
+    /// The number of field elements in each leaf.
+    pub cols: usize,
+
+    /// The hashes of the top row of the tree.
+    pub top: &'a [Digest],
+}
+
+/// An opening of one leaf of a Merkle tree.
+#[derive(Clone, Copy, Debug)]
+pub struct MerkleOpening<'a, F: Field> {
+    /// The field elements of the leaf.
+    pub row: &'a [F::Elem],
+
+    /// The sibling hashes from the leaf up to the top row of the tree.
+    pub branch: &'a [Digest],
+}
+
+/// The openings for a single FRI query.
+#[derive(Clone, Debug)]
+pub struct Query<'a, F: Field> {
+    /// The opening of the accum group.
+    pub accum: MerkleOpening<'a, F>,
+
+    /// The opening of the code group.
+    pub code: MerkleOpening<'a, F>,
+
+    /// The opening of the data group.
+    pub data: MerkleOpening<'a, F>,
+
+    /// The opening of the check group.
+    pub check: MerkleOpening<'a, F>,
+
+    /// The openings of each FRI layer.
+    pub fri: Vec<MerkleOpening<'a, F>>,
+}
+
+/// A parsed view of a seal for a given circuit.
+pub struct Reader<'a, F: Field> {
+    globals: &'a [F::Elem],
+    po2: u32,
+    code: MerkleCommitment<'a>,
+    data: MerkleCommitment<'a>,
+    accum: MerkleCommitment<'a>,
+    check: MerkleCommitment<'a>,
+    coeffs: &'a [F::ExtElem],
+    fri_layers: Vec<MerkleCommitment<'a>>,
+    fri_final_coeffs: &'a [F::Elem],
+    queries: Vec<Query<'a, F>>,
+}
+
+impl<'a, F: Field> Reader<'a, F> {
+    /// Split `seal` into its parts, following the layout for `circuit`.
+    ///
+    /// Returns [VerificationError::ReceiptFormatError] if the seal is too short, too long, or
+    /// encodes an invalid `po2`.
+    pub fn new<C: CircuitCoreDef<F>>(
+        circuit: &C,
+        seal: &'a [u32],
+    ) -> Result<Self, VerificationError> {
+        let taps = circuit.get_taps();
+        let mut cursor = Cursor { seal };
+
+        let globals: &[F::Elem] = cursor.elems(C::OUTPUT_SIZE + 1)?;
+        let (globals, [po2]) = globals.split_at(C::OUTPUT_SIZE) else {
+            unreachable!()
+        };
+        let po2 = match po2.to_u32_words()[..] {
+            [po2] if po2 as usize <= MAX_CYCLES_PO2 => po2,
+            _ => return Err(VerificationError::ReceiptFormatError),
+        };
+        let size = 1usize << po2;
+        let domain = INV_RATE * size;
+        let check_size = INV_RATE * F::ExtElem::EXT_SIZE;
+
+        let code = cursor.commitment(domain, taps.group_size(REGISTER_GROUP_CODE))?;
+        let data = cursor.commitment(domain, taps.group_size(REGISTER_GROUP_DATA))?;
+        let accum = cursor.commitment(domain, taps.group_size(REGISTER_GROUP_ACCUM))?;
+        let check = cursor.commitment(domain, check_size)?;
+        let coeffs = cursor.elems(taps.tap_size() + check_size)?;
+
+        let mut fri_layers = Vec::new();
+        let mut fri_domain = domain;
+        let mut degree = size;
+        while degree > FRI_MIN_DEGREE {
+            fri_domain /= FRI_FOLD;
+            degree /= FRI_FOLD;
+            fri_layers.push(cursor.commitment(fri_domain, FRI_FOLD * F::ExtElem::EXT_SIZE)?);
+        }
+        let fri_final_coeffs = cursor.elems(F::ExtElem::EXT_SIZE * degree)?;
+
+        let mut queries = Vec::with_capacity(QUERIES);
+        for _ in 0..QUERIES {
+            queries.push(Query {
+                accum: cursor.opening(&accum)?,
+                code: cursor.opening(&code)?,
+                data: cursor.opening(&data)?,
+                check: cursor.opening(&check)?,
+                fri: fri_layers
+                    .iter()
+                    .map(|layer| cursor.opening(layer))
+                    .collect::<Result<_, _>>()?,
+            });
+        }
+        if !cursor.seal.is_empty() {
+            return Err(VerificationError::ReceiptFormatError);
+        }
+
+        Ok(Self {
+            globals,
+            po2,
+            code,
+            data,
+            accum,
+            check,
+            coeffs,
+            fri_layers,
+            fri_final_coeffs,
+            queries,
+        })
+    }
+
+    /// The globals of the circuit, i.e. the public outputs of the proof.
+    pub fn globals(&self) -> &'a [F::Elem] {
+        self.globals
+    }
+
+    /// The base 2 logarithm of the number of cycles in the trace.
+    pub fn po2(&self) -> u32 {
+        self.po2
+    }
+
+    /// The commitment to the code group, whose root is checked against the control IDs.
+    pub fn code(&self) -> &MerkleCommitment<'a> {
+        &self.code
+    }
+
+    /// The commitment to the data group.
+    pub fn data(&self) -> &MerkleCommitment<'a> {
+        &self.data
+    }
+
+    /// The commitment to the accum group.
+    pub fn accum(&self) -> &MerkleCommitment<'a> {
+        &self.accum
+    }
+
+    /// The commitment to the check group, i.e. the constraint quotient polynomial.
+    pub fn check(&self) -> &MerkleCommitment<'a> {
+        &self.check
+    }
+
+    /// The coefficients of the polynomials interpolating each tap, followed by those of the
+    /// check group.
+    pub fn coeffs(&self) -> &'a [F::ExtElem] {
+        self.coeffs
+    }
+
+    /// The commitments to each FRI layer, from the largest domain to the smallest.
+    pub fn fri_layers(&self) -> &[MerkleCommitment<'a>] {
+        &self.fri_layers
+    }
+
+    /// The coefficients of the final FRI polynomial.
+    pub fn fri_final_coeffs(&self) -> &'a [F::Elem] {
+        self.fri_final_coeffs
+    }
+
+    /// The openings for each query.
+    pub fn queries(&self) -> &[Query<'a, F>] {
+        &self.queries
+    }
+}
+
+struct Cursor<'a> {
+    seal: &'a [u32],
+}
+
+impl<'a> Cursor<'a> {
+    fn words(&mut self, n: usize) -> Result<&'a [u32], VerificationError> {
+        if n > self.seal.len() {
+            return Err(VerificationError::ReceiptFormatError);
+        }
+        let words;
+        (words, self.seal) = self.seal.split_at(n);
+        Ok(words)
+    }
+
+    fn elems<T: Elem>(&mut self, n: usize) -> Result<&'a [T], VerificationError> {
+        Ok(T::from_u32_slice(self.words(n * T::WORDS)?))
+    }
+
+    fn digests(&mut self, n: usize) -> Result<&'a [Digest], VerificationError> {
+        Ok(bytemuck::cast_slice(self.words(n * DIGEST_WORDS)?))
+    }
+
+    fn commitment(
+        &mut self,
+        rows: usize,
+        cols: usize,
+    ) -> Result<MerkleCommitment<'a>, VerificationError> {
+        let params = MerkleTreeParams::new(rows, cols, QUERIES);
+        Ok(MerkleCommitment {
+            rows,
+            cols,
+            top: self.digests(params.top_size)?,
+        })
+    }
+
+    fn opening<F: Field>(
+        &mut self,
+        tree: &MerkleCommitment<'a>,
+    ) -> Result<MerkleOpening<'a, F>, VerificationError> {
+        let row = self.elems(tree.cols)?;
+        // The branch ascends from the leaves, which have virtual indexes [rows, 2 * rows), to
+        // the top row, which has virtual indexes [top_size, 2 * top_size).
+        let top_size = tree.top.len();
+        let mut idx = tree.rows;
+        let mut depth = 0;
+        while idx >= 2 * top_size {
+            idx /= 2;
+            depth += 1;
+        }
+        Ok(MerkleOpening {
+            row,
+            branch: self.digests(depth)?,
+        })
+    }
+}
@@ -0,0 +1,213 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `hugepages` measures the data TLB misses of executing guests that touch many pages, with the
+//! executor's copy of guest memory on the heap and backed by hugepages. See
+//! [ExecutorEnvBuilder::hugepages][risc0_zkvm::ExecutorEnvBuilder::hugepages].
+//!
+//! Misses are counted with a Linux perf counter, which requires a hardware PMU and
+//! `perf_event_paranoid` to be 2 or less. The benchmark is skipped otherwise.
+
+#[cfg(target_os = "linux")]
+mod dtlb {
+    use std::{fs::File, io::Read, os::fd::FromRawFd, sync::Arc};
+
+    use criterion::{
+        measurement::{Measurement, ValueFormatter},
+        BenchmarkId, Criterion, SamplingMode, Throughput,
+    };
+    use risc0_zkvm::{ExecutorEnv, ExecutorImpl};
+    use risc0_zkvm_methods::{
+        bench::{BenchmarkSpec, SpecWithIters},
+        BENCH_ELF,
+    };
+
+    /// The first version of `struct perf_event_attr`, which every kernel accepts.
+    #[repr(C)]
+    #[derive(Default)]
+    struct PerfEventAttr {
+        kind: u32,
+        size: u32,
+        config: u64,
+        sample_period: u64,
+        sample_type: u64,
+        read_format: u64,
+        flags: u64,
+        wakeup_events: u32,
+        bp_type: u32,
+        config1: u64,
+    }
+
+    const PERF_TYPE_HW_CACHE: u32 = 3;
+    const PERF_COUNT_HW_CACHE_DTLB: u64 = 3;
+    const PERF_COUNT_HW_CACHE_OP_READ: u64 = 0;
+    const PERF_COUNT_HW_CACHE_RESULT_MISS: u64 = 1;
+    const FLAG_INHERIT: u64 = 1 << 1;
+    const FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+    const FLAG_EXCLUDE_HV: u64 = 1 << 6;
+
+    /// A [Measurement] of the data TLB read misses of this process in user space.
+    #[derive(Clone)]
+    pub struct DtlbMisses(Arc<File>);
+
+    impl DtlbMisses {
+        /// Open a counter, or return `None` if the kernel does not allow it.
+        pub fn open() -> Option<Self> {
+            let attr = PerfEventAttr {
+                kind: PERF_TYPE_HW_CACHE,
+                size: std::mem::size_of::<PerfEventAttr>() as u32,
+                config: PERF_COUNT_HW_CACHE_DTLB
+                    | (PERF_COUNT_HW_CACHE_OP_READ << 8)
+                    | (PERF_COUNT_HW_CACHE_RESULT_MISS << 16),
+                flags: FLAG_INHERIT | FLAG_EXCLUDE_KERNEL | FLAG_EXCLUDE_HV,
+                ..Default::default()
+            };
+            // Count this thread, and the threads it starts, on any CPU.
+            let fd = unsafe {
+                libc::syscall(
+                    libc::SYS_perf_event_open,
+                    &attr as *const PerfEventAttr,
+                    0,
+                    -1,
+                    -1,
+                    0,
+                )
+            };
+            if fd < 0 {
+                return None;
+            }
+            // SAFETY: the kernel returned a new file descriptor that nothing else owns.
+            Some(Self(Arc::new(unsafe { File::from_raw_fd(fd as i32) })))
+        }
+
+        fn read(&self) -> u64 {
+            let mut buf = [0; 8];
+            (&*self.0).read_exact(&mut buf).unwrap();
+            u64::from_ne_bytes(buf)
+        }
+    }
+
+    impl Measurement for DtlbMisses {
+        type Intermediate = u64;
+        type Value = u64;
+
+        fn start(&self) -> u64 {
+            self.read()
+        }
+
+        fn end(&self, start: u64) -> u64 {
+            self.read() - start
+        }
+
+        fn add(&self, v1: &u64, v2: &u64) -> u64 {
+            v1 + v2
+        }
+
+        fn zero(&self) -> u64 {
+            0
+        }
+
+        fn to_f64(&self, value: &u64) -> f64 {
+            *value as f64
+        }
+
+        fn formatter(&self) -> &dyn ValueFormatter {
+            &MissFormatter
+        }
+    }
+
+    struct MissFormatter;
+
+    impl ValueFormatter for MissFormatter {
+        fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+            "misses"
+        }
+
+        fn scale_throughputs(
+            &self,
+            _typical_value: f64,
+            throughput: &Throughput,
+            values: &mut [f64],
+        ) -> &'static str {
+            match throughput {
+                Throughput::Bytes(bytes) | Throughput::BytesDecimal(bytes) => {
+                    for value in values {
+                        *value /= *bytes as f64;
+                    }
+                    "misses/byte"
+                }
+                Throughput::Elements(elems) => {
+                    for value in values {
+                        *value /= *elems as f64;
+                    }
+                    "misses/element"
+                }
+            }
+        }
+
+        fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+            "misses"
+        }
+    }
+
+    fn run_guest(counter: &DtlbMisses, spec: SpecWithIters, hugepages: bool) -> u64 {
+        let env = ExecutorEnv::builder()
+            .write(&spec)
+            .unwrap()
+            .hugepages(hugepages)
+            .build()
+            .unwrap();
+        let mut exec = ExecutorImpl::from_elf(env, BENCH_ELF).unwrap();
+
+        let start = counter.start();
+        exec.run().unwrap();
+        counter.end(start)
+    }
+
+    pub fn bench(c: &mut Criterion<DtlbMisses>, counter: &DtlbMisses) {
+        let mut group = c.benchmark_group("dtlb_misses");
+        group.sampling_mode(SamplingMode::Flat).sample_size(10);
+        for len in [1 << 20, 16 << 20] {
+            group.throughput(Throughput::Bytes(len as u64));
+            for hugepages in [false, true] {
+                let name = if hugepages { "hugepages" } else { "heap" };
+                group.bench_function(BenchmarkId::new(name, len), |b| {
+                    b.iter_custom(|iters| {
+                        let spec = SpecWithIters(BenchmarkSpec::Memset { len }, iters);
+                        run_guest(counter, spec, hugepages)
+                    })
+                });
+            }
+        }
+        group.finish();
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn main() {
+    let Some(counter) = dtlb::DtlbMisses::open() else {
+        eprintln!("Skipping: data TLB misses cannot be counted on this host");
+        return;
+    };
+    let mut c = criterion::Criterion::default()
+        .with_measurement(counter.clone())
+        .configure_from_args();
+    dtlb::bench(&mut c, &counter);
+    c.final_summary();
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() {
+    eprintln!("Skipping: data TLB misses can only be counted on Linux");
+}
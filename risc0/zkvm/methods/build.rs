@@ -42,6 +42,7 @@ fn main() {
             GuestOptions {
                 features: vec![],
                 use_docker,
+                variants: vec![],
             },
         ),
         (
@@ -49,6 +50,7 @@ fn main() {
             GuestOptions {
                 features: vec!["test_feature1".to_string(), "test_feature2".to_string()],
                 use_docker: None,
+                variants: vec![],
             },
         ),
         (
@@ -56,6 +58,7 @@ fn main() {
             GuestOptions {
                 features: vec![],
                 use_docker: None,
+                variants: vec![],
             },
         ),
     ]);
@@ -28,9 +28,10 @@ use core::arch::asm;
 use getrandom::getrandom;
 use risc0_zkp::core::hash::sha::testutil::test_sha_impl;
 use risc0_zkvm::{
+    authenticated::AuthMap,
     guest::{
         env::{self, FdReader, FdWriter, Read as _, Write as _},
-        memory_barrier, sha,
+        fixed, fp, memory_barrier, sha,
     },
     sha::{Digest, Sha256},
     Assumption, ReceiptClaim,
@@ -40,8 +41,9 @@ use risc0_zkvm_platform::{
     fileno,
     memory::{self, SYSTEM},
     syscall::{
-        bigint, sys_bigint, sys_execute_zkr, sys_exit, sys_fork, sys_log, sys_pipe, sys_read,
-        sys_read_words, sys_write,
+        bigint, sys_bigint, sys_close, sys_dup, sys_execute_zkr, sys_exit, sys_fork, sys_log,
+        sys_open, sys_panic_at, sys_pipe, sys_read, sys_read_exact, sys_read_words, sys_readv,
+        sys_write, sys_writev, IoVec,
     },
     PAGE_SIZE,
 };
@@ -60,6 +62,21 @@ fn profile_test_func2() {
     unsafe { asm!("nop") }
 }
 
+risc0_zkvm::memoize! {
+    fn iterated_sha(n: u32) -> Digest {
+        (0..n).fold(Digest::ZERO, |digest, _| *sha::Impl::hash_bytes(digest.as_bytes()))
+    }
+}
+
+#[inline(never)]
+fn recurse(depth: u32) -> u8 {
+    let frame = core::hint::black_box([depth as u8; 1024]);
+    if depth == 0 {
+        return frame[0];
+    }
+    recurse(depth - 1).wrapping_add(frame[1023])
+}
+
 fn main() {
     let impl_select: MultiTestSpec = env::read();
     match impl_select {
@@ -107,6 +124,10 @@ fn main() {
         MultiTestSpec::Panic => {
             panic!("MultiTestSpec::Panic invoked");
         }
+        MultiTestSpec::PanicAt => unsafe {
+            const FILE: &str = "src/bin/multi_test.rs";
+            sys_panic_at(FILE.as_ptr(), FILE.len(), 12, 34)
+        },
         MultiTestSpec::Fault => unsafe {
             asm!("sw x0, 1(x0)");
         },
@@ -192,11 +213,29 @@ fn main() {
             }
             env::commit(&(buf, num_read));
         }
+        MultiTestSpec::SysReadExact {
+            mut buf,
+            fd,
+            pos_and_len,
+        } => {
+            let mut num_read = alloc::vec::Vec::with_capacity(pos_and_len.len());
+            for (pos, len) in pos_and_len {
+                let n =
+                    unsafe { sys_read_exact(fd, buf.as_mut_ptr().add(pos as usize), len as usize) };
+                num_read.push(n);
+                assert!(n <= len as usize);
+            }
+            env::commit(&(buf, num_read));
+        }
         MultiTestSpec::SysVerify(pairs) => {
             for (image_id, journal) in pairs.into_iter() {
                 env::verify(image_id, &journal).unwrap();
             }
         }
+        MultiTestSpec::CommitVerified { image_id, journal } => {
+            env::verify(image_id, &journal).unwrap();
+            env::commit_slice(&journal);
+        }
         MultiTestSpec::SysVerifyIntegrity { claim_words } => {
             let claim: ReceiptClaim = risc0_zkvm::serde::from_slice(&claim_words).unwrap();
             // NOTE: This panic string is used in a test.
@@ -226,6 +265,104 @@ fn main() {
             unsafe { sys_read_words(fd, buf.as_mut_ptr(), nwords) };
             env::commit_slice(&buf);
         }
+        MultiTestSpec::EchoVectored { fd, lens } => {
+            let mut bufs: alloc::vec::Vec<alloc::vec::Vec<u8>> =
+                lens.iter().map(|len| vec![0u8; *len as usize]).collect();
+            let iovs: alloc::vec::Vec<IoVec> = bufs
+                .iter_mut()
+                .map(|buf| IoVec {
+                    base: buf.as_mut_ptr(),
+                    len: buf.len(),
+                })
+                .collect();
+            let nread = unsafe { sys_readv(fd, iovs.as_ptr(), iovs.len()) };
+            let nwritten = unsafe { sys_writev(fileno::STDOUT, iovs.as_ptr(), iovs.len()) };
+            env::commit(&(nread, nwritten));
+        }
+        MultiTestSpec::CommitSlices { lens, flush } => {
+            let mut next = 0u32;
+            for len in lens {
+                let slice: alloc::vec::Vec<u8> =
+                    (next..next + len).map(|x| (x % 251) as u8).collect();
+                env::commit_slice(&slice);
+                if flush {
+                    env::flush_journal();
+                }
+                next += len;
+            }
+        }
+        MultiTestSpec::CommitFields => {
+            env::commit_field("name", b"alice");
+            env::commit_field("age", &30u32.to_le_bytes());
+            env::commit_field("balance", &1000u64.to_le_bytes());
+        }
+        MultiTestSpec::CommitDuplicateField => {
+            env::commit_field("name", b"alice");
+            env::commit_field("name", b"bob");
+        }
+        MultiTestSpec::CommitFieldAfterSlice => {
+            env::commit_slice(&[1u32]);
+            env::commit_field("name", b"alice");
+        }
+        MultiTestSpec::CommitEncrypted { data } => {
+            env::commit_encrypted(&data);
+        }
+        MultiTestSpec::Memoize { n } => {
+            let start = env::cycle_count();
+            let first = iterated_sha(n);
+            let middle = env::cycle_count();
+            let second = iterated_sha(n);
+            let end = env::cycle_count();
+            env::commit(&(first, second, middle - start, end - middle));
+        }
+        MultiTestSpec::ReadFrames => {
+            let count: u32 = env::read_frame();
+            let name: alloc::string::String = env::read_frame();
+            env::commit(&(count, name));
+        }
+        MultiTestSpec::NamedStream => {
+            assert!(env::open("missing").is_none());
+            let name = "prices";
+            let fd = unsafe { sys_open(name.as_ptr(), name.len()) };
+            let dup_fd = sys_dup(fd);
+            let closed_std = [
+                fileno::STDIN,
+                fileno::STDOUT,
+                fileno::STDERR,
+                fileno::JOURNAL,
+            ]
+            .map(sys_close);
+            let closed = [sys_close(fd), sys_close(fd)];
+            let mut buf = [0u8; 3];
+            let nread = unsafe { sys_read_exact(dup_fd, buf.as_mut_ptr(), buf.len()) };
+            env::commit(&(fd, dup_fd, closed_std, closed, buf[..nread].to_vec()));
+        }
+        MultiTestSpec::FpEnv => fp::commit_fp_env(),
+        MultiTestSpec::MulDiv { cases } => {
+            let results: alloc::vec::Vec<Option<i128>> = cases
+                .iter()
+                .map(|&(x, y, d)| fixed::mul_div(x, y, d))
+                .collect();
+            let price = fixed::Decimal::<2>::from_units(1005) * fixed::Decimal::from_units(50);
+            env::commit(&(results, format!("{price}")));
+        }
+        MultiTestSpec::AuthenticatedStore { root } => {
+            let mut accounts = AuthMap::new("accounts", root);
+            let balance = |value: Option<alloc::vec::Vec<u8>>| -> u64 {
+                u64::from_le_bytes(value.unwrap().try_into().unwrap())
+            };
+            let alice = balance(accounts.get(b"alice"));
+            let bob = balance(accounts.get(b"bob"));
+            assert_eq!(accounts.get(b"carol"), None);
+            accounts.insert(b"alice", &(alice - 30).to_le_bytes());
+            accounts.insert(b"bob", &(bob + 30).to_le_bytes());
+            accounts.remove(b"dave");
+            env::commit(&accounts.root());
+        }
+        MultiTestSpec::MemoryOutput { len } => {
+            let output: alloc::vec::Vec<u8> = (0..len).map(|i| i as u8).collect();
+            env::pause_with_memory_output(0, &output);
+        }
         MultiTestSpec::BusyLoop { cycles } => {
             let mut last_cycles = env::cycle_count();
 
@@ -254,6 +391,15 @@ fn main() {
             }
             env::commit_slice(&result);
         }
+        MultiTestSpec::BigIntOps { x, y, modulus } => {
+            use risc0_zkvm::guest::bigint;
+            let x_mod = bigint::reduce(&x, &modulus);
+            let y_mod = bigint::reduce(&y, &modulus);
+            env::commit_slice(&x_mod);
+            env::commit_slice(&bigint::modadd(&x_mod, &y_mod, &modulus));
+            env::commit_slice(&bigint::modsub(&x_mod, &y_mod, &modulus));
+            env::commit_slice(&bigint::mul_wide(&x, &y));
+        }
         MultiTestSpec::LibM => {
             use core::hint::black_box;
             let f = black_box(1.0_f32);
@@ -266,6 +412,9 @@ fn main() {
             let len = memory::SYSTEM.start() as usize;
             let _data = black_box(vec![0_u8; len]);
         }
+        MultiTestSpec::Recurse { depth } => {
+            core::hint::black_box(recurse(depth));
+        }
         MultiTestSpec::RsaCompat => {
             // This test comes from: https://github.com/RustCrypto/RSA/blob/master/tests/pkcs1v15.rs
             use risc0_zkvm::sha::rust_crypto::Sha256;
@@ -36,6 +36,8 @@ pub enum MultiTestSpec {
     EventTrace,
     Profiler,
     Panic,
+    /// Report a panic at a source location, as a guest with compact panics does.
+    PanicAt,
     Fault,
     Halt(u8),
     PauseResume(u8),
@@ -57,6 +59,13 @@ pub enum MultiTestSpec {
         // Position and length to do reads
         pos_and_len: Vec<(u32, u32)>,
     },
+    SysReadExact {
+        // Buffer to read to
+        buf: Vec<u8>,
+        fd: u32,
+        // Position and length to do reads
+        pos_and_len: Vec<(u32, u32)>,
+    },
     SysVerify(Vec<(Digest, Vec<u8>)>),
     SysVerifyIntegrity {
         // ReceiptClaim: Field is serialized to avoid circular dependency issues.
@@ -69,6 +78,11 @@ pub enum MultiTestSpec {
     Echo {
         bytes: Vec<u8>,
     },
+    // Verify a receipt for `journal`, and commit it.
+    CommitVerified {
+        image_id: Digest,
+        journal: Vec<u8>,
+    },
     EchoStdout {
         nbytes: u32,
         fd: u32,
@@ -77,17 +91,70 @@ pub enum MultiTestSpec {
         fd: u32,
         nwords: u32,
     },
+    EchoVectored {
+        fd: u32,
+        // Lengths of the buffers to read into and write from
+        lens: Vec<u32>,
+    },
+    CommitSlices {
+        // Lengths of the slices to commit, filled with a counting pattern
+        lens: Vec<u32>,
+        // Whether to flush the journal after every commit
+        flush: bool,
+    },
     BigInt {
         x: [u32; bigint::WIDTH_WORDS],
         y: [u32; bigint::WIDTH_WORDS],
         modulus: [u32; bigint::WIDTH_WORDS],
     },
+    BigIntOps {
+        x: [u32; bigint::WIDTH_WORDS],
+        y: [u32; bigint::WIDTH_WORDS],
+        modulus: [u32; bigint::WIDTH_WORDS],
+    },
+    CommitFields,
+    /// Commit two fields with the same name with `env::commit_field`.
+    CommitDuplicateField,
+    /// Commit a field with `env::commit_field` after committing other journal data.
+    CommitFieldAfterSlice,
+    /// Commit `data` encrypted to the journal recipient with `env::commit_encrypted`.
+    CommitEncrypted {
+        data: Vec<u8>,
+    },
+    /// Call a memoized function twice with the same argument, and commit both results and the
+    /// cycles taken by each call.
+    Memoize {
+        n: u32,
+    },
+    /// Read a `u32` frame and a `String` frame with `env::read_frame`, and commit both.
+    ReadFrames,
+    /// Open the named stream `"prices"`, read it through a duplicate of its descriptor, and commit
+    /// the descriptors, what the fd syscalls returned, and the bytes read.
+    NamedStream,
+    /// Commit the floating-point environment with `guest::fp::commit_fp_env`.
+    FpEnv,
+    /// Commit `guest::fixed::mul_div` of each case, and a product of decimals as a string.
+    MulDiv {
+        cases: Vec<(i128, i128, i128)>,
+    },
+    /// Move a balance between two accounts of an authenticated map, and commit its new root.
+    AuthenticatedStore {
+        root: Digest,
+    },
+    /// Pause with an output of `len` bytes left in memory.
+    MemoryOutput {
+        len: u32,
+    },
     BusyLoop {
         /// Busy loop until the guest has run for at least this number of cycles
         cycles: u64,
     },
     LibM,
     Oom,
+    /// Recurse `depth` times, with a stack frame of at least 1 KiB at each level.
+    Recurse {
+        depth: u32,
+    },
     OutOfBounds,
     OutOfBoundsEcall,
     RsaCompat,
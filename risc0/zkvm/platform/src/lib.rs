@@ -41,6 +41,9 @@ pub mod fileno {
     pub const STDOUT: u32 = 1;
     pub const STDERR: u32 = 2;
     pub const JOURNAL: u32 = 3;
+
+    /// Returned by the file descriptor syscalls to indicate failure.
+    pub const INVALID: u32 = u32::MAX;
 }
 
 /// Align address upwards.
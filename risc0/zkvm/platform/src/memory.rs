@@ -21,6 +21,9 @@ pub const GUEST_MAX_MEM: usize = SYSTEM.start;
 
 /// Top of stack; stack grows down from this location.
 pub const STACK_TOP: u32 = 0x0020_0400;
+/// The address and length of an output buffer that the guest leaves for the host to read from
+/// the post-state memory of a paused session, as two words just above the top of the stack.
+pub const MEMORY_OUTPUT: u32 = STACK_TOP;
 /// Program (text followed by data and then bss) gets loaded in
 /// starting at this location.  HEAP begins right afterwards.
 pub const TEXT_START: u32 = 0x0020_0800;
@@ -30,6 +30,7 @@ use crate::syscall::{sys_alloc_aligned, sys_panic};
 extern crate alloc;
 
 /// panic! implementation for use in no_std guest programs.
+#[cfg(not(feature = "compact-panic"))]
 #[cfg_attr(feature = "panic-handler", panic_handler)]
 pub fn panic_fault(panic_info: &PanicInfo) -> ! {
     let msg = alloc::format!("{}", panic_info);
@@ -37,6 +38,33 @@ pub fn panic_fault(panic_info: &PanicInfo) -> ! {
     unsafe { sys_panic(msg.as_ptr(), msg.len()) }
 }
 
+/// panic! implementation for use in no_std guest programs, which reports only the location of the
+/// panic.
+///
+/// The panic message is never formatted, so `core::fmt` and the panic message strings can be
+/// dropped from the binary. The file, line and column of the panic are passed to the host.
+#[cfg(feature = "compact-panic")]
+#[cfg_attr(feature = "panic-handler", panic_handler)]
+pub fn panic_fault(panic_info: &PanicInfo) -> ! {
+    match panic_info.location() {
+        Some(location) => {
+            let file = location.file();
+            unsafe {
+                crate::syscall::sys_panic_at(
+                    file.as_ptr(),
+                    file.len(),
+                    location.line(),
+                    location.column(),
+                )
+            }
+        }
+        None => {
+            const MSG: &[u8] = "Guest panicked".as_bytes();
+            unsafe { sys_panic(MSG.as_ptr(), MSG.len()) }
+        }
+    }
+}
+
 #[cfg(feature = "entrypoint")]
 mod entrypoint {
     use crate::syscall::sys_halt;
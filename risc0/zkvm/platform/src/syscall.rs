@@ -26,6 +26,7 @@ pub mod ecall {
     pub const BIGINT: u32 = 4;
     pub const USER: u32 = 5;
     pub const MACHINE: u32 = 5;
+    pub const TRAP: u32 = 6;
 }
 
 pub mod halt {
@@ -34,6 +35,34 @@ pub mod halt {
     pub const SPLIT: u32 = 2;
 }
 
+/// Operations of the [ecall::TRAP] ecall, which lets the guest handle its own traps when it is
+/// executed. The operation is passed in the low byte of `a0`.
+///
+/// Trap handling is an executor feature: a segment which uses it can be executed, but not
+/// proven.
+pub mod trap {
+    /// Register the handler at the address in `a1` for the [trap_kind][super::trap_kind] in the
+    /// second byte of `a0`, or remove the handler if the address is null.
+    ///
+    /// When a trap of that kind occurs, execution continues at the handler, with the registers
+    /// as they were at the trap. A trap within a handler terminates execution.
+    pub const SET_HANDLER: u32 = 0;
+
+    /// Within a handler, return the kind of the trap in `a0`, the address of the instruction
+    /// that trapped in `a1`, and the instruction, or the number of the unknown ecall, in `a2`.
+    pub const INFO: u32 = 1;
+
+    /// Within a handler, set the address the `mret` at the end of the handler returns to, which
+    /// is the instruction that trapped unless set, to the address in `a1`.
+    pub const SET_EPC: u32 = 2;
+}
+
+/// Kinds of trap a guest can register a handler for, with [trap::SET_HANDLER].
+pub mod trap_kind {
+    pub const ILLEGAL_INSTRUCTION: u32 = 0;
+    pub const UNKNOWN_ECALL: u32 = 1;
+}
+
 pub mod reg_abi {
     pub const REG_ZERO: usize = 0; // zero constant
     pub const REG_RA: usize = 1; // return address
@@ -83,6 +112,33 @@ pub const MAX_BUF_BYTES: usize = 4 * 1024;
 pub const MAX_BUF_WORDS: usize = MAX_BUF_BYTES / WORD_SIZE;
 pub const MAX_SHA_COMPRESS_BLOCKS: usize = 1000;
 
+/// The floating-point environment reported by `SYS_FP_ENV`, packed into a word.
+pub mod fp_env {
+    /// Mask of the rounding mode, encoded as in the `frm` field of the RISC-V F extension.
+    pub const ROUNDING_MASK: u32 = 0x7;
+
+    /// Round to nearest, ties to even.
+    pub const RNE: u32 = 0;
+
+    /// Round towards zero.
+    pub const RTZ: u32 = 1;
+
+    /// Round down, towards negative infinity.
+    pub const RDN: u32 = 2;
+
+    /// Round up, towards positive infinity.
+    pub const RUP: u32 = 3;
+
+    /// Round to nearest, ties to max magnitude.
+    pub const RMM: u32 = 4;
+
+    /// Set if subnormal numbers are supported rather than flushed to zero.
+    pub const SUBNORMALS: u32 = 1 << 3;
+
+    /// The environment of software floating point in the zkVM.
+    pub const ZKVM: u32 = RNE | SUBNORMALS;
+}
+
 pub mod bigint {
     pub const OP_MULTIPLY: u32 = 0;
 
@@ -96,6 +152,18 @@ pub mod bigint {
     pub const WIDTH_WORDS: usize = WIDTH_BYTES / crate::WORD_SIZE;
 }
 
+/// Values for the `whence` argument of [sys_lseek].
+pub mod whence {
+    /// Seek relative to the start of the stream.
+    pub const SET: u32 = 0;
+
+    /// Seek relative to the current position.
+    pub const CUR: u32 = 1;
+
+    /// Seek relative to the end of the stream.
+    pub const END: u32 = 2;
+}
+
 /// A UTF-8 NUL-terminated name of a syscall with static lifetime.
 #[derive(Clone, Copy, Debug)]
 #[repr(transparent)]
@@ -129,19 +197,34 @@ macro_rules! declare_syscall {
 }
 
 pub mod nr {
+    declare_syscall!(pub SYS_ALLOC_FAILED);
     declare_syscall!(pub SYS_ARGC);
     declare_syscall!(pub SYS_ARGV);
+    declare_syscall!(pub SYS_AUTH_GET);
+    declare_syscall!(pub SYS_AUTH_PUT);
+    declare_syscall!(pub SYS_CLOSE);
+    declare_syscall!(pub SYS_COMMIT_FIELD);
     declare_syscall!(pub SYS_CYCLE_COUNT);
+    declare_syscall!(pub SYS_DIV_WIDE);
+    declare_syscall!(pub SYS_DUP);
     declare_syscall!(pub SYS_EXIT);
     declare_syscall!(pub SYS_FORK);
+    declare_syscall!(pub SYS_FP_ENV);
     declare_syscall!(pub SYS_GETENV);
+    declare_syscall!(pub SYS_JOURNAL_RECIPIENT);
     declare_syscall!(pub SYS_LOG);
+    declare_syscall!(pub SYS_LSEEK);
+    declare_syscall!(pub SYS_MEMOIZE_GET);
+    declare_syscall!(pub SYS_MEMOIZE_PUT);
+    declare_syscall!(pub SYS_OPEN);
     declare_syscall!(pub SYS_PANIC);
+    declare_syscall!(pub SYS_PANIC_AT);
     declare_syscall!(pub SYS_PIPE);
     declare_syscall!(pub SYS_RANDOM);
     declare_syscall!(pub SYS_READ);
     declare_syscall!(pub SYS_VERIFY_INTEGRITY);
     declare_syscall!(pub SYS_WRITE);
+    declare_syscall!(pub SYS_WRITEV);
     declare_syscall!(pub SYS_EXECUTE_ZKR);
 }
 
@@ -324,6 +407,22 @@ pub unsafe extern "C" fn sys_pause(user_exit: u8, out_state: *const [u32; DIGEST
     );
 }
 
+/// Register `handler` for traps of `kind`, one of [trap_kind], or remove the handler of `kind`
+/// if `handler` is null. See [trap] for how a handler is entered and returns.
+///
+/// # Safety
+///
+/// `handler` must be null, or the address of code which handles the trap and returns with
+/// `mret`, preserving the registers of the code that trapped.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_trap_handler(kind: u32, handler: *const u8) {
+    ecall_1(
+        ecall::TRAP,
+        trap::SET_HANDLER | ((kind & 0xff) << 8),
+        handler as u32,
+    );
+}
+
 #[cfg_attr(feature = "export-syscalls", no_mangle)]
 pub extern "C" fn sys_input(index: u32) -> u32 {
     let t0 = ecall::INPUT;
@@ -440,6 +539,36 @@ pub unsafe extern "C" fn sys_panic(msg_ptr: *const u8, len: usize) -> ! {
     unreachable!()
 }
 
+/// Report a panic at the given source location, without a message.
+///
+/// This is used by guests built with the `compact-panic` feature, which never format panic
+/// messages.
+///
+/// # Safety
+///
+/// `file_ptr` must be aligned and dereferenceable.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_panic_at(
+    file_ptr: *const u8,
+    file_len: usize,
+    line: u32,
+    col: u32,
+) -> ! {
+    syscall_4(
+        nr::SYS_PANIC_AT,
+        null_mut(),
+        0,
+        file_ptr as u32,
+        file_len as u32,
+        line,
+        col,
+    );
+
+    // As a fallback for hosts that do not know this syscall, panic without the location.
+    const MSG: &[u8] = "Guest panicked".as_bytes();
+    sys_panic(MSG.as_ptr(), MSG.len())
+}
+
 /// # Safety
 ///
 /// `msg_ptr` must be aligned and dereferenceable.
@@ -592,6 +721,119 @@ fn sys_read_internal(fd: u32, recv_ptr: *mut u32, nwords: usize, nbytes: usize)
     (nread_total_bytes, final_word)
 }
 
+/// Reads the given number of bytes into the given buffer, which may have any
+/// alignment. Unlike [sys_read], this keeps reading until the buffer is full,
+/// and only returns a short read if EOF was reached.  Returns the number of
+/// bytes actually read.
+///
+/// This is built on [sys_read], which already transfers the partial words at
+/// either end of an unaligned buffer in registers, so the guest does not need
+/// to copy through an aligned buffer.
+///
+/// # Safety
+///
+/// `recv_ptr` must be valid for writes of `nbytes` bytes.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_read_exact(fd: u32, recv_ptr: *mut u8, nbytes: usize) -> usize {
+    let mut nread_total = 0;
+    while nread_total < nbytes {
+        let nread = sys_read(fd, recv_ptr.add(nread_total), nbytes - nread_total);
+        if nread == 0 {
+            // We've reached EOF.
+            break;
+        }
+        nread_total += nread;
+    }
+    nread_total
+}
+
+/// A buffer used for vectored I/O with [sys_readv] and [sys_writev], laid
+/// out like the POSIX `struct iovec`.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct IoVec {
+    pub base: *mut u8,
+    pub len: usize,
+}
+
+/// Reads into each of the given buffers in turn, posix `readv`-style.
+/// Returns the total number of bytes read, which is only less than the total
+/// size of the buffers if EOF was reached.
+///
+/// Data is transferred from the host with one system call per
+/// [MAX_BUF_BYTES] bytes, regardless of the number of buffers.
+///
+/// # Safety
+///
+/// `iov` must point to `iovcnt` valid [IoVec]s, each of which must be valid
+/// for writes of `len` bytes.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_readv(fd: u32, iov: *const IoVec, iovcnt: usize) -> usize {
+    let iovs = core::slice::from_raw_parts(iov, iovcnt);
+    let total: usize = iovs.iter().map(|iov| iov.len).sum();
+
+    let mut chunk = [0u32; MAX_BUF_WORDS];
+    let mut iov_idx = 0;
+    let mut iov_pos = 0;
+    let mut nread_total = 0;
+    while nread_total < total {
+        let chunk_len = min(total - nread_total, MAX_BUF_BYTES);
+        let chunk_words = chunk_len / WORD_SIZE;
+        let Return(nread, last_word) = syscall_2(
+            nr::SYS_READ,
+            chunk.as_mut_ptr(),
+            chunk_words,
+            fd,
+            chunk_len as u32,
+        );
+        let nread = nread as usize;
+        if nread > chunk_words * WORD_SIZE {
+            chunk[chunk_words] = last_word;
+        }
+
+        // Scatter the chunk into the buffers.
+        let mut copied = 0;
+        while copied < nread {
+            let iov = &iovs[iov_idx];
+            let ncopy = min(iov.len - iov_pos, nread - copied);
+            core::ptr::copy_nonoverlapping(
+                chunk.as_ptr().cast::<u8>().add(copied),
+                iov.base.add(iov_pos),
+                ncopy,
+            );
+            copied += ncopy;
+            iov_pos += ncopy;
+            if iov_pos == iov.len {
+                iov_idx += 1;
+                iov_pos = 0;
+            }
+        }
+
+        nread_total += nread;
+        if nread < chunk_len {
+            // We've reached EOF.
+            break;
+        }
+    }
+    nread_total
+}
+
+/// Writes each of the given buffers in turn, posix `writev`-style.  Returns
+/// the total number of bytes written.
+///
+/// All of the buffers are transferred to the host with a single system call.
+///
+/// # Safety
+///
+/// `iov` must point to `iovcnt` valid [IoVec]s, each of which must be valid
+/// for reads of `len` bytes.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_writev(fd: u32, iov: *const IoVec, iovcnt: usize) -> usize {
+    let Return(nwritten, _) =
+        syscall_3(nr::SYS_WRITEV, null_mut(), 0, fd, iov as u32, iovcnt as u32);
+    nwritten as usize
+}
+
 /// # Safety
 ///
 /// `write_ptr` must be aligned and dereferenceable.
@@ -614,6 +856,69 @@ pub unsafe extern "C" fn sys_write(fd: u32, write_ptr: *const u8, nbytes: usize)
     }
 }
 
+/// Opens the stream registered by the host under the given name, and returns a
+/// new file descriptor for reading from it.
+///
+/// Returns [fileno::INVALID][crate::fileno::INVALID] if the host has no stream
+/// with that name.
+///
+/// # Safety
+///
+/// `name_ptr` must be valid for reads of `name_len` bytes.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_open(name_ptr: *const u8, name_len: usize) -> u32 {
+    let Return(fd, _) = syscall_2(
+        nr::SYS_OPEN,
+        null_mut(),
+        0,
+        name_ptr as u32,
+        name_len as u32,
+    );
+    fd
+}
+
+/// Closes the given file descriptor.
+///
+/// Returns 0 on success, or [fileno::INVALID][crate::fileno::INVALID] if the
+/// file descriptor was not open or is one of the standard descriptors, which
+/// cannot be closed.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub extern "C" fn sys_close(fd: u32) -> u32 {
+    let Return(result, _) = unsafe { syscall_1(nr::SYS_CLOSE, null_mut(), 0, fd) };
+    result
+}
+
+/// Returns a new file descriptor that refers to the same stream as `fd`.
+///
+/// Returns [fileno::INVALID][crate::fileno::INVALID] if `fd` is not open.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub extern "C" fn sys_dup(fd: u32) -> u32 {
+    let Return(new_fd, _) = unsafe { syscall_1(nr::SYS_DUP, null_mut(), 0, fd) };
+    new_fd
+}
+
+/// Repositions the given file descriptor, posix `lseek`-style, using one of
+/// the [whence] values.  Returns the new position from the start of the
+/// stream, or -1 if the stream is not seekable or the position is invalid.
+///
+/// Only file descriptors that the host registered with a seekable source
+/// support seeking.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub extern "C" fn sys_lseek(fd: u32, offset: i64, whence: u32) -> i64 {
+    let Return(lo, hi) = unsafe {
+        syscall_4(
+            nr::SYS_LSEEK,
+            null_mut(),
+            0,
+            fd,
+            offset as u32,
+            (offset >> 32) as u32,
+            whence,
+        )
+    };
+    (((hi as u64) << 32) | lo as u64) as i64
+}
+
 /// Retrieves the value of an environment variable, and stores as much
 /// of it as it can it in the memory at [out_words, out_words +
 /// out_nwords).
@@ -700,6 +1005,18 @@ pub extern "C" fn sys_alloc_words(nwords: usize) -> *mut u32 {
 #[cfg(feature = "export-syscalls")]
 #[no_mangle]
 pub unsafe extern "C" fn sys_alloc_aligned(bytes: usize, align: usize) -> *mut u8 {
+    // The address this function returns to, reported if the allocation fails. The allocator
+    // entry points tail-call into this function, so this is usually in the code that allocated.
+    // Read before anything else so that `ra` still holds it.
+    #[cfg(target_os = "zkvm")]
+    let caller = {
+        let ra: usize;
+        unsafe { core::arch::asm!("mv {}, ra", out(reg) ra, options(nomem, nostack)) };
+        ra
+    };
+    #[cfg(not(target_os = "zkvm"))]
+    let caller = 0;
+
     #[cfg(target_os = "zkvm")]
     extern "C" {
         // This symbol is defined by the loader and marks the end
@@ -715,12 +1032,16 @@ pub unsafe extern "C" fn sys_alloc_aligned(bytes: usize, align: usize) -> *mut u
     // initialized.
     static mut HEAP_POS: usize = 0;
 
+    #[cfg(target_os = "zkvm")]
+    let heap_start = unsafe { (&_end) as *const u8 as usize };
+    #[cfg(not(target_os = "zkvm"))]
+    let heap_start = 0;
+
     // SAFETY: Single threaded, so nothing else can touch this while we're working.
     let mut heap_pos = unsafe { HEAP_POS };
 
-    #[cfg(target_os = "zkvm")]
     if heap_pos == 0 {
-        heap_pos = unsafe { (&_end) as *const u8 as usize };
+        heap_pos = heap_start;
     }
 
     // Honor requested alignment if larger than word size.
@@ -733,18 +1054,53 @@ pub unsafe extern "C" fn sys_alloc_aligned(bytes: usize, align: usize) -> *mut u
     }
 
     let ptr = heap_pos as *mut u8;
-    heap_pos += bytes;
 
     // Check to make sure heap doesn't collide with SYSTEM memory.
-    if crate::memory::SYSTEM.start() < heap_pos {
-        const MSG: &[u8] = "Out of memory!".as_bytes();
-        unsafe { sys_panic(MSG.as_ptr(), MSG.len()) };
+    match heap_pos.checked_add(bytes) {
+        Some(end) if end <= crate::memory::SYSTEM.start() => heap_pos = end,
+        _ => unsafe { sys_alloc_failed(bytes, align, heap_start, heap_pos, caller) },
     }
 
     unsafe { HEAP_POS = heap_pos };
     ptr
 }
 
+/// Report to the host that an allocation of `bytes` bytes aligned to `align`, requested by the
+/// code at `caller`, does not fit in the heap, which starts at `heap_start` and is used up to
+/// `heap_pos`, and abort.
+///
+/// The host fails execution with the requested size, the heap usage, and `caller`, rather than a
+/// bare panic message. Guests with their own allocators can call this when they run out of
+/// memory, passing the return address of their allocation function as `caller`.
+///
+/// # Safety
+///
+/// This function should be safe to call, but clippy complains if it is not marked as `unsafe`.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_alloc_failed(
+    bytes: usize,
+    align: usize,
+    heap_start: usize,
+    heap_pos: usize,
+    caller: usize,
+) -> ! {
+    syscall_5(
+        nr::SYS_ALLOC_FAILED,
+        null_mut(),
+        0,
+        bytes as u32,
+        align as u32,
+        heap_start as u32,
+        heap_pos as u32,
+        caller as u32,
+    );
+
+    // The host fails execution on this syscall, and one that does not know it fails on an unknown
+    // syscall, so this is only reached if a handler returns. This function must not return.
+    const MSG: &[u8] = "Out of memory!".as_bytes();
+    sys_panic(MSG.as_ptr(), MSG.len())
+}
+
 /// Send a ReceiptClaim digest to the host to request verification.
 ///
 /// A cooperative prover will only return if there is a verifying proof
@@ -0,0 +1,414 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Authenticated maps and vectors whose contents are held by the host.
+//!
+//! An [AuthenticatedStore] is a sparse Merkle tree with [DEPTH] levels, held by the host and
+//! registered with `ExecutorEnvBuilder::authenticated_store`. The guest is only given the
+//! [root][AuthenticatedStore::root] of the store, e.g. as input, and opens it as an [AuthMap] or
+//! an [AuthVec]. Each read fetches an entry and its Merkle [Witness] from the host with the
+//! `SYS_AUTH_GET` syscall, and checks the witness against the root held by the guest. Each write
+//! computes the new root from the same witness, and sends the new entry to the host with
+//! `SYS_AUTH_PUT`. The guest can therefore operate on far more state than fits in guest memory
+//! without trusting the host, and commit the final root to the journal.
+//!
+//! Entries of a map are placed by the first 64 bits of the SHA-256 digest of their key, and
+//! entries of a vector by their index. Each lookup hashes at most [DEPTH] pairs of digests.
+//!
+//! ```rust
+//! use risc0_zkvm::authenticated::AuthenticatedStore;
+//!
+//! let store = AuthenticatedStore::new();
+//! store.insert(b"alice", b"100").unwrap();
+//! store.set(7, b"seven");
+//! assert_eq!(store.get(b"alice"), Some(b"100".to_vec()));
+//!
+//! // The guest is given this root, and the store is registered with the executor.
+//! let root = store.root();
+//! ```
+
+use alloc::{string::String, vec::Vec};
+
+use risc0_binfmt::tagged_struct;
+use risc0_zkvm_platform::syscall::nr::{SYS_AUTH_GET, SYS_AUTH_PUT};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    guest::env,
+    sha::{self, Digest, Sha256},
+};
+
+/// The number of levels of the Merkle tree of a store, below the root.
+pub const DEPTH: usize = 64;
+
+/// An entry of an authenticated store.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// The key of the entry, or the little-endian index for an entry of a vector.
+    pub key: Vec<u8>,
+
+    /// The value of the entry.
+    pub value: Vec<u8>,
+}
+
+impl Entry {
+    fn digest(&self) -> Digest {
+        tagged_struct::<sha::Impl>(
+            "risc0.AuthenticatedEntry",
+            &[
+                *sha::Impl::hash_bytes(&self.key),
+                *sha::Impl::hash_bytes(&self.value),
+            ],
+            &[],
+        )
+    }
+}
+
+/// The entry at a position of a store, with the digests of its siblings from the leaf up.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Witness {
+    /// The entry at the position, if any.
+    pub entry: Option<Entry>,
+
+    /// The sibling of each node on the path from the leaf to the root.
+    pub siblings: Vec<Digest>,
+}
+
+impl Witness {
+    /// Compute the root of the tree with `leaf` at `position` and the siblings of this witness.
+    fn root(&self, position: u64, leaf: Digest) -> Digest {
+        assert_eq!(self.siblings.len(), DEPTH, "Invalid witness length");
+        self.siblings
+            .iter()
+            .enumerate()
+            .fold(leaf, |digest, (level, sibling)| {
+                if (position >> level) & 1 == 0 {
+                    node(&digest, sibling)
+                } else {
+                    node(sibling, &digest)
+                }
+            })
+    }
+}
+
+/// The digest of an inner node. Empty subtrees have the zero digest, so that an empty store
+/// needs no hashing.
+fn node(left: &Digest, right: &Digest) -> Digest {
+    if *left == Digest::ZERO && *right == Digest::ZERO {
+        Digest::ZERO
+    } else {
+        *sha::Impl::hash_pair(left, right)
+    }
+}
+
+fn leaf(entry: Option<&Entry>) -> Digest {
+    entry.map_or(Digest::ZERO, Entry::digest)
+}
+
+/// The position of the entry of a map with the given key.
+pub fn key_position(key: &[u8]) -> u64 {
+    let digest = sha::Impl::hash_bytes(key);
+    u64::from_le_bytes(digest.as_bytes()[..8].try_into().unwrap())
+}
+
+/// A guest view of a store, which checks every entry it reads against its root.
+struct GuestStore {
+    name: String,
+    root: Digest,
+}
+
+impl GuestStore {
+    fn fetch(&self, position: u64) -> Witness {
+        let request = crate::serde::to_vec(&(&self.name, position)).unwrap();
+        let response = env::send_recv_slice::<u32, u32>(SYS_AUTH_GET, &request);
+        let witness: Witness = crate::serde::from_slice(response).unwrap();
+        assert_eq!(
+            witness.root(position, leaf(witness.entry.as_ref())),
+            self.root,
+            "sys_auth_get: witness does not match the root of {}",
+            self.name
+        );
+        witness
+    }
+
+    fn get(&self, position: u64, key: &[u8]) -> Option<Vec<u8>> {
+        self.fetch(position)
+            .entry
+            .filter(|entry| entry.key == key)
+            .map(|entry| entry.value)
+    }
+
+    fn put(&mut self, position: u64, key: &[u8], value: Option<&[u8]>) -> Option<Vec<u8>> {
+        let witness = self.fetch(position);
+        let old = match witness.entry.clone() {
+            Some(entry) if entry.key == key => Some(entry.value),
+            Some(_) if value.is_none() => return None,
+            Some(_) => panic!("Position of key collides with another key in {}", self.name),
+            None => None,
+        };
+        let entry = value.map(|value| Entry {
+            key: key.to_vec(),
+            value: value.to_vec(),
+        });
+        self.root = witness.root(position, leaf(entry.as_ref()));
+        let request = crate::serde::to_vec(&(&self.name, position, entry)).unwrap();
+        env::syscall(SYS_AUTH_PUT, bytemuck::cast_slice(&request), &mut []);
+        old
+    }
+}
+
+/// A map from byte strings to byte strings, held by the host.
+///
+/// See the [module documentation][self].
+pub struct AuthMap(GuestStore);
+
+impl AuthMap {
+    /// Open the store registered on the host under `name`, which must have the given root.
+    pub fn new(name: &str, root: Digest) -> Self {
+        Self(GuestStore {
+            name: name.into(),
+            root,
+        })
+    }
+
+    /// The root of the map, which commits to all of its entries.
+    pub fn root(&self) -> Digest {
+        self.0.root
+    }
+
+    /// Return the value of `key`, if any.
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.0.get(key_position(key), key)
+    }
+
+    /// Set the value of `key`, and return its previous value, if any.
+    ///
+    /// Panics if the position of `key` is taken by another key, which happens with probability
+    /// about `n^2 / 2^65` for a map with `n` keys.
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        self.0.put(key_position(key), key, Some(value))
+    }
+
+    /// Remove `key`, and return its value, if any.
+    pub fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        self.0.put(key_position(key), key, None)
+    }
+}
+
+/// A vector of byte strings indexed by [u64], held by the host, where unset indices are empty.
+///
+/// See the [module documentation][self].
+pub struct AuthVec(GuestStore);
+
+impl AuthVec {
+    /// Open the store registered on the host under `name`, which must have the given root.
+    pub fn new(name: &str, root: Digest) -> Self {
+        Self(GuestStore {
+            name: name.into(),
+            root,
+        })
+    }
+
+    /// The root of the vector, which commits to all of its entries.
+    pub fn root(&self) -> Digest {
+        self.0.root
+    }
+
+    /// Return the value at `index`, if any.
+    pub fn get(&self, index: u64) -> Option<Vec<u8>> {
+        self.0.get(index, &index.to_le_bytes())
+    }
+
+    /// Set the value at `index`, and return its previous value, if any.
+    pub fn set(&mut self, index: u64, value: &[u8]) -> Option<Vec<u8>> {
+        self.0.put(index, &index.to_le_bytes(), Some(value))
+    }
+
+    /// Clear the value at `index`, and return it, if any.
+    pub fn clear(&mut self, index: u64) -> Option<Vec<u8>> {
+        self.0.put(index, &index.to_le_bytes(), None)
+    }
+}
+
+#[cfg(not(target_os = "zkvm"))]
+pub use host::AuthenticatedStore;
+
+#[cfg(not(target_os = "zkvm"))]
+mod host {
+    use alloc::{collections::BTreeMap, rc::Rc, vec::Vec};
+    use core::cell::RefCell;
+
+    use anyhow::{bail, Result};
+
+    use super::{key_position, leaf, node, Entry, Witness, DEPTH};
+    use crate::sha::Digest;
+
+    /// The host side of an authenticated map or vector.
+    ///
+    /// Clones of a store share its contents, so the host can read the entries written by the
+    /// guest from a clone of the store registered with the executor.
+    #[derive(Clone, Default)]
+    pub struct AuthenticatedStore(Rc<RefCell<Tree>>);
+
+    #[derive(Default)]
+    struct Tree {
+        entries: BTreeMap<u64, Entry>,
+        // The non-empty nodes of the tree, by level and index, where level 0 holds the leaves.
+        nodes: BTreeMap<(usize, u64), Digest>,
+    }
+
+    impl Tree {
+        fn node(&self, level: usize, index: u64) -> Digest {
+            self.nodes
+                .get(&(level, index))
+                .copied()
+                .unwrap_or(Digest::ZERO)
+        }
+
+        fn set(&mut self, position: u64, entry: Option<Entry>) {
+            let mut digest = leaf(entry.as_ref());
+            match entry {
+                Some(entry) => self.entries.insert(position, entry),
+                None => self.entries.remove(&position),
+            };
+            let mut index = position;
+            for level in 0..DEPTH {
+                if digest == Digest::ZERO {
+                    self.nodes.remove(&(level, index));
+                } else {
+                    self.nodes.insert((level, index), digest);
+                }
+                let sibling = self.node(level, index ^ 1);
+                digest = if index & 1 == 0 {
+                    node(&digest, &sibling)
+                } else {
+                    node(&sibling, &digest)
+                };
+                index >>= 1;
+            }
+            self.nodes.insert((DEPTH, 0), digest);
+        }
+    }
+
+    impl AuthenticatedStore {
+        /// Construct an empty [AuthenticatedStore].
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// The root of the store, which commits to all of its entries.
+        pub fn root(&self) -> Digest {
+            self.0.borrow().node(DEPTH, 0)
+        }
+
+        /// Set the value of `key`, for use as an [AuthMap][super::AuthMap].
+        ///
+        /// Fails if the position of `key` is taken by another key.
+        pub fn insert(&self, key: &[u8], value: &[u8]) -> Result<()> {
+            let position = key_position(key);
+            let mut tree = self.0.borrow_mut();
+            if let Some(entry) = tree.entries.get(&position) {
+                if entry.key != key {
+                    bail!("Position of key collides with another key");
+                }
+            }
+            tree.set(
+                position,
+                Some(Entry {
+                    key: key.to_vec(),
+                    value: value.to_vec(),
+                }),
+            );
+            Ok(())
+        }
+
+        /// Return the value of `key` of an [AuthMap][super::AuthMap], if any.
+        pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+            let tree = self.0.borrow();
+            let entry = tree.entries.get(&key_position(key))?;
+            (entry.key == key).then(|| entry.value.clone())
+        }
+
+        /// Set the value at `index`, for use as an [AuthVec][super::AuthVec].
+        pub fn set(&self, index: u64, value: &[u8]) {
+            self.0.borrow_mut().set(
+                index,
+                Some(Entry {
+                    key: index.to_le_bytes().to_vec(),
+                    value: value.to_vec(),
+                }),
+            );
+        }
+
+        /// Return the value at `index` of an [AuthVec][super::AuthVec], if any.
+        pub fn get_index(&self, index: u64) -> Option<Vec<u8>> {
+            self.0
+                .borrow()
+                .entries
+                .get(&index)
+                .map(|entry| entry.value.clone())
+        }
+
+        /// The entry at `position`, and the siblings of its path to the root.
+        pub(crate) fn witness(&self, position: u64) -> Witness {
+            let tree = self.0.borrow();
+            Witness {
+                entry: tree.entries.get(&position).cloned(),
+                siblings: (0..DEPTH)
+                    .map(|level| tree.node(level, (position >> level) ^ 1))
+                    .collect(),
+            }
+        }
+
+        /// Replace the entry at `position` with one written by the guest.
+        pub(crate) fn put(&self, position: u64, entry: Option<Entry>) {
+            self.0.borrow_mut().set(position, entry);
+        }
+    }
+}
+
+#[cfg(all(test, not(target_os = "zkvm")))]
+mod tests {
+    use super::{key_position, leaf, AuthenticatedStore, DEPTH};
+    use crate::sha::Digest;
+
+    #[test]
+    fn witness() {
+        let store = AuthenticatedStore::new();
+        assert_eq!(store.root(), Digest::ZERO);
+        store.insert(b"alice", b"100").unwrap();
+        store.insert(b"bob", b"200").unwrap();
+        store.set(3, b"three");
+
+        let position = key_position(b"alice");
+        let witness = store.witness(position);
+        assert_eq!(witness.siblings.len(), DEPTH);
+        assert_eq!(
+            witness.root(position, leaf(witness.entry.as_ref())),
+            store.root()
+        );
+
+        // An empty position proves that no entry is there.
+        let witness = store.witness(4);
+        assert!(witness.entry.is_none());
+        assert_eq!(witness.root(4, Digest::ZERO), store.root());
+
+        store.put(3, None);
+        assert_eq!(store.get_index(3), None);
+        let other = AuthenticatedStore::new();
+        other.insert(b"bob", b"200").unwrap();
+        other.insert(b"alice", b"100").unwrap();
+        assert_eq!(store.root(), other.root());
+    }
+}
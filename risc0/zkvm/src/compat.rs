@@ -0,0 +1,115 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks that receipts produced by earlier releases are still accepted.
+//!
+//! Each release embeds a golden receipt for the `fib` test guest, proven and serialized with
+//! bincode by that release with `cargo xtask gen-compat-receipt`, along with the image ID and
+//! journal it was proven for. [check_backward_compat] decodes every golden receipt with the
+//! current types, checks that it encodes back to the same bytes, and verifies it with the current
+//! verifier. Packagers can run it after patching the verifier or its dependencies, to detect
+//! changes that would reject receipts that have already been published.
+
+mod golden;
+
+use alloc::{format, string::String, vec::Vec};
+
+use anyhow::{bail, ensure, Context as _, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{sha::Digest, Receipt};
+
+/// A golden receipt, as written by `cargo xtask gen-compat-receipt`.
+#[derive(Serialize, Deserialize)]
+struct Golden {
+    image_id: Digest,
+    journal: Vec<u8>,
+    receipt: Vec<u8>,
+}
+
+/// The releases with a golden receipt, oldest first.
+pub fn releases() -> impl Iterator<Item = &'static str> {
+    golden::GOLDEN.iter().map(|(release, _)| *release)
+}
+
+/// Check that the golden receipt of `release` decodes to the same bytes and still verifies.
+pub fn check_release(release: &str) -> Result<()> {
+    let (_, bytes) = golden::GOLDEN
+        .iter()
+        .find(|(name, _)| *name == release)
+        .with_context(|| format!("No golden receipt for release {release}"))?;
+    let golden: Golden = bincode::deserialize(bytes).context("Failed to decode golden file")?;
+
+    let receipt: Receipt =
+        bincode::deserialize(&golden.receipt).context("Failed to decode receipt")?;
+    let encoded = bincode::serialize(&receipt)?;
+    ensure!(
+        encoded == golden.receipt,
+        "Receipt encodes to different bytes than it was decoded from"
+    );
+    ensure!(
+        receipt.journal.bytes == golden.journal,
+        "Receipt decodes to a different journal"
+    );
+    receipt
+        .verify(golden.image_id)
+        .context("Receipt failed to verify")?;
+    Ok(())
+}
+
+/// Check the golden receipt of every earlier release with [check_release].
+///
+/// Returns an error naming every release whose receipt was not accepted, or if no golden receipts
+/// are embedded, so that a build without them cannot pass the check.
+pub fn check_backward_compat() -> Result<()> {
+    ensure!(
+        releases().next().is_some(),
+        "No golden receipts are embedded; generate one with `cargo xtask gen-compat-receipt`"
+    );
+    let failures: Vec<String> = releases()
+        .filter_map(|release| {
+            check_release(release)
+                .err()
+                .map(|err| format!("{release}: {err:#}"))
+        })
+        .collect();
+    if !failures.is_empty() {
+        bail!(
+            "Receipts of earlier releases were rejected:\n{}",
+            failures.join("\n")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use test_log::test;
+
+    use super::{check_backward_compat, check_release, releases};
+
+    #[test]
+    fn backward_compat() {
+        // Until a golden receipt is embedded, the check must fail rather than pass vacuously.
+        match releases().next() {
+            Some(_) => check_backward_compat().unwrap(),
+            None => assert!(check_backward_compat().is_err()),
+        }
+    }
+
+    #[test]
+    fn unknown_release() {
+        assert!(check_release("0.0.0").is_err());
+    }
+}
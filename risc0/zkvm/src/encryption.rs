@@ -0,0 +1,156 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Journals encrypted to a single recipient.
+//!
+//! The host supplies the X25519 public key of the recipient with
+//! `ExecutorEnvBuilder::journal_recipient`, and the guest commits an [EncryptedJournal] with
+//! [env::commit_encrypted][crate::guest::env::commit_encrypted]. The receipt then commits to the
+//! ciphertext, so anyone can verify it, but only the holder of the matching secret key can read
+//! the output, with [Receipt::decrypt_journal][crate::Receipt::decrypt_journal].
+//!
+//! Each journal is encrypted with ChaCha20-Poly1305 under a key derived from an X25519 exchange
+//! between the recipient and a fresh ephemeral key drawn by the guest, with a fresh random nonce.
+//! The recipient key is part of the committed journal, so a verifier can check who the output was
+//! encrypted to.
+//!
+//! # Trust model
+//!
+//! Encryption hides the output from everyone who sees the receipt but not the recipient's secret
+//! key, e.g. the verifiers of the receipt. It does not hide it from the host that executes or
+//! proves the guest: the host sees the memory of the guest, including the plaintext, and the
+//! ephemeral secret and the nonce come from `sys_rand`, which the host answers without the circuit
+//! checking the result. Only run the guest on a host that is trusted with the plaintext.
+//!
+//! ```rust
+//! use risc0_zkvm::encryption::{public_key, EncryptedJournal};
+//!
+//! let secret_key = [7u8; 32];
+//! let encrypted =
+//!     EncryptedJournal::encrypt(&public_key(&secret_key), [9u8; 32], [1u8; 12], b"hello");
+//! assert_eq!(encrypted.decrypt(&secret_key).unwrap(), b"hello");
+//! assert!(encrypted.decrypt(&[8u8; 32]).is_err());
+//! ```
+
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, ensure, Result};
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, Key, KeyInit, Nonce};
+use serde::{Deserialize, Serialize};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::sha::{self, Sha256};
+
+/// A journal encrypted to the holder of an X25519 secret key.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptedJournal {
+    /// The X25519 public key of the recipient.
+    pub recipient: [u8; 32],
+
+    /// The X25519 public key of the ephemeral key used to encrypt this journal.
+    pub ephemeral: [u8; 32],
+
+    /// The ChaCha20-Poly1305 nonce used to encrypt this journal.
+    pub nonce: [u8; 12],
+
+    /// The encrypted journal, followed by its authentication tag.
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedJournal {
+    /// Encrypt `plaintext` to `recipient`, using `ephemeral_secret` as the secret of the
+    /// ephemeral key, and `nonce` as the nonce of the cipher.
+    ///
+    /// The ephemeral secret and the nonce must both be fresh randomness that is never reused.
+    pub fn encrypt(
+        recipient: &[u8; 32],
+        ephemeral_secret: [u8; 32],
+        nonce: [u8; 12],
+        plaintext: &[u8],
+    ) -> Self {
+        let ephemeral_secret = StaticSecret::from(ephemeral_secret);
+        let ephemeral = PublicKey::from(&ephemeral_secret).to_bytes();
+        let shared = ephemeral_secret.diffie_hellman(&PublicKey::from(*recipient));
+        let ciphertext = cipher(shared.as_bytes(), &ephemeral, recipient)
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .expect("ChaCha20-Poly1305 encryption failed");
+        Self {
+            recipient: *recipient,
+            ephemeral,
+            nonce,
+            ciphertext,
+        }
+    }
+
+    /// Decrypt this journal with the secret key of the recipient.
+    pub fn decrypt(&self, secret_key: &[u8; 32]) -> Result<Vec<u8>> {
+        ensure!(
+            public_key(secret_key) == self.recipient,
+            "journal is encrypted to a different recipient"
+        );
+        let shared = StaticSecret::from(*secret_key).diffie_hellman(&self.ephemeral.into());
+        cipher(shared.as_bytes(), &self.ephemeral, &self.recipient)
+            .decrypt(Nonce::from_slice(&self.nonce), self.ciphertext.as_slice())
+            .map_err(|_| anyhow!("failed to decrypt journal"))
+    }
+}
+
+/// The X25519 public key for `secret_key`.
+pub fn public_key(secret_key: &[u8; 32]) -> [u8; 32] {
+    PublicKey::from(&StaticSecret::from(*secret_key)).to_bytes()
+}
+
+/// The cipher for a journal, keyed by the shared secret and both public keys.
+fn cipher(shared: &[u8; 32], ephemeral: &[u8; 32], recipient: &[u8; 32]) -> ChaCha20Poly1305 {
+    let key = sha::Impl::hash_bytes(
+        &[
+            b"risc0.EncryptedJournal".as_slice(),
+            shared,
+            ephemeral,
+            recipient,
+        ]
+        .concat(),
+    );
+    ChaCha20Poly1305::new(Key::from_slice(key.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{public_key, EncryptedJournal};
+
+    #[test]
+    fn round_trip() {
+        let secret_key = [1u8; 32];
+        let encrypted =
+            EncryptedJournal::encrypt(&public_key(&secret_key), [2u8; 32], [4u8; 12], b"secret");
+        assert_ne!(encrypted.ciphertext[..6], b"secret"[..]);
+        assert_eq!(encrypted.decrypt(&secret_key).unwrap(), b"secret");
+
+        // The nonce is part of the encryption.
+        let renonced =
+            EncryptedJournal::encrypt(&public_key(&secret_key), [2u8; 32], [5u8; 12], b"secret");
+        assert_ne!(renonced.ciphertext, encrypted.ciphertext);
+        let mut tampered = encrypted.clone();
+        tampered.nonce[0] ^= 1;
+        assert!(tampered.decrypt(&secret_key).is_err());
+
+        // The ciphertext is authenticated.
+        let mut tampered = encrypted.clone();
+        tampered.ciphertext[0] ^= 1;
+        assert!(tampered.decrypt(&secret_key).is_err());
+
+        // Only the recipient can decrypt.
+        assert!(encrypted.decrypt(&[3u8; 32]).is_err());
+    }
+}
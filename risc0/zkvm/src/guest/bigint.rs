@@ -0,0 +1,173 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! 256-bit integer arithmetic built on the BigInt accelerator.
+//!
+//! The accelerator circuit implements a single operation, 256-bit modular multiplication, which
+//! is exposed as [modmul]. The other operations here are built from it: reduction multiplies by
+//! one, and a wide multiplication is assembled from four products of 128-bit halves, which the
+//! accelerator computes exactly when the modulus is zero. Modular addition and subtraction only
+//! need a carry and a conditional correction, which are cheap in software.
+//!
+//! Integers are arrays of [WIDTH_WORDS] little-endian words. A modulus of zero means no
+//! reduction, i.e. arithmetic modulo 2^256, and a modulus of one reduces every result to zero.
+
+use risc0_zkvm_platform::syscall::{
+    bigint::{OP_MULTIPLY, WIDTH_WORDS},
+    sys_bigint,
+};
+
+/// A 256-bit unsigned integer, as little-endian words.
+pub type BigInt = [u32; WIDTH_WORDS];
+
+/// A 512-bit unsigned integer, as little-endian words.
+pub type WideBigInt = [u32; 2 * WIDTH_WORDS];
+
+const ZERO: BigInt = [0; WIDTH_WORDS];
+const ONE: BigInt = {
+    let mut one = ZERO;
+    one[0] = 1;
+    one
+};
+const HALF_WORDS: usize = WIDTH_WORDS / 2;
+
+/// Compute `x * y mod modulus` with the accelerator.
+///
+/// At least one of `x` and `y` must be less than `modulus`. If `modulus` is zero, the product is
+/// computed without reduction, and must fit in 256 bits.
+pub fn modmul(x: &BigInt, y: &BigInt, modulus: &BigInt) -> BigInt {
+    let mut result = ZERO;
+    unsafe { sys_bigint(&mut result, OP_MULTIPLY, x, y, modulus) };
+    result
+}
+
+/// Compute `x mod modulus` with the accelerator.
+pub fn reduce(x: &BigInt, modulus: &BigInt) -> BigInt {
+    // The accelerator needs one factor to be less than the modulus, which one is not for a
+    // modulus of one.
+    if *modulus == ONE {
+        return ZERO;
+    }
+    modmul(x, &ONE, modulus)
+}
+
+/// Compute `x + y mod modulus`, where `x` and `y` are less than `modulus`, unless it is zero.
+pub fn modadd(x: &BigInt, y: &BigInt, modulus: &BigInt) -> BigInt {
+    let (sum, carry) = add(x, y);
+    if carry || !less_than(&sum, modulus) {
+        sub(&sum, modulus).0
+    } else {
+        sum
+    }
+}
+
+/// Compute `x - y mod modulus`, where `x` and `y` are less than `modulus`, unless it is zero.
+pub fn modsub(x: &BigInt, y: &BigInt, modulus: &BigInt) -> BigInt {
+    let (diff, borrow) = sub(x, y);
+    if borrow {
+        add(&diff, modulus).0
+    } else {
+        diff
+    }
+}
+
+/// Compute the full 512-bit product `x * y` with the accelerator.
+pub fn mul_wide(x: &BigInt, y: &BigInt) -> WideBigInt {
+    let (x_lo, x_hi) = halves(x);
+    let (y_lo, y_hi) = halves(y);
+
+    let mut result = [0; 2 * WIDTH_WORDS];
+    for (x_half, y_half, offset) in [
+        (&x_lo, &y_lo, 0),
+        (&x_lo, &y_hi, HALF_WORDS),
+        (&x_hi, &y_lo, HALF_WORDS),
+        (&x_hi, &y_hi, WIDTH_WORDS),
+    ] {
+        // The product of two 128-bit halves always fits in 256 bits.
+        let product = modmul(x_half, y_half, &ZERO);
+        add_at(&mut result, &product, offset);
+    }
+    result
+}
+
+fn halves(x: &BigInt) -> (BigInt, BigInt) {
+    let mut lo = ZERO;
+    let mut hi = ZERO;
+    lo[..HALF_WORDS].copy_from_slice(&x[..HALF_WORDS]);
+    hi[..HALF_WORDS].copy_from_slice(&x[HALF_WORDS..]);
+    (lo, hi)
+}
+
+pub(super) fn add(x: &BigInt, y: &BigInt) -> (BigInt, bool) {
+    let mut result = ZERO;
+    let mut carry = false;
+    for ((out, x), y) in result.iter_mut().zip(x).zip(y) {
+        let (sum, c1) = x.overflowing_add(*y);
+        let (sum, c2) = sum.overflowing_add(carry as u32);
+        *out = sum;
+        carry = c1 || c2;
+    }
+    (result, carry)
+}
+
+fn sub(x: &BigInt, y: &BigInt) -> (BigInt, bool) {
+    let mut result = ZERO;
+    let mut borrow = false;
+    for ((out, x), y) in result.iter_mut().zip(x).zip(y) {
+        let (diff, b1) = x.overflowing_sub(*y);
+        let (diff, b2) = diff.overflowing_sub(borrow as u32);
+        *out = diff;
+        borrow = b1 || b2;
+    }
+    (result, borrow)
+}
+
+fn less_than(x: &BigInt, y: &BigInt) -> bool {
+    x.iter().rev().lt(y.iter().rev())
+}
+
+fn add_at(acc: &mut WideBigInt, x: &BigInt, offset: usize) {
+    let mut carry = false;
+    for (i, word) in acc[offset..].iter_mut().enumerate() {
+        if i >= WIDTH_WORDS && !carry {
+            break;
+        }
+        let addend = x.get(i).copied().unwrap_or(0);
+        let (sum, c1) = word.overflowing_add(addend);
+        let (sum, c2) = sum.overflowing_add(carry as u32);
+        *word = sum;
+        carry = c1 || c2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add, add_at, less_than, sub, BigInt, WideBigInt};
+
+    #[test]
+    fn carries() {
+        let max: BigInt = [u32::MAX; 8];
+        let one: BigInt = [1, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(add(&max, &one), ([0; 8], true));
+        assert_eq!(sub(&[0; 8], &one), (max, true));
+        assert!(less_than(&one, &max));
+        assert!(!less_than(&max, &max));
+
+        let mut acc: WideBigInt = [0; 16];
+        acc[..8].copy_from_slice(&max);
+        add_at(&mut acc, &one, 0);
+        assert_eq!(acc[8], 1);
+        assert!(acc[..8].iter().all(|&word| word == 0));
+    }
+}
@@ -68,27 +68,35 @@
 //! [proof composition]:https://www.risczero.com/blog/proof-composition
 //! [guest-optimization]: https://dev.risczero.com/api/zkvm/optimization#when-reading-data-as-raw-bytes-use-envread_slice
 
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
 use core::{cell::OnceCell, convert::Infallible, fmt};
 
 use bytemuck::Pod;
+use risc0_binfmt::tagged_struct;
 use risc0_zkvm_platform::{
     align_up, fileno,
+    memory::MEMORY_OUTPUT,
     syscall::{
-        self, sys_alloc_words, sys_cycle_count, sys_exit, sys_fork, sys_halt, sys_input, sys_log,
-        sys_pause, sys_read, sys_read_words, sys_verify_integrity, sys_write, syscall_2,
-        SyscallName,
+        self,
+        nr::{SYS_COMMIT_FIELD, SYS_MEMOIZE_GET, SYS_MEMOIZE_PUT},
+        sys_alloc_words, sys_cycle_count, sys_exit, sys_fork, sys_halt, sys_input, sys_log,
+        sys_open, sys_pause, sys_read, sys_read_exact, sys_read_words, sys_verify_integrity,
+        sys_write, syscall_2, SyscallName,
     },
-    WORD_SIZE,
+    PAGE_SIZE, WORD_SIZE,
 };
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
-    serde::{Deserializer, Serializer, WordRead, WordWrite},
+    redaction::RedactableJournal,
+    schema::{self, JournalSchema},
+    serde::{frame, Deserializer, FrameTag, Serializer, WordRead, WordWrite},
     sha::{
+        self,
         rust_crypto::{Digest as _, Sha256},
-        Digest, Digestible,
+        Digest, Digestible, Sha256 as _,
     },
-    Assumption, Assumptions, MaybePruned, Output, PrunedValueError, ReceiptClaim,
+    Assumption, Assumptions, MaybePruned, Output, PrunedValueError, ReceiptClaim, Statement,
 };
 
 static mut HASHER: OnceCell<Sha256> = OnceCell::new();
@@ -103,6 +111,99 @@ static mut ASSUMPTIONS_DIGEST: MaybePruned<Assumptions> = MaybePruned::Pruned(Di
 /// information leakage through the post-state digest.
 static mut MEMORY_IMAGE_ENTROPY: [u32; 4] = [0u32; 4];
 
+/// Buffer for data written to the journal, so that many small commits can be sent to the host
+/// with a single syscall.
+static mut JOURNAL_BUF: [u8; JOURNAL_BUF_SIZE] = [0u8; JOURNAL_BUF_SIZE];
+static mut JOURNAL_BUF_LEN: usize = 0;
+const JOURNAL_BUF_SIZE: usize = PAGE_SIZE;
+
+/// The number of bytes committed to the journal so far.
+static mut JOURNAL_LEN: usize = 0;
+
+/// Fields committed with [commit_field], whose root is committed to the journal on exit.
+static mut COMMITTED_FIELDS: RedactableJournal = RedactableJournal { fields: Vec::new() };
+
+/// Digests of the results of calls made with [memoize], by the digest of the function and its
+/// arguments.
+static mut MEMOIZED: BTreeMap<Digest, Digest> = BTreeMap::new();
+
+/// The number of frames read with [read_frame].
+static mut FRAMES_READ: u32 = 0;
+
+/// Whether any data has been read from stdin, which [commit_statement] must read all of.
+static mut STDIN_READ: bool = false;
+
+/// Running hash of all data read from stdin, checked against the [input_digest] at the end of
+/// stdin. It is `None` if the input digest is not set, and so there is nothing to check.
+#[cfg(feature = "verify-input")]
+static mut STDIN_HASHER: OnceCell<Option<Sha256>> = OnceCell::new();
+
+/// Whether stdin has been checked against the [input_digest].
+#[cfg(feature = "verify-input")]
+static mut STDIN_CHECKED: bool = false;
+
+/// Record data read from `fd` so that it can be checked against the [input_digest], and check it
+/// once the end of stdin is reached.
+#[cfg(feature = "verify-input")]
+fn record_read(fd: u32, bytes: &[u8], eof: bool) {
+    if fd != fileno::STDIN {
+        return;
+    }
+    unsafe { STDIN_READ = true };
+    if unsafe { STDIN_CHECKED } {
+        return;
+    }
+    unsafe {
+        STDIN_HASHER.get_or_init(|| (input_digest() != Digest::ZERO).then(Sha256::new));
+        if let Some(Some(hasher)) = STDIN_HASHER.get_mut() {
+            hasher.update(bytes);
+        }
+    }
+    if eof {
+        check_input_digest();
+    }
+}
+
+#[cfg(not(feature = "verify-input"))]
+fn record_read(fd: u32, _bytes: &[u8], _eof: bool) {
+    if fd == fileno::STDIN {
+        unsafe { STDIN_READ = true };
+    }
+}
+
+/// Abort execution if the data read from stdin does not match the [input_digest].
+#[cfg(feature = "verify-input")]
+fn check_input_digest() {
+    unsafe {
+        if STDIN_CHECKED {
+            return;
+        }
+        STDIN_CHECKED = true;
+    }
+    let expected = input_digest();
+    if expected == Digest::ZERO {
+        return;
+    }
+    let hasher = unsafe { STDIN_HASHER.take() }.flatten().unwrap_or_default();
+    let stdin_digest: Digest = hasher.finalize().as_slice().try_into().unwrap();
+    if stdin_digest != expected {
+        crate::guest::abort("stdin does not match the committed input digest");
+    }
+}
+
+/// Read the rest of stdin, so that all of it is checked against the [input_digest] even if the
+/// guest did not consume it.
+#[cfg(feature = "verify-input")]
+fn finish_input() {
+    if unsafe { STDIN_CHECKED } || input_digest() == Digest::ZERO {
+        return;
+    }
+    let mut reader = stdin();
+    let mut buf = [0u8; 1024];
+    while reader.read_bytes(&mut buf) != 0 {}
+    check_input_digest();
+}
+
 pub(crate) fn init() {
     unsafe {
         HASHER.set(Sha256::new()).unwrap();
@@ -114,6 +215,16 @@ pub(crate) fn init() {
 }
 
 pub(crate) fn finalize(halt: bool, user_exit: u8) {
+    #[cfg(feature = "verify-input")]
+    if halt {
+        finish_input();
+    }
+    let fields = unsafe { &*core::ptr::addr_of!(COMMITTED_FIELDS) };
+    if halt && !fields.fields.is_empty() {
+        assert_journal_empty();
+        commit_redactable(fields);
+    }
+    flush_journal();
     unsafe {
         let hasher = HASHER.take();
         let journal_digest: Digest = hasher.unwrap().finalize().as_slice().try_into().unwrap();
@@ -148,6 +259,24 @@ pub fn pause(exit_code: u8) {
     init();
 }
 
+/// Pause the execution of the zkVM, leaving `output` in guest memory for the host.
+///
+/// The address and length of `output` are written to [MEMORY_OUTPUT] before pausing, and the
+/// host reads the output from the post-state memory of the session with `Session::memory_output`.
+/// The claim of a paused session commits to its post-state memory, so a verifier of the receipt
+/// can check the output against a Merkle proof of that memory. Unlike the journal, the output
+/// does not need to be sent to every verifier, which suits large outputs that only one party
+/// needs.
+pub fn pause_with_memory_output(exit_code: u8, output: &[u8]) {
+    let descriptor = MEMORY_OUTPUT as *mut u32;
+    // SAFETY: The two words at MEMORY_OUTPUT are reserved for this descriptor, above the stack.
+    unsafe {
+        descriptor.write_volatile(output.as_ptr() as u32);
+        descriptor.add(1).write_volatile(output.len() as u32);
+    }
+    pause(exit_code);
+}
+
 /// Exchange data with the host.
 pub fn syscall(syscall: SyscallName, to_host: &[u8], from_host: &mut [u32]) -> syscall::Return {
     unsafe {
@@ -355,6 +484,49 @@ pub fn read<T: DeserializeOwned>() -> T {
     stdin().read()
 }
 
+/// Read a frame written by `ExecutorEnvBuilder::write_frame` from the STDIN of the zkVM, and
+/// deserialize it.
+///
+/// Frames must be read in the order they were written, and as a type with the same
+/// [FrameTag] as the type they were written as. Otherwise, this panics with a message naming the
+/// frame and both tags, rather than decoding the wrong data.
+///
+/// # Example
+///
+/// ```no_run
+/// use risc0_zkvm::guest::env;
+///
+/// let count: u32 = env::read_frame();
+/// let data: Vec<u8> = env::read_frame();
+/// ```
+pub fn read_frame<T: DeserializeOwned + FrameTag>() -> T {
+    let name = T::tag();
+    let expected = unsafe { FRAMES_READ };
+    let mut header = [0u32; frame::HEADER_WORDS];
+    read_slice(&mut header);
+    let [marker, index, name_len, payload_len] = header;
+    if marker != frame::FRAME_MARKER {
+        panic!("expected input frame {expected} ({name}), found input not written as a frame");
+    }
+    if index != expected {
+        panic!("expected input frame {expected} ({name}), found input frame {index}");
+    }
+
+    let mut found = vec![0u32; (name_len as usize).div_ceil(WORD_SIZE)];
+    read_slice(&mut found);
+    let found = &bytemuck::cast_slice::<u32, u8>(&found)[..name_len as usize];
+    if found != name.as_bytes() {
+        let found = core::str::from_utf8(found).unwrap_or("an invalid tag");
+        panic!("input frame {index} was written as {found}, but read as {name}");
+    }
+
+    let mut payload = vec![0u32; payload_len as usize];
+    read_slice(&mut payload);
+    unsafe { FRAMES_READ += 1 };
+    crate::serde::from_slice(&payload)
+        .unwrap_or_else(|err| panic!("failed to decode input frame {index} as {name}: {err}"))
+}
+
 /// Read a slice from the STDIN of the zkVM.
 ///
 /// This function reads a slice of [plain old data][bytemuck::Pod], not
@@ -385,6 +557,16 @@ pub fn read_slice<T: Pod>(slice: &mut [T]) {
     stdin().read_slice(slice)
 }
 
+/// Read bytes from the STDIN of the zkVM into a buffer with any alignment.
+///
+/// Fills the buffer completely unless the end of STDIN is reached, and returns the number of
+/// bytes read. Unlike [read_slice], this does not require the buffer to be word-aligned to be
+/// efficient.
+#[must_use = "read_bytes can return a short read at the end of STDIN; this case should be handled."]
+pub fn read_bytes(buf: &mut [u8]) -> usize {
+    stdin().read_bytes_all(buf)
+}
+
 /// Serialize the given data and write it to the STDOUT of the zkVM.
 ///
 /// This is available to the host as the private output on the prover.
@@ -505,6 +687,192 @@ pub fn commit_slice<T: Pod>(slice: &[T]) {
     journal().write_slice(slice);
 }
 
+/// Commit `data` to the journal, preceded by the digest of its [JournalSchema], so that the host
+/// can check that it decodes the journal with the same schema.
+///
+/// This is called by the `commit` method of structs declared with
+/// [journal_schema][crate::journal_schema].
+pub fn commit_schema<T: JournalSchema>(data: &T) {
+    let words = schema::encode(data).unwrap();
+    commit_slice(&words);
+}
+
+/// Commit the root of a [RedactableJournal] to the journal, so that the host can later reveal
+/// any subset of its fields.
+///
+/// Only the root is committed. The fields must be sent to the host separately, e.g. with [write].
+/// The root must be the only data committed to the journal, as checked by
+/// [verify_journal][crate::redaction::RedactedJournal::verify_journal]. See [crate::redaction] for
+/// details.
+pub fn commit_redactable(journal: &RedactableJournal) {
+    commit_slice(journal.root().as_words());
+}
+
+/// Commit a named field that the host can later reveal or redact independently of the others.
+///
+/// Each field is committed with a random salt and sent to the host, which collects the fields of
+/// a session in `Session::committed_fields`. When the guest halts, the root of a
+/// [RedactableJournal] over all fields committed this way is committed as the whole journal, as
+/// checked by [verify_journal][crate::redaction::RedactedJournal::verify_journal]. See
+/// [crate::redaction] for details.
+///
+/// # Panics
+///
+/// Panics if a field with the same name was already committed, since the verifier could not tell
+/// which of them it was given, or if other data was committed to the journal.
+///
+/// # Example
+///
+/// ```no_run
+/// use risc0_zkvm::guest::env;
+///
+/// env::commit_field("age", &30u32.to_le_bytes());
+/// env::commit_field("name", b"alice");
+/// ```
+pub fn commit_field(name: &str, bytes: &[u8]) {
+    assert_journal_empty();
+    let mut salt = [0u32; 8];
+    unsafe { syscall::sys_rand(salt.as_mut_ptr(), salt.len()) };
+    let journal = unsafe { &mut *core::ptr::addr_of_mut!(COMMITTED_FIELDS) };
+    if let Err(err) = journal.push_named(name, salt.into(), bytes.to_vec()) {
+        panic!("env::commit_field: {err}");
+    }
+
+    let field = journal.fields.last().unwrap();
+    let words = crate::serde::to_vec(field).unwrap();
+    syscall(SYS_COMMIT_FIELD, bytemuck::cast_slice(&words), &mut []);
+}
+
+/// Check that nothing but the root of the fields committed with [commit_field] is in the journal.
+fn assert_journal_empty() {
+    assert_eq!(
+        unsafe { JOURNAL_LEN },
+        0,
+        "env::commit_field cannot be combined with other journal data"
+    );
+}
+
+/// Call `f`, or return its result from an earlier call in this session with the same `function`
+/// name and `args`.
+///
+/// `f` must be a pure function of `args`. The first call runs `f`, records a digest of its result
+/// in the guest, and sends the result to the host, which caches it for the rest of the session.
+/// Later calls fetch the result from the host instead of running `f` again, and check it against
+/// the recorded digest, falling back to running `f` if the host does not return the same result.
+/// The guest therefore only needs to store 64 bytes per distinct call, and the claim of the
+/// receipt is the same whether or not the host answers from its cache.
+///
+/// This is used by [memoize!][crate::memoize], which derives `function` from the path of the
+/// function it wraps.
+///
+/// # Example
+///
+/// ```no_run
+/// use risc0_zkvm::guest::env;
+///
+/// fn fib(n: u64) -> u64 {
+///     (0..n).fold((0u64, 1u64), |(a, b), _| (b, a.wrapping_add(b))).0
+/// }
+///
+/// let x = env::memoize("fib", &1000u64, || fib(1000));
+/// let y = env::memoize("fib", &1000u64, || fib(1000)); // answered by the host
+/// assert_eq!(x, y);
+/// ```
+pub fn memoize<A: Serialize, R: Serialize + DeserializeOwned>(
+    function: &str,
+    args: &A,
+    f: impl FnOnce() -> R,
+) -> R {
+    let args = crate::serde::to_vec(args).unwrap();
+    let key = tagged_struct::<sha::Impl>(
+        "risc0.MemoizedCall",
+        &[
+            *sha::Impl::hash_bytes(function.as_bytes()),
+            *sha::Impl::hash_words(&args),
+        ],
+        &[],
+    );
+    let memoized = unsafe { &mut *core::ptr::addr_of_mut!(MEMOIZED) };
+
+    if let Some(result_digest) = memoized.get(&key) {
+        let syscall::Return(nbytes, _) = syscall(SYS_MEMOIZE_GET, key.as_bytes(), &mut []);
+        if nbytes != u32::MAX {
+            let mut words = vec![0u32; nbytes as usize / WORD_SIZE];
+            syscall(SYS_MEMOIZE_GET, key.as_bytes(), &mut words);
+            if *sha::Impl::hash_words(&words) == *result_digest {
+                return crate::serde::from_slice(&words).unwrap();
+            }
+        }
+    }
+
+    let result = f();
+    let words = crate::serde::to_vec(&result).unwrap();
+    memoized.insert(key, *sha::Impl::hash_words(&words));
+    let to_host = [key.as_bytes(), bytemuck::cast_slice(&words)].concat();
+    syscall(SYS_MEMOIZE_PUT, &to_host, &mut []);
+    result
+}
+
+/// Encrypt `data` to the recipient supplied by the host, and commit the result to the journal.
+///
+/// The recipient's public key is set on the host with
+/// `ExecutorEnvBuilder::journal_recipient`, and execution fails if it is not set. The journal
+/// commits to the [EncryptedJournal][crate::encryption::EncryptedJournal], including the
+/// recipient's key, and only the recipient can read `data`, with
+/// [Receipt::decrypt_journal][crate::Receipt::decrypt_journal]. This should be the only commit
+/// made by the guest. The host that runs the guest can still read `data`; see
+/// [crate::encryption] for the trust model.
+#[cfg(feature = "journal-encryption")]
+pub fn commit_encrypted<T: Serialize>(data: &T) {
+    let mut recipient = [0u32; 8];
+    syscall(syscall::nr::SYS_JOURNAL_RECIPIENT, &[], &mut recipient);
+    let mut ephemeral_secret = [0u32; 8];
+    unsafe { syscall::sys_rand(ephemeral_secret.as_mut_ptr(), ephemeral_secret.len()) };
+    let mut nonce = [0u32; 3];
+    unsafe { syscall::sys_rand(nonce.as_mut_ptr(), nonce.len()) };
+
+    let plaintext = crate::serde::to_vec(data).unwrap();
+    let encrypted = crate::encryption::EncryptedJournal::encrypt(
+        bytemuck::cast_ref(&recipient),
+        bytemuck::cast(ephemeral_secret),
+        bytemuck::cast(nonce),
+        bytemuck::cast_slice(&plaintext),
+    );
+    commit(&encrypted);
+}
+
+/// Send any buffered journal data to the host.
+///
+/// Data committed to the journal with [commit], [commit_slice], or a [journal] writer is
+/// buffered in the guest and sent to the host in page-sized batches, which saves the overhead of
+/// one syscall per commit. The buffer is flushed automatically when the guest exits or pauses.
+/// Call this function before writing to [fileno::JOURNAL] directly with `sys_write` to make sure
+/// the journal data stays in order.
+pub fn flush_journal() {
+    unsafe {
+        if JOURNAL_BUF_LEN > 0 {
+            sys_write(fileno::JOURNAL, JOURNAL_BUF.as_ptr(), JOURNAL_BUF_LEN);
+            JOURNAL_BUF_LEN = 0;
+        }
+    }
+}
+
+/// Append bytes to the journal buffer, flushing it when full.
+fn write_journal(bytes: &[u8]) {
+    unsafe {
+        if JOURNAL_BUF_LEN + bytes.len() > JOURNAL_BUF_SIZE {
+            flush_journal();
+        }
+        if bytes.len() >= JOURNAL_BUF_SIZE {
+            // Large writes bypass the buffer entirely.
+            sys_write(fileno::JOURNAL, bytes.as_ptr(), bytes.len());
+            return;
+        }
+        JOURNAL_BUF[JOURNAL_BUF_LEN..JOURNAL_BUF_LEN + bytes.len()].copy_from_slice(bytes);
+        JOURNAL_BUF_LEN += bytes.len();
+    }
+}
+
 /// Return the number of processor cycles that have occurred since the guest
 /// began.
 ///
@@ -533,8 +901,9 @@ pub fn stderr() -> FdWriter<impl for<'a> Fn(&'a [u8])> {
 
 /// Return a writer for the JOURNAL.
 pub fn journal() -> FdWriter<impl for<'a> Fn(&'a [u8])> {
-    FdWriter::new(fileno::JOURNAL, |bytes| {
-        unsafe { HASHER.get_mut().unwrap_unchecked().update(bytes) };
+    FdWriter::new(fileno::JOURNAL, |bytes| unsafe {
+        HASHER.get_mut().unwrap_unchecked().update(bytes);
+        JOURNAL_LEN += bytes.len();
     })
 }
 
@@ -543,6 +912,14 @@ pub fn stdin() -> FdReader {
     FdReader::new(fileno::STDIN)
 }
 
+/// Open a stream that the host registered under the given name.
+///
+/// Returns `None` if the host did not register a stream with this name.
+pub fn open(name: &str) -> Option<FdReader> {
+    let fd = unsafe { sys_open(name.as_ptr(), name.len()) };
+    (fd != fileno::INVALID).then(|| FdReader::new(fd))
+}
+
 /// Reads and deserializes objects
 pub trait Read {
     /// Read data from the host.
@@ -575,23 +952,17 @@ impl FdReader {
 
     #[must_use = "read_bytes can potentially do a short read; this case should be handled."]
     fn read_bytes(&mut self, buf: &mut [u8]) -> usize {
-        unsafe { sys_read(self.fd, buf.as_mut_ptr(), buf.len()) }
+        let nread = unsafe { sys_read(self.fd, buf.as_mut_ptr(), buf.len()) };
+        record_read(self.fd, &buf[..nread], nread == 0 && !buf.is_empty());
+        nread
     }
 
     // Like read_bytes, but fills the buffer completely or until EOF occurs.
     #[must_use = "read_bytes_all can potentially return EOF; this case should be handled."]
-    fn read_bytes_all(&mut self, mut buf: &mut [u8]) -> usize {
-        let mut tot_read = 0;
-        while !buf.is_empty() {
-            let nread = self.read_bytes(buf);
-            if nread == 0 {
-                break;
-            }
-            tot_read += nread;
-            (_, buf) = buf.split_at_mut(nread);
-        }
-
-        tot_read
+    fn read_bytes_all(&mut self, buf: &mut [u8]) -> usize {
+        let nread = unsafe { sys_read_exact(self.fd, buf.as_mut_ptr(), buf.len()) };
+        record_read(self.fd, &buf[..nread], nread < buf.len());
+        nread
     }
 }
 
@@ -616,6 +987,11 @@ impl Read for FdReader {
 impl WordRead for FdReader {
     fn read_words(&mut self, words: &mut [u32]) -> crate::serde::Result<()> {
         let nread_bytes = unsafe { sys_read_words(self.fd, words.as_mut_ptr(), words.len()) };
+        record_read(
+            self.fd,
+            &bytemuck::cast_slice(words)[..nread_bytes],
+            nread_bytes < words.len() * WORD_SIZE,
+        );
         if nread_bytes == words.len() * WORD_SIZE {
             Ok(())
         } else {
@@ -647,6 +1023,19 @@ impl std::io::Read for FdReader {
     }
 }
 
+#[cfg(feature = "std")]
+impl std::io::Seek for FdReader {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        let (offset, whence) = match pos {
+            std::io::SeekFrom::Start(offset) => (offset as i64, syscall::whence::SET),
+            std::io::SeekFrom::Current(offset) => (offset, syscall::whence::CUR),
+            std::io::SeekFrom::End(offset) => (offset, syscall::whence::END),
+        };
+        let pos = syscall::sys_lseek(self.fd, offset, whence);
+        u64::try_from(pos).map_err(|_| std::io::Error::other("file descriptor is not seekable"))
+    }
+}
+
 /// Serializes and writes objects.
 pub trait Write {
     /// Write a serialized object.
@@ -679,7 +1068,11 @@ impl<F: Fn(&[u8])> FdWriter<F> {
     }
 
     fn write_bytes(&mut self, bytes: &[u8]) {
-        unsafe { sys_write(self.fd, bytes.as_ptr(), bytes.len()) }
+        if self.fd == fileno::JOURNAL {
+            write_journal(bytes);
+        } else {
+            unsafe { sys_write(self.fd, bytes.as_ptr(), bytes.len()) }
+        }
         (self.hook)(bytes);
     }
 }
@@ -737,11 +1130,97 @@ pub fn input_digest() -> Digest {
     ])
 }
 
+/// Read all of stdin, all of the arguments, and the environment variables named in `env_vars`,
+/// and commit the digest of the resulting [Statement] to the journal.
+///
+/// The guest hashes the inputs it reads itself, so the journal binds the receipt to them. The
+/// guest should take its inputs from the returned [Statement], e.g. by decoding
+/// [Statement::stdin] with [crate::serde::from_slice], rather than asking the host for them
+/// again. Environment variables that are not set are left out of the [Statement].
+///
+/// If the host set an [input_digest], e.g. with `ExecutorEnvBuilder::capture_statement`, the
+/// guest also checks that it is the digest of the [Statement], so that the claim of the receipt
+/// binds the same inputs.
+///
+/// # Panics
+///
+/// Panics if stdin was read before, since the data already read would not be part of the
+/// [Statement], or if the inputs do not match the [input_digest].
+///
+/// # Example
+///
+/// ```no_run
+/// use risc0_zkvm::guest::env;
+///
+/// let statement = env::commit_statement(&["MODE"]);
+/// let n: u32 = risc0_zkvm::serde::from_slice(&statement.stdin).unwrap();
+/// ```
+pub fn commit_statement(env_vars: &[&str]) -> Statement {
+    assert!(
+        !unsafe { STDIN_READ },
+        "env::commit_statement must be called before anything is read from stdin"
+    );
+    let mut stdin_data = Vec::new();
+    let mut buf = [0u8; 1024];
+    let mut reader = stdin();
+    loop {
+        let nread = reader.read_bytes(&mut buf);
+        if nread == 0 {
+            break;
+        }
+        stdin_data.extend_from_slice(&buf[..nread]);
+    }
+    let args = (0..syscall::sys_argc())
+        .map(|idx| {
+            read_host_string(|words, nwords| unsafe { syscall::sys_argv(words, nwords, idx) })
+                .unwrap()
+        })
+        .collect();
+    let env_vars = env_vars
+        .iter()
+        .filter_map(|&name| {
+            let value = read_host_string(|words, nwords| unsafe {
+                syscall::sys_getenv(words, nwords, name.as_ptr(), name.len())
+            })?;
+            Some((name.into(), value))
+        })
+        .collect();
+    let statement = Statement {
+        stdin: stdin_data,
+        args,
+        env_vars,
+    };
+
+    let digest = statement.digest();
+    let expected = input_digest();
+    assert!(
+        expected == Digest::ZERO || expected == digest,
+        "the inputs of the guest do not match the input digest"
+    );
+    commit_slice(digest.as_words());
+    statement
+}
+
+/// Read a string from the host with `read`, which is called with a buffer of words and its length
+/// and returns the length of the string in bytes, or `usize::MAX` if there is none.
+fn read_host_string(read: impl Fn(*mut u32, usize) -> usize) -> Option<String> {
+    let len = read(core::ptr::null_mut(), 0);
+    if len == usize::MAX {
+        return None;
+    }
+    let mut words = vec![0u32; align_up(len, WORD_SIZE) / WORD_SIZE];
+    read(words.as_mut_ptr(), words.len());
+    let bytes = &bytemuck::cast_slice::<u32, u8>(&words)[..len];
+    Some(String::from_utf8(bytes.to_vec()).expect("host sent a string that is not UTF-8"))
+}
+
 /// EXPERIMENTAL: Run the given function without proving that it was executed
 /// correctly.  This does not provide any guarantees about the
 /// soundness of the execution, but can potentially be executed
 /// faster.
 pub fn run_unconstrained(f: impl FnOnce()) {
+    // The child process must not inherit buffered journal data.
+    flush_journal();
     let pid = sys_fork();
     if pid == 0 {
         f();
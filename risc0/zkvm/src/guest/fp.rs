@@ -0,0 +1,164 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks of the floating-point environment of the guest.
+//!
+//! The zkVM has no floating-point unit, so `f32` and `f64` arithmetic is done in software by the
+//! routines of `compiler_builtins` linked into the guest. These round to nearest with ties to even
+//! and support subnormal numbers, and do not depend on the machine of the prover, so the results
+//! of a guest are the same wherever it is proven. A numerical guest can check these semantics
+//! with [assert_fp_env], or [commit][commit_fp_env] to them so that verifiers of its receipt know
+//! the semantics its results were computed with.
+//!
+//! The environment is [measured][measure] by arithmetic whose result depends on it, so the check
+//! holds even if the host lies. The host reports the environment it expects in answer to the
+//! `SYS_FP_ENV` syscall, which lets a guest built with different floating-point routines fail
+//! loudly rather than produce results that differ from the host's.
+
+use core::hint::black_box;
+
+use risc0_zkvm_platform::syscall::{fp_env, nr::SYS_FP_ENV};
+use serde::{Deserialize, Serialize};
+
+use super::env;
+
+/// A rounding mode of floating-point arithmetic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Rounding {
+    /// Round to nearest, ties to even.
+    NearestEven,
+    /// Round towards zero.
+    TowardZero,
+    /// Round down, towards negative infinity.
+    Down,
+    /// Round up, towards positive infinity.
+    Up,
+    /// Round to nearest, ties to max magnitude.
+    NearestMaxMagnitude,
+}
+
+/// The semantics of floating-point arithmetic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FpEnv {
+    /// The rounding mode of arithmetic.
+    pub rounding: Rounding,
+    /// Whether subnormal numbers are supported rather than flushed to zero.
+    pub subnormals: bool,
+}
+
+impl FpEnv {
+    /// The environment of software floating point in the zkVM.
+    pub const ZKVM: Self = Self {
+        rounding: Rounding::NearestEven,
+        subnormals: true,
+    };
+
+    /// Decode an environment from a word packed as in [fp_env], or `None` if the word is invalid.
+    pub fn from_word(word: u32) -> Option<Self> {
+        let rounding = match word & fp_env::ROUNDING_MASK {
+            fp_env::RNE => Rounding::NearestEven,
+            fp_env::RTZ => Rounding::TowardZero,
+            fp_env::RDN => Rounding::Down,
+            fp_env::RUP => Rounding::Up,
+            fp_env::RMM => Rounding::NearestMaxMagnitude,
+            _ => return None,
+        };
+        if word & !(fp_env::ROUNDING_MASK | fp_env::SUBNORMALS) != 0 {
+            return None;
+        }
+        Some(Self {
+            rounding,
+            subnormals: word & fp_env::SUBNORMALS != 0,
+        })
+    }
+
+    /// Encode the environment into a word packed as in [fp_env].
+    pub fn to_word(self) -> u32 {
+        let rounding = match self.rounding {
+            Rounding::NearestEven => fp_env::RNE,
+            Rounding::TowardZero => fp_env::RTZ,
+            Rounding::Down => fp_env::RDN,
+            Rounding::Up => fp_env::RUP,
+            Rounding::NearestMaxMagnitude => fp_env::RMM,
+        };
+        let subnormals = if self.subnormals {
+            fp_env::SUBNORMALS
+        } else {
+            0
+        };
+        rounding | subnormals
+    }
+}
+
+/// Measure the floating-point environment of the guest with arithmetic whose result depends on it.
+pub fn measure() -> FpEnv {
+    let one = black_box(1.0f64);
+    let eps = black_box(f64::EPSILON);
+    // Halfway between 1 and the next number up, above halfway, and their negations.
+    let tie = one + eps / 2.0;
+    let above = one + eps * 0.75;
+    let neg_below = -one - eps / 4.0;
+    let neg_tie = -one - eps / 2.0;
+
+    let rounding = if above == one {
+        if neg_below == -one {
+            Rounding::TowardZero
+        } else {
+            Rounding::Down
+        }
+    } else if tie == one {
+        Rounding::NearestEven
+    } else if neg_tie == -one {
+        Rounding::Up
+    } else {
+        Rounding::NearestMaxMagnitude
+    };
+    let subnormals = black_box(f64::MIN_POSITIVE) / 2.0 > 0.0;
+    FpEnv {
+        rounding,
+        subnormals,
+    }
+}
+
+/// The floating-point environment that the host expects the guest to have.
+///
+/// The answer comes from the host and is not checked; use [measure] to learn the environment of
+/// the guest.
+pub fn host_fp_env() -> Option<FpEnv> {
+    let ret = env::syscall(SYS_FP_ENV, &[], &mut []);
+    FpEnv::from_word(ret.0)
+}
+
+/// Assert that the guest has the floating-point environment of the zkVM, and that the host
+/// expects it.
+pub fn assert_fp_env() {
+    let measured = measure();
+    assert_eq!(
+        measured,
+        FpEnv::ZKVM,
+        "floating-point environment differs from the zkVM"
+    );
+    assert_eq!(
+        host_fp_env(),
+        Some(measured),
+        "floating-point environment differs from the host"
+    );
+}
+
+/// [Assert][assert_fp_env] the floating-point environment of the guest, and commit it to the
+/// journal.
+pub fn commit_fp_env() {
+    assert_fp_env();
+    env::commit(&FpEnv::ZKVM);
+}
@@ -79,15 +79,21 @@
 
 #![deny(missing_docs)]
 
+pub mod bigint;
 pub mod env;
+pub mod fixed;
+pub mod fp;
 pub use risc0_zkp::core::hash::sha;
 
+use alloc::{collections::BTreeSet, vec::Vec};
 #[cfg(target_os = "zkvm")]
 use core::arch::asm;
 
+use risc0_binfmt::tagged_list;
 use risc0_zkvm_platform::syscall::sys_panic;
 
 pub use crate::entry;
+use crate::sha::{Digest, Impl, Sha256 as _};
 
 #[cfg(target_os = "zkvm")]
 core::arch::global_asm!(include_str!("memset.s"));
@@ -147,6 +153,121 @@ macro_rules! entry {
     };
 }
 
+/// Declare the syscalls that the guest is allowed to make.
+///
+/// The names are embedded in the `.risc0.syscalls` section of the guest ELF, which is loaded into
+/// the memory image, so the image ID commits to them. An executor constructed from the ELF fails
+/// at the first syscall that is not listed. Standard syscalls are named by their constant in
+/// [risc0_zkvm_platform::syscall::nr], e.g. `SYS_READ`, and others by the full name given to
+/// [declare_syscall][crate::declare_syscall], as a string literal.
+///
+/// The guest also commits the digest of the manifest to the journal, so that a verifier can check
+/// which syscalls the guest declared from the receipt alone, with
+/// `SyscallManifest::verify_journal`.
+/// The manifest is only enforced by the executor, so it bounds what an honest host provides to
+/// the guest, not what a malicious one could.
+///
+/// The guest runtime may always panic, draw randomness, and write to the journal. Invoke this
+/// macro at the start of `main`, before anything else is committed, since the digest must be the
+/// start of the journal. This cannot be combined with [env::commit_field], which requires the
+/// journal to hold nothing else.
+///
+/// # Example
+///
+/// ```ignore
+/// fn main() {
+///     risc0_zkvm::syscall_manifest!(SYS_READ, "my_crate::SYS_CUSTOM");
+///     // ...
+/// }
+/// ```
+#[macro_export]
+macro_rules! syscall_manifest {
+    ($($name:tt),* $(,)?) => {{
+        const MANIFEST: &str = concat!($($crate::__syscall_manifest_name!($name), "\n"),*);
+        #[used]
+        #[link_section = ".risc0.syscalls"]
+        static SYSCALL_MANIFEST: [u8; MANIFEST.len()] = $crate::guest::manifest_bytes(MANIFEST);
+        // Reference the manifest so that the linker keeps its section.
+        unsafe { ::core::ptr::read_volatile(SYSCALL_MANIFEST.as_ptr()) };
+        $crate::guest::commit_syscall_manifest(MANIFEST);
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __syscall_manifest_name {
+    ($name:ident) => {
+        concat!("risc0_zkvm_platform::syscall::nr::", stringify!($name))
+    };
+    ($name:literal) => {
+        $name
+    };
+}
+
+/// Define a function whose results are memoized with [env::memoize].
+///
+/// Calls with arguments that serialize to the same words as an earlier call in the session are
+/// answered from the executor's cache, and checked by the guest against a digest of the earlier
+/// result. The function must be pure, its arguments and result must implement `Serialize`, and
+/// its result must implement `DeserializeOwned`. Calls are keyed by the module path and name of
+/// the function.
+///
+/// # Example
+///
+/// ```ignore
+/// risc0_zkvm::memoize! {
+///     fn modexp(base: u64, exp: u64, modulus: u64) -> u64 {
+///         (0..exp).fold(1, |acc, _| acc * base % modulus)
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! memoize {
+    (
+        $(#[$attr:meta])*
+        $vis:vis fn $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty $body:block
+    ) => {
+        $(#[$attr])*
+        $vis fn $name($($arg: $ty),*) -> $ret {
+            // Serialize the arguments before the body can take ownership of them.
+            let args = $crate::serde::to_vec(&($(&$arg,)*)).unwrap();
+            $crate::guest::env::memoize(
+                concat!(module_path!(), "::", stringify!($name)),
+                &args,
+                move || -> $ret { $body },
+            )
+        }
+    };
+}
+
+/// Commit the digest of the syscall manifest declared with [syscall_manifest!] to the journal.
+#[doc(hidden)]
+pub fn commit_syscall_manifest(manifest: &str) {
+    env::commit_slice(syscall_manifest_digest(manifest.split('\n')).as_words());
+}
+
+/// The digest of a syscall manifest with the given names, which may be repeated or empty.
+pub(crate) fn syscall_manifest_digest<'a>(names: impl IntoIterator<Item = &'a str>) -> Digest {
+    let names: BTreeSet<&str> = names.into_iter().filter(|name| !name.is_empty()).collect();
+    let names: Vec<Digest> = names
+        .into_iter()
+        .map(|name| *Impl::hash_bytes(name.as_bytes()))
+        .collect();
+    tagged_list::<Impl>("risc0.SyscallManifest", &names)
+}
+
+#[doc(hidden)]
+pub const fn manifest_bytes<const N: usize>(manifest: &str) -> [u8; N] {
+    let bytes = manifest.as_bytes();
+    let mut out = [0u8; N];
+    let mut i = 0;
+    while i < N {
+        out[i] = bytes[i];
+        i += 1;
+    }
+    out
+}
+
 #[cfg(target_os = "zkvm")]
 #[no_mangle]
 unsafe extern "C" fn __start() -> ! {
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::path::Path;
+use std::{path::Path, time::Instant};
 
 use anyhow::{anyhow, bail, Result};
 use bytes::Bytes;
@@ -452,7 +452,41 @@ impl Client {
         env: &ExecutorEnv<'_>,
         binary: pb::api::Asset,
     ) -> Result<pb::api::ExecutorEnv> {
-        Ok(pb::api::ExecutorEnv {
+        // These run on the host that builds the env, so a remote executor cannot honor them.
+        let mut local_only = vec![];
+        if env.cycle_meter.is_some() {
+            local_only.push("cycle_meter");
+        }
+        if env.tenant.is_some() {
+            local_only.push("tenant");
+        }
+        if env.journal_tee.is_some() {
+            local_only.push("journal_tee");
+        }
+        if !env.authenticated_stores.is_empty() {
+            local_only.push("authenticated_store");
+        }
+        #[cfg(feature = "executor")]
+        {
+            if env.on_runaway.is_some() {
+                local_only.push("on_runaway");
+            }
+            if env.progress_callback.is_some() {
+                local_only.push("progress_callback");
+            }
+            if env.execution_cache.is_some() {
+                local_only.push("execution_cache");
+            }
+        }
+        if !local_only.is_empty() {
+            bail!(
+                "not supported by a remote executor: {}",
+                local_only.join(", ")
+            );
+        }
+
+        #[allow(unused_mut)]
+        let mut request = pb::api::ExecutorEnv {
             binary: Some(binary),
             env_vars: env.env_vars.clone(),
             args: env.args.clone(),
@@ -460,8 +494,17 @@ impl Client {
             read_fds: env.posix_io.borrow().read_fds(),
             write_fds: env.posix_io.borrow().write_fds(),
             segment_limit_po2: env.segment_limit_po2,
+            segment_po2_schedule: env.segment_po2_schedule.clone(),
             session_limit: env.session_limit,
             trace_events: (!env.trace.is_empty()).then_some(()),
+            trace_prev_values: env.trace_prev_values,
+            trace_paging: env.trace_paging,
+            linux_compat: env.linux_compat,
+            insn_stats: env.insn_stats,
+            journal_recipient: env.journal_recipient.map(Vec::from),
+            input_digest: env.input_digest.map(Into::into),
+            session_id: env.session_id.clone(),
+            watchpoints: env.watchpoints.iter().map(Into::into).collect(),
             pprof_out: env
                 .pprof_out
                 .as_ref()
@@ -502,7 +545,56 @@ impl Client {
                 .as_ref()
                 .map(|x| x.path().to_string_lossy().into())
                 .unwrap_or_default(),
-        })
+            fd_quotas: env
+                .posix_io
+                .borrow()
+                .quotas
+                .iter()
+                .map(|(fd, quota)| (*fd, (*quota).into()))
+                .collect(),
+            pause_at_cycle: env.pause_at_cycle,
+            wall_clock_timeout_ms: env.wall_clock_deadline.map(|deadline| {
+                deadline
+                    .saturating_duration_since(Instant::now())
+                    .as_millis()
+                    .try_into()
+                    .unwrap_or(u64::MAX)
+            }),
+            stack_size: env.stack_size,
+            hugepages: env.hugepages,
+            page_heatmap_out: env
+                .page_heatmap_out
+                .as_ref()
+                .map(|x| x.to_string_lossy().into())
+                .unwrap_or_default(),
+            commit_input: env.commit_input,
+            capture_statement: env.capture_statement,
+            record_transcript: env.record_transcript,
+            record_transcript_out: env
+                .record_transcript_out
+                .as_ref()
+                .map(|x| x.to_string_lossy().into())
+                .unwrap_or_default(),
+            audit_nondeterminism: env.audit_nondeterminism,
+            replay_transcript: env
+                .replay_transcript
+                .as_ref()
+                .map(bincode::serialize)
+                .transpose()?,
+            self_check: env.self_check,
+            perf_warnings: env.perf_warnings,
+            taint_tracking: env.taint_tracking,
+            cycle_audit: env.cycle_audit,
+            runaway_heuristics: None,
+            syscall_manifest: None,
+            segments_in_memory: env.segments_in_memory,
+        };
+        #[cfg(feature = "executor")]
+        {
+            request.runaway_heuristics = env.runaway_heuristics.clone().map(Into::into);
+            request.syscall_manifest = env.syscall_manifest.as_ref().map(Into::into);
+        }
+        Ok(request)
     }
 
     fn execute_handler<F>(
@@ -529,21 +621,27 @@ impl Client {
                             conn.send(msg)?;
                         }
                         pb::api::client_callback::Kind::SegmentDone(segment) => {
-                            let reply: pb::api::GenericReply = segment
-                                .segment
-                                .map_or_else(
-                                    || Err(malformed_err()),
-                                    |segment| {
-                                        let asset =
-                                            segment.segment.ok_or(malformed_err())?.try_into()?;
-                                        let info = SegmentInfo {
-                                            po2: segment.po2,
-                                            cycles: segment.cycles,
-                                        };
-                                        segments.push(info.clone());
-                                        segment_callback(info, asset)
-                                    },
-                                )
+                            // The server flushes the trace at the end of each segment, before
+                            // the segment is done.
+                            let reply: pb::api::GenericReply = self
+                                .flush_trace(env)
+                                .and_then(|()| {
+                                    segment.segment.map_or_else(
+                                        || Err(malformed_err()),
+                                        |segment| {
+                                            let asset = segment
+                                                .segment
+                                                .ok_or(malformed_err())?
+                                                .try_into()?;
+                                            let info = SegmentInfo {
+                                                po2: segment.po2,
+                                                cycles: segment.cycles,
+                                            };
+                                            segments.push(info.clone());
+                                            segment_callback(info, asset)
+                                        },
+                                    )
+                                })
                                 .into();
                             tracing::trace!("tx: {reply:?}");
                             conn.send(reply)?;
@@ -664,6 +762,13 @@ impl Client {
         }
         Ok(())
     }
+
+    fn flush_trace(&self, env: &ExecutorEnv<'_>) -> Result<()> {
+        for trace_callback in env.trace.iter() {
+            trace_callback.borrow_mut().flush()?;
+        }
+        Ok(())
+    }
 }
 
 impl From<Result<Bytes, anyhow::Error>> for pb::api::OnIoReply {
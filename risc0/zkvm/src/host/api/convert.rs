@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{fmt::Debug, path::PathBuf};
+use std::{fmt::Debug, ops::Range, path::PathBuf};
 
 use anyhow::{anyhow, bail, Result};
 use prost::{Message, Name};
@@ -28,9 +28,12 @@ use crate::{
         SuccinctReceipt,
     },
     receipt_claim::Unknown,
-    Assumption, Assumptions, ExitCode, Groth16Receipt, Input, Journal, MaybePruned, Output,
-    ProveInfo, ProverOpts, Receipt, ReceiptClaim, ReceiptKind, SessionStats, TraceEvent,
+    Assumption, Assumptions, ExitCode, FdQuota, FdUsage, Groth16Receipt, Input, Journal,
+    MaybePruned, Output, ProveInfo, ProverOpts, Receipt, ReceiptClaim, ReceiptKind, SessionStats,
+    TraceEvent, WatchKind,
 };
+#[cfg(feature = "executor")]
+use crate::{RunawayHeuristics, SyscallManifest, TightLoop};
 
 mod ver {
     use super::pb::base::CompatVersion;
@@ -107,9 +110,11 @@ impl TryFrom<pb::api::Asset> for Asset {
     }
 }
 
-impl From<TraceEvent> for pb::api::TraceEvent {
-    fn from(event: TraceEvent) -> Self {
-        match event {
+impl TryFrom<TraceEvent> for pb::api::TraceEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(event: TraceEvent) -> Result<Self> {
+        Ok(match event {
             TraceEvent::InstructionStart { cycle, pc, insn } => Self {
                 kind: Some(pb::api::trace_event::Kind::InsnStart(
                     pb::api::trace_event::InstructionStart { cycle, pc, insn },
@@ -132,7 +137,45 @@ impl From<TraceEvent> for pb::api::TraceEvent {
                     },
                 )),
             },
-        }
+            TraceEvent::MemoryRegionSet { addr, bytes } => Self {
+                kind: Some(pb::api::trace_event::Kind::MemoryRegionSet(
+                    pb::api::trace_event::MemoryRegionSet { addr, bytes },
+                )),
+            },
+            TraceEvent::Paging { pc, cycles } => Self {
+                kind: Some(pb::api::trace_event::Kind::Paging(
+                    pb::api::trace_event::Paging { pc, cycles },
+                )),
+            },
+            TraceEvent::Watchpoint { pc, addr, kind } => Self {
+                kind: Some(pb::api::trace_event::Kind::Watchpoint(
+                    pb::api::trace_event::Watchpoint {
+                        pc,
+                        addr,
+                        kind: pb::api::WatchKind::from(kind) as i32,
+                    },
+                )),
+            },
+            TraceEvent::RegisterPrev { idx, value } => Self {
+                kind: Some(pb::api::trace_event::Kind::RegisterPrev(
+                    pb::api::trace_event::RegisterPrev {
+                        idx: idx as u32,
+                        value,
+                    },
+                )),
+            },
+            TraceEvent::MemoryPrev { addr, region } => Self {
+                kind: Some(pb::api::trace_event::Kind::MemoryPrev(
+                    pb::api::trace_event::MemoryPrev { addr, region },
+                )),
+            },
+            TraceEvent::MemoryRegionPrev { addr, bytes } => Self {
+                kind: Some(pb::api::trace_event::Kind::MemoryRegionPrev(
+                    pb::api::trace_event::MemoryRegionPrev { addr, bytes },
+                )),
+            },
+            event => bail!("Unsupported trace event: {event:?}"),
+        })
     }
 }
 
@@ -154,10 +197,137 @@ impl TryFrom<pb::api::TraceEvent> for TraceEvent {
                 addr: event.addr,
                 region: event.region,
             },
+            pb::api::trace_event::Kind::MemoryRegionSet(event) => TraceEvent::MemoryRegionSet {
+                addr: event.addr,
+                bytes: event.bytes,
+            },
+            pb::api::trace_event::Kind::Paging(event) => TraceEvent::Paging {
+                pc: event.pc,
+                cycles: event.cycles,
+            },
+            pb::api::trace_event::Kind::Watchpoint(event) => TraceEvent::Watchpoint {
+                pc: event.pc,
+                addr: event.addr,
+                kind: watch_kind_from_pb(event.kind)?,
+            },
+            pb::api::trace_event::Kind::RegisterPrev(event) => TraceEvent::RegisterPrev {
+                idx: event.idx as usize,
+                value: event.value,
+            },
+            pb::api::trace_event::Kind::MemoryPrev(event) => TraceEvent::MemoryPrev {
+                addr: event.addr,
+                region: event.region,
+            },
+            pb::api::trace_event::Kind::MemoryRegionPrev(event) => TraceEvent::MemoryRegionPrev {
+                addr: event.addr,
+                bytes: event.bytes,
+            },
         })
     }
 }
 
+impl From<WatchKind> for pb::api::WatchKind {
+    fn from(kind: WatchKind) -> Self {
+        match kind {
+            WatchKind::Read => Self::Read,
+            WatchKind::Write => Self::Write,
+            WatchKind::Access => Self::Access,
+        }
+    }
+}
+
+fn watch_kind_from_pb(kind: i32) -> Result<WatchKind> {
+    Ok(match kind {
+        0 => WatchKind::Read,
+        1 => WatchKind::Write,
+        2 => WatchKind::Access,
+        _ => return Err(malformed_err()),
+    })
+}
+
+impl From<&(Range<u32>, WatchKind)> for pb::api::Watchpoint {
+    fn from((range, kind): &(Range<u32>, WatchKind)) -> Self {
+        Self {
+            start: range.start,
+            end: range.end,
+            kind: pb::api::WatchKind::from(*kind) as i32,
+        }
+    }
+}
+
+impl TryFrom<pb::api::Watchpoint> for (Range<u32>, WatchKind) {
+    type Error = anyhow::Error;
+
+    fn try_from(watchpoint: pb::api::Watchpoint) -> Result<Self> {
+        Ok((
+            watchpoint.start..watchpoint.end,
+            watch_kind_from_pb(watchpoint.kind)?,
+        ))
+    }
+}
+
+impl From<FdQuota> for pb::api::FdQuota {
+    fn from(quota: FdQuota) -> Self {
+        Self {
+            max_bytes: quota.max_bytes,
+            max_calls: quota.max_calls,
+        }
+    }
+}
+
+impl From<pb::api::FdQuota> for FdQuota {
+    fn from(quota: pb::api::FdQuota) -> Self {
+        Self {
+            max_bytes: quota.max_bytes,
+            max_calls: quota.max_calls,
+        }
+    }
+}
+
+#[cfg(feature = "executor")]
+impl From<RunawayHeuristics> for pb::api::RunawayHeuristics {
+    fn from(heuristics: RunawayHeuristics) -> Self {
+        Self {
+            idle_cycles: heuristics.idle_cycles,
+            tight_loop: heuristics.tight_loop.map(|tight_loop| pb::api::TightLoop {
+                cycles: tight_loop.cycles,
+                span: tight_loop.span,
+            }),
+            abort: heuristics.abort,
+        }
+    }
+}
+
+#[cfg(feature = "executor")]
+impl From<pb::api::RunawayHeuristics> for RunawayHeuristics {
+    fn from(heuristics: pb::api::RunawayHeuristics) -> Self {
+        Self {
+            idle_cycles: heuristics.idle_cycles,
+            tight_loop: heuristics.tight_loop.map(|tight_loop| TightLoop {
+                cycles: tight_loop.cycles,
+                span: tight_loop.span,
+            }),
+            abort: heuristics.abort,
+        }
+    }
+}
+
+#[cfg(feature = "executor")]
+impl From<&SyscallManifest> for pb::api::SyscallManifest {
+    fn from(manifest: &SyscallManifest) -> Self {
+        Self {
+            names: manifest.names().map(Into::into).collect(),
+        }
+    }
+}
+
+#[cfg(feature = "executor")]
+impl From<pb::api::SyscallManifest> for SyscallManifest {
+    fn from(manifest: pb::api::SyscallManifest) -> Self {
+        Self::new(manifest.names)
+    }
+}
+
 impl From<ExitCode> for pb::base::ExitCode {
     fn from(value: ExitCode) -> Self {
         Self {
@@ -227,6 +397,7 @@ impl TryFrom<pb::api::ProverOpts> for ProverOpts {
                 .into_iter()
                 .map(TryInto::try_into)
                 .collect::<Result<_>>()?,
+            backend: opts.backend,
         })
     }
 }
@@ -238,6 +409,7 @@ impl From<ProverOpts> for pb::api::ProverOpts {
             prove_guest_errors: opts.prove_guest_errors,
             receipt_kind: opts.receipt_kind as i32,
             control_ids: opts.control_ids.into_iter().map(Into::into).collect(),
+            backend: opts.backend,
         }
     }
 }
@@ -275,6 +447,23 @@ impl From<SessionStats> for pb::core::SessionStats {
             segments: value.segments.try_into().unwrap(),
             total_cycles: value.total_cycles,
             user_cycles: value.user_cycles,
+            stdout_bytes: value.stdout_bytes,
+            stderr_bytes: value.stderr_bytes,
+            paging_cycles: value.paging_cycles,
+            saved_paging_cycles: value.saved_paging_cycles,
+            insn_counts: value.insn_counts.unwrap_or_default().into_iter().collect(),
+            fd_usage: value
+                .fd_usage
+                .into_iter()
+                .map(|(fd, usage)| {
+                    let usage = pb::core::FdUsage {
+                        bytes: usage.bytes,
+                        calls: usage.calls,
+                        exceeded: usage.exceeded,
+                    };
+                    (fd, usage)
+                })
+                .collect(),
         }
     }
 }
@@ -287,6 +476,24 @@ impl TryFrom<pb::core::SessionStats> for SessionStats {
             segments: value.segments.try_into()?,
             total_cycles: value.total_cycles,
             user_cycles: value.user_cycles,
+            stdout_bytes: value.stdout_bytes,
+            stderr_bytes: value.stderr_bytes,
+            paging_cycles: value.paging_cycles,
+            saved_paging_cycles: value.saved_paging_cycles,
+            insn_counts: (!value.insn_counts.is_empty())
+                .then(|| value.insn_counts.into_iter().collect()),
+            fd_usage: value
+                .fd_usage
+                .into_iter()
+                .map(|(fd, usage)| {
+                    let usage = FdUsage {
+                        bytes: usage.bytes,
+                        calls: usage.calls,
+                        exceeded: usage.exceeded,
+                    };
+                    (fd, usage)
+                })
+                .collect(),
         })
     }
 }
@@ -16,6 +16,7 @@ use std::{
     error::Error as StdError,
     io::{BufReader, Error as IoError, ErrorKind as IoErrorKind, Read, Write},
     path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, bail, Result};
@@ -170,7 +171,7 @@ impl TraceCallback for TraceProxy {
         let request = pb::api::ServerReply {
             kind: Some(pb::api::server_reply::Kind::Ok(pb::api::ClientCallback {
                 kind: Some(pb::api::client_callback::Kind::Io(pb::api::OnIoRequest {
-                    kind: Some(pb::api::on_io_request::Kind::Trace(event.into())),
+                    kind: Some(pb::api::on_io_request::Kind::Trace(event.try_into()?)),
                 })),
             })),
         };
@@ -266,7 +267,7 @@ impl Server {
 
             let segments_out = request.segments_out.ok_or(malformed_err())?;
             let bytes = binary.as_bytes()?;
-            let mut exec = ExecutorImpl::from_elf(env, &bytes)?;
+            let mut exec = ExecutorImpl::from_owned_elf(env, bytes)?;
 
             let session = exec.run_with_callback(|segment| {
                 let segment_bytes = bincode::serialize(&segment)?;
@@ -645,17 +646,92 @@ fn build_env<'a>(
     if let Some(segment_limit_po2) = request.segment_limit_po2 {
         env_builder.segment_limit_po2(segment_limit_po2);
     }
+    env_builder.segment_po2_schedule(&request.segment_po2_schedule);
     env_builder.session_limit(request.session_limit);
     if request.trace_events.is_some() {
         let proxy = TraceProxy::new(conn.try_clone()?);
         env_builder.trace_callback(proxy);
     }
+    if request.trace_prev_values {
+        env_builder.trace_prev_values();
+    }
+    if request.trace_paging {
+        env_builder.trace_paging();
+    }
+    for watchpoint in request.watchpoints.iter().cloned() {
+        let (range, kind) = watchpoint.try_into()?;
+        env_builder.watch(range, kind);
+    }
+    if request.linux_compat {
+        env_builder.linux_compat();
+    }
+    env_builder.insn_stats(request.insn_stats);
+    if let Some(input_digest) = request.input_digest.clone() {
+        env_builder.input_digest(input_digest.try_into()?);
+    }
+    if let Some(session_id) = &request.session_id {
+        env_builder.session_id(session_id);
+    }
+    if let Some(public_key) = &request.journal_recipient {
+        env_builder.journal_recipient(
+            public_key
+                .as_slice()
+                .try_into()
+                .map_err(|_| malformed_err())?,
+        );
+    }
     if !request.pprof_out.is_empty() {
         env_builder.enable_profiler(Path::new(&request.pprof_out));
     }
     if !request.segment_path.is_empty() {
         env_builder.segment_path(Path::new(&request.segment_path));
     }
+    env_builder.segments_in_memory(request.segments_in_memory);
+    for (fd, quota) in request.fd_quotas.iter() {
+        env_builder.fd_quota(*fd, quota.clone().into());
+    }
+    if let Some(cycles) = request.pause_at_cycle {
+        env_builder.pause_at_cycle(cycles);
+    }
+    if let Some(timeout_ms) = request.wall_clock_timeout_ms {
+        env_builder.wall_clock_deadline(Instant::now() + Duration::from_millis(timeout_ms));
+    }
+    if let Some(stack_size) = request.stack_size {
+        env_builder.stack_size(stack_size);
+    }
+    if let Some(hugepages) = request.hugepages {
+        env_builder.hugepages(hugepages);
+    }
+    if !request.page_heatmap_out.is_empty() {
+        env_builder.enable_page_heatmap(Path::new(&request.page_heatmap_out));
+    }
+    if request.commit_input {
+        env_builder.commit_input();
+    }
+    if request.capture_statement {
+        env_builder.capture_statement();
+    }
+    if !request.record_transcript_out.is_empty() {
+        env_builder.record_syscall_transcript_to(Path::new(&request.record_transcript_out));
+    } else if request.record_transcript {
+        env_builder.record_syscall_transcript();
+    }
+    if request.audit_nondeterminism {
+        env_builder.audit_nondeterminism();
+    }
+    if let Some(transcript) = &request.replay_transcript {
+        env_builder.replay_transcript(bincode::deserialize(transcript)?);
+    }
+    env_builder.self_check(request.self_check);
+    env_builder.perf_warnings(request.perf_warnings);
+    env_builder.taint_tracking(request.taint_tracking);
+    env_builder.cycle_audit(request.cycle_audit);
+    if let Some(heuristics) = request.runaway_heuristics.clone() {
+        env_builder.runaway_heuristics(heuristics.into());
+    }
+    if let Some(manifest) = request.syscall_manifest.clone() {
+        env_builder.syscall_manifest(manifest.into());
+    }
 
     for assumption in request.assumptions.iter() {
         match assumption.kind.as_ref().ok_or(malformed_err())? {
@@ -31,9 +31,9 @@ use test_log::test;
 
 use super::{Asset, AssetRequest, ConnectionWrapper, Connector, TcpConnection};
 use crate::{
-    receipt::SuccinctReceipt, recursion::MerkleGroup, ApiClient, ApiServer, ExecutorEnv,
+    receipt::SuccinctReceipt, recursion::MerkleGroup, ApiClient, ApiServer, ExecutorEnv, ExitCode,
     InnerReceipt, ProverOpts, Receipt, ReceiptClaim, SegmentReceipt, SessionInfo,
-    SuccinctReceiptVerifierParameters, VerifierContext,
+    SuccinctReceiptVerifierParameters, Tee, VerifierContext,
 };
 
 struct TestClientConnector {
@@ -340,3 +340,31 @@ fn guest_error_forwarding() {
     let binary = Asset::Inline(MULTI_TEST_ELF.into());
     TestClient::new().execute(env, binary);
 }
+
+#[test]
+fn forward_pause_at_cycle() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::ShaDigestIter {
+            data: vec![0; 32],
+            num_iter: 1000,
+        })
+        .unwrap()
+        .segment_limit_po2(14)
+        .pause_at_cycle(2 << 14)
+        .build()
+        .unwrap();
+    let binary = Asset::Inline(MULTI_TEST_ELF.into());
+    let session = TestClient::new().execute(env, binary);
+    assert_eq!(session.exit_code, ExitCode::SystemSplit);
+}
+
+#[test]
+#[should_panic(expected = "not supported by a remote executor: journal_tee")]
+fn reject_local_only_env() {
+    let env = ExecutorEnv::builder()
+        .journal_tee(Tee::new().with(std::io::sink()))
+        .build()
+        .unwrap();
+    let binary = Asset::Inline(MULTI_TEST_ELF.into());
+    TestClient::new().execute(env, binary);
+}
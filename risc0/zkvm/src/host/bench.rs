@@ -0,0 +1,524 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Standard workloads for measuring the throughput of the executor and the prover.
+//!
+//! [run_benchmarks] executes each [Workload] and, with the `prove` feature, proves its segments
+//! with each of the requested provers, producing a [BenchReport] that can be serialized to
+//! compare hardware or to track regressions across releases. The workloads are small programs
+//! assembled by this module rather than compiled guests, so that they do not change with the
+//! guest toolchain.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use risc0_zkvm::bench::{run_benchmarks, BenchOpts};
+//!
+//! let report = run_benchmarks(&BenchOpts::default()).unwrap();
+//! println!("{}", report.to_json().unwrap());
+//! ```
+
+use std::{collections::BTreeMap, fmt, time::Instant};
+
+use anyhow::{ensure, Result};
+use risc0_binfmt::{ExitCode, MemoryImage, Program};
+use risc0_zkvm_platform::{
+    syscall::{ecall, halt, nr::SYS_CYCLE_COUNT},
+    PAGE_SIZE, WORD_SIZE,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "prove")]
+use crate::{get_prover_server, ProverOpts, Session, VerifierContext};
+use crate::{ExecutorEnv, ExecutorImpl, VERSION};
+
+/// Address of the code of each workload.
+const CODE_ADDR: u32 = 0x0000_4000;
+
+/// Address of the data of each workload.
+const DATA_ADDR: u32 = 0x0001_0000;
+
+/// The region of memory touched by [Workload::MemoryWalk].
+const WALK_ADDR: u32 = 0x0040_0000;
+const WALK_BYTES: u32 = 4 * 1024 * 1024;
+
+/// The distance between the words touched by [Workload::MemoryWalk], so that each access is to
+/// a different page than the last.
+const WALK_STRIDE: u32 = (PAGE_SIZE + WORD_SIZE) as u32;
+
+/// A standard workload, with a cost per iteration that does not depend on the guest toolchain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum Workload {
+    /// Compresses a block with the SHA-256 accelerator each iteration.
+    HashLoop,
+
+    /// Loads, increments, and stores a word each iteration, walking a 4 MiB region a page at a
+    /// time so that every segment pages in as much memory as it can.
+    MemoryWalk,
+
+    /// Makes a `sys_cycle_count` syscall each iteration, measuring the round trip to the host.
+    EcallStorm,
+}
+
+impl Workload {
+    /// All the workloads, in the order they are run by default.
+    pub const ALL: [Workload; 3] = [Self::HashLoop, Self::MemoryWalk, Self::EcallStorm];
+
+    /// The program running this workload for `iterations` iterations.
+    ///
+    /// Returns an error if `iterations` is zero: every workload runs at least one iteration.
+    pub fn program(&self, iterations: u32) -> Result<Program> {
+        ensure!(iterations > 0, "{self} must run at least one iteration");
+        let mut asm = Asm::new();
+        let mut data = BTreeMap::new();
+        asm.li(S0, iterations);
+        match self {
+            Self::HashLoop => {
+                // The state is updated in place, after the 64-byte block.
+                let state = DATA_ADDR + 64;
+                asm.li(A0, state);
+                asm.li(A1, state);
+                asm.li(A2, DATA_ADDR);
+                asm.li(A3, DATA_ADDR + 32);
+                asm.li(A4, 1);
+                asm.li(T0, ecall::SHA);
+                let head = asm.pc();
+                asm.ecall();
+                asm.addi(S0, S0, -1);
+                asm.bne(S0, ZERO, head);
+            }
+            Self::MemoryWalk => {
+                asm.li(S1, WALK_ADDR);
+                asm.li(S2, 0);
+                asm.li(S3, WALK_BYTES - 1);
+                asm.li(S4, WALK_STRIDE);
+                let head = asm.pc();
+                asm.add(T1, S1, S2);
+                asm.lw(T2, T1, 0);
+                asm.addi(T2, T2, 1);
+                asm.sw(T2, T1, 0);
+                asm.add(S2, S2, S4);
+                asm.and(S2, S2, S3);
+                asm.addi(S0, S0, -1);
+                asm.bne(S0, ZERO, head);
+            }
+            Self::EcallStorm => {
+                let name = SYS_CYCLE_COUNT.as_str().as_bytes();
+                for (idx, chunk) in name.chunks(WORD_SIZE).enumerate() {
+                    let mut word = [0; WORD_SIZE];
+                    word[..chunk.len()].copy_from_slice(chunk);
+                    data.insert(
+                        DATA_ADDR + (idx * WORD_SIZE) as u32,
+                        u32::from_le_bytes(word),
+                    );
+                }
+                // Follow the name with a zero word, so that it is terminated whatever its length.
+                let end = DATA_ADDR + name.len().next_multiple_of(WORD_SIZE) as u32;
+                data.entry(end).or_insert(0);
+                asm.li(S1, DATA_ADDR);
+                let head = asm.pc();
+                asm.li(T0, ecall::SOFTWARE);
+                asm.li(A0, 0);
+                asm.li(A1, 0);
+                asm.addi(A2, S1, 0);
+                asm.ecall();
+                asm.addi(S0, S0, -1);
+                asm.bne(S0, ZERO, head);
+            }
+        }
+        asm.li(T0, ecall::HALT);
+        asm.li(A0, halt::TERMINATE);
+        asm.li(A1, DATA_ADDR);
+        asm.ecall();
+
+        let mut program = asm.program();
+        program.image.extend(data);
+        Ok(program)
+    }
+
+    /// The name of the workload in reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::HashLoop => "hash_loop",
+            Self::MemoryWalk => "memory_walk",
+            Self::EcallStorm => "ecall_storm",
+        }
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Options for [run_benchmarks].
+#[derive(Clone)]
+pub struct BenchOpts {
+    /// The workloads to run.
+    pub workloads: Vec<Workload>,
+
+    /// The number of iterations of each workload.
+    pub iterations: u32,
+
+    /// The segment limit of each execution, in powers of 2 cycles.
+    pub segment_limit_po2: u32,
+
+    /// The provers to prove the segments of each workload with, each measured separately.
+    /// Execution alone is measured if this is empty.
+    #[cfg(feature = "prove")]
+    pub provers: Vec<ProverOpts>,
+}
+
+impl Default for BenchOpts {
+    fn default() -> Self {
+        Self {
+            workloads: Workload::ALL.to_vec(),
+            iterations: 1 << 16,
+            segment_limit_po2: 20,
+            #[cfg(feature = "prove")]
+            provers: Vec::new(),
+        }
+    }
+}
+
+impl BenchOpts {
+    /// Return [BenchOpts] that run only the given workloads.
+    pub fn with_workloads(self, workloads: impl IntoIterator<Item = Workload>) -> Self {
+        Self {
+            workloads: workloads.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Return [BenchOpts] that run each workload for `iterations` iterations.
+    pub fn with_iterations(self, iterations: u32) -> Self {
+        Self { iterations, ..self }
+    }
+
+    /// Return [BenchOpts] with the given segment limit, in powers of 2 cycles.
+    pub fn with_segment_limit_po2(self, segment_limit_po2: u32) -> Self {
+        Self {
+            segment_limit_po2,
+            ..self
+        }
+    }
+
+    /// Return [BenchOpts] that also prove each workload with `opts`.
+    #[cfg(feature = "prove")]
+    pub fn with_prover(mut self, opts: ProverOpts) -> Self {
+        self.provers.push(opts);
+        self
+    }
+}
+
+/// The results of [run_benchmarks].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchReport {
+    /// The version of this crate.
+    pub version: String,
+
+    /// The results of each workload, in the order they were run.
+    pub workloads: Vec<WorkloadReport>,
+}
+
+impl BenchReport {
+    /// Serialize the report as JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// The results of a single [Workload].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorkloadReport {
+    /// The workload.
+    pub workload: Workload,
+
+    /// The number of iterations run.
+    pub iterations: u32,
+
+    /// The number of segments executed.
+    pub segments: usize,
+
+    /// The user cycles executed.
+    pub user_cycles: u64,
+
+    /// The total cycles executed, including paging and padding.
+    pub total_cycles: u64,
+
+    /// The time spent executing, in seconds.
+    pub execution_secs: f64,
+
+    /// The total cycles executed per second.
+    pub cycles_per_sec: f64,
+
+    /// The results of proving the segments with each prover.
+    pub proving: Vec<ProvingReport>,
+}
+
+/// The results of proving the segments of a [Workload] with one prover.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProvingReport {
+    /// The name of the prover backend, or `default` for the one selected by the enabled
+    /// features.
+    pub backend: String,
+
+    /// The hash function of the proofs.
+    pub hashfn: String,
+
+    /// The time spent proving, in seconds.
+    pub proving_secs: f64,
+
+    /// The segments proved per second.
+    pub segments_per_sec: f64,
+}
+
+/// Run the workloads in `opts`, and report their throughput.
+pub fn run_benchmarks(opts: &BenchOpts) -> Result<BenchReport> {
+    let mut workloads = Vec::new();
+    for &workload in &opts.workloads {
+        tracing::info!("running {workload}");
+        workloads.push(run_workload(opts, workload)?);
+    }
+    Ok(BenchReport {
+        version: VERSION.to_string(),
+        workloads,
+    })
+}
+
+fn run_workload(opts: &BenchOpts, workload: Workload) -> Result<WorkloadReport> {
+    let program = workload.program(opts.iterations)?;
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
+    let env = ExecutorEnv::builder()
+        .segment_limit_po2(opts.segment_limit_po2)
+        .build()?;
+    let mut exec = ExecutorImpl::new(env, image)?;
+
+    let start = Instant::now();
+    let session = exec.run()?;
+    let execution_secs = start.elapsed().as_secs_f64();
+    ensure!(
+        session.exit_code == ExitCode::Halted(0),
+        "{workload} exited with {:?}",
+        session.exit_code
+    );
+
+    #[cfg(feature = "prove")]
+    let proving = prove_workload(opts, &session)?;
+    #[cfg(not(feature = "prove"))]
+    let proving = Vec::new();
+
+    Ok(WorkloadReport {
+        workload,
+        iterations: opts.iterations,
+        segments: session.segments.len(),
+        user_cycles: session.user_cycles,
+        total_cycles: session.total_cycles,
+        execution_secs,
+        cycles_per_sec: session.total_cycles as f64 / execution_secs,
+        proving,
+    })
+}
+
+/// Prove the segments of `session` with each of the provers in `opts`.
+#[cfg(feature = "prove")]
+fn prove_workload(opts: &BenchOpts, session: &Session) -> Result<Vec<ProvingReport>> {
+    let segments = session
+        .segments
+        .iter()
+        .map(|segment| segment.resolve())
+        .collect::<Result<Vec<_>>>()?;
+    let ctx = VerifierContext::default();
+
+    let mut reports = Vec::new();
+    for prover_opts in &opts.provers {
+        let prover = get_prover_server(prover_opts)?;
+        let start = Instant::now();
+        for segment in &segments {
+            prover.prove_segment(&ctx, segment)?;
+        }
+        let proving_secs = start.elapsed().as_secs_f64();
+        reports.push(ProvingReport {
+            backend: prover_opts
+                .backend
+                .clone()
+                .unwrap_or_else(|| "default".to_string()),
+            hashfn: prover_opts.hashfn.clone(),
+            proving_secs,
+            segments_per_sec: segments.len() as f64 / proving_secs,
+        });
+    }
+    Ok(reports)
+}
+
+// Registers used by the workloads.
+const ZERO: u32 = 0;
+const T0: u32 = 5;
+const T1: u32 = 6;
+const T2: u32 = 7;
+const S0: u32 = 8;
+const S1: u32 = 9;
+const A0: u32 = 10;
+const A1: u32 = 11;
+const A2: u32 = 12;
+const A3: u32 = 13;
+const A4: u32 = 14;
+const S2: u32 = 18;
+const S3: u32 = 19;
+const S4: u32 = 20;
+
+/// A minimal assembler for the instructions used by the workloads.
+struct Asm {
+    insns: Vec<u32>,
+}
+
+impl Asm {
+    fn new() -> Self {
+        Self { insns: Vec::new() }
+    }
+
+    /// The address of the next instruction.
+    fn pc(&self) -> u32 {
+        CODE_ADDR + (self.insns.len() * WORD_SIZE) as u32
+    }
+
+    fn program(self) -> Program {
+        let image = self
+            .insns
+            .into_iter()
+            .enumerate()
+            .map(|(idx, insn)| (CODE_ADDR + (idx * WORD_SIZE) as u32, insn))
+            .collect();
+        Program {
+            entry: CODE_ADDR,
+            image,
+        }
+    }
+
+    fn i_type(&mut self, opcode: u32, funct3: u32, rd: u32, rs1: u32, imm: i32) {
+        assert!((-2048..2048).contains(&imm));
+        let imm = imm as u32 & 0xfff;
+        self.insns
+            .push(imm << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode);
+    }
+
+    fn r_type(&mut self, funct3: u32, rd: u32, rs1: u32, rs2: u32) {
+        self.insns
+            .push(rs2 << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | 0b0110011);
+    }
+
+    fn addi(&mut self, rd: u32, rs1: u32, imm: i32) {
+        self.i_type(0b0010011, 0b000, rd, rs1, imm);
+    }
+
+    fn add(&mut self, rd: u32, rs1: u32, rs2: u32) {
+        self.r_type(0b000, rd, rs1, rs2);
+    }
+
+    fn and(&mut self, rd: u32, rs1: u32, rs2: u32) {
+        self.r_type(0b111, rd, rs1, rs2);
+    }
+
+    fn lw(&mut self, rd: u32, rs1: u32, imm: i32) {
+        self.i_type(0b0000011, 0b010, rd, rs1, imm);
+    }
+
+    fn sw(&mut self, rs2: u32, rs1: u32, imm: i32) {
+        assert!((-2048..2048).contains(&imm));
+        let imm = imm as u32 & 0xfff;
+        self.insns.push(
+            (imm >> 5) << 25 | rs2 << 20 | rs1 << 15 | 0b010 << 12 | (imm & 0x1f) << 7 | 0b0100011,
+        );
+    }
+
+    fn bne(&mut self, rs1: u32, rs2: u32, target: u32) {
+        let offset = target.wrapping_sub(self.pc());
+        self.insns.push(
+            (offset >> 12 & 1) << 31
+                | (offset >> 5 & 0x3f) << 25
+                | rs2 << 20
+                | rs1 << 15
+                | 0b001 << 12
+                | (offset >> 1 & 0xf) << 8
+                | (offset >> 11 & 1) << 7
+                | 0b1100011,
+        );
+    }
+
+    /// Load `value` into `rd`, with `lui` and `addi`.
+    fn li(&mut self, rd: u32, value: u32) {
+        let lo = ((value & 0xfff) as i32) << 20 >> 20;
+        let hi = value.wrapping_sub(lo as u32);
+        if hi != 0 {
+            // lui
+            self.insns.push(hi | rd << 7 | 0b0110111);
+            self.addi(rd, rd, lo);
+        } else {
+            self.addi(rd, ZERO, lo);
+        }
+    }
+
+    fn ecall(&mut self) {
+        self.insns.push(0x00000073);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_benchmarks, Asm, BenchOpts, Workload, A1, A4, ZERO};
+
+    #[test]
+    fn encoding() {
+        const A5: u32 = 15;
+        // Encodings from the simple loop in risc0_circuit_rv32im::prove::emu::testutil.
+        let mut asm = Asm::new();
+        asm.addi(A4, ZERO, 0);
+        asm.addi(A4, A4, 1);
+        asm.li(A1, 0x0100_0000);
+        let head = asm.pc() - 8;
+        asm.bne(A4, A5, head);
+        assert_eq!(asm.insns[..2], [0x00000713, 0x00170713]);
+        // lui a1, 0x1000; addi a1, a1, 0
+        assert_eq!(asm.insns[2..4], [0x010005b7, 0x00058593]);
+        // bne a4, a5, -8
+        assert_eq!(asm.insns[4], 0xfef71ce3);
+    }
+
+    #[test]
+    fn workloads() {
+        let opts = BenchOpts::default()
+            .with_iterations(1000)
+            .with_segment_limit_po2(16);
+        let report = run_benchmarks(&opts).unwrap();
+        assert_eq!(report.workloads.len(), Workload::ALL.len());
+        for (result, workload) in report.workloads.iter().zip(Workload::ALL) {
+            assert_eq!(result.workload, workload);
+            // Each iteration runs a few instructions.
+            assert!(result.user_cycles > 3000, "{result:?}");
+            assert!(result.cycles_per_sec > 0.0);
+        }
+        // Walking a page at a time pages in more memory than fits in a segment.
+        assert!(report.workloads[1].segments > 1);
+        assert!(report.to_json().unwrap().contains("\"memory_walk\""));
+    }
+
+    #[test]
+    fn zero_iterations() {
+        let opts = BenchOpts::default().with_iterations(0);
+        let err = run_benchmarks(&opts).unwrap_err();
+        assert!(err.to_string().contains("at least one iteration"), "{err}");
+    }
+}
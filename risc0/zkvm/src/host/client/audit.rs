@@ -0,0 +1,257 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Audits of the nondeterministic data that the host supplies to the guest.
+
+use std::{collections::BTreeMap, fmt};
+
+use risc0_zkvm_platform::{
+    fileno,
+    syscall::nr::{
+        SYS_ALLOC_FAILED, SYS_ARGC, SYS_ARGV, SYS_AUTH_GET, SYS_AUTH_PUT, SYS_COMMIT_FIELD,
+        SYS_CYCLE_COUNT, SYS_DIV_WIDE, SYS_EXECUTE_ZKR, SYS_FP_ENV, SYS_GETENV,
+        SYS_JOURNAL_RECIPIENT, SYS_LOG, SYS_MEMOIZE_GET, SYS_MEMOIZE_PUT, SYS_PANIC, SYS_PANIC_AT,
+        SYS_RANDOM, SYS_READ, SYS_VERIFY_INTEGRITY, SYS_WRITE, SYS_WRITEV,
+    },
+    WORD_SIZE,
+};
+use serde::{Deserialize, Serialize};
+
+/// The kind of trust placed in the host by a source of nondeterminism.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum NondeterminismKind {
+    /// Data read from the guest input, which is committed to by the input digest. See
+    /// [ExecutorEnvBuilder::commit_input][crate::ExecutorEnvBuilder::commit_input].
+    CommittedInput,
+
+    /// Data read from the guest input, which is not committed to. The guest must validate it,
+    /// or commit to it in the journal.
+    UncommittedInput,
+
+    /// Random bytes supplied by the host.
+    Randomness,
+
+    /// Environment variables and command line arguments supplied by the host.
+    Environment,
+
+    /// Assumptions added by the guest, which are resolved when the receipt is composed.
+    Assumption,
+
+    /// Data returned by any other syscall, such as host callbacks, slice I/O, or file
+    /// descriptors other than stdin.
+    Host,
+}
+
+impl fmt::Display for NondeterminismKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::CommittedInput => "committed-input",
+            Self::UncommittedInput => "uncommitted-input",
+            Self::Randomness => "randomness",
+            Self::Environment => "environment",
+            Self::Assumption => "assumption",
+            Self::Host => "host",
+        };
+        f.pad(name)
+    }
+}
+
+/// A syscall through which the host supplied data to the guest during a session.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NondeterminismSource {
+    /// The name of the syscall, e.g. `risc0_zkvm_platform::syscall::nr::SYS_RANDOM`.
+    pub syscall: String,
+
+    /// The file descriptor read from, for read syscalls.
+    pub fd: Option<u32>,
+
+    /// The kind of trust placed in the host by this source.
+    pub kind: NondeterminismKind,
+
+    /// The number of times the guest made the syscall.
+    pub calls: u64,
+
+    /// The total number of bytes the host returned to the guest, including registers.
+    pub bytes: u64,
+
+    /// The cycle at which the guest first made the syscall.
+    pub first_cycle: u64,
+
+    /// The program counter at which the guest first made the syscall.
+    pub first_pc: u32,
+}
+
+/// A report of every source of host-provided nondeterminism consumed by the guest in a session.
+///
+/// The report enumerates the data that the guest did not derive from its own code or from
+/// committed inputs, and so the trust assumptions that a verifier of the receipt makes about the
+/// host. Syscalls that only send data to the host, such as writes to stdout and the journal, and
+/// syscalls whose answers are determined by the execution, such as the cycle count, are not
+/// listed.
+///
+/// Enable the audit with
+/// [ExecutorEnvBuilder::audit_nondeterminism][crate::ExecutorEnvBuilder::audit_nondeterminism].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NondeterminismReport {
+    /// The sources of nondeterminism, in the order in which the guest first used them.
+    pub sources: Vec<NondeterminismSource>,
+}
+
+impl NondeterminismReport {
+    /// Returns true if the guest consumed no data from the host other than committed input.
+    pub fn is_deterministic(&self) -> bool {
+        self.sources
+            .iter()
+            .all(|source| source.kind == NondeterminismKind::CommittedInput)
+    }
+}
+
+impl fmt::Display for NondeterminismReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<18} {:>8} {:>12} {:>12} {:>10}  syscall",
+            "kind", "calls", "bytes", "first cycle", "first pc"
+        )?;
+        for source in &self.sources {
+            let syscall = source
+                .syscall
+                .rsplit("::")
+                .next()
+                .unwrap_or(&source.syscall);
+            write!(
+                f,
+                "{:<18} {:>8} {:>12} {:>12} 0x{:08x}  {syscall}",
+                source.kind, source.calls, source.bytes, source.first_cycle, source.first_pc
+            )?;
+            if let Some(fd) = source.fd {
+                write!(f, " (fd {fd})")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Records the syscalls answered by the host while auditing a session.
+pub(crate) struct NondeterminismAudit {
+    commit_input: bool,
+    sources: BTreeMap<(String, Option<u32>), NondeterminismSource>,
+}
+
+impl NondeterminismAudit {
+    pub(crate) fn new(commit_input: bool) -> Self {
+        Self {
+            commit_input,
+            sources: BTreeMap::new(),
+        }
+    }
+
+    /// Record a syscall made with `a3` as its first argument, and the words the host wrote to the
+    /// guest, besides the two registers it returns.
+    pub(crate) fn record(&mut self, syscall: &str, a3: u32, cycle: u64, pc: u32, to_guest: &[u32]) {
+        let is = |name: &risc0_zkvm_platform::syscall::SyscallName| name.as_str() == syscall;
+        let (kind, fd) = if is(&SYS_READ) {
+            let kind = match (a3, self.commit_input) {
+                (fileno::STDIN, true) => NondeterminismKind::CommittedInput,
+                (fileno::STDIN, false) => NondeterminismKind::UncommittedInput,
+                _ => NondeterminismKind::Host,
+            };
+            (kind, Some(a3))
+        } else if is(&SYS_RANDOM) {
+            (NondeterminismKind::Randomness, None)
+        } else if is(&SYS_GETENV) || is(&SYS_ARGC) || is(&SYS_ARGV) || is(&SYS_JOURNAL_RECIPIENT) {
+            (NondeterminismKind::Environment, None)
+        } else if is(&SYS_VERIFY_INTEGRITY) || is(&SYS_EXECUTE_ZKR) {
+            (NondeterminismKind::Assumption, None)
+        } else if [
+            SYS_WRITE,
+            SYS_WRITEV,
+            SYS_LOG,
+            SYS_PANIC,
+            SYS_PANIC_AT,
+            SYS_ALLOC_FAILED,
+            SYS_CYCLE_COUNT,
+            SYS_COMMIT_FIELD,
+            // Memoized results are checked by the guest against its own digests.
+            SYS_MEMOIZE_GET,
+            SYS_MEMOIZE_PUT,
+            // Quotients are checked by the guest by multiplying them out.
+            SYS_DIV_WIDE,
+            // The floating-point environment is checked by the guest against its measurement.
+            SYS_FP_ENV,
+            // Authenticated entries are checked by the guest against its root.
+            SYS_AUTH_GET,
+            SYS_AUTH_PUT,
+        ]
+        .iter()
+        .any(is)
+        {
+            return;
+        } else {
+            (NondeterminismKind::Host, None)
+        };
+
+        let bytes = ((to_guest.len() + 2) * WORD_SIZE) as u64;
+        let source = self
+            .sources
+            .entry((syscall.to_string(), fd))
+            .or_insert_with(|| NondeterminismSource {
+                syscall: syscall.to_string(),
+                fd,
+                kind,
+                calls: 0,
+                bytes: 0,
+                first_cycle: cycle,
+                first_pc: pc,
+            });
+        source.calls += 1;
+        source.bytes += bytes;
+    }
+
+    pub(crate) fn report(&self) -> NondeterminismReport {
+        let mut sources: Vec<_> = self.sources.values().cloned().collect();
+        sources.sort_by_key(|source| source.first_cycle);
+        NondeterminismReport { sources }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkvm_platform::{
+        fileno,
+        syscall::nr::{SYS_RANDOM, SYS_READ, SYS_WRITE},
+    };
+
+    use super::{NondeterminismAudit, NondeterminismKind};
+
+    #[test]
+    fn classify() {
+        let mut audit = NondeterminismAudit::new(true);
+        audit.record(SYS_READ.as_str(), fileno::STDIN, 10, 0x1000, &[0; 4]);
+        audit.record(SYS_WRITE.as_str(), fileno::STDOUT, 20, 0x1004, &[]);
+        audit.record(SYS_RANDOM.as_str(), 0, 30, 0x1008, &[0; 2]);
+        audit.record(SYS_READ.as_str(), fileno::STDIN, 40, 0x1000, &[0; 1]);
+
+        let report = audit.report();
+        assert_eq!(report.sources.len(), 2);
+        assert_eq!(report.sources[0].kind, NondeterminismKind::CommittedInput);
+        assert_eq!(report.sources[0].calls, 2);
+        assert_eq!(report.sources[0].bytes, 36);
+        assert_eq!(report.sources[1].kind, NondeterminismKind::Randomness);
+        assert_eq!(report.sources[1].first_pc, 0x1008);
+        assert!(!report.is_deterministic());
+        assert!(report.to_string().contains("SYS_RANDOM"));
+    }
+}
@@ -17,32 +17,57 @@
 use std::{
     cell::RefCell,
     collections::HashMap,
-    io::{BufRead, BufReader, Cursor, Read, Write},
+    io::{BufRead, BufReader, Cursor, Read, Seek, Write},
     mem,
+    ops::{ControlFlow, Range},
     path::{Path, PathBuf},
     rc::Rc,
     sync::Arc,
+    time::Instant,
 };
 
-use anyhow::Result;
+use anyhow::{bail, ensure, Result};
 use bytemuck::Pod;
 use bytes::Bytes;
-use risc0_zkp::core::digest::Digest;
-use risc0_zkvm_platform::{self, fileno};
+use risc0_zkp::{core::digest::Digest, MAX_CYCLES_PO2, MIN_CYCLES_PO2};
+use risc0_zkvm_platform::{
+    self, align_up, fileno,
+    memory::{GUEST_MIN_MEM, STACK_TOP},
+    PAGE_SIZE,
+};
 use serde::Serialize;
 use tempfile::TempDir;
 
-#[cfg(feature = "prove")]
-use crate::Assumption;
 use crate::{
+    authenticated::AuthenticatedStore,
     host::client::{
-        posix_io::PosixIo,
+        posix_io::{FdQuota, PosixIo},
+        quota::QuotaManager,
+        sink::{ByteCounter, Sink, Tee},
         slice_io::{slice_io_from_fn, SliceIo, SliceIoTable},
+        transcript::SyscallTranscript,
     },
-    serde::to_vec,
-    AssumptionReceipt, TraceCallback,
+    redaction::RedactableJournal,
+    serde::{to_vec, FrameTag},
+    sha::{Digestible, Impl, Sha256},
+    AssumptionReceipt, Statement, TraceCallback, WatchHit, WatchKind,
+};
+#[cfg(feature = "executor")]
+use crate::{
+    Assumption, ExecutionCache, ExecutionProgress, RunawayHeuristics, RunawayReport,
+    SyscallManifest, SyscallRing,
 };
 
+/// A callback charged with the address, name, and user cycles of each instruction the guest
+/// executes. See [ExecutorEnvBuilder::cycle_meter].
+pub(crate) type CycleMeter<'a> = dyn FnMut(u32, &str, u64) -> ControlFlow<()> + 'a;
+
+/// A callback passed each access to watched memory. See [ExecutorEnvBuilder::watch_callback].
+pub(crate) type WatchCallback<'a> = dyn FnMut(WatchHit) -> Result<()> + 'a;
+
+/// The largest guest stack, in bytes, that leaves room for a guard page below it.
+const MAX_STACK_SIZE: u32 = STACK_TOP - (GUEST_MIN_MEM + PAGE_SIZE) as u32;
+
 /// A builder pattern used to construct an [ExecutorEnv].
 #[derive(Default)]
 pub struct ExecutorEnvBuilder<'a> {
@@ -55,7 +80,7 @@ pub(crate) struct AssumptionReceipts {
     pub(crate) cached: Vec<AssumptionReceipt>,
     // An ordered list of assumptions accessed during execution, along a receipt if available. Each
     // time an assumption is used, it is cloned and pushed to the head of the list.
-    #[cfg(feature = "prove")]
+    #[cfg(feature = "executor")]
     pub(crate) accessed: Vec<(Assumption, AssumptionReceipt)>,
 }
 
@@ -84,15 +109,61 @@ pub struct ExecutorEnv<'a> {
     pub(crate) env_vars: HashMap<String, String>,
     pub(crate) args: Vec<String>,
     pub(crate) segment_limit_po2: Option<u32>,
+    pub(crate) segment_po2_schedule: Vec<u32>,
     pub(crate) session_limit: Option<u64>,
+    pub(crate) wall_clock_deadline: Option<Instant>,
+    pub(crate) pause_at_cycle: Option<u64>,
+    #[cfg(feature = "executor")]
+    pub(crate) runaway_heuristics: Option<RunawayHeuristics>,
+    #[cfg(feature = "executor")]
+    pub(crate) on_runaway: Option<Rc<RefCell<dyn FnMut(&RunawayReport) + 'a>>>,
+    pub(crate) cycle_meter: Option<Rc<RefCell<CycleMeter<'a>>>>,
+    #[cfg(feature = "executor")]
+    pub(crate) progress_callback: Option<(u64, Rc<RefCell<dyn FnMut(&ExecutionProgress) + 'a>>)>,
+    pub(crate) stack_size: Option<u32>,
+    pub(crate) hugepages: Option<bool>,
     pub(crate) posix_io: Rc<RefCell<PosixIo<'a>>>,
     pub(crate) slice_io: Rc<RefCell<SliceIoTable<'a>>>,
+    #[cfg(feature = "executor")]
+    pub(crate) syscall_rings: HashMap<String, SyscallRing>,
     pub(crate) input: Vec<u8>,
+    // The number of frames written with `write_frame`.
+    pub(crate) input_frames: u32,
     pub(crate) trace: Vec<Rc<RefCell<dyn TraceCallback + 'a>>>,
+    pub(crate) trace_prev_values: bool,
+    pub(crate) trace_paging: bool,
+    pub(crate) watchpoints: Vec<(Range<u32>, WatchKind)>,
+    pub(crate) watch_callback: Option<Rc<RefCell<WatchCallback<'a>>>>,
+    pub(crate) linux_compat: bool,
     pub(crate) assumptions: Rc<RefCell<AssumptionReceipts>>,
     pub(crate) segment_path: Option<SegmentPath>,
+    pub(crate) segments_in_memory: bool,
     pub(crate) pprof_out: Option<PathBuf>,
+    pub(crate) page_heatmap_out: Option<PathBuf>,
     pub(crate) input_digest: Option<Digest>,
+    pub(crate) commit_input: bool,
+    pub(crate) capture_statement: bool,
+    pub(crate) tenant: Option<(String, QuotaManager)>,
+    #[cfg(feature = "executor")]
+    pub(crate) execution_cache: Option<ExecutionCache>,
+    pub(crate) stdout_counter: Option<ByteCounter>,
+    pub(crate) stderr_counter: Option<ByteCounter>,
+    pub(crate) journal_tee: Option<Tee<'a>>,
+    pub(crate) record_transcript: bool,
+    pub(crate) record_transcript_out: Option<PathBuf>,
+    pub(crate) audit_nondeterminism: bool,
+    pub(crate) replay_transcript: Option<SyscallTranscript>,
+    pub(crate) self_check: bool,
+    pub(crate) insn_stats: bool,
+    pub(crate) perf_warnings: bool,
+    pub(crate) taint_tracking: bool,
+    pub(crate) cycle_audit: bool,
+    pub(crate) committed_fields: Rc<RefCell<RedactableJournal>>,
+    pub(crate) journal_recipient: Option<[u8; 32]>,
+    pub(crate) session_id: Option<String>,
+    pub(crate) authenticated_stores: HashMap<String, AuthenticatedStore>,
+    #[cfg(feature = "executor")]
+    pub(crate) syscall_manifest: Option<SyscallManifest>,
 }
 
 impl<'a> ExecutorEnv<'a> {
@@ -108,6 +179,146 @@ impl<'a> ExecutorEnv<'a> {
     pub fn builder() -> ExecutorEnvBuilder<'a> {
         ExecutorEnvBuilder::default()
     }
+
+    /// The input digest of sessions run with this environment, if set.
+    pub fn input_digest(&self) -> Option<Digest> {
+        self.input_digest
+    }
+
+    /// Compute a digest over all of the data this environment provides to the guest, and every
+    /// setting that affects the result of running it.
+    ///
+    /// Returns `None` if the result of a run cannot be reused: the guest can receive data from
+    /// host-side handlers (e.g. readers, slice I/O callbacks, or assumptions) whose contents cannot
+    /// be captured in a digest, or can write to host-side writers other than the default stdout
+    /// and stderr, or the run has effects beyond its result (e.g. trace callbacks, quota
+    /// accounting, or a self-check), which a cached result would skip.
+    pub(crate) fn request_digest(&self) -> Option<Digest> {
+        // Every field is named here, so that adding a field fails to compile until it is either
+        // digested or made to bypass the cache.
+        let Self {
+            env_vars,
+            args,
+            segment_limit_po2,
+            segment_po2_schedule,
+            session_limit,
+            wall_clock_deadline,
+            pause_at_cycle,
+            #[cfg(feature = "executor")]
+            runaway_heuristics,
+            #[cfg(feature = "executor")]
+            on_runaway,
+            cycle_meter,
+            #[cfg(feature = "executor")]
+            progress_callback,
+            stack_size,
+            hugepages: _,
+            posix_io,
+            slice_io,
+            // Only describes the syscall table.
+            #[cfg(feature = "executor")]
+                syscall_rings: _,
+            input,
+            input_frames: _,
+            trace,
+            // Only observed by trace callbacks, which bypass the cache.
+            trace_prev_values: _,
+            trace_paging: _,
+            watchpoints,
+            watch_callback,
+            linux_compat,
+            assumptions,
+            segment_path: _,
+            segments_in_memory: _,
+            pprof_out,
+            page_heatmap_out,
+            input_digest,
+            // Only used to compute the input digest.
+            commit_input: _,
+            capture_statement: _,
+            tenant,
+            #[cfg(feature = "executor")]
+                execution_cache: _,
+            stdout_counter,
+            stderr_counter,
+            journal_tee,
+            record_transcript,
+            record_transcript_out,
+            audit_nondeterminism,
+            replay_transcript,
+            self_check,
+            insn_stats,
+            perf_warnings,
+            taint_tracking,
+            cycle_audit,
+            // The committed fields of a run are cached along with its journal.
+            committed_fields: _,
+            journal_recipient,
+            session_id: _,
+            authenticated_stores,
+            #[cfg(feature = "executor")]
+            syscall_manifest,
+        } = self;
+
+        let opaque_io = !posix_io.borrow().custom_read_fds.is_empty()
+            || !posix_io.borrow().custom_write_fds.is_empty()
+            || !posix_io.borrow().named_streams.is_empty()
+            || !slice_io.borrow().inner.is_empty()
+            || !assumptions.borrow().cached.is_empty()
+            || !authenticated_stores.is_empty()
+            || replay_transcript.is_some();
+        let observed = !trace.is_empty()
+            || !watchpoints.is_empty()
+            || watch_callback.is_some()
+            || cycle_meter.is_some()
+            || pprof_out.is_some()
+            || page_heatmap_out.is_some()
+            || stdout_counter.is_some()
+            || stderr_counter.is_some()
+            || journal_tee.is_some()
+            || *record_transcript
+            || record_transcript_out.is_some()
+            || *audit_nondeterminism
+            || *insn_stats
+            || *perf_warnings
+            || *taint_tracking
+            || *cycle_audit;
+        #[cfg(feature = "executor")]
+        let observed = observed
+            || runaway_heuristics.is_some()
+            || on_runaway.is_some()
+            || progress_callback.is_some();
+        // Where a run with a deadline stops depends on how long it takes, and a run with a pause
+        // cycle may stop before the guest exits.
+        let unrepeatable = wall_clock_deadline.is_some() || pause_at_cycle.is_some();
+        if opaque_io || observed || unrepeatable || tenant.is_some() || *self_check {
+            return None;
+        }
+
+        let mut env_vars: Vec<_> = env_vars.iter().collect();
+        env_vars.sort();
+        let request = (
+            input,
+            args,
+            env_vars,
+            segment_limit_po2,
+            segment_po2_schedule,
+            session_limit,
+            input_digest,
+            stack_size,
+            linux_compat,
+            journal_recipient,
+        );
+        #[cfg(feature = "executor")]
+        let request = (request, syscall_manifest);
+        Some(*Impl::hash_bytes(&bincode::serialize(&request).ok()?))
+    }
+
+    /// The guard page below the guest stack, if its size is limited.
+    pub(crate) fn stack_guard(&self) -> Option<Range<u32>> {
+        let stack_bottom = STACK_TOP - align_up(self.stack_size? as usize, PAGE_SIZE) as u32;
+        Some(stack_bottom - PAGE_SIZE as u32..stack_bottom)
+    }
 }
 
 impl<'a> ExecutorEnvBuilder<'a> {
@@ -126,12 +337,59 @@ impl<'a> ExecutorEnvBuilder<'a> {
     pub fn build(&mut self) -> Result<ExecutorEnv<'a>> {
         let mut inner = mem::take(&mut self.inner);
 
+        if inner.commit_input && inner.input_digest.is_none() {
+            ensure!(
+                !inner
+                    .posix_io
+                    .borrow()
+                    .custom_read_fds
+                    .contains(&fileno::STDIN),
+                "Input commitment requires stdin to be written with write or write_slice, or an \
+                 explicit input_digest"
+            );
+            inner.input_digest = Some(*Impl::hash_bytes(&inner.input));
+        }
+
+        if inner.capture_statement {
+            ensure!(
+                !inner
+                    .posix_io
+                    .borrow()
+                    .custom_read_fds
+                    .contains(&fileno::STDIN),
+                "Statement capture requires stdin to be written with write or write_slice"
+            );
+            let statement = Statement {
+                stdin: inner.input.clone(),
+                args: inner.args.clone(),
+                env_vars: inner.env_vars.clone().into_iter().collect(),
+            };
+            inner.input_digest = Some(statement.digest());
+        }
+
+        if let Some(stack_size) = inner.stack_size {
+            ensure!(
+                align_up(stack_size as usize, PAGE_SIZE) as u32 <= MAX_STACK_SIZE,
+                "Stack size of {stack_size} bytes exceeds the maximum of {MAX_STACK_SIZE} bytes"
+            );
+        }
+
+        if let Some(po2) = inner
+            .segment_po2_schedule
+            .iter()
+            .find(|&&po2| !(MIN_CYCLES_PO2..=MAX_CYCLES_PO2).contains(&(po2 as usize)))
+        {
+            bail!(
+                "Segment po2 {po2} in the schedule is outside of {MIN_CYCLES_PO2}..={MAX_CYCLES_PO2}"
+            );
+        }
+
         if !inner.input.is_empty() {
             let reader = Cursor::new(inner.input.clone());
-            inner
-                .posix_io
-                .borrow_mut()
-                .with_read_fd(fileno::STDIN, reader);
+            let mut posix_io = inner.posix_io.borrow_mut();
+            posix_io.with_read_fd(fileno::STDIN, reader);
+            // The data read from stdin is the input held by the env.
+            posix_io.custom_read_fds.remove(&fileno::STDIN);
         }
 
         if inner.pprof_out.is_none() {
@@ -140,6 +398,18 @@ impl<'a> ExecutorEnvBuilder<'a> {
             }
         }
 
+        if inner.page_heatmap_out.is_none() {
+            if let Ok(env_var) = std::env::var("RISC0_PAGE_HEATMAP_OUT") {
+                inner.page_heatmap_out = Some(env_var.into());
+            }
+        }
+
+        if inner.hugepages.is_none() {
+            if let Ok(env_var) = std::env::var("RISC0_HUGEPAGES") {
+                inner.hugepages = Some(env_var == "1");
+            }
+        }
+
         Ok(inner)
     }
 
@@ -152,8 +422,38 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Set the segment limit of each segment in turn, specified in powers of 2 cycles.
+    ///
+    /// The first segment is split at `schedule[0]`, the second at `schedule[1]`, and so on.
+    /// Segments past the end of the schedule use the
+    /// [segment limit][ExecutorEnvBuilder::segment_limit_po2]. This allows segments to be sized
+    /// for the provers they are sent to, e.g. when proving on a fleet of machines with
+    /// different amounts of memory.
+    ///
+    /// Each value must be between [risc0_zkp::MIN_CYCLES_PO2] and
+    /// [risc0_zkp::MAX_CYCLES_PO2] (inclusive), or [ExecutorEnvBuilder::build] fails.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .segment_po2_schedule(&[20, 20, 21])
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn segment_po2_schedule(&mut self, schedule: &[u32]) -> &mut Self {
+        self.inner.segment_po2_schedule = schedule.to_vec();
+        self
+    }
+
     /// Set a session limit, specified in number of cycles.
     ///
+    /// A session that reaches the limit ends with [ExitCode::SessionLimit][crate::ExitCode::SessionLimit],
+    /// and contains every segment executed up to the limit, along with the data written to the
+    /// journal so far. Such a session can be inspected, but not proven.
+    ///
     /// # Example
     ///
     /// ```
@@ -169,6 +469,216 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Stop executing at the end of the first segment to end after `deadline`, and return a
+    /// session that can be resumed, rather than running the guest to completion.
+    ///
+    /// The last segment of a session stopped at the deadline exits with
+    /// [ExitCode::SystemSplit][crate::ExitCode::SystemSplit]. Running the same executor again
+    /// resumes the guest where it stopped, and the session can be proven like any other, with a
+    /// receipt claiming that the guest reached its post-state. The deadline is only checked at
+    /// segment boundaries, so execution may run past it by the time it takes to execute a
+    /// segment, and a session that halts first is returned as usual. Sessions run with a
+    /// deadline are not cached.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::{Duration, Instant};
+    ///
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .wall_clock_deadline(Instant::now() + Duration::from_millis(500))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn wall_clock_deadline(&mut self, deadline: Instant) -> &mut Self {
+        self.inner.wall_clock_deadline = Some(deadline);
+        self
+    }
+
+    /// Stop executing at the end of the first segment that brings the total cycles of the
+    /// session to `cycles`, and return a session that can be resumed, e.g. to share a prover
+    /// between guests by running each for a slice of cycles at a time.
+    ///
+    /// The guest is stopped as if it had paused: its state is saved to the post-image of the
+    /// session, and running the same executor again resumes it where it stopped, with another
+    /// `cycles` total cycles. The last segment exits with
+    /// [ExitCode::SystemSplit][crate::ExitCode::SystemSplit] rather than
+    /// [ExitCode::Paused][crate::ExitCode::Paused], as only a guest that pauses itself can be
+    /// proven to have paused, so the session can be proven like one stopped at its
+    /// [wall-clock deadline][ExecutorEnvBuilder::wall_clock_deadline]. Total cycles are counted
+    /// in whole segments, and a segment that ends in the middle of a syscall is not stopped at.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .pause_at_cycle(1 << 24)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn pause_at_cycle(&mut self, cycles: u64) -> &mut Self {
+        self.inner.pause_at_cycle = Some(cycles);
+        self
+    }
+
+    /// Check the guest against `heuristics` as it runs, to catch a guest that is stuck, e.g. in
+    /// an infinite loop, in seconds rather than when it reaches the
+    /// [session limit][Self::session_limit].
+    ///
+    /// When a heuristic triggers, a [RunawayReport] with the state of the guest is logged as a
+    /// warning, passed to the callback set with [Self::on_runaway], and recorded as an
+    /// [ExecutionEventKind::Runaway][crate::ExecutionEventKind::Runaway] event. With
+    /// [RunawayHeuristics::abort] set, the run then fails. Each heuristic triggers once until
+    /// the guest makes progress again. A guest may legitimately run for a long time without
+    /// syscalls or in a small loop, e.g. while hashing, so the heuristics should be tuned to the
+    /// guest.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::{ExecutorEnv, RunawayHeuristics};
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .runaway_heuristics(RunawayHeuristics::default().with_idle_cycles(100_000_000))
+    ///     .on_runaway(|report| eprintln!("{report}"))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[cfg(feature = "executor")]
+    pub fn runaway_heuristics(&mut self, heuristics: RunawayHeuristics) -> &mut Self {
+        self.inner.runaway_heuristics = Some(heuristics);
+        self
+    }
+
+    /// Call `callback` whenever one of the [runaway heuristics][Self::runaway_heuristics]
+    /// triggers, before the run is aborted if it is set to be.
+    #[cfg(feature = "executor")]
+    pub fn on_runaway(&mut self, callback: impl FnMut(&RunawayReport) + 'a) -> &mut Self {
+        self.inner.on_runaway = Some(Rc::new(RefCell::new(callback)));
+        self
+    }
+
+    /// Call `callback` every `interval_cycles` user cycles with the [ExecutionProgress] of the
+    /// run, e.g. to show a progress bar for a long execution.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .progress_callback(10_000_000, |progress| {
+    ///         eprintln!(
+    ///             "{} cycles in {} segments after {:?}",
+    ///             progress.user_cycles, progress.segments, progress.elapsed
+    ///         );
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[cfg(feature = "executor")]
+    pub fn progress_callback(
+        &mut self,
+        interval_cycles: u64,
+        callback: impl FnMut(&ExecutionProgress) + 'a,
+    ) -> &mut Self {
+        self.inner.progress_callback = Some((interval_cycles, Rc::new(RefCell::new(callback))));
+        self
+    }
+
+    /// Call `callback` with the address, name, and user cycles of each instruction the guest
+    /// executes, e.g. to price execution per class of instruction.
+    ///
+    /// Instructions are named after their mnemonic, e.g. `add` or `lw`. An `ecall` is named
+    /// after the syscall it makes, e.g. `risc0_zkvm_platform::syscall::nr::SYS_READ`, the
+    /// accelerator it invokes, `sha` or `bigint`, or `halt` or `input`. The cycles spent paging
+    /// memory in and out are not charged to any instruction. If `callback` returns
+    /// [ControlFlow::Break], execution fails at the instruction. Calling `callback` for every
+    /// instruction slows down execution.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::ops::ControlFlow;
+    ///
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let mut gas = 1_000_000u64;
+    /// let env = ExecutorEnv::builder()
+    ///     .cycle_meter(move |_pc, name, cycles| {
+    ///         let price = if name.starts_with("risc0_zkvm_platform::syscall") { 10 } else { 1 };
+    ///         match gas.checked_sub(price * cycles) {
+    ///             Some(left) => {
+    ///                 gas = left;
+    ///                 ControlFlow::Continue(())
+    ///             }
+    ///             None => ControlFlow::Break(()),
+    ///         }
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn cycle_meter(
+        &mut self,
+        callback: impl FnMut(u32, &str, u64) -> ControlFlow<()> + 'a,
+    ) -> &mut Self {
+        self.inner.cycle_meter = Some(Rc::new(RefCell::new(callback)));
+        self
+    }
+
+    /// Set the size of the guest stack, in bytes, and guard the page below it.
+    ///
+    /// The stack starts at [STACK_TOP][risc0_zkvm_platform::memory::STACK_TOP] and grows down.
+    /// By default it may grow down to the bottom of guest memory. With a stack size set, the
+    /// executor reserves the page below the stack, rounding the size up to a whole number of
+    /// pages, and execution fails with a "stack overflow at pc=..." error at the first load or
+    /// store to that page, e.g. from deep recursion. A function whose stack frame is larger than
+    /// a page may skip over the guard page without accessing it. Building the environment fails
+    /// if the stack and its guard page do not fit below `STACK_TOP`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .stack_size(256 * 1024)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn stack_size(&mut self, bytes: u32) -> &mut Self {
+        self.inner.stack_size = Some(bytes);
+        self
+    }
+
+    /// Back the executor's copy of guest memory with OS hugepages.
+    ///
+    /// On Linux, the pages of guest memory loaded by each segment are held in a single buffer
+    /// that the kernel is advised to back with transparent hugepages. This reduces TLB misses in
+    /// guests that touch many pages, e.g. ones that hash or copy large buffers, on hosts with
+    /// transparent hugepages enabled. Elsewhere, or if the kernel refuses, a warning is logged
+    /// and the heap is used as usual. If not set, this is enabled by setting the
+    /// `RISC0_HUGEPAGES` environment variable to `1`.
+    ///
+    /// The `hugepages` benchmark of this crate compares the data TLB misses of execution with
+    /// and without this option.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder().hugepages(true).build().unwrap();
+    /// ```
+    pub fn hugepages(&mut self, enable: bool) -> &mut Self {
+        self.inner.hugepages = Some(enable);
+        self
+    }
+
     /// Add environment variables to the guest environment.
     ///
     /// # Example
@@ -256,6 +766,31 @@ impl<'a> ExecutorEnvBuilder<'a> {
         Ok(self.write_slice(&to_vec(data)?))
     }
 
+    /// Write input data to the zkVM guest stdin as a frame, to be read by `env::read_frame`.
+    ///
+    /// Each frame records its position and the [tag][FrameTag] of its type, so that a guest
+    /// reading frames in a different order, or as a type with a different tag, than they were
+    /// written fails with an error naming both tags, rather than decoding garbage.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .write_frame(&42u32)
+    ///     .unwrap()
+    ///     .write_frame(&vec![1u8, 2, 3])
+    ///     .unwrap()
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn write_frame<T: Serialize + FrameTag + ?Sized>(&mut self, data: &T) -> Result<&mut Self> {
+        let frame = crate::serde::frame::encode(self.inner.input_frames, data)?;
+        self.inner.input_frames += 1;
+        Ok(self.write_slice(&frame))
+    }
+
     /// Write input data to the zkVM guest stdin.
     ///
     /// This function writes a slice directly to the underlying buffer. A
@@ -289,26 +824,196 @@ impl<'a> ExecutorEnvBuilder<'a> {
 
     /// Add a posix-style standard output.
     pub fn stdout(&mut self, writer: impl Write + 'a) -> &mut Self {
+        self.inner.stdout_counter = None;
         self.write_fd(fileno::STDOUT, writer)
     }
 
     /// Add a posix-style standard error.
     pub fn stderr(&mut self, writer: impl Write + 'a) -> &mut Self {
+        self.inner.stderr_counter = None;
         self.write_fd(fileno::STDERR, writer)
     }
 
+    /// Send the guest's standard output to a built-in [Sink].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::{ExecutorEnv, Sink};
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .stdout_sink(Sink::Counting)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn stdout_sink(&mut self, sink: Sink) -> &mut Self {
+        self.inner.stdout_counter = self.sink_fd(fileno::STDOUT, sink);
+        self
+    }
+
+    /// Send the guest's standard error to a built-in [Sink].
+    pub fn stderr_sink(&mut self, sink: Sink) -> &mut Self {
+        self.inner.stderr_counter = self.sink_fd(fileno::STDERR, sink);
+        self
+    }
+
+    /// Copy everything the guest writes to its journal to the sinks of `tee`, as it is written.
+    ///
+    /// The journal is still captured by the executor and committed as usual; this allows a large
+    /// journal to be streamed, e.g. to a file, while the guest runs.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::{cell::RefCell, rc::Rc};
+    ///
+    /// use risc0_zkvm::{ExecutorEnv, Tee};
+    ///
+    /// let copy = Rc::new(RefCell::new(Vec::new()));
+    /// let env = ExecutorEnv::builder()
+    ///     .journal_tee(
+    ///         Tee::new()
+    ///             .with_shared(copy.clone())
+    ///             .with_callback(|bytes| println!("journal: {} bytes", bytes.len())),
+    ///     )
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn journal_tee(&mut self, tee: Tee<'a>) -> &mut Self {
+        self.inner.journal_tee = Some(tee);
+        self
+    }
+
+    fn sink_fd(&mut self, fd: u32, sink: Sink) -> Option<ByteCounter> {
+        match sink {
+            Sink::Null => {
+                self.write_fd(fd, std::io::sink());
+                None
+            }
+            Sink::Counting => {
+                let counter = ByteCounter::default();
+                self.write_fd(fd, counter.clone());
+                Some(counter)
+            }
+        }
+    }
+
     /// Add a posix-style file descriptor for reading.
     pub fn read_fd(&mut self, fd: u32, reader: impl BufRead + 'a) -> &mut Self {
         self.inner.posix_io.borrow_mut().with_read_fd(fd, reader);
         self
     }
 
+    /// Add a posix-style file descriptor for reading from a slow source, such as a file or a
+    /// network connection.
+    ///
+    /// Once the guest starts reading from the descriptor, the next chunks of input are read ahead
+    /// on a helper thread, so that the guest does not wait on host I/O for every `sys_read`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let file = std::fs::File::open("input.bin").unwrap();
+    /// let env = ExecutorEnv::builder()
+    ///     .prefetch_read_fd(3, file)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn prefetch_read_fd(&mut self, fd: u32, reader: impl Read + Send + 'static) -> &mut Self {
+        self.inner
+            .posix_io
+            .borrow_mut()
+            .with_prefetch_read_fd(fd, reader);
+        self
+    }
+
+    /// Add a posix-style file descriptor for reading, which the guest can also reposition with
+    /// `sys_lseek`.
+    pub fn seekable_read_fd(&mut self, fd: u32, reader: impl Read + Seek + 'a) -> &mut Self {
+        self.inner
+            .posix_io
+            .borrow_mut()
+            .with_seekable_read_fd(fd, reader);
+        self
+    }
+
     /// Add a posix-style file descriptor for writing.
     pub fn write_fd(&mut self, fd: u32, writer: impl Write + 'a) -> &mut Self {
         self.inner.posix_io.borrow_mut().with_write_fd(fd, writer);
         self
     }
 
+    /// Limit the number of bytes and calls the guest may transfer through a file descriptor.
+    ///
+    /// Once the quota of a descriptor being read is used up, the guest sees end of file. A write
+    /// that would exceed the quota fails execution with [FdQuotaExceeded][crate::FdQuotaExceeded].
+    /// The usage of each descriptor with a quota is reported in
+    /// [Session::fd_usage][crate::Session::fd_usage].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::{ExecutorEnv, FdQuota};
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .fd_quota(
+    ///         0,
+    ///         FdQuota {
+    ///             max_bytes: Some(1 << 20),
+    ///             max_calls: Some(1024),
+    ///         },
+    ///     )
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn fd_quota(&mut self, fd: u32, quota: FdQuota) -> &mut Self {
+        self.inner.posix_io.borrow_mut().with_fd_quota(fd, quota);
+        self
+    }
+
+    /// Register a stream that the guest can open by name.
+    ///
+    /// The guest opens the stream with `sys_open`, which returns a new file descriptor for
+    /// reading from `reader`. This avoids having to agree on file descriptor numbers
+    /// out-of-band.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let prices: &[u8] = b"1,2,3";
+    /// let env = ExecutorEnv::builder()
+    ///     .named_stream("prices", prices)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn named_stream(&mut self, name: &str, reader: impl Read + 'a) -> &mut Self {
+        self.inner
+            .posix_io
+            .borrow_mut()
+            .with_named_stream(name, reader);
+        self
+    }
+
+    /// Register a seekable stream that the guest can open by name.
+    ///
+    /// Like [ExecutorEnvBuilder::named_stream], but the guest can also reposition the stream
+    /// with `sys_lseek`, e.g. to parse container formats without buffering the entire input.
+    pub fn seekable_named_stream(
+        &mut self,
+        name: &str,
+        reader: impl Read + Seek + 'a,
+    ) -> &mut Self {
+        self.inner
+            .posix_io
+            .borrow_mut()
+            .with_seekable_named_stream(name, reader);
+        self
+    }
+
     /// Add a handler for simple I/O handling.
     pub fn slice_io(&mut self, channel: &str, handler: impl SliceIo + 'a) -> &mut Self {
         self.inner
@@ -331,6 +1036,19 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Declare the [SyscallRing] of the handler added for `channel` with
+    /// [ExecutorEnvBuilder::slice_io] or [ExecutorEnvBuilder::io_callback].
+    ///
+    /// Handlers are assumed to return unchecked host input, [SyscallRing::Input], unless declared
+    /// otherwise.
+    #[cfg(feature = "executor")]
+    pub fn syscall_ring<C: AsRef<str>>(&mut self, channel: C, ring: SyscallRing) -> &mut Self {
+        self.inner
+            .syscall_rings
+            .insert(channel.as_ref().to_string(), ring);
+        self
+    }
+
     /// Add an [AssumptionReceipt] to the [ExecutorEnv], for use in [composition].
     ///
     /// During execution, when the guest calls `env::verify` or `env::verify_integrity`, this
@@ -352,26 +1070,434 @@ impl<'a> ExecutorEnvBuilder<'a> {
     }
 
     /// Add a callback handler for raw trace messages.
+    ///
+    /// Wrap the callback in a [WriteCombiner][crate::WriteCombiner] to receive the stores of the
+    /// guest as [TraceEvent::MemoryRegionSet][crate::TraceEvent::MemoryRegionSet] events
+    /// covering runs of adjacent stores, rather than one event per store, along with a
+    /// [TraceEvent::MemoryRegionPrev][crate::TraceEvent::MemoryRegionPrev] for each if
+    /// [previous values][Self::trace_prev_values] are traced.
     pub fn trace_callback(&mut self, callback: impl TraceCallback + 'a) -> &mut Self {
         self.inner.trace.push(Rc::new(RefCell::new(callback)));
         self
     }
 
+    /// Record the previous contents of registers and memory in trace events.
+    ///
+    /// When enabled, each [TraceEvent::RegisterSet][crate::TraceEvent::RegisterSet] and
+    /// [TraceEvent::MemorySet][crate::TraceEvent::MemorySet] event is accompanied by a
+    /// [TraceEvent::RegisterPrev][crate::TraceEvent::RegisterPrev] or
+    /// [TraceEvent::MemoryPrev][crate::TraceEvent::MemoryPrev] event carrying the value that was
+    /// overwritten, so that a debugger consuming the trace can step backwards by undoing each
+    /// write. This has no effect unless a [trace callback][Self::trace_callback] is registered.
+    pub fn trace_prev_values(&mut self) -> &mut Self {
+        self.inner.trace_prev_values = true;
+        self
+    }
+
+    /// Record the cycles spent paging in trace events.
+    ///
+    /// When enabled, an instruction that pages memory in, or marks it to be paged out, is followed
+    /// by a [TraceEvent::Paging][crate::TraceEvent::Paging] event with the paging cycles it
+    /// caused. This has no effect unless a [trace callback][Self::trace_callback] is registered.
+    pub fn trace_paging(&mut self) -> &mut Self {
+        self.inner.trace_paging = true;
+        self
+    }
+
+    /// Watch the guest memory in `range` for accesses of the given kind, each reported to the
+    /// [watch callback][Self::watch_callback], and to the
+    /// [trace callbacks][Self::trace_callback] as a
+    /// [TraceEvent::Watchpoint][crate::TraceEvent::Watchpoint] event.
+    ///
+    /// This saves a debugger looking for the instruction that corrupts some memory from checking
+    /// every [TraceEvent::MemorySet][crate::TraceEvent::MemorySet]. Instructions are matched by
+    /// the word they access, so a load of a byte next to a watched byte in the same word is also
+    /// reported.
+    pub fn watch(&mut self, range: Range<u32>, kind: WatchKind) -> &mut Self {
+        self.inner.watchpoints.push((range, kind));
+        self
+    }
+
+    /// Call `callback` with each access to the memory watched with [Self::watch], once the
+    /// instruction that made it completes.
+    ///
+    /// Unlike a [trace callback][Self::trace_callback], this does not have the executor trace
+    /// every instruction, so watching memory costs little more than checking each load and store
+    /// against the watched ranges. If `callback` fails, execution fails at the instruction.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::{ExecutorEnv, WatchKind};
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .watch(0x5000..0x5004, WatchKind::Write)
+    ///     .watch_callback(|hit| {
+    ///         println!("0x{:08x} wrote to 0x{:08x}", hit.pc, hit.addr);
+    ///         Ok(())
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn watch_callback(
+        &mut self,
+        callback: impl FnMut(WatchHit) -> Result<()> + 'a,
+    ) -> &mut Self {
+        self.inner.watch_callback = Some(Rc::new(RefCell::new(callback)));
+        self
+    }
+
+    /// Interpret every `ecall` made by the guest as a Linux syscall.
+    ///
+    /// This translates a small set of Linux syscalls (`read`, `write`, `exit`, `exit_group`,
+    /// `brk` and `clock_gettime`) into zkVM syscalls, so that lightly-modified RISC-V Linux
+    /// binaries can be run under the executor for experimentation. The binary must be statically
+    /// linked for rv32im, and must set up its own stack. Sessions executed in this mode cannot be
+    /// proven, and guests built for the zkVM do not run in it.
+    pub fn linux_compat(&mut self) -> &mut Self {
+        self.inner.linux_compat = true;
+        self
+    }
+
     /// Set the path where segments will be stored.
     pub fn segment_path<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
         self.inner.segment_path = Some(SegmentPath::Path(path.as_ref().to_path_buf()));
         self
     }
 
+    /// Hand segments to the prover in memory, as [InMemorySegmentRef][crate::InMemorySegmentRef]s,
+    /// rather than saving them to the [segment path][ExecutorEnvBuilder::segment_path].
+    ///
+    /// This saves writing each segment to disk and reading it back, but holds every segment of
+    /// the session in memory until it is proven, so it is only suited to short sessions.
+    pub fn segments_in_memory(&mut self, enable: bool) -> &mut Self {
+        self.inner.segments_in_memory = enable;
+        self
+    }
+
     /// Enable the profiler and output results to the specified path.
     pub fn enable_profiler<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
         self.inner.pprof_out = Some(path.as_ref().to_path_buf());
         self
     }
 
+    /// Record how often the guest loads from and stores to each page of memory, and how often
+    /// each page is paged in and out, and write a report to the specified path.
+    ///
+    /// The report is a tab-separated table with one row per page, listing the memory region or
+    /// ELF section, the counts, and the symbols on the page, with the most frequently accessed
+    /// pages first.
+    /// Paging is a large part of the cycle count of many guests, and the report can help guide
+    /// changes to data layout that reduce the number of pages each segment touches. Symbols are
+    /// only available when the executor is constructed with [crate::ExecutorImpl::from_elf].
+    pub fn enable_page_heatmap<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.inner.page_heatmap_out = Some(path.as_ref().to_path_buf());
+        self
+    }
+
     /// Set the input digest.
     pub fn input_digest(&mut self, digest: Digest) -> &mut Self {
         self.inner.input_digest = Some(digest);
         self
     }
+
+    /// Account the sessions run with this environment to the given tenant.
+    ///
+    /// The tenant's usage is tracked by `quotas`, which is typically shared by all of the
+    /// environments constructed by an embedded prover. See [QuotaManager] for details.
+    pub fn tenant(&mut self, id: &str, quotas: &QuotaManager) -> &mut Self {
+        self.inner.tenant = Some((id.to_string(), quotas.clone()));
+        self
+    }
+
+    /// Reuse the results of earlier runs with the same memory image and environment from the
+    /// given [ExecutionCache], and retain the results of new runs in it.
+    ///
+    /// See [ExecutionCache] for which runs are cached.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::{ExecutionCache, ExecutorEnv};
+    ///
+    /// let cache = ExecutionCache::new(16);
+    /// let env = ExecutorEnv::builder()
+    ///     .write(&42u32)
+    ///     .unwrap()
+    ///     .execution_cache(&cache)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[cfg(feature = "executor")]
+    pub fn execution_cache(&mut self, cache: &ExecutionCache) -> &mut Self {
+        self.inner.execution_cache = Some(cache.clone());
+        self
+    }
+
+    /// Set the input digest to the SHA-256 digest of all data written to the guest's stdin.
+    ///
+    /// Guests built with the `verify-input` feature of `risc0-zkvm`, which they have to enable,
+    /// hash everything they read from stdin and abort if the result does not match the input
+    /// digest. The check runs when the guest reaches the end of stdin, and on exit, when
+    /// the runtime reads and hashes any stdin the guest did not consume. This allows the host to
+    /// commit to the guest input without any checking code in the guest itself. Data read from
+    /// stdin other than through `risc0_zkvm::guest::env` is not hashed, and so fails the check.
+    ///
+    /// If a digest is set with [ExecutorEnvBuilder::input_digest], the guest checks stdin against
+    /// it instead, which allows committing to input streamed from
+    /// [ExecutorEnvBuilder::stdin]. Otherwise, stdin must be written with
+    /// [ExecutorEnvBuilder::write] or [ExecutorEnvBuilder::write_slice].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .write(&42u32)
+    ///     .unwrap()
+    ///     .commit_input()
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn commit_input(&mut self) -> &mut Self {
+        self.inner.commit_input = true;
+        self
+    }
+
+    /// Set the input digest to the digest of a [Statement] over the stdin, arguments, and
+    /// environment variables of the guest.
+    ///
+    /// The input digest is part of the [ReceiptClaim][crate::ReceiptClaim] of each session. The
+    /// host cannot bind the receipt to the inputs on its own: the guest must read them with
+    /// `env::commit_statement`, which hashes them in the guest, commits the digest to the journal,
+    /// and checks that it matches the input digest. Pass the guest the names of exactly the
+    /// environment variables set here. Stdin must be written with [ExecutorEnvBuilder::write] or
+    /// [ExecutorEnvBuilder::write_slice], since data from other readers cannot be captured.
+    ///
+    /// This overrides [ExecutorEnvBuilder::input_digest] and [ExecutorEnvBuilder::commit_input].
+    /// Guests built with the `verify-input` feature check stdin alone against the input digest,
+    /// and so cannot be run with this option.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    ///
+    /// use risc0_zkvm::{sha::Digestible, ExecutorEnv, Statement};
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .write_slice(b"input")
+    ///     .args(&["--verbose".to_string()])
+    ///     .capture_statement()
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// // A verifier recomputes the digest from the inputs it expects.
+    /// let statement = Statement {
+    ///     stdin: b"input".to_vec(),
+    ///     args: vec!["--verbose".to_string()],
+    ///     env_vars: BTreeMap::new(),
+    /// };
+    /// assert_eq!(env.input_digest(), Some(statement.digest()));
+    /// ```
+    pub fn capture_statement(&mut self) -> &mut Self {
+        self.inner.capture_statement = true;
+        self
+    }
+
+    /// Record every syscall answered by the host in a [SyscallTranscript].
+    ///
+    /// The transcript is available from [Session::syscall_transcript][crate::Session::syscall_transcript]
+    /// once the executor has run.
+    pub fn record_syscall_transcript(&mut self) -> &mut Self {
+        self.inner.record_transcript = true;
+        self
+    }
+
+    /// Record every syscall answered by the host in a [SyscallTranscript], and write it to the
+    /// specified path at the end of each run, including runs that fail.
+    ///
+    /// The file holds the syscalls of every run of the executor so far, so that resuming a paused
+    /// session extends it rather than replacing it. Replaying it with
+    /// [ExecutorEnvBuilder::replay_transcript_from] reproduces the execution exactly on another
+    /// machine, without the host-side handlers or the sources of the input.
+    ///
+    /// The transcript holds every answer the host gave the guest, such as data read from stdin,
+    /// random numbers, environment variables, and the results of verifying assumptions, so it
+    /// should be treated as being as sensitive as the private input of the guest.
+    pub fn record_syscall_transcript_to<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.inner.record_transcript = true;
+        self.inner.record_transcript_out = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Record every source of nondeterministic data that the host supplies to the guest in a
+    /// [NondeterminismReport][crate::NondeterminismReport].
+    ///
+    /// The report is available from
+    /// [Session::nondeterminism_report][crate::Session::nondeterminism_report] once the executor
+    /// has run, and lists the trust assumptions that a verifier makes about the host.
+    pub fn audit_nondeterminism(&mut self) -> &mut Self {
+        self.inner.audit_nondeterminism = true;
+        self
+    }
+
+    /// Answer the guest's syscalls from a recorded [SyscallTranscript] instead of the host-side
+    /// handlers.
+    ///
+    /// Execution fails if the guest makes a syscall with a different name or different arguments
+    /// than the next entry in the transcript, or if the guest halts before the transcript is
+    /// used up. This allows an auditor to confirm that a transcript is exactly what the host fed
+    /// the guest.
+    ///
+    /// Syscalls that send data to the host (`sys_write`, `sys_writev`, `sys_log` and `sys_panic`)
+    /// are still handled by this environment, so that the journal and any output are produced
+    /// as usual, and their answers are checked against the transcript. Assumptions are not
+    /// resolved during a replay, so the resulting session is meant for auditing, not proving.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::{ExecutorEnv, SyscallTranscript};
+    ///
+    /// let transcript = SyscallTranscript::default();
+    /// let env = ExecutorEnv::builder()
+    ///     .replay_transcript(transcript)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn replay_transcript(&mut self, transcript: SyscallTranscript) -> &mut Self {
+        self.inner.replay_transcript = Some(transcript);
+        self
+    }
+
+    /// Answer the guest's syscalls from the [SyscallTranscript] in the file at `path`, written
+    /// by [ExecutorEnvBuilder::record_syscall_transcript_to], as with
+    /// [ExecutorEnvBuilder::replay_transcript].
+    pub fn replay_transcript_from<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self> {
+        let transcript = SyscallTranscript::load(path)?;
+        transcript.verify()?;
+        Ok(self.replay_transcript(transcript))
+    }
+
+    /// Check that execution is deterministic by running the guest a second time.
+    ///
+    /// When enabled, each run of the executor records the syscalls made by the guest, then
+    /// executes the guest again from the same memory image, answering every syscall from the
+    /// recorded transcript without calling the host-side handlers. The run fails if the two
+    /// executions differ in their exit code, final program counter or memory, journal, or
+    /// cycle counts. This roughly doubles the cost of execution, and is meant for testing.
+    pub fn self_check(&mut self, enable: bool) -> &mut Self {
+        self.inner.self_check = enable;
+        self
+    }
+
+    /// Count the number of times the guest executes each kind of instruction.
+    ///
+    /// The counts are reported by opcode mnemonic, e.g. `add` or `lw`, in the
+    /// [SessionStats][crate::SessionStats] of the session. Environment calls are counted as
+    /// `eany`. Instructions executed again after a segment split are only counted once.
+    pub fn insn_stats(&mut self, enable: bool) -> &mut Self {
+        self.inner.insn_stats = enable;
+        self
+    }
+
+    /// Look for patterns in the guest that are known to waste cycles, and report them in
+    /// [Session::perf_warnings][crate::Session::perf_warnings].
+    ///
+    /// The executor reports hot loops that cross a page boundary, many small reads from a file
+    /// descriptor, SHA-256 computed in software rather than with the accelerator, and large
+    /// amounts of bss being zeroed. Each warning is also logged. Function names and bss are only
+    /// known to executors constructed from an ELF. This slows down execution, and is meant for
+    /// development.
+    pub fn perf_warnings(&mut self, enable: bool) -> &mut Self {
+        self.inner.perf_warnings = enable;
+        self
+    }
+
+    /// Track which guest data is derived from host syscalls, and report writes of such data to
+    /// the journal in [Session::tainted_commits][crate::Session::tainted_commits].
+    ///
+    /// Data the host returns from syscalls is not committed to by the receipt, so a guest that
+    /// commits it without checking it lets the prover choose what the journal says. Taint
+    /// follows data through registers and memory, and is cleared from data hashed with the
+    /// SHA-256 accelerator, on the assumption that the guest checks the digest. It does not
+    /// follow control flow, so this is an aid to auditing guests rather than a proof of their
+    /// soundness. This slows down execution, and is meant for development.
+    pub fn taint_tracking(&mut self, enable: bool) -> &mut Self {
+        self.inner.taint_tracking = enable;
+        self
+    }
+
+    /// Check the cycles the executor counts for each instruction against the cycles the prover's
+    /// preflight gives it, as each segment is produced.
+    ///
+    /// Segments are sized from the executor's counts, so a miscounted instruction, e.g. in a new
+    /// accelerator, shows up as a segment that fails to prove. With this set, execution instead
+    /// fails at the end of the segment with a [CycleMismatch][crate::CycleMismatch] naming the
+    /// first instruction counted differently. This runs the preflight of every segment, and is
+    /// meant for developers of accelerators and of the circuit.
+    pub fn cycle_audit(&mut self, enable: bool) -> &mut Self {
+        self.inner.cycle_audit = enable;
+        self
+    }
+
+    /// Restrict the guest to the syscalls in `manifest`, failing at the first syscall that is not
+    /// listed, as with a manifest declared by the guest with
+    /// [syscall_manifest!][crate::syscall_manifest].
+    ///
+    /// This replaces any manifest declared by the guest. Unlike a declared manifest, it is only
+    /// enforced by the host, and its digest is not committed to the journal, so it tells a
+    /// verifier nothing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::{ExecutorEnv, SyscallManifest};
+    /// use risc0_zkvm_platform::syscall::nr::SYS_READ;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .syscall_manifest(SyscallManifest::new([SYS_READ.as_str()]))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[cfg(feature = "executor")]
+    pub fn syscall_manifest(&mut self, manifest: SyscallManifest) -> &mut Self {
+        self.inner.syscall_manifest = Some(manifest);
+        self
+    }
+
+    /// Set the X25519 public key that the guest encrypts its journal to when it calls
+    /// `env::commit_encrypted`.
+    ///
+    /// Encrypted journals require the `journal-encryption` feature.
+    pub fn journal_recipient(&mut self, public_key: [u8; 32]) -> &mut Self {
+        self.inner.journal_recipient = Some(public_key);
+        self
+    }
+
+    /// Register an [AuthenticatedStore] that the guest can open by `name` as an
+    /// [AuthMap][crate::authenticated::AuthMap] or [AuthVec][crate::authenticated::AuthVec].
+    ///
+    /// The guest checks every entry it reads against the root it was given, and the entries it
+    /// writes are applied to `store`, so the host can read them from a clone of `store` once the
+    /// session ends.
+    pub fn authenticated_store(&mut self, name: &str, store: AuthenticatedStore) -> &mut Self {
+        self.inner
+            .authenticated_stores
+            .insert(name.to_string(), store);
+        self
+    }
+
+    /// Set the ID of the [Session][crate::Session] produced by the executor.
+    ///
+    /// The ID is recorded as the `session_id` field of the `tracing` spans emitted while
+    /// executing and proving the session, and is carried by each of its segments, so that a job
+    /// proven across several machines can be traced end-to-end. A random ID is used if none is
+    /// set.
+    pub fn session_id(&mut self, id: impl Into<String>) -> &mut Self {
+        self.inner.session_id = Some(id.into());
+        self
+    }
 }
@@ -12,7 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub(crate) mod audit;
 pub(crate) mod env;
 pub(crate) mod posix_io;
+pub(crate) mod prefetch;
 pub(crate) mod prove;
+pub(crate) mod quota;
+pub(crate) mod sink;
 pub(crate) mod slice_io;
+pub(crate) mod transcript;
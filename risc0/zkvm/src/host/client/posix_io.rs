@@ -14,23 +14,113 @@
 
 use std::{
     cell::RefCell,
-    collections::BTreeMap,
-    io::{stderr, stdout, Cursor, Read, Write},
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    io::{self, stderr, stdout, Cursor, Read, Seek, SeekFrom, Write},
     rc::Rc,
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, ensure, Result};
 
 use risc0_zkvm_platform::fileno;
+use serde::{Deserialize, Serialize};
+
+use super::prefetch::PrefetchReader;
+use crate::host::prove_info::FdUsage;
 
 type SharedRead<'a> = Rc<RefCell<dyn Read + 'a>>;
 type SharedWrite<'a> = Rc<RefCell<dyn Write + 'a>>;
+type SharedSeek<'a> = Rc<RefCell<dyn Seek + 'a>>;
+
+/// A stream registered by name, which the guest can open at runtime.
+#[derive(Clone)]
+pub(crate) struct NamedStream<'a> {
+    pub(crate) reader: SharedRead<'a>,
+    pub(crate) seeker: Option<SharedSeek<'a>>,
+}
+
+/// What a file descriptor opened or closed by the guest refers to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum GuestFd {
+    /// The named stream opened with `sys_open`.
+    Stream(String),
+    /// A duplicate of the file descriptor of the environment.
+    Dup(u32),
+    /// An end of a pipe.
+    Pipe,
+    /// Closed with `sys_close`.
+    Closed,
+}
+
+/// Where the guest reads from: a file descriptor of the environment, or a named stream.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub(crate) enum ReadSource {
+    Fd(u32),
+    Stream(String),
+}
+
+/// The state of the file descriptors of the guest, saved in an
+/// [ExecutorState][crate::ExecutorState].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct PosixIoState {
+    guest_fds: BTreeMap<u32, GuestFd>,
+    // The offset of each seekable source, and the bytes read from each other one.
+    positions: BTreeMap<ReadSource, u64>,
+    usage: BTreeMap<u32, FdUsage>,
+}
+
+/// Limits on the data the guest may transfer through a single file descriptor.
+///
+/// Once a read quota is used up, the guest sees end of file on the descriptor. A write that
+/// would exceed its quota fails execution with [FdQuotaExceeded].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FdQuota {
+    /// Maximum number of bytes transferred over all calls.
+    pub max_bytes: Option<u64>,
+
+    /// Maximum number of `sys_read` or `sys_write` calls.
+    pub max_calls: Option<u64>,
+}
+
+/// The error returned when the guest exceeds the [FdQuota] of a file descriptor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FdQuotaExceeded {
+    /// The file descriptor.
+    pub fd: u32,
+
+    /// The quota of the file descriptor.
+    pub quota: FdQuota,
+}
+
+impl fmt::Display for FdQuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Guest exceeded the quota of file descriptor {}: {:?}",
+            self.fd, self.quota
+        )
+    }
+}
+
+impl std::error::Error for FdQuotaExceeded {}
 
 /// Posix-style I/O
 #[derive(Clone)]
 pub struct PosixIo<'a> {
     pub(crate) read_fds: BTreeMap<u32, SharedRead<'a>>,
     pub(crate) write_fds: BTreeMap<u32, SharedWrite<'a>>,
+    pub(crate) seek_fds: BTreeMap<u32, SharedSeek<'a>>,
+    pub(crate) named_streams: BTreeMap<String, NamedStream<'a>>,
+    pub(crate) quotas: BTreeMap<u32, FdQuota>,
+    pub(crate) usage: BTreeMap<u32, FdUsage>,
+    // The read fds set after the defaults, whose readers supply data that is not in the env.
+    pub(crate) custom_read_fds: BTreeSet<u32>,
+    // The write fds set after the defaults, whose writers observe what the guest writes.
+    pub(crate) custom_write_fds: BTreeSet<u32>,
+    // The fds the guest opened, duplicated, or closed.
+    pub(crate) guest_fds: BTreeMap<u32, GuestFd>,
+    // The bytes the guest read from each source.
+    consumed: BTreeMap<ReadSource, u64>,
 }
 
 impl<'a> Default for PosixIo<'a> {
@@ -39,6 +129,8 @@ impl<'a> Default for PosixIo<'a> {
         new.with_read_fd(fileno::STDIN, Cursor::new(vec![]))
             .with_write_fd(fileno::STDOUT, stdout())
             .with_write_fd(fileno::STDERR, stderr());
+        new.custom_read_fds.clear();
+        new.custom_write_fds.clear();
         new
     }
 }
@@ -48,6 +140,14 @@ impl<'a> PosixIo<'a> {
         Self {
             read_fds: Default::default(),
             write_fds: Default::default(),
+            seek_fds: Default::default(),
+            named_streams: Default::default(),
+            quotas: Default::default(),
+            usage: Default::default(),
+            custom_read_fds: Default::default(),
+            custom_write_fds: Default::default(),
+            guest_fds: Default::default(),
+            consumed: Default::default(),
         }
     }
 
@@ -68,6 +168,24 @@ impl<'a> PosixIo<'a> {
         T: Read + 'a,
     {
         self.read_fds.insert(fd, reader);
+        self.seek_fds.remove(&fd);
+        self.custom_read_fds.insert(fd);
+        self
+    }
+
+    pub fn with_prefetch_read_fd(
+        &mut self,
+        fd: u32,
+        reader: impl Read + Send + 'static,
+    ) -> &mut Self {
+        self.with_read_fd(fd, PrefetchReader::new(reader))
+    }
+
+    pub fn with_seekable_read_fd(&mut self, fd: u32, reader: impl Read + Seek + 'a) -> &mut Self {
+        let reader = Rc::new(RefCell::new(reader));
+        self.read_fds.insert(fd, reader.clone());
+        self.seek_fds.insert(fd, reader);
+        self.custom_read_fds.insert(fd);
         self
     }
 
@@ -80,9 +198,184 @@ impl<'a> PosixIo<'a> {
         T: Write + 'a,
     {
         self.write_fds.insert(fd, writer);
+        self.custom_write_fds.insert(fd);
         self
     }
 
+    pub fn with_named_stream(&mut self, name: &str, reader: impl Read + 'a) -> &mut Self {
+        let stream = NamedStream {
+            reader: Rc::new(RefCell::new(reader)),
+            seeker: None,
+        };
+        self.named_streams.insert(name.to_string(), stream);
+        self
+    }
+
+    pub fn with_seekable_named_stream(
+        &mut self,
+        name: &str,
+        reader: impl Read + Seek + 'a,
+    ) -> &mut Self {
+        let reader = Rc::new(RefCell::new(reader));
+        let stream = NamedStream {
+            reader: reader.clone(),
+            seeker: Some(reader),
+        };
+        self.named_streams.insert(name.to_string(), stream);
+        self
+    }
+
+    pub fn with_fd_quota(&mut self, fd: u32, quota: FdQuota) -> &mut Self {
+        self.quotas.insert(fd, quota);
+        self.usage.insert(fd, FdUsage::default());
+        self
+    }
+
+    /// Count a call on `fd` that asks to transfer `nbytes` bytes, and return how many of them fit
+    /// in the quota of `fd`. The bytes actually transferred are counted with [PosixIo::record].
+    pub(crate) fn charge(&mut self, fd: u32, nbytes: usize) -> Result<usize, FdQuotaExceeded> {
+        let Some(&quota) = self.quotas.get(&fd) else {
+            return Ok(nbytes);
+        };
+        let usage = self.usage.entry(fd).or_default();
+        usage.calls += 1;
+        let calls_left = quota.max_calls.map_or(true, |max| usage.calls <= max);
+        let bytes_left = quota
+            .max_bytes
+            .map_or(u64::MAX, |max| max.saturating_sub(usage.bytes));
+        if !calls_left || (bytes_left == 0 && nbytes > 0) {
+            usage.exceeded = true;
+            return Err(FdQuotaExceeded { fd, quota });
+        }
+        let allowed = nbytes.min(bytes_left.try_into().unwrap_or(usize::MAX));
+        usage.exceeded |= allowed < nbytes;
+        Ok(allowed)
+    }
+
+    /// Count `nbytes` bytes transferred through `fd`.
+    pub(crate) fn record(&mut self, fd: u32, nbytes: usize) {
+        if let Some(usage) = self.usage.get_mut(&fd) {
+            usage.bytes += nbytes as u64;
+        }
+    }
+
+    /// Count `nbytes` bytes read by the guest from `fd`.
+    pub(crate) fn consume(&mut self, fd: u32, nbytes: usize) {
+        let source = match self.guest_fds.get(&fd) {
+            Some(GuestFd::Stream(name)) => ReadSource::Stream(name.clone()),
+            Some(GuestFd::Dup(fd)) => ReadSource::Fd(*fd),
+            _ => ReadSource::Fd(fd),
+        };
+        *self.consumed.entry(source).or_default() += nbytes as u64;
+    }
+
+    /// The state of the file descriptors, which [PosixIo::restore] applies to the equivalent
+    /// file descriptors of another environment.
+    pub(crate) fn save(&self) -> Result<PosixIoState> {
+        if let Some(fd) = self
+            .guest_fds
+            .iter()
+            .find_map(|(fd, guest_fd)| (*guest_fd == GuestFd::Pipe).then_some(fd))
+        {
+            bail!("The state of pipe {fd} opened by the guest cannot be saved");
+        }
+        let mut positions = self.consumed.clone();
+        for (fd, seeker) in &self.seek_fds {
+            if !self.guest_fds.contains_key(fd) {
+                let pos = seeker.borrow_mut().stream_position()?;
+                positions.insert(ReadSource::Fd(*fd), pos);
+            }
+        }
+        for (name, stream) in &self.named_streams {
+            if let Some(seeker) = &stream.seeker {
+                let pos = seeker.borrow_mut().stream_position()?;
+                positions.insert(ReadSource::Stream(name.clone()), pos);
+            }
+        }
+        Ok(PosixIoState {
+            guest_fds: self.guest_fds.clone(),
+            positions,
+            usage: self.usage.clone(),
+        })
+    }
+
+    /// Continue from a state taken by [PosixIo::save], whose sources hold the same data as the
+    /// ones of this environment: seekable sources are sought to where they were, and the bytes
+    /// the guest already read from the others are skipped.
+    pub(crate) fn restore(&mut self, state: PosixIoState) -> Result<()> {
+        for (source, &pos) in &state.positions {
+            let (reader, seeker) = match source {
+                ReadSource::Fd(fd) => (self.read_fds.get(fd), self.seek_fds.get(fd)),
+                ReadSource::Stream(name) => match self.named_streams.get(name) {
+                    Some(stream) => (Some(&stream.reader), stream.seeker.as_ref()),
+                    None => (None, None),
+                },
+            };
+            if let Some(seeker) = seeker {
+                seeker.borrow_mut().seek(SeekFrom::Start(pos))?;
+                continue;
+            }
+            let Some(reader) = reader else {
+                bail!("The environment has no {source:?} for the guest to read from");
+            };
+            let skipped = io::copy(&mut (&mut *reader.borrow_mut()).take(pos), &mut io::sink())?;
+            ensure!(
+                skipped == pos,
+                "{source:?} ended after {skipped} of the {pos} bytes the guest had read"
+            );
+        }
+        self.consumed = state.positions;
+
+        for (fd, guest_fd) in &state.guest_fds {
+            match guest_fd {
+                GuestFd::Stream(name) => {
+                    let stream = self
+                        .named_streams
+                        .get(name)
+                        .ok_or_else(|| anyhow!("The environment has no stream named {name:?}"))?
+                        .clone();
+                    self.read_fds.insert(*fd, stream.reader);
+                    if let Some(seeker) = stream.seeker {
+                        self.seek_fds.insert(*fd, seeker);
+                    }
+                }
+                GuestFd::Dup(orig) => {
+                    if let Some(reader) = self.read_fds.get(orig).cloned() {
+                        self.read_fds.insert(*fd, reader);
+                    }
+                    if let Some(writer) = self.write_fds.get(orig).cloned() {
+                        self.write_fds.insert(*fd, writer);
+                    }
+                    if let Some(seeker) = self.seek_fds.get(orig).cloned() {
+                        self.seek_fds.insert(*fd, seeker);
+                    }
+                }
+                GuestFd::Pipe => {
+                    bail!("The state of pipe {fd} opened by the guest cannot be restored")
+                }
+                GuestFd::Closed => {
+                    self.read_fds.remove(fd);
+                    self.write_fds.remove(fd);
+                    self.seek_fds.remove(fd);
+                }
+            }
+        }
+        self.guest_fds = state.guest_fds;
+        self.usage.extend(state.usage);
+        Ok(())
+    }
+
+    /// Like [PosixIo::charge], but fail unless all `nbytes` bytes fit in the quota of `fd`.
+    pub(crate) fn charge_all(&mut self, fd: u32, nbytes: usize) -> Result<(), FdQuotaExceeded> {
+        if self.charge(fd, nbytes)? < nbytes {
+            return Err(FdQuotaExceeded {
+                fd,
+                quota: self.quotas[&fd],
+            });
+        }
+        Ok(())
+    }
+
     pub fn get_reader(&self, fd: u32) -> Result<SharedRead<'a>> {
         self.read_fds
             .get(&fd)
@@ -96,4 +389,11 @@ impl<'a> PosixIo<'a> {
             .ok_or(anyhow!("Bad write file descriptor {fd}"))
             .cloned()
     }
+
+    pub fn get_seeker(&self, fd: u32) -> Result<SharedSeek<'a>> {
+        self.seek_fds
+            .get(&fd)
+            .ok_or(anyhow!("File descriptor {fd} is not seekable"))
+            .cloned()
+    }
 }
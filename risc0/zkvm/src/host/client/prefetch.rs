@@ -0,0 +1,181 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    io::{self, ErrorKind, Read},
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+};
+
+/// The smallest chunk requested from the backing reader.
+const MIN_CHUNK_SIZE: usize = 4 * 1024;
+
+/// The largest chunk requested from the backing reader.
+const MAX_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The number of chunks to keep in flight while the guest is streaming.
+const PREFETCH_DEPTH: usize = 2;
+
+/// A reader that fetches data from a slow backing reader on a helper thread.
+///
+/// Nothing is read until the guest issues its first `sys_read`. From then on the guest is assumed
+/// to be streaming through the input, and the helper thread keeps up to [PREFETCH_DEPTH] chunks
+/// in flight. The chunk size follows the size of the guest's most recent read, so that a guest
+/// reading in large blocks is served with large chunks.
+pub(crate) struct PrefetchReader {
+    requests: Sender<usize>,
+    chunks: Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+    pending: usize,
+    chunk_size: usize,
+    eof: bool,
+}
+
+impl PrefetchReader {
+    pub(crate) fn new(mut inner: impl Read + Send + 'static) -> Self {
+        let (requests, request_rx) = channel::<usize>();
+        let (chunk_tx, chunks) = channel();
+        // The thread exits once this reader is dropped and the request channel is closed.
+        thread::spawn(move || {
+            for len in request_rx {
+                let mut chunk = vec![0u8; len];
+                let result = read_chunk(&mut inner, &mut chunk).map(|nread| {
+                    chunk.truncate(nread);
+                    chunk
+                });
+                if chunk_tx.send(result).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            requests,
+            chunks,
+            chunk: Vec::new(),
+            pos: 0,
+            pending: 0,
+            chunk_size: MIN_CHUNK_SIZE,
+            eof: false,
+        }
+    }
+
+    fn request(&mut self) -> io::Result<()> {
+        self.requests
+            .send(self.chunk_size)
+            .map_err(|_| io::Error::other("prefetch thread exited"))?;
+        self.pending += 1;
+        Ok(())
+    }
+
+    fn recv(&mut self) -> io::Result<Vec<u8>> {
+        self.pending -= 1;
+        self.chunks
+            .recv()
+            .map_err(|_| io::Error::other("prefetch thread exited"))?
+    }
+}
+
+impl Read for PrefetchReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.chunk_size = buf.len().clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE);
+
+        if self.pos == self.chunk.len() {
+            if self.pending == 0 {
+                if self.eof {
+                    return Ok(0);
+                }
+                self.request()?;
+            }
+            self.chunk = self.recv()?;
+            self.pos = 0;
+            if self.chunk.is_empty() {
+                self.eof = true;
+                return Ok(0);
+            }
+        }
+
+        let nread = buf.len().min(self.chunk.len() - self.pos);
+        buf[..nread].copy_from_slice(&self.chunk[self.pos..self.pos + nread]);
+        self.pos += nread;
+
+        // Keep the helper thread busy while the guest works through the current chunk.
+        if !self.eof {
+            while self.pending < PREFETCH_DEPTH {
+                self.request()?;
+            }
+        }
+        Ok(nread)
+    }
+}
+
+/// Read until `buf` is full or the reader reaches EOF.
+fn read_chunk(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut tot_nread = 0;
+    while tot_nread < buf.len() {
+        match reader.read(&mut buf[tot_nread..]) {
+            Ok(0) => break,
+            Ok(nread) => tot_nread += nread,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(tot_nread)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+
+    use super::{PrefetchReader, MIN_CHUNK_SIZE};
+
+    #[test]
+    fn streams_all_data() {
+        let data: Vec<u8> = (0..5 * MIN_CHUNK_SIZE + 17)
+            .map(|x| (x % 251) as u8)
+            .collect();
+        let mut reader = PrefetchReader::new(io::Cursor::new(data.clone()));
+
+        let mut actual = Vec::new();
+        for len in [1, 3, 100, MIN_CHUNK_SIZE * 2, 7].into_iter().cycle() {
+            let mut buf = vec![0u8; len];
+            let nread = reader.read(&mut buf).unwrap();
+            if nread == 0 {
+                break;
+            }
+            actual.extend_from_slice(&buf[..nread]);
+        }
+        assert_eq!(actual, data);
+        assert_eq!(reader.read(&mut [0u8; 4]).unwrap(), 0);
+    }
+
+    #[test]
+    fn propagates_errors() {
+        struct Failing;
+
+        impl Read for Failing {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("backing reader failed"))
+            }
+        }
+
+        let mut reader = PrefetchReader::new(Failing);
+        let err = reader.read(&mut [0u8; 4]).unwrap_err();
+        assert_eq!(err.to_string(), "backing reader failed");
+    }
+}
@@ -115,11 +115,11 @@ impl Prover for BonsaiProver {
                 }
                 break ProveInfo {
                     receipt,
-                    stats: crate::SessionStats {
-                        segments: stats.segments,
-                        total_cycles: stats.total_cycles,
-                        user_cycles: stats.cycles,
-                    },
+                    stats: crate::SessionStats::new(
+                        stats.segments,
+                        stats.total_cycles,
+                        stats.cycles,
+                    ),
                 };
             } else {
                 bail!(
@@ -0,0 +1,433 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Content-addressed caching of receipts produced by a [Prover][super::Prover].
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{ensure, Context as _, Result};
+use hex::FromHex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    sha::{Digest, Digestible, Impl, Sha256},
+    ProveInfo, VERSION,
+};
+
+/// The key under which a [Receipt] is stored in a [ReceiptCache].
+///
+/// Two proving requests with the same key are expected to produce receipts for the same claim.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ReceiptCacheKey {
+    /// Image ID of the guest program being proven.
+    pub image_id: Digest,
+
+    /// Digest of everything else that determines the proving request, such as the guest input
+    /// and the requested receipt kind.
+    pub input_digest: Digest,
+
+    /// Version of the prover that produced the receipt.
+    pub prover_version: String,
+}
+
+impl ReceiptCacheKey {
+    /// Construct a [ReceiptCacheKey] for the current prover version.
+    pub fn new(image_id: impl Into<Digest>, input_digest: impl Into<Digest>) -> Self {
+        Self {
+            image_id: image_id.into(),
+            input_digest: input_digest.into(),
+            prover_version: VERSION.to_string(),
+        }
+    }
+
+    /// Returns a digest uniquely identifying this key.
+    pub fn digest(&self) -> Digest {
+        *Impl::hash_bytes(
+            &[
+                self.image_id.as_bytes(),
+                self.input_digest.as_bytes(),
+                self.prover_version.as_bytes(),
+            ]
+            .concat(),
+        )
+    }
+}
+
+/// A store of previously generated receipts, consulted before proving.
+///
+/// Entries are stored as [ProveInfo] so that the session stats of the original proving run are
+/// available on a cache hit.
+pub trait ReceiptCache {
+    /// Look up the [ProveInfo] stored under the given key.
+    ///
+    /// Returns `Ok(None)` on a cache miss. Implementations should treat entries that fail
+    /// integrity checks as a miss rather than returning them.
+    fn get(&self, key: &ReceiptCacheKey) -> Result<Option<ProveInfo>>;
+
+    /// Store a [ProveInfo] under the given key.
+    fn put(&self, key: &ReceiptCacheKey, info: &ProveInfo) -> Result<()>;
+}
+
+/// Information about an entry in a [FileReceiptCache], passed to an [EvictionPolicy].
+#[derive(Clone, Debug)]
+pub struct ReceiptCacheEntry {
+    /// Digest of the [ReceiptCacheKey] for this entry.
+    pub key_digest: Digest,
+
+    /// Size of the entry on disk, in bytes.
+    pub size: u64,
+
+    /// The last time this entry was written or read.
+    pub last_used: SystemTime,
+}
+
+/// A hook deciding which entries to remove from a [FileReceiptCache].
+///
+/// The policy is invoked each time a new entry is stored.
+pub trait EvictionPolicy {
+    /// Select the entries to evict, given all entries currently in the cache.
+    fn select(&self, entries: &[ReceiptCacheEntry]) -> Vec<Digest>;
+}
+
+/// An [EvictionPolicy] that never evicts anything.
+pub struct NoEviction;
+
+impl EvictionPolicy for NoEviction {
+    fn select(&self, _entries: &[ReceiptCacheEntry]) -> Vec<Digest> {
+        Vec::new()
+    }
+}
+
+/// An [EvictionPolicy] that keeps at most the given number of entries, evicting the least
+/// recently used ones first.
+pub struct MaxEntries(pub usize);
+
+impl EvictionPolicy for MaxEntries {
+    fn select(&self, entries: &[ReceiptCacheEntry]) -> Vec<Digest> {
+        lru_order(entries)
+            .into_iter()
+            .take(entries.len().saturating_sub(self.0))
+            .map(|entry| entry.key_digest)
+            .collect()
+    }
+}
+
+/// An [EvictionPolicy] that keeps the total size of the cache under the given number of bytes,
+/// evicting the least recently used entries first.
+pub struct MaxBytes(pub u64);
+
+impl EvictionPolicy for MaxBytes {
+    fn select(&self, entries: &[ReceiptCacheEntry]) -> Vec<Digest> {
+        let mut total: u64 = entries.iter().map(|entry| entry.size).sum();
+        lru_order(entries)
+            .into_iter()
+            .take_while(|entry| {
+                let evict = total > self.0;
+                total -= entry.size;
+                evict
+            })
+            .map(|entry| entry.key_digest)
+            .collect()
+    }
+}
+
+fn lru_order(entries: &[ReceiptCacheEntry]) -> Vec<&ReceiptCacheEntry> {
+    let mut sorted: Vec<_> = entries.iter().collect();
+    sorted.sort_by_key(|entry| entry.last_used);
+    sorted
+}
+
+/// The record stored on disk for each entry of a [FileReceiptCache].
+#[derive(Serialize, Deserialize)]
+struct CacheRecord {
+    key: ReceiptCacheKey,
+    checksum: Digest,
+    info: Vec<u8>,
+}
+
+/// A [ReceiptCache] that stores each receipt as a file in a directory.
+///
+/// Entries are named by the digest of their [ReceiptCacheKey]. Each entry records its key and a
+/// checksum of its contents, which are checked on every read along with the image ID committed to
+/// by the receipt.
+pub struct FileReceiptCache {
+    dir: PathBuf,
+    policy: Box<dyn EvictionPolicy>,
+}
+
+const ENTRY_EXT: &str = "receipt";
+
+impl FileReceiptCache {
+    /// Construct a [FileReceiptCache] that stores entries in the given directory.
+    ///
+    /// The directory is created if it does not exist.
+    pub fn new<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create receipt cache: {}", dir.display()))?;
+        Ok(Self {
+            dir,
+            policy: Box::new(NoEviction),
+        })
+    }
+
+    /// Set the [EvictionPolicy] used by this cache.
+    pub fn with_eviction_policy(mut self, policy: impl EvictionPolicy + 'static) -> Self {
+        self.policy = Box::new(policy);
+        self
+    }
+
+    /// List the entries currently in the cache.
+    pub fn entries(&self) -> Result<Vec<ReceiptCacheEntry>> {
+        let mut entries = Vec::new();
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(ENTRY_EXT) {
+                continue;
+            }
+            let Some(key_digest) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| Digest::from_hex(stem).ok())
+            else {
+                continue;
+            };
+            let metadata = fs::metadata(&path)?;
+            entries.push(ReceiptCacheEntry {
+                key_digest,
+                size: metadata.len(),
+                last_used: metadata.modified()?,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Remove the entry with the given key digest, if present.
+    pub fn remove(&self, key_digest: &Digest) -> Result<()> {
+        match fs::remove_file(self.entry_path(key_digest)) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn entry_path(&self, key_digest: &Digest) -> PathBuf {
+        self.dir
+            .join(format!("{}.{ENTRY_EXT}", hex::encode(key_digest)))
+    }
+
+    fn read_record(&self, path: &Path, key: &ReceiptCacheKey) -> Result<ProveInfo> {
+        let record: CacheRecord = bincode::deserialize(&fs::read(path)?)?;
+        ensure!(&record.key == key, "cache entry key mismatch");
+        ensure!(
+            *Impl::hash_bytes(&record.info) == record.checksum,
+            "cache entry checksum mismatch"
+        );
+        let info: ProveInfo = bincode::deserialize(&record.info)?;
+        if let Ok(claim) = info.receipt.claim()?.as_value() {
+            ensure!(
+                claim.pre.digest() == key.image_id,
+                "cached receipt does not match image ID"
+            );
+        }
+        Ok(info)
+    }
+}
+
+impl ReceiptCache for FileReceiptCache {
+    fn get(&self, key: &ReceiptCacheKey) -> Result<Option<ProveInfo>> {
+        let path = self.entry_path(&key.digest());
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        match self.read_record(&path, key) {
+            Ok(info) => {
+                // Record the access for the eviction policy.
+                fs::File::options()
+                    .write(true)
+                    .open(&path)?
+                    .set_modified(SystemTime::now())?;
+                Ok(Some(info))
+            }
+            Err(err) => {
+                tracing::warn!("discarding corrupt receipt cache entry {path:?}: {err}");
+                fs::remove_file(&path)?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn put(&self, key: &ReceiptCacheKey, info: &ProveInfo) -> Result<()> {
+        let info = bincode::serialize(info)?;
+        let record = CacheRecord {
+            key: key.clone(),
+            checksum: *Impl::hash_bytes(&info),
+            info,
+        };
+
+        // Write to a temporary file first so that readers never observe a partial entry.
+        let path = self.entry_path(&key.digest());
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, bincode::serialize(&record)?)?;
+        fs::rename(&tmp_path, &path)?;
+
+        for key_digest in self.policy.select(&self.entries()?) {
+            tracing::debug!("evicting receipt cache entry: {key_digest}");
+            self.remove(&key_digest)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A [ReceiptCache] that could not be opened, which fails every lookup with the reason.
+///
+/// This lets [default_prover][super::default_prover], which cannot return an error, report a
+/// misconfigured cache on the first proving request rather than panic or silently prove without
+/// it.
+#[cfg(feature = "prove")]
+pub(crate) struct UnavailableReceiptCache {
+    reason: String,
+}
+
+#[cfg(feature = "prove")]
+impl UnavailableReceiptCache {
+    pub(crate) fn new(err: anyhow::Error) -> Self {
+        Self {
+            reason: format!("{err:#}"),
+        }
+    }
+}
+
+#[cfg(feature = "prove")]
+impl ReceiptCache for UnavailableReceiptCache {
+    fn get(&self, _key: &ReceiptCacheKey) -> Result<Option<ProveInfo>> {
+        anyhow::bail!("Receipt cache unavailable: {}", self.reason)
+    }
+
+    fn put(&self, _key: &ReceiptCacheKey, _info: &ProveInfo) -> Result<()> {
+        anyhow::bail!("Receipt cache unavailable: {}", self.reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread::sleep, time::Duration};
+
+    use super::*;
+    use crate::{
+        ExitCode, FakeReceipt, InnerReceipt, MaybePruned, Receipt, ReceiptClaim, SessionStats,
+        SystemState,
+    };
+
+    fn fake_info(image_id: Digest, journal: &[u8]) -> ProveInfo {
+        let claim = ReceiptClaim {
+            pre: MaybePruned::Pruned(image_id),
+            post: SystemState {
+                pc: 0,
+                merkle_root: Digest::ZERO,
+            }
+            .into(),
+            exit_code: ExitCode::Halted(0),
+            input: MaybePruned::Pruned(Digest::ZERO),
+            output: None.into(),
+        };
+        ProveInfo {
+            receipt: Receipt::new(
+                InnerReceipt::Fake(FakeReceipt::new(claim)),
+                journal.to_vec(),
+            ),
+            stats: SessionStats::new(1, 1 << 16, 1000),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = FileReceiptCache::new(dir.path()).unwrap();
+        let key = ReceiptCacheKey::new(Digest::from([1u32; 8]), Digest::from([2u32; 8]));
+        let info = fake_info(key.image_id, b"journal");
+
+        assert!(cache.get(&key).unwrap().is_none());
+        cache.put(&key, &info).unwrap();
+        let cached = cache.get(&key).unwrap().unwrap();
+        assert_eq!(cached.receipt, info.receipt);
+        assert_eq!(cached.stats.user_cycles, info.stats.user_cycles);
+
+        let other = ReceiptCacheKey::new(key.image_id, Digest::from([3u32; 8]));
+        assert!(cache.get(&other).unwrap().is_none());
+    }
+
+    #[test]
+    fn corrupt_entry_is_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = FileReceiptCache::new(dir.path()).unwrap();
+        let key = ReceiptCacheKey::new(Digest::from([1u32; 8]), Digest::ZERO);
+        cache
+            .put(&key, &fake_info(key.image_id, b"journal"))
+            .unwrap();
+
+        let path = cache.entry_path(&key.digest());
+        let mut bytes = fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        fs::write(&path, bytes).unwrap();
+
+        assert!(cache.get(&key).unwrap().is_none());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn mismatched_image_id_is_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = FileReceiptCache::new(dir.path()).unwrap();
+        let key = ReceiptCacheKey::new(Digest::from([1u32; 8]), Digest::ZERO);
+        cache
+            .put(&key, &fake_info(Digest::from([9u32; 8]), b"journal"))
+            .unwrap();
+        assert!(cache.get(&key).unwrap().is_none());
+    }
+
+    #[test]
+    fn max_entries_evicts_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = FileReceiptCache::new(dir.path())
+            .unwrap()
+            .with_eviction_policy(MaxEntries(2));
+        let keys: Vec<_> = (0..3u32)
+            .map(|i| ReceiptCacheKey::new(Digest::from([1u32; 8]), Digest::from([i; 8])))
+            .collect();
+
+        for key in &keys[..2] {
+            cache
+                .put(key, &fake_info(key.image_id, b"journal"))
+                .unwrap();
+            sleep(Duration::from_millis(10));
+        }
+        // Touch the first entry so that the second becomes the least recently used.
+        assert!(cache.get(&keys[0]).unwrap().is_some());
+        sleep(Duration::from_millis(10));
+        cache
+            .put(&keys[2], &fake_info(keys[2].image_id, b"journal"))
+            .unwrap();
+
+        assert_eq!(cache.entries().unwrap().len(), 2);
+        assert!(cache.get(&keys[0]).unwrap().is_some());
+        assert!(cache.get(&keys[1]).unwrap().is_none());
+        assert!(cache.get(&keys[2]).unwrap().is_some());
+    }
+}
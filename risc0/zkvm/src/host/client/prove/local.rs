@@ -12,18 +12,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::rc::Rc;
+
 use anyhow::Result;
+use risc0_binfmt::compute_image_id;
 
-use super::{Executor, Prover, ProverOpts};
+use super::{
+    cache::{ReceiptCache, ReceiptCacheKey},
+    Executor, Prover, ProverOpts,
+};
 use crate::{
-    get_prover_server, host::server::session::NullSegmentRef, ExecutorEnv, ExecutorImpl, ProveInfo,
-    Receipt, SegmentInfo, SessionInfo, VerifierContext,
+    get_prover_server,
+    sha::{Impl, Sha256},
+    ExecutorEnv, LocalExecutor, ProveInfo, Receipt, SessionInfo, VerifierContext,
 };
 
 /// A [Prover] implementation that selects a [ProverServer][crate::ProverServer] by calling
 /// [get_prover_server].
 pub struct LocalProver {
     name: String,
+    cache: Option<Rc<dyn ReceiptCache>>,
 }
 
 impl LocalProver {
@@ -31,8 +39,35 @@ impl LocalProver {
     pub fn new(name: &str) -> Self {
         Self {
             name: name.to_string(),
+            cache: None,
         }
     }
+
+    /// Consult the given [ReceiptCache] before proving, and store newly generated receipts in it.
+    ///
+    /// Only requests where all of the guest input is known up front (i.e. provided with
+    /// [ExecutorEnvBuilder::write][crate::ExecutorEnvBuilder::write] and friends) are cached.
+    pub fn with_cache(mut self, cache: impl ReceiptCache + 'static) -> Self {
+        self.cache = Some(Rc::new(cache));
+        self
+    }
+
+    fn cache_key(
+        &self,
+        env: &ExecutorEnv<'_>,
+        elf: &[u8],
+        opts: &ProverOpts,
+    ) -> Result<Option<ReceiptCacheKey>> {
+        let Some(request_digest) = env.request_digest() else {
+            return Ok(None);
+        };
+        let opts_digest = Impl::hash_bytes(&bincode::serialize(opts)?);
+        let input_digest = Impl::hash_pair(&request_digest, &opts_digest);
+        Ok(Some(ReceiptCacheKey::new(
+            compute_image_id(elf)?,
+            *input_digest,
+        )))
+    }
 }
 
 impl Prover for LocalProver {
@@ -43,7 +78,28 @@ impl Prover for LocalProver {
         elf: &[u8],
         opts: &ProverOpts,
     ) -> Result<ProveInfo> {
-        get_prover_server(opts)?.prove_with_ctx(env, ctx, elf)
+        let Some(cache) = &self.cache else {
+            return get_prover_server(opts)?.prove_with_ctx(env, ctx, elf);
+        };
+        let Some(key) = self.cache_key(&env, elf, opts)? else {
+            tracing::debug!("receipt cache: request is not cacheable");
+            return get_prover_server(opts)?.prove_with_ctx(env, ctx, elf);
+        };
+
+        if let Some(info) = cache.get(&key)? {
+            match info.receipt.verify_integrity_with_context(ctx) {
+                Ok(()) => {
+                    tracing::debug!("receipt cache hit: {}", key.digest());
+                    return Ok(info);
+                }
+                Err(err) => tracing::warn!("ignoring cached receipt: {err}"),
+            }
+        }
+
+        tracing::debug!("receipt cache miss: {}", key.digest());
+        let info = get_prover_server(opts)?.prove_with_ctx(env, ctx, elf)?;
+        cache.put(&key, &info)?;
+        Ok(info)
     }
 
     fn get_name(&self) -> String {
@@ -57,19 +113,6 @@ impl Prover for LocalProver {
 
 impl Executor for LocalProver {
     fn execute(&self, env: ExecutorEnv<'_>, elf: &[u8]) -> Result<SessionInfo> {
-        let mut exec = ExecutorImpl::from_elf(env, elf)?;
-        let mut segments = Vec::new();
-        let session = exec.run_with_callback(|segment| {
-            segments.push(SegmentInfo {
-                po2: segment.inner.po2 as u32,
-                cycles: segment.inner.insn_cycles as u32,
-            });
-            Ok(Box::new(NullSegmentRef))
-        })?;
-        Ok(SessionInfo {
-            segments,
-            journal: session.journal.unwrap_or_default(),
-            exit_code: session.exit_code,
-        })
+        LocalExecutor::new(&self.name).execute(env, elf)
     }
 }
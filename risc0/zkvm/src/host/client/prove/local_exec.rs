@@ -0,0 +1,62 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::Result;
+
+use super::Executor;
+use crate::{
+    host::server::session::NullSegmentRef, ExecutorEnv, ExecutorImpl, SegmentInfo, SessionInfo,
+};
+
+/// An [Executor] implementation that executes guests in-process with [ExecutorImpl].
+///
+/// Unlike `LocalProver`, which requires the `prove` feature, this only requires the `executor`
+/// feature.
+#[derive(Clone, Debug)]
+pub struct LocalExecutor {
+    name: String,
+}
+
+impl LocalExecutor {
+    /// Construct a [LocalExecutor].
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+
+    /// Return the name of this [LocalExecutor].
+    pub fn get_name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl Executor for LocalExecutor {
+    fn execute(&self, env: ExecutorEnv<'_>, elf: &[u8]) -> Result<SessionInfo> {
+        let mut exec = ExecutorImpl::from_elf(env, elf)?;
+        let mut segments = Vec::new();
+        let session = exec.run_with_callback(|segment| {
+            segments.push(SegmentInfo {
+                po2: segment.inner.po2 as u32,
+                cycles: segment.inner.insn_cycles as u32,
+            });
+            Ok(Box::new(NullSegmentRef))
+        })?;
+        Ok(SessionInfo {
+            segments,
+            journal: session.journal.unwrap_or_default(),
+            exit_code: session.exit_code,
+        })
+    }
+}
@@ -13,9 +13,14 @@
 // limitations under the License.
 
 pub(crate) mod bonsai;
+pub(crate) mod cache;
 pub(crate) mod external;
 #[cfg(feature = "prove")]
 pub(crate) mod local;
+#[cfg(feature = "executor")]
+pub(crate) mod local_exec;
+#[cfg(all(feature = "executor", not(feature = "prove")))]
+pub(crate) mod prove_feature;
 
 use std::{path::PathBuf, rc::Rc};
 
@@ -27,6 +32,8 @@ use risc0_circuit_recursion::control_id::ALLOWED_CONTROL_IDS;
 use risc0_circuit_rv32im::control_id::SHA256_CONTROL_IDS;
 use risc0_zkp::core::digest::Digest;
 
+#[cfg(feature = "prove")]
+use self::cache::{FileReceiptCache, UnavailableReceiptCache};
 use self::{bonsai::BonsaiProver, external::ExternalProver};
 use crate::{
     get_version, host::prove_info::ProveInfo, is_dev_mode, ExecutorEnv, Receipt, SessionInfo,
@@ -163,6 +170,12 @@ pub struct ProverOpts {
     /// programs that are allowed to run and is a key field in the
     /// [SuccinctReceiptVerifierParameters][crate::SuccinctReceiptVerifierParameters].
     pub control_ids: Vec<Digest>,
+    /// Name of the prover backend to use for proving segments, as registered with
+    /// `risc0_circuit_rv32im::prove::register_backend`.
+    ///
+    /// When not set, the built-in backend selected by the enabled features is used.
+    #[serde(default)]
+    pub backend: Option<String>,
 }
 
 /// An enumeration of receipt kinds that can be requested to be generated.
@@ -196,6 +209,7 @@ impl Default for ProverOpts {
             prove_guest_errors: false,
             receipt_kind: ReceiptKind::Composite,
             control_ids: ALLOWED_CONTROL_IDS.to_vec(),
+            backend: None,
         }
     }
 }
@@ -209,6 +223,7 @@ impl ProverOpts {
             prove_guest_errors: false,
             receipt_kind: ReceiptKind::Composite,
             control_ids: SHA256_CONTROL_IDS.to_vec(),
+            backend: None,
         }
     }
 
@@ -220,6 +235,7 @@ impl ProverOpts {
             prove_guest_errors: false,
             receipt_kind: ReceiptKind::Composite,
             control_ids: ALLOWED_CONTROL_IDS.to_vec(),
+            backend: None,
         }
     }
 
@@ -231,6 +247,7 @@ impl ProverOpts {
             prove_guest_errors: false,
             receipt_kind: ReceiptKind::Succinct,
             control_ids: ALLOWED_CONTROL_IDS.to_vec(),
+            backend: None,
         }
     }
 
@@ -244,6 +261,7 @@ impl ProverOpts {
             prove_guest_errors: false,
             receipt_kind: ReceiptKind::Groth16,
             control_ids: ALLOWED_CONTROL_IDS.to_vec(),
+            backend: None,
         }
     }
 
@@ -279,6 +297,14 @@ impl ProverOpts {
         }
     }
 
+    /// Return [ProverOpts] that prove segments with the named prover backend.
+    pub fn with_backend(self, backend: impl Into<String>) -> Self {
+        Self {
+            backend: Some(backend.into()),
+            ..self
+        }
+    }
+
     #[cfg(feature = "prove")]
     pub(crate) fn hash_suite(
         &self,
@@ -304,6 +330,11 @@ impl ProverOpts {
 ///   variables are set unless `RISC0_DEV_MODE` is enabled.
 /// * LocalProver if the `prove` feature flag is enabled.
 /// * [ExternalProver] otherwise.
+///
+/// When a LocalProver is selected and `RISC0_RECEIPT_CACHE` is set to a directory,
+/// receipts are cached in that directory using a [FileReceiptCache][crate::FileReceiptCache].
+/// If the directory cannot be used as a cache, proving with the returned prover fails with the
+/// reason.
 pub fn default_prover() -> Rc<dyn Prover> {
     let explicit = std::env::var("RISC0_PROVER").unwrap_or_default();
     if !explicit.is_empty() {
@@ -311,7 +342,7 @@ pub fn default_prover() -> Rc<dyn Prover> {
             "bonsai" => Rc::new(BonsaiProver::new("bonsai")),
             "ipc" => Rc::new(ExternalProver::new("ipc", get_r0vm_path().unwrap())),
             #[cfg(feature = "prove")]
-            "local" => Rc::new(self::local::LocalProver::new("local")),
+            "local" => Rc::new(local_prover()),
             _ => unimplemented!("Unsupported prover: {explicit}"),
         };
     }
@@ -325,41 +356,57 @@ pub fn default_prover() -> Rc<dyn Prover> {
 
     if cfg!(feature = "prove") {
         #[cfg(feature = "prove")]
-        return Rc::new(self::local::LocalProver::new("local"));
+        return Rc::new(local_prover());
     }
 
     Rc::new(ExternalProver::new("ipc", get_r0vm_path().unwrap()))
 }
 
+/// Construct a LocalProver, backed by a [FileReceiptCache][crate::FileReceiptCache] if the
+/// `RISC0_RECEIPT_CACHE` environment variable is set to a directory path.
+#[cfg(feature = "prove")]
+fn local_prover() -> self::local::LocalProver {
+    let prover = self::local::LocalProver::new("local");
+    match std::env::var("RISC0_RECEIPT_CACHE") {
+        Ok(dir) if !dir.is_empty() => match FileReceiptCache::new(&dir) {
+            Ok(cache) => prover.with_cache(cache),
+            Err(err) => prover.with_cache(UnavailableReceiptCache::new(
+                err.context(format!("RISC0_RECEIPT_CACHE={dir}")),
+            )),
+        },
+        _ => prover,
+    }
+}
+
 /// Return a default [Executor] based on environment variables and feature
 /// flags.
 ///
 /// The `RISC0_EXECUTOR` environment variable, if specified, will select the
 /// following [Executor] implementation:
-/// * `local`: LocalProver to execute locally in-process. Note: this is
-///   only available when the `prove` feature is enabled.
+/// * `local`: LocalExecutor to execute locally in-process. Note: this is
+///   only available when the `executor` feature is enabled.
 /// * `ipc`: [ExternalProver] to execute using an `r0vm` sub-process. Note:
 ///   `r0vm` must be installed. To specify the path to `r0vm`, use
 ///   `RISC0_SERVER_PATH`.
 ///
 /// If `RISC0_EXECUTOR` is not specified, the following rules are used to select
 /// an [Executor]:
-/// * LocalProver if the `prove` feature flag is enabled.
+/// * LocalExecutor if the `executor` feature flag is enabled.
 /// * [ExternalProver] otherwise.
 pub fn default_executor() -> Rc<dyn Executor> {
     let explicit = std::env::var("RISC0_EXECUTOR").unwrap_or_default();
     if !explicit.is_empty() {
         return match explicit.to_lowercase().as_str() {
             "ipc" => Rc::new(ExternalProver::new("ipc", get_r0vm_path().unwrap())),
-            #[cfg(feature = "prove")]
-            "local" => Rc::new(self::local::LocalProver::new("local")),
+            #[cfg(feature = "executor")]
+            "local" => Rc::new(self::local_exec::LocalExecutor::new("local")),
             _ => unimplemented!("Unsupported executor: {explicit}"),
         };
     }
 
-    if cfg!(feature = "prove") {
-        #[cfg(feature = "prove")]
-        return Rc::new(self::local::LocalProver::new("local"));
+    if cfg!(feature = "executor") {
+        #[cfg(feature = "executor")]
+        return Rc::new(self::local_exec::LocalExecutor::new("local"));
     }
 
     Rc::new(ExternalProver::new("ipc", get_r0vm_path().unwrap()))
@@ -0,0 +1,29 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Stand-ins for the local proving APIs, used when the `executor` feature is enabled without
+//! `prove`, so that using them names the missing feature rather than failing with an
+//! unresolved import.
+
+use std::convert::Infallible;
+
+/// Proving in-process requires the `prove` feature of risc0-zkvm.
+///
+/// This stand-in cannot be constructed; using it warns that the feature is missing.
+#[deprecated(
+    note = "LocalProver requires the `prove` feature of risc0-zkvm. Enable it, or use \
+            `default_prover()` to prove with Bonsai or an `r0vm` sub-process, and \
+            `LocalExecutor` to execute in-process."
+)]
+pub struct LocalProver(Infallible);
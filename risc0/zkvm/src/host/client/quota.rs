@@ -0,0 +1,280 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Per-tenant resource quotas shared across many [ExecutorEnv][crate::ExecutorEnv]s.
+
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{bail, Result};
+
+/// Limits applied to the aggregate resource usage of a single tenant.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TenantQuota {
+    /// Maximum number of user cycles, summed over all sessions run by the tenant.
+    pub max_cycles: Option<u64>,
+
+    /// Maximum number of bytes of guest memory, summed over all sessions of the tenant that are
+    /// executing at the same time. The memory of a session is the size of the pages of its memory
+    /// image, which grows as the guest writes to pages it has not touched before.
+    pub max_memory: Option<u64>,
+}
+
+/// Resources currently accounted to a single tenant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TenantUsage {
+    /// Number of user cycles used by completed sessions.
+    pub cycles: u64,
+
+    /// Number of bytes of guest memory held by sessions that are currently executing.
+    pub memory: u64,
+}
+
+#[derive(Default)]
+struct Tenant {
+    quota: TenantQuota,
+    usage: TenantUsage,
+}
+
+/// A shared handle used to enforce per-tenant quotas.
+///
+/// A [QuotaManager] is cheap to clone; all clones refer to the same accounting state. An env is
+/// associated with a tenant using [ExecutorEnvBuilder::tenant][crate::ExecutorEnvBuilder::tenant].
+/// Quotas are checked when a session starts: the session limit is lowered to the number of cycles
+/// remaining for the tenant, and the session is rejected if its memory image does not fit in the
+/// tenant's remaining memory quota. The memory image is measured again periodically during
+/// execution, and the session fails once it no longer fits. The cycles executed by a session are
+/// charged when it ends, whether it completes, reaches the session limit, or fails.
+///
+/// # Example
+///
+/// ```
+/// use risc0_zkvm::{ExecutorEnv, QuotaManager, TenantQuota};
+///
+/// let quotas = QuotaManager::new();
+/// quotas.set_quota(
+///     "acme",
+///     TenantQuota {
+///         max_cycles: Some(1 << 30),
+///         max_memory: None,
+///     },
+/// );
+///
+/// let env = ExecutorEnv::builder()
+///     .tenant("acme", &quotas)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct QuotaManager {
+    tenants: Arc<Mutex<HashMap<String, Tenant>>>,
+}
+
+impl QuotaManager {
+    /// Construct a [QuotaManager] with no tenants.
+    ///
+    /// Tenants without a quota are not limited, but their usage is still tracked.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the quota for the given tenant, replacing any previous quota.
+    pub fn set_quota(&self, tenant: &str, quota: TenantQuota) {
+        let mut tenants = self.tenants.lock().unwrap();
+        tenants.entry(tenant.to_string()).or_default().quota = quota;
+    }
+
+    /// Return the resources currently accounted to the given tenant.
+    pub fn usage(&self, tenant: &str) -> TenantUsage {
+        let tenants = self.tenants.lock().unwrap();
+        tenants
+            .get(tenant)
+            .map(|tenant| tenant.usage)
+            .unwrap_or_default()
+    }
+
+    /// Reset the cycles used by the given tenant, e.g. at the start of a new billing period.
+    pub fn reset_cycles(&self, tenant: &str) {
+        let mut tenants = self.tenants.lock().unwrap();
+        if let Some(tenant) = tenants.get_mut(tenant) {
+            tenant.usage.cycles = 0;
+        }
+    }
+
+    /// Reserve `memory` bytes for a session run by `tenant`.
+    ///
+    /// The memory is released when the returned [QuotaReservation] is dropped.
+    pub(crate) fn reserve(&self, tenant: &str, memory: u64) -> Result<QuotaReservation> {
+        let mut tenants = self.tenants.lock().unwrap();
+        let entry = tenants.entry(tenant.to_string()).or_default();
+
+        let cycle_limit = match entry.quota.max_cycles {
+            Some(max_cycles) if entry.usage.cycles >= max_cycles => {
+                bail!("Cycle quota exhausted for tenant {tenant:?}: {max_cycles} cycles")
+            }
+            Some(max_cycles) => Some(max_cycles - entry.usage.cycles),
+            None => None,
+        };
+
+        entry.grow_memory(tenant, 0, memory)?;
+
+        Ok(QuotaReservation {
+            manager: self.clone(),
+            tenant: tenant.to_string(),
+            memory: Cell::new(memory),
+            cycle_limit,
+        })
+    }
+}
+
+impl Tenant {
+    /// Account for a session growing from `held` to `memory` bytes.
+    fn grow_memory(&mut self, name: &str, held: u64, memory: u64) -> Result<()> {
+        let added = memory.saturating_sub(held);
+        if let Some(max_memory) = self.quota.max_memory {
+            if self.usage.memory + added > max_memory {
+                bail!(
+                    "Memory quota exceeded for tenant {name:?}: {} + {added} > {max_memory} bytes",
+                    self.usage.memory
+                );
+            }
+        }
+        self.usage.memory += added;
+        Ok(())
+    }
+}
+
+/// Resources held by a running session on behalf of a tenant.
+pub(crate) struct QuotaReservation {
+    manager: QuotaManager,
+    tenant: String,
+    memory: Cell<u64>,
+    /// The number of cycles remaining in the tenant's quota when the session started.
+    pub(crate) cycle_limit: Option<u64>,
+}
+
+impl QuotaReservation {
+    /// Charge the cycles used by a session to the tenant.
+    pub(crate) fn charge_cycles(&self, cycles: u64) {
+        let mut tenants = self.manager.tenants.lock().unwrap();
+        let tenant = tenants.entry(self.tenant.clone()).or_default();
+        tenant.usage.cycles = tenant.usage.cycles.saturating_add(cycles);
+    }
+
+    /// Grow the memory held by the session to `memory` bytes, if it holds less.
+    pub(crate) fn grow_memory(&self, memory: u64) -> Result<()> {
+        let held = self.memory.get();
+        if memory <= held {
+            return Ok(());
+        }
+        let mut tenants = self.manager.tenants.lock().unwrap();
+        let tenant = tenants.entry(self.tenant.clone()).or_default();
+        tenant.grow_memory(&self.tenant, held, memory)?;
+        self.memory.set(memory);
+        Ok(())
+    }
+}
+
+impl Drop for QuotaReservation {
+    fn drop(&mut self) {
+        let mut tenants = self.manager.tenants.lock().unwrap();
+        if let Some(tenant) = tenants.get_mut(&self.tenant) {
+            tenant.usage.memory = tenant.usage.memory.saturating_sub(self.memory.get());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QuotaManager, TenantQuota, TenantUsage};
+
+    #[test]
+    fn cycles_are_aggregated() {
+        let quotas = QuotaManager::new();
+        quotas.set_quota(
+            "a",
+            TenantQuota {
+                max_cycles: Some(100),
+                max_memory: None,
+            },
+        );
+
+        let reservation = quotas.reserve("a", 0).unwrap();
+        assert_eq!(reservation.cycle_limit, Some(100));
+        reservation.charge_cycles(60);
+        drop(reservation);
+
+        let reservation = quotas.reserve("a", 0).unwrap();
+        assert_eq!(reservation.cycle_limit, Some(40));
+        reservation.charge_cycles(40);
+        drop(reservation);
+
+        assert!(quotas.reserve("a", 0).is_err());
+        assert!(quotas.reserve("b", 0).unwrap().cycle_limit.is_none());
+
+        quotas.reset_cycles("a");
+        assert_eq!(quotas.reserve("a", 0).unwrap().cycle_limit, Some(100));
+    }
+
+    #[test]
+    fn memory_is_released() {
+        let quotas = QuotaManager::new();
+        quotas.set_quota(
+            "a",
+            TenantQuota {
+                max_cycles: None,
+                max_memory: Some(1024),
+            },
+        );
+
+        let first = quotas.reserve("a", 768).unwrap();
+        assert!(quotas.reserve("a", 512).is_err());
+        assert_eq!(
+            quotas.usage("a"),
+            TenantUsage {
+                cycles: 0,
+                memory: 768
+            }
+        );
+
+        drop(first);
+        assert_eq!(quotas.usage("a").memory, 0);
+        quotas.reserve("a", 512).unwrap();
+    }
+
+    #[test]
+    fn memory_grows() {
+        let quotas = QuotaManager::new();
+        quotas.set_quota(
+            "a",
+            TenantQuota {
+                max_cycles: None,
+                max_memory: Some(1024),
+            },
+        );
+
+        let reservation = quotas.reserve("a", 256).unwrap();
+        reservation.grow_memory(768).unwrap();
+        reservation.grow_memory(512).unwrap();
+        assert_eq!(quotas.usage("a").memory, 768);
+        assert!(reservation.grow_memory(2048).is_err());
+        assert_eq!(quotas.usage("a").memory, 768);
+
+        drop(reservation);
+        assert_eq!(quotas.usage("a").memory, 0);
+    }
+}
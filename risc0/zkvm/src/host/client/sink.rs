@@ -0,0 +1,122 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    cell::{Cell, RefCell},
+    io::Write,
+    rc::Rc,
+};
+
+/// A built-in destination for the guest's standard output or standard error.
+///
+/// See [ExecutorEnvBuilder::stdout_sink][crate::ExecutorEnvBuilder::stdout_sink].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sink {
+    /// Discard all output.
+    Null,
+
+    /// Discard all output, but count the number of bytes written.
+    ///
+    /// The count is reported in the [SessionStats][crate::SessionStats] of the session.
+    Counting,
+}
+
+/// A writer that discards its input and counts the number of bytes written.
+#[derive(Clone, Default)]
+pub(crate) struct ByteCounter(Rc<Cell<u64>>);
+
+impl ByteCounter {
+    /// Return the number of bytes written since the last call, and reset the count.
+    pub(crate) fn take(&self) -> u64 {
+        self.0.take()
+    }
+}
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.set(self.0.get() + buf.len() as u64);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A writer that copies everything written to it to each of a list of sinks, in order.
+///
+/// See [ExecutorEnvBuilder::journal_tee][crate::ExecutorEnvBuilder::journal_tee].
+#[derive(Clone, Default)]
+pub struct Tee<'a> {
+    sinks: Vec<Rc<RefCell<dyn Write + 'a>>>,
+}
+
+impl<'a> Tee<'a> {
+    /// Construct a [Tee] with no sinks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a sink, such as a file to stream to.
+    pub fn with(self, writer: impl Write + 'a) -> Self {
+        self.with_shared(Rc::new(RefCell::new(writer)))
+    }
+
+    /// Add a sink that remains accessible to the caller, such as a buffer capturing the output
+    /// in memory.
+    pub fn with_shared<W: Write + 'a>(mut self, writer: Rc<RefCell<W>>) -> Self {
+        self.sinks.push(writer);
+        self
+    }
+
+    /// Add a sink that passes each write to `callback`.
+    pub fn with_callback(self, callback: impl FnMut(&[u8]) + 'a) -> Self {
+        self.with(CallbackWriter(callback))
+    }
+
+    /// Add the sinks of `other` after the sinks of this [Tee].
+    pub(crate) fn chain(mut self, other: &Tee<'a>) -> Self {
+        self.sinks.extend(other.sinks.iter().cloned());
+        self
+    }
+}
+
+impl Write for Tee<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for sink in &self.sinks {
+            sink.borrow_mut().write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        for sink in &self.sinks {
+            sink.borrow_mut().flush()?;
+        }
+        Ok(())
+    }
+}
+
+struct CallbackWriter<F>(F);
+
+impl<F: FnMut(&[u8])> Write for CallbackWriter<F> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        (self.0)(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
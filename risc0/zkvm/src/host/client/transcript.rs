@@ -0,0 +1,248 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transcripts of the syscalls answered by the host, for auditing what the host fed the guest.
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, ensure, Context as _, Result};
+use risc0_binfmt::{tagged_iter, tagged_struct, Digestible};
+use risc0_zkp::core::digest::Digest;
+use serde::{Deserialize, Serialize};
+
+use crate::sha::{Impl, Sha256};
+
+/// A single syscall made by the guest, along with the host's answer.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranscriptEntry {
+    /// The name of the syscall, e.g. `risc0_zkvm_platform::syscall::nr::SYS_READ`.
+    pub name: String,
+
+    /// Digest of the arguments passed by the guest: the number of words the guest asked to
+    /// receive, followed by registers `a3` through `a7`.
+    pub inputs: Digest,
+
+    /// Digest of the answer returned by the host: registers `a0` and `a1`, followed by the words
+    /// sent to the guest.
+    pub outputs: Digest,
+
+    /// The cycle at which the guest made the syscall.
+    pub cycle: u64,
+
+    /// The words sent to the guest.
+    pub to_guest: Vec<u32>,
+
+    /// The values returned to the guest in registers `a0` and `a1`.
+    pub regs: (u32, u32),
+}
+
+impl TranscriptEntry {
+    pub(crate) fn new(
+        name: &str,
+        inputs: Digest,
+        cycle: u64,
+        to_guest: &[u32],
+        regs: (u32, u32),
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            inputs,
+            outputs: outputs_digest::<Impl>(to_guest, regs),
+            cycle,
+            to_guest: to_guest.to_vec(),
+            regs,
+        }
+    }
+
+    /// Check that [TranscriptEntry::outputs] matches the recorded answer.
+    pub fn verify(&self) -> Result<()> {
+        ensure!(
+            self.outputs == outputs_digest::<Impl>(&self.to_guest, self.regs),
+            "Output digest does not match the recorded answer to {}",
+            self.name
+        );
+        Ok(())
+    }
+
+    /// Check that this entry answers a syscall with the given name and arguments.
+    pub(crate) fn check(&self, name: &str, inputs: &Digest, to_guest_len: usize) -> Result<()> {
+        if self.name != name || self.inputs != *inputs || self.to_guest.len() != to_guest_len {
+            bail!(
+                "Guest diverged from syscall transcript at cycle {}: expected {}, got {name}",
+                self.cycle,
+                self.name
+            );
+        }
+        self.verify()
+    }
+}
+
+impl Digestible for TranscriptEntry {
+    /// Hash the syscall and the answer recorded in the entry.
+    ///
+    /// The answer is hashed from [TranscriptEntry::to_guest] and [TranscriptEntry::regs], rather
+    /// than taken from [TranscriptEntry::outputs], so that the digest commits to the data the
+    /// guest would be given on replay.
+    fn digest<S: Sha256>(&self) -> Digest {
+        let name = *S::hash_bytes(self.name.as_bytes());
+        let outputs = outputs_digest::<S>(&self.to_guest, self.regs);
+        tagged_struct::<S>(
+            "risc0.TranscriptEntry",
+            &[name, self.inputs, outputs],
+            &[self.cycle as u32, (self.cycle >> 32) as u32],
+        )
+    }
+}
+
+/// An ordered list of the syscalls answered by the host during a single run of the executor.
+///
+/// Record a transcript with
+/// [ExecutorEnvBuilder::record_syscall_transcript][crate::ExecutorEnvBuilder::record_syscall_transcript]
+/// and rerun the guest against it with
+/// [ExecutorEnvBuilder::replay_transcript][crate::ExecutorEnvBuilder::replay_transcript].
+///
+/// The [digest][Digestible::digest] of a transcript is computed over its entries, so a host can
+/// publish it alongside a receipt and an auditor can check a transcript against it before
+/// replaying it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyscallTranscript {
+    /// The syscalls, in the order the guest made them.
+    pub entries: Vec<TranscriptEntry>,
+}
+
+impl SyscallTranscript {
+    /// Check that the output digest of every entry matches its recorded answer.
+    pub fn verify(&self) -> Result<()> {
+        self.entries.iter().try_for_each(TranscriptEntry::verify)
+    }
+
+    /// Check that the transcript has the given [digest][Digestible::digest].
+    pub fn verify_digest(&self, expected: &Digest) -> Result<()> {
+        let digest = self.digest::<Impl>();
+        ensure!(
+            digest == *expected,
+            "Syscall transcript digest {digest} does not match the expected digest {expected}"
+        );
+        Ok(())
+    }
+
+    /// Write the transcript to the file at `path`, e.g. to replay the execution on another
+    /// machine.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, bincode::serialize(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Read a transcript written by [SyscallTranscript::save].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents =
+            fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        bincode::deserialize(&contents)
+            .with_context(|| format!("failed to decode {}", path.display()))
+    }
+}
+
+impl Digestible for SyscallTranscript {
+    /// Hash the entries of the transcript, in order.
+    fn digest<S: Sha256>(&self) -> Digest {
+        tagged_iter::<S>(
+            "risc0.SyscallTranscript",
+            self.entries.iter().map(|entry| entry.digest::<S>()),
+        )
+    }
+}
+
+pub(crate) fn inputs_digest(to_guest_len: usize, args: &[u32]) -> Digest {
+    let mut words = vec![to_guest_len as u32];
+    words.extend_from_slice(args);
+    *Impl::hash_words(&words)
+}
+
+fn outputs_digest<S: Sha256>(to_guest: &[u32], regs: (u32, u32)) -> Digest {
+    let mut words = vec![regs.0, regs.1];
+    words.extend_from_slice(to_guest);
+    *S::hash_words(&words)
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_binfmt::Digestible;
+
+    use super::{inputs_digest, SyscallTranscript, TranscriptEntry};
+    use crate::sha::Impl;
+
+    #[test]
+    fn check_entry() {
+        let inputs = inputs_digest(2, &[1, 2, 3, 4, 5]);
+        let mut entry = TranscriptEntry::new(
+            "risc0_zkvm_platform::syscall::nr::SYS_READ",
+            inputs,
+            10,
+            &[7, 8],
+            (8, 0),
+        );
+        entry
+            .check("risc0_zkvm_platform::syscall::nr::SYS_READ", &inputs, 2)
+            .unwrap();
+        assert!(entry
+            .check("risc0_zkvm_platform::syscall::nr::SYS_RANDOM", &inputs, 2)
+            .is_err());
+        assert!(entry
+            .check(
+                "risc0_zkvm_platform::syscall::nr::SYS_READ",
+                &inputs_digest(2, &[1, 2, 3, 4, 6]),
+                2
+            )
+            .is_err());
+
+        entry.to_guest[0] = 9;
+        let transcript = SyscallTranscript {
+            entries: vec![entry],
+        };
+        assert!(transcript.verify().is_err());
+    }
+
+    #[test]
+    fn transcript_digest() {
+        let entry = |cycle, to_guest: &[u32]| {
+            TranscriptEntry::new(
+                "risc0_zkvm_platform::syscall::nr::SYS_READ",
+                inputs_digest(to_guest.len(), &[0, 0, 0, 0, 0]),
+                cycle,
+                to_guest,
+                (to_guest.len() as u32, 0),
+            )
+        };
+        let transcript = SyscallTranscript {
+            entries: vec![entry(10, &[1]), entry(20, &[2])],
+        };
+        let digest = transcript.digest::<Impl>();
+        transcript.verify_digest(&digest).unwrap();
+
+        // The digest commits to the order and number of the entries.
+        let mut reordered = transcript.clone();
+        reordered.entries.reverse();
+        assert!(reordered.verify_digest(&digest).is_err());
+        let mut truncated = transcript.clone();
+        truncated.entries.pop();
+        assert!(truncated.verify_digest(&digest).is_err());
+
+        // And to the recorded answers, even when the output digest of an entry is left as is.
+        let mut tampered = transcript;
+        tampered.entries[1].to_guest[0] = 3;
+        assert!(tampered.verify_digest(&digest).is_err());
+    }
+}
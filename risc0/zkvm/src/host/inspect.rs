@@ -0,0 +1,321 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Human-readable summaries of serialized receipts, segments, and sessions.
+//!
+//! This backs the `r0vm inspect` command, and can be used to debug artifacts written by a host
+//! without writing code to deserialize them.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write as _},
+    fs,
+    path::Path,
+};
+
+use anyhow::{bail, Context as _, Result};
+
+use crate::{
+    sha::Digestible, InnerReceipt, MaybePruned, Output, Receipt, ReceiptClaim, Segment,
+    VerifierContext,
+};
+
+/// The number of journal bytes shown in a summary.
+const JOURNAL_PREVIEW_LEN: usize = 64;
+
+/// A serialized artifact produced by the zkVM.
+#[non_exhaustive]
+pub enum Artifact {
+    /// A [Receipt], encoded with bincode or as JSON.
+    Receipt(Box<Receipt>),
+
+    /// A [Segment], encoded with bincode as written by a [FileSegmentRef][crate::FileSegmentRef].
+    Segment(Box<Segment>),
+
+    /// The segments of a session, ordered by index.
+    Session(Vec<Segment>),
+}
+
+impl Artifact {
+    /// Load the artifact at `path`.
+    ///
+    /// A file is read as a receipt or a segment, whichever it decodes as. A directory is read as
+    /// a session: the segments written to the
+    /// [segment path][crate::ExecutorEnvBuilder::segment_path] of an executor.
+    pub fn load(path: &Path) -> Result<Self> {
+        if path.is_dir() {
+            return load_session(path);
+        }
+        let contents = fs::read(path).with_context(|| format!("failed to read {path:?}"))?;
+        if contents.first() == Some(&b'{') {
+            let json = std::str::from_utf8(&contents).context("invalid JSON receipt")?;
+            return Ok(Self::Receipt(Box::new(Receipt::from_json(json)?)));
+        }
+        if let Ok(receipt) = bincode::deserialize::<Receipt>(&contents) {
+            return Ok(Self::Receipt(Box::new(receipt)));
+        }
+        if let Ok(segment) = bincode::deserialize::<Segment>(&contents) {
+            return Ok(Self::Segment(Box::new(segment)));
+        }
+        bail!("{path:?} is not a receipt or a segment")
+    }
+}
+
+fn load_session(dir: &Path) -> Result<Artifact> {
+    let mut segments = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "bincode") {
+            let contents = fs::read(&path)?;
+            let segment: Segment = bincode::deserialize(&contents)
+                .with_context(|| format!("{path:?} is not a segment"))?;
+            segments.push(segment);
+        }
+    }
+    if segments.is_empty() {
+        bail!("{dir:?} does not contain any segments");
+    }
+    segments.sort_by_key(|segment| segment.index);
+    Ok(Artifact::Session(segments))
+}
+
+impl fmt::Display for Artifact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Receipt(receipt) => f.write_str(&receipt_summary(receipt)),
+            Self::Segment(segment) => f.write_str(&segment_summary(segment)),
+            Self::Session(segments) => f.write_str(&session_summary(segments)),
+        }
+    }
+}
+
+/// Summarize a receipt, including the result of verifying its integrity.
+pub fn receipt_summary(receipt: &Receipt) -> String {
+    let mut out = String::new();
+    let (kind, seal_len) = match &receipt.inner {
+        InnerReceipt::Composite(inner) => (
+            "composite",
+            inner
+                .segments
+                .iter()
+                .map(|segment| segment.seal.len() * 4)
+                .sum(),
+        ),
+        InnerReceipt::Succinct(inner) => ("succinct", inner.seal.len() * 4),
+        InnerReceipt::Groth16(inner) => ("groth16", inner.seal.len()),
+        InnerReceipt::Fake(_) => ("fake", 0),
+    };
+    writeln!(out, "Receipt:      {kind}").unwrap();
+    writeln!(out, "Seal size:    {seal_len} bytes").unwrap();
+    if let InnerReceipt::Composite(inner) = &receipt.inner {
+        writeln!(out, "Segments:     {}", inner.segments.len()).unwrap();
+        writeln!(out, "Assumptions:  {}", inner.assumption_receipts.len()).unwrap();
+        let mut po2s = BTreeMap::new();
+        for segment in inner.segments.iter() {
+            if let Ok(po2) = segment.po2() {
+                *po2s.entry(po2 as usize).or_default() += 1;
+            }
+        }
+        write_po2_histogram(&mut out, &po2s);
+    }
+    match receipt.claim() {
+        Ok(claim) => write_claim(&mut out, &claim),
+        Err(err) => writeln!(out, "Claim:        unavailable ({err})").unwrap(),
+    }
+    write_journal(&mut out, &receipt.journal.bytes);
+    let integrity = match receipt.verify_integrity_with_context(&VerifierContext::default()) {
+        Ok(()) => "ok".to_string(),
+        Err(err) => format!("FAILED ({err})"),
+    };
+    writeln!(out, "Integrity:    {integrity}").unwrap();
+    out
+}
+
+/// Summarize a segment, including whether its memory image matches its pre-state.
+pub fn segment_summary(segment: &Segment) -> String {
+    let mut out = String::new();
+    let inner = &segment.inner;
+    writeln!(out, "Segment:      {}", segment.index).unwrap();
+    writeln!(out, "Exit code:    {:?}", inner.exit_code).unwrap();
+    writeln!(out, "Po2:          {}", inner.po2).unwrap();
+    writeln!(out, "User cycles:  {}", inner.insn_cycles).unwrap();
+    writeln!(out, "Syscalls:     {}", inner.syscalls.len()).unwrap();
+    writeln!(out, "Pre-state:    {}", inner.pre_state.digest()).unwrap();
+    writeln!(out, "  pc:         0x{:08x}", inner.pre_state.pc).unwrap();
+    writeln!(out, "Post-state:   {}", inner.post_state.digest()).unwrap();
+    writeln!(out, "  pc:         0x{:08x}", inner.post_state.pc).unwrap();
+    writeln!(out, "Input:        {}", inner.input_digest).unwrap();
+    if let Some(output) = &segment.output {
+        write_output(&mut out, output);
+    }
+    let integrity = if inner.partial_image.compute_root_hash() == inner.pre_state.merkle_root {
+        "ok".to_string()
+    } else {
+        "FAILED (memory image does not match the pre-state)".to_string()
+    };
+    writeln!(out, "Integrity:    {integrity}").unwrap();
+    out
+}
+
+/// Summarize the segments of a session.
+pub fn session_summary(segments: &[Segment]) -> String {
+    let mut out = String::new();
+    let (Some(first), Some(last)) = (segments.first(), segments.last()) else {
+        return out;
+    };
+    let user_cycles: usize = segments
+        .iter()
+        .map(|segment| segment.inner.insn_cycles)
+        .sum();
+    let total_cycles: u64 = segments.iter().map(|segment| 1u64 << segment.po2()).sum();
+    writeln!(out, "Session:      {} segments", segments.len()).unwrap();
+    writeln!(out, "Exit code:    {:?}", last.inner.exit_code).unwrap();
+    writeln!(out, "Image ID:     {}", first.inner.pre_state.digest()).unwrap();
+    writeln!(out, "User cycles:  {user_cycles}").unwrap();
+    writeln!(out, "Total cycles: {total_cycles}").unwrap();
+    let mut po2s = BTreeMap::new();
+    for segment in segments {
+        *po2s.entry(segment.po2()).or_default() += 1;
+    }
+    write_po2_histogram(&mut out, &po2s);
+    if let Some(output) = &last.output {
+        write_output(&mut out, output);
+    }
+
+    let mut errors = Vec::new();
+    for (expected, segment) in segments.iter().enumerate() {
+        if segment.index as usize != expected {
+            errors.push(format!("segment {expected} is missing"));
+            break;
+        }
+        if segment.inner.partial_image.compute_root_hash() != segment.inner.pre_state.merkle_root {
+            errors.push(format!(
+                "segment {} memory image does not match its pre-state",
+                segment.index
+            ));
+        }
+    }
+    for pair in segments.windows(2) {
+        if pair[0].inner.post_state.digest() != pair[1].inner.pre_state.digest() {
+            errors.push(format!(
+                "segment {} does not continue from segment {}",
+                pair[1].index, pair[0].index
+            ));
+        }
+    }
+    if errors.is_empty() {
+        writeln!(out, "Integrity:    ok").unwrap();
+    } else {
+        writeln!(out, "Integrity:    FAILED").unwrap();
+        for error in errors {
+            writeln!(out, "  {error}").unwrap();
+        }
+    }
+    out
+}
+
+fn write_claim(out: &mut String, claim: &MaybePruned<ReceiptClaim>) {
+    writeln!(out, "Claim:        {}", claim.digest()).unwrap();
+    let MaybePruned::Value(claim) = claim else {
+        return;
+    };
+    writeln!(out, "Exit code:    {:?}", claim.exit_code).unwrap();
+    writeln!(out, "Image ID:     {}", claim.pre.digest()).unwrap();
+    writeln!(out, "Post-state:   {}", claim.post.digest()).unwrap();
+    writeln!(out, "Input:        {}", claim.input.digest()).unwrap();
+    match &claim.output {
+        MaybePruned::Value(Some(output)) => {
+            writeln!(out, "Output:       {}", output.digest()).unwrap();
+            writeln!(out, "  assumptions: {}", output.assumptions.digest()).unwrap();
+        }
+        MaybePruned::Value(None) => writeln!(out, "Output:       none").unwrap(),
+        MaybePruned::Pruned(digest) => writeln!(out, "Output:       {digest}").unwrap(),
+    }
+}
+
+fn write_output(out: &mut String, output: &Output) {
+    writeln!(out, "Output:       {}", output.digest()).unwrap();
+    match &output.journal {
+        MaybePruned::Value(journal) => write_journal(out, journal),
+        MaybePruned::Pruned(digest) => writeln!(out, "Journal:      {digest}").unwrap(),
+    }
+}
+
+fn write_journal(out: &mut String, journal: &[u8]) {
+    writeln!(
+        out,
+        "Journal:      {} bytes, digest {}",
+        journal.len(),
+        journal.digest()
+    )
+    .unwrap();
+    if journal.is_empty() {
+        return;
+    }
+    let preview = &journal[..journal.len().min(JOURNAL_PREVIEW_LEN)];
+    let ellipsis = if journal.len() > preview.len() {
+        "..."
+    } else {
+        ""
+    };
+    writeln!(out, "  hex:        {}{ellipsis}", hex::encode(preview)).unwrap();
+    if let Ok(text) = std::str::from_utf8(preview) {
+        if text
+            .chars()
+            .all(|c| !c.is_control() || c.is_ascii_whitespace())
+        {
+            writeln!(out, "  text:       {text:?}{ellipsis}").unwrap();
+        }
+    }
+}
+
+fn write_po2_histogram(out: &mut String, po2s: &BTreeMap<usize, usize>) {
+    if po2s.is_empty() {
+        return;
+    }
+    writeln!(out, "Po2 histogram:").unwrap();
+    let max = po2s.values().copied().max().unwrap_or(1);
+    for (po2, count) in po2s {
+        let bar = "#".repeat((count * 40).div_ceil(max));
+        writeln!(out, "  {po2:>2}: {count:>6} {bar}").unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::receipt_summary;
+    use crate::{
+        sha::{Digest, DIGEST_BYTES},
+        FakeReceipt, InnerReceipt, Receipt, ReceiptClaim,
+    };
+
+    #[test]
+    fn fake_receipt_summary() {
+        let image_id = Digest::from([1u8; DIGEST_BYTES]);
+        let journal = b"hello".to_vec();
+        let receipt = Receipt::new(
+            InnerReceipt::Fake(FakeReceipt::new(ReceiptClaim::ok(
+                image_id,
+                journal.clone(),
+            ))),
+            journal,
+        );
+
+        let summary = receipt_summary(&receipt);
+        assert!(summary.contains("Receipt:      fake"));
+        assert!(summary.contains("Exit code:    Halted(0)"));
+        assert!(summary.contains(&format!("Image ID:     {image_id}")));
+        assert!(summary.contains("text:       \"hello\""));
+    }
+}
@@ -14,11 +14,17 @@
 
 #[cfg(any(feature = "client", feature = "prove"))]
 pub(crate) mod api;
+#[cfg(feature = "executor")]
+pub mod bench;
 #[cfg(feature = "client")]
 pub(crate) mod client;
+#[cfg(feature = "prove")]
+pub mod inspect;
+#[cfg(feature = "otel-export")]
+pub mod otel;
 #[cfg(any(feature = "client", feature = "prove"))]
 mod protos;
 pub(crate) mod prove_info;
 pub mod recursion;
-#[cfg(feature = "prove")]
+#[cfg(feature = "executor")]
 pub(crate) mod server;
@@ -0,0 +1,312 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export of `tracing` spans in the OpenTelemetry protocol (OTLP) JSON format.
+//!
+//! The zkVM records the `execute`, `segment`, `prove_session`, `prove_segment`, `lift`, `join`
+//! and `compress` spans of a job, with the [ID][crate::Session::id] of the session as the
+//! `session_id` field. [OtlpJsonLayer] writes each span as it closes as one line of OTLP/JSON,
+//! which can be read by the file receiver of the OpenTelemetry Collector.
+//!
+//! The trace ID of a span is derived from the nearest `session_id` field on the span or its
+//! ancestors, so the spans of one session share a trace, even when its segments are proven by
+//! other processes or machines. A caller may open its own span with a `session_id` field to
+//! group other work, e.g. compressing the receipt of a session, into the same trace.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use tracing_subscriber::prelude::*;
+//!
+//! let layer = risc0_zkvm::otel::otlp_json_layer_from_env().unwrap();
+//! tracing_subscriber::registry().with(layer).init();
+//! ```
+
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::Write,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context as _, Result};
+use serde_json::{json, Value};
+use sha2::{Digest as _, Sha256};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+/// The environment variable naming the file that [otlp_json_layer_from_env] exports spans to.
+pub const OTLP_JSON_EXPORT_ENV: &str = "RISC0_OTLP_JSON_EXPORT";
+
+const SERVICE_NAME: &str = "risc0-zkvm";
+
+/// A [Layer] that writes spans to `W` as lines of OTLP/JSON.
+pub struct OtlpJsonLayer<W> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write> OtlpJsonLayer<W> {
+    /// Construct an [OtlpJsonLayer] that writes to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+}
+
+/// Construct an [OtlpJsonLayer] that appends to the file named by the `RISC0_OTLP_JSON_EXPORT`
+/// environment variable, or `None` if it is not set.
+pub fn otlp_json_layer_from_env() -> Result<Option<OtlpJsonLayer<File>>> {
+    let Ok(path) = std::env::var(OTLP_JSON_EXPORT_ENV) else {
+        return Ok(None);
+    };
+    let file = File::options()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {path} for span export"))?;
+    Ok(Some(OtlpJsonLayer::new(file)))
+}
+
+struct SpanData {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    start: u128,
+    attributes: Vec<(String, Value)>,
+}
+
+impl SpanData {
+    fn session_id(&self) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == "session_id")
+            .and_then(|(_, value)| value["stringValue"].as_str())
+    }
+}
+
+struct AttributeVisitor<'a>(&'a mut Vec<(String, Value)>);
+
+impl AttributeVisitor<'_> {
+    fn record(&mut self, field: &Field, value: Value) {
+        self.0.retain(|(key, _)| key != field.name());
+        self.0.push((field.name().to_string(), value));
+    }
+}
+
+impl Visit for AttributeVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.record(field, json!({ "stringValue": format!("{value:?}") }));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, json!({ "stringValue": value }));
+    }
+
+    // OTLP/JSON encodes 64-bit integers as strings.
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record(field, json!({ "intValue": value.to_string() }));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record(field, json!({ "intValue": value.to_string() }));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.record(field, json!({ "boolValue": value }));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.record(field, json!({ "doubleValue": value }));
+    }
+}
+
+fn unix_nanos() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+}
+
+fn trace_id_of(session_id: &str) -> [u8; 16] {
+    Sha256::digest(session_id.as_bytes())[..16]
+        .try_into()
+        .unwrap()
+}
+
+impl<S, W> Layer<S> for OtlpJsonLayer<W>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: Write + Send + 'static,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let start = unix_nanos();
+        let mut attributes = Vec::new();
+        attrs.record(&mut AttributeVisitor(&mut attributes));
+
+        // Span IDs only need to be unique within a trace, which may span several processes.
+        let mut hasher = Sha256::new();
+        hasher.update(std::process::id().to_le_bytes());
+        hasher.update(id.into_u64().to_le_bytes());
+        hasher.update(start.to_le_bytes());
+        let span_id: [u8; 8] = hasher.finalize()[..8].try_into().unwrap();
+
+        let parent = span.parent().and_then(|parent| {
+            parent
+                .extensions()
+                .get::<SpanData>()
+                .map(|data| (data.trace_id, data.span_id))
+        });
+        let mut data = SpanData {
+            trace_id: [0; 16],
+            span_id,
+            parent_span_id: parent.map(|(_, span_id)| span_id),
+            start,
+            attributes,
+        };
+        data.trace_id = match (data.session_id(), parent) {
+            (Some(session_id), _) => trace_id_of(session_id),
+            (None, Some((trace_id, _))) => trace_id,
+            (None, None) => trace_id_of(&hex::encode(span_id)),
+        };
+        span.extensions_mut().insert(data);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if let Some(data) = span.extensions_mut().get_mut::<SpanData>() {
+            values.record(&mut AttributeVisitor(&mut data.attributes));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let end = unix_nanos();
+        let extensions = span.extensions();
+        let Some(data) = extensions.get::<SpanData>() else {
+            return;
+        };
+
+        let mut otlp_span = json!({
+            "traceId": hex::encode(data.trace_id),
+            "spanId": hex::encode(data.span_id),
+            "name": span.name(),
+            // SPAN_KIND_INTERNAL
+            "kind": 1,
+            "startTimeUnixNano": data.start.to_string(),
+            "endTimeUnixNano": end.to_string(),
+            "attributes": data
+                .attributes
+                .iter()
+                .map(|(key, value)| json!({ "key": key, "value": value }))
+                .collect::<Vec<_>>(),
+        });
+        if let Some(parent_span_id) = data.parent_span_id {
+            otlp_span["parentSpanId"] = hex::encode(parent_span_id).into();
+        }
+        let line = json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [
+                        { "key": "service.name", "value": { "stringValue": SERVICE_NAME } },
+                    ],
+                },
+                "scopeSpans": [{
+                    "scope": { "name": span.metadata().target() },
+                    "spans": [otlp_span],
+                }],
+            }],
+        });
+
+        let mut writer = self.writer.lock().unwrap();
+        if let Err(err) = writeln!(writer, "{line}") {
+            eprintln!("Failed to export span: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::Value;
+    use tracing_subscriber::prelude::*;
+
+    use super::OtlpJsonLayer;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn export(f: impl FnOnce()) -> Vec<Value> {
+        let buffer = Buffer::default();
+        let subscriber = tracing_subscriber::registry().with(OtlpJsonLayer::new(buffer.clone()));
+        tracing::subscriber::with_default(subscriber, f);
+        let bytes = buffer.0.lock().unwrap().clone();
+        String::from_utf8(bytes)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let line: Value = serde_json::from_str(line).unwrap();
+                line["resourceSpans"][0]["scopeSpans"][0]["spans"][0].clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn session_trace() {
+        let exported = export(|| {
+            tracing::info_span!("execute", session_id = "job").in_scope(|| {
+                tracing::info_span!("segment", index = 3u32).in_scope(|| {});
+            });
+        });
+        let remote = export(|| {
+            tracing::info_span!("prove_segment", session_id = "job", po2 = 20).in_scope(|| {});
+        });
+
+        let [segment, execute] = exported.as_slice() else {
+            panic!("expected two spans, got {exported:?}");
+        };
+        assert_eq!(segment["name"], "segment");
+        assert_eq!(segment["parentSpanId"], execute["spanId"]);
+        assert_eq!(segment["traceId"], execute["traceId"]);
+        assert_eq!(remote[0]["traceId"], execute["traceId"]);
+        assert!(execute.get("parentSpanId").is_none());
+        assert_eq!(
+            segment["attributes"][0],
+            serde_json::json!({ "key": "index", "value": { "intValue": "3" } })
+        );
+    }
+}
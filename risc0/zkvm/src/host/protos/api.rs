@@ -300,6 +300,112 @@ pub struct ExecutorEnv {
     pub assumptions: ::prost::alloc::vec::Vec<AssumptionReceipt>,
     #[prost(string, tag = "12")]
     pub segment_path: ::prost::alloc::string::String,
+    /// Record previous register and memory values in trace events.
+    #[prost(bool, tag = "13")]
+    pub trace_prev_values: bool,
+    /// Interpret ecalls as Linux syscalls.
+    #[prost(bool, tag = "14")]
+    pub linux_compat: bool,
+    /// The segment limit po2 of each segment, in order.
+    #[prost(uint32, repeated, tag = "15")]
+    pub segment_po2_schedule: ::prost::alloc::vec::Vec<u32>,
+    /// Count the instructions executed by kind.
+    #[prost(bool, tag = "16")]
+    pub insn_stats: bool,
+    /// The public key that the guest encrypts its journal to.
+    #[prost(bytes = "vec", optional, tag = "17")]
+    pub journal_recipient: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    /// The input digest of the session.
+    #[prost(message, optional, tag = "18")]
+    pub input_digest: ::core::option::Option<super::base::Digest>,
+    /// The ID of the session, used to correlate tracing spans.
+    #[prost(string, optional, tag = "19")]
+    pub session_id: ::core::option::Option<::prost::alloc::string::String>,
+    /// The memory watched for accesses, reported as trace events.
+    #[prost(message, repeated, tag = "20")]
+    pub watchpoints: ::prost::alloc::vec::Vec<Watchpoint>,
+    /// Record paging cycles in trace events.
+    #[prost(bool, tag = "21")]
+    pub trace_paging: bool,
+    /// The quota of each file descriptor, by descriptor.
+    #[prost(map = "uint32, message", tag = "22")]
+    pub fd_quotas: ::std::collections::HashMap<u32, FdQuota>,
+    /// Pause at the end of the segment that brings the session to this many cycles.
+    #[prost(uint64, optional, tag = "23")]
+    pub pause_at_cycle: ::core::option::Option<u64>,
+    /// The time left until the wall clock deadline, in milliseconds.
+    #[prost(uint64, optional, tag = "24")]
+    pub wall_clock_timeout_ms: ::core::option::Option<u64>,
+    #[prost(uint32, optional, tag = "25")]
+    pub stack_size: ::core::option::Option<u32>,
+    #[prost(bool, optional, tag = "26")]
+    pub hugepages: ::core::option::Option<bool>,
+    #[prost(string, tag = "27")]
+    pub page_heatmap_out: ::prost::alloc::string::String,
+    /// Commit the input digest to the journal.
+    #[prost(bool, tag = "28")]
+    pub commit_input: bool,
+    /// Capture the statement of the session.
+    #[prost(bool, tag = "29")]
+    pub capture_statement: bool,
+    /// Record the syscall transcript of the session.
+    #[prost(bool, tag = "30")]
+    pub record_transcript: bool,
+    #[prost(string, tag = "31")]
+    pub record_transcript_out: ::prost::alloc::string::String,
+    /// Run the guest twice and compare the syscall transcripts.
+    #[prost(bool, tag = "32")]
+    pub audit_nondeterminism: bool,
+    /// A SyscallTranscript to replay, encoded with bincode.
+    #[prost(bytes = "vec", optional, tag = "33")]
+    pub replay_transcript: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    #[prost(bool, tag = "34")]
+    pub self_check: bool,
+    #[prost(bool, tag = "35")]
+    pub perf_warnings: bool,
+    #[prost(bool, tag = "36")]
+    pub taint_tracking: bool,
+    #[prost(bool, tag = "37")]
+    pub cycle_audit: bool,
+    #[prost(message, optional, tag = "38")]
+    pub runaway_heuristics: ::core::option::Option<RunawayHeuristics>,
+    #[prost(message, optional, tag = "39")]
+    pub syscall_manifest: ::core::option::Option<SyscallManifest>,
+    /// Hand segments to the prover in memory rather than saving them.
+    #[prost(bool, tag = "40")]
+    pub segments_in_memory: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FdQuota {
+    #[prost(uint64, optional, tag = "1")]
+    pub max_bytes: ::core::option::Option<u64>,
+    #[prost(uint64, optional, tag = "2")]
+    pub max_calls: ::core::option::Option<u64>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RunawayHeuristics {
+    #[prost(uint64, optional, tag = "1")]
+    pub idle_cycles: ::core::option::Option<u64>,
+    #[prost(message, optional, tag = "2")]
+    pub tight_loop: ::core::option::Option<TightLoop>,
+    #[prost(bool, tag = "3")]
+    pub abort: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TightLoop {
+    #[prost(uint64, tag = "1")]
+    pub cycles: u64,
+    #[prost(uint32, tag = "2")]
+    pub span: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SyscallManifest {
+    #[prost(string, repeated, tag = "1")]
+    pub names: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -322,6 +428,16 @@ pub mod assumption_receipt {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Watchpoint {
+    #[prost(uint32, tag = "1")]
+    pub start: u32,
+    #[prost(uint32, tag = "2")]
+    pub end: u32,
+    #[prost(enumeration = "WatchKind", tag = "3")]
+    pub kind: i32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ProverOpts {
     #[prost(string, tag = "1")]
     pub hashfn: ::prost::alloc::string::String,
@@ -331,6 +447,8 @@ pub struct ProverOpts {
     pub receipt_kind: i32,
     #[prost(message, repeated, tag = "4")]
     pub control_ids: ::prost::alloc::vec::Vec<super::base::Digest>,
+    #[prost(string, optional, tag = "5")]
+    pub backend: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -487,7 +605,7 @@ pub mod posix_cmd {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct TraceEvent {
-    #[prost(oneof = "trace_event::Kind", tags = "1, 2, 3")]
+    #[prost(oneof = "trace_event::Kind", tags = "1, 2, 3, 4, 5, 6, 7, 8, 9")]
     pub kind: ::core::option::Option<trace_event::Kind>,
 }
 /// Nested message and enum types in `TraceEvent`.
@@ -521,6 +639,56 @@ pub mod trace_event {
         pub region: ::prost::alloc::vec::Vec<u8>,
     }
     #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Paging {
+        #[prost(uint32, tag = "1")]
+        pub pc: u32,
+        #[prost(uint64, tag = "2")]
+        pub cycles: u64,
+    }
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct MemoryRegionSet {
+        #[prost(uint32, tag = "1")]
+        pub addr: u32,
+        #[prost(bytes = "vec", tag = "2")]
+        pub bytes: ::prost::alloc::vec::Vec<u8>,
+    }
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Watchpoint {
+        #[prost(uint32, tag = "1")]
+        pub pc: u32,
+        #[prost(uint32, tag = "2")]
+        pub addr: u32,
+        #[prost(enumeration = "super::WatchKind", tag = "3")]
+        pub kind: i32,
+    }
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct RegisterPrev {
+        #[prost(uint32, tag = "1")]
+        pub idx: u32,
+        #[prost(uint32, tag = "2")]
+        pub value: u32,
+    }
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct MemoryPrev {
+        #[prost(uint32, tag = "1")]
+        pub addr: u32,
+        #[prost(bytes = "vec", tag = "2")]
+        pub region: ::prost::alloc::vec::Vec<u8>,
+    }
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct MemoryRegionPrev {
+        #[prost(uint32, tag = "1")]
+        pub addr: u32,
+        #[prost(bytes = "vec", tag = "2")]
+        pub bytes: ::prost::alloc::vec::Vec<u8>,
+    }
+    #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Kind {
         #[prost(message, tag = "1")]
@@ -529,6 +697,18 @@ pub mod trace_event {
         RegisterSet(RegisterSet),
         #[prost(message, tag = "3")]
         MemorySet(MemorySet),
+        #[prost(message, tag = "4")]
+        Paging(Paging),
+        #[prost(message, tag = "5")]
+        Watchpoint(Watchpoint),
+        #[prost(message, tag = "6")]
+        MemoryRegionSet(MemoryRegionSet),
+        #[prost(message, tag = "7")]
+        RegisterPrev(RegisterPrev),
+        #[prost(message, tag = "8")]
+        MemoryPrev(MemoryPrev),
+        #[prost(message, tag = "9")]
+        MemoryRegionPrev(MemoryRegionPrev),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -591,6 +771,35 @@ pub struct GenericError {
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
+pub enum WatchKind {
+    Read = 0,
+    Write = 1,
+    Access = 2,
+}
+impl WatchKind {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            WatchKind::Read => "READ",
+            WatchKind::Write => "WRITE",
+            WatchKind::Access => "ACCESS",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "READ" => Some(Self::Read),
+            "WRITE" => Some(Self::Write),
+            "ACCESS" => Some(Self::Access),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
 pub enum ReceiptKind {
     Composite = 0,
     Succinct = 1,
@@ -16,6 +16,30 @@ pub struct SessionStats {
     pub total_cycles: u64,
     #[prost(uint64, tag = "3")]
     pub user_cycles: u64,
+    #[prost(uint64, optional, tag = "4")]
+    pub stdout_bytes: ::core::option::Option<u64>,
+    #[prost(uint64, optional, tag = "5")]
+    pub stderr_bytes: ::core::option::Option<u64>,
+    #[prost(uint64, optional, tag = "6")]
+    pub paging_cycles: ::core::option::Option<u64>,
+    #[prost(uint64, optional, tag = "7")]
+    pub saved_paging_cycles: ::core::option::Option<u64>,
+    /// Executions of each kind of instruction, by mnemonic. Empty if not counted.
+    #[prost(map = "string, uint64", tag = "8")]
+    pub insn_counts: ::std::collections::HashMap<::prost::alloc::string::String, u64>,
+    /// Data transferred through each file descriptor with a quota.
+    #[prost(map = "uint32, message", tag = "9")]
+    pub fd_usage: ::std::collections::HashMap<u32, FdUsage>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FdUsage {
+    #[prost(uint64, tag = "1")]
+    pub bytes: u64,
+    #[prost(uint64, tag = "2")]
+    pub calls: u64,
+    #[prost(bool, tag = "3")]
+    pub exceeded: bool,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
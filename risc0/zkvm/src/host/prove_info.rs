@@ -14,9 +14,14 @@
 
 //! Struct containing information about a prover's execution including the receipt.
 
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
 use crate::Receipt;
 
 /// Information returned by the prover including receipt as well as other information useful for debugging
+#[derive(Serialize, Deserialize)]
 pub struct ProveInfo {
     /// receipt from the computation
     pub receipt: Receipt,
@@ -25,7 +30,10 @@ pub struct ProveInfo {
 }
 
 /// Struct containing information about a prover's cycle count after running the guest program
-#[derive(Debug)]
+///
+/// Fields are added to this struct as the executor reports more about a session. Build it with
+/// [SessionStats::new], or with `..Default::default()`, rather than listing every field.
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct SessionStats {
     /// Count of segments in this proof request
     pub segments: usize,
@@ -33,4 +41,108 @@ pub struct SessionStats {
     pub total_cycles: u64,
     /// User cycles run within guest
     pub user_cycles: u64,
+    /// Bytes written by the guest to stdout, if counted with [Sink::Counting][crate::Sink]
+    #[serde(default)]
+    pub stdout_bytes: Option<u64>,
+    /// Bytes written by the guest to stderr, if counted with [Sink::Counting][crate::Sink]
+    #[serde(default)]
+    pub stderr_bytes: Option<u64>,
+    /// Data transferred through each file descriptor with a quota
+    #[serde(default)]
+    pub fd_usage: BTreeMap<u32, FdUsage>,
+    /// Cycles spent paging memory in and out, if known
+    #[serde(default)]
+    pub paging_cycles: Option<u64>,
+    /// Paging cycles saved by sharing page table pages between accesses in a segment, if known
+    #[serde(default)]
+    pub saved_paging_cycles: Option<u64>,
+    /// Executions of each kind of instruction by mnemonic, if counted with
+    /// [ExecutorEnvBuilder::insn_stats][crate::ExecutorEnvBuilder::insn_stats]
+    #[serde(default)]
+    pub insn_counts: Option<BTreeMap<String, u64>>,
+}
+
+impl SessionStats {
+    /// Construct the stats of a session from its cycle counts, with everything else unknown
+    pub fn new(segments: usize, total_cycles: u64, user_cycles: u64) -> Self {
+        Self {
+            segments,
+            total_cycles,
+            user_cycles,
+            ..Default::default()
+        }
+    }
+}
+
+/// Struct containing the data transferred through a file descriptor with a quota, set with
+/// [ExecutorEnvBuilder::fd_quota][crate::ExecutorEnvBuilder::fd_quota]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FdUsage {
+    /// Number of bytes transferred
+    pub bytes: u64,
+    /// Number of calls made, including calls refused by the quota
+    pub calls: u64,
+    /// Whether a call was refused or shortened by the quota
+    pub exceeded: bool,
+}
+
+/// Struct containing the use of an accelerator over a session, reported by
+/// [Session::accelerator_stats][crate::Session::accelerator_stats]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AcceleratorStats {
+    /// Number of `ecall`s made to the accelerator
+    pub invocations: u64,
+    /// Number of cycles spent in those `ecall`s
+    pub cycles: u64,
+}
+
+/// Struct containing the cycle counts and paging of a single segment
+///
+/// Recorded when the segment is executed, so that a [SegmentRef][crate::SegmentRef] can report
+/// it without resolving the segment.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentStats {
+    /// Index of the segment within its session
+    pub index: u32,
+    /// Power of two of the cycles in the segment
+    pub po2: usize,
+    /// Cycles spent running instructions
+    pub user_cycles: u64,
+    /// Pages paged in, including page table pages
+    pub page_reads: u32,
+    /// Pages paged out, including page table pages
+    pub page_writes: u32,
+    /// Cycles spent paging pages in
+    pub page_read_cycles: u64,
+    /// Cycles spent paging pages out
+    pub page_write_cycles: u64,
+}
+
+impl SegmentStats {
+    /// Total cycles in the segment, including padding
+    pub fn total_cycles(&self) -> u64 {
+        1 << self.po2
+    }
+
+    /// Fraction of the total cycles of the segment spent paging pages in and out
+    pub fn paging_ratio(&self) -> f64 {
+        (self.page_read_cycles + self.page_write_cycles) as f64 / self.total_cycles() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SessionStats;
+
+    #[test]
+    fn session_stats_compat() {
+        // Stats serialized before the optional counts were added still deserialize.
+        let stats: SessionStats =
+            serde_json::from_str(r#"{"segments":2,"total_cycles":2048,"user_cycles":1500}"#)
+                .unwrap();
+        assert_eq!(stats.segments, 2);
+        assert_eq!(stats.stdout_bytes, None);
+        assert_eq!(stats.insn_counts, None);
+        assert!(stats.fd_usage.is_empty());
+    }
 }
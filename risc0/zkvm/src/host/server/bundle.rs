@@ -0,0 +1,238 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A [Session] saved to a directory, along with the receipts of the segments proven so far.
+
+use std::{
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{ensure, Context as _, Result};
+use risc0_binfmt::{MemoryImage, SystemState};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    host::prove_info::SegmentStats, sha::Digest, Assumption, AssumptionReceipt, ExitCode, Segment,
+    SegmentReceipt, SegmentRef, Session, SessionEvents,
+};
+
+const MANIFEST: &str = "session.bincode";
+const SEGMENTS: &str = "segments";
+const RECEIPTS: &str = "receipts";
+
+/// A [Session] saved to a directory, so that it can be proven by another process, and the
+/// proving resumed where it left off if that process dies.
+///
+/// The directory holds the session itself, each of its segments, and the receipt of each segment
+/// proven so far, saved by
+/// [ProverServer::resume_proving][crate::ProverServer::resume_proving] as it goes.
+pub struct SessionBundle {
+    dir: PathBuf,
+    manifest: Manifest,
+    hooks: Vec<Box<dyn SessionEvents>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    id: String,
+    segments: Vec<SegmentStats>,
+    input: Digest,
+    journal: Option<Vec<u8>>,
+    exit_code: ExitCode,
+    post_image: MemoryImage,
+    assumptions: Vec<(Assumption, AssumptionReceipt)>,
+    user_cycles: u64,
+    total_cycles: u64,
+    pre_state: SystemState,
+    post_state: SystemState,
+}
+
+impl SessionBundle {
+    /// Save `session` to the directory at `dir`, creating it if needed.
+    pub fn create(dir: impl AsRef<Path>, session: &Session) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(dir.join(SEGMENTS))?;
+        fs::create_dir_all(dir.join(RECEIPTS))?;
+
+        let mut segments = Vec::new();
+        for (idx, segment_ref) in session.segments.iter().enumerate() {
+            let segment = segment_ref.resolve()?;
+            write_atomic(&segment_path(&dir, idx), &bincode::serialize(&segment)?)?;
+            segments.push(segment.stats());
+        }
+
+        let manifest = Manifest {
+            id: session.id.clone(),
+            segments,
+            input: session.input,
+            journal: session
+                .journal
+                .as_ref()
+                .map(|journal| journal.bytes.clone()),
+            exit_code: session.exit_code,
+            post_image: session.post_image.clone(),
+            assumptions: session.assumptions.clone(),
+            user_cycles: session.user_cycles,
+            total_cycles: session.total_cycles,
+            pre_state: session.pre_state.clone(),
+            post_state: session.post_state.clone(),
+        };
+        // The manifest is written last, so that a bundle that opens has all of its segments.
+        write_atomic(&dir.join(MANIFEST), &bincode::serialize(&manifest)?)?;
+        Ok(Self {
+            dir,
+            manifest,
+            hooks: Vec::new(),
+        })
+    }
+
+    /// Open a bundle saved by [SessionBundle::create].
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let path = dir.join(MANIFEST);
+        let contents =
+            fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        let manifest = bincode::deserialize(&contents)
+            .with_context(|| format!("failed to decode {}", path.display()))?;
+        Ok(Self {
+            dir,
+            manifest,
+            hooks: Vec::new(),
+        })
+    }
+
+    /// The directory holding the bundle.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Add a hook to be called around the proving of each segment that has no saved receipt.
+    ///
+    /// Hooks added to a [Session] are not saved in the bundle, so they must be added again here.
+    pub fn add_hook<E: SessionEvents + 'static>(&mut self, hook: E) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    #[cfg(feature = "prove")]
+    pub(crate) fn hooks(&self) -> &[Box<dyn SessionEvents>] {
+        &self.hooks
+    }
+
+    /// The number of segments in the session.
+    pub fn segments(&self) -> usize {
+        self.manifest.segments.len()
+    }
+
+    /// The saved session, with segments read from the bundle as they are resolved.
+    pub fn session(&self) -> Result<Session> {
+        let manifest = &self.manifest;
+        let segments = manifest
+            .segments
+            .iter()
+            .enumerate()
+            .map(|(idx, &stats)| {
+                Box::new(BundleSegmentRef {
+                    path: segment_path(&self.dir, idx),
+                    stats,
+                }) as Box<dyn SegmentRef>
+            })
+            .collect();
+        let mut session = Session::new(
+            segments,
+            manifest.input,
+            manifest.journal.clone(),
+            manifest.exit_code,
+            manifest.post_image.clone(),
+            manifest.assumptions.clone(),
+            manifest.user_cycles,
+            manifest.total_cycles,
+            manifest.pre_state.clone(),
+            manifest.post_state.clone(),
+        );
+        session.id = manifest.id.clone();
+        Ok(session)
+    }
+
+    /// The indices of the segments whose receipts have been saved.
+    pub fn proven_segments(&self) -> Vec<usize> {
+        (0..self.segments())
+            .filter(|&idx| receipt_path(&self.dir, idx).exists())
+            .collect()
+    }
+
+    /// The saved receipt of the segment at `idx`, if it has been proven.
+    pub fn segment_receipt(&self, idx: usize) -> Result<Option<SegmentReceipt>> {
+        ensure!(idx < self.segments(), "segment {idx} is not in the bundle");
+        let path = receipt_path(&self.dir, idx);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read(&path)?;
+        let receipt = bincode::deserialize(&contents)
+            .with_context(|| format!("failed to decode {}", path.display()))?;
+        Ok(Some(receipt))
+    }
+
+    /// Save the receipt of the segment at `idx`.
+    ///
+    /// The receipt is written atomically, so a process dying part way through leaves either no
+    /// receipt or a complete one.
+    pub fn save_segment_receipt(&self, idx: usize, receipt: &SegmentReceipt) -> Result<()> {
+        ensure!(idx < self.segments(), "segment {idx} is not in the bundle");
+        ensure!(
+            receipt.index as usize == idx,
+            "receipt of segment {} saved as segment {idx}",
+            receipt.index
+        );
+        write_atomic(&receipt_path(&self.dir, idx), &bincode::serialize(receipt)?)
+    }
+}
+
+/// A [SegmentRef] to a segment saved in a [SessionBundle].
+struct BundleSegmentRef {
+    path: PathBuf,
+    stats: SegmentStats,
+}
+
+impl SegmentRef for BundleSegmentRef {
+    fn resolve(&self) -> Result<Segment> {
+        let contents = fs::read(&self.path)
+            .with_context(|| format!("failed to read {}", self.path.display()))?;
+        Ok(bincode::deserialize(&contents)?)
+    }
+
+    fn stats(&self) -> Option<SegmentStats> {
+        Some(self.stats)
+    }
+}
+
+fn segment_path(dir: &Path, idx: usize) -> PathBuf {
+    dir.join(SEGMENTS).join(format!("{idx}.bincode"))
+}
+
+fn receipt_path(dir: &Path, idx: usize) -> PathBuf {
+    dir.join(RECEIPTS).join(format!("{idx}.bincode"))
+}
+
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    file.write_all(contents)?;
+    file.as_file().sync_all()?;
+    file.persist(path)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(())
+}
@@ -0,0 +1,193 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{rc::Rc, sync::Arc};
+
+use anyhow::{ensure, Result};
+use rayon::prelude::*;
+use risc0_binfmt::{ElfSegments, MemoryImage};
+use risc0_zkvm_platform::{memory::GUEST_MAX_MEM, PAGE_SIZE};
+
+use super::executor::ExecutorImpl;
+use crate::{ExecutorEnv, Session};
+
+/// Executes one guest program over many independent inputs in parallel.
+///
+/// The program is loaded and its page hashes are computed once, when the [BatchExecutor] is
+/// constructed, and each run starts from a copy of the resulting [MemoryImage]. Each run has its
+/// own [ExecutorEnv], which is built on the thread that executes it, since an [ExecutorEnv] cannot
+/// be sent between threads. Runs are scheduled on the global rayon thread pool.
+///
+/// # Example
+///
+/// ```
+/// use risc0_zkvm::{BatchExecutor, ExecutorEnv};
+/// use risc0_zkvm_methods::{multi_test::MultiTestSpec, MULTI_TEST_ELF};
+///
+/// let batch = BatchExecutor::from_elf(MULTI_TEST_ELF).unwrap();
+/// let sessions = batch
+///     .run_all((0..4).map(|_| {
+///         || {
+///             ExecutorEnv::builder()
+///                 .write(&MultiTestSpec::DoNothing)?
+///                 .build()
+///         }
+///     }))
+///     .unwrap();
+/// assert_eq!(sessions.len(), 4);
+/// ```
+pub struct BatchExecutor {
+    image: MemoryImage,
+    elf: Option<(Arc<[u8]>, u32)>,
+}
+
+impl BatchExecutor {
+    /// Construct a [BatchExecutor] that runs the given [MemoryImage].
+    pub fn new(image: MemoryImage) -> Result<Self> {
+        ensure!(
+            image.page_size() as usize == PAGE_SIZE,
+            "Unsupported page size: expected {PAGE_SIZE}, got {}",
+            image.page_size()
+        );
+        Ok(Self { image, elf: None })
+    }
+
+    /// Construct a [BatchExecutor] that runs the guest program in the given ELF binary.
+    pub fn from_elf(elf: &[u8]) -> Result<Self> {
+        let segments = ElfSegments::parse(elf, GUEST_MAX_MEM as u32)?;
+        let image = MemoryImage::from_elf_segments(&segments, PAGE_SIZE as u32)?;
+        Ok(Self {
+            image,
+            elf: Some((elf.into(), segments.end())),
+        })
+    }
+
+    /// Run the program once for each environment, returning the sessions in the same order.
+    ///
+    /// Each item of `envs` builds the [ExecutorEnv] of one run. Fails with the error of the
+    /// first run, in order, that failed.
+    pub fn run_all<'a, F, I>(&self, envs: I) -> Result<Vec<Session>>
+    where
+        I: IntoIterator<Item = F>,
+        F: FnOnce() -> Result<ExecutorEnv<'a>> + Send,
+    {
+        let envs: Vec<F> = envs.into_iter().collect();
+        let sessions: Vec<Result<SendSession>> = envs
+            .into_par_iter()
+            .map(|make_env| self.run(make_env()?).map(SendSession::new))
+            .collect();
+        sessions
+            .into_iter()
+            .map(|session| session.map(SendSession::into_inner))
+            .collect()
+    }
+
+    fn run(&self, env: ExecutorEnv<'_>) -> Result<Session> {
+        let image = self.image.clone();
+        let mut exec = match &self.elf {
+            Some((elf, program_end)) => {
+                ExecutorImpl::from_loaded_elf(env, image, Rc::new(elf.clone()), *program_end)?
+            }
+            None => ExecutorImpl::new(env, image)?,
+        };
+        exec.run()
+    }
+}
+
+/// A [Session] that has no hooks, and so can be sent back from the thread that created it.
+pub(crate) struct SendSession(Session);
+
+impl SendSession {
+    /// Wrap `session`, dropping any hooks on the thread that created them. Sessions returned by
+    /// the executor have none.
+    pub(crate) fn new(mut session: Session) -> Self {
+        session.hooks.clear();
+        Self(session)
+    }
+
+    pub(crate) fn into_inner(self) -> Session {
+        self.0
+    }
+}
+
+// SAFETY: Hooks are the only part of a [Session] that may not be [Send], as checked by
+// `assert_send_without_hooks`, and a [SendSession] has none.
+unsafe impl Send for SendSession {}
+
+// Naming every field here makes adding a field to [Session] a build error until it is checked to
+// be [Send].
+#[allow(dead_code)]
+fn assert_send_without_hooks(session: Session) {
+    fn send<T: Send>(_: T) {}
+    let Session {
+        id,
+        segments,
+        input,
+        journal,
+        exit_code,
+        post_image,
+        assumptions,
+        hooks: _,
+        user_cycles,
+        total_cycles,
+        pre_state,
+        post_state,
+        stdout_bytes,
+        stderr_bytes,
+        fd_usage,
+        paging_cycles,
+        saved_paging_cycles,
+        insn_counts,
+        committed_fields,
+        syscall_manifest,
+        perf_warnings,
+        tainted_commits,
+        syscall_transcript,
+        nondeterminism_report,
+        events,
+        accelerators,
+        executor_state,
+    } = session;
+    send((
+        id,
+        segments,
+        input,
+        journal,
+        exit_code,
+        post_image,
+        assumptions,
+    ));
+    send((
+        user_cycles,
+        total_cycles,
+        pre_state,
+        post_state,
+        stdout_bytes,
+        stderr_bytes,
+    ));
+    send((
+        fd_usage,
+        paging_cycles,
+        saved_paging_cycles,
+        insn_counts,
+        committed_fields,
+    ));
+    send((
+        syscall_manifest,
+        perf_warnings,
+        tainted_commits,
+        syscall_transcript,
+    ));
+    send((nondeterminism_report, events, accelerators, executor_state));
+}
@@ -0,0 +1,119 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use risc0_binfmt::{MemoryImage, SystemState};
+use risc0_circuit_rv32im::prove::emu::exec::{PagingStats, TrapState};
+use risc0_zkp::core::digest::Digest;
+
+use crate::{redaction::RedactableJournal, Assumption, AssumptionReceipt, ExitCode, Segment};
+
+/// The results of a session retained by an [ExecutionCache].
+pub(crate) struct CachedSession {
+    pub(crate) segments: Vec<Segment>,
+    pub(crate) journal: Option<Vec<u8>>,
+    pub(crate) exit_code: ExitCode,
+    pub(crate) post_image: MemoryImage,
+    pub(crate) traps: TrapState,
+    pub(crate) assumptions: Vec<(Assumption, AssumptionReceipt)>,
+    pub(crate) user_cycles: u64,
+    pub(crate) total_cycles: u64,
+    pub(crate) pre_state: SystemState,
+    pub(crate) post_state: SystemState,
+    pub(crate) paging: PagingStats,
+    pub(crate) committed_fields: RedactableJournal,
+}
+
+#[derive(Default)]
+struct Inner {
+    sessions: HashMap<Digest, Arc<CachedSession>>,
+    // Keys, from least to most recently inserted.
+    order: VecDeque<Digest>,
+}
+
+/// An in-process cache of the results of executing a guest.
+///
+/// When an env is configured with an [ExecutionCache] using
+/// [ExecutorEnvBuilder::execution_cache][crate::ExecutorEnvBuilder::execution_cache], the
+/// executor looks up each run by the image ID of the memory image it starts from and a digest
+/// of the environment. On a hit, the segments of the cached session are passed to the segment
+/// callback again and the session is returned without executing the guest. This saves
+/// re-executing idempotent requests, e.g. in services that retry failed proofs.
+///
+/// Only runs whose input is fully known up front (i.e. provided with
+/// [ExecutorEnvBuilder::write][crate::ExecutorEnvBuilder::write] and friends) are cached, and
+/// only if nothing observes the execution itself, such as trace callbacks, the profiler, or a
+/// syscall transcript. Output written by the guest to stdout and stderr is not replayed.
+///
+/// An [ExecutionCache] is cheap to clone; all clones refer to the same cache.
+#[derive(Clone)]
+pub struct ExecutionCache {
+    inner: Arc<Mutex<Inner>>,
+    max_sessions: usize,
+}
+
+impl ExecutionCache {
+    /// Construct an [ExecutionCache] that holds at most `max_sessions` sessions.
+    ///
+    /// Once full, the least recently inserted session is evicted first. Every segment of a
+    /// cached session is held in memory.
+    pub fn new(max_sessions: usize) -> Self {
+        Self {
+            inner: Default::default(),
+            max_sessions,
+        }
+    }
+
+    /// Return the number of cached sessions.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().sessions.len()
+    }
+
+    /// Return true if no sessions are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove every cached session.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.sessions.clear();
+        inner.order.clear();
+    }
+
+    pub(crate) fn get(&self, key: &Digest) -> Option<Arc<CachedSession>> {
+        self.inner.lock().unwrap().sessions.get(key).cloned()
+    }
+
+    /// Retain `session`, evicting older sessions if the cache is full.
+    pub(crate) fn insert(&self, key: Digest, session: CachedSession) {
+        if self.max_sessions == 0 {
+            return;
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        if inner.sessions.insert(key, Arc::new(session)).is_some() {
+            inner.order.retain(|k| *k != key);
+        }
+        inner.order.push_back(key);
+        while inner.order.len() > self.max_sessions {
+            let evicted = inner.order.pop_front().unwrap();
+            inner.sessions.remove(&evicted);
+        }
+    }
+}
@@ -16,15 +16,20 @@
 
 use crate::{
     host::client::env::AssumptionReceipts,
-    receipt::merkle::MerkleGroup,
-    receipt_claim::Unknown,
-    sha::Digestible,
     sha::{Digest, DIGEST_BYTES},
-    Assumption, AssumptionReceipt, MaybePruned, SuccinctReceipt, SuccinctReceiptVerifierParameters,
+    Assumption, AssumptionReceipt,
+};
+#[cfg(feature = "prove")]
+use crate::{
+    receipt::merkle::MerkleGroup, receipt_claim::Unknown, sha::Digestible, MaybePruned,
+    SuccinctReceipt, SuccinctReceiptVerifierParameters,
 };
 use anyhow::{anyhow, bail, Context, Result};
+#[cfg(feature = "prove")]
 use risc0_zkp::{core::hash::poseidon2::Poseidon2HashSuite, field::baby_bear::BabyBearElem};
-use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+#[cfg(feature = "prove")]
+use std::collections::VecDeque;
+use std::{cell::RefCell, rc::Rc};
 
 #[derive(Clone)]
 pub(crate) struct SysCompose {
@@ -111,6 +116,7 @@ impl SysCompose {
         Ok((0, 0))
     }
 
+    #[cfg(feature = "prove")]
     pub(crate) fn sys_execute_zkr(
         &mut self,
         control_id: &Digest,
@@ -156,16 +162,35 @@ impl SysCompose {
             .push(succinct_receipt.into());
         Ok((0, 0))
     }
+
+    /// Recursion programs are run by the recursion prover, which is not built without the
+    /// `prove` feature.
+    #[cfg(not(feature = "prove"))]
+    pub(crate) fn sys_execute_zkr(
+        &mut self,
+        control_id: &Digest,
+        _input: &[u32],
+    ) -> Result<(u32, u32)> {
+        bail!(
+            "Cannot run recursion program {control_id}: executing a guest that calls \
+             sys_execute_zkr requires the `prove` feature of risc0-zkvm"
+        )
+    }
 }
 
+#[cfg(feature = "prove")]
 use risc0_circuit_recursion::prove::Program;
 /// A registry to look up programs by control ID.
+#[cfg(feature = "prove")]
 use std::{collections::BTreeMap, sync::Mutex};
+#[cfg(feature = "prove")]
 pub(crate) type ZkrRegistry = BTreeMap<Digest, Box<dyn Fn() -> Result<Program> + Send + 'static>>;
 
+#[cfg(feature = "prove")]
 pub(crate) static ZKR_REGISTRY: Mutex<ZkrRegistry> = Mutex::new(BTreeMap::new());
 
 /// Registers a function to retrieve a recursion program (zkr) based on a control id.
+#[cfg(feature = "prove")]
 pub fn register_zkr(
     control_id: &Digest,
     get_program_fn: impl Fn() -> Result<Program> + Send + 'static,
@@ -178,6 +203,7 @@ pub fn register_zkr(
     );
 }
 
+#[cfg(feature = "prove")]
 fn get_zkr(control_id: &Digest) -> Result<Program> {
     let registry = ZKR_REGISTRY.lock().unwrap();
     registry
@@ -0,0 +1,65 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checking of the cycles the executor counts for each instruction against the cycles the
+//! prover's preflight gives it, for developers of accelerators and of the circuit.
+
+use anyhow::Result;
+use risc0_circuit_rv32im::{
+    prove::{
+        emu::cycle_audit::{CycleMismatch, InsnCycles},
+        segment::Segment as CircuitSegment,
+    },
+    trace::{TraceCallback, TraceEvent},
+};
+
+/// Records the cycle at which each instruction of the current segment starts, and checks the
+/// cycles between them against the preflight once the segment is complete.
+#[derive(Default)]
+pub(crate) struct CycleAudit {
+    // The address and user cycle of each instruction of the current segment.
+    starts: Vec<(u32, u64)>,
+}
+
+impl CycleAudit {
+    /// Check the instructions recorded since the last segment, which make up `segment`, and fail
+    /// with the first [CycleMismatch] if the preflight counts them differently.
+    pub(crate) fn check(&mut self, segment: &CircuitSegment) -> Result<()> {
+        let starts = std::mem::take(&mut self.starts);
+        let end = starts.first().map_or(0, |&(_, cycle)| cycle) + segment.insn_cycles as u64;
+        let ends = starts.iter().skip(1).map(|&(_, cycle)| cycle).chain([end]);
+        let executor: Vec<_> = starts
+            .iter()
+            .zip(ends)
+            .map(|(&(pc, start), end)| InsnCycles {
+                pc,
+                cycles: (end - start) as usize,
+            })
+            .collect();
+        let preflight = segment.preflight_insn_cycles()?;
+        match CycleMismatch::find(segment.index, &executor, &preflight) {
+            Some(mismatch) => Err(mismatch.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl TraceCallback for CycleAudit {
+    fn trace_callback(&mut self, event: TraceEvent) -> Result<()> {
+        if let TraceEvent::InstructionStart { cycle, pc, .. } = event {
+            self.starts.push((pc, cycle));
+        }
+        Ok(())
+    }
+}
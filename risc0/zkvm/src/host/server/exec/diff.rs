@@ -0,0 +1,275 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Comparison of two sessions of the same guest, e.g. executed on different machines.
+
+use std::fmt;
+
+use anyhow::{Context as _, Result};
+use risc0_zkp::core::digest::Digest;
+use serde::{Deserialize, Serialize};
+
+use crate::{sha::Digestible, ExitCode, Segment, Session, TranscriptEntry};
+
+/// The differences between two sessions, as found by [compare_sessions].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct SessionDiff {
+    /// The exit codes of the sessions, if they differ.
+    pub exit_code: Option<(ExitCode, ExitCode)>,
+
+    /// The first segment that differs between the sessions, if any.
+    pub segment: Option<SegmentDiff>,
+
+    /// The journals of the sessions, if they differ.
+    pub journal: Option<JournalDiff>,
+
+    /// The first syscall whose answer differs between the sessions, if any. Syscalls are only
+    /// compared if both sessions recorded a
+    /// [transcript][crate::ExecutorEnvBuilder::record_syscall_transcript].
+    pub syscall: Option<SyscallDiff>,
+
+    /// The user cycles of the second session, less those of the first.
+    pub user_cycle_delta: i64,
+
+    /// The total cycles of the second session, less those of the first.
+    pub total_cycle_delta: i64,
+}
+
+impl SessionDiff {
+    /// Whether the sessions executed identically.
+    pub fn is_empty(&self) -> bool {
+        self.exit_code.is_none()
+            && self.segment.is_none()
+            && self.journal.is_none()
+            && self.syscall.is_none()
+            && self.user_cycle_delta == 0
+            && self.total_cycle_delta == 0
+    }
+}
+
+/// A segment that differs between two sessions.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SegmentDiff {
+    /// The index of the segment.
+    pub index: u32,
+
+    /// The segment in the first session, if it has that many segments.
+    pub a: Option<SegmentSummary>,
+
+    /// The segment in the second session, if it has that many segments.
+    pub b: Option<SegmentSummary>,
+}
+
+/// What a segment was executed from, and what it executed to, as compared by [compare_sessions].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SegmentSummary {
+    /// The po2 of the segment.
+    pub po2: usize,
+
+    /// The exit code of the segment.
+    pub exit_code: ExitCode,
+
+    /// The digest of the system state the segment started from.
+    pub pre_state: Digest,
+
+    /// The digest of the system state the segment ended with.
+    pub post_state: Digest,
+
+    /// The user cycles executed by the segment.
+    pub user_cycles: u64,
+
+    /// The number of syscalls made by the segment.
+    pub syscalls: usize,
+}
+
+impl SegmentSummary {
+    fn new(segment: &Segment) -> Self {
+        Self {
+            po2: segment.inner.po2,
+            exit_code: segment.inner.exit_code,
+            pre_state: segment.inner.pre_state.digest(),
+            post_state: segment.inner.post_state.digest(),
+            user_cycles: segment.inner.insn_cycles as u64,
+            syscalls: segment.inner.syscalls.len(),
+        }
+    }
+}
+
+/// The journals of two sessions, which differ. A session without a journal has an empty one.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalDiff {
+    /// The offset of the first byte that differs, or the length of the shorter journal.
+    pub offset: usize,
+
+    /// The journal of the first session.
+    pub a: Vec<u8>,
+
+    /// The journal of the second session.
+    pub b: Vec<u8>,
+}
+
+/// A syscall whose answer differs between two sessions.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyscallDiff {
+    /// The index of the syscall in the transcripts.
+    pub index: usize,
+
+    /// The syscall made by the first session, unless it made fewer syscalls.
+    pub a: Option<TranscriptEntry>,
+
+    /// The syscall made by the second session, unless it made fewer syscalls.
+    pub b: Option<TranscriptEntry>,
+}
+
+/// Compare two sessions of the same guest, to find where their executions diverge.
+///
+/// Segments are resolved to compare them, which fails if either session was run with segments
+/// that cannot be resolved, e.g. with [NullSegmentRef][crate::NullSegmentRef].
+pub fn compare_sessions(a: &Session, b: &Session) -> Result<SessionDiff> {
+    let exit_code = (a.exit_code != b.exit_code).then_some((a.exit_code, b.exit_code));
+
+    let mut segment = None;
+    for index in 0..a.segments.len().max(b.segments.len()) {
+        let summarize = |session: &Session, name: &str| {
+            session
+                .segments
+                .get(index)
+                .map(|segment| {
+                    let segment = segment
+                        .resolve()
+                        .with_context(|| format!("Failed to resolve segment {index} of {name}"))?;
+                    Ok::<_, anyhow::Error>(SegmentSummary::new(&segment))
+                })
+                .transpose()
+        };
+        let (summary_a, summary_b) = (summarize(a, "a")?, summarize(b, "b")?);
+        if summary_a != summary_b {
+            segment = Some(SegmentDiff {
+                index: index as u32,
+                a: summary_a,
+                b: summary_b,
+            });
+            break;
+        }
+    }
+
+    let journal_a = a
+        .journal
+        .as_ref()
+        .map_or(&[][..], |journal| journal.bytes.as_slice());
+    let journal_b = b
+        .journal
+        .as_ref()
+        .map_or(&[][..], |journal| journal.bytes.as_slice());
+    let journal = (journal_a != journal_b).then(|| JournalDiff {
+        offset: first_difference(journal_a, journal_b),
+        a: journal_a.to_vec(),
+        b: journal_b.to_vec(),
+    });
+
+    let syscall = match (a.syscall_transcript(), b.syscall_transcript()) {
+        (Some(transcript_a), Some(transcript_b)) => {
+            let answer =
+                |entry: &TranscriptEntry| (entry.name.clone(), entry.inputs, entry.outputs);
+            let entries_a: Vec<_> = transcript_a.entries.iter().map(answer).collect();
+            let entries_b: Vec<_> = transcript_b.entries.iter().map(answer).collect();
+            (entries_a != entries_b).then(|| {
+                let index = first_difference(&entries_a, &entries_b);
+                SyscallDiff {
+                    index,
+                    a: transcript_a.entries.get(index).cloned(),
+                    b: transcript_b.entries.get(index).cloned(),
+                }
+            })
+        }
+        _ => None,
+    };
+
+    Ok(SessionDiff {
+        exit_code,
+        segment,
+        journal,
+        syscall,
+        user_cycle_delta: b.user_cycles as i64 - a.user_cycles as i64,
+        total_cycle_delta: b.total_cycles as i64 - a.total_cycles as i64,
+    })
+}
+
+/// The index of the first element that differs between `a` and `b`, or the length of the
+/// shorter of the two.
+fn first_difference<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    a.iter()
+        .zip(b)
+        .position(|(a, b)| a != b)
+        .unwrap_or(a.len().min(b.len()))
+}
+
+impl fmt::Display for SessionDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "sessions are identical");
+        }
+        if let Some((a, b)) = &self.exit_code {
+            writeln!(f, "exit code: {a:?} != {b:?}")?;
+        }
+        if let Some(segment) = &self.segment {
+            writeln!(f, "first divergent segment: {}", segment.index)?;
+            for (name, summary) in [("a", &segment.a), ("b", &segment.b)] {
+                match summary {
+                    Some(summary) => {
+                        writeln!(
+                            f,
+                            "  {name}: po2 {}, {} user cycles, {} syscalls, exit {:?}",
+                            summary.po2, summary.user_cycles, summary.syscalls, summary.exit_code
+                        )?;
+                        writeln!(
+                            f,
+                            "     pre-state {}, post-state {}",
+                            summary.pre_state, summary.post_state
+                        )?;
+                    }
+                    None => writeln!(f, "  {name}: no such segment")?,
+                }
+            }
+        }
+        if let Some(journal) = &self.journal {
+            writeln!(
+                f,
+                "journals differ at byte {} ({} vs {} bytes)",
+                journal.offset,
+                journal.a.len(),
+                journal.b.len()
+            )?;
+        }
+        if let Some(syscall) = &self.syscall {
+            writeln!(f, "first divergent syscall: {}", syscall.index)?;
+            for (name, entry) in [("a", &syscall.a), ("b", &syscall.b)] {
+                match entry {
+                    Some(entry) => writeln!(
+                        f,
+                        "  {name}: {} at cycle {}, answered {:?}",
+                        entry.name, entry.cycle, entry.regs
+                    )?,
+                    None => writeln!(f, "  {name}: no such syscall")?,
+                }
+            }
+        }
+        writeln!(
+            f,
+            "cycle delta: {:+} user, {:+} total",
+            self.user_cycle_delta, self.total_cycle_delta
+        )
+    }
+}
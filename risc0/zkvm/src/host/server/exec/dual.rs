@@ -0,0 +1,354 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Two guests executed concurrently, connected by pipes through the host.
+
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, Read, Write},
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread,
+};
+
+use anyhow::{anyhow, Result};
+
+use super::{batch::SendSession, executor::ExecutorImpl};
+use crate::{ExecutorEnv, ExecutorEnvBuilder, Session};
+
+type MakeEnv = Box<dyn FnOnce(&mut ExecutorEnvBuilder<'static>) -> Result<()> + Send>;
+
+/// One of the two guests of a [DualExecutor].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DualGuest {
+    /// The first guest.
+    First,
+    /// The second guest.
+    Second,
+}
+
+impl DualGuest {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn peer(self) -> Self {
+        match self {
+            Self::First => Self::Second,
+            Self::Second => Self::First,
+        }
+    }
+}
+
+/// Runs two guests concurrently, each in its own session on its own thread, connected by pipes
+/// through the host, e.g. to develop and test a prover and verifier pair of guests in one
+/// process.
+///
+/// This API is experimental, and may change in a future release.
+///
+/// Each [pipe][DualExecutor::pipe] carries the bytes one guest writes to a file descriptor to a
+/// file descriptor the other guest reads from. A read returns once the requested number of bytes
+/// have been written to the pipe, or the writing guest has exited, so what each guest reads does
+/// not depend on how the two threads are scheduled, and the sessions are the same on every run.
+/// A guest should therefore read exactly as many bytes as its peer writes before waiting for a
+/// reply. A run in which both guests wait to read from each other fails with a deadlock error,
+/// rather than hanging.
+///
+/// # Example
+///
+/// ```
+/// use std::io::Cursor;
+///
+/// use risc0_zkvm::{DualExecutor, DualGuest};
+/// use risc0_zkvm_methods::{multi_test::MultiTestSpec, MULTI_TEST_ELF};
+/// use risc0_zkvm_platform::fileno;
+///
+/// let data = b"a message for the second guest..".to_vec();
+/// let nwords = (data.len() / 4) as u32;
+/// let input = data.clone();
+/// let mut dual = DualExecutor::new(
+///     MULTI_TEST_ELF,
+///     move |env| {
+///         // Echo stdin to stdout.
+///         env.write(&MultiTestSpec::EchoStdout { nbytes: 7, fd: fileno::STDIN })?
+///             .stdin(Cursor::new(input));
+///         Ok(())
+///     },
+///     MULTI_TEST_ELF,
+///     move |env| {
+///         // Commit the words read from file descriptor 5.
+///         env.write(&MultiTestSpec::EchoWords { fd: 5, nwords })?;
+///         Ok(())
+///     },
+/// );
+/// dual.pipe(DualGuest::First, fileno::STDOUT, 5);
+/// let (_, second) = dual.run().unwrap();
+/// assert_eq!(second.journal.unwrap().bytes, data);
+/// ```
+pub struct DualExecutor {
+    guests: [(Arc<[u8]>, MakeEnv); 2],
+    // The guest writing to each pipe, and the descriptors it is written to and read from.
+    pipes: Vec<(DualGuest, u32, u32)>,
+}
+
+impl DualExecutor {
+    /// Construct an executor running the guest program in `first_elf` alongside the one in
+    /// `second_elf`.
+    ///
+    /// `make_first_env` and `make_second_env` are called on the thread running each guest, to add
+    /// its input and handlers to the builder of its environment. The pipes of the guest are
+    /// added to the builder afterwards.
+    pub fn new<F, G>(
+        first_elf: impl Into<Arc<[u8]>>,
+        make_first_env: F,
+        second_elf: impl Into<Arc<[u8]>>,
+        make_second_env: G,
+    ) -> Self
+    where
+        F: FnOnce(&mut ExecutorEnvBuilder<'static>) -> Result<()> + Send + 'static,
+        G: FnOnce(&mut ExecutorEnvBuilder<'static>) -> Result<()> + Send + 'static,
+    {
+        Self {
+            guests: [
+                (first_elf.into(), Box::new(make_first_env)),
+                (second_elf.into(), Box::new(make_second_env)),
+            ],
+            pipes: Vec::new(),
+        }
+    }
+
+    /// Connect the file descriptor `write_fd` of the guest `from` to the file descriptor
+    /// `read_fd` of its peer, replacing any writer or reader of those descriptors added to the
+    /// environment of either guest.
+    pub fn pipe(&mut self, from: DualGuest, write_fd: u32, read_fd: u32) -> &mut Self {
+        self.pipes.push((from, write_fd, read_fd));
+        self
+    }
+
+    /// Run both guests until they exit, and return the session of the first guest and that of
+    /// the second.
+    ///
+    /// If either guest fails, the error of the guest that failed first is returned, as its peer
+    /// is likely to fail from reading a pipe that was closed early. A panic in either session,
+    /// e.g. in one of its handlers, is resumed on the calling thread.
+    pub fn run(self) -> Result<(Session, Session)> {
+        let pipes = Arc::new(Pipes::new(
+            self.pipes.iter().map(|(from, ..)| *from).collect(),
+        ));
+        let [first, second] = self.guests;
+        let handles = [(DualGuest::First, first), (DualGuest::Second, second)].map(
+            |(guest, (elf, make_env))| {
+                let pipes = pipes.clone();
+                let specs = self.pipes.clone();
+                thread::spawn(move || {
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                        let mut builder = ExecutorEnv::builder();
+                        make_env(&mut builder)?;
+                        for (idx, &(from, write_fd, read_fd)) in specs.iter().enumerate() {
+                            if from == guest {
+                                builder.write_fd(write_fd, PipeWriter::new(pipes.clone(), idx));
+                            } else {
+                                builder
+                                    .read_fd(read_fd, PipeReader::new(pipes.clone(), idx, guest));
+                            }
+                        }
+                        let env = builder.build()?;
+                        ExecutorImpl::from_owned_elf(env, elf)?
+                            .run()
+                            .map(SendSession::new)
+                    }));
+                    // Closing the pipes the guest writes to lets its peer read to the end.
+                    pipes.exit(guest, !matches!(result, Ok(Ok(_))));
+                    result
+                })
+            },
+        );
+
+        let mut results = handles.map(|handle| match handle.join() {
+            Ok(Ok(result)) => Some(result),
+            Ok(Err(payload)) | Err(payload) => panic::resume_unwind(payload),
+        });
+        if let Some(guest) = pipes.lock().failed.first().copied() {
+            let err = results[guest.index()]
+                .take()
+                .and_then(Result::err)
+                .unwrap_or_else(|| anyhow!("guest failed"));
+            return Err(err.context(format!("{guest:?} guest failed")));
+        }
+        let [first, second] = results.map(|result| result.unwrap().map(SendSession::into_inner));
+        Ok((first?, second?))
+    }
+}
+
+/// The pipes between the two guests of a [DualExecutor].
+struct Pipes {
+    // The guest writing to each pipe.
+    writers: Vec<DualGuest>,
+    state: Mutex<PipeState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct PipeState {
+    buffers: Vec<VecDeque<u8>>,
+    closed: Vec<bool>,
+    // The pipe each guest is waiting to read from, if any.
+    waiting: [Option<usize>; 2],
+    deadlock: bool,
+    // The guests that failed, in the order they failed.
+    failed: Vec<DualGuest>,
+}
+
+impl PipeState {
+    fn is_readable(&self, idx: usize) -> bool {
+        !self.buffers[idx].is_empty() || self.closed[idx]
+    }
+}
+
+impl Pipes {
+    fn new(writers: Vec<DualGuest>) -> Self {
+        let state = PipeState {
+            buffers: vec![VecDeque::new(); writers.len()],
+            closed: vec![false; writers.len()],
+            ..Default::default()
+        };
+        Self {
+            writers,
+            state: Mutex::new(state),
+            changed: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PipeState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Wait until pipe `idx` has bytes to read, or is closed, and take its bytes, which are
+    /// none once it is closed.
+    fn recv(&self, idx: usize, guest: DualGuest) -> io::Result<Vec<u8>> {
+        let mut state = self.lock();
+        loop {
+            if state.deadlock {
+                return Err(deadlock());
+            }
+            if state.is_readable(idx) {
+                state.waiting[guest.index()] = None;
+                return Ok(state.buffers[idx].drain(..).collect());
+            }
+            // Neither guest can write what the other is waiting for.
+            if let Some(peer_idx) = state.waiting[guest.peer().index()] {
+                if !state.is_readable(peer_idx) {
+                    state.deadlock = true;
+                    self.changed.notify_all();
+                    return Err(deadlock());
+                }
+            }
+            state.waiting[guest.index()] = Some(idx);
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    fn send(&self, idx: usize, bytes: &[u8]) {
+        self.lock().buffers[idx].extend(bytes);
+        self.changed.notify_all();
+    }
+
+    /// Close the pipes written by `guest`, which has exited.
+    fn exit(&self, guest: DualGuest, failed: bool) {
+        let mut state = self.lock();
+        for (idx, writer) in self.writers.iter().enumerate() {
+            if *writer == guest {
+                state.closed[idx] = true;
+            }
+        }
+        state.waiting[guest.index()] = None;
+        if failed {
+            state.failed.push(guest);
+        }
+        drop(state);
+        self.changed.notify_all();
+    }
+}
+
+fn deadlock() -> io::Error {
+    io::Error::other("Deadlock: both guests are waiting to read from each other")
+}
+
+/// The end of a pipe read by a guest.
+struct PipeReader {
+    pipes: Arc<Pipes>,
+    idx: usize,
+    guest: DualGuest,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl PipeReader {
+    fn new(pipes: Arc<Pipes>, idx: usize, guest: DualGuest) -> Self {
+        Self {
+            pipes,
+            idx,
+            guest,
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let nread = available.len().min(buf.len());
+        buf[..nread].copy_from_slice(&available[..nread]);
+        self.consume(nread);
+        Ok(nread)
+    }
+}
+
+impl BufRead for PipeReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.buf.len() {
+            self.buf = self.pipes.recv(self.idx, self.guest)?;
+            self.pos = 0;
+        }
+        Ok(&self.buf[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.buf.len());
+    }
+}
+
+/// The end of a pipe written by a guest.
+struct PipeWriter {
+    pipes: Arc<Pipes>,
+    idx: usize,
+}
+
+impl PipeWriter {
+    fn new(pipes: Arc<Pipes>, idx: usize) -> Self {
+        Self { pipes, idx }
+    }
+}
+
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pipes.send(self.idx, buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -12,29 +12,72 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{cell::RefCell, io::Write, mem, rc::Rc, sync::Arc, time::Instant};
+use std::{
+    cell::{Cell, OnceCell, RefCell},
+    collections::{BTreeSet, VecDeque},
+    io::Write,
+    mem,
+    rc::Rc,
+    sync::Arc,
+    time::Instant,
+};
 
-use anyhow::{Context as _, Result};
-use risc0_binfmt::{MemoryImage, Program};
+use anyhow::{anyhow, bail, ensure, Context as _, Result};
+use risc0_binfmt::{ElfSegments, MemoryImage};
 use risc0_circuit_rv32im::prove::emu::{
     addr::ByteAddr,
     exec::{
-        Executor, Syscall as NewSyscall, SyscallContext as NewSyscallContext,
-        DEFAULT_SEGMENT_LIMIT_PO2,
+        Executor, ExecutorResult, MeteredOp, Syscall as NewSyscall,
+        SyscallContext as NewSyscallContext, TrapState, DEFAULT_SEGMENT_LIMIT_PO2,
+    },
+};
+use risc0_circuit_rv32im::prove::segment::Segment as CircuitSegment;
+use risc0_zkp::core::{
+    digest::Digest,
+    hash::sha::{Impl, Sha256},
+};
+use risc0_zkvm_platform::{
+    fileno,
+    memory::{is_guest_memory, GUEST_MAX_MEM, SYSTEM},
+    syscall::{
+        nr::{
+            SYS_ALLOC_FAILED, SYS_COMMIT_FIELD, SYS_LOG, SYS_PANIC, SYS_PANIC_AT, SYS_PIPE,
+            SYS_READ, SYS_WRITE, SYS_WRITEV,
+        },
+        reg_abi::{REG_A3, REG_A4, REG_A5, REG_A6, REG_A7, REG_MAX},
+        SyscallName,
     },
+    PAGE_SIZE, WORD_SIZE,
 };
-use risc0_zkp::core::digest::Digest;
-use risc0_zkvm_platform::{fileno, memory::GUEST_MAX_MEM, PAGE_SIZE};
 use tempfile::tempdir;
 
 use crate::{
-    host::client::env::SegmentPath, Assumptions, ExecutorEnv, FileSegmentRef, Output, Segment,
-    SegmentRef, Session,
+    host::{
+        client::{
+            audit::NondeterminismAudit,
+            env::SegmentPath,
+            sink::{ByteCounter, Tee},
+            transcript::{self, SyscallTranscript, TranscriptEntry},
+        },
+        server::session::new_session_id,
+    },
+    AcceleratorStats, Assumptions, ExecutionEvent, ExecutionEventKind, ExecutionProgress,
+    ExecutorEnv, ExitCode, FileSegmentRef, Output, Segment, SegmentRef, Session,
 };
 
 use super::{
+    cache::{CachedSession, ExecutionCache},
+    cycle_audit::CycleAudit,
+    heatmap::PageHeatmap,
+    manifest::SyscallManifest,
+    perf::PerfAnalyzer,
     profiler::Profiler,
+    runaway::{RunawayDetector, RunawayReport},
+    snapshot::ExecutorState,
+    step::{StepResult, StepTrace, Stepper},
+    symbolizer::Symbolizer,
     syscall::{SyscallContext, SyscallTable},
+    taint::TaintTracker,
 };
 
 // The Executor provides an implementation for the execution phase.
@@ -45,8 +88,138 @@ pub struct ExecutorImpl<'a> {
     image: MemoryImage,
     pub(crate) syscall_table: SyscallTable<'a>,
     profiler: Option<Rc<RefCell<Profiler>>>,
+    heatmap: Option<PageHeatmap>,
+    perf: Option<Rc<RefCell<PerfAnalyzer>>>,
+    taint: Option<Rc<RefCell<TaintTracker>>>,
+    cycle_audit: Option<Rc<RefCell<CycleAudit>>>,
+    // Built from `elf` on first use, since parsing the debug info is slow.
+    symbolizer: OnceCell<Option<Rc<Symbolizer>>>,
+    // Shared rather than copied, so that a mapped ELF is only read from disk where it is used.
+    elf: Option<Rc<dyn AsRef<[u8]>>>,
+    transcript: Option<RefCell<Vec<TranscriptEntry>>>,
+    // The syscalls answered by every run so far, including failed runs, as written to
+    // `record_transcript_out`.
+    recorded: Vec<TranscriptEntry>,
+    replay: Option<RefCell<VecDeque<TranscriptEntry>>>,
+    audit: Option<RefCell<NondeterminismAudit>>,
+    // The transcript answering every syscall while re-running the guest for a self-check.
+    check_replay: Option<RefCell<VecDeque<TranscriptEntry>>>,
+    // The end of the program loaded from the ELF, if any.
+    program_end: Option<u32>,
+    // The syscalls the guest is allowed to make, if its ELF declares them.
+    pub(crate) syscall_manifest: Option<SyscallManifest>,
+    // The events of every run so far, in order.
+    events: RefCell<Vec<ExecutionEvent>>,
+    // The user cycles executed by the runs so far.
+    cycle_offset: u64,
+    breakpoints: BTreeSet<u32>,
+    // The breakpoint the last run stopped at, if any.
+    stopped_at: Option<u32>,
+    // The trap handlers of the guest where the last run stopped, which are not part of the image.
+    traps: TrapState,
+}
+
+/// Where the self-check re-run of a run that stopped before the guest exited stops.
+enum SelfCheckStop {
+    /// After this many segments.
+    Split(usize),
+    /// At the breakpoint at `pc`, once `cycle` user cycles have been executed.
+    Breakpoint { pc: u32, cycle: u64 },
+}
+
+/// What the self-check compares between the first run of the guest and its re-run.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct RunOutcome {
+    pub(super) exit_code: ExitCode,
+    pub(super) pc: u32,
+    pub(super) merkle_root: Digest,
+    pub(super) output_digest: Option<Digest>,
+    pub(super) user_cycles: u64,
+    pub(super) total_cycles: u64,
+}
+
+impl From<&ExecutorResult> for RunOutcome {
+    fn from(result: &ExecutorResult) -> Self {
+        Self {
+            exit_code: result.exit_code,
+            pc: result.post_state.pc,
+            merkle_root: result.post_state.merkle_root,
+            output_digest: result.output_digest,
+            user_cycles: result.user_cycles,
+            total_cycles: result.total_cycles,
+        }
+    }
+}
+
+/// Fail with every difference between the `expected` outcome of the first run and the `actual`
+/// outcome of the self-check re-run, which left `unused` transcript entries behind.
+pub(super) fn check_outcomes(
+    expected: &RunOutcome,
+    actual: &RunOutcome,
+    unused: usize,
+) -> Result<()> {
+    let mut diffs = Vec::new();
+    if actual.exit_code != expected.exit_code {
+        diffs.push(format!(
+            "exit code {:?} != {:?}",
+            actual.exit_code, expected.exit_code
+        ));
+    }
+    if actual.pc != expected.pc {
+        diffs.push(format!("pc 0x{:08x} != 0x{:08x}", actual.pc, expected.pc));
+    }
+    if actual.merkle_root != expected.merkle_root {
+        diffs.push(format!(
+            "memory root {} != {}",
+            actual.merkle_root, expected.merkle_root
+        ));
+    }
+    if actual.output_digest != expected.output_digest {
+        diffs.push(format!(
+            "journal digest {:?} != {:?}",
+            actual.output_digest, expected.output_digest
+        ));
+    }
+    if actual.user_cycles != expected.user_cycles {
+        diffs.push(format!(
+            "user cycles {} != {}",
+            actual.user_cycles, expected.user_cycles
+        ));
+    }
+    if actual.total_cycles != expected.total_cycles {
+        diffs.push(format!(
+            "total cycles {} != {}",
+            actual.total_cycles, expected.total_cycles
+        ));
+    }
+    if unused != 0 {
+        diffs.push(format!("{unused} syscalls were not made"));
+    }
+    if !diffs.is_empty() {
+        bail!(
+            "Self-check failed: re-execution diverged from the first run: {}",
+            diffs.join(", ")
+        );
+    }
+    Ok(())
 }
 
+/// Syscalls that are still handled by the host when replaying a [SyscallTranscript], because
+/// they send data to the host rather than fetching it.
+const REPLAY_HANDLED: [SyscallName; 7] = [
+    SYS_WRITE,
+    SYS_WRITEV,
+    SYS_LOG,
+    SYS_PANIC,
+    SYS_PANIC_AT,
+    SYS_ALLOC_FAILED,
+    SYS_COMMIT_FIELD,
+];
+
+/// How often, in user cycles, the memory image of a tenant's session is measured against its
+/// memory quota.
+const QUOTA_MEMORY_INTERVAL: usize = 1 << 12;
+
 impl<'a> ExecutorImpl<'a> {
     /// Construct a new [ExecutorImpl] from a [MemoryImage] and entry point.
     ///
@@ -56,7 +229,9 @@ impl<'a> ExecutorImpl<'a> {
     /// the guest program is executed to determine how its proof should be
     /// divided into subparts.
     pub fn new(env: ExecutorEnv<'a>, image: MemoryImage) -> Result<Self> {
-        Self::with_details(env, image, None)
+        let heatmap = env.page_heatmap_out.is_some().then(PageHeatmap::default);
+        let perf = env.perf_warnings.then(PerfAnalyzer::default);
+        Self::with_details(env, image, None, heatmap, perf)
     }
 
     /// Construct a new [ExecutorImpl] from the ELF binary of the guest program
@@ -75,35 +250,389 @@ impl<'a> ExecutorImpl<'a> {
     ///     .unwrap();
     /// let mut exec = ExecutorImpl::from_elf(env, BENCH_ELF).unwrap();
     /// ```
-    pub fn from_elf(mut env: ExecutorEnv<'a>, elf: &[u8]) -> Result<Self> {
-        let program = Program::load_elf(elf, GUEST_MAX_MEM as u32)?;
-        let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
+    ///
+    /// The executor keeps a copy of the ELF to describe where the guest faulted, if it does; use
+    /// [ExecutorImpl::from_owned_elf] to avoid the copy.
+    pub fn from_elf(env: ExecutorEnv<'a>, elf: &[u8]) -> Result<Self> {
+        Self::from_owned_elf(env, elf.to_vec())
+    }
+
+    /// Construct a new [ExecutorImpl] from the ELF binary of the guest program, which the
+    /// executor takes ownership of rather than copying.
+    ///
+    /// This allows the ELF to be memory-mapped, so that only the pages of it that are loaded
+    /// into the guest, or read to describe a fault, are read from disk.
+    pub fn from_owned_elf(env: ExecutorEnv<'a>, elf: impl AsRef<[u8]> + 'static) -> Result<Self> {
+        let elf: Rc<dyn AsRef<[u8]>> = Rc::new(elf);
+        let segments = ElfSegments::parse((*elf).as_ref(), GUEST_MAX_MEM as u32)?;
+        let image = MemoryImage::from_elf_segments(&segments, PAGE_SIZE as u32)?;
+        let program_end = segments.end();
+        Self::from_loaded_elf(env, image, elf, program_end)
+    }
 
-        let profiler = if env.pprof_out.is_some() {
-            let profiler = Rc::new(RefCell::new(Profiler::new(elf, None)?));
+    /// Construct a new [ExecutorImpl] from a [MemoryImage] that was already loaded from `elf`,
+    /// and the end of the program it contains.
+    pub(crate) fn from_loaded_elf(
+        mut env: ExecutorEnv<'a>,
+        image: MemoryImage,
+        shared_elf: Rc<dyn AsRef<[u8]>>,
+        program_end: u32,
+    ) -> Result<Self> {
+        let elf = (*shared_elf).as_ref();
+        // The profiler needs the symbolizer up front; otherwise it is only built if a fault
+        // needs to be reported, to keep constructing the executor fast.
+        let (profiler, symbolizer) = if env.pprof_out.is_some() {
+            let symbolizer = Rc::new(Symbolizer::new(elf)?);
+            let profiler = Rc::new(RefCell::new(Profiler::with_symbolizer(
+                elf,
+                None,
+                symbolizer.clone(),
+            )?));
             env.trace.push(profiler.clone());
-            Some(profiler)
+            (Some(profiler), OnceCell::from(Some(symbolizer)))
         } else {
-            None
+            (None, OnceCell::new())
         };
 
-        Self::with_details(env, image, profiler)
+        let heatmap = env
+            .page_heatmap_out
+            .is_some()
+            .then(|| PageHeatmap::new(elf))
+            .transpose()?;
+        let perf = env
+            .perf_warnings
+            .then(|| PerfAnalyzer::new(elf))
+            .transpose()?;
+
+        let mut exec = Self::with_details(env, image, profiler, heatmap, perf)?;
+        exec.symbolizer = symbolizer;
+        exec.elf = Some(shared_elf.clone());
+        exec.program_end = Some(program_end);
+        if exec.syscall_manifest.is_none() {
+            exec.syscall_manifest = SyscallManifest::from_elf(elf)?;
+        }
+        Ok(exec)
     }
 
     fn with_details(
-        env: ExecutorEnv<'a>,
+        mut env: ExecutorEnv<'a>,
         image: MemoryImage,
         profiler: Option<Rc<RefCell<Profiler>>>,
+        heatmap: Option<PageHeatmap>,
+        perf: Option<PerfAnalyzer>,
     ) -> Result<Self> {
+        ensure!(
+            image.page_size() as usize == PAGE_SIZE,
+            "Unsupported page size: expected {PAGE_SIZE}, got {}",
+            image.page_size()
+        );
         let syscall_table = SyscallTable::from_env(&env);
+        let syscall_manifest = env.syscall_manifest.clone();
+        let transcript = (env.record_transcript || env.self_check).then(Default::default);
+        let audit = env
+            .audit_nondeterminism
+            .then(|| RefCell::new(NondeterminismAudit::new(env.commit_input)));
+        let replay = env
+            .replay_transcript
+            .take()
+            .map(|transcript| RefCell::new(transcript.entries.into()));
+        let perf = perf.map(|perf| Rc::new(RefCell::new(perf)));
+        if let Some(perf) = &perf {
+            env.trace.push(perf.clone());
+        }
+        let taint = env
+            .taint_tracking
+            .then(|| Rc::new(RefCell::new(TaintTracker::default())));
+        if let Some(taint) = &taint {
+            env.trace.push(taint.clone());
+        }
+        let cycle_audit = env
+            .cycle_audit
+            .then(|| Rc::new(RefCell::new(CycleAudit::default())));
+        if let Some(cycle_audit) = &cycle_audit {
+            env.trace.push(cycle_audit.clone());
+        }
         Ok(Self {
             env,
             image,
             syscall_table,
             profiler,
+            heatmap,
+            perf,
+            taint,
+            cycle_audit,
+            symbolizer: OnceCell::new(),
+            elf: None,
+            transcript,
+            recorded: Vec::new(),
+            replay,
+            audit,
+            check_replay: None,
+            program_end: None,
+            syscall_manifest,
+            events: RefCell::default(),
+            cycle_offset: 0,
+            breakpoints: BTreeSet::new(),
+            stopped_at: None,
+            traps: TrapState::default(),
+        })
+    }
+
+    /// The events of every run of this executor so far, in order, including runs that failed.
+    ///
+    /// See [Session::events].
+    pub fn events(&self) -> Vec<ExecutionEvent> {
+        self.events.borrow().clone()
+    }
+
+    /// Replace the host-side syscall handlers with the ones configured in `env`.
+    ///
+    /// This may be called between runs, e.g. after the guest has paused, so that long-lived
+    /// sessions can upgrade their handlers without restarting execution. The I/O handlers
+    /// (file descriptors, slice I/O callbacks), environment variables, and arguments are taken
+    /// from `env`; all other settings, the assumptions available to the guest, and the state of
+    /// any pipes opened by the guest are preserved.
+    pub fn update_syscalls(&mut self, env: ExecutorEnv<'a>) {
+        self.env.posix_io = env.posix_io;
+        self.env.slice_io = env.slice_io;
+        self.env.env_vars = env.env_vars;
+        self.env.args = env.args;
+
+        let pipes = self.syscall_table.inner.get(SYS_PIPE.as_str()).cloned();
+        self.syscall_table = SyscallTable::from_env(&self.env);
+        if let Some(pipes) = pipes {
+            self.syscall_table
+                .inner
+                .insert(SYS_PIPE.as_str().to_string(), pipes);
+        }
+    }
+
+    /// Stop execution before the instruction at `addr` is executed.
+    ///
+    /// A run that reaches a breakpoint ends with a [Session] whose exit code is
+    /// [ExitCode::SystemSplit], and [ExecutorImpl::stopped_at] returns the breakpoint. The state
+    /// of the guest can then be inspected with [ExecutorImpl::peek_register] and
+    /// [ExecutorImpl::peek_region], and the guest resumed by running the executor again.
+    pub fn add_breakpoint(&mut self, addr: u32) {
+        self.breakpoints.insert(addr);
+    }
+
+    /// Remove a breakpoint added with [ExecutorImpl::add_breakpoint], returning whether it was
+    /// set.
+    pub fn remove_breakpoint(&mut self, addr: u32) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    /// Run the executor until the guest reaches the instruction at `pc`, stops at a breakpoint,
+    /// or exits.
+    pub fn run_until(&mut self, pc: u32) -> Result<Session> {
+        let added = self.breakpoints.insert(pc);
+        let session = self.run();
+        if added {
+            self.breakpoints.remove(&pc);
+        }
+        session
+    }
+
+    /// The breakpoint the last run stopped at, if it stopped at one.
+    pub fn stopped_at(&self) -> Option<u32> {
+        self.stopped_at
+    }
+
+    /// The pc where the last run stopped, or the entry point before the first run.
+    pub fn pc(&self) -> u32 {
+        self.image.pc
+    }
+
+    /// The value of register `idx` where the last run stopped, or before the first run.
+    pub fn peek_register(&self, idx: usize) -> Result<u32> {
+        ensure!(idx < REG_MAX, "invalid register: x{idx}");
+        let mut bytes = [0u8; WORD_SIZE];
+        self.image
+            .load_region_in_page(SYSTEM.start() as u32 + (idx * WORD_SIZE) as u32, &mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    /// The word of guest memory at `addr` where the last run stopped, or before the first run.
+    pub fn peek_u32(&self, addr: u32) -> Result<u32> {
+        ensure!(
+            addr % WORD_SIZE as u32 == 0,
+            "unaligned address: 0x{addr:08x}"
+        );
+        let bytes = self.peek_region(addr, WORD_SIZE as u32)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// `len` bytes of guest memory at `addr` where the last run stopped, or before the first run.
+    pub fn peek_region(&self, addr: u32, len: u32) -> Result<Vec<u8>> {
+        let end = addr
+            .checked_add(len)
+            .with_context(|| format!("Memory range at 0x{addr:08x} overflows"))?;
+        ensure!(
+            len == 0 || (is_guest_memory(addr) && is_guest_memory(end - 1)),
+            "0x{addr:08x}..0x{end:08x} is not guest memory"
+        );
+        let mut region = vec![0u8; len as usize];
+        let mut offset = 0;
+        while offset < region.len() {
+            let addr = addr + offset as u32;
+            let page_end = (addr / PAGE_SIZE as u32 + 1) as usize * PAGE_SIZE;
+            let chunk = (page_end - addr as usize).min(region.len() - offset);
+            self.image
+                .load_region_in_page(addr, &mut region[offset..offset + chunk])?;
+            offset += chunk;
+        }
+        Ok(region)
+    }
+
+    /// Overwrite guest memory at `addr` with `data`, before the next run or step.
+    ///
+    /// The segments of the next run start from the modified memory, so a proof of the session
+    /// attests to an execution from the modified state rather than from the program.
+    pub fn poke_region(&mut self, addr: u32, data: &[u8]) -> Result<()> {
+        let end = addr
+            .checked_add(data.len() as u32)
+            .with_context(|| format!("Memory range at 0x{addr:08x} overflows"))?;
+        ensure!(
+            data.is_empty() || (is_guest_memory(addr) && is_guest_memory(end - 1)),
+            "0x{addr:08x}..0x{end:08x} is not guest memory"
+        );
+        let mut offset = 0;
+        while offset < data.len() {
+            let addr = addr + offset as u32;
+            let page_end = (addr / PAGE_SIZE as u32 + 1) as usize * PAGE_SIZE;
+            let chunk = (page_end - addr as usize).min(data.len() - offset);
+            self.image
+                .store_region_in_page(addr, &data[offset..offset + chunk]);
+            offset += chunk;
+        }
+        self.image.hash_pages();
+        Ok(())
+    }
+
+    /// Take a snapshot of the state of the executor, where the last run or step stopped, to
+    /// [restore][ExecutorImpl::restore] it later, possibly on another machine.
+    ///
+    /// Fails if the guest has a pipe open, or is between the two calls of a syscall that returns
+    /// a variable amount of data; see [ExecutorState].
+    pub fn snapshot(&self) -> Result<ExecutorState> {
+        if let Some((name, _)) = self
+            .syscall_table
+            .inner
+            .iter()
+            .find(|(_, entry)| entry.handler.borrow().is_pending())
+        {
+            bail!("Cannot take a snapshot while the guest is fetching the result of {name}");
+        }
+        let io = self.syscall_table.posix_io.borrow().save()?;
+        Ok(ExecutorState {
+            io: Some(io),
+            ..self.paused_state()
         })
     }
 
+    /// The state of the executor for resuming a paused session with new syscall handlers, which
+    /// leaves out the state of the file descriptors.
+    fn paused_state(&self) -> ExecutorState {
+        ExecutorState {
+            image: self.image.clone(),
+            cycle_offset: self.cycle_offset,
+            stopped_at: self.stopped_at,
+            traps: self.traps.clone(),
+            events: self.events.borrow().clone(),
+            transcript: self
+                .transcript
+                .as_ref()
+                .map(|transcript| transcript.borrow().clone()),
+            replay: self
+                .replay
+                .as_ref()
+                .map(|replay| replay.borrow().iter().cloned().collect()),
+            io: None,
+        }
+    }
+
+    /// Restore a state taken by [ExecutorImpl::snapshot], so that the next run continues the
+    /// guest from where the snapshot was taken.
+    ///
+    /// The executor should be constructed from the same program and with an equivalent
+    /// environment as the one the snapshot was taken from.
+    pub fn restore(&mut self, state: ExecutorState) -> Result<()> {
+        ensure!(
+            state.image.page_size() as usize == PAGE_SIZE,
+            "Unsupported page size: expected {PAGE_SIZE}, got {}",
+            state.image.page_size()
+        );
+        self.image = state.image;
+        self.cycle_offset = state.cycle_offset;
+        self.stopped_at = state.stopped_at;
+        self.traps = state.traps;
+        self.events = RefCell::new(state.events);
+        if let Some(transcript) = state.transcript {
+            self.transcript = Some(RefCell::new(transcript));
+        }
+        if let Some(replay) = state.replay {
+            self.replay = Some(RefCell::new(replay.into()));
+        }
+        if let Some(io) = state.io {
+            self.syscall_table.posix_io.borrow_mut().restore(io)?;
+        }
+        Ok(())
+    }
+
+    /// Execute a single instruction of the guest, where the last run or step stopped.
+    ///
+    /// Stepping produces no segments, so the instructions stepped over are not part of any
+    /// [Session], nor is data they write to the journal. Breakpoints are not stopped at.
+    ///
+    /// Each call copies the memory image and commits the memory the instruction wrote back to
+    /// it; use [ExecutorImpl::steps] to execute many instructions on a live executor.
+    pub fn step(&mut self) -> Result<StepResult> {
+        self.steps(|stepper| stepper.step())
+    }
+
+    /// Execute the guest one instruction at a time with `f`, where the last run or step stopped.
+    ///
+    /// The [Stepper] passed to `f` keeps a live executor across its steps, so that the memory
+    /// image is copied and updated once rather than on every instruction. The state reached by
+    /// the steps `f` completed is kept, even if `f` fails. As with [ExecutorImpl::step], stepping
+    /// produces no segments and breakpoints are not stopped at.
+    pub fn steps<T>(&mut self, f: impl FnOnce(&mut Stepper<'_, 'a>) -> Result<T>) -> Result<T> {
+        let trace = Rc::new(RefCell::new(StepTrace::default()));
+        let mut callbacks = self.env.trace.clone();
+        callbacks.push(trace.clone());
+
+        let mut exec = Executor::new(self.image.clone(), &*self, self.env.input_digest, callbacks);
+        exec.traps(self.traps.clone());
+        exec.watchpoints(self.env.watchpoints.clone());
+        if let Some(callback) = self.env.watch_callback.clone() {
+            exec.on_watch(move |hit| (callback.borrow_mut())(hit));
+        }
+        if let Some(guard) = self.env.stack_guard() {
+            exec.stack_guard(guard);
+        }
+        if self.env.linux_compat {
+            let program_end = self
+                .program_end
+                .context("Linux syscalls require an executor constructed from an ELF")?;
+            exec.linux_compat(program_end);
+        }
+        let mut stepper = Stepper {
+            executor: &*self,
+            exec,
+            trace,
+        };
+        let result = f(&mut stepper);
+        let cycles = stepper.exec.user_cycles();
+        let (image, traps) = stepper.exec.finish_steps();
+
+        self.image = image;
+        self.traps = traps;
+        self.cycle_offset += cycles;
+        self.stopped_at = None;
+        result
+    }
+
     /// This will run the executor to get a [Session] which contain the results
     /// of the execution.
     pub fn run(&mut self) -> Result<Session> {
@@ -115,35 +644,253 @@ impl<'a> ExecutorImpl<'a> {
         self.run_with_callback(|segment| Ok(Box::new(FileSegmentRef::new(&segment, &path)?)))
     }
 
+    /// Run the executor until the guest halts, handing each pause to `handler`.
+    ///
+    /// Whenever the guest pauses, `handler` is called with the
+    /// [pause code][Session::pause_code] and the paused [Session], and returns the environment
+    /// whose syscall handlers answer the guest once it resumes, as with
+    /// [ExecutorImpl::update_syscalls]. This lets a host serve several kinds of requests from
+    /// the guest, distinguished by their pause codes. Returns every [Session] in order, the
+    /// last of which did not end in a pause.
+    pub fn run_with_pause_handler<F>(&mut self, mut handler: F) -> Result<Vec<Session>>
+    where
+        F: FnMut(u32, &Session) -> Result<ExecutorEnv<'a>>,
+    {
+        let mut sessions = Vec::new();
+        loop {
+            let session = self.run()?;
+            let Some(code) = session.pause_code() else {
+                sessions.push(session);
+                return Ok(sessions);
+            };
+            let env = handler(code, &session)
+                .with_context(|| format!("Failed to handle pause code {code}"))?;
+            self.update_syscalls(env);
+            sessions.push(session);
+        }
+    }
+
     /// Run the executor until [crate::ExitCode::Halted] or
     /// [crate::ExitCode::Paused] is reached, producing a [Session] as a result.
-    pub fn run_with_callback<F>(&mut self, mut callback: F) -> Result<Session>
+    pub fn run_with_callback<F>(&mut self, callback: F) -> Result<Session>
     where
         F: FnMut(Segment) -> Result<Box<dyn SegmentRef>>,
     {
+        let result = self.run_session(callback);
+        let Some(path) = self.env.record_transcript_out.clone() else {
+            return result;
+        };
+        match &result {
+            Ok(session) => {
+                if let Some(transcript) = &session.syscall_transcript {
+                    self.recorded.extend(transcript.entries.iter().cloned());
+                }
+            }
+            // A failed run still answered the syscalls made before it failed, and those are what
+            // reproduce the failure elsewhere.
+            Err(_) => {
+                if let Some(transcript) = &self.transcript {
+                    self.recorded.extend(transcript.take());
+                }
+            }
+        }
+        let saved = SyscallTranscript {
+            entries: self.recorded.clone(),
+        }
+        .save(&path);
+        match saved {
+            Err(err) if result.is_ok() => Err(err),
+            Err(err) => {
+                // Report the failure of the run rather than the failure to save its transcript.
+                tracing::error!("{err:#}");
+                result
+            }
+            Ok(()) => result,
+        }
+    }
+
+    fn run_session<F>(&mut self, mut callback: F) -> Result<Session>
+    where
+        F: FnMut(Segment) -> Result<Box<dyn SegmentRef>>,
+    {
+        let session_id = self.env.session_id.clone().unwrap_or_else(new_session_id);
+        let _span = tracing::info_span!("execute", session_id = %session_id).entered();
+
+        let paused = matches!(
+            self.events.borrow().last(),
+            Some(ExecutionEvent {
+                kind: ExecutionEventKind::Segment {
+                    exit_code: ExitCode::Paused(_) | ExitCode::SystemSplit,
+                    ..
+                } | ExecutionEventKind::Breakpoint { .. }
+                    | ExecutionEventKind::SessionLimit,
+                ..
+            })
+        );
+        if paused {
+            self.record_event(self.cycle_offset, ExecutionEventKind::Resumed);
+        }
+        // A run resumed from a breakpoint does not stop at it again before executing it.
+        let resumed_at = self.stopped_at.take();
+
+        let cache = self.execution_cache_key();
+        if let Some((cache, key)) = &cache {
+            if let Some(cached) = cache.get(key) {
+                tracing::debug!("execution cache hit: {key}");
+                return self.run_cached(&cached, session_id, callback);
+            }
+        }
+
         nvtx::range_push!("execute");
 
         let journal = Journal::default();
+        let mut journal_writer = Tee::new().with(journal.clone());
+        if let Some(tee) = &self.env.journal_tee {
+            journal_writer = journal_writer.chain(tee);
+        }
         self.env
             .posix_io
             .borrow_mut()
-            .with_write_fd(fileno::JOURNAL, journal.clone());
+            .with_write_fd(fileno::JOURNAL, journal_writer);
 
         let segment_limit_po2 = self
             .env
             .segment_limit_po2
             .unwrap_or(DEFAULT_SEGMENT_LIMIT_PO2 as u32) as usize;
 
+        // Hold the tenant's quota reservation until the end of the session.
+        let quota = self
+            .env
+            .tenant
+            .as_ref()
+            .map(|(tenant, quotas)| {
+                quotas
+                    .reserve(tenant, (self.image.pages.len() * PAGE_SIZE) as u64)
+                    .map(Rc::new)
+            })
+            .transpose()?;
+        let session_limit = match quota.as_ref().and_then(|quota| quota.cycle_limit) {
+            Some(remaining) => Some(
+                self.env
+                    .session_limit
+                    .map_or(remaining, |x| x.min(remaining)),
+            ),
+            None => self.env.session_limit,
+        };
+
         let mut refs = Vec::new();
+        let mut cached_segments = Vec::new();
         let mut exec = Executor::new(
             self.image.clone(),
             self,
             self.env.input_digest,
             self.env.trace.clone(),
         );
+        exec.traps(self.traps.clone());
+        exec.trace_prev_values(self.env.trace_prev_values);
+        exec.trace_paging(self.env.trace_paging);
+        exec.watchpoints(self.env.watchpoints.clone());
+        if let Some(callback) = self.env.watch_callback.clone() {
+            exec.on_watch(move |hit| (callback.borrow_mut())(hit));
+        }
+        exec.use_hugepages(self.env.hugepages.unwrap_or_default());
+        exec.segment_po2_schedule(self.segment_po2_schedule());
+        if let Some(guard) = self.env.stack_guard() {
+            exec.stack_guard(guard);
+        }
+        exec.collect_insn_counts(self.env.insn_stats);
+        exec.collect_page_accesses(self.heatmap.is_some());
+        // The total cycles of the segments executed so far, as counted by the circuit.
+        let total_cycles = Rc::new(Cell::new(0u64));
+        let deadline = self.env.wall_clock_deadline;
+        let pause_at_cycle = self.env.pause_at_cycle;
+        if deadline.is_some() || pause_at_cycle.is_some() {
+            let total_cycles = total_cycles.clone();
+            exec.stop_at_split(move |_| {
+                deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                    || pause_at_cycle.is_some_and(|cycles| total_cycles.get() >= cycles)
+            });
+        }
+        let hit = Rc::new(Cell::new(None));
+        if !self.breakpoints.is_empty() {
+            let hit = hit.clone();
+            exec.breakpoints(self.breakpoints.clone(), move |ctx| {
+                let pc = ctx.get_pc();
+                if ctx.get_cycle() == 0 && Some(pc) == resumed_at {
+                    return Ok(false);
+                }
+                hit.set(Some(pc));
+                Ok(true)
+            });
+        }
+        if let Some(meter) = self.env.cycle_meter.clone() {
+            exec.meter(move |pc, op, cycles| {
+                let name = match op {
+                    MeteredOp::Insn(kind) => kind.mnemonic(),
+                    MeteredOp::Ecall(name) => name,
+                };
+                (meter.borrow_mut())(pc.0, name, cycles)
+            });
+        }
+        let runaway = Rc::new(RefCell::new(Vec::new()));
+        if let Some(heuristics) = self.env.runaway_heuristics.clone() {
+            let abort = heuristics.abort;
+            let mut detector = RunawayDetector::new(heuristics);
+            let on_runaway = self.env.on_runaway.clone();
+            let symbolizer = self.symbolizer().cloned();
+            let reports = runaway.clone();
+            let cycle_offset = self.cycle_offset;
+            exec.monitor(detector.interval(), move |ctx, sample| {
+                for kind in detector.observe(sample) {
+                    let report =
+                        RunawayReport::new(kind, ctx, cycle_offset, symbolizer.as_deref())?;
+                    tracing::warn!("{report}");
+                    if let Some(on_runaway) = &on_runaway {
+                        (on_runaway.borrow_mut())(&report);
+                    }
+                    let kind = report.kind.clone();
+                    reports.borrow_mut().push(report);
+                    if abort {
+                        bail!("Runaway guest: {kind}");
+                    }
+                }
+                Ok(())
+            });
+        }
+        if self.env.linux_compat {
+            let program_end = self
+                .program_end
+                .context("Linux syscalls require an executor constructed from an ELF")?;
+            exec.linux_compat(program_end);
+        }
+
+        if let Some(quota) = quota.clone() {
+            exec.monitor(QUOTA_MEMORY_INTERVAL, move |_, sample| {
+                quota.grow_memory(sample.resident_bytes)
+            });
+        }
 
         let start_time = Instant::now();
-        let result = exec.run(segment_limit_po2, self.env.session_limit, |inner| {
+        if let Some((interval, callback)) = self.env.progress_callback.clone() {
+            let cycle_offset = self.cycle_offset;
+            exec.monitor(interval as usize, move |ctx, sample| {
+                (callback.borrow_mut())(&ExecutionProgress {
+                    user_cycles: cycle_offset + ctx.get_cycle(),
+                    total_cycles: sample.total_cycles,
+                    segments: sample.segments as u32,
+                    elapsed: start_time.elapsed(),
+                });
+                Ok(())
+            });
+        }
+        let mut cycle = self.cycle_offset;
+        let result = exec.run(segment_limit_po2, session_limit, |inner| {
+            if let Some(cycle_audit) = &self.cycle_audit {
+                cycle_audit.borrow_mut().check(&inner)?;
+            }
+            self.record_runaway(&runaway);
+            self.record_segment(&mut cycle, &inner);
+            total_cycles.set(total_cycles.get() + (1 << inner.po2));
             let output = inner
                 .exit_code
                 .expects_output()
@@ -174,20 +921,73 @@ impl<'a> ExecutorImpl<'a> {
 
             let segment = Segment {
                 index: inner.index as u32,
+                session_id: session_id.clone(),
                 inner,
                 output,
             };
-            let segment_ref = callback(segment)?;
+            if cache.is_some() {
+                cached_segments.push(segment.clone());
+            }
+            let segment_ref = tracing::info_span!(
+                "segment",
+                session_id = %session_id,
+                index = segment.index,
+                po2 = segment.po2(),
+            )
+            .in_scope(|| callback(segment))?;
             refs.push(segment_ref);
             Ok(())
+        });
+        self.record_runaway(&runaway);
+        // Charge the tenant for every cycle executed, including by runs that fail or stop at the
+        // session limit.
+        if let Some(quota) = &quota {
+            quota.charge_cycles(exec.user_cycles());
+        }
+        let result = result.map_err(|err| {
+            let cycle = self.cycle_offset + exec.user_cycles();
+            let kind = ExecutionEventKind::Failed {
+                error: format!("{err:#}"),
+            };
+            self.record_event(cycle, kind);
+            self.fault_report(err, exec.pc())
         })?;
+        self.cycle_offset += result.user_cycles;
+        if result.exit_code == ExitCode::SessionLimit {
+            self.record_event(self.cycle_offset, ExecutionEventKind::SessionLimit);
+        }
+        self.stopped_at = hit.get();
+        if let Some(pc) = self.stopped_at {
+            self.record_event(self.cycle_offset, ExecutionEventKind::Breakpoint { pc });
+        }
         let elapsed = start_time.elapsed();
 
-        // Set the session_journal to the committed data iff the guest set a non-zero output.
-        let session_journal = result
-            .output_digest
-            .and_then(|digest| (digest != Digest::ZERO).then(|| journal.buf.take()));
-        if !result.exit_code.expects_output() && session_journal.is_some() {
+        if let Some(replay) = &self.replay {
+            let remaining = replay.borrow().len();
+            ensure!(
+                !matches!(result.exit_code, ExitCode::Halted(_)) || remaining == 0,
+                "Guest halted with {remaining} unused entries in the syscall transcript"
+            );
+        }
+
+        if self.env.self_check {
+            self.self_check(&result, segment_limit_po2, session_limit)?;
+        }
+
+        // Set the session_journal to the committed data iff the guest set a non-zero output. A
+        // guest stopped at the session limit has not committed its output, so the data it wrote
+        // to the journal so far is kept for inspection.
+        let session_journal = if result.exit_code == ExitCode::SessionLimit {
+            Some(journal.buf.take()).filter(|journal| !journal.is_empty())
+        } else {
+            result
+                .output_digest
+                .and_then(|digest| (digest != Digest::ZERO).then(|| journal.buf.take()))
+        };
+        if !result.exit_code.expects_output()
+            && result.exit_code != ExitCode::SessionLimit
+            && session_journal.is_some()
+        {
             tracing::debug!(
                 "dropping non-empty journal due to exit code {:?}: 0x{}",
                 result.exit_code,
@@ -204,9 +1004,36 @@ impl<'a> ExecutorImpl<'a> {
             std::fs::write(self.env.pprof_out.as_ref().unwrap(), report)?;
         }
 
+        if let Some(heatmap) = &self.heatmap {
+            let accesses = result.page_accesses.clone().unwrap_or_default();
+            let report = heatmap.report(&accesses, &result.page_faults);
+            std::fs::write(self.env.page_heatmap_out.as_ref().unwrap(), report)?;
+        }
+
         self.image = result.post_image.clone();
+        self.traps = result.traps.clone();
+
+        if let Some((cache, key)) = cache {
+            cache.insert(
+                key,
+                CachedSession {
+                    segments: cached_segments,
+                    journal: session_journal.clone(),
+                    exit_code: result.exit_code,
+                    post_image: result.post_image.clone(),
+                    traps: result.traps.clone(),
+                    assumptions: assumptions.clone(),
+                    user_cycles: result.user_cycles,
+                    total_cycles: result.total_cycles,
+                    pre_state: result.pre_state.clone(),
+                    post_state: result.post_state.clone(),
+                    paging: result.paging,
+                    committed_fields: self.env.committed_fields.borrow().clone(),
+                },
+            );
+        }
 
-        let session = Session::new(
+        let mut session = Session::new(
             refs,
             self.env.input_digest.unwrap_or_default(),
             session_journal,
@@ -218,6 +1045,55 @@ impl<'a> ExecutorImpl<'a> {
             result.pre_state,
             result.post_state,
         );
+        session.id = session_id;
+        session.stdout_bytes = self.env.stdout_counter.as_ref().map(ByteCounter::take);
+        session.stderr_bytes = self.env.stderr_counter.as_ref().map(ByteCounter::take);
+        session.fd_usage = self.env.posix_io.borrow().usage.clone();
+        session.paging_cycles = Some(result.paging.cycles);
+        session.saved_paging_cycles = Some(result.paging.saved_cycles);
+        session.insn_counts = result.insn_counts.map(|counts| {
+            counts
+                .into_iter()
+                .map(|(kind, count)| (format!("{kind:?}").to_lowercase(), count))
+                .collect()
+        });
+        session.accelerators = result
+            .accelerators
+            .into_iter()
+            .map(|(accelerator, usage)| {
+                let stats = AcceleratorStats {
+                    invocations: usage.invocations,
+                    cycles: usage.cycles,
+                };
+                (accelerator.name().to_string(), stats)
+            })
+            .collect();
+        session.committed_fields = self.env.committed_fields.borrow().clone();
+        session.syscall_manifest = self.syscall_manifest.clone();
+        session.syscall_transcript = self
+            .transcript
+            .as_ref()
+            .map(|transcript| SyscallTranscript {
+                entries: transcript.take(),
+            })
+            .filter(|_| self.env.record_transcript);
+        session.nondeterminism_report = self.audit.as_ref().map(|audit| audit.borrow().report());
+        session.events = self.events.borrow().clone();
+        if let ExitCode::Paused(_) = session.exit_code {
+            session.executor_state = Some(self.paused_state());
+        }
+        if let Some(perf) = &self.perf {
+            session.perf_warnings = perf.borrow().warnings(self.symbolizer().map(Rc::as_ref));
+            for warning in &session.perf_warnings {
+                tracing::warn!("{warning}");
+            }
+        }
+        if let Some(taint) = &self.taint {
+            session.tainted_commits = taint.borrow().commits(self.symbolizer().map(Rc::as_ref));
+            for commit in &session.tainted_commits {
+                tracing::warn!("{commit}");
+            }
+        }
 
         tracing::info_span!("executor").in_scope(|| {
             tracing::info!("execution time: {elapsed:?}");
@@ -229,9 +1105,9 @@ impl<'a> ExecutorImpl<'a> {
     }
 }
 
-struct ContextAdapter<'a, 'b> {
-    ctx: &'b mut dyn NewSyscallContext,
-    syscall_table: SyscallTable<'a>,
+pub(crate) struct ContextAdapter<'a, 'b> {
+    pub(crate) ctx: &'b mut dyn NewSyscallContext,
+    pub(crate) syscall_table: SyscallTable<'a>,
 }
 
 impl<'a, 'b> SyscallContext<'a> for ContextAdapter<'a, 'b> {
@@ -264,22 +1140,306 @@ impl<'a, 'b> SyscallContext<'a> for ContextAdapter<'a, 'b> {
     }
 }
 
-impl<'a> NewSyscall for ExecutorImpl<'a> {
-    fn syscall(
+impl<'a> ExecutorImpl<'a> {
+    /// Returns the [ExecutionCache] configured in the env and the key of the next run, if the
+    /// run can be cached.
+    fn execution_cache_key(&self) -> Option<(ExecutionCache, Digest)> {
+        let cache = self.env.execution_cache.as_ref()?;
+        // The env decides which of its settings bypass the cache. A transcript restored from a
+        // snapshot also observes the run, and a run with breakpoints may stop before the guest
+        // exits.
+        let request_digest = self.env.request_digest()?;
+        if self.transcript.is_some() || self.replay.is_some() || !self.breakpoints.is_empty() {
+            return None;
+        }
+        let request = (self.image.compute_id(), &self.traps, request_digest);
+        let key = *Impl::hash_bytes(&bincode::serialize(&request).ok()?);
+        Some((cache.clone(), key))
+    }
+
+    /// Produce a [Session] from the results of an earlier run, passing its segments to
+    /// `callback` again.
+    fn run_cached<F>(
+        &mut self,
+        cached: &CachedSession,
+        session_id: String,
+        mut callback: F,
+    ) -> Result<Session>
+    where
+        F: FnMut(Segment) -> Result<Box<dyn SegmentRef>>,
+    {
+        let mut cycle = self.cycle_offset;
+        let refs = cached
+            .segments
+            .iter()
+            .cloned()
+            .map(|segment| {
+                self.record_segment(&mut cycle, &segment.inner);
+                callback(Segment {
+                    session_id: session_id.clone(),
+                    ..segment
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.image = cached.post_image.clone();
+        self.traps = cached.traps.clone();
+
+        let mut session = Session::new(
+            refs,
+            self.env.input_digest.unwrap_or_default(),
+            cached.journal.clone(),
+            cached.exit_code,
+            cached.post_image.clone(),
+            cached.assumptions.clone(),
+            cached.user_cycles,
+            cached.total_cycles,
+            cached.pre_state.clone(),
+            cached.post_state.clone(),
+        );
+        session.id = session_id;
+        session.paging_cycles = Some(cached.paging.cycles);
+        session.saved_paging_cycles = Some(cached.paging.saved_cycles);
+        session.committed_fields = cached.committed_fields.clone();
+        session.syscall_manifest = self.syscall_manifest.clone();
+        self.cycle_offset += cached.user_cycles;
+        session.events = self.events.borrow().clone();
+        if let ExitCode::Paused(_) = session.exit_code {
+            session.executor_state = Some(self.paused_state());
+        }
+        Ok(session)
+    }
+
+    fn record_event(&self, cycle: u64, kind: ExecutionEventKind) {
+        self.events
+            .borrow_mut()
+            .push(ExecutionEvent { cycle, kind });
+    }
+
+    /// Record the reports of the runaway heuristics that triggered since this was last called.
+    fn record_runaway(&self, reports: &RefCell<Vec<RunawayReport>>) {
+        for report in reports.take() {
+            self.record_event(report.cycle, ExecutionEventKind::Runaway { report });
+        }
+    }
+
+    /// Record the end of `segment`, advancing `cycle` past it.
+    fn record_segment(&self, cycle: &mut u64, segment: &CircuitSegment) {
+        *cycle += segment.insn_cycles as u64;
+        self.record_event(
+            *cycle,
+            ExecutionEventKind::Segment {
+                index: segment.index as u32,
+                po2: segment.po2 as u32,
+                exit_code: segment.exit_code,
+            },
+        );
+    }
+
+    /// Run the guest again from the same memory image, answering every syscall from the
+    /// transcript recorded by the first run, and check that both runs agree.
+    fn self_check(
+        &mut self,
+        expected: &ExecutorResult,
+        segment_limit_po2: usize,
+        session_limit: Option<u64>,
+    ) -> Result<()> {
+        let entries = self.transcript.as_ref().unwrap().borrow().clone();
+        self.check_replay = Some(RefCell::new(entries.into()));
+        // A run stopped at its deadline is re-run up to the same segment, and a run stopped at a
+        // breakpoint up to the same cycle.
+        let stop = match self.stopped_at {
+            Some(pc) => Some(SelfCheckStop::Breakpoint {
+                pc,
+                cycle: expected.user_cycles,
+            }),
+            None => (expected.exit_code == ExitCode::SystemSplit)
+                .then_some(SelfCheckStop::Split(expected.segments)),
+        };
+        let result = self.self_check_run(segment_limit_po2, session_limit, stop);
+        let remaining = self.check_replay.take().unwrap().into_inner().len();
+        let result = result.context("Self-check failed: re-execution did not complete")?;
+
+        check_outcomes(
+            &RunOutcome::from(expected),
+            &RunOutcome::from(&result),
+            remaining,
+        )
+    }
+
+    fn self_check_run(
+        &self,
+        segment_limit_po2: usize,
+        session_limit: Option<u64>,
+        stop: Option<SelfCheckStop>,
+    ) -> Result<ExecutorResult> {
+        let mut exec = Executor::new(self.image.clone(), self, self.env.input_digest, vec![]);
+        exec.traps(self.traps.clone());
+        exec.segment_po2_schedule(self.segment_po2_schedule());
+        match stop {
+            Some(SelfCheckStop::Split(stop_after)) => {
+                exec.stop_at_split(move |segments| segments == stop_after)
+            }
+            Some(SelfCheckStop::Breakpoint { pc, cycle }) => exec.breakpoints(
+                BTreeSet::from([pc]),
+                move |ctx| Ok(ctx.get_cycle() == cycle),
+            ),
+            None => (),
+        }
+        if let Some(guard) = self.env.stack_guard() {
+            exec.stack_guard(guard);
+        }
+        if self.env.linux_compat {
+            exec.linux_compat(self.program_end.unwrap());
+        }
+        exec.run(segment_limit_po2, session_limit, |_| Ok(()))
+    }
+
+    fn segment_po2_schedule(&self) -> Vec<usize> {
+        self.env
+            .segment_po2_schedule
+            .iter()
+            .map(|&po2| po2 as usize)
+            .collect()
+    }
+
+    /// The symbolizer for the guest ELF, if the executor was constructed from one.
+    fn symbolizer(&self) -> Option<&Rc<Symbolizer>> {
+        self.symbolizer
+            .get_or_init(|| {
+                let elf = self.elf.as_ref()?;
+                Symbolizer::new((**elf).as_ref()).ok().map(Rc::new)
+            })
+            .as_ref()
+    }
+
+    /// Add the guest function and source location at `pc` to an error that stopped execution.
+    pub(super) fn fault_report(&self, err: anyhow::Error, pc: ByteAddr) -> anyhow::Error {
+        match self.symbolizer().and_then(|s| s.describe(pc.0)) {
+            Some(location) => anyhow!("{err:#}\n    at {pc:?}: {location}"),
+            None => err,
+        }
+    }
+
+    fn dispatch(
         &self,
         syscall: &str,
         ctx: &mut dyn NewSyscallContext,
         into_guest: &mut [u32],
     ) -> Result<(u32, u32)> {
+        let cycle = self.cycle_offset + ctx.get_cycle();
         let mut ctx = ContextAdapter {
             ctx,
             syscall_table: self.syscall_table.clone(),
         };
-        self.syscall_table
+        let result = self
+            .syscall_table
             .get_syscall(syscall)
-            .context(format!("Unknown syscall: {syscall:?}"))?
+            .context(format!("Unknown syscall: {syscall:?}"))
+            .and_then(|handler| handler.borrow_mut().syscall(syscall, &mut ctx, into_guest));
+        if let Err(err) = &result {
+            self.record_event(
+                cycle,
+                ExecutionEventKind::SyscallError {
+                    name: syscall.to_string(),
+                    error: format!("{err:#}"),
+                },
+            );
+        }
+        result
+    }
+
+    /// Answer a syscall from the transcript being replayed.
+    fn replay_syscall(
+        &self,
+        replay: &RefCell<VecDeque<TranscriptEntry>>,
+        syscall: &str,
+        ctx: &mut dyn NewSyscallContext,
+        into_guest: &mut [u32],
+        inputs: &Digest,
+        dispatch_writes: bool,
+    ) -> Result<(u32, u32)> {
+        let entry = replay
             .borrow_mut()
-            .syscall(syscall, &mut ctx, into_guest)
+            .pop_front()
+            .with_context(|| format!("Syscall transcript exhausted at {syscall}"))?;
+        entry.check(syscall, inputs, into_guest.len())?;
+
+        if dispatch_writes && REPLAY_HANDLED.iter().any(|name| name.as_str() == syscall) {
+            let regs = self.dispatch(syscall, ctx, into_guest)?;
+            ensure!(
+                regs == entry.regs && into_guest == entry.to_guest.as_slice(),
+                "Host answer to {syscall} at cycle {} differs from the syscall transcript",
+                entry.cycle
+            );
+            return Ok(regs);
+        }
+
+        into_guest.copy_from_slice(&entry.to_guest);
+        Ok(entry.regs)
+    }
+}
+
+impl<'a> NewSyscall for ExecutorImpl<'a> {
+    fn syscall(
+        &self,
+        syscall: &str,
+        ctx: &mut dyn NewSyscallContext,
+        into_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        if let Some(manifest) = &self.syscall_manifest {
+            let fd = ctx.peek_register(REG_A3)?;
+            ensure!(
+                manifest.allows(syscall, fd),
+                "Syscall {syscall} is not in the syscall manifest of the guest"
+            );
+        }
+        // Reads made while re-running the guest for a self-check were already counted.
+        if let (Some(perf), None) = (&self.perf, &self.check_replay) {
+            if syscall == SYS_READ.as_str() {
+                let fd = ctx.peek_register(REG_A3)?;
+                let nbytes = ctx.peek_register(REG_A4)?;
+                perf.borrow_mut().on_read(fd, nbytes);
+            }
+        }
+        if let (Some(taint), None) = (&self.taint, &self.check_replay) {
+            taint.borrow_mut().on_syscall(syscall, ctx)?;
+        }
+        if self.transcript.is_none()
+            && self.replay.is_none()
+            && self.audit.is_none()
+            && self.check_replay.is_none()
+        {
+            return self.dispatch(syscall, ctx, into_guest);
+        }
+
+        let args = [REG_A3, REG_A4, REG_A5, REG_A6, REG_A7]
+            .into_iter()
+            .map(|idx| ctx.peek_register(idx))
+            .collect::<Result<Vec<_>>>()?;
+        let inputs = transcript::inputs_digest(into_guest.len(), &args);
+        if let Some(replay) = &self.check_replay {
+            return self.replay_syscall(replay, syscall, ctx, into_guest, &inputs, false);
+        }
+        let cycle = ctx.get_cycle();
+        let pc = ctx.get_pc();
+
+        let regs = match &self.replay {
+            Some(replay) => self.replay_syscall(replay, syscall, ctx, into_guest, &inputs, true)?,
+            None => self.dispatch(syscall, ctx, into_guest)?,
+        };
+
+        if let Some(transcript) = &self.transcript {
+            transcript.borrow_mut().push(TranscriptEntry::new(
+                syscall, inputs, cycle, into_guest, regs,
+            ));
+        }
+        if let Some(audit) = &self.audit {
+            audit
+                .borrow_mut()
+                .record(syscall, args[0], cycle, pc, into_guest);
+        }
+        Ok(regs)
     }
 }
 
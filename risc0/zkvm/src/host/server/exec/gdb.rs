@@ -0,0 +1,283 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A stub of the GDB remote serial protocol, for debugging a guest with `riscv32-unknown-elf-gdb`.
+
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, ToSocketAddrs},
+};
+
+use anyhow::{ensure, Context as _, Result};
+use risc0_binfmt::ExitCode;
+use risc0_zkvm_platform::syscall::reg_abi::REG_MAX;
+
+use super::executor::ExecutorImpl;
+
+/// The number GDB gives the pc of RV32, after the registers x0 to x31.
+const PC_REGNUM: usize = REG_MAX;
+
+/// Why the guest last stopped, as reported to the debugger.
+#[derive(Clone, Copy)]
+enum Stop {
+    /// At a breakpoint, a pause, or after a step.
+    Trap,
+    /// The guest halted with this exit code.
+    Exited(u32),
+    /// The executor failed, e.g. because the guest faulted.
+    Aborted,
+}
+
+impl Stop {
+    fn reply(self) -> String {
+        match self {
+            // SIGTRAP
+            Stop::Trap => "S05".to_string(),
+            // The exit status of a process is 8 bits.
+            Stop::Exited(code) => format!("W{:02x}", code & 0xff),
+            // SIGABRT
+            Stop::Aborted => "X06".to_string(),
+        }
+    }
+}
+
+/// Serves a debugger attached to an [ExecutorImpl] over the GDB remote serial protocol.
+///
+/// The stub supports reading registers, reading and writing memory, software and hardware
+/// breakpoints, single-stepping, and continuing, built on [ExecutorImpl::step] and
+/// [ExecutorImpl::add_breakpoint]. Each continue runs the executor, so the [Session]s of the
+/// guest are produced as usual, while single steps are not part of any session.
+///
+/// GDB should be given the ELF of the guest to read its symbols, e.g.
+///
+/// ```text
+/// $ riscv32-unknown-elf-gdb target/riscv-guest/.../method
+/// (gdb) target remote localhost:9000
+/// ```
+///
+/// [Session]: crate::Session
+pub struct GdbStub<'a, 'b> {
+    executor: &'b mut ExecutorImpl<'a>,
+    stop: Stop,
+}
+
+impl<'a, 'b> GdbStub<'a, 'b> {
+    /// Construct a stub debugging the guest of `executor` from where it stopped, or from its
+    /// entry point before it is first run.
+    pub fn new(executor: &'b mut ExecutorImpl<'a>) -> Self {
+        Self {
+            executor,
+            stop: Stop::Trap,
+        }
+    }
+
+    /// Listen on `addr`, and serve the first debugger to connect until it detaches or kills the
+    /// guest.
+    pub fn serve(&mut self, addr: impl ToSocketAddrs) -> Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        tracing::info!("Waiting for gdb on {}", listener.local_addr()?);
+        let (stream, peer) = listener.accept()?;
+        tracing::info!("gdb connected from {peer}");
+        stream.set_nodelay(true)?;
+        self.serve_connection(stream)
+    }
+
+    /// Serve the debugger connected over `stream` until it detaches, kills the guest, or
+    /// disconnects.
+    pub fn serve_connection<S: Read + Write>(&mut self, stream: S) -> Result<()> {
+        let mut conn = Connection { stream };
+        while let Some(packet) = conn.recv()? {
+            match packet.first() {
+                Some(b'k') => break,
+                Some(b'D') => {
+                    conn.send("OK")?;
+                    break;
+                }
+                _ => {
+                    let reply = self.handle(&packet).unwrap_or_else(|err| {
+                        tracing::debug!("gdb request failed: {err:?}");
+                        "E01".to_string()
+                    });
+                    conn.send(&reply)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle a packet, returning the reply. Unsupported packets get an empty reply.
+    fn handle(&mut self, packet: &[u8]) -> Result<String> {
+        let packet = std::str::from_utf8(packet)?;
+        let Some(cmd) = packet.chars().next() else {
+            return Ok(String::new());
+        };
+        let args = &packet[1..];
+        match cmd {
+            '?' => Ok(self.stop.reply()),
+            'g' => {
+                let mut regs = String::with_capacity((PC_REGNUM + 1) * 8);
+                for regnum in 0..=PC_REGNUM {
+                    regs += &hex::encode(self.register(regnum)?.to_le_bytes());
+                }
+                Ok(regs)
+            }
+            'p' => {
+                let regnum = usize::from_str_radix(args, 16)?;
+                Ok(hex::encode(self.register(regnum)?.to_le_bytes()))
+            }
+            'm' => {
+                let (addr, len) = parse_addr_len(args)?;
+                Ok(hex::encode(self.executor.peek_region(addr, len)?))
+            }
+            'M' => {
+                let (addr_len, data) = args.split_once(':').context("missing data")?;
+                let (addr, len) = parse_addr_len(addr_len)?;
+                let data = hex::decode(data)?;
+                ensure!(
+                    data.len() == len as usize,
+                    "data does not match length {len}"
+                );
+                self.executor.poke_region(addr, &data)?;
+                Ok("OK".to_string())
+            }
+            'Z' | 'z' => {
+                let mut fields = args.split(',');
+                // Software and hardware breakpoints are the same to the executor.
+                if !matches!(fields.next(), Some("0" | "1")) {
+                    return Ok(String::new());
+                }
+                let addr = u32::from_str_radix(fields.next().context("missing address")?, 16)?;
+                if cmd == 'Z' {
+                    self.executor.add_breakpoint(addr);
+                } else {
+                    self.executor.remove_breakpoint(addr);
+                }
+                Ok("OK".to_string())
+            }
+            'c' | 's' => {
+                // A guest that exited stays exited.
+                if let Stop::Trap = self.stop {
+                    let stop = if cmd == 'c' {
+                        self.resume()
+                    } else {
+                        self.step()
+                    };
+                    self.stop = stop.unwrap_or_else(|err| {
+                        tracing::error!("Guest aborted: {err:?}");
+                        Stop::Aborted
+                    });
+                }
+                Ok(self.stop.reply())
+            }
+            'H' => Ok("OK".to_string()),
+            'q' if args.starts_with("Supported") => Ok("PacketSize=1000".to_string()),
+            'q' if args == "Attached" => Ok("1".to_string()),
+            _ => Ok(String::new()),
+        }
+    }
+
+    fn register(&self, regnum: usize) -> Result<u32> {
+        match regnum {
+            PC_REGNUM => Ok(self.executor.pc()),
+            regnum => self.executor.peek_register(regnum),
+        }
+    }
+
+    fn resume(&mut self) -> Result<Stop> {
+        let session = self.executor.run()?;
+        Ok(match session.exit_code {
+            ExitCode::Halted(code) => Stop::Exited(code),
+            _ => Stop::Trap,
+        })
+    }
+
+    fn step(&mut self) -> Result<Stop> {
+        let step = self.executor.step()?;
+        Ok(match step.exit_code {
+            Some(ExitCode::Halted(code)) => Stop::Exited(code),
+            _ => Stop::Trap,
+        })
+    }
+}
+
+/// Parse the `addr,length` arguments of a memory packet.
+fn parse_addr_len(args: &str) -> Result<(u32, u32)> {
+    let (addr, len) = args.split_once(',').context("missing length")?;
+    Ok((
+        u32::from_str_radix(addr, 16)?,
+        u32::from_str_radix(len, 16)?,
+    ))
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// The packets of a connection to a debugger, acknowledged as they are received.
+struct Connection<S> {
+    stream: S,
+}
+
+impl<S: Read + Write> Connection<S> {
+    /// Receive the next packet, or `None` if the debugger disconnected.
+    fn recv(&mut self) -> Result<Option<Vec<u8>>> {
+        loop {
+            // Acknowledgements and interrupts outside of packets are ignored, as the guest only
+            // runs while the debugger waits for a reply.
+            match self.read_byte()? {
+                None => return Ok(None),
+                Some(b'$') => (),
+                Some(_) => continue,
+            }
+            let mut data = Vec::new();
+            loop {
+                match self
+                    .read_byte()?
+                    .context("Connection closed within a packet")?
+                {
+                    b'#' => break,
+                    byte => data.push(byte),
+                }
+            }
+            let mut expected = [0u8; 2];
+            self.stream.read_exact(&mut expected)?;
+            let expected = u8::from_str_radix(std::str::from_utf8(&expected)?, 16)?;
+            if checksum(&data) == expected {
+                self.stream.write_all(b"+")?;
+                return Ok(Some(data));
+            }
+            self.stream.write_all(b"-")?;
+            self.stream.flush()?;
+        }
+    }
+
+    /// Send a packet, until the debugger acknowledges it.
+    fn send(&mut self, data: &str) -> Result<()> {
+        loop {
+            write!(self.stream, "${data}#{:02x}", checksum(data.as_bytes()))?;
+            self.stream.flush()?;
+            if self.read_byte()? != Some(b'-') {
+                return Ok(());
+            }
+        }
+    }
+
+    fn read_byte(&mut self) -> Result<Option<u8>> {
+        let mut byte = [0u8];
+        Ok(match self.stream.read(&mut byte)? {
+            0 => None,
+            _ => Some(byte[0]),
+        })
+    }
+}
@@ -0,0 +1,245 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Support for reporting how often the guest loads from and stores to each page of memory.
+//!
+//! Every segment pays a fixed number of cycles to page in each page it touches, and again to page
+//! out each page it modifies. The report attributes the loads and stores of the guest, along with
+//! the resulting page faults, to the ELF sections and symbols that live on each page, so that
+//! guest authors can see which data structures dominate paging costs.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    ops::Range,
+};
+
+use anyhow::Result;
+use elf::{
+    abi::{SHF_ALLOC, STT_FUNC, STT_OBJECT},
+    endian::LittleEndian,
+    ElfBytes,
+};
+use risc0_circuit_rv32im::prove::emu::exec::{PageAccessCounts, PageFaultCounts};
+use risc0_zkvm_platform::{
+    memory::{PAGE_TABLE, STACK_TOP, SYSTEM},
+    PAGE_SIZE,
+};
+use rustc_demangle::demangle;
+
+/// The maximum number of symbols listed for each page.
+const MAX_SYMBOLS: usize = 3;
+
+/// Maps pages of guest memory to the ELF sections and symbols they contain.
+#[derive(Default)]
+pub(crate) struct PageHeatmap {
+    sections: Vec<(Range<u32>, String)>,
+    symbols: Vec<(Range<u32>, String)>,
+    elf_end: u32,
+}
+
+impl PageHeatmap {
+    pub(crate) fn new(elf_data: &[u8]) -> Result<Self> {
+        let elf = ElfBytes::<LittleEndian>::minimal_parse(elf_data)?;
+        let mut heatmap = Self::default();
+
+        if let (Some(headers), Some(strtab)) = elf.section_headers_with_strtab()? {
+            for header in headers.iter() {
+                if header.sh_flags & SHF_ALLOC as u64 == 0 || header.sh_size == 0 {
+                    continue;
+                }
+                let start = header.sh_addr as u32;
+                let end = start + header.sh_size as u32;
+                let name = strtab.get(header.sh_name as usize)?;
+                heatmap.sections.push((start..end, name.to_string()));
+                heatmap.elf_end = heatmap.elf_end.max(end);
+            }
+        }
+
+        if let Some((symtab, strtab)) = elf.symbol_table()? {
+            for sym in symtab {
+                let symtype = sym.st_symtype();
+                if (symtype != STT_FUNC && symtype != STT_OBJECT) || sym.st_size == 0 {
+                    continue;
+                }
+                let start = sym.st_value as u32;
+                let name = strtab.get(sym.st_name as usize)?;
+                heatmap.symbols.push((
+                    start..start + sym.st_size as u32,
+                    demangle(name).to_string(),
+                ));
+            }
+        }
+        heatmap.symbols.sort_by_key(|(range, _)| range.start);
+
+        Ok(heatmap)
+    }
+
+    /// Return the name of the memory region containing the given page.
+    fn region(&self, page: &Range<u32>) -> String {
+        if let Some((_, name)) = self
+            .sections
+            .iter()
+            .find(|(range, _)| overlaps(range, page))
+        {
+            return name.clone();
+        }
+        let addr = page.start as usize;
+        if PAGE_TABLE.start() <= addr && addr < PAGE_TABLE.end() {
+            "page table".to_string()
+        } else if SYSTEM.start() <= addr && addr < SYSTEM.end() {
+            "system".to_string()
+        } else if page.start < STACK_TOP {
+            "stack".to_string()
+        } else if self.elf_end != 0 && page.start >= self.elf_end {
+            "heap".to_string()
+        } else {
+            "unknown".to_string()
+        }
+    }
+
+    /// Return the names of the symbols on the given page.
+    fn symbols(&self, page: &Range<u32>) -> String {
+        let names: Vec<_> = self
+            .symbols
+            .iter()
+            .filter(|(range, _)| overlaps(range, page))
+            .map(|(_, name)| name.as_str())
+            .collect();
+        if names.len() > MAX_SYMBOLS {
+            format!(
+                "{} (+{} more)",
+                names[..MAX_SYMBOLS].join(" "),
+                names.len() - MAX_SYMBOLS
+            )
+        } else {
+            names.join(" ")
+        }
+    }
+
+    /// Produce a tab-separated report of the loads, stores and page faults on each page, with
+    /// the most frequently accessed pages first.
+    pub(crate) fn report(
+        &self,
+        accesses: &BTreeMap<u32, PageAccessCounts>,
+        faults: &BTreeMap<u32, PageFaultCounts>,
+    ) -> String {
+        let mut pages: Vec<_> = accesses
+            .keys()
+            .chain(faults.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|page_idx| {
+                let access = accesses.get(page_idx).copied().unwrap_or_default();
+                let fault = faults.get(page_idx).copied().unwrap_or_default();
+                (*page_idx, access, fault)
+            })
+            .collect();
+        pages.sort_by_key(|(page_idx, access, fault)| {
+            (
+                Reverse(access.loads + access.stores),
+                Reverse(fault.reads + fault.writes),
+                *page_idx,
+            )
+        });
+
+        let mut report =
+            String::from("page\taddress\tregion\tloads\tstores\tpage_ins\tpage_outs\tsymbols\n");
+        for (page_idx, access, fault) in pages {
+            let start = page_idx * PAGE_SIZE as u32;
+            let page = start..start + PAGE_SIZE as u32;
+            writeln!(
+                report,
+                "0x{page_idx:05x}\t0x{start:08x}\t{}\t{}\t{}\t{}\t{}\t{}",
+                self.region(&page),
+                access.loads,
+                access.stores,
+                fault.reads,
+                fault.writes,
+                self.symbols(&page)
+            )
+            .unwrap();
+        }
+        report
+    }
+}
+
+fn overlaps(a: &Range<u32>, b: &Range<u32>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use risc0_circuit_rv32im::prove::emu::exec::{PageAccessCounts, PageFaultCounts};
+    use risc0_zkvm_platform::{memory::PAGE_TABLE, PAGE_SIZE};
+
+    use super::PageHeatmap;
+
+    #[test]
+    fn report() {
+        let heatmap = PageHeatmap {
+            sections: vec![(0x0020_0800..0x0020_1000, ".text".to_string())],
+            symbols: vec![
+                (0x0020_0800..0x0020_0900, "main".to_string()),
+                (0x0020_0900..0x0020_0c00, "helper".to_string()),
+            ],
+            elf_end: 0x0020_1000,
+        };
+        let text_idx = 0x0020_0800 / PAGE_SIZE as u32;
+        let table_idx = PAGE_TABLE.start() as u32 / PAGE_SIZE as u32;
+        let data_idx = 0x0030_0000 / PAGE_SIZE as u32;
+        let accesses = BTreeMap::from([
+            (
+                text_idx,
+                PageAccessCounts {
+                    loads: 2,
+                    stores: 0,
+                },
+            ),
+            (
+                data_idx,
+                PageAccessCounts {
+                    loads: 40,
+                    stores: 30,
+                },
+            ),
+        ]);
+        let faults = BTreeMap::from([
+            (
+                text_idx,
+                PageFaultCounts {
+                    reads: 1,
+                    writes: 0,
+                },
+            ),
+            (
+                table_idx,
+                PageFaultCounts {
+                    reads: 3,
+                    writes: 2,
+                },
+            ),
+        ]);
+
+        let report = heatmap.report(&accesses, &faults);
+        let lines: Vec<_> = report.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].contains("heap\t40\t30\t0\t0"));
+        assert!(lines[2].ends_with(".text\t2\t0\t1\t0\tmain helper"));
+        assert!(lines[3].contains("page table\t0\t0\t3\t2"));
+    }
+}
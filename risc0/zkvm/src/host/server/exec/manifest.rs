@@ -0,0 +1,127 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+
+use anyhow::{ensure, Result};
+use elf::{endian::LittleEndian, ElfBytes};
+use risc0_zkvm_platform::{
+    fileno,
+    syscall::nr::{SYS_ALLOC_FAILED, SYS_PANIC, SYS_PANIC_AT, SYS_RANDOM, SYS_WRITE},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{guest::syscall_manifest_digest, sha::Digest};
+
+/// The name of the ELF section that holds the manifest.
+pub(crate) const MANIFEST_SECTION: &str = ".risc0.syscalls";
+
+/// The syscalls that a guest program is allowed to make, as declared with
+/// [syscall_manifest!][crate::syscall_manifest].
+///
+/// The manifest is stored in a section of the guest ELF that is loaded into the memory image, so
+/// the image ID of the guest commits to it, and the guest commits its
+/// [digest][SyscallManifest::digest] to the start of the journal. A verifier can therefore check
+/// which syscalls the guest of a receipt declared with [SyscallManifest::verify_journal], without
+/// knowing its ELF. The manifest is only enforced by the executor, so this bounds what an honest
+/// host provided to the guest.
+///
+/// The guest runtime may always panic, report running out of heap, draw randomness, and write to
+/// the journal, whether or not the manifest lists [SYS_PANIC], [SYS_PANIC_AT], [SYS_ALLOC_FAILED],
+/// [SYS_RANDOM], and [SYS_WRITE].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyscallManifest {
+    names: BTreeSet<String>,
+}
+
+impl SyscallManifest {
+    /// Construct a manifest that allows the given syscalls.
+    pub fn new<S: AsRef<str>>(names: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            names: names
+                .into_iter()
+                .map(|name| name.as_ref().to_string())
+                .collect(),
+        }
+    }
+
+    /// Read the manifest embedded in a guest ELF, or `None` if the guest does not declare one.
+    pub fn from_elf(elf: &[u8]) -> Result<Option<Self>> {
+        let elf = ElfBytes::<LittleEndian>::minimal_parse(elf)?;
+        let Some(header) = elf.section_header_by_name(MANIFEST_SECTION)? else {
+            return Ok(None);
+        };
+        let (data, _) = elf.section_data(&header)?;
+        let names = std::str::from_utf8(data)?
+            .split(['\n', '\0'])
+            .filter(|name| !name.is_empty());
+        Ok(Some(Self::new(names)))
+    }
+
+    /// The names of the allowed syscalls, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    /// Whether the guest may make `syscall`, whose first argument register holds `fd`.
+    pub(crate) fn allows(&self, syscall: &str, fd: u32) -> bool {
+        self.names.contains(syscall)
+            || syscall == SYS_PANIC.as_str()
+            || syscall == SYS_PANIC_AT.as_str()
+            || syscall == SYS_ALLOC_FAILED.as_str()
+            || syscall == SYS_RANDOM.as_str()
+            || (syscall == SYS_WRITE.as_str() && fd == fileno::JOURNAL)
+    }
+
+    /// A digest that commits to the allowed syscalls.
+    pub fn digest(&self) -> Digest {
+        syscall_manifest_digest(self.names())
+    }
+
+    /// Check that `journal` was committed by a guest that declared this manifest with
+    /// [syscall_manifest!][crate::syscall_manifest], and return the rest of the journal, which
+    /// follows the digest of the manifest.
+    pub fn verify_journal<'a>(&self, journal: &'a [u8]) -> Result<&'a [u8]> {
+        let digest = self.digest();
+        ensure!(
+            journal.starts_with(digest.as_bytes()),
+            "journal does not start with the digest of the syscall manifest"
+        );
+        Ok(&journal[digest.as_bytes().len()..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkvm_platform::{
+        fileno,
+        syscall::nr::{SYS_READ, SYS_WRITE},
+    };
+
+    use super::SyscallManifest;
+
+    #[test]
+    fn allows() {
+        let manifest = SyscallManifest::new([SYS_READ.as_str()]);
+        assert!(manifest.allows(SYS_READ.as_str(), fileno::STDIN));
+        assert!(manifest.allows(SYS_WRITE.as_str(), fileno::JOURNAL));
+        assert!(!manifest.allows(SYS_WRITE.as_str(), fileno::STDOUT));
+
+        // The digest does not depend on the order of the names.
+        let other = SyscallManifest::new([SYS_WRITE.as_str(), SYS_READ.as_str()]);
+        let reversed = SyscallManifest::new([SYS_READ.as_str(), SYS_WRITE.as_str()]);
+        assert_eq!(other.digest(), reversed.digest());
+        assert_ne!(manifest.digest(), other.digest());
+    }
+}
@@ -18,10 +18,30 @@
 //! [crate::Session] contains one or more [crate::Segment]s, each of which
 //! contains an execution trace of the specified program.
 
+pub(crate) mod batch;
+pub(crate) mod cache;
 pub(crate) mod compose;
+pub(crate) mod cycle_audit;
+pub(crate) mod diff;
+pub(crate) mod dual;
 pub(crate) mod executor;
+#[cfg(feature = "gdb")]
+pub(crate) mod gdb;
+pub(crate) mod heatmap;
+pub(crate) mod manifest;
+pub(crate) mod patches;
+pub(crate) mod perf;
+pub(crate) mod pool;
 pub(crate) mod profiler;
 mod proto;
+pub(crate) mod replay;
+pub(crate) mod runaway;
+#[cfg(feature = "rv64im")]
+pub(crate) mod rv64;
+pub(crate) mod snapshot;
+pub(crate) mod step;
+pub(crate) mod symbolizer;
 pub(crate) mod syscall;
+pub(crate) mod taint;
 #[cfg(test)]
 mod tests;
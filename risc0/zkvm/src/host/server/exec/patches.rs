@@ -0,0 +1,145 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Detection of guest dependencies that compute in software what an accelerator can do, because
+//! they were not patched with the accelerated fork of the crate.
+
+use std::fmt;
+
+use anyhow::Result;
+use elf::{abi::STT_FUNC, endian::LittleEndian, ElfBytes};
+use rustc_demangle::demangle;
+use serde::{Deserialize, Serialize};
+
+/// A crate with an accelerated fork, and the functions only found in its software version.
+struct Accelerated {
+    name: &'static str,
+    patch: &'static str,
+    // Prefixes of the demangled names of the functions of the software version.
+    software: &'static [&'static str],
+}
+
+const ACCELERATED: [Accelerated; 2] = [
+    Accelerated {
+        name: "sha2",
+        patch: "https://github.com/risc0/RustCrypto-hashes",
+        software: &["sha2::sha256::soft::", "sha2::sha256_utils::"],
+    },
+    Accelerated {
+        name: "k256",
+        patch: "https://github.com/risc0/RustCrypto-elliptic-curves",
+        software: &[
+            "k256::arithmetic::field::field_10x26::",
+            "k256::arithmetic::scalar::wide32::",
+        ],
+    },
+];
+
+/// A dependency of the guest that was not patched to use an accelerator, found by
+/// [missing_patches].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MissingPatch {
+    /// The name of the crate.
+    pub crate_name: String,
+    /// The repository of the accelerated fork of the crate, to add to `[patch.crates-io]`.
+    pub patch: String,
+    /// The functions of the guest computing in software what the accelerator can do.
+    pub functions: Vec<String>,
+}
+
+impl fmt::Display for MissingPatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is not patched to use the accelerator ({} software functions, e.g. {}); patch it \
+             with {}",
+            self.crate_name,
+            self.functions.len(),
+            self.functions.first().map_or("none", String::as_str),
+            self.patch
+        )
+    }
+}
+
+/// Inspect the symbol table of a guest ELF for functions of crates that compute in software what
+/// an accelerator can do, such as the software SHA-256 compression of `sha2`, and report the
+/// accelerated forks to patch them with.
+///
+/// Functions inlined into their callers have no symbol of their own, so a missing patch can go
+/// unreported if all of its software functions were inlined. At runtime, software hashing is
+/// also reported by
+/// [ExecutorEnvBuilder::perf_warnings][crate::ExecutorEnvBuilder::perf_warnings].
+pub fn missing_patches(elf: &[u8]) -> Result<Vec<MissingPatch>> {
+    let elf = ElfBytes::<LittleEndian>::minimal_parse(elf)?;
+    let mut functions = Vec::new();
+    if let Some((symtab, strtab)) = elf.symbol_table()? {
+        for sym in symtab {
+            if sym.st_symtype() == STT_FUNC {
+                let name = strtab.get(sym.st_name as usize)?;
+                functions.push(format!("{:#}", demangle(name)));
+            }
+        }
+    }
+    Ok(find_missing(&functions))
+}
+
+fn find_missing(functions: &[String]) -> Vec<MissingPatch> {
+    ACCELERATED
+        .iter()
+        .filter_map(|krate| {
+            let mut found: Vec<_> = functions
+                .iter()
+                .filter(|name| krate.software.iter().any(|prefix| name.starts_with(prefix)))
+                .cloned()
+                .collect();
+            found.sort();
+            found.dedup();
+            (!found.is_empty()).then(|| MissingPatch {
+                crate_name: krate.name.to_string(),
+                patch: krate.patch.to_string(),
+                functions: found,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkvm_methods::MULTI_TEST_ELF;
+
+    use super::{find_missing, missing_patches};
+
+    #[test]
+    fn software_functions() {
+        let functions = [
+            "sha2::sha256::soft::compress",
+            "sha2::sha256::compress256",
+            "k256::arithmetic::field::field_8x32_risc0::FieldElement8x32R0::mul",
+            "guest::main",
+        ]
+        .map(String::from);
+        let missing = find_missing(&functions);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].crate_name, "sha2");
+        assert_eq!(missing[0].functions, ["sha2::sha256::soft::compress"]);
+    }
+
+    #[test]
+    fn elf() {
+        // The test guest does not depend on k256.
+        let missing = missing_patches(MULTI_TEST_ELF).unwrap();
+        assert!(missing.iter().all(|patch| patch.crate_name != "k256"));
+        assert!(missing_patches(b"not an elf").is_err());
+    }
+}
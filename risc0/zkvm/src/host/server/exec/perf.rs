@@ -0,0 +1,334 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Detection of guest code patterns that are known to waste cycles in the zkVM.
+
+use std::{collections::HashMap, fmt, ops::Range};
+
+use anyhow::Result;
+use elf::{abi::PT_LOAD, endian::LittleEndian, ElfBytes};
+use risc0_circuit_rv32im::trace::{TraceCallback, TraceEvent};
+use risc0_zkvm_platform::{PAGE_SIZE, WORD_SIZE};
+use serde::{Deserialize, Serialize};
+
+use super::symbolizer::Symbolizer;
+
+/// Backward branches taken at least this many times are considered the end of a hot loop.
+const HOT_LOOP_ITERATIONS: u64 = 10_000;
+
+/// Reads of fewer bytes than this are considered small.
+const SMALL_READ_BYTES: u32 = 64;
+
+/// Descriptors read with at least this many small reads are reported.
+const SMALL_READ_CALLS: u64 = 1_000;
+
+/// Functions computing SHA-256 in software with at least this many instructions are reported.
+const SOFTWARE_HASH_INSTRUCTIONS: u64 = 100_000;
+
+/// Guests that zero at least this many bytes of bss are reported.
+const BSS_ZEROING_BYTES: u64 = 64 * 1024;
+
+/// Functions of the `sha2` crate that compute SHA-256 without the accelerator.
+const SOFTWARE_SHA256: [&str; 2] = ["sha2::sha256::soft::", "sha2::sha256::compress256"];
+
+/// A pattern in the guest that is known to waste cycles, found by
+/// [ExecutorEnvBuilder::perf_warnings][crate::ExecutorEnvBuilder::perf_warnings].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum PerfWarning {
+    /// A hot loop whose code crosses a page boundary, so that every segment it runs in pages in
+    /// two pages of code rather than one.
+    PageCrossingLoop {
+        /// Address of the branch that closes the loop.
+        branch_pc: u32,
+        /// Address of the start of the loop.
+        target_pc: u32,
+        /// Number of times the branch was taken.
+        iterations: u64,
+        /// Function containing the loop, if known.
+        function: Option<String>,
+    },
+
+    /// Many small `sys_read` calls on a file descriptor, each of which costs a syscall round
+    /// trip regardless of its size.
+    SmallReads {
+        /// The file descriptor.
+        fd: u32,
+        /// Number of reads of fewer than 64 bytes.
+        calls: u64,
+        /// Number of bytes requested by those reads.
+        bytes: u64,
+    },
+
+    /// SHA-256 computed in software rather than with the accelerator.
+    SoftwareHash {
+        /// The function computing SHA-256.
+        function: String,
+        /// Number of instructions executed in the function.
+        instructions: u64,
+    },
+
+    /// The guest zeroed memory in bss, which the loader already zeroes.
+    BssZeroing {
+        /// Number of bytes of bss zeroed before they were otherwise written.
+        bytes: u64,
+    },
+}
+
+impl fmt::Display for PerfWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PageCrossingLoop {
+                branch_pc,
+                target_pc,
+                iterations,
+                function,
+            } => {
+                write!(
+                    f,
+                    "loop from 0x{target_pc:08x} to 0x{branch_pc:08x} crosses a page boundary and ran {iterations} times"
+                )?;
+                if let Some(function) = function {
+                    write!(f, " in {function}")?;
+                }
+                write!(f, "; align it to avoid paging in two code pages")
+            }
+            Self::SmallReads { fd, calls, bytes } => write!(
+                f,
+                "{calls} reads of fd {fd} averaged {} bytes; read larger buffers at once",
+                bytes / calls
+            ),
+            Self::SoftwareHash {
+                function,
+                instructions,
+            } => write!(
+                f,
+                "{function} computed SHA-256 in software for {instructions} instructions; use the \
+                 accelerated sha2 crate or risc0_zkvm::sha instead"
+            ),
+            Self::BssZeroing { bytes } => write!(
+                f,
+                "guest zeroed {bytes} bytes of bss, which is already zeroed when the program is loaded"
+            ),
+        }
+    }
+}
+
+/// Memory that the loader zeroes, with a bit per word recording whether it has been written.
+struct Bss {
+    range: Range<u32>,
+    written: Vec<u64>,
+}
+
+impl Bss {
+    fn new(range: Range<u32>) -> Self {
+        let words = (range.end - range.start) as usize / WORD_SIZE + 1;
+        Self {
+            range,
+            written: vec![0; words.div_ceil(64)],
+        }
+    }
+
+    /// Mark the word at `addr` as written, and return whether it was written before.
+    fn write(&mut self, addr: u32) -> bool {
+        let idx = (addr - self.range.start) as usize / WORD_SIZE;
+        let (word, bit) = (idx / 64, 1 << (idx % 64));
+        let written = self.written[word] & bit != 0;
+        self.written[word] |= bit;
+        written
+    }
+}
+
+/// Collects the statistics behind [PerfWarning]s from the trace of an execution.
+#[derive(Default)]
+pub(crate) struct PerfAnalyzer {
+    bss: Vec<Bss>,
+    prev: Option<(u32, u32)>,
+    back_branches: HashMap<(u32, u32), u64>,
+    insn_counts: HashMap<u32, u64>,
+    small_reads: HashMap<u32, (u64, u64)>,
+    bss_zeroed: u64,
+}
+
+impl PerfAnalyzer {
+    /// Construct a [PerfAnalyzer] for the program in `elf`.
+    ///
+    /// Without the ELF, zeroing of bss cannot be detected.
+    pub(crate) fn new(elf: &[u8]) -> Result<Self> {
+        let mut analyzer = Self::default();
+        let elf = ElfBytes::<LittleEndian>::minimal_parse(elf)?;
+        for segment in elf.segments().into_iter().flatten() {
+            if segment.p_type == PT_LOAD && segment.p_memsz > segment.p_filesz {
+                let start = (segment.p_vaddr + segment.p_filesz) as u32;
+                let end = (segment.p_vaddr + segment.p_memsz) as u32;
+                analyzer.bss.push(Bss::new(start..end));
+            }
+        }
+        Ok(analyzer)
+    }
+
+    /// Record a `sys_read` of `nbytes` bytes from `fd`.
+    pub(crate) fn on_read(&mut self, fd: u32, nbytes: u32) {
+        if nbytes < SMALL_READ_BYTES {
+            let (calls, bytes) = self.small_reads.entry(fd).or_default();
+            *calls += 1;
+            *bytes += nbytes as u64;
+        }
+    }
+
+    fn on_insn(&mut self, pc: u32, insn: u32) {
+        *self.insn_counts.entry(pc).or_default() += 1;
+        if let Some((prev_pc, prev_insn)) = self.prev {
+            if pc < prev_pc && is_jump(prev_insn) && page(pc) != page(prev_pc) {
+                *self.back_branches.entry((prev_pc, pc)).or_default() += 1;
+            }
+        }
+        self.prev = Some((pc, insn));
+    }
+
+    fn on_store(&mut self, addr: u32, region: &[u8]) {
+        let end = addr + region.len() as u32;
+        let Some(bss) = self.bss.iter_mut().find(|bss| bss.range.contains(&addr)) else {
+            return;
+        };
+        let zero = region.iter().all(|&byte| byte == 0);
+        let mut word = addr - addr % WORD_SIZE as u32;
+        while word < end.min(bss.range.end) {
+            if !bss.write(word) && zero {
+                let start = word.max(addr);
+                let stop = (word + WORD_SIZE as u32).min(end);
+                self.bss_zeroed += (stop - start) as u64;
+            }
+            word += WORD_SIZE as u32;
+        }
+    }
+
+    /// The warnings for the execution traced so far.
+    pub(crate) fn warnings(&self, symbolizer: Option<&Symbolizer>) -> Vec<PerfWarning> {
+        let function = |pc: u32| symbolizer.and_then(|s| s.function(pc)).map(str::to_string);
+        let mut warnings = Vec::new();
+
+        let mut loops: Vec<_> = self
+            .back_branches
+            .iter()
+            .filter(|(_, &iterations)| iterations >= HOT_LOOP_ITERATIONS)
+            .collect();
+        loops.sort();
+        for (&(branch_pc, target_pc), &iterations) in loops {
+            warnings.push(PerfWarning::PageCrossingLoop {
+                branch_pc,
+                target_pc,
+                iterations,
+                function: function(branch_pc),
+            });
+        }
+
+        let mut reads: Vec<_> = self
+            .small_reads
+            .iter()
+            .filter(|(_, &(calls, _))| calls >= SMALL_READ_CALLS)
+            .collect();
+        reads.sort();
+        for (&fd, &(calls, bytes)) in reads {
+            warnings.push(PerfWarning::SmallReads { fd, calls, bytes });
+        }
+
+        let mut hashing: HashMap<String, u64> = HashMap::new();
+        for (&pc, &count) in &self.insn_counts {
+            if let Some(name) = function(pc) {
+                if SOFTWARE_SHA256
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix))
+                {
+                    *hashing.entry(name).or_default() += count;
+                }
+            }
+        }
+        let mut hashing: Vec<_> = hashing
+            .into_iter()
+            .filter(|(_, instructions)| *instructions >= SOFTWARE_HASH_INSTRUCTIONS)
+            .collect();
+        hashing.sort();
+        for (function, instructions) in hashing {
+            warnings.push(PerfWarning::SoftwareHash {
+                function,
+                instructions,
+            });
+        }
+
+        if self.bss_zeroed >= BSS_ZEROING_BYTES {
+            warnings.push(PerfWarning::BssZeroing {
+                bytes: self.bss_zeroed,
+            });
+        }
+        warnings
+    }
+}
+
+impl TraceCallback for PerfAnalyzer {
+    fn trace_callback(&mut self, event: TraceEvent) -> Result<()> {
+        match event {
+            TraceEvent::InstructionStart { pc, insn, .. } => self.on_insn(pc, insn),
+            TraceEvent::MemorySet { addr, region } => self.on_store(addr, &region),
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+fn page(addr: u32) -> u32 {
+    addr / PAGE_SIZE as u32
+}
+
+/// Whether `insn` is a conditional branch, or a jump that does not save a return address.
+fn is_jump(insn: u32) -> bool {
+    const BRANCH: u32 = 0b1100011;
+    const JAL: u32 = 0b1101111;
+    let opcode = insn & 0x7f;
+    let rd = (insn >> 7) & 0x1f;
+    opcode == BRANCH || (opcode == JAL && rd == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PerfAnalyzer, PerfWarning};
+
+    #[test]
+    fn page_crossing_loop() {
+        // beq x0, x0, -8
+        const BEQ_BACK: u32 = 0xfe000ce3;
+        let mut analyzer = PerfAnalyzer::default();
+        for _ in 0..10_000 {
+            analyzer.on_insn(0x1ffc, 0x00000013);
+            analyzer.on_insn(0x2000, BEQ_BACK);
+        }
+        // A loop within a single page is not reported.
+        for _ in 0..10_000 {
+            analyzer.on_insn(0x3000, 0x00000013);
+            analyzer.on_insn(0x3004, BEQ_BACK);
+        }
+
+        let warnings = analyzer.warnings(None);
+        assert_eq!(
+            warnings,
+            vec![PerfWarning::PageCrossingLoop {
+                branch_pc: 0x2000,
+                target_pc: 0x1ffc,
+                // The first pass through the loop does not take the branch.
+                iterations: 9_999,
+                function: None,
+            }]
+        );
+    }
+}
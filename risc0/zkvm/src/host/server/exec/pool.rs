@@ -0,0 +1,151 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::BTreeMap,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc},
+    thread,
+};
+
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+
+use super::{batch::SendSession, executor::ExecutorImpl};
+use crate::{ExecutorEnv, ExecutorEnvBuilder, Session};
+
+type Service = Arc<dyn Fn(Bytes) -> Result<Bytes> + Send + Sync>;
+
+/// Runs many sessions concurrently on a dedicated thread pool, sharing host-side services, such
+/// as database or RPC clients, between them.
+///
+/// Services are registered once with [ExecutorPool::add_service], and added to the environment
+/// of every session as an [io_callback][ExecutorEnvBuilder::io_callback]. Everything else is
+/// isolated per session: each session has its own [ExecutorEnv], built on the thread that runs
+/// it, so its file descriptors, input, and assumptions are never seen by another session.
+///
+/// # Example
+///
+/// ```
+/// use risc0_zkvm::ExecutorPool;
+/// use risc0_zkvm_methods::{multi_test::MultiTestSpec, MULTI_TEST_ELF};
+///
+/// let pool = ExecutorPool::new(2).unwrap();
+/// let handle = pool.spawn(MULTI_TEST_ELF, |env| {
+///     env.write(&MultiTestSpec::DoNothing)?;
+///     Ok(())
+/// });
+/// let session = handle.join().unwrap();
+/// ```
+pub struct ExecutorPool {
+    pool: rayon::ThreadPool,
+    services: BTreeMap<String, Service>,
+}
+
+impl ExecutorPool {
+    /// Construct a pool running up to `threads` sessions at once, or one per CPU if `threads` is
+    /// zero.
+    pub fn new(threads: usize) -> Result<Self> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|idx| format!("executor-pool-{idx}"))
+            .build()?;
+        Ok(Self {
+            pool,
+            services: BTreeMap::new(),
+        })
+    }
+
+    /// The number of sessions the pool runs at once.
+    pub fn threads(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+
+    /// Share `service` between the sessions spawned from now on, answering their guests on
+    /// `channel`, as with [ExecutorEnvBuilder::io_callback].
+    ///
+    /// The service is called concurrently by the sessions running at the same time. A session
+    /// can replace it by adding its own handler for the same channel.
+    pub fn add_service(
+        &mut self,
+        channel: impl AsRef<str>,
+        service: impl Fn(Bytes) -> Result<Bytes> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.services
+            .insert(channel.as_ref().to_string(), Arc::new(service));
+        self
+    }
+
+    /// Run the guest program in `elf` in a new session on the pool.
+    ///
+    /// `make_env` is called on the thread running the session, with a builder that has the
+    /// shared services added, to add the input and handlers of this session.
+    pub fn spawn<F>(&self, elf: impl Into<Arc<[u8]>>, make_env: F) -> SessionHandle
+    where
+        F: FnOnce(&mut ExecutorEnvBuilder<'static>) -> Result<()> + Send + 'static,
+    {
+        let elf: Arc<[u8]> = elf.into();
+        let services = self.services.clone();
+        let (sender, receiver) = mpsc::sync_channel(1);
+        self.pool.spawn(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let mut builder = ExecutorEnv::builder();
+                for (channel, service) in services {
+                    builder.io_callback(channel, move |from_guest| service(from_guest));
+                }
+                make_env(&mut builder)?;
+                let env = builder.build()?;
+                ExecutorImpl::from_owned_elf(env, elf)?
+                    .run()
+                    .map(SendSession::new)
+            }));
+            // The handle may have been dropped, if nobody is waiting on the session.
+            let _ = sender.send(result);
+        });
+        SessionHandle { receiver }
+    }
+
+    /// Run the guest program in `elf` once for each item of `make_envs`, returning the result
+    /// of each session in the same order.
+    pub fn run_all<F, I>(&self, elf: &[u8], make_envs: I) -> Vec<Result<Session>>
+    where
+        I: IntoIterator<Item = F>,
+        F: FnOnce(&mut ExecutorEnvBuilder<'static>) -> Result<()> + Send + 'static,
+    {
+        let elf: Arc<[u8]> = elf.into();
+        let handles: Vec<_> = make_envs
+            .into_iter()
+            .map(|make_env| self.spawn(elf.clone(), make_env))
+            .collect();
+        handles.into_iter().map(SessionHandle::join).collect()
+    }
+}
+
+/// A session spawned on an [ExecutorPool].
+pub struct SessionHandle {
+    receiver: mpsc::Receiver<thread::Result<Result<SendSession>>>,
+}
+
+impl SessionHandle {
+    /// Wait for the session to finish, and return it.
+    ///
+    /// A panic in the session, e.g. in one of its handlers, is resumed on the calling thread.
+    pub fn join(self) -> Result<Session> {
+        match self.receiver.recv() {
+            Ok(Ok(session)) => session.map(SendSession::into_inner),
+            Ok(Err(payload)) => panic::resume_unwind(payload),
+            Err(_) => Err(anyhow!("session was dropped by the executor pool")),
+        }
+    }
+}
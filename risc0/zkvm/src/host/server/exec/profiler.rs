@@ -27,20 +27,16 @@ use std::{
     rc::Rc,
 };
 
-use addr2line::{
-    fallible_iterator::FallibleIterator,
-    gimli::{EndianRcSlice, RunTimeEndian},
-    object::{File, Object, ObjectSegment},
-    LookupResult, ObjectContext,
-};
+use addr2line::object::{File, Object, ObjectSegment};
 use anyhow::{anyhow, Result};
-use elf::{abi::STT_FUNC, endian::LittleEndian, ElfBytes};
 use prost::Message;
 use risc0_zkvm_platform::memory::TEXT_START;
 use rrs_lib::instruction_formats::{IType, JType, OPCODE_JAL, OPCODE_JALR};
-use rustc_demangle::demangle;
 
-use super::proto;
+use super::{
+    proto,
+    symbolizer::{Frame, Symbolizer},
+};
 use crate::{TraceCallback, TraceEvent};
 
 /// Operations effecting the function call stack.
@@ -153,60 +149,25 @@ pub struct Profiler {
     // Current CallNode key in the stack
     current_key: u32,
 
-    ctx: ObjectContext,
+    symbolizer: Rc<Symbolizer>,
 
     profile: ProfileBuilder,
 }
 
-/// Represents a frame.
-#[derive(Clone, Debug)]
-pub struct Frame {
-    /// Function name
-    pub name: String,
-
-    /// Line number
-    pub lineno: i64,
-
-    /// Filename where this function is defined
-    pub filename: String,
-}
-
-fn decode_frame(fr: addr2line::Frame<EndianRcSlice<RunTimeEndian>>) -> Option<Frame> {
-    Some(Frame {
-        name: fr.function.as_ref()?.demangle().ok()?.to_string(),
-        lineno: fr.location.as_ref()?.line? as i64,
-        filename: fr.location.as_ref()?.file?.to_string(),
-    })
-}
-
-fn lookup_pc(pc: u32, ctx: &ObjectContext) -> Vec<Frame> {
-    let frames = match ctx.find_frames(pc as u64) {
-        LookupResult::Output(result) => result.unwrap(),
-        LookupResult::Load {
-            load: _,
-            continuation: _,
-        } => unimplemented!(),
-    };
-    frames
-        .filter_map(|frame| Ok(decode_frame(frame)))
-        .collect()
-        .unwrap()
-}
-
-fn demangle_name(name: String) -> String {
-    if let Some(index) = name.rfind("::") {
-        let truncated = &name[0..index];
-        truncated.to_string()
-    } else {
-        name
-    }
-}
-
 impl Profiler {
     /// Return a new profile from the given RISC-V ELF.
     pub fn new(elf_data: &[u8], filename: Option<&str>) -> Result<Self> {
+        let symbolizer = Rc::new(Symbolizer::new(elf_data)?);
+        Self::with_symbolizer(elf_data, filename, symbolizer)
+    }
+
+    /// Return a new profile from the given RISC-V ELF, using an existing [Symbolizer] for it.
+    pub(crate) fn with_symbolizer(
+        elf_data: &[u8],
+        filename: Option<&str>,
+        symbolizer: Rc<Symbolizer>,
+    ) -> Result<Self> {
         let file = File::parse(elf_data)?;
-        let ctx = ObjectContext::new(&file)?;
         let root = Rc::new(RefCell::new(CallNode::default()));
         let mut profiler = Profiler {
             pc: u32::MAX,
@@ -217,7 +178,7 @@ impl Profiler {
             current_node: Some(root),
             current_key: 0,
             call_stack_path: Vec::new(),
-            ctx,
+            symbolizer,
             profile: ProfileBuilder::new(),
         };
 
@@ -240,55 +201,12 @@ impl Profiler {
             }
         }
 
-        let elf = ElfBytes::<LittleEndian>::minimal_parse(elf_data)?;
-        if let Some((symtab, strtab)) = elf.symbol_table()? {
-            for sym in symtab {
-                if sym.st_symtype() == STT_FUNC {
-                    let name = strtab.get(sym.st_name as usize)?;
-                    profiler
-                        .profile
-                        .function_lookup
-                        .insert(sym.st_value, demangle(name).to_string());
-                }
-            }
-        }
-
         Ok(profiler)
     }
 
     /// Returns the frames name at the given pc.
     pub fn lookup_pc(&self, pc: u64) -> Vec<Frame> {
-        let frames = if let Some(symbol) = self.profile.function_lookup.get(&pc).cloned() {
-            let mut dwarf_frames = lookup_pc(pc as u32, &self.ctx);
-            dwarf_frames.reverse();
-            let name = demangle_name(symbol).replace('&', "");
-            let mut lineno: i64 = 0;
-            let mut filename = "unknown".to_string();
-            if !dwarf_frames.is_empty() {
-                let debug_frame = &dwarf_frames[0];
-                let debug_name = debug_frame.name.replace('&', "");
-                if name == debug_name {
-                    lineno = debug_frame.lineno;
-                    filename = debug_frame.filename.clone();
-                }
-            }
-            let mut frames = vec![Frame {
-                name,
-                lineno,
-                filename,
-            }];
-            if dwarf_frames.len() > 1 {
-                for fr in dwarf_frames[1..].iter() {
-                    frames.push(fr.clone())
-                }
-            }
-            frames
-        } else {
-            // only used when debug is set. However, it currently misinterprets when no_std
-            // lookup_pc(pc as u32, &self.ctx)
-            vec![]
-        };
-        frames
+        self.symbolizer.call_frames(pc as u32)
     }
 
     /// Walk the profile tree rooted at node_ref, adding all call stacks in the profile to the
@@ -434,8 +352,7 @@ impl TraceCallback for Profiler {
                 self.insn = insn;
                 self.cycle = cycle;
             }
-            TraceEvent::RegisterSet { .. } => (),
-            TraceEvent::MemorySet { .. } => (),
+            _ => (),
         }
         Ok(())
     }
@@ -452,7 +369,6 @@ pub(crate) struct ProfileBuilder {
     strings: HashMap<String, i64>,
     functions: HashMap<(String, String), u64>,
     locations: HashMap<LocationKey, u64>,
-    function_lookup: HashMap<u64, String>,
     profile: proto::Profile,
 }
 
@@ -463,7 +379,6 @@ impl ProfileBuilder {
             functions: HashMap::new(),
             locations: HashMap::new(),
             profile: Default::default(),
-            function_lookup: Default::default(),
         };
 
         // First string must always be the empty string
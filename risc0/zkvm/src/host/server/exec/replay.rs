@@ -0,0 +1,365 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Re-execution of the segments of a [Session], to audit them against their receipts or to
+//! debug them in isolation.
+
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use anyhow::{anyhow, bail, ensure, Context as _, Result};
+use risc0_circuit_rv32im::{
+    prove::{
+        emu::exec::{Executor, Syscall, SyscallContext},
+        segment::{Segment as CircuitSegment, SyscallRecord},
+    },
+    trace::{TraceCallback, TraceEvent},
+};
+use risc0_zkp::core::digest::Digest;
+use risc0_zkvm_platform::syscall::reg_abi::{REG_A3, REG_A4, REG_A5, REG_A6, REG_A7};
+
+use crate::{
+    host::client::transcript::{self, TranscriptEntry},
+    sha::Digestible,
+    ExitCode, Segment, SegmentReceipt, Session, SyscallTranscript,
+};
+
+/// The answers to the syscalls of a replayed segment.
+trait Answers: Syscall {
+    /// The number of answers that were not used.
+    fn remaining(&self) -> usize;
+}
+
+/// Answers the syscalls of a segment with the answers recorded when it was executed.
+struct RecordedSyscalls(RefCell<VecDeque<SyscallRecord>>);
+
+impl Answers for RecordedSyscalls {
+    fn remaining(&self) -> usize {
+        self.0.borrow().len()
+    }
+}
+
+impl Syscall for RecordedSyscalls {
+    fn syscall(
+        &self,
+        syscall: &str,
+        _ctx: &mut dyn SyscallContext,
+        into_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let record = self
+            .0
+            .borrow_mut()
+            .pop_front()
+            .with_context(|| format!("{syscall} has no recorded answer"))?;
+        ensure!(
+            record.to_guest.len() == into_guest.len(),
+            "{syscall} asked for {} words, but {} were recorded",
+            into_guest.len(),
+            record.to_guest.len()
+        );
+        into_guest.copy_from_slice(&record.to_guest);
+        Ok(record.regs)
+    }
+}
+
+/// Answers the syscalls of a segment from a [SyscallTranscript], checking that each syscall is
+/// the one recorded in the transcript.
+struct TranscriptSyscalls(RefCell<VecDeque<TranscriptEntry>>);
+
+impl Answers for TranscriptSyscalls {
+    fn remaining(&self) -> usize {
+        self.0.borrow().len()
+    }
+}
+
+impl Syscall for TranscriptSyscalls {
+    fn syscall(
+        &self,
+        syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        into_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let args = [REG_A3, REG_A4, REG_A5, REG_A6, REG_A7]
+            .into_iter()
+            .map(|idx| ctx.peek_register(idx))
+            .collect::<Result<Vec<_>>>()?;
+        let inputs = transcript::inputs_digest(into_guest.len(), &args);
+        let entry = self
+            .0
+            .borrow_mut()
+            .pop_front()
+            .with_context(|| format!("Syscall transcript exhausted at {syscall}"))?;
+        entry.check(syscall, &inputs, into_guest.len())?;
+        into_guest.copy_from_slice(&entry.to_guest);
+        Ok(entry.regs)
+    }
+}
+
+/// The outcome of re-executing a single segment with [Segment::replay].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayResult {
+    /// The exit code the segment ended with when replayed.
+    pub exit_code: ExitCode,
+
+    /// The number of user cycles executed by the replay.
+    pub user_cycles: u64,
+
+    /// The digest of the system state the replay reached.
+    pub post_state: Digest,
+
+    /// The digest of the output committed by the replay, if any.
+    pub output: Option<Digest>,
+
+    /// Whether the replay reached the post-state and exit code recorded for the segment, after
+    /// the same number of cycles.
+    pub matches_segment: bool,
+}
+
+impl Segment {
+    /// Re-execute this segment alone from its partial memory image, without rerunning the rest
+    /// of its [Session].
+    ///
+    /// Syscalls are answered from `transcript`, which must hold the syscalls of this segment in
+    /// order, as returned by [Session::segment_transcript], or from the answers recorded in the
+    /// segment if `transcript` is `None`. A transcript may be edited to see how the guest
+    /// behaves with other answers, and the replay fails if the guest makes a syscall other than
+    /// the one recorded in the transcript.
+    pub fn replay(&self, transcript: Option<&SyscallTranscript>) -> Result<ReplayResult> {
+        self.replay_with_hook(transcript, |_: TraceEvent| Ok(()))
+    }
+
+    /// [Replay][Segment::replay] this segment, passing each step of its execution to `hook`.
+    ///
+    /// `hook` receives a [TraceEvent] for each instruction and each write to
+    /// registers and memory, as with
+    /// [ExecutorEnvBuilder::trace_callback][crate::ExecutorEnvBuilder::trace_callback], and can
+    /// stop the replay by returning an error.
+    pub fn replay_with_hook(
+        &self,
+        transcript: Option<&SyscallTranscript>,
+        hook: impl TraceCallback,
+    ) -> Result<ReplayResult> {
+        let hook: Rc<RefCell<dyn TraceCallback + '_>> = Rc::new(RefCell::new(hook));
+        let inner = &self.inner;
+        let replayed = match transcript {
+            Some(transcript) => {
+                let answers = TranscriptSyscalls(RefCell::new(transcript.entries.clone().into()));
+                replay_segment(inner, &answers, vec![hook])?
+            }
+            None => replay_segment(inner, &recorded_syscalls(inner), vec![hook])?,
+        };
+        Ok(ReplayResult {
+            exit_code: replayed.exit_code,
+            user_cycles: replayed.insn_cycles as u64,
+            post_state: replayed.post_state.digest(),
+            output: replayed.output_digest,
+            matches_segment: replayed.post_state == inner.post_state
+                && replayed.exit_code == inner.exit_code
+                && replayed.insn_cycles == inner.insn_cycles,
+        })
+    }
+}
+
+impl Session {
+    /// The syscalls of the segment with the given index, taken from the
+    /// [syscall transcript][Session::syscall_transcript] of this session, for
+    /// [Segment::replay].
+    ///
+    /// Fails if the session has no transcript, which must be recorded with
+    /// [ExecutorEnvBuilder::record_syscall_transcript][crate::ExecutorEnvBuilder::record_syscall_transcript].
+    pub fn segment_transcript(&self, index: u32) -> Result<SyscallTranscript> {
+        let transcript = self
+            .syscall_transcript()
+            .context("Session has no syscall transcript")?;
+        ensure!(
+            (index as usize) < self.segments.len(),
+            "Session has no segment {index}"
+        );
+        let mut skip = 0;
+        for segment in &self.segments[..index as usize] {
+            skip += segment.resolve()?.inner.syscalls.len();
+        }
+        let take = self.segments[index as usize]
+            .resolve()?
+            .inner
+            .syscalls
+            .len();
+        ensure!(
+            skip + take <= transcript.entries.len(),
+            "Syscall transcript is shorter than the session"
+        );
+        Ok(SyscallTranscript {
+            entries: transcript.entries[skip..skip + take].to_vec(),
+        })
+    }
+}
+
+fn recorded_syscalls(segment: &CircuitSegment) -> RecordedSyscalls {
+    RecordedSyscalls(RefCell::new(segment.syscalls.iter().cloned().collect()))
+}
+
+/// Re-execute `segment` from its partial memory image, answering its syscalls with `answers`,
+/// and return the segment produced by the re-execution.
+fn replay_segment<'b>(
+    segment: &CircuitSegment,
+    answers: &impl Answers,
+    trace: Vec<Rc<RefCell<dyn TraceCallback + 'b>>>,
+) -> Result<CircuitSegment> {
+    let mut exec = Executor::new(
+        segment.partial_image.clone(),
+        answers,
+        Some(segment.input_digest),
+        trace,
+    );
+
+    // A split segment must be split at the same po2 as it was executed with. The last segment
+    // ends on its own, so give it room to avoid splitting on a segment that exactly fills its po2.
+    let segment_po2 = match segment.exit_code {
+        ExitCode::SystemSplit => segment.po2,
+        _ => segment.po2 + 1,
+    };
+    let mut replayed = None;
+    // Stop at the end of the first segment, since the partial image holds no other pages.
+    let result = exec.run(segment_po2, None, |segment| {
+        replayed = Some(segment);
+        Err(anyhow!("end of replayed segment"))
+    });
+    let replayed = match (replayed, result) {
+        (Some(replayed), _) => replayed,
+        (None, Err(err)) => {
+            return Err(err.context(format!("Replay failed at pc=0x{:08x}", exec.pc().0)))
+        }
+        (None, Ok(_)) => bail!("Replay produced no segment"),
+    };
+    ensure!(
+        answers.remaining() == 0,
+        "Replay made fewer syscalls than were recorded"
+    );
+    Ok(replayed)
+}
+
+fn audit_segment(segment: &Segment, receipt: &SegmentReceipt) -> Result<()> {
+    let inner = &segment.inner;
+    let replayed = replay_segment(inner, &recorded_syscalls(inner), vec![])
+        .context("Failed to replay segment")?;
+    ensure!(
+        replayed.pre_state == inner.pre_state && replayed.post_state == inner.post_state,
+        "Replay reached image ID {} rather than {}",
+        replayed.post_state.merkle_root,
+        inner.post_state.merkle_root
+    );
+    ensure!(
+        replayed.exit_code == inner.exit_code && replayed.insn_cycles == inner.insn_cycles,
+        "Replay ended with {:?} after {} cycles rather than {:?} after {} cycles",
+        replayed.exit_code,
+        replayed.insn_cycles,
+        inner.exit_code,
+        inner.insn_cycles
+    );
+    ensure!(
+        replayed.output_digest == inner.output_digest
+            && segment.output.digest() == inner.output_digest.unwrap_or(Digest::ZERO),
+        "Replay committed a different output"
+    );
+
+    let claim = &receipt.claim;
+    ensure!(
+        receipt.index == segment.index,
+        "Receipt is for segment {}",
+        receipt.index
+    );
+    ensure!(
+        claim.pre.digest() == inner.pre_state.digest(),
+        "Receipt claims a different pre-state"
+    );
+    // The post-state of a halted segment is not committed by the segment itself.
+    if !matches!(inner.exit_code, ExitCode::Halted(_)) {
+        ensure!(
+            claim.post.digest() == inner.post_state.digest(),
+            "Receipt claims a different post-state"
+        );
+    }
+    ensure!(
+        claim.exit_code == inner.exit_code,
+        "Receipt claims exit code {:?} rather than {:?}",
+        claim.exit_code,
+        inner.exit_code
+    );
+    ensure!(
+        claim.input.digest() == inner.input_digest,
+        "Receipt claims a different input"
+    );
+    ensure!(
+        claim.output.digest() == segment.output.digest(),
+        "Receipt claims a different output"
+    );
+    Ok(())
+}
+
+/// Audit a [Session] against the [SegmentReceipt]s proving it, by re-executing it.
+///
+/// Each segment is re-executed from its partial memory image with the syscall answers recorded
+/// when it was executed, and the image IDs, exit code, and output it reaches are checked against
+/// the segment and against the claim of its receipt, which must be given in order. The segments
+/// are also checked to form a chain from the pre-state of the session to its exit code and
+/// journal.
+///
+/// This is a cheap check for bugs in an execution and proving pipeline, e.g. receipts that were
+/// proven from the wrong segments or a session whose segments were lost. It does not verify the
+/// seals of the receipts, which must still be done with
+/// [SegmentReceipt::verify_integrity_with_context]. Sessions executed with
+/// [ExecutorEnvBuilder::linux_compat][crate::ExecutorEnvBuilder::linux_compat] cannot be replayed.
+pub fn audit_session(session: &Session, receipts: &[SegmentReceipt]) -> Result<()> {
+    ensure!(
+        receipts.len() == session.segments.len(),
+        "Session has {} segments but {} receipts were given",
+        session.segments.len(),
+        receipts.len()
+    );
+
+    let mut expected_pre = session.pre_state.digest();
+    let mut last = None;
+    for (index, (segment_ref, receipt)) in session.segments.iter().zip(receipts).enumerate() {
+        let segment = segment_ref.resolve()?;
+        ensure!(
+            segment.index as usize == index,
+            "Segment {index} has index {}",
+            segment.index
+        );
+        ensure!(
+            segment.inner.pre_state.digest() == expected_pre,
+            "Segment {index} does not start from the post-state of the previous segment"
+        );
+        audit_segment(&segment, receipt)
+            .with_context(|| format!("Segment {index} failed audit"))?;
+        expected_pre = segment.inner.post_state.digest();
+        last = Some(segment);
+    }
+
+    let last = last.context("Session has no segments")?;
+    ensure!(
+        last.inner.exit_code == session.exit_code,
+        "Session exit code {:?} does not match its last segment",
+        session.exit_code
+    );
+    let journal = last.output.as_ref().map(|output| output.journal.digest());
+    ensure!(
+        journal
+            == session
+                .journal
+                .as_ref()
+                .map(|journal| journal.bytes.digest()),
+        "Session journal does not match the output of its last segment"
+    );
+    Ok(())
+}
@@ -0,0 +1,329 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Heuristics for catching a guest that is stuck, e.g. in an infinite loop.
+
+use std::fmt;
+
+use anyhow::Result;
+use risc0_circuit_rv32im::prove::emu::exec::{MonitorSample, SyscallContext};
+use risc0_zkvm_platform::syscall::reg_abi::REG_MAX;
+use serde::{Deserialize, Serialize};
+
+use super::symbolizer::Symbolizer;
+
+/// The heuristics are checked this many times per the shortest number of cycles they look at,
+/// so they trigger at most an eighth of that number of cycles late.
+const CHECKS_PER_WINDOW: u64 = 8;
+
+/// Heuristics for catching a guest that is likely stuck, long before it reaches the session
+/// limit, configured with
+/// [ExecutorEnvBuilder::runaway_heuristics][crate::ExecutorEnvBuilder::runaway_heuristics].
+///
+/// # Example
+///
+/// ```
+/// use risc0_zkvm::RunawayHeuristics;
+///
+/// let heuristics = RunawayHeuristics::default()
+///     .with_idle_cycles(50_000_000)
+///     .with_tight_loop(10_000_000, 64)
+///     .with_abort(true);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunawayHeuristics {
+    /// Trigger when the guest makes no syscalls, including writes to the journal, for this many
+    /// user cycles.
+    pub idle_cycles: Option<u64>,
+
+    /// Trigger when every instruction the guest executes for `cycles` user cycles is within
+    /// `span` bytes of code.
+    pub tight_loop: Option<TightLoop>,
+
+    /// Fail the run when a heuristic triggers, rather than only reporting it.
+    pub abort: bool,
+}
+
+/// The tight loop heuristic of [RunawayHeuristics].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TightLoop {
+    /// The number of user cycles the loop must run for.
+    pub cycles: u64,
+
+    /// The largest distance in bytes between the instructions of the loop.
+    pub span: u32,
+}
+
+impl RunawayHeuristics {
+    /// Trigger when the guest makes no syscalls for `cycles` user cycles.
+    pub fn with_idle_cycles(self, cycles: u64) -> Self {
+        Self {
+            idle_cycles: Some(cycles),
+            ..self
+        }
+    }
+
+    /// Trigger when the guest only executes instructions within `span` bytes of code for
+    /// `cycles` user cycles.
+    pub fn with_tight_loop(self, cycles: u64, span: u32) -> Self {
+        Self {
+            tight_loop: Some(TightLoop { cycles, span }),
+            ..self
+        }
+    }
+
+    /// Fail the run when a heuristic triggers.
+    pub fn with_abort(self, abort: bool) -> Self {
+        Self { abort, ..self }
+    }
+}
+
+/// The reason a [RunawayHeuristics] heuristic triggered.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum RunawayKind {
+    /// The guest made no syscalls for `cycles` user cycles.
+    Idle {
+        /// The user cycles since the last syscall.
+        cycles: u64,
+    },
+
+    /// The guest only executed the instructions from `start` to `end` for `cycles` user cycles.
+    TightLoop {
+        /// The lowest address of the loop.
+        start: u32,
+        /// The highest address of the loop.
+        end: u32,
+        /// The user cycles spent in the loop.
+        cycles: u64,
+    },
+}
+
+/// The state of a guest caught by a [RunawayHeuristics] heuristic.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunawayReport {
+    /// Why the heuristic triggered.
+    pub kind: RunawayKind,
+
+    /// The number of user cycles executed before the heuristic triggered, counted from the
+    /// start of the first run of the executor, as in
+    /// [ExecutionEvent::cycle][crate::ExecutionEvent::cycle].
+    pub cycle: u64,
+
+    /// The address of the next instruction of the guest.
+    pub pc: u32,
+
+    /// The registers of the guest, x0 to x31.
+    pub registers: Vec<u32>,
+
+    /// The guest function and source location at `pc`, if known.
+    pub location: Option<String>,
+}
+
+impl fmt::Display for RunawayKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Idle { cycles } => write!(f, "no syscalls for {cycles} cycles"),
+            Self::TightLoop { start, end, cycles } => write!(
+                f,
+                "only executed 0x{start:08x}..=0x{end:08x} for {cycles} cycles"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for RunawayReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "runaway guest: {} at cycle {}, pc 0x{:08x}",
+            self.kind, self.cycle, self.pc
+        )?;
+        if let Some(location) = &self.location {
+            write!(f, " ({location})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Applies [RunawayHeuristics] to the [MonitorSample]s of a run.
+pub(crate) struct RunawayDetector {
+    heuristics: RunawayHeuristics,
+    // The user cycles since the last syscall.
+    idle_cycles: u64,
+    // The addresses and user cycles of the loop the guest may be stuck in.
+    loop_range: Option<(u32, u32)>,
+    loop_cycles: u64,
+    // Each heuristic triggers once, until the guest makes progress again.
+    idle_reported: bool,
+    loop_reported: bool,
+}
+
+impl RunawayDetector {
+    pub(crate) fn new(heuristics: RunawayHeuristics) -> Self {
+        Self {
+            heuristics,
+            idle_cycles: 0,
+            loop_range: None,
+            loop_cycles: 0,
+            idle_reported: false,
+            loop_reported: false,
+        }
+    }
+
+    /// The number of user cycles between samples.
+    pub(crate) fn interval(&self) -> usize {
+        let window = [
+            self.heuristics.idle_cycles,
+            self.heuristics
+                .tight_loop
+                .map(|tight_loop| tight_loop.cycles),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(u64::MAX);
+        (window / CHECKS_PER_WINDOW).clamp(1, u32::MAX as u64) as usize
+    }
+
+    /// Apply the heuristics to `sample`, returning those that triggered.
+    pub(crate) fn observe(&mut self, sample: &MonitorSample) -> Vec<RunawayKind> {
+        let mut triggered = Vec::new();
+
+        if sample.syscalls > 0 {
+            self.idle_cycles = 0;
+            self.idle_reported = false;
+        } else {
+            self.idle_cycles += sample.cycles;
+        }
+        if let Some(idle_cycles) = self.heuristics.idle_cycles {
+            if self.idle_cycles >= idle_cycles && !self.idle_reported {
+                self.idle_reported = true;
+                triggered.push(RunawayKind::Idle {
+                    cycles: self.idle_cycles,
+                });
+            }
+        }
+
+        if let Some(tight_loop) = self.heuristics.tight_loop {
+            let within = |(start, end): (u32, u32)| end.saturating_sub(start) <= tight_loop.span;
+            let range = (sample.min_pc, sample.max_pc);
+            let merged = self
+                .loop_range
+                .map(|(start, end)| (start.min(range.0), end.max(range.1)));
+            match merged.filter(|merged| within(*merged)) {
+                Some(merged) => {
+                    self.loop_range = Some(merged);
+                    self.loop_cycles += sample.cycles;
+                }
+                None => {
+                    // The guest left the loop, and may have entered another one.
+                    self.loop_range = within(range).then_some(range);
+                    self.loop_cycles = sample.cycles;
+                    self.loop_reported = false;
+                }
+            }
+            if let Some((start, end)) = self.loop_range {
+                if self.loop_cycles >= tight_loop.cycles && !self.loop_reported {
+                    self.loop_reported = true;
+                    triggered.push(RunawayKind::TightLoop {
+                        start,
+                        end,
+                        cycles: self.loop_cycles,
+                    });
+                }
+            }
+        }
+
+        triggered
+    }
+}
+
+impl RunawayReport {
+    /// Capture the state of the guest in `ctx`, whose run started at `cycle_offset`.
+    pub(crate) fn new(
+        kind: RunawayKind,
+        ctx: &mut dyn SyscallContext,
+        cycle_offset: u64,
+        symbolizer: Option<&Symbolizer>,
+    ) -> Result<Self> {
+        let pc = ctx.get_pc();
+        let registers = (0..REG_MAX)
+            .map(|idx| ctx.peek_register(idx))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            kind,
+            cycle: cycle_offset + ctx.get_cycle(),
+            pc,
+            registers,
+            location: symbolizer.and_then(|symbolizer| symbolizer.describe(pc)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_circuit_rv32im::prove::emu::exec::MonitorSample;
+
+    use super::{RunawayDetector, RunawayHeuristics, RunawayKind};
+
+    fn sample(cycles: u64, min_pc: u32, max_pc: u32, syscalls: u64) -> MonitorSample {
+        MonitorSample {
+            cycles,
+            min_pc,
+            max_pc,
+            syscalls,
+            segments: 0,
+            total_cycles: 0,
+            resident_bytes: 0,
+        }
+    }
+
+    #[test]
+    fn idle() {
+        let mut detector = RunawayDetector::new(RunawayHeuristics::default().with_idle_cycles(100));
+        assert_eq!(detector.interval(), 12);
+        assert!(detector.observe(&sample(60, 0, 0x1000, 0)).is_empty());
+        assert_eq!(
+            detector.observe(&sample(60, 0, 0x1000, 0)),
+            [RunawayKind::Idle { cycles: 120 }]
+        );
+        // Reported once, until the guest makes a syscall.
+        assert!(detector.observe(&sample(60, 0, 0x1000, 0)).is_empty());
+        assert!(detector.observe(&sample(60, 0, 0x1000, 1)).is_empty());
+        assert!(detector.observe(&sample(60, 0, 0x1000, 0)).is_empty());
+        assert_eq!(detector.observe(&sample(60, 0, 0x1000, 0)).len(), 1);
+    }
+
+    #[test]
+    fn tight_loop() {
+        let mut detector =
+            RunawayDetector::new(RunawayHeuristics::default().with_tight_loop(100, 16));
+        assert!(detector.observe(&sample(60, 0x100, 0x108, 1)).is_empty());
+        assert_eq!(
+            detector.observe(&sample(60, 0x104, 0x110, 1)),
+            [RunawayKind::TightLoop {
+                start: 0x100,
+                end: 0x110,
+                cycles: 120
+            }]
+        );
+        assert!(detector.observe(&sample(60, 0x100, 0x110, 0)).is_empty());
+
+        // Leaving the loop starts over.
+        assert!(detector.observe(&sample(60, 0x200, 0x400, 0)).is_empty());
+        assert!(detector.observe(&sample(60, 0x300, 0x304, 0)).is_empty());
+        assert_eq!(detector.observe(&sample(60, 0x300, 0x304, 0)).len(), 1);
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Execution of RV64IM guests.
+
+use std::{cell::RefCell, rc::Rc};
+
+use anyhow::{Context as _, Result};
+use risc0_binfmt::ExitCode;
+use risc0_circuit_rv32im::prove::emu::{
+    exec::{Syscall as NewSyscall, SyscallContext as NewSyscallContext},
+    rv64im::Rv64Executor,
+};
+use risc0_zkp::core::digest::Digest;
+use risc0_zkvm_platform::fileno;
+
+use super::{executor::ContextAdapter, syscall::SyscallTable};
+use crate::ExecutorEnv;
+
+/// The result of running an RV64IM guest with [execute_rv64].
+#[derive(Clone, Debug)]
+pub struct Rv64Session {
+    /// The [ExitCode] of the guest.
+    pub exit_code: ExitCode,
+
+    /// The data the guest wrote to the journal.
+    pub journal: Vec<u8>,
+
+    /// The output digest that the guest halted with.
+    pub output_digest: Digest,
+
+    /// The number of instructions the guest executed.
+    pub user_cycles: u64,
+}
+
+/// Answers the syscalls of an RV64IM guest with the [SyscallTable] of its env.
+struct Rv64Syscalls<'a> {
+    syscall_table: SyscallTable<'a>,
+}
+
+impl NewSyscall for Rv64Syscalls<'_> {
+    fn syscall(
+        &self,
+        syscall: &str,
+        ctx: &mut dyn NewSyscallContext,
+        into_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let mut ctx = ContextAdapter {
+            ctx,
+            syscall_table: self.syscall_table.clone(),
+        };
+        self.syscall_table
+            .get_syscall(syscall)
+            .context(format!("Unknown syscall: {syscall:?}"))?
+            .borrow_mut()
+            .syscall(syscall, &mut ctx, into_guest)
+    }
+}
+
+/// Run the RV64IM guest `elf` with `env`.
+///
+/// The guest is answered by the same syscalls as an RV32IM guest, and runs until it halts or
+/// reaches the session limit of `env`. Execution is not split into segments, and cannot be proven,
+/// so the settings of `env` that configure segments, tracing, or proving are not used.
+///
+/// The buffers that the guest passes to syscalls must be in the low 4 GiB of its memory (see
+/// [Rv64Executor]).
+pub fn execute_rv64(env: ExecutorEnv<'_>, elf: &[u8]) -> Result<Rv64Session> {
+    let journal = Rc::new(RefCell::new(Vec::new()));
+    env.posix_io
+        .borrow_mut()
+        .with_shared_write_fd(fileno::JOURNAL, journal.clone());
+    let syscalls = Rv64Syscalls {
+        syscall_table: SyscallTable::from_env(&env),
+    };
+    let result = Rv64Executor::from_elf(elf, &syscalls)?.run(env.session_limit)?;
+    let journal = journal.take();
+    Ok(Rv64Session {
+        exit_code: result.exit_code,
+        journal,
+        output_digest: result.output_digest,
+        user_cycles: result.user_cycles,
+    })
+}
@@ -0,0 +1,81 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checkpoints of an executor between runs, for resuming a long execution on another machine.
+
+use std::{fs, path::Path};
+
+use anyhow::{Context as _, Result};
+use risc0_binfmt::MemoryImage;
+use risc0_circuit_rv32im::prove::emu::exec::TrapState;
+use serde::{Deserialize, Serialize};
+
+use crate::{host::client::posix_io::PosixIoState, ExecutionEvent, TranscriptEntry};
+
+/// The state of an [ExecutorImpl][crate::ExecutorImpl] between runs, taken by
+/// [ExecutorImpl::snapshot][crate::ExecutorImpl::snapshot] and restored by
+/// [ExecutorImpl::restore][crate::ExecutorImpl::restore].
+///
+/// The memory image holds the registers and the pc of the guest. The state also holds the trap
+/// handlers the guest registered and the trap it is handling, the usage of the file descriptors
+/// with a quota, the named streams the guest opened, and how far the guest has read each file
+/// descriptor and named stream. The environment of the restored executor has to provide the same
+/// input as the original one: seekable inputs are sought to where the guest left them, and the
+/// bytes the guest already read from the others are skipped.
+///
+/// A snapshot cannot be taken while the guest has a pipe open, or is between the two calls of a
+/// syscall that returns a variable amount of data.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExecutorState {
+    pub(crate) image: MemoryImage,
+    pub(crate) cycle_offset: u64,
+    pub(crate) stopped_at: Option<u32>,
+    pub(crate) traps: TrapState,
+    pub(crate) events: Vec<ExecutionEvent>,
+    // The syscalls recorded so far, if the executor records a transcript.
+    pub(crate) transcript: Option<Vec<TranscriptEntry>>,
+    // The syscalls yet to be answered, if the executor replays a transcript.
+    pub(crate) replay: Option<Vec<TranscriptEntry>>,
+    // The state of the file descriptors, unless the state is only for resuming a paused session
+    // with new syscall handlers.
+    pub(crate) io: Option<PosixIoState>,
+}
+
+impl ExecutorState {
+    /// The pc the guest resumes at.
+    pub fn pc(&self) -> u32 {
+        self.image.pc
+    }
+
+    /// The user cycles executed before the snapshot was taken.
+    pub fn user_cycles(&self) -> u64 {
+        self.cycle_offset
+    }
+
+    /// Write the state to the file at `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, bincode::serialize(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Read a state written by [ExecutorState::save].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents =
+            fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        bincode::deserialize(&contents)
+            .with_context(|| format!("failed to decode {}", path.display()))
+    }
+}
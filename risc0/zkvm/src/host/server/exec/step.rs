@@ -0,0 +1,126 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Execution of a single instruction, for debuggers and fuzzers built on the executor.
+
+use std::{cell::RefCell, rc::Rc};
+
+use anyhow::Result;
+use risc0_binfmt::ExitCode;
+use risc0_circuit_rv32im::{
+    prove::emu::{
+        addr::ByteAddr,
+        exec::{Executor, SyscallContext as _},
+    },
+    trace::{TraceCallback, TraceEvent},
+};
+use serde::{Deserialize, Serialize};
+
+use super::executor::ExecutorImpl;
+
+/// An instruction executed by [ExecutorImpl::step] or [Stepper::step], and its effects on the
+/// state of the guest.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct StepResult {
+    /// Address of the instruction.
+    pub pc: u32,
+    /// The encoded instruction.
+    pub insn: u32,
+    /// The mnemonic of the decoded instruction, e.g. `addi` or `ecall`, or empty if the
+    /// instruction trapped to a handler of the guest.
+    pub mnemonic: String,
+    /// Address of the next instruction.
+    pub next_pc: u32,
+    /// The registers written, by index, with their new values.
+    pub register_writes: Vec<(usize, u32)>,
+    /// The memory written, by address, with the bytes written.
+    pub memory_writes: Vec<(u32, Vec<u8>)>,
+    /// The user cycles spent executing the instruction.
+    pub cycles: u64,
+    /// The exit code of the guest, if the instruction halted or paused it.
+    pub exit_code: Option<ExitCode>,
+}
+
+/// Executes the guest one instruction at a time, on an executor that stays live between steps.
+///
+/// See [ExecutorImpl::steps].
+pub struct Stepper<'x, 'a> {
+    pub(crate) executor: &'x ExecutorImpl<'a>,
+    pub(crate) exec: Executor<'x, 'a, ExecutorImpl<'a>>,
+    pub(crate) trace: Rc<RefCell<StepTrace>>,
+}
+
+impl Stepper<'_, '_> {
+    /// Execute the next instruction of the guest.
+    pub fn step(&mut self) -> Result<StepResult> {
+        let outcome = self
+            .exec
+            .step()
+            .map_err(|err| self.executor.fault_report(err, self.exec.pc()))?;
+        let trace = self.trace.take();
+        Ok(StepResult {
+            pc: trace.pc,
+            insn: trace.insn,
+            mnemonic: outcome
+                .kind
+                .map(|kind| kind.mnemonic().to_string())
+                .unwrap_or_default(),
+            next_pc: self.exec.pc().0,
+            register_writes: trace.register_writes,
+            memory_writes: trace.memory_writes,
+            cycles: outcome.cycles,
+            exit_code: outcome.exit_code,
+        })
+    }
+
+    /// The address of the next instruction.
+    pub fn pc(&self) -> u32 {
+        self.exec.pc().0
+    }
+
+    /// The value of register `idx`.
+    pub fn peek_register(&mut self, idx: usize) -> Result<u32> {
+        self.exec.peek_register(idx)
+    }
+
+    /// The word of guest memory at `addr`.
+    pub fn peek_u32(&mut self, addr: u32) -> Result<u32> {
+        self.exec.peek_u32(ByteAddr(addr))
+    }
+}
+
+/// Collects the trace events of a single instruction.
+#[derive(Default)]
+pub(crate) struct StepTrace {
+    pub(crate) pc: u32,
+    pub(crate) insn: u32,
+    pub(crate) register_writes: Vec<(usize, u32)>,
+    pub(crate) memory_writes: Vec<(u32, Vec<u8>)>,
+}
+
+impl TraceCallback for StepTrace {
+    fn trace_callback(&mut self, event: TraceEvent) -> Result<()> {
+        match event {
+            TraceEvent::InstructionStart { pc, insn, .. } => {
+                self.pc = pc;
+                self.insn = insn;
+            }
+            TraceEvent::RegisterSet { idx, value } => self.register_writes.push((idx, value)),
+            TraceEvent::MemorySet { addr, region } => self.memory_writes.push((addr, region)),
+            _ => (),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Support for mapping guest addresses to functions and source locations.
+
+use std::collections::BTreeMap;
+
+use addr2line::{
+    fallible_iterator::FallibleIterator,
+    gimli::{EndianRcSlice, RunTimeEndian},
+    object::File,
+    LookupResult, ObjectContext,
+};
+use anyhow::Result;
+use elf::{abi::STT_FUNC, endian::LittleEndian, ElfBytes};
+use rustc_demangle::demangle;
+
+/// Represents a frame.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    /// Function name
+    pub name: String,
+
+    /// Line number
+    pub lineno: i64,
+
+    /// Filename where this function is defined
+    pub filename: String,
+}
+
+/// Maps addresses in a guest program to function names and source locations.
+///
+/// Function names are taken from the ELF symbol table, and source locations and inlined
+/// functions from the DWARF debug info, if the ELF has any. The same [Symbolizer] is used by the
+/// profiler and to describe where the guest faulted, so that tools reading addresses from trace
+/// events can symbolize them consistently with the executor.
+pub struct Symbolizer {
+    ctx: ObjectContext,
+
+    // Function symbols by start address, along with the end address and demangled name.
+    functions: BTreeMap<u32, (u32, String)>,
+}
+
+impl Symbolizer {
+    /// Construct a [Symbolizer] from the given RISC-V ELF.
+    pub fn new(elf_data: &[u8]) -> Result<Self> {
+        let file = File::parse(elf_data)?;
+        let ctx = ObjectContext::new(&file)?;
+
+        let mut functions = BTreeMap::new();
+        let elf = ElfBytes::<LittleEndian>::minimal_parse(elf_data)?;
+        if let Some((symtab, strtab)) = elf.symbol_table()? {
+            for sym in symtab {
+                if sym.st_symtype() == STT_FUNC {
+                    let name = strtab.get(sym.st_name as usize)?;
+                    let start = sym.st_value as u32;
+                    let end = start.saturating_add(sym.st_size as u32);
+                    functions.insert(start, (end, demangle(name).to_string()));
+                }
+            }
+        }
+
+        Ok(Self { ctx, functions })
+    }
+
+    /// Return the name of the function containing `addr`, according to the ELF symbol table.
+    pub fn function(&self, addr: u32) -> Option<&str> {
+        let (start, (end, name)) = self.functions.range(..=addr).next_back()?;
+        (addr == *start || addr < *end).then_some(name.as_str())
+    }
+
+    /// Return the name of the function containing `addr`, as [Symbolizer::function] does, without
+    /// the hash that rustc appends to legacy mangled symbol names.
+    pub fn function_name(&self, addr: u32) -> Option<&str> {
+        self.function(addr).map(strip_hash)
+    }
+
+    /// Return the stack of inlined functions at `addr`, from the outermost function to the
+    /// innermost, according to the DWARF debug info.
+    ///
+    /// Returns an empty stack if the ELF has no debug info for `addr`.
+    pub fn frames(&self, addr: u32) -> Vec<Frame> {
+        let LookupResult::Output(Ok(frames)) = self.ctx.find_frames(addr as u64) else {
+            return Vec::new();
+        };
+        let mut frames: Vec<_> = frames
+            .filter_map(|frame| Ok(decode_frame(frame)))
+            .collect()
+            .unwrap_or_default();
+        frames.reverse();
+        frames
+    }
+
+    /// Return a short description of `addr`, e.g. `guest::main (src/main.rs:12)`.
+    ///
+    /// Returns `None` if `addr` does not belong to any function.
+    pub fn describe(&self, addr: u32) -> Option<String> {
+        let inner = self.frames(addr).pop();
+        let name = match (self.function(addr), &inner) {
+            (_, Some(frame)) => frame.name.clone(),
+            (Some(name), None) => name.to_string(),
+            (None, None) => return None,
+        };
+        Some(match inner {
+            Some(frame) => format!("{name} ({}:{})", frame.filename, frame.lineno),
+            None => name,
+        })
+    }
+
+    /// Return the frames for a call to the function that starts at `addr`.
+    ///
+    /// The first frame is named after the function symbol, and carries a source location if
+    /// the debug info agrees on the name; it is followed by any functions inlined at `addr`.
+    pub(crate) fn call_frames(&self, addr: u32) -> Vec<Frame> {
+        let Some((_, symbol)) = self.functions.get(&addr) else {
+            return Vec::new();
+        };
+        let dwarf_frames = self.frames(addr);
+        let name = strip_hash(symbol).replace('&', "");
+        let mut lineno: i64 = 0;
+        let mut filename = "unknown".to_string();
+        if let Some(debug_frame) = dwarf_frames.first() {
+            let debug_name = debug_frame.name.replace('&', "");
+            if name == debug_name {
+                lineno = debug_frame.lineno;
+                filename = debug_frame.filename.clone();
+            }
+        }
+        let mut frames = vec![Frame {
+            name,
+            lineno,
+            filename,
+        }];
+        frames.extend(dwarf_frames.into_iter().skip(1));
+        frames
+    }
+}
+
+fn decode_frame(fr: addr2line::Frame<EndianRcSlice<RunTimeEndian>>) -> Option<Frame> {
+    Some(Frame {
+        name: fr.function.as_ref()?.demangle().ok()?.to_string(),
+        lineno: fr.location.as_ref()?.line? as i64,
+        filename: fr.location.as_ref()?.file?.to_string(),
+    })
+}
+
+/// Remove the hash that rustc appends to legacy mangled symbol names, e.g. `::h0123456789abcdef`.
+fn strip_hash(name: &str) -> &str {
+    match name.rsplit_once("::h") {
+        Some((prefix, hash)) if hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            prefix
+        }
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkvm_methods::MULTI_TEST_ELF;
+
+    use super::{strip_hash, Symbolizer};
+
+    #[test]
+    fn symbolize_functions() {
+        let symbolizer = Symbolizer::new(MULTI_TEST_ELF).unwrap();
+        let (&start, &(end, _)) = symbolizer
+            .functions
+            .iter()
+            .find(|(_, (_, name))| name == "profile_test_func1")
+            .unwrap();
+
+        assert_eq!(symbolizer.function(start), Some("profile_test_func1"));
+        assert_eq!(symbolizer.function(end - 1), Some("profile_test_func1"));
+        assert!(symbolizer
+            .describe(start)
+            .unwrap()
+            .starts_with("profile_test_func"));
+        assert_eq!(symbolizer.call_frames(start)[0].name, "profile_test_func1");
+        assert!(symbolizer.function(0).is_none());
+        assert!(symbolizer.describe(0).is_none());
+    }
+
+    #[test]
+    fn strip_legacy_hash() {
+        assert_eq!(strip_hash("guest::main::h0123456789abcdef"), "guest::main");
+        assert_eq!(strip_hash("guest::helper"), "guest::helper");
+    }
+}
@@ -14,37 +14,50 @@
 
 //! Handlers for two-way private I/O between host and guest.
 
+mod fd;
 mod fork;
 mod pipe;
 
 use std::{cell::RefCell, cmp::min, collections::HashMap, rc::Rc};
 
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, ensure, Result};
 use bytes::Bytes;
 use risc0_circuit_rv32im::prove::emu::addr::ByteAddr;
 use risc0_zkvm_platform::{
     fileno,
+    memory::SYSTEM,
     syscall::{
+        fp_env,
         nr::{
-            SYS_ARGC, SYS_ARGV, SYS_CYCLE_COUNT, SYS_EXECUTE_ZKR, SYS_FORK, SYS_GETENV, SYS_LOG,
-            SYS_PANIC, SYS_PIPE, SYS_RANDOM, SYS_READ, SYS_VERIFY_INTEGRITY, SYS_WRITE,
+            SYS_ALLOC_FAILED, SYS_ARGC, SYS_ARGV, SYS_AUTH_GET, SYS_AUTH_PUT, SYS_CLOSE,
+            SYS_COMMIT_FIELD, SYS_CYCLE_COUNT, SYS_DIV_WIDE, SYS_DUP, SYS_EXECUTE_ZKR, SYS_FORK,
+            SYS_FP_ENV, SYS_GETENV, SYS_JOURNAL_RECIPIENT, SYS_LOG, SYS_LSEEK, SYS_MEMOIZE_GET,
+            SYS_MEMOIZE_PUT, SYS_OPEN, SYS_PANIC, SYS_PANIC_AT, SYS_PIPE, SYS_RANDOM, SYS_READ,
+            SYS_VERIFY_INTEGRITY, SYS_WRITE, SYS_WRITEV,
         },
-        reg_abi::{REG_A3, REG_A4, REG_A5},
+        reg_abi::{REG_A3, REG_A4, REG_A5, REG_A6, REG_A7},
         SyscallName,
     },
     WORD_SIZE,
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    authenticated::{AuthenticatedStore, Entry},
     host::{
         client::{posix_io::PosixIo, slice_io::SliceIo},
         server::exec::compose::SysCompose,
     },
+    redaction::{RedactableField, RedactableJournal},
     sha::{Digest, DIGEST_BYTES},
     ExecutorEnv,
 };
 
-use self::{fork::SysFork, pipe::SysPipe};
+use self::{
+    fd::{SysClose, SysDup, SysLseek, SysOpen},
+    fork::SysFork,
+    pipe::SysPipe,
+};
 
 /// A host-side implementation of a system call.
 pub(crate) trait Syscall {
@@ -55,6 +68,11 @@ pub(crate) trait Syscall {
         ctx: &mut dyn SyscallContext,
         to_guest: &mut [u32],
     ) -> Result<(u32, u32)>;
+
+    /// Whether the handler holds an answer that the guest has yet to fetch with a second call.
+    fn is_pending(&self) -> bool {
+        false
+    }
 }
 
 /// Access to memory and machine state for syscalls.
@@ -90,59 +108,214 @@ pub(crate) trait SyscallContext<'a> {
     fn syscall_table(&self) -> &SyscallTable<'a>;
 }
 
+/// Where the handler of a syscall was registered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum SyscallProvenance {
+    /// Provided by the zkVM to every guest.
+    Builtin,
+
+    /// Registered by the host with
+    /// [ExecutorEnvBuilder::slice_io][crate::ExecutorEnvBuilder::slice_io] or
+    /// [ExecutorEnvBuilder::io_callback][crate::ExecutorEnvBuilder::io_callback].
+    Env,
+
+    /// Installed by the host in place of the handler provided by the zkVM.
+    Override,
+}
+
+/// The trust that the answers of a syscall place in the host, in rings from least to most.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum SyscallRing {
+    /// The syscall only sends data from the guest to the host, e.g. `sys_write`.
+    Output,
+
+    /// The syscall returns data that the guest checks, e.g. `sys_div_wide`, or that is resolved
+    /// when the receipt is composed, e.g. `sys_verify_integrity`.
+    Checked,
+
+    /// The syscall returns data from the host that the guest cannot check, e.g. `sys_read`.
+    Input,
+}
+
+/// A syscall that the host answers, as listed by [SyscallTable::list].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyscallDescriptor {
+    /// The name of the syscall, e.g. `risc0_zkvm_platform::syscall::nr::SYS_READ`.
+    pub name: String,
+
+    /// Where the handler of the syscall was registered.
+    pub provenance: SyscallProvenance,
+
+    /// The trust that the answers of the syscall place in the host.
+    pub ring: SyscallRing,
+}
+
+/// A handler in a [SyscallTable], with the metadata it was registered with.
+#[derive(Clone)]
+pub(crate) struct SyscallEntry<'a> {
+    pub(crate) handler: Rc<RefCell<dyn Syscall + 'a>>,
+    provenance: SyscallProvenance,
+    ring: SyscallRing,
+}
+
+/// The host-side handlers of the syscalls available to a guest.
 #[derive(Clone)]
-pub(crate) struct SyscallTable<'a> {
-    pub(crate) inner: HashMap<String, Rc<RefCell<dyn Syscall + 'a>>>,
+pub struct SyscallTable<'a> {
+    pub(crate) inner: HashMap<String, SyscallEntry<'a>>,
     pub(crate) posix_io: Rc<RefCell<PosixIo<'a>>>,
 }
 
 impl<'a> SyscallTable<'a> {
-    pub fn new(posix_io: Rc<RefCell<PosixIo<'a>>>) -> Self {
+    pub(crate) fn new(posix_io: Rc<RefCell<PosixIo<'a>>>) -> Self {
         Self {
             inner: HashMap::new(),
             posix_io,
         }
     }
 
+    /// Construct the [SyscallTable] that answers the syscalls of guests run with `env`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::{ExecutorEnv, SyscallProvenance, SyscallRing, SyscallTable};
+    ///
+    /// let env = ExecutorEnv::builder().build().unwrap();
+    /// for syscall in SyscallTable::from_env(&env).list() {
+    ///     if syscall.ring == SyscallRing::Input {
+    ///         println!("{} ({:?})", syscall.name, syscall.provenance);
+    ///     }
+    /// }
+    /// ```
     pub fn from_env(env: &ExecutorEnv<'a>) -> Self {
         let mut this = Self::new(env.posix_io.clone());
 
         let sys_compose = SysCompose::new(env.assumptions.clone());
-
-        this.with_syscall(SYS_ARGC, Args(env.args.clone()))
-            .with_syscall(SYS_ARGV, Args(env.args.clone()))
-            .with_syscall(SYS_CYCLE_COUNT, SysCycleCount)
-            .with_syscall(SYS_FORK, SysFork)
-            .with_syscall(SYS_GETENV, SysGetenv(env.env_vars.clone()))
-            .with_syscall(SYS_LOG, SysLog)
-            .with_syscall(SYS_PANIC, SysPanic)
-            .with_syscall(SYS_PIPE, SysPipe::default())
-            .with_syscall(SYS_RANDOM, SysRandom)
-            .with_syscall(SYS_READ, SysRead)
-            .with_syscall(SYS_VERIFY_INTEGRITY, sys_compose.clone())
-            .with_syscall(SYS_EXECUTE_ZKR, sys_compose.clone())
-            .with_syscall(SYS_WRITE, SysWrite);
+        let memoized = Rc::new(RefCell::new(HashMap::new()));
+        let auth_stores = Rc::new(env.authenticated_stores.clone());
+
+        this.with_builtin(SYS_ALLOC_FAILED, SyscallRing::Output, SysAllocFailed)
+            .with_builtin(SYS_ARGC, SyscallRing::Input, Args(env.args.clone()))
+            .with_builtin(SYS_ARGV, SyscallRing::Input, Args(env.args.clone()))
+            .with_builtin(
+                SYS_AUTH_GET,
+                SyscallRing::Checked,
+                SysSliceIo::new(Rc::new(RefCell::new(AuthGet(auth_stores.clone())))),
+            )
+            .with_builtin(SYS_AUTH_PUT, SyscallRing::Output, SysAuthPut(auth_stores))
+            .with_builtin(SYS_CLOSE, SyscallRing::Input, SysClose)
+            .with_builtin(
+                SYS_COMMIT_FIELD,
+                SyscallRing::Output,
+                SysCommitField(env.committed_fields.clone()),
+            )
+            .with_builtin(SYS_CYCLE_COUNT, SyscallRing::Input, SysCycleCount)
+            .with_builtin(SYS_DIV_WIDE, SyscallRing::Checked, SysDivWide)
+            .with_builtin(SYS_DUP, SyscallRing::Input, SysDup)
+            .with_builtin(SYS_FORK, SyscallRing::Input, SysFork)
+            .with_builtin(SYS_FP_ENV, SyscallRing::Checked, SysFpEnv)
+            .with_builtin(
+                SYS_GETENV,
+                SyscallRing::Input,
+                SysGetenv(env.env_vars.clone()),
+            )
+            .with_builtin(
+                SYS_JOURNAL_RECIPIENT,
+                SyscallRing::Input,
+                SysJournalRecipient(env.journal_recipient),
+            )
+            .with_builtin(SYS_LOG, SyscallRing::Output, SysLog)
+            .with_builtin(SYS_LSEEK, SyscallRing::Input, SysLseek)
+            .with_builtin(
+                SYS_MEMOIZE_GET,
+                SyscallRing::Checked,
+                SysMemoizeGet::new(memoized.clone()),
+            )
+            .with_builtin(
+                SYS_MEMOIZE_PUT,
+                SyscallRing::Output,
+                SysMemoizePut(memoized),
+            )
+            .with_builtin(SYS_OPEN, SyscallRing::Input, SysOpen)
+            .with_builtin(SYS_PANIC, SyscallRing::Output, SysPanic)
+            .with_builtin(SYS_PANIC_AT, SyscallRing::Output, SysPanicAt)
+            .with_builtin(SYS_PIPE, SyscallRing::Input, SysPipe::default())
+            .with_builtin(SYS_RANDOM, SyscallRing::Input, SysRandom)
+            .with_builtin(SYS_READ, SyscallRing::Input, SysRead)
+            .with_builtin(
+                SYS_VERIFY_INTEGRITY,
+                SyscallRing::Checked,
+                sys_compose.clone(),
+            )
+            .with_builtin(SYS_EXECUTE_ZKR, SyscallRing::Checked, sys_compose.clone())
+            .with_builtin(SYS_WRITE, SyscallRing::Output, SysWrite)
+            .with_builtin(SYS_WRITEV, SyscallRing::Output, SysWritev);
         for (syscall, handler) in env.slice_io.borrow().inner.iter() {
             let handler = SysSliceIo::new(handler.clone());
-            this.inner
-                .insert(syscall.clone(), Rc::new(RefCell::new(handler)));
+            let ring = env
+                .syscall_rings
+                .get(syscall)
+                .copied()
+                .unwrap_or(SyscallRing::Input);
+            this.insert(syscall, SyscallProvenance::Env, ring, handler);
         }
 
         this
     }
 
+    /// Describe every syscall in the table, sorted by name.
+    pub fn list(&self) -> Vec<SyscallDescriptor> {
+        let mut list: Vec<_> = self
+            .inner
+            .iter()
+            .map(|(name, entry)| SyscallDescriptor {
+                name: name.clone(),
+                provenance: entry.provenance,
+                ring: entry.ring,
+            })
+            .collect();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        list
+    }
+
+    fn with_builtin(
+        &mut self,
+        syscall: SyscallName,
+        ring: SyscallRing,
+        handler: impl Syscall + 'a,
+    ) -> &mut Self {
+        self.insert(syscall.as_str(), SyscallProvenance::Builtin, ring, handler);
+        self
+    }
+
+    /// Install `handler` in place of any handler of `syscall`, in the given ring.
     pub(crate) fn with_syscall(
         &mut self,
         syscall: SyscallName,
+        ring: SyscallRing,
         handler: impl Syscall + 'a,
     ) -> &mut Self {
-        self.inner
-            .insert(syscall.as_str().to_string(), Rc::new(RefCell::new(handler)));
+        self.insert(syscall.as_str(), SyscallProvenance::Override, ring, handler);
         self
     }
 
+    fn insert(
+        &mut self,
+        syscall: &str,
+        provenance: SyscallProvenance,
+        ring: SyscallRing,
+        handler: impl Syscall + 'a,
+    ) {
+        let entry = SyscallEntry {
+            handler: Rc::new(RefCell::new(handler)),
+            provenance,
+            ring,
+        };
+        self.inner.insert(syscall.to_string(), entry);
+    }
+
     pub(crate) fn get_syscall(&self, name: &str) -> Option<&Rc<RefCell<(dyn Syscall + 'a)>>> {
-        self.inner.get(name)
+        self.inner.get(name).map(|entry| &entry.handler)
     }
 }
 
@@ -161,6 +334,108 @@ impl Syscall for SysCycleCount {
     }
 }
 
+/// Reports the floating-point environment of the zkVM, which `guest::fp` checks against the
+/// environment it measures.
+struct SysFpEnv;
+impl Syscall for SysFpEnv {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        _ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        Ok((fp_env::ZKVM, 0))
+    }
+}
+
+type AuthenticatedStores = Rc<HashMap<String, AuthenticatedStore>>;
+
+fn auth_store<'a>(stores: &'a AuthenticatedStores, name: &str) -> Result<&'a AuthenticatedStore> {
+    stores
+        .get(name)
+        .ok_or_else(|| anyhow!("Unknown authenticated store: {name}"))
+}
+
+/// Returns the [Witness][crate::authenticated::Witness] of a position of an authenticated store,
+/// which the guest checks against its root.
+struct AuthGet(AuthenticatedStores);
+impl SliceIo for AuthGet {
+    fn handle_io(&mut self, _syscall: &str, from_guest: Bytes) -> Result<Bytes> {
+        let request: Vec<u32> = bytemuck::pod_collect_to_vec(&from_guest);
+        let (name, position): (String, u64) = crate::serde::from_slice(&request)?;
+        let witness = auth_store(&self.0, &name)?.witness(position);
+        let words = crate::serde::to_vec(&witness)?;
+        Ok(Bytes::copy_from_slice(bytemuck::cast_slice(&words)))
+    }
+}
+
+/// Applies an entry written by the guest to an authenticated store.
+struct SysAuthPut(AuthenticatedStores);
+impl Syscall for SysAuthPut {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let buf_ptr = ByteAddr(ctx.load_register(REG_A3));
+        let buf_len = ctx.load_register(REG_A4);
+        let from_guest: Vec<u32> =
+            bytemuck::pod_collect_to_vec(&ctx.load_region(buf_ptr, buf_len)?);
+        let (name, position, entry): (String, u64, Option<Entry>) =
+            crate::serde::from_slice(&from_guest)?;
+        auth_store(&self.0, &name)?.put(position, entry);
+        Ok((0, 0))
+    }
+}
+
+/// Divides a 256-bit integer by a 128-bit integer, for `guest::fixed`, which checks the quotient
+/// and remainder by multiplying them out.
+pub(crate) struct SysDivWide;
+impl Syscall for SysDivWide {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let buf_ptr = ByteAddr(ctx.load_register(REG_A3));
+        let buf_len = ctx.load_register(REG_A4);
+        let from_guest = ctx.load_region(buf_ptr, buf_len)?;
+        ensure!(
+            from_guest.len() == 48 && to_guest.len() == 8,
+            "sys_div_wide: invalid buffer sizes"
+        );
+        let read_u128 =
+            |i: usize| u128::from_le_bytes(from_guest[16 * i..16 * (i + 1)].try_into().unwrap());
+        let (lo, hi, divisor) = (read_u128(0), read_u128(1), read_u128(2));
+        ensure!(
+            hi < divisor,
+            "sys_div_wide: quotient does not fit in 128 bits"
+        );
+
+        // Long division of hi:lo by the divisor, one bit of the quotient at a time. The
+        // remainder is always less than the divisor, so shifting it overflows by at most one bit.
+        let (mut quotient, mut remainder) = (0u128, hi);
+        for i in (0..128).rev() {
+            let overflow = remainder >> 127 == 1;
+            remainder = (remainder << 1) | ((lo >> i) & 1);
+            quotient <<= 1;
+            if overflow || remainder >= divisor {
+                remainder = remainder.wrapping_sub(divisor);
+                quotient |= 1;
+            }
+        }
+
+        for (out, value) in to_guest.chunks_exact_mut(4).zip([quotient, remainder]) {
+            for (i, word) in out.iter_mut().enumerate() {
+                *word = (value >> (32 * i)) as u32;
+            }
+        }
+        Ok((0, 0))
+    }
+}
+
 pub(crate) struct SysGetenv(pub HashMap<String, String>);
 impl Syscall for SysGetenv {
     fn syscall(
@@ -186,6 +461,26 @@ impl Syscall for SysGetenv {
     }
 }
 
+pub(crate) struct SysJournalRecipient(pub Option<[u8; 32]>);
+impl Syscall for SysJournalRecipient {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        _ctx: &mut dyn SyscallContext,
+        to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let public_key = self
+            .0
+            .ok_or_else(|| anyhow!("guest requested a journal recipient, but none was set"))?;
+        ensure!(
+            to_guest.len() * WORD_SIZE == public_key.len(),
+            "invalid journal recipient buffer"
+        );
+        bytemuck::cast_slice_mut(to_guest).copy_from_slice(&public_key);
+        Ok((0, 0))
+    }
+}
+
 pub(crate) struct SysPanic;
 impl Syscall for SysPanic {
     fn syscall(
@@ -202,6 +497,58 @@ impl Syscall for SysPanic {
     }
 }
 
+// Upper bound on the length of the file name of a panic location, to avoid reading an arbitrary
+// amount of guest memory.
+const MAX_PANIC_FILE_LEN: u32 = 4096;
+
+/// Fails execution with the source location of a guest panic, reported without a message.
+pub(crate) struct SysPanicAt;
+impl Syscall for SysPanicAt {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let file_ptr = ByteAddr(ctx.load_register(REG_A3));
+        let file_len = ctx.load_register(REG_A4);
+        let line = ctx.load_register(REG_A5);
+        let col = ctx.load_register(REG_A6);
+        ensure!(
+            file_len <= MAX_PANIC_FILE_LEN,
+            "Guest panicked at a location with a {file_len}-byte file name"
+        );
+        let file = ctx.load_region(file_ptr, file_len)?;
+        let file = std::str::from_utf8(&file)?;
+        bail!("Guest panicked at {file}:{line}:{col}");
+    }
+}
+
+/// Fails execution with the details of a guest allocation that did not fit in the heap.
+pub(crate) struct SysAllocFailed;
+impl Syscall for SysAllocFailed {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let bytes = ctx.load_register(REG_A3);
+        let align = ctx.load_register(REG_A4);
+        let heap_start = ctx.load_register(REG_A5);
+        let heap_pos = ctx.load_register(REG_A6);
+        let caller = ctx.load_register(REG_A7);
+        let heap_limit = SYSTEM.start() as u32;
+        let used = heap_pos.saturating_sub(heap_start);
+        let size = heap_limit.saturating_sub(heap_start);
+        bail!(
+            "Guest ran out of heap at pc=0x{caller:08x}: requested {bytes} bytes aligned to \
+             {align} with {used} of {size} heap bytes in use \
+             (heap 0x{heap_start:08x}..0x{heap_limit:08x})"
+        );
+    }
+}
+
 pub(crate) struct SysRandom;
 impl Syscall for SysRandom {
     fn syscall(
@@ -306,6 +653,10 @@ impl<'a> Syscall for SysSliceIo<'a> {
             }
         })
     }
+
+    fn is_pending(&self) -> bool {
+        self.stored_result.borrow().is_some()
+    }
 }
 
 struct SysRead;
@@ -330,6 +681,14 @@ impl Syscall for SysRead {
         );
 
         let reader = ctx.syscall_table().posix_io.borrow().get_reader(fd)?;
+        // Past the quota of the descriptor, the guest sees end of file.
+        let allowed = match ctx.syscall_table().posix_io.borrow_mut().charge(fd, nbytes) {
+            Ok(allowed) => allowed,
+            Err(err) => {
+                tracing::debug!("{err}");
+                return Ok((0, 0));
+            }
+        };
 
         // So that we don't have to deal with short reads, keep
         // reading until we get EOF or fill the buffer.
@@ -346,14 +705,15 @@ impl Syscall for SysRead {
             Ok(tot_nread)
         };
 
-        let to_guest_u8 = bytemuck::cast_slice_mut(to_guest);
-        let nread_main = read_all(to_guest_u8)?;
+        let to_guest_u8: &mut [u8] = bytemuck::cast_slice_mut(to_guest);
+        let main_len = to_guest_u8.len().min(allowed);
+        let nread_main = read_all(&mut to_guest_u8[..main_len])?;
 
         tracing::trace!("read: {nread_main}, requested: {}", to_guest_u8.len());
 
         // It's possible that there's an unaligned word at the end
         let unaligned_end = if nbytes - nread_main <= WORD_SIZE {
-            nbytes - nread_main
+            (nbytes - nread_main).min(allowed - nread_main)
         } else {
             // We encountered an EOF. There's nothing left to read
             0
@@ -362,6 +722,14 @@ impl Syscall for SysRead {
         // Fill unaligned word out.
         let mut to_guest_end: [u8; WORD_SIZE] = [0; WORD_SIZE];
         let nread_end = read_all(&mut to_guest_end[0..unaligned_end])?;
+        ctx.syscall_table()
+            .posix_io
+            .borrow_mut()
+            .record(fd, nread_main + nread_end);
+        ctx.syscall_table()
+            .posix_io
+            .borrow_mut()
+            .consume(fd, nread_main + nread_end);
 
         Ok((
             (nread_main + nread_end) as u32,
@@ -386,11 +754,167 @@ impl Syscall for SysWrite {
 
         tracing::trace!("sys_write(fd: {fd}, bytes: {buf_len})");
 
+        ctx.syscall_table()
+            .posix_io
+            .borrow_mut()
+            .charge_all(fd, from_guest_bytes.len())?;
         writer.borrow_mut().write_all(from_guest_bytes.as_slice())?;
+        ctx.syscall_table()
+            .posix_io
+            .borrow_mut()
+            .record(fd, from_guest_bytes.len());
         Ok((0, 0))
     }
 }
 
+/// Writes a list of guest buffers, described by an array of `IoVec`s, to a file descriptor.
+struct SysWritev;
+impl Syscall for SysWritev {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let fd = ctx.load_register(REG_A3);
+        let iov_ptr = ByteAddr(ctx.load_register(REG_A4));
+        let iovcnt = ctx.load_register(REG_A5);
+        let iovs_len = iovcnt
+            .checked_mul(2 * WORD_SIZE as u32)
+            .ok_or_else(|| anyhow!("sys_writev: too many buffers: {iovcnt}"))?;
+        let iovs = ctx.load_region(iov_ptr, iovs_len)?;
+        let writer = ctx.syscall_table().posix_io.borrow().get_writer(fd)?;
+
+        tracing::trace!("sys_writev(fd: {fd}, iovcnt: {iovcnt})");
+
+        let mut bufs = Vec::with_capacity(iovcnt as usize);
+        let mut nwritten: u32 = 0;
+        for iov in iovs.chunks_exact(2 * WORD_SIZE) {
+            let (base, len) = iov.split_at(WORD_SIZE);
+            let base = ByteAddr(u32::from_le_bytes(base.try_into()?));
+            let len = u32::from_le_bytes(len.try_into()?);
+            bufs.push((base, len));
+            nwritten = nwritten
+                .checked_add(len)
+                .ok_or_else(|| anyhow!("sys_writev: total length overflow"))?;
+        }
+
+        ctx.syscall_table()
+            .posix_io
+            .borrow_mut()
+            .charge_all(fd, nwritten as usize)?;
+        for (base, len) in bufs {
+            let from_guest_bytes = ctx.load_region(base, len)?;
+            writer.borrow_mut().write_all(from_guest_bytes.as_slice())?;
+        }
+        ctx.syscall_table()
+            .posix_io
+            .borrow_mut()
+            .record(fd, nwritten as usize);
+        Ok((nwritten, 0))
+    }
+}
+
+struct SysCommitField(Rc<RefCell<RedactableJournal>>);
+impl Syscall for SysCommitField {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let buf_ptr = ByteAddr(ctx.load_register(REG_A3));
+        let buf_len = ctx.load_register(REG_A4);
+        let from_guest = ctx.load_region(buf_ptr, buf_len)?;
+        let field: RedactableField = crate::serde::from_slice(&from_guest)?;
+        tracing::debug!(
+            "sys_commit_field({:?}, {} bytes)",
+            field.name,
+            field.data.len()
+        );
+        self.0.borrow_mut().fields.push(field);
+        Ok((0, 0))
+    }
+}
+
+/// Results of memoized guest calls, by the digest of the function and its arguments.
+type MemoizedResults = Rc<RefCell<HashMap<Digest, Vec<u8>>>>;
+
+struct SysMemoizePut(MemoizedResults);
+impl Syscall for SysMemoizePut {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let buf_ptr = ByteAddr(ctx.load_register(REG_A3));
+        let buf_len = ctx.load_register(REG_A4);
+        let from_guest = ctx.load_region(buf_ptr, buf_len)?;
+        ensure!(
+            from_guest.len() >= DIGEST_BYTES,
+            "sys_memoize_put: missing call digest"
+        );
+        let (key, result) = from_guest.split_at(DIGEST_BYTES);
+        let key = Digest::try_from(key)?;
+        tracing::debug!("sys_memoize_put({key}, {} bytes)", result.len());
+        self.0.borrow_mut().insert(key, result.to_vec());
+        Ok((0, 0))
+    }
+}
+
+/// Returns a memoized result to the guest in two calls, like [SysSliceIo]. The first call
+/// returns the length of the result, or `u32::MAX` if there is none, and the second copies it
+/// into the buffer allocated by the guest.
+struct SysMemoizeGet {
+    memoized: MemoizedResults,
+    pending: Option<Vec<u8>>,
+}
+
+impl SysMemoizeGet {
+    fn new(memoized: MemoizedResults) -> Self {
+        Self {
+            memoized,
+            pending: None,
+        }
+    }
+}
+
+impl Syscall for SysMemoizeGet {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        if let Some(result) = self.pending.take() {
+            let to_guest_bytes: &mut [u8] = bytemuck::cast_slice_mut(to_guest);
+            ensure!(
+                to_guest_bytes.len() == result.len(),
+                "sys_memoize_get: invalid result buffer"
+            );
+            to_guest_bytes.copy_from_slice(&result);
+            return Ok((0, 0));
+        }
+
+        let buf_ptr = ByteAddr(ctx.load_register(REG_A3));
+        let buf_len = ctx.load_register(REG_A4);
+        let key = Digest::try_from(ctx.load_region(buf_ptr, buf_len)?.as_slice())?;
+        match self.memoized.borrow().get(&key) {
+            None => Ok((u32::MAX, 0)),
+            Some(result) => {
+                tracing::debug!("sys_memoize_get({key}): {} bytes", result.len());
+                self.pending = Some(result.clone());
+                Ok((result.len() as u32, 0))
+            }
+        }
+    }
+
+    fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+}
+
 struct SysLog;
 impl Syscall for SysLog {
     fn syscall(
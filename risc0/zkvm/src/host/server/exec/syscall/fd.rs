@@ -0,0 +1,282 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::SeekFrom;
+
+use anyhow::{bail, Result};
+use risc0_circuit_rv32im::prove::emu::addr::ByteAddr;
+use risc0_zkvm_platform::{
+    fileno,
+    syscall::{
+        reg_abi::{REG_A3, REG_A4, REG_A5, REG_A6},
+        whence,
+    },
+};
+
+use crate::host::client::posix_io::{GuestFd, PosixIo};
+
+use super::{Syscall, SyscallContext};
+
+// Descriptors below this one are the standard streams. They are never handed out by `sys_open` or
+// `sys_dup`, and the guest cannot close them.
+const FIRST_DYNAMIC_FD: u32 = fileno::JOURNAL + 1;
+
+/// Opens a stream registered with [crate::ExecutorEnvBuilder::named_stream].
+pub(crate) struct SysOpen;
+
+impl Syscall for SysOpen {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let name_ptr = ByteAddr(ctx.load_register(REG_A3));
+        let name_len = ctx.load_register(REG_A4);
+        let name = String::from_utf8(ctx.load_region(name_ptr, name_len)?)?;
+        let fd = ctx
+            .syscall_table()
+            .posix_io
+            .borrow_mut()
+            .open_named_stream(&name)
+            .unwrap_or(fileno::INVALID);
+
+        tracing::trace!("sys_open({name:?}) -> {fd}");
+        Ok((fd, 0))
+    }
+}
+
+pub(crate) struct SysClose;
+
+impl Syscall for SysClose {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let fd = ctx.load_register(REG_A3);
+        let closed = ctx.syscall_table().posix_io.borrow_mut().close(fd);
+
+        tracing::trace!("sys_close({fd}) -> {closed}");
+        Ok((if closed { 0 } else { fileno::INVALID }, 0))
+    }
+}
+
+pub(crate) struct SysDup;
+
+impl Syscall for SysDup {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let fd = ctx.load_register(REG_A3);
+        let new_fd = ctx
+            .syscall_table()
+            .posix_io
+            .borrow_mut()
+            .dup(fd)
+            .unwrap_or(fileno::INVALID);
+
+        tracing::trace!("sys_dup({fd}) -> {new_fd}");
+        Ok((new_fd, 0))
+    }
+}
+
+/// Repositions a file descriptor registered with a seekable reader.
+pub(crate) struct SysLseek;
+
+impl Syscall for SysLseek {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let fd = ctx.load_register(REG_A3);
+        let offset_lo = ctx.load_register(REG_A4);
+        let offset_hi = ctx.load_register(REG_A5);
+        let offset = (((offset_hi as u64) << 32) | offset_lo as u64) as i64;
+        let pos = match ctx.load_register(REG_A6) {
+            whence::SET => SeekFrom::Start(offset as u64),
+            whence::CUR => SeekFrom::Current(offset),
+            whence::END => SeekFrom::End(offset),
+            other => bail!("sys_lseek: invalid whence {other}"),
+        };
+
+        let Ok(seeker) = ctx.syscall_table().posix_io.borrow().get_seeker(fd) else {
+            tracing::trace!("sys_lseek({fd}, {pos:?}) -> not seekable");
+            return Ok((fileno::INVALID, fileno::INVALID));
+        };
+        let result = seeker.borrow_mut().seek(pos);
+
+        tracing::trace!("sys_lseek({fd}, {pos:?}) -> {result:?}");
+        match result {
+            Ok(new_pos) => Ok((new_pos as u32, (new_pos >> 32) as u32)),
+            Err(_) => Ok((fileno::INVALID, fileno::INVALID)),
+        }
+    }
+}
+
+impl<'a> PosixIo<'a> {
+    fn open_named_stream(&mut self, name: &str) -> Option<u32> {
+        let stream = self.named_streams.get(name)?.clone();
+        let fd = self.find_free_fd(FIRST_DYNAMIC_FD)?;
+        self.read_fds.insert(fd, stream.reader);
+        if let Some(seeker) = stream.seeker {
+            self.seek_fds.insert(fd, seeker);
+        }
+        self.guest_fds.insert(fd, GuestFd::Stream(name.to_string()));
+        Some(fd)
+    }
+
+    fn close(&mut self, fd: u32) -> bool {
+        if fd < FIRST_DYNAMIC_FD {
+            return false;
+        }
+        let reader = self.read_fds.remove(&fd);
+        let writer = self.write_fds.remove(&fd);
+        self.seek_fds.remove(&fd);
+        let closed = reader.is_some() || writer.is_some();
+        if closed {
+            self.guest_fds.insert(fd, GuestFd::Closed);
+        }
+        closed
+    }
+
+    fn dup(&mut self, fd: u32) -> Option<u32> {
+        let reader = self.read_fds.get(&fd).cloned();
+        let writer = self.write_fds.get(&fd).cloned();
+        let seeker = self.seek_fds.get(&fd).cloned();
+        if reader.is_none() && writer.is_none() {
+            return None;
+        }
+
+        let new_fd = self.find_free_fd(FIRST_DYNAMIC_FD)?;
+        if let Some(reader) = reader {
+            self.read_fds.insert(new_fd, reader);
+        }
+        if let Some(writer) = writer {
+            self.write_fds.insert(new_fd, writer);
+        }
+        if let Some(seeker) = seeker {
+            self.seek_fds.insert(new_fd, seeker);
+        }
+        let origin = self.guest_fds.get(&fd).cloned();
+        self.guest_fds
+            .insert(new_fd, origin.unwrap_or(GuestFd::Dup(fd)));
+        Some(new_fd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, SeekFrom};
+
+    use risc0_zkvm_platform::fileno;
+
+    use super::FIRST_DYNAMIC_FD;
+    use crate::host::client::posix_io::PosixIo;
+
+    #[test]
+    fn open_close_dup() {
+        let mut posix_io = PosixIo::new();
+        posix_io.with_named_stream("prices", Cursor::new(vec![1, 2, 3]));
+
+        assert_eq!(posix_io.open_named_stream("missing"), None);
+        let fd = posix_io.open_named_stream("prices").unwrap();
+        let dup_fd = posix_io.dup(fd).unwrap();
+        assert_ne!(fd, dup_fd);
+        assert!(fd >= FIRST_DYNAMIC_FD && dup_fd >= FIRST_DYNAMIC_FD);
+
+        let mut buf = [0u8; 2];
+        posix_io
+            .get_reader(fd)
+            .unwrap()
+            .borrow_mut()
+            .read_exact(&mut buf)
+            .unwrap();
+        assert_eq!(buf, [1, 2]);
+
+        // Both descriptors share the same stream.
+        assert!(posix_io.close(fd));
+        assert!(!posix_io.close(fd));
+        let mut buf = [0u8; 1];
+        posix_io
+            .get_reader(dup_fd)
+            .unwrap()
+            .borrow_mut()
+            .read_exact(&mut buf)
+            .unwrap();
+        assert_eq!(buf, [3]);
+
+        assert_eq!(posix_io.dup(fd), None);
+    }
+
+    #[test]
+    fn standard_fds_reserved() {
+        // No standard descriptor is open, yet none of them is handed out.
+        let mut posix_io = PosixIo::new();
+        posix_io.with_named_stream("prices", Cursor::new(vec![1, 2, 3]));
+        assert_eq!(posix_io.open_named_stream("prices"), Some(FIRST_DYNAMIC_FD));
+
+        let mut posix_io = PosixIo::default();
+        posix_io.with_write_fd(fileno::JOURNAL, Vec::new());
+        for fd in [
+            fileno::STDIN,
+            fileno::STDOUT,
+            fileno::STDERR,
+            fileno::JOURNAL,
+        ] {
+            assert!(!posix_io.close(fd));
+        }
+        assert!(posix_io.get_reader(fileno::STDIN).is_ok());
+        assert!(posix_io.get_writer(fileno::STDOUT).is_ok());
+        assert!(posix_io.get_writer(fileno::JOURNAL).is_ok());
+        assert!(posix_io.dup(fileno::STDIN).unwrap() >= FIRST_DYNAMIC_FD);
+    }
+
+    #[test]
+    fn seekable_named_stream() {
+        let mut posix_io = PosixIo::new();
+        posix_io
+            .with_named_stream("plain", Cursor::new(vec![1, 2, 3]))
+            .with_seekable_named_stream("seekable", Cursor::new(vec![1, 2, 3]));
+
+        let fd = posix_io.open_named_stream("plain").unwrap();
+        assert!(posix_io.get_seeker(fd).is_err());
+
+        let fd = posix_io.open_named_stream("seekable").unwrap();
+        let dup_fd = posix_io.dup(fd).unwrap();
+        posix_io
+            .get_seeker(fd)
+            .unwrap()
+            .borrow_mut()
+            .seek(SeekFrom::End(-1))
+            .unwrap();
+        let mut buf = [0u8; 1];
+        posix_io
+            .get_reader(dup_fd)
+            .unwrap()
+            .borrow_mut()
+            .read_exact(&mut buf)
+            .unwrap();
+        assert_eq!(buf, [3]);
+
+        posix_io.close(fd);
+        assert!(posix_io.get_seeker(fd).is_err());
+    }
+}
@@ -209,11 +209,11 @@ impl<'a, 'b> EmuContext for ChildExecutor<'a, 'b> {
         }
     }
 
-    fn mret(&self) -> Result<bool> {
+    fn mret(&mut self) -> Result<bool> {
         unimplemented!()
     }
 
-    fn trap(&self, cause: TrapCause) -> Result<bool> {
+    fn trap(&mut self, cause: TrapCause) -> Result<bool> {
         let msg = format!("Trap: {cause:08x?}, pc: {:?}", self.pc);
         tracing::info!("{msg}");
         bail!("{msg}");
@@ -21,7 +21,7 @@ use std::{
 
 use anyhow::{anyhow, Result};
 
-use crate::host::client::posix_io::PosixIo;
+use crate::host::client::posix_io::{GuestFd, PosixIo};
 
 use super::{Syscall, SyscallContext};
 
@@ -49,7 +49,7 @@ impl Syscall for SysPipe {
 }
 
 impl<'a> PosixIo<'a> {
-    fn find_free_fd(&self, start: u32) -> Option<u32> {
+    pub(super) fn find_free_fd(&self, start: u32) -> Option<u32> {
         (start..MAX_FD)
             .find(|&i| !self.read_fds.contains_key(&i) && !self.write_fds.contains_key(&i))
     }
@@ -62,6 +62,8 @@ impl<'a> PosixIo<'a> {
         let write_fd = self.find_free_fd(read_fd)?;
         self.read_fds.insert(read_fd, pipe.clone());
         self.write_fds.insert(write_fd, pipe);
+        self.guest_fds.insert(read_fd, GuestFd::Pipe);
+        self.guest_fds.insert(write_fd, GuestFd::Pipe);
         Some((read_fd, write_fd))
     }
 }
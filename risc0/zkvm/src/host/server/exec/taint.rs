@@ -0,0 +1,489 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tracking of guest data derived from the host, to find host data committed to the journal
+//! without being verified.
+//!
+//! Every byte of guest memory and every register carries the set of host syscalls it was derived
+//! from. Data returned by a host syscall is derived from that syscall, and the sets flow through
+//! loads, stores, arithmetic, and the SHA-256 and BigInt accelerators. When the guest verifies a
+//! claim with `sys_verify_integrity`, the syscalls whose data was hashed into the claim digest are
+//! verified, and data derived only from verified syscalls is no longer tainted. Taint flowing
+//! through control flow or address computations is not tracked.
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt,
+};
+
+use anyhow::Result;
+use risc0_circuit_rv32im::{
+    prove::emu::{addr::ByteAddr, exec::SyscallContext},
+    trace::{TraceCallback, TraceEvent},
+};
+use risc0_zkp::core::digest::DIGEST_BYTES;
+use risc0_zkvm_platform::{
+    fileno,
+    syscall::{
+        ecall,
+        nr::{SYS_VERIFY_INTEGRITY, SYS_WRITE, SYS_WRITEV},
+        reg_abi::{REG_A1, REG_A2, REG_A3, REG_A4, REG_A5, REG_MAX, REG_T0},
+    },
+    WORD_SIZE,
+};
+use serde::{Deserialize, Serialize};
+
+use super::symbolizer::Symbolizer;
+
+/// Size of each operand of the BigInt accelerator.
+const BIGINT_BYTES: u32 = 32;
+
+/// Half of a block hashed by the SHA-256 accelerator.
+const SHA_HALF_BLOCK_BYTES: u32 = 32;
+
+/// A write to the journal of data derived from host syscalls, found by
+/// [ExecutorEnvBuilder::taint_tracking][crate::ExecutorEnvBuilder::taint_tracking].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaintedCommit {
+    /// The user cycle of the write.
+    pub cycle: u64,
+    /// Address of the `ecall` making the write.
+    pub pc: u32,
+    /// Function making the write, if known.
+    pub function: Option<String>,
+    /// Number of bytes written to the journal.
+    pub len: u32,
+    /// Number of those bytes derived from host syscalls.
+    pub tainted: u32,
+}
+
+impl fmt::Display for TaintedCommit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} bytes committed at cycle {} (pc=0x{:08x}",
+            self.tainted, self.len, self.cycle, self.pc
+        )?;
+        if let Some(function) = &self.function {
+            write!(f, " in {function}")?;
+        }
+        write!(f, ") came from the host without being verified")
+    }
+}
+
+/// The sets of host syscalls that data was derived from, interned so that each register and byte
+/// of memory holds the ID of its set, with [Origins::NONE] for data not derived from the host.
+struct Origins {
+    sets: Vec<BTreeSet<u32>>,
+    ids: HashMap<BTreeSet<u32>, u32>,
+    unions: HashMap<(u32, u32), u32>,
+    // The host syscalls whose data has been hashed into a claim verified by the guest.
+    verified: HashSet<u32>,
+}
+
+impl Default for Origins {
+    fn default() -> Self {
+        Self {
+            sets: vec![BTreeSet::new()],
+            ids: HashMap::from([(BTreeSet::new(), Self::NONE)]),
+            unions: HashMap::new(),
+            verified: HashSet::new(),
+        }
+    }
+}
+
+impl Origins {
+    /// The ID of the empty set.
+    const NONE: u32 = 0;
+
+    fn intern(&mut self, set: BTreeSet<u32>) -> u32 {
+        if let Some(&id) = self.ids.get(&set) {
+            return id;
+        }
+        let id = self.sets.len() as u32;
+        self.sets.push(set.clone());
+        self.ids.insert(set, id);
+        id
+    }
+
+    /// The set of the single host syscall `syscall`.
+    fn syscall(&mut self, syscall: u32) -> u32 {
+        self.intern(BTreeSet::from([syscall]))
+    }
+
+    fn union(&mut self, a: u32, b: u32) -> u32 {
+        if a == b || b == Self::NONE {
+            return a;
+        }
+        if a == Self::NONE {
+            return b;
+        }
+        let key = (a.min(b), a.max(b));
+        if let Some(&id) = self.unions.get(&key) {
+            return id;
+        }
+        let set = self.sets[a as usize]
+            .union(&self.sets[b as usize])
+            .copied()
+            .collect();
+        let id = self.intern(set);
+        self.unions.insert(key, id);
+        id
+    }
+
+    /// Whether data with origins `id` came from a host syscall that has not been verified.
+    fn tainted(&self, id: u32) -> bool {
+        self.sets[id as usize]
+            .iter()
+            .any(|syscall| !self.verified.contains(syscall))
+    }
+
+    fn verify(&mut self, id: u32) {
+        let syscalls = self.sets[id as usize].clone();
+        self.verified.extend(syscalls);
+    }
+}
+
+/// Tracks which registers and bytes of guest memory hold data derived from host syscalls.
+#[derive(Default)]
+pub(crate) struct TaintTracker {
+    // The value of each register, as far as the trace has shown it.
+    regs: [u32; REG_MAX],
+    // A bit per register whose value is known.
+    known: u32,
+    // The origins of each register.
+    reg_origins: [u32; REG_MAX],
+    // The origins of each byte of memory derived from the host.
+    memory: HashMap<u32, u32>,
+    origins: Origins,
+    // The number of host syscalls so far, which identifies the next one.
+    syscalls: u32,
+    // The origins of the results of the instruction being traced.
+    result: u32,
+    // Whether the instruction being traced is a store, whose taint was already applied.
+    store: bool,
+    commits: Vec<TaintedCommit>,
+}
+
+impl TaintTracker {
+    /// Check a syscall about to be handled by the host for tainted writes to the journal, and
+    /// apply the claims it verifies.
+    pub(crate) fn on_syscall(&mut self, syscall: &str, ctx: &mut dyn SyscallContext) -> Result<()> {
+        // Registers are not traced until they are set, so take the chance to learn them all.
+        for idx in 0..REG_MAX {
+            self.regs[idx] = ctx.peek_register(idx)?;
+        }
+        self.known = u32::MAX;
+
+        // The guest sends the digest of the claim it verifies, which commits to all of the data
+        // hashed into it, followed by the control root.
+        if syscall == SYS_VERIFY_INTEGRITY.as_str() {
+            let claim = self.origins_of(self.regs[REG_A3], DIGEST_BYTES as u32);
+            self.origins.verify(claim);
+            return Ok(());
+        }
+
+        if self.regs[REG_A3] != fileno::JOURNAL {
+            return Ok(());
+        }
+        let mut ranges = Vec::new();
+        if syscall == SYS_WRITE.as_str() {
+            ranges.push((self.regs[REG_A4], self.regs[REG_A5]));
+        } else if syscall == SYS_WRITEV.as_str() {
+            let iov = self.regs[REG_A4];
+            for idx in 0..self.regs[REG_A5] {
+                let entry = ByteAddr(iov + idx * 2 * WORD_SIZE as u32);
+                let base = ctx.peek_u32(entry)?;
+                let len = ctx.peek_u32(entry + WORD_SIZE as u32)?;
+                ranges.push((base, len));
+            }
+        }
+
+        let len = ranges.iter().map(|&(_, len)| len).sum();
+        let tainted = ranges
+            .iter()
+            .map(|&(addr, len)| self.count_tainted(addr, len))
+            .sum();
+        if tainted > 0 {
+            self.commits.push(TaintedCommit {
+                cycle: ctx.get_cycle(),
+                pc: ctx.get_pc(),
+                function: None,
+                len,
+                tainted,
+            });
+        }
+        Ok(())
+    }
+
+    /// The tainted writes to the journal traced so far.
+    pub(crate) fn commits(&self, symbolizer: Option<&Symbolizer>) -> Vec<TaintedCommit> {
+        let mut commits = self.commits.clone();
+        for commit in &mut commits {
+            commit.function = symbolizer
+                .and_then(|s| s.function(commit.pc))
+                .map(str::to_string);
+        }
+        commits
+    }
+
+    #[cfg(test)]
+    fn reg_tainted(&self, idx: usize) -> bool {
+        self.origins.tainted(self.reg_origins[idx])
+    }
+
+    /// The value of a register, if it is known.
+    fn reg(&self, idx: usize) -> Option<u32> {
+        (self.known & (1 << idx) != 0).then_some(self.regs[idx])
+    }
+
+    fn count_tainted(&self, addr: u32, len: u32) -> u32 {
+        (0..len)
+            .filter(|&i| {
+                self.memory
+                    .get(&addr.wrapping_add(i))
+                    .is_some_and(|&id| self.origins.tainted(id))
+            })
+            .count() as u32
+    }
+
+    /// The union of the origins of `len` bytes of memory from `addr`.
+    fn origins_of(&mut self, addr: u32, len: u32) -> u32 {
+        (0..len).fold(Origins::NONE, |acc, i| {
+            let id = self
+                .memory
+                .get(&addr.wrapping_add(i))
+                .copied()
+                .unwrap_or(Origins::NONE);
+            self.origins.union(acc, id)
+        })
+    }
+
+    fn set_origins(&mut self, addr: u32, len: u32, id: u32) {
+        for i in 0..len {
+            let addr = addr.wrapping_add(i);
+            if id == Origins::NONE {
+                self.memory.remove(&addr);
+            } else {
+                self.memory.insert(addr, id);
+            }
+        }
+    }
+
+    fn on_insn(&mut self, insn: u32) {
+        let opcode = insn & 0x7f;
+        let funct3 = (insn >> 12) & 0x7;
+        let rs1 = ((insn >> 15) & 0x1f) as usize;
+        let rs2 = ((insn >> 20) & 0x1f) as usize;
+        // Loads and stores of 1, 2, or 4 bytes.
+        let width = 1 << (funct3 & 0x3);
+
+        self.store = false;
+        self.result = match opcode {
+            // OP
+            0b0110011 => self
+                .origins
+                .union(self.reg_origins[rs1], self.reg_origins[rs2]),
+            // OP-IMM
+            0b0010011 => self.reg_origins[rs1],
+            // LOAD
+            0b0000011 => {
+                let imm = (insn as i32 >> 20) as u32;
+                match self.reg(rs1) {
+                    Some(base) => self.origins_of(base.wrapping_add(imm), width),
+                    None => Origins::NONE,
+                }
+            }
+            // STORE
+            0b0100011 => {
+                let imm = (((insn as i32 >> 25) << 5) as u32) | ((insn >> 7) & 0x1f);
+                if let Some(base) = self.reg(rs1) {
+                    self.set_origins(base.wrapping_add(imm), width, self.reg_origins[rs2]);
+                }
+                self.store = true;
+                Origins::NONE
+            }
+            // ECALL
+            0b1110011 if insn == 0x00000073 => self.on_ecall(),
+            // LUI, AUIPC, JAL, JALR, and the rest produce no data from registers or memory.
+            _ => Origins::NONE,
+        };
+    }
+
+    /// Return the origins of the results of an `ecall`.
+    fn on_ecall(&mut self) -> u32 {
+        match self.reg(REG_T0) {
+            Some(ecall::SOFTWARE) => {
+                let syscall = self.syscalls;
+                self.syscalls += 1;
+                self.origins.syscall(syscall)
+            }
+            // The digest is derived from its input state and every block it hashes.
+            Some(ecall::SHA) => {
+                let (Some(in_state), Some(block1), Some(block2), Some(count)) = (
+                    self.reg(REG_A1),
+                    self.reg(REG_A2),
+                    self.reg(REG_A3),
+                    self.reg(REG_A4),
+                ) else {
+                    return Origins::NONE;
+                };
+                let mut id = self.origins_of(in_state, DIGEST_BYTES as u32);
+                for i in 0..count {
+                    let offset = i * 2 * SHA_HALF_BLOCK_BYTES;
+                    let half1 = self.origins_of(block1.wrapping_add(offset), SHA_HALF_BLOCK_BYTES);
+                    let half2 = self.origins_of(block2.wrapping_add(offset), SHA_HALF_BLOCK_BYTES);
+                    id = self.origins.union(id, half1);
+                    id = self.origins.union(id, half2);
+                }
+                id
+            }
+            Some(ecall::BIGINT) => {
+                [REG_A2, REG_A3, REG_A4]
+                    .into_iter()
+                    .fold(Origins::NONE, |acc, idx| match self.reg(idx) {
+                        Some(ptr) => {
+                            let id = self.origins_of(ptr, BIGINT_BYTES);
+                            self.origins.union(acc, id)
+                        }
+                        None => acc,
+                    })
+            }
+            // The input digest is committed as part of the claim.
+            _ => Origins::NONE,
+        }
+    }
+}
+
+impl TraceCallback for TaintTracker {
+    fn trace_callback(&mut self, event: TraceEvent) -> Result<()> {
+        match event {
+            TraceEvent::InstructionStart { insn, .. } => self.on_insn(insn),
+            TraceEvent::RegisterSet { idx, value } => {
+                self.regs[idx] = value;
+                self.known |= 1 << idx;
+                self.reg_origins[idx] = self.result;
+            }
+            TraceEvent::MemorySet { addr, region } if !self.store => {
+                self.set_origins(addr, region.len() as u32, self.result);
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_circuit_rv32im::trace::{TraceCallback, TraceEvent};
+    use risc0_zkvm_platform::syscall::{
+        ecall,
+        reg_abi::{REG_A1, REG_A2, REG_A3, REG_A4, REG_T0},
+    };
+
+    use super::TaintTracker;
+
+    fn exec(tracker: &mut TaintTracker, insn: u32, events: Vec<TraceEvent>) {
+        let start = TraceEvent::InstructionStart {
+            cycle: 0,
+            pc: 0,
+            insn,
+        };
+        for event in [start].into_iter().chain(events) {
+            tracker.trace_callback(event).unwrap();
+        }
+    }
+
+    fn set_reg(idx: usize, value: u32) -> TraceEvent {
+        TraceEvent::RegisterSet { idx, value }
+    }
+
+    #[test]
+    fn propagation() {
+        let mut tracker = TaintTracker::default();
+        // addi t0, zero, 2; a software ecall writing a word to 0x1000.
+        exec(&mut tracker, 0x00200293, vec![set_reg(5, 2)]);
+        exec(
+            &mut tracker,
+            0x00000073,
+            vec![TraceEvent::MemorySet {
+                addr: 0x1000,
+                region: vec![1, 2, 3, 4],
+            }],
+        );
+        assert_eq!(tracker.count_tainted(0x1000, 4), 4);
+
+        // lui a0, 0x1; lw a1, 0(a0); sw a1, 8(a0)
+        exec(&mut tracker, 0x00001537, vec![set_reg(10, 0x1000)]);
+        exec(&mut tracker, 0x00052583, vec![set_reg(11, 0x04030201)]);
+        assert!(tracker.reg_tainted(11));
+        exec(&mut tracker, 0x00b52423, vec![]);
+        assert_eq!(tracker.count_tainted(0x1008, 4), 4);
+
+        // addi a1, zero, 0 clears the taint of a1, and sb a1, 9(a0) clears one byte.
+        exec(&mut tracker, 0x00000593, vec![set_reg(11, 0)]);
+        assert!(!tracker.reg_tainted(11));
+        exec(&mut tracker, 0x00b504a3, vec![]);
+        assert_eq!(tracker.count_tainted(0x1008, 4), 3);
+    }
+
+    #[test]
+    fn sha() {
+        let mut tracker = TaintTracker::default();
+        // A software ecall writing a word to 0x1000.
+        exec(
+            &mut tracker,
+            0x00000013,
+            vec![set_reg(REG_T0, ecall::SOFTWARE)],
+        );
+        exec(
+            &mut tracker,
+            0x00000073,
+            vec![TraceEvent::MemorySet {
+                addr: 0x1000,
+                region: vec![1, 2, 3, 4],
+            }],
+        );
+
+        // Hash a block from 0x1000 into a digest at 0x3000.
+        exec(
+            &mut tracker,
+            0x00000013,
+            vec![
+                set_reg(REG_T0, ecall::SHA),
+                set_reg(REG_A1, 0x2000),
+                set_reg(REG_A2, 0x1000),
+                set_reg(REG_A3, 0x1020),
+                set_reg(REG_A4, 1),
+            ],
+        );
+        exec(
+            &mut tracker,
+            0x00000073,
+            vec![TraceEvent::MemorySet {
+                addr: 0x3000,
+                region: vec![0; 32],
+            }],
+        );
+        // The input is still tainted, and so is the digest derived from it.
+        assert_eq!(tracker.count_tainted(0x1000, 4), 4);
+        assert_eq!(tracker.count_tainted(0x3000, 32), 32);
+
+        // Verifying a claim that the digest was hashed into clears both.
+        let claim = tracker.origins_of(0x3000, 32);
+        tracker.origins.verify(claim);
+        assert_eq!(tracker.count_tainted(0x1000, 4), 0);
+        assert_eq!(tracker.count_tainted(0x3000, 32), 0);
+    }
+}
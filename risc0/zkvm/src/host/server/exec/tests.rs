@@ -13,34 +13,61 @@
 // limitations under the License.
 
 use std::{
-    collections::{BTreeMap, HashSet},
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
     io::Cursor,
+    ops::ControlFlow,
+    rc::Rc,
     str::from_utf8,
-    sync::Mutex,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
 };
 
 use anyhow::Result;
 use bytes::Bytes;
 use risc0_binfmt::{MemoryImage, Program};
+use risc0_circuit_rv32im::prove::emu::testutil;
+use risc0_zkp::core::digest::DIGEST_BYTES;
 use risc0_zkvm_methods::{
     multi_test::{MultiTestSpec, SYS_MULTI_TEST, SYS_MULTI_TEST_WORDS},
-    BLST_ELF, HELLO_COMMIT_ELF, MULTI_TEST_ELF, RAND_ELF, SLICE_IO_ELF, STANDARD_LIB_ELF,
+    BLST_ELF, HELLO_COMMIT_ELF, HELLO_COMMIT_ID, MULTI_TEST_ELF, RAND_ELF, SLICE_IO_ELF,
+    STANDARD_LIB_ELF, STATEMENT_ELF, SYSCALL_MANIFEST_ELF,
+};
+use risc0_zkvm_platform::{
+    fileno,
+    memory::{GUEST_MAX_MEM, MEMORY_OUTPUT, STACK_TOP, SYSTEM},
+    syscall::{
+        nr::{SYS_CYCLE_COUNT, SYS_RANDOM, SYS_READ, SYS_WRITE},
+        reg_abi::{REG_A1, REG_A4},
+    },
+    PAGE_SIZE, WORD_SIZE,
 };
-use risc0_zkvm_platform::{fileno, syscall::nr::SYS_RANDOM, PAGE_SIZE, WORD_SIZE};
 use sha2::{Digest as _, Sha256};
 use test_log::test;
 
 use crate::{
+    authenticated::AuthenticatedStore,
+    guest::fp::FpEnv,
     host::server::{
         exec::{
-            profiler::{Frame, Profiler},
-            syscall::{Syscall, SyscallContext},
+            executor::{check_outcomes, RunOutcome},
+            profiler::Profiler,
+            symbolizer::{Frame, Symbolizer},
+            syscall::{SysCycleCount, Syscall, SyscallContext},
         },
         testutils,
     },
     serde::to_vec,
     sha::{Digest, Digestible},
-    ExecutorEnv, ExecutorImpl, ExitCode,
+    BatchExecutor, DualExecutor, DualGuest, ExecutionCache, ExecutionEvent, ExecutionEventKind,
+    ExecutorEnv, ExecutorEnvBuilder, ExecutorImpl, ExecutorPool, ExecutorState, ExitCode, FdQuota,
+    FdQuotaExceeded, FdUsage, MaybePruned, PerfWarning, QuotaManager, ReceiptClaim,
+    RunawayHeuristics, RunawayKind, SegmentReceipt, Session, Sink, Statement, SyscallManifest,
+    SyscallProvenance, SyscallRing, SyscallTranscript, Tee, TenantQuota, TraceEvent, WatchHit,
+    WatchKind, WriteCombiner,
 };
 
 fn run_test(spec: MultiTestSpec) {
@@ -81,6 +108,38 @@ fn insufficient_segment_limit() {
         .unwrap();
 }
 
+#[test]
+fn owned_elf() {
+    let run = |exec: Result<ExecutorImpl>| {
+        let session = exec.unwrap().run().unwrap();
+        let segment = session.segments[0].resolve().unwrap();
+        (segment.inner.pre_state.digest(), session.exit_code)
+    };
+    let env = || {
+        ExecutorEnv::builder()
+            .write(&MultiTestSpec::DoNothing)
+            .unwrap()
+            .build()
+            .unwrap()
+    };
+    let expected = run(ExecutorImpl::from_elf(env(), MULTI_TEST_ELF));
+    assert_eq!(
+        run(ExecutorImpl::from_owned_elf(env(), MULTI_TEST_ELF.to_vec())),
+        expected
+    );
+    assert_eq!(
+        run(ExecutorImpl::from_owned_elf(
+            env(),
+            Bytes::from_static(MULTI_TEST_ELF)
+        )),
+        expected
+    );
+    assert_eq!(
+        expected.0,
+        risc0_binfmt::compute_image_id(MULTI_TEST_ELF).unwrap()
+    );
+}
+
 #[test]
 fn basic() {
     let env = ExecutorEnv::default();
@@ -110,6 +169,419 @@ fn basic() {
     assert_eq!(segment.index, 0);
 }
 
+#[test]
+fn breakpoints() {
+    let image = BTreeMap::from([
+        (0x4000, 0x00000713), // li a4, 0
+        (0x4004, 0x00300793), // li a5, 3
+        (0x4008, 0x00170713), // loop: addi a4, a4, 1
+        (0x400c, 0xfef74ee3), // blt a4, a5, loop
+        (0x4010, 0x000055b7), // lui a1, 0x5
+        (0x4014, 0x00000073), // ecall(halt)
+    ]);
+    let program = Program {
+        entry: 0x4000,
+        image,
+    };
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = ExecutorImpl::new(ExecutorEnv::default(), image).unwrap();
+
+    // Each run stops at the top of the loop, and resuming runs one iteration.
+    exec.add_breakpoint(0x4008);
+    for count in 0..3 {
+        let session = exec.run().unwrap();
+        assert_eq!(session.exit_code, ExitCode::SystemSplit);
+        assert_eq!(session.post_state.pc, 0x4008);
+        assert_eq!(exec.stopped_at(), Some(0x4008));
+        assert_eq!(exec.peek_register(REG_A4).unwrap(), count);
+    }
+    assert!(exec.remove_breakpoint(0x4008));
+
+    exec.run_until(0x4014).unwrap();
+    assert_eq!(exec.stopped_at(), Some(0x4014));
+    assert_eq!(exec.peek_register(REG_A1).unwrap(), 0x5000);
+
+    let session = exec.run().unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert_eq!(exec.stopped_at(), None);
+    assert!(session
+        .events()
+        .iter()
+        .any(|event| event.kind == ExecutionEventKind::Breakpoint { pc: 0x4014 }));
+}
+
+#[test]
+fn step() {
+    let image = BTreeMap::from([
+        (0x4000, 0x000055b7), // lui a1, 0x5
+        (0x4004, 0x00b5a023), // sw a1, 0(a1)
+        (0x4008, 0x00000073), // ecall(halt)
+    ]);
+    let program = Program {
+        entry: 0x4000,
+        image,
+    };
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = ExecutorImpl::new(ExecutorEnv::default(), image).unwrap();
+
+    let step = exec.step().unwrap();
+    assert_eq!(step.pc, 0x4000);
+    assert_eq!(step.insn, 0x000055b7);
+    assert_eq!(step.mnemonic, "lui");
+    assert_eq!(step.next_pc, 0x4004);
+    assert_eq!(step.register_writes, [(REG_A1, 0x5000)]);
+    assert!(step.memory_writes.is_empty());
+    assert_eq!(step.exit_code, None);
+    assert_eq!(exec.peek_register(REG_A1).unwrap(), 0x5000);
+
+    let step = exec.step().unwrap();
+    assert_eq!(step.mnemonic, "sw");
+    assert!(step.register_writes.is_empty());
+    assert_eq!(
+        step.memory_writes,
+        [(0x5000, 0x5000u32.to_le_bytes().to_vec())]
+    );
+    assert_eq!(exec.peek_u32(0x5000).unwrap(), 0x5000);
+
+    let step = exec.step().unwrap();
+    assert_eq!(step.pc, 0x4008);
+    assert_eq!(step.mnemonic, "ecall");
+    assert_eq!(step.exit_code, Some(ExitCode::Halted(0)));
+}
+
+#[test]
+fn steps() {
+    let image = BTreeMap::from([
+        (0x4000, 0x000055b7), // lui a1, 0x5
+        (0x4004, 0x00b5a023), // sw a1, 0(a1)
+        (0x4008, 0x00000073), // ecall(halt)
+    ]);
+    let program = Program {
+        entry: 0x4000,
+        image,
+    };
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = ExecutorImpl::new(ExecutorEnv::default(), image).unwrap();
+
+    let mnemonics = exec
+        .steps(|stepper| {
+            let lui = stepper.step()?;
+            assert_eq!(stepper.peek_register(REG_A1)?, 0x5000);
+            let sw = stepper.step()?;
+            assert_eq!(stepper.peek_u32(0x5000)?, 0x5000);
+            assert_eq!(stepper.pc(), 0x4008);
+            Ok([lui.mnemonic, sw.mnemonic])
+        })
+        .unwrap();
+    assert_eq!(mnemonics, ["lui", "sw"]);
+    assert_eq!(exec.pc(), 0x4008);
+    assert_eq!(exec.peek_u32(0x5000).unwrap(), 0x5000);
+
+    let exit_code = exec
+        .steps(|stepper| {
+            let step = stepper.step()?;
+            assert!(stepper.step().is_err());
+            Ok(step.exit_code)
+        })
+        .unwrap();
+    assert_eq!(exit_code, Some(ExitCode::Halted(0)));
+}
+
+#[cfg(feature = "gdb")]
+#[test]
+fn gdb_stub() {
+    use std::io::{Read, Write};
+
+    use crate::GdbStub;
+
+    // The side of the debugger: the packets it sends, and everything the stub sends back.
+    struct Debugger {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Debugger {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Debugger {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let image = BTreeMap::from([
+        (0x4000, 0x000055b7), // lui a1, 0x5
+        (0x4004, 0x00b5a023), // sw a1, 0(a1)
+        (0x4008, 0x00000073), // ecall(halt)
+    ]);
+    let program = Program {
+        entry: 0x4000,
+        image,
+    };
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = ExecutorImpl::new(ExecutorEnv::default(), image).unwrap();
+
+    let requests = [
+        ("?", "S05"),
+        ("p20", "00400000"),
+        ("m4000,4", "b7550000"),
+        ("Z0,4008,4", "OK"),
+        ("c", "S05"),
+        ("pb", "00500000"),
+        ("m5000,4", "00500000"),
+        ("M5000,4:efbeadde", "OK"),
+        ("m5000,4", "efbeadde"),
+        ("p21", "E01"),
+        ("z0,4008,4", "OK"),
+        ("s", "W00"),
+        ("c", "W00"),
+    ];
+    let mut input = String::new();
+    for (request, _) in requests {
+        let checksum = request
+            .bytes()
+            .fold(0u8, |sum, byte| sum.wrapping_add(byte));
+        // Every reply is acknowledged.
+        input += &format!("${request}#{checksum:02x}+");
+    }
+    input += "$k#6b";
+
+    let mut debugger = Debugger {
+        input: Cursor::new(input.into_bytes()),
+        output: Vec::new(),
+    };
+    GdbStub::new(&mut exec)
+        .serve_connection(&mut debugger)
+        .unwrap();
+
+    let output = from_utf8(&debugger.output).unwrap();
+    let replies: Vec<_> = output
+        .split('$')
+        .skip(1)
+        .map(|packet| packet.split_once('#').unwrap().0)
+        .collect();
+    let expected: Vec<_> = requests.iter().map(|(_, reply)| *reply).collect();
+    assert_eq!(replies, expected);
+    assert_eq!(output.matches('+').count(), requests.len() + 1);
+}
+
+#[test]
+fn watchpoints() {
+    let image = BTreeMap::from([
+        (0x4000, 0x000055b7), // lui a1, 0x5
+        (0x4004, 0x00b5a023), // sw a1, 0(a1)
+        (0x4008, 0x0045a603), // lw a2, 4(a1)
+        (0x400c, 0x00000073), // ecall(halt)
+    ]);
+    let program = Program {
+        entry: 0x4000,
+        image,
+    };
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut events = Vec::new();
+    {
+        let env = ExecutorEnv::builder()
+            .watch(0x5000..0x5004, WatchKind::Write)
+            .watch(0x5004..0x5008, WatchKind::Access)
+            .watch(0x6000..0x7000, WatchKind::Access)
+            .trace_callback(|event| {
+                if let TraceEvent::Watchpoint { .. } = event {
+                    events.push(event);
+                }
+                Ok(())
+            })
+            .build()
+            .unwrap();
+        let session = ExecutorImpl::new(env, image).unwrap().run().unwrap();
+        assert_eq!(session.exit_code, ExitCode::Halted(0));
+    }
+    assert_eq!(
+        events,
+        [
+            TraceEvent::Watchpoint {
+                pc: 0x4004,
+                addr: 0x5000,
+                kind: WatchKind::Write,
+            },
+            TraceEvent::Watchpoint {
+                pc: 0x4008,
+                addr: 0x5004,
+                kind: WatchKind::Read,
+            },
+        ]
+    );
+}
+
+#[test]
+fn watch_callback() {
+    let image = BTreeMap::from([
+        (0x4000, 0x000055b7), // lui a1, 0x5
+        (0x4004, 0x00b5a023), // sw a1, 0(a1)
+        (0x4008, 0x0045a603), // lw a2, 4(a1)
+        (0x400c, 0x00000073), // ecall(halt)
+    ]);
+    let program = Program {
+        entry: 0x4000,
+        image,
+    };
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut hits = Vec::new();
+    {
+        let env = ExecutorEnv::builder()
+            .watch(0x5000..0x5004, WatchKind::Write)
+            .watch(0x5004..0x5008, WatchKind::Access)
+            .watch_callback(|hit| {
+                hits.push(hit);
+                Ok(())
+            })
+            .build()
+            .unwrap();
+        let session = ExecutorImpl::new(env, image).unwrap().run().unwrap();
+        assert_eq!(session.exit_code, ExitCode::Halted(0));
+    }
+    assert_eq!(
+        hits,
+        [
+            WatchHit {
+                pc: 0x4004,
+                addr: 0x5000,
+                kind: WatchKind::Write,
+            },
+            WatchHit {
+                pc: 0x4008,
+                addr: 0x5004,
+                kind: WatchKind::Read,
+            },
+        ]
+    );
+
+    // Writes by the host in answer to a syscall are reported too, at the `ecall`.
+    let mut hits = Vec::new();
+    {
+        let env = ExecutorEnv::builder()
+            .stdin(&[1u8, 2, 3, 4, 5, 6, 7, 8][..])
+            .watch(0x5004..0x5008, WatchKind::Write)
+            .watch_callback(|hit| {
+                hits.push(hit);
+                Ok(())
+            })
+            .build()
+            .unwrap();
+        ExecutorImpl::new(env, read_stdin_image())
+            .unwrap()
+            .run()
+            .unwrap();
+    }
+    assert_eq!(
+        hits,
+        [WatchHit {
+            pc: 0x4028,
+            addr: 0x5004,
+            kind: WatchKind::Write,
+        }]
+    );
+}
+
+#[test]
+fn snapshot_restore() {
+    let image = BTreeMap::from([
+        (0x4000, 0x00000713), // li a4, 0
+        (0x4004, 0x00300793), // li a5, 3
+        (0x4008, 0x00170713), // loop: addi a4, a4, 1
+        (0x400c, 0xfef74ee3), // blt a4, a5, loop
+        (0x4010, 0x00000073), // ecall(halt)
+    ]);
+    let program = Program {
+        entry: 0x4000,
+        image,
+    };
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = ExecutorImpl::new(ExecutorEnv::default(), image.clone()).unwrap();
+    exec.run_until(0x400c).unwrap();
+    assert_eq!(exec.peek_register(REG_A4).unwrap(), 1);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("state.bincode");
+    exec.snapshot().unwrap().save(&path).unwrap();
+    let state = ExecutorState::load(&path).unwrap();
+    assert_eq!(state.pc(), 0x400c);
+    assert!(state.user_cycles() > 0);
+
+    let mut restored = ExecutorImpl::new(ExecutorEnv::default(), image).unwrap();
+    restored.restore(state).unwrap();
+    assert_eq!(restored.peek_register(REG_A4).unwrap(), 1);
+    let session = restored.run().unwrap();
+    let expected = exec.run().unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert_eq!(session.post_state, expected.post_state);
+    assert_eq!(restored.peek_register(REG_A4).unwrap(), 3);
+    assert_eq!(session.events(), expected.events());
+}
+
+/// A program that reads a word from stdin into 0x5000, then another into 0x5004, and halts.
+fn read_stdin_image() -> MemoryImage {
+    let mut image = BTreeMap::from([
+        (0x4000, 0x00200293), // li t0, 2 (software)
+        (0x4004, 0x00005537), // lui a0, 0x5
+        (0x4008, 0x00100593), // li a1, 1
+        (0x400c, 0x00006637), // lui a2, 0x6
+        (0x4010, 0x00000693), // li a3, 0 (stdin)
+        (0x4014, 0x00400713), // li a4, 4
+        (0x4018, 0x00000073), // ecall(sys_read)
+        (0x401c, 0x00005537), // lui a0, 0x5
+        (0x4020, 0x00450513), // addi a0, a0, 4
+        (0x4024, 0x00100593), // li a1, 1
+        (0x4028, 0x00000073), // ecall(sys_read)
+        (0x402c, 0x00000293), // li t0, 0 (halt)
+        (0x4030, 0x00000513), // li a0, 0
+        (0x4034, 0x00000593), // li a1, 0
+        (0x4038, 0x00000073), // ecall(halt)
+    ]);
+    let mut name = SYS_READ.as_str().as_bytes().to_vec();
+    name.resize(name.len() / WORD_SIZE * WORD_SIZE + WORD_SIZE, 0);
+    for (i, word) in name.chunks(WORD_SIZE).enumerate() {
+        image.insert(
+            0x6000 + (i * WORD_SIZE) as u32,
+            u32::from_le_bytes(word.try_into().unwrap()),
+        );
+    }
+    let program = Program {
+        entry: 0x4000,
+        image,
+    };
+    MemoryImage::new(&program, PAGE_SIZE as u32).unwrap()
+}
+
+#[test]
+fn snapshot_restore_input() {
+    let image = read_stdin_image();
+    let input = [1u8, 2, 3, 4, 5, 6, 7, 8];
+    let env = || ExecutorEnv::builder().stdin(&input[..]).build().unwrap();
+
+    let mut exec = ExecutorImpl::new(env(), image.clone()).unwrap();
+    exec.run_until(0x401c).unwrap();
+    assert_eq!(exec.peek_u32(0x5000).unwrap(), 0x04030201);
+    let state = exec.snapshot().unwrap();
+
+    let mut restored = ExecutorImpl::new(env(), image.clone()).unwrap();
+    restored.restore(state.clone()).unwrap();
+    let session = restored.run().unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert_eq!(restored.peek_u32(0x5004).unwrap(), 0x08070605);
+
+    // The environment of the restored executor has to hold the input the guest already read.
+    let short = ExecutorEnv::builder().stdin(&input[..2]).build().unwrap();
+    let mut restored = ExecutorImpl::new(short, image).unwrap();
+    assert!(restored.restore(state).is_err());
+}
+
 #[test]
 fn system_split() {
     let entry = 0x4000;
@@ -155,6 +627,40 @@ fn system_split() {
     assert_eq!(segments[1].index, 1);
 }
 
+#[test]
+fn segment_po2_schedule() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles: 1 << 18 })
+        .unwrap()
+        .segment_limit_po2(16)
+        .segment_po2_schedule(&[15, 17])
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let po2s: Vec<_> = session
+        .segments
+        .iter()
+        .map(|x| x.resolve().unwrap().inner.po2)
+        .collect();
+
+    // Segments past the schedule are split at the segment limit; the last one may be smaller.
+    assert!(po2s.len() > 3);
+    assert_eq!(po2s[..3], [15, 17, 16]);
+    assert!(po2s[3..].iter().all(|&po2| po2 <= 16));
+
+    for po2 in [0, 12, 25, u32::MAX] {
+        let err = ExecutorEnv::builder()
+            .segment_po2_schedule(&[16, po2])
+            .build()
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains(&format!("Segment po2 {po2}")));
+    }
+}
+
 #[test]
 fn libm_build() {
     run_test(MultiTestSpec::LibM);
@@ -234,6 +740,42 @@ fn sha_accel() {
     run_test(MultiTestSpec::ShaConforms);
 }
 
+#[test]
+fn bigint_ops() {
+    let mut cases = testutils::generate_bigint_test_cases(&mut rand::thread_rng(), 10);
+    // A modulus of zero means arithmetic modulo 2^256, and a modulus of one reduces to zero.
+    let max = [u32::MAX; 8];
+    for modulus in [[0; 8], [1, 0, 0, 0, 0, 0, 0, 0]] {
+        cases.push(testutils::BigIntTestCase {
+            x: max,
+            y: [3, 0, 0, 0, 0, 0, 0, 1],
+            modulus,
+        });
+    }
+    for case in cases.iter() {
+        let input = MultiTestSpec::BigIntOps {
+            x: case.x,
+            y: case.y,
+            modulus: case.modulus,
+        };
+        let env = ExecutorEnv::builder()
+            .write(&input)
+            .unwrap()
+            .build()
+            .unwrap();
+        let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        assert_eq!(session.exit_code, ExitCode::Halted(0));
+        assert_eq!(
+            session.journal.unwrap().bytes.as_slice(),
+            bytemuck::cast_slice::<u32, u8>(&case.expected_ops()),
+            "{case:x?}"
+        );
+    }
+}
+
 #[test]
 fn sha_cycle_count() {
     run_test(MultiTestSpec::ShaCycleCount);
@@ -292,284 +834,313 @@ fn env_stdio() {
     assert_eq!(MSG, from_utf8(&stdout).unwrap());
 }
 
-// Tests sys_read into a buffer of bytes that may not be word aligned.
-//
-// To make sure we don't miss any edge cases, this tries all permutations of
-// start alignment, end alignment, and 0, 1, or 2 whole words.
 #[test]
-fn posix_style_read() {
+fn counting_sink() {
+    const MSG: &str = "Hello world!  This is a test of standard input and output.";
     const FD: u32 = 123;
-    // Initial buffer to read bytes on top of.
-    let buf: Vec<u8> = (b'a'..b'z')
-        .chain(b'0'..b'9')
-        .chain(b"!@#$%^&*()".iter().cloned())
-        .collect();
-    // Input to read bytes from.
-    let readbuf: Vec<u8> = (b'A'..b'Z').collect();
-
-    let run = |pos_and_len: Vec<(u32, u32)>| {
-        let mut expected = buf.to_vec();
-
-        let mut expected_readbuf = readbuf.as_slice();
-        for (pos, len) in pos_and_len.iter() {
-            let pos = *pos as usize;
-            let len = *len as usize;
+    let spec = to_vec(&MultiTestSpec::EchoStdout { nbytes: 9, fd: FD }).unwrap();
+    let env = ExecutorEnv::builder()
+        .read_fd(FD, MSG.as_bytes())
+        .stdin(bytemuck::cast_slice(&spec))
+        .stdout_sink(Sink::Counting)
+        .stderr_sink(Sink::Null)
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
 
-            let this_read;
-            (this_read, expected_readbuf) = expected_readbuf.split_at(len);
-            expected[pos..pos + len].clone_from_slice(this_read);
-        }
+    let stats = session.stats();
+    assert_eq!(stats.stdout_bytes, Some(MSG.len() as u64));
+    assert_eq!(stats.stderr_bytes, None);
+}
 
-        let spec = MultiTestSpec::SysRead {
-            fd: FD,
-            buf: buf.to_vec(),
-            pos_and_len: pos_and_len.clone(),
-        };
+#[test]
+fn journal_tee() {
+    let slice = b"stream me";
+    let copy = Rc::new(RefCell::new(Vec::new()));
+    let mut streamed = Vec::new();
+    let session = {
         let env = ExecutorEnv::builder()
-            .read_fd(FD, readbuf.as_slice())
-            .write(&spec)
-            .unwrap()
+            .write_slice(&[slice.len() as u32])
+            .write_slice(slice)
+            .journal_tee(
+                Tee::new()
+                    .with_shared(copy.clone())
+                    .with_callback(|bytes| streamed.extend_from_slice(bytes)),
+            )
             .build()
             .unwrap();
-        let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
-        let session = exec.run().unwrap();
-        assert_eq!(session.exit_code, ExitCode::Halted(0));
-
-        let (actual, num_read): (Vec<u8>, Vec<usize>) = session.journal.unwrap().decode().unwrap();
-        for ((_, len), n_read) in pos_and_len.iter().zip(num_read) {
-            assert_eq!(
-                *len as usize, n_read,
-                "length mismatch, pos and lens: {pos_and_len:?}"
-            )
-        }
-        assert_eq!(
-            from_utf8(&actual).unwrap(),
-            from_utf8(&expected).unwrap(),
-            "pos and lens: {pos_and_len:?}"
-        );
+        ExecutorImpl::from_elf(env, SLICE_IO_ELF)
+            .unwrap()
+            .run()
+            .unwrap()
     };
-
-    fn next_offset(mut pos: u32, offset: u32) -> u32 {
-        while (pos % WORD_SIZE as u32) != offset {
-            pos += 1;
-        }
-        pos
-    }
-
-    for start_offset in 0..WORD_SIZE as u32 {
-        for end_offset in 0..WORD_SIZE as u32 {
-            let mut pos = 0;
-            let mut pos_and_len: Vec<(u32, u32)> = Vec::new();
-
-            // Make up a bunch of reads to overwrite parts of the buffer.
-            for nwords in 0..3 {
-                pos = next_offset(pos, start_offset);
-                let start = pos;
-                pos += nwords * WORD_SIZE as u32;
-                pos = next_offset(pos, end_offset);
-                let len = pos - start;
-                pos_and_len.push((pos, len));
-                assert!(
-                    pos + len < buf.len() as u32,
-                    "Ran out of space to test writes. pos: {pos} len: {len} end: {end_offset} start = {start_offset}"
-                );
-                // Make sure there's at least one non-overwritten character between reads.
-                pos += 1;
-            }
-
-            run(pos_and_len);
-        }
-    }
+    assert_eq!(session.journal.unwrap().bytes, slice);
+    assert_eq!(copy.borrow().as_slice(), slice);
+    assert_eq!(streamed, slice);
 }
 
 #[test]
-fn short_read_combinations() {
+fn fd_quota() {
+    const MSG: &str = "Hello world!  This is a test of standard input and output.";
     const FD: u32 = 123;
-    // Initial buffer to read bytes on top of.
-    let buf: Vec<u8> = (b'a'..=b'l').collect();
-    // Input to read bytes from.
-    let readbuf: Vec<u8> = (b'A'..b'L').collect();
-
-    for read_len in 0..WORD_SIZE {
-        for excess_buffer in 0..WORD_SIZE * 2 {
-            let buffer = read_len + excess_buffer;
-            let mut expected = buf.to_vec();
-
-            expected[..read_len].copy_from_slice(&readbuf[..read_len]);
-
-            // The current behaviour of the read call for more bytes than available is to write
-            // zeroes for the remaining bytes.
-            expected[read_len..buffer].fill(0);
+    let spec = to_vec(&MultiTestSpec::EchoStdout { nbytes: 9, fd: FD }).unwrap();
 
-            let spec = MultiTestSpec::SysRead {
-                fd: FD,
-                buf: buf.to_vec(),
-                pos_and_len: vec![(0, buffer as u32)],
-            };
-            let env = ExecutorEnv::builder()
-                .read_fd(FD, &readbuf[..read_len])
-                .write(&spec)
-                .unwrap()
-                .build()
-                .unwrap();
-            let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
-            let session = exec.run().unwrap();
-            assert_eq!(session.exit_code, ExitCode::Halted(0));
+    // Past its byte quota, the guest sees end of file on the descriptor.
+    let mut stdout: Vec<u8> = Vec::new();
+    let session = {
+        let env = ExecutorEnv::builder()
+            .read_fd(FD, MSG.as_bytes())
+            .fd_quota(
+                FD,
+                FdQuota {
+                    max_bytes: Some(20),
+                    max_calls: None,
+                },
+            )
+            .stdin(bytemuck::cast_slice(&spec))
+            .stdout(&mut stdout)
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap()
+    };
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert_eq!(&MSG[..20], from_utf8(&stdout).unwrap());
+    let usage = FdUsage {
+        bytes: 20,
+        calls: 4,
+        exceeded: true,
+    };
+    assert_eq!(session.fd_usage, BTreeMap::from([(FD, usage)]));
+    assert_eq!(session.stats().fd_usage[&FD], usage);
 
-            let (actual, num_read): (Vec<u8>, Vec<usize>) =
-                session.journal.unwrap().decode().unwrap();
-            assert_eq!(
-                [read_len].as_slice(),
-                &num_read,
-                "length mismatch, length {read_len} buffer: {buffer}"
-            );
-            assert_eq!(
-                from_utf8(&actual).unwrap(),
-                from_utf8(&expected).unwrap(),
-                "length {read_len}, buffer {buffer}"
-            );
-        }
+    // Past its call quota, too.
+    let mut stdout: Vec<u8> = Vec::new();
+    {
+        let env = ExecutorEnv::builder()
+            .read_fd(FD, MSG.as_bytes())
+            .fd_quota(
+                FD,
+                FdQuota {
+                    max_bytes: None,
+                    max_calls: Some(2),
+                },
+            )
+            .stdin(bytemuck::cast_slice(&spec))
+            .stdout(&mut stdout)
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
     }
+    assert_eq!(&MSG[..18], from_utf8(&stdout).unwrap());
+
+    // A write past its quota fails execution.
+    let quota = FdQuota {
+        max_bytes: Some(10),
+        max_calls: None,
+    };
+    let env = ExecutorEnv::builder()
+        .read_fd(FD, MSG.as_bytes())
+        .fd_quota(fileno::STDOUT, quota)
+        .stdin(bytemuck::cast_slice(&spec))
+        .stdout_sink(Sink::Null)
+        .build()
+        .unwrap();
+    let err = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.downcast_ref::<FdQuotaExceeded>(),
+        Some(&FdQuotaExceeded {
+            fd: fileno::STDOUT,
+            quota
+        })
+    );
 }
 
 #[test]
-fn unaligned_short_read() {
-    const FD: u32 = 123;
-    // Initial buffer to read bytes on top of.
-    let buf: Vec<u8> = vec![0; 9];
-    let readbuf: &[u8] = b"1234567";
-
-    let spec = MultiTestSpec::SysRead {
-        fd: FD,
-        buf: buf.clone(),
-        pos_and_len: vec![(0, 9)],
-    };
+fn paging_stats() {
     let env = ExecutorEnv::builder()
-        .read_fd(FD, readbuf)
-        .write(&spec)
+        .write(&MultiTestSpec::DoNothing)
         .unwrap()
         .build()
         .unwrap();
-    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
-    let session = exec.run().unwrap();
-    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
 
-    let actual: Vec<u8> = session.journal.unwrap().decode().unwrap();
-    let mut expected = readbuf.to_vec();
-    expected.resize(buf.len(), 0);
-    assert_eq!(actual, expected, "pos and lens: {spec:?}");
+    let stats = session.stats();
+    let paging_cycles = stats.paging_cycles.unwrap();
+    assert!(paging_cycles > 0);
+    assert!(stats.saved_paging_cycles.unwrap() > 0);
+    assert!(stats.user_cycles + paging_cycles <= stats.total_cycles);
 }
 
 #[test]
-fn large_io_words() {
-    const FD: u32 = 123;
-    let buf: Vec<u32> = (0..400_000).collect();
-    let expected = buf.clone();
-    let input = MultiTestSpec::EchoWords {
-        fd: FD,
-        nwords: buf.len() as u32,
-    };
+fn insn_stats() {
     let env = ExecutorEnv::builder()
-        .read_fd(FD, bytemuck::cast_slice(&buf))
-        .write(&input)
+        .write(&MultiTestSpec::DoNothing)
         .unwrap()
-        .session_limit(Some(20_000_000))
+        .insn_stats(true)
         .build()
         .unwrap();
-    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
-    let session = exec.run().unwrap();
-    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
 
-    let actual: &[u32] = bytemuck::cast_slice(&session.journal.as_ref().unwrap().bytes);
-    assert_eq!(actual, expected);
+    let stats = session.stats();
+    let counts = stats.insn_counts.unwrap();
+    assert!(counts["eany"] > 0);
+    assert!(counts["lw"] > 0);
+    assert!(counts.values().sum::<u64>() <= stats.user_cycles);
 }
 
 #[test]
-fn large_io_bytes() {
-    const FD: u32 = 123;
-    let buf: Vec<u32> = (0..400_000).collect();
-    let nbytes = (buf.len() * WORD_SIZE) as u32;
-    let spec = to_vec(&MultiTestSpec::EchoStdout { nbytes, fd: FD }).unwrap();
-    let mut stdout: Vec<u8> = Vec::new();
-    {
+fn accelerator_stats() {
+    let run = |spec: MultiTestSpec| {
         let env = ExecutorEnv::builder()
-            .read_fd(FD, bytemuck::cast_slice(&buf))
-            .stdin(bytemuck::cast_slice(&spec))
-            .stdout(&mut stdout)
+            .write(&spec)
+            .unwrap()
             .build()
             .unwrap();
-        let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
             .unwrap()
             .run()
-            .unwrap();
-        assert_eq!(session.exit_code, ExitCode::Halted(0));
-    }
-    let actual: &[u32] = bytemuck::cast_slice(&stdout);
-    assert_eq!(&buf, actual);
-}
-
-mod sys_verify {
-    use risc0_zkvm_methods::{
-        multi_test::MultiTestSpec, HELLO_COMMIT_ELF, HELLO_COMMIT_ID, MULTI_TEST_ELF, MULTI_TEST_ID,
+            .unwrap()
     };
-    use test_log::test;
 
-    use crate::{
-        serde::to_vec, sha::Digestible, ExecutorEnv, ExecutorEnvBuilder, ExecutorImpl, ExitCode,
-        MaybePruned, ReceiptClaim, Session,
-    };
+    let session = run(MultiTestSpec::DoNothing);
+    assert!(!session.accelerator_stats().contains_key("bigint"));
 
-    fn exec_hello_commit() -> Session {
-        let session = ExecutorImpl::from_elf(ExecutorEnv::default(), HELLO_COMMIT_ELF)
+    let session = run(MultiTestSpec::ShaDigest {
+        data: vec![7; 1000],
+    });
+    let sha = session.accelerator_stats()["sha"];
+    assert!(sha.invocations > 0);
+    assert!(sha.cycles >= sha.invocations);
+    assert!(sha.cycles <= session.user_cycles);
+}
+
+#[test]
+fn perf_warnings() {
+    const FD: u32 = 123;
+    let input = vec![b'x'; 2000];
+    let spec = to_vec(&MultiTestSpec::EchoStdout { nbytes: 1, fd: FD }).unwrap();
+    let session = {
+        let env = ExecutorEnv::builder()
+            .read_fd(FD, input.as_slice())
+            .stdin(bytemuck::cast_slice(&spec))
+            .stdout(std::io::sink())
+            .perf_warnings(true)
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
             .unwrap()
             .run()
-            .unwrap();
-        assert_eq!(session.exit_code, ExitCode::Halted(0));
-        session
-    }
+            .unwrap()
+    };
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert!(
+        session.perf_warnings.iter().any(|warning| matches!(
+            warning,
+            PerfWarning::SmallReads { fd: FD, calls, .. } if *calls > 2000
+        )),
+        "{:?}",
+        session.perf_warnings
+    );
 
-    fn exec_halt(exit_code: u8) -> Session {
-        let env = ExecutorEnvBuilder::default()
-            .write(&MultiTestSpec::Halt(exit_code))
+    // Nothing is reported unless asked for.
+    let env = ExecutorEnv::builder()
+        .read_fd(FD, input.as_slice())
+        .stdin(bytemuck::cast_slice(&spec))
+        .stdout(std::io::sink())
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert!(session.perf_warnings.is_empty());
+}
+
+#[test]
+fn taint_tracking() {
+    let hello_commit = ExecutorImpl::from_elf(ExecutorEnv::default(), HELLO_COMMIT_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let run = |spec: MultiTestSpec| {
+        let env = ExecutorEnv::builder()
+            .write(&spec)
             .unwrap()
+            .taint_tracking(true)
+            .add_assumption(hello_commit.claim().unwrap())
             .build()
             .unwrap();
-        let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
             .unwrap()
             .run()
-            .unwrap();
-        assert_eq!(session.exit_code, ExitCode::Halted(exit_code as u32));
-        session
-    }
-
-    fn exec_pause(exit_code: u8) -> Session {
-        let env = ExecutorEnvBuilder::default()
-            .write(&MultiTestSpec::PauseResume(exit_code))
             .unwrap()
-            .build()
-            .unwrap();
-        let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
-            .unwrap()
-            .run()
-            .unwrap();
-        assert_eq!(session.exit_code, ExitCode::Paused(exit_code as u32));
-        session
-    }
+    };
 
-    #[test]
-    fn sys_verify() {
-        let hello_commit_session = exec_hello_commit();
+    // Bytes read from the host and committed as they are.
+    let bytes = b"unverified".to_vec();
+    let session = run(MultiTestSpec::Echo {
+        bytes: bytes.clone(),
+    });
+    assert_eq!(session.journal.unwrap().bytes, bytes);
+    assert_eq!(session.tainted_commits.len(), 1);
+    assert_eq!(session.tainted_commits[0].tainted, bytes.len() as u32);
 
-        let spec = &MultiTestSpec::SysVerify(vec![(
-            HELLO_COMMIT_ID.into(),
-            hello_commit_session.journal.clone().unwrap().bytes,
-        )]);
+    // A digest of host data is derived from the host.
+    let session = run(MultiTestSpec::ShaDigest { data: bytes });
+    assert_eq!(session.tainted_commits.len(), 1);
+    assert_eq!(session.tainted_commits[0].tainted, DIGEST_BYTES as u32);
 
-        // Test that it works when the assumption is added.
+    // A journal hashed into a claim verified by the guest is no longer tainted.
+    let journal = hello_commit.journal.unwrap().bytes;
+    let session = run(MultiTestSpec::CommitVerified {
+        image_id: HELLO_COMMIT_ID.into(),
+        journal: journal.clone(),
+    });
+    assert_eq!(session.journal.unwrap().bytes, journal);
+    assert!(
+        session.tainted_commits.is_empty(),
+        "{:?}",
+        session.tainted_commits
+    );
+}
+
+#[test]
+fn cycle_audit() {
+    // Accelerators, syscalls, and splits all agree with the preflight.
+    for spec in [
+        MultiTestSpec::BusyLoop { cycles: 1 << 17 },
+        MultiTestSpec::ShaDigest {
+            data: vec![7; 1000],
+        },
+        MultiTestSpec::Echo {
+            bytes: b"audited".to_vec(),
+        },
+    ] {
         let env = ExecutorEnv::builder()
             .write(&spec)
             .unwrap()
-            .add_assumption(hello_commit_session.claim().unwrap())
+            .segment_limit_po2(16)
+            .cycle_audit(true)
             .build()
             .unwrap();
         let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
@@ -577,319 +1148,2423 @@ mod sys_verify {
             .run()
             .unwrap();
         assert_eq!(session.exit_code, ExitCode::Halted(0));
+    }
+}
 
-        // Test that it does not work when the assumption is not added.
+#[test]
+fn hugepages() {
+    let run = |hugepages| {
         let env = ExecutorEnv::builder()
-            .write(&spec)
+            .write(&MultiTestSpec::ShaDigest {
+                data: vec![7; 64 * 1024],
+            })
             .unwrap()
+            .segment_limit_po2(16)
+            .hugepages(hugepages)
             .build()
             .unwrap();
-        assert!(ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
             .unwrap()
             .run()
-            .is_err());
-    }
-
-    #[test]
-    fn sys_verify_halt_codes() {
-        for code in [0u8, 1, 2, 255] {
-            tracing::debug!("sys_verify_pause_codes: code = {code}");
-            let halt_session = exec_halt(code);
-
-            let spec = &MultiTestSpec::SysVerify(vec![(MULTI_TEST_ID.into(), Vec::new())]);
+            .unwrap()
+    };
 
-            let env = ExecutorEnv::builder()
-                .write(&spec)
-                .unwrap()
-                .add_assumption(halt_session.claim().unwrap())
-                .build()
-                .unwrap();
-            let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap().run();
+    // Where hugepages are unavailable, execution falls back to the heap.
+    let session = run(true);
+    let expected = run(false);
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert_eq!(session.post_state.digest(), expected.post_state.digest());
+    assert_eq!(session.journal, expected.journal);
+    assert_eq!(session.segments.len(), expected.segments.len());
+}
 
-            if code == 0 {
-                assert_eq!(session.unwrap().exit_code, ExitCode::Halted(0));
-            } else {
-                assert!(session.is_err());
-            }
-        }
+#[test]
+fn session_id() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .session_id("job-1")
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.id, "job-1");
+    for segment in &session.segments {
+        assert_eq!(segment.resolve().unwrap().session_id, "job-1");
     }
 
-    #[test]
-    fn sys_verify_integrity() {
-        let hello_commit_session = exec_hello_commit();
-
-        let spec = &MultiTestSpec::SysVerifyIntegrity {
-            claim_words: to_vec(&hello_commit_session.claim().unwrap()).unwrap(),
-        };
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let other = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(other.id.len(), 32);
+    assert_ne!(other.id, session.id);
+}
 
-        // Test that it works when the assumption is added.
-        let env = ExecutorEnv::builder()
-            .write(&spec)
-            .unwrap()
-            .add_assumption(hello_commit_session.claim().unwrap())
-            .build()
-            .unwrap();
-        let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
-            .unwrap()
-            .run()
-            .unwrap();
-        assert_eq!(session.exit_code, ExitCode::Halted(0));
+#[test]
+fn segment_stats() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles: 1 << 17 })
+        .unwrap()
+        .segment_limit_po2(16)
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert!(session.segments.len() > 1);
 
-        // Test that it does not work when the assumption is not added.
-        let env = ExecutorEnv::builder()
-            .write(&spec)
-            .unwrap()
-            .build()
-            .unwrap();
-        assert!(ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
-            .unwrap()
-            .run()
-            .is_err());
+    let mut paging_cycles = 0;
+    for (index, segment_ref) in session.segments.iter().enumerate() {
+        // FileSegmentRef records the stats without deserializing the segment.
+        let stats = segment_ref.stats().unwrap();
+        assert_eq!(stats, segment_ref.resolve().unwrap().stats());
+        assert_eq!(stats.index, index as u32);
+        assert!(stats.page_reads > 0);
+        assert!(stats.page_writes <= stats.page_reads);
+        assert!(stats.user_cycles < stats.total_cycles());
+        assert!(stats.paging_ratio() > 0.0 && stats.paging_ratio() < 1.0);
+        paging_cycles += stats.page_read_cycles + stats.page_write_cycles;
     }
+    assert_eq!(session.stats().paging_cycles, Some(paging_cycles));
+}
 
-    #[test]
-    fn sys_verify_integrity_halt_codes() {
-        for code in [0u8, 1, 2, 255] {
-            tracing::debug!("sys_verify_pause_codes: code = {code}");
-            let halt_session = exec_halt(code);
-
-            let spec = &MultiTestSpec::SysVerifyIntegrity {
-                claim_words: to_vec(&halt_session.claim().unwrap()).unwrap(),
-            };
+#[test]
+fn audit_session() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles: 1 << 17 })
+        .unwrap()
+        .segment_limit_po2(16)
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert!(session.segments.len() > 1);
 
-            let env = ExecutorEnv::builder()
-                .write(&spec)
-                .unwrap()
-                .add_assumption(halt_session.claim().unwrap())
-                .build()
-                .unwrap();
-            let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
-                .unwrap()
-                .run()
-                .unwrap();
-            assert_eq!(session.exit_code, ExitCode::Halted(0));
-        }
-    }
+    // Stand in for proven receipts with receipts carrying the claims the prover would produce.
+    let receipts: Vec<_> = session
+        .segments
+        .iter()
+        .map(|segment_ref| {
+            let segment = segment_ref.resolve().unwrap();
+            SegmentReceipt {
+                seal: vec![],
+                index: segment.index,
+                hashfn: "poseidon2".into(),
+                verifier_parameters: Digest::ZERO,
+                claim: ReceiptClaim {
+                    pre: segment.inner.pre_state.clone().into(),
+                    post: segment.inner.post_state.clone().into(),
+                    exit_code: segment.inner.exit_code,
+                    input: MaybePruned::Pruned(segment.inner.input_digest),
+                    output: segment.output.clone().into(),
+                },
+            }
+        })
+        .collect();
+    crate::audit_session(&session, &receipts).unwrap();
 
-    #[test]
-    fn sys_verify_integrity_pause_codes() {
-        for code in [0u8, 1, 2, 255] {
-            tracing::debug!("sys_verify_halt_codes: code = {code}");
-            let pause_session = exec_pause(code);
+    let mut wrong_exit = receipts.clone();
+    wrong_exit.last_mut().unwrap().claim.exit_code = ExitCode::Paused(0);
+    assert!(crate::audit_session(&session, &wrong_exit).is_err());
 
-            let spec = &MultiTestSpec::SysVerifyIntegrity {
-                claim_words: to_vec(&pause_session.claim().unwrap()).unwrap(),
-            };
+    let mut swapped = receipts.clone();
+    swapped.swap(0, 1);
+    assert!(crate::audit_session(&session, &swapped).is_err());
 
-            let env = ExecutorEnv::builder()
-                .write(&spec)
-                .unwrap()
-                .add_assumption(pause_session.claim().unwrap())
-                .build()
-                .unwrap();
-            let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
-                .unwrap()
-                .run()
-                .unwrap();
-            assert_eq!(session.exit_code, ExitCode::Halted(0));
-        }
-    }
+    assert!(crate::audit_session(&session, &receipts[1..]).is_err());
+}
 
-    #[test]
-    fn sys_verify_integrity_pruned_claim() {
-        let hello_commit_session = exec_hello_commit();
+#[test]
+fn commit_input() {
+    // The guest reads a buffer capacity, and then fills a buffer of that size from stdin.
+    let mut stdin: Vec<u8> = bytemuck::cast_slice(&to_vec(&4usize).unwrap()).to_vec();
+    stdin.extend_from_slice(b"hello world");
+    let expected = Digest::try_from(Sha256::digest(&stdin).as_slice()).unwrap();
+    let run = |env: ExecutorEnv| ExecutorImpl::from_elf(env, STANDARD_LIB_ELF).unwrap().run();
 
-        // Prune the claim before providing it as input so that it cannot be checked to have no
-        // assumptions.
-        let mut pruned_claim: ReceiptClaim = hello_commit_session.claim().unwrap();
-        pruned_claim.output = MaybePruned::Pruned(pruned_claim.output.digest());
-        let spec = &MultiTestSpec::SysVerifyIntegrity {
-            claim_words: to_vec(&pruned_claim).unwrap(),
-        };
+    // The guest reads only part of stdin, and the runtime hashes the rest on exit.
+    let env = ExecutorEnv::builder()
+        .env_var("TEST_MODE", "BUF_READ")
+        .write_slice(&stdin)
+        .commit_input()
+        .build()
+        .unwrap();
+    assert_eq!(env.input_digest(), Some(expected));
+    let session = run(env).unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert_eq!(session.journal.unwrap().bytes, b"hell");
 
-        // Test that it works when the assumption is added.
-        let env = ExecutorEnv::builder()
-            .write(&spec)
-            .unwrap()
-            .add_assumption(hello_commit_session.claim().unwrap())
-            .build()
-            .unwrap();
+    // Input streamed from a reader is checked against an explicit digest.
+    let env = ExecutorEnv::builder()
+        .env_var("TEST_MODE", "BUF_READ")
+        .stdin(Cursor::new(stdin.clone()))
+        .input_digest(expected)
+        .commit_input()
+        .build()
+        .unwrap();
+    assert_eq!(run(env).unwrap().exit_code, ExitCode::Halted(0));
 
-        // Result of execution should be a guest panic resulting from the pruned input.
-        let err = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
-            .unwrap()
-            .run()
-            .map(|_| ())
-            .unwrap_err();
+    // Input that does not match the committed digest faults the guest, even in the part of stdin
+    // that the guest did not read.
+    let mut mismatched = stdin.clone();
+    *mismatched.last_mut().unwrap() ^= 1;
+    let env = ExecutorEnv::builder()
+        .env_var("TEST_MODE", "BUF_READ")
+        .stdin(Cursor::new(mismatched))
+        .input_digest(expected)
+        .commit_input()
+        .build()
+        .unwrap();
+    let err = run(env).err().unwrap();
+    assert!(
+        format!("{err:?}").contains("does not match the committed input digest"),
+        "{err:?}"
+    );
 
-        tracing::debug!("err: {err}");
-        assert!(err
-            .to_string()
-            .contains("env::verify_integrity returned error"));
-    }
+    // Without an explicit digest, data from other readers cannot be committed to.
+    let err = ExecutorEnv::builder()
+        .stdin(Cursor::new(stdin))
+        .commit_input()
+        .build()
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("Input commitment"));
 }
 
 #[test]
-fn large_sha() {
-    let data = vec![0u8; 100_000];
-    let expected = hex::encode(Sha256::digest(&data));
+fn update_syscalls_on_pause() {
+    // The guest logs to stdout before and after it pauses.
+    let digest = Digest::from([7u32; 8]);
+    let mut before = Vec::new();
     let env = ExecutorEnv::builder()
-        .write(&MultiTestSpec::ShaDigest { data })
+        .write(&MultiTestSpec::PauseResume(0))
         .unwrap()
+        .input_digest(digest)
+        .stdout(&mut before)
         .build()
         .unwrap();
     let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
     let session = exec.run().unwrap();
-    let actual = hex::encode(Digest::try_from(session.journal.unwrap().bytes).unwrap());
-    assert_eq!(expected, actual);
-}
+    assert_eq!(session.exit_code, ExitCode::Paused(0));
 
-#[test]
-fn std_stdio() {
-    const STDIN: &str = "Hello world from stdin!\n";
-    const EXPECTED_STDOUT: &str = "Hello world on stdout!\n";
-    const EXPECTED_STDERR: &str = "Hello world on stderr!\n";
+    // The resumed session writes to the new stdout, and keeps the state of the executor.
+    let mut after = Vec::new();
+    let resumed = session
+        .resume_with_syscalls(ExecutorEnv::builder().stdout(&mut after).build().unwrap())
+        .unwrap();
+    assert_eq!(resumed.exit_code, ExitCode::Halted(0));
+    assert_eq!(resumed.input, digest);
+    assert_eq!(resumed.claim().unwrap().input.digest(), digest);
+    assert!(from_utf8(&after).unwrap().contains("after"));
+    assert!(!from_utf8(&after).unwrap().contains("before"));
+    assert!(resumed
+        .resume_with_syscalls(ExecutorEnv::default())
+        .is_err());
+    let err = session
+        .resume_with_syscalls(
+            ExecutorEnv::builder()
+                .input_digest(Digest::ZERO)
+                .build()
+                .unwrap(),
+        )
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("does not match"), "{err}");
 
-    fn expected_stdout() -> String {
-        format!("{EXPECTED_STDOUT}{STDIN}")
-    }
+    let mut updated = Vec::new();
+    exec.update_syscalls(ExecutorEnv::builder().stdout(&mut updated).build().unwrap());
+    let session = exec.run().unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    drop(exec);
+    assert!(from_utf8(&before).unwrap().contains("before"));
+    assert!(!from_utf8(&before).unwrap().contains("after"));
+    assert!(from_utf8(&updated).unwrap().contains("after"));
+}
 
-    let mut stderr: Vec<u8> = Vec::new();
-    let mut stdout: Vec<u8> = Vec::new();
+#[test]
+fn pause_handler() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::PauseResume(3))
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut codes = Vec::new();
+    let sessions = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run_with_pause_handler(|code, _| {
+            codes.push(code);
+            Ok(ExecutorEnv::default())
+        })
+        .unwrap();
+
+    assert_eq!(codes, [3]);
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[0].pause_code(), Some(3));
+    assert_eq!(sessions[0].pause_reason::<u8>(), Some(Ok(3)));
+    assert_eq!(sessions[0].claim().unwrap().exit_code, ExitCode::Paused(3));
+    assert_eq!(sessions[1].pause_code(), None);
+    assert_eq!(sessions[1].exit_code, ExitCode::Halted(0));
+}
+
+#[test]
+fn session_events() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::PauseResume(3))
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+    let paused = exec.run().unwrap();
+    let halted = exec.run().unwrap();
+
+    assert!(
+        matches!(
+            paused.events(),
+            [ExecutionEvent {
+                cycle,
+                kind: ExecutionEventKind::Segment {
+                    index: 0,
+                    exit_code: ExitCode::Paused(3),
+                    ..
+                },
+            }] if *cycle == paused.user_cycles
+        ),
+        "{:?}",
+        paused.events()
+    );
+    let events = halted.events();
+    assert_eq!(events.len(), 3, "{events:?}");
+    assert_eq!(&events[..1], paused.events());
+    assert_eq!(
+        events[1],
+        ExecutionEvent {
+            cycle: paused.user_cycles,
+            kind: ExecutionEventKind::Resumed,
+        }
+    );
+    assert!(matches!(
+        events[2].kind,
+        ExecutionEventKind::Segment {
+            index: 0,
+            exit_code: ExitCode::Halted(0),
+            ..
+        }
+    ));
+    assert_eq!(events[2].cycle, paused.user_cycles + halted.user_cycles);
+    assert_eq!(exec.events(), events);
+
+    // Stopping at the session limit is recorded after the last segment.
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles: 1_000_000 })
+        .unwrap()
+        .session_limit(Some(10_000))
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+    let session = exec.run().unwrap();
+    assert_eq!(session.exit_code, ExitCode::SessionLimit);
+    let events = session.events();
+    assert_eq!(
+        events.last().unwrap().kind,
+        ExecutionEventKind::SessionLimit
+    );
+    assert!(events.last().unwrap().cycle >= 10_000);
+}
+
+#[test]
+fn progress_callback() {
+    let progress = Rc::new(RefCell::new(Vec::new()));
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles: 1_000_000 })
+        .unwrap()
+        .segment_limit_po2(16)
+        .progress_callback(100_000, {
+            let progress = progress.clone();
+            move |update| progress.borrow_mut().push(update.clone())
+        })
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+
+    let progress = progress.take();
+    assert!(progress.len() >= 9, "{progress:#?}");
+    for pair in progress.windows(2) {
+        assert!(pair[1].user_cycles >= pair[0].user_cycles + 100_000);
+        assert!(pair[1].segments >= pair[0].segments);
+        assert!(pair[1].total_cycles >= pair[0].total_cycles);
+        assert!(pair[1].elapsed >= pair[0].elapsed);
+    }
+    let last = progress.last().unwrap();
+    assert!(last.user_cycles <= session.user_cycles);
+    assert!(last.segments > 0);
+    assert!((last.segments as usize) < session.segments.len());
+    assert!(last.total_cycles < session.total_cycles);
+
+    // A resumed session counts the user cycles of its earlier runs.
+    let resumed = Rc::new(RefCell::new(Vec::new()));
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles: 1_000_000 })
+        .unwrap()
+        .session_limit(Some(200_000))
+        .progress_callback(100_000, {
+            let resumed = resumed.clone();
+            move |update| resumed.borrow_mut().push(update.clone())
+        })
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+    let stopped = exec.run().unwrap();
+    assert_eq!(stopped.exit_code, ExitCode::SessionLimit);
+    resumed.take();
+    exec.run().unwrap();
+    let resumed = resumed.take();
+    assert!(!resumed.is_empty());
+    assert!(resumed[0].user_cycles >= stopped.user_cycles + 100_000);
+}
+
+#[test]
+fn cycle_meter() {
+    let charged = Rc::new(RefCell::new(BTreeMap::<String, u64>::new()));
+    let env = ExecutorEnv::builder()
+        .cycle_meter({
+            let charged = charged.clone();
+            move |_, name, cycles| {
+                *charged.borrow_mut().entry(name.to_string()).or_default() += cycles;
+                ControlFlow::Continue(())
+            }
+        })
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, HELLO_COMMIT_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+
+    let charged = charged.take();
+    assert_eq!(charged.values().sum::<u64>(), session.user_cycles);
+    assert!(charged.contains_key("add"), "{charged:?}");
+    assert!(charged.contains_key(SYS_WRITE.as_str()), "{charged:?}");
+    assert!(charged.contains_key("halt"), "{charged:?}");
+
+    // Breaking out of the meter stops execution, as when gas runs out.
+    let mut gas = 1_000u64;
+    let env = ExecutorEnv::builder()
+        .cycle_meter(move |_, _, cycles| match gas.checked_sub(cycles) {
+            Some(left) => {
+                gas = left;
+                ControlFlow::Continue(())
+            }
+            None => ControlFlow::Break(()),
+        })
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, HELLO_COMMIT_ELF).unwrap();
+    let err = exec.run().err().unwrap();
+    assert!(err.to_string().contains("cycle meter"), "{err:#}");
+}
+
+#[test]
+fn runaway_heuristics() {
+    // A loop without syscalls, which stands in for a guest stuck in an infinite loop.
+    let spec = MultiTestSpec::RunUnconstrained {
+        unconstrained: false,
+        cycles: 1_000_000,
+    };
+    let heuristics = RunawayHeuristics::default()
+        .with_idle_cycles(200_000)
+        .with_tight_loop(200_000, 64);
+
+    let reports = Rc::new(RefCell::new(Vec::new()));
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .runaway_heuristics(heuristics.clone())
+        .on_runaway({
+            let reports = reports.clone();
+            move |report| reports.borrow_mut().push(report.clone())
+        })
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+    let reports = reports.take();
+    assert_eq!(reports.len(), 2, "{reports:#?}");
+    assert!(reports
+        .iter()
+        .any(|report| matches!(report.kind, RunawayKind::Idle { cycles } if cycles >= 200_000)));
+    assert!(reports.iter().any(|report| matches!(
+        report.kind,
+        RunawayKind::TightLoop { start, end, .. } if end - start <= 64
+    )));
+    for report in &reports {
+        assert_eq!(report.registers.len(), 32);
+        assert!(report.cycle < session.user_cycles);
+    }
+    let events: Vec<_> = session
+        .events()
+        .iter()
+        .filter_map(|event| match &event.kind {
+            ExecutionEventKind::Runaway { report } => Some(report.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(events, reports);
+
+    // Aborting stops the guest in the loop.
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .runaway_heuristics(heuristics.with_abort(true))
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+    let err = exec.run().err().unwrap();
+    assert!(err.to_string().contains("Runaway guest"), "{err:#}");
+    let events = exec.events();
+    assert!(matches!(
+        events.last().unwrap().kind,
+        ExecutionEventKind::Failed { .. }
+    ));
+    assert!(matches!(
+        events[events.len() - 2].kind,
+        ExecutionEventKind::Runaway { .. }
+    ));
+}
+
+// Tests sys_read into a buffer of bytes that may not be word aligned.
+//
+// To make sure we don't miss any edge cases, this tries all permutations of
+// start alignment, end alignment, and 0, 1, or 2 whole words.
+#[test]
+fn posix_style_read() {
+    const FD: u32 = 123;
+    // Initial buffer to read bytes on top of.
+    let buf: Vec<u8> = (b'a'..b'z')
+        .chain(b'0'..b'9')
+        .chain(b"!@#$%^&*()".iter().cloned())
+        .collect();
+    // Input to read bytes from.
+    let readbuf: Vec<u8> = (b'A'..b'Z').collect();
+
+    let run = |pos_and_len: Vec<(u32, u32)>| {
+        let mut expected = buf.to_vec();
+
+        let mut expected_readbuf = readbuf.as_slice();
+        for (pos, len) in pos_and_len.iter() {
+            let pos = *pos as usize;
+            let len = *len as usize;
+
+            let this_read;
+            (this_read, expected_readbuf) = expected_readbuf.split_at(len);
+            expected[pos..pos + len].clone_from_slice(this_read);
+        }
+
+        let spec = MultiTestSpec::SysRead {
+            fd: FD,
+            buf: buf.to_vec(),
+            pos_and_len: pos_and_len.clone(),
+        };
+        let env = ExecutorEnv::builder()
+            .read_fd(FD, readbuf.as_slice())
+            .write(&spec)
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+        let session = exec.run().unwrap();
+        assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+        let (actual, num_read): (Vec<u8>, Vec<usize>) = session.journal.unwrap().decode().unwrap();
+        for ((_, len), n_read) in pos_and_len.iter().zip(num_read) {
+            assert_eq!(
+                *len as usize, n_read,
+                "length mismatch, pos and lens: {pos_and_len:?}"
+            )
+        }
+        assert_eq!(
+            from_utf8(&actual).unwrap(),
+            from_utf8(&expected).unwrap(),
+            "pos and lens: {pos_and_len:?}"
+        );
+    };
+
+    fn next_offset(mut pos: u32, offset: u32) -> u32 {
+        while (pos % WORD_SIZE as u32) != offset {
+            pos += 1;
+        }
+        pos
+    }
+
+    for start_offset in 0..WORD_SIZE as u32 {
+        for end_offset in 0..WORD_SIZE as u32 {
+            let mut pos = 0;
+            let mut pos_and_len: Vec<(u32, u32)> = Vec::new();
+
+            // Make up a bunch of reads to overwrite parts of the buffer.
+            for nwords in 0..3 {
+                pos = next_offset(pos, start_offset);
+                let start = pos;
+                pos += nwords * WORD_SIZE as u32;
+                pos = next_offset(pos, end_offset);
+                let len = pos - start;
+                pos_and_len.push((pos, len));
+                assert!(
+                    pos + len < buf.len() as u32,
+                    "Ran out of space to test writes. pos: {pos} len: {len} end: {end_offset} start = {start_offset}"
+                );
+                // Make sure there's at least one non-overwritten character between reads.
+                pos += 1;
+            }
+
+            run(pos_and_len);
+        }
+    }
+}
+
+#[test]
+fn short_read_combinations() {
+    const FD: u32 = 123;
+    // Initial buffer to read bytes on top of.
+    let buf: Vec<u8> = (b'a'..=b'l').collect();
+    // Input to read bytes from.
+    let readbuf: Vec<u8> = (b'A'..b'L').collect();
+
+    for read_len in 0..WORD_SIZE {
+        for excess_buffer in 0..WORD_SIZE * 2 {
+            let buffer = read_len + excess_buffer;
+            let mut expected = buf.to_vec();
+
+            expected[..read_len].copy_from_slice(&readbuf[..read_len]);
+
+            // The current behaviour of the read call for more bytes than available is to write
+            // zeroes for the remaining bytes.
+            expected[read_len..buffer].fill(0);
+
+            let spec = MultiTestSpec::SysRead {
+                fd: FD,
+                buf: buf.to_vec(),
+                pos_and_len: vec![(0, buffer as u32)],
+            };
+            let env = ExecutorEnv::builder()
+                .read_fd(FD, &readbuf[..read_len])
+                .write(&spec)
+                .unwrap()
+                .build()
+                .unwrap();
+            let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+            let session = exec.run().unwrap();
+            assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+            let (actual, num_read): (Vec<u8>, Vec<usize>) =
+                session.journal.unwrap().decode().unwrap();
+            assert_eq!(
+                [read_len].as_slice(),
+                &num_read,
+                "length mismatch, length {read_len} buffer: {buffer}"
+            );
+            assert_eq!(
+                from_utf8(&actual).unwrap(),
+                from_utf8(&expected).unwrap(),
+                "length {read_len}, buffer {buffer}"
+            );
+        }
+    }
+}
+
+#[test]
+fn unaligned_short_read() {
+    const FD: u32 = 123;
+    // Initial buffer to read bytes on top of.
+    let buf: Vec<u8> = vec![0; 9];
+    let readbuf: &[u8] = b"1234567";
+
+    let spec = MultiTestSpec::SysRead {
+        fd: FD,
+        buf: buf.clone(),
+        pos_and_len: vec![(0, 9)],
+    };
+    let env = ExecutorEnv::builder()
+        .read_fd(FD, readbuf)
+        .write(&spec)
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+    let session = exec.run().unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+    let actual: Vec<u8> = session.journal.unwrap().decode().unwrap();
+    let mut expected = readbuf.to_vec();
+    expected.resize(buf.len(), 0);
+    assert_eq!(actual, expected, "pos and lens: {spec:?}");
+}
+
+// Tests sys_read_exact at every combination of start and end alignment, including reads that
+// span more than one chunk and reads that are cut short by EOF.
+#[test]
+fn read_exact_unaligned() {
+    const FD: u32 = 123;
+    let readbuf: Vec<u8> = (0..10_000).map(|x| (x % 251) as u8).collect();
+
+    for start in 0..WORD_SIZE as u32 {
+        for len in [0, 1, 2, 3, 5, 6, 7, 9, 4097, 9000, 20_000] {
+            let buf: Vec<u8> = vec![0xff; start as usize + len as usize + WORD_SIZE];
+            let spec = MultiTestSpec::SysReadExact {
+                fd: FD,
+                buf: buf.clone(),
+                pos_and_len: vec![(start, len)],
+            };
+            let env = ExecutorEnv::builder()
+                .read_fd(FD, readbuf.as_slice())
+                .write(&spec)
+                .unwrap()
+                .build()
+                .unwrap();
+            let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+                .unwrap()
+                .run()
+                .unwrap();
+            assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+            let nread = readbuf.len().min(len as usize);
+            let mut expected = buf.clone();
+            expected[start as usize..][..nread].copy_from_slice(&readbuf[..nread]);
+
+            let (actual, num_read): (Vec<u8>, Vec<usize>) =
+                session.journal.unwrap().decode().unwrap();
+            assert_eq!(num_read, [nread], "start: {start}, len: {len}");
+            assert_eq!(actual, expected, "start: {start}, len: {len}");
+        }
+    }
+}
+
+#[test]
+fn vectored_io() {
+    const FD: u32 = 123;
+    let lens = vec![3, 0, 1, 4097, 6, 5000];
+    let total: u32 = lens.iter().sum();
+    let readbuf: Vec<u8> = (0..total).map(|x| (x % 251) as u8).collect();
+
+    let mut stdout: Vec<u8> = Vec::new();
+    let session = {
+        let env = ExecutorEnv::builder()
+            .read_fd(FD, readbuf.as_slice())
+            .write(&MultiTestSpec::EchoVectored { fd: FD, lens })
+            .unwrap()
+            .stdout(&mut stdout)
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap()
+    };
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+    let (nread, nwritten): (usize, usize) = session.journal.unwrap().decode().unwrap();
+    assert_eq!(nread, total as usize);
+    assert_eq!(nwritten, total as usize);
+    assert_eq!(stdout, readbuf);
+}
+
+#[test]
+fn buffered_journal() {
+    let lens = vec![1, 3, 0, 1020, 4, 2048, 7, 1024, 5];
+    let total: u32 = lens.iter().sum();
+    let expected: Vec<u8> = (0..total).map(|x| (x % 251) as u8).collect();
+
+    for flush in [false, true] {
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::CommitSlices {
+                lens: lens.clone(),
+                flush,
+            })
+            .unwrap()
+            .build()
+            .unwrap();
+        let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        assert_eq!(session.exit_code, ExitCode::Halted(0));
+        assert_eq!(session.journal.unwrap().bytes, expected);
+    }
+}
+
+#[test]
+fn large_io_words() {
+    const FD: u32 = 123;
+    let buf: Vec<u32> = (0..400_000).collect();
+    let expected = buf.clone();
+    let input = MultiTestSpec::EchoWords {
+        fd: FD,
+        nwords: buf.len() as u32,
+    };
+    let env = ExecutorEnv::builder()
+        .read_fd(FD, bytemuck::cast_slice(&buf))
+        .write(&input)
+        .unwrap()
+        .session_limit(Some(20_000_000))
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+    let session = exec.run().unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+    let actual: &[u32] = bytemuck::cast_slice(&session.journal.as_ref().unwrap().bytes);
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn large_io_bytes() {
+    const FD: u32 = 123;
+    let buf: Vec<u32> = (0..400_000).collect();
+    let nbytes = (buf.len() * WORD_SIZE) as u32;
+    let spec = to_vec(&MultiTestSpec::EchoStdout { nbytes, fd: FD }).unwrap();
+    let mut stdout: Vec<u8> = Vec::new();
+    {
+        let env = ExecutorEnv::builder()
+            .read_fd(FD, bytemuck::cast_slice(&buf))
+            .stdin(bytemuck::cast_slice(&spec))
+            .stdout(&mut stdout)
+            .build()
+            .unwrap();
+        let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        assert_eq!(session.exit_code, ExitCode::Halted(0));
+    }
+    let actual: &[u32] = bytemuck::cast_slice(&stdout);
+    assert_eq!(&buf, actual);
+}
+
+#[test]
+fn prefetch_read_fd() {
+    const FD: u32 = 123;
+    let buf: Vec<u8> = (0..100_000).map(|x| (x % 251) as u8).collect();
+    let spec = to_vec(&MultiTestSpec::EchoStdout {
+        nbytes: buf.len() as u32,
+        fd: FD,
+    })
+    .unwrap();
+    let mut stdout: Vec<u8> = Vec::new();
+    {
+        let env = ExecutorEnv::builder()
+            .prefetch_read_fd(FD, std::io::Cursor::new(buf.clone()))
+            .stdin(bytemuck::cast_slice(&spec))
+            .stdout(&mut stdout)
+            .build()
+            .unwrap();
+        let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        assert_eq!(session.exit_code, ExitCode::Halted(0));
+    }
+    assert_eq!(stdout, buf);
+}
+
+mod sys_verify {
+    use risc0_zkvm_methods::{
+        multi_test::MultiTestSpec, HELLO_COMMIT_ELF, HELLO_COMMIT_ID, MULTI_TEST_ELF, MULTI_TEST_ID,
+    };
+    use test_log::test;
+
+    use crate::{
+        serde::to_vec, sha::Digestible, ExecutorEnv, ExecutorEnvBuilder, ExecutorImpl, ExitCode,
+        MaybePruned, ReceiptClaim, Session,
+    };
+
+    fn exec_hello_commit() -> Session {
+        let session = ExecutorImpl::from_elf(ExecutorEnv::default(), HELLO_COMMIT_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        assert_eq!(session.exit_code, ExitCode::Halted(0));
+        session
+    }
+
+    fn exec_halt(exit_code: u8) -> Session {
+        let env = ExecutorEnvBuilder::default()
+            .write(&MultiTestSpec::Halt(exit_code))
+            .unwrap()
+            .build()
+            .unwrap();
+        let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        assert_eq!(session.exit_code, ExitCode::Halted(exit_code as u32));
+        session
+    }
+
+    fn exec_pause(exit_code: u8) -> Session {
+        let env = ExecutorEnvBuilder::default()
+            .write(&MultiTestSpec::PauseResume(exit_code))
+            .unwrap()
+            .build()
+            .unwrap();
+        let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        assert_eq!(session.exit_code, ExitCode::Paused(exit_code as u32));
+        session
+    }
+
+    #[test]
+    fn sys_verify() {
+        let hello_commit_session = exec_hello_commit();
+
+        let spec = &MultiTestSpec::SysVerify(vec![(
+            HELLO_COMMIT_ID.into(),
+            hello_commit_session.journal.clone().unwrap().bytes,
+        )]);
+
+        // Test that it works when the assumption is added.
+        let env = ExecutorEnv::builder()
+            .write(&spec)
+            .unwrap()
+            .add_assumption(hello_commit_session.claim().unwrap())
+            .build()
+            .unwrap();
+        let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+        // Test that it does not work when the assumption is not added.
+        let env = ExecutorEnv::builder()
+            .write(&spec)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .is_err());
+    }
+
+    #[test]
+    fn sys_verify_halt_codes() {
+        for code in [0u8, 1, 2, 255] {
+            tracing::debug!("sys_verify_pause_codes: code = {code}");
+            let halt_session = exec_halt(code);
+
+            let spec = &MultiTestSpec::SysVerify(vec![(MULTI_TEST_ID.into(), Vec::new())]);
+
+            let env = ExecutorEnv::builder()
+                .write(&spec)
+                .unwrap()
+                .add_assumption(halt_session.claim().unwrap())
+                .build()
+                .unwrap();
+            let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap().run();
+
+            if code == 0 {
+                assert_eq!(session.unwrap().exit_code, ExitCode::Halted(0));
+            } else {
+                assert!(session.is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn sys_verify_integrity() {
+        let hello_commit_session = exec_hello_commit();
+
+        let spec = &MultiTestSpec::SysVerifyIntegrity {
+            claim_words: to_vec(&hello_commit_session.claim().unwrap()).unwrap(),
+        };
+
+        // Test that it works when the assumption is added.
+        let env = ExecutorEnv::builder()
+            .write(&spec)
+            .unwrap()
+            .add_assumption(hello_commit_session.claim().unwrap())
+            .build()
+            .unwrap();
+        let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+        // Test that it does not work when the assumption is not added.
+        let env = ExecutorEnv::builder()
+            .write(&spec)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .is_err());
+    }
+
+    #[test]
+    fn sys_verify_integrity_halt_codes() {
+        for code in [0u8, 1, 2, 255] {
+            tracing::debug!("sys_verify_pause_codes: code = {code}");
+            let halt_session = exec_halt(code);
+
+            let spec = &MultiTestSpec::SysVerifyIntegrity {
+                claim_words: to_vec(&halt_session.claim().unwrap()).unwrap(),
+            };
+
+            let env = ExecutorEnv::builder()
+                .write(&spec)
+                .unwrap()
+                .add_assumption(halt_session.claim().unwrap())
+                .build()
+                .unwrap();
+            let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+                .unwrap()
+                .run()
+                .unwrap();
+            assert_eq!(session.exit_code, ExitCode::Halted(0));
+        }
+    }
+
+    #[test]
+    fn sys_verify_integrity_pause_codes() {
+        for code in [0u8, 1, 2, 255] {
+            tracing::debug!("sys_verify_halt_codes: code = {code}");
+            let pause_session = exec_pause(code);
+
+            let spec = &MultiTestSpec::SysVerifyIntegrity {
+                claim_words: to_vec(&pause_session.claim().unwrap()).unwrap(),
+            };
+
+            let env = ExecutorEnv::builder()
+                .write(&spec)
+                .unwrap()
+                .add_assumption(pause_session.claim().unwrap())
+                .build()
+                .unwrap();
+            let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+                .unwrap()
+                .run()
+                .unwrap();
+            assert_eq!(session.exit_code, ExitCode::Halted(0));
+        }
+    }
+
+    #[test]
+    fn sys_verify_integrity_pruned_claim() {
+        let hello_commit_session = exec_hello_commit();
+
+        // Prune the claim before providing it as input so that it cannot be checked to have no
+        // assumptions.
+        let mut pruned_claim: ReceiptClaim = hello_commit_session.claim().unwrap();
+        pruned_claim.output = MaybePruned::Pruned(pruned_claim.output.digest());
+        let spec = &MultiTestSpec::SysVerifyIntegrity {
+            claim_words: to_vec(&pruned_claim).unwrap(),
+        };
+
+        // Test that it works when the assumption is added.
+        let env = ExecutorEnv::builder()
+            .write(&spec)
+            .unwrap()
+            .add_assumption(hello_commit_session.claim().unwrap())
+            .build()
+            .unwrap();
+
+        // Result of execution should be a guest panic resulting from the pruned input.
+        let err = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .map(|_| ())
+            .unwrap_err();
+
+        tracing::debug!("err: {err}");
+        assert!(err
+            .to_string()
+            .contains("env::verify_integrity returned error"));
+    }
+}
+
+#[test]
+fn large_sha() {
+    let data = vec![0u8; 100_000];
+    let expected = hex::encode(Sha256::digest(&data));
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::ShaDigest { data })
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+    let session = exec.run().unwrap();
+    let actual = hex::encode(Digest::try_from(session.journal.unwrap().bytes).unwrap());
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn std_stdio() {
+    const STDIN: &str = "Hello world from stdin!\n";
+    const EXPECTED_STDOUT: &str = "Hello world on stdout!\n";
+    const EXPECTED_STDERR: &str = "Hello world on stderr!\n";
+
+    fn expected_stdout() -> String {
+        format!("{EXPECTED_STDOUT}{STDIN}")
+    }
+
+    let mut stderr: Vec<u8> = Vec::new();
+    let mut stdout: Vec<u8> = Vec::new();
+    {
+        let env = ExecutorEnv::builder()
+            .env_var("TEST_MODE", "STDIO")
+            .stdin(STDIN.as_bytes())
+            .stderr(&mut stderr)
+            .stdout(&mut stdout)
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, STANDARD_LIB_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+    }
+    assert_eq!(from_utf8(&stdout).unwrap(), expected_stdout());
+    assert_eq!(from_utf8(&stderr).unwrap(), EXPECTED_STDERR);
+}
+
+#[test]
+fn environment() {
+    let env = ExecutorEnv::builder()
+        .env_var("TEST_MODE", "ENV_VARS")
+        .env_var("ENV_VAR1", "val1")
+        .env_var("ENV_VAR2", "")
+        .read_fd(
+            fileno::STDIN,
+            Cursor::new(
+                r"ENV_VAR1
+ENV_VAR2
+ENV_VAR3",
+            ),
+        )
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, STANDARD_LIB_ELF).unwrap();
+    let session = exec.run().unwrap();
+    let actual = &session.journal.as_ref().unwrap().bytes;
+    assert_eq!(
+        from_utf8(actual).unwrap(),
+        r"ENV_VAR1=val1
+ENV_VAR2=
+!ENV_VAR3
+"
+    );
+}
+
+#[test]
+fn args() {
+    let test_cases: [&[String]; 3] = [
+        &[String::default()],
+        &[
+            "grep".to_string(),
+            "-c".to_string(),
+            "foo bar".to_string(),
+            "-".to_string(),
+        ],
+        &[String::default()],
+    ];
+    for args_arr in test_cases {
+        let env = ExecutorEnv::builder()
+            .env_var("TEST_MODE", "ARGS")
+            .args(&args_arr)
+            .build()
+            .unwrap();
+        let mut exec = ExecutorImpl::from_elf(env, STANDARD_LIB_ELF).unwrap();
+        let session = exec.run().unwrap();
+        let output: Vec<String> = session.journal.unwrap().decode().unwrap();
+        assert_eq!(
+            output,
+            args_arr
+                .into_iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>(),
+        );
+    }
+}
+
+#[test]
+fn buf_read() {
+    // Host-provided input is 7 bytes, while the guest requests to read 9.
+    let input = b"1234567";
+    let env = ExecutorEnv::builder()
+        .env_var("TEST_MODE", "BUF_READ")
+        // Previously failed on anything > buf and not % 4 == 0
+        // https://github.com/risc0/risc0/pull/1557
+        .write(&9usize)
+        .unwrap()
+        .write_slice(input.as_slice())
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, STANDARD_LIB_ELF).unwrap();
+    let session = exec.run().unwrap();
+    let output = session.journal.unwrap().bytes;
+    assert_eq!(output, input);
+}
+
+#[test]
+fn commit_hello_world() {
+    ExecutorImpl::from_elf(ExecutorEnv::default(), HELLO_COMMIT_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+}
+
+#[test]
+fn random() {
+    run_test(MultiTestSpec::DoRandom);
+}
+
+#[test]
+#[should_panic(expected = "WARNING: `getrandom()` called from guest.")]
+fn getrandom_panic() {
+    let env = ExecutorEnv::builder().build().unwrap();
+    let _session = ExecutorImpl::from_elf(env, RAND_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+}
+
+#[test]
+fn slice_io() {
+    let run = |slice: &[u8]| {
+        let env = ExecutorEnv::builder()
+            .write_slice(&[slice.len() as u32])
+            .write_slice(slice)
+            .build()
+            .unwrap();
+        let mut exec = ExecutorImpl::from_elf(env, SLICE_IO_ELF).unwrap();
+        let session = exec.run().unwrap();
+        assert_eq!(session.journal.unwrap().bytes, slice);
+    };
+
+    run(b"");
+    run(b"xyz");
+    run(b"0000");
+}
+
+#[test]
+fn prove_memory() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::PauseResume(0))
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let segment = session.segments[0].resolve().unwrap();
+
+    // The entry point is always paged in by the first segment.
+    let pc = segment.inner.pre_state.pc;
+    let range = pc..pc + 64;
+    let proof = segment.prove_memory(range.clone()).unwrap();
+    proof.verify(&segment.inner.pre_state.digest()).unwrap();
+    let mut expected = vec![0u8; range.len()];
+    session
+        .post_image
+        .load_region_in_page(pc, &mut expected)
+        .unwrap();
+    assert_eq!(proof.read(range.clone()).unwrap(), expected);
+
+    // Memory that the guest never touches is not paged in.
+    let untouched = GUEST_MAX_MEM as u32 - 64..GUEST_MAX_MEM as u32;
+    assert!(segment.prove_memory(untouched).is_err());
+
+    // The session's post-image commits to the state of a paused guest.
+    let proof = session.prove_memory(range).unwrap();
+    proof.verify(&session.post_state.digest()).unwrap();
+}
+
+#[test]
+fn page_heatmap() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("heatmap.tsv");
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .enable_page_heatmap(&path)
+        .build()
+        .unwrap();
+    ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+
+    let report = std::fs::read_to_string(path).unwrap();
+    let mut lines = report.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "page\taddress\tregion\tloads\tstores\tpage_ins\tpage_outs\tsymbols"
+    );
+    let rows: Vec<Vec<_>> = lines.map(|line| line.split('\t').collect()).collect();
+    assert!(rows.iter().any(|row| row[2] == "page table"));
+    assert!(rows.iter().any(|row| row[2] == ".text"));
+
+    // The guest loads from and stores to its stack, and the most accessed page comes first.
+    let stack = rows.iter().find(|row| row[2] == "stack").unwrap();
+    assert!(stack[3].parse::<u64>().unwrap() > 0 && stack[4].parse::<u64>().unwrap() > 0);
+    let accesses =
+        |row: &Vec<&str>| row[3].parse::<u64>().unwrap() + row[4].parse::<u64>().unwrap();
+    assert!(rows.windows(2).all(|w| accesses(&w[0]) >= accesses(&w[1])));
+}
+
+#[test]
+fn trace_prev_values() {
+    let mut events: Vec<TraceEvent> = Vec::new();
     {
         let env = ExecutorEnv::builder()
-            .env_var("TEST_MODE", "STDIO")
-            .stdin(STDIN.as_bytes())
-            .stderr(&mut stderr)
-            .stdout(&mut stdout)
+            .write(&MultiTestSpec::EventTrace)
+            .unwrap()
+            .trace_callback(|event| Ok(events.push(event)))
+            .trace_prev_values()
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+    }
+
+    // Each write is accompanied, within the same instruction, by an event carrying the value it
+    // overwrote.
+    let mut regs = BTreeMap::new();
+    let mut stores = 0;
+    for insn in events.split(|event| matches!(event, TraceEvent::InstructionStart { .. })) {
+        let mut prev_regs = BTreeMap::new();
+        let mut prev_regions = BTreeMap::new();
+        for event in insn {
+            match event {
+                TraceEvent::RegisterPrev { idx, value } => {
+                    prev_regs.insert(*idx, *value);
+                }
+                TraceEvent::MemoryPrev { addr, region } => {
+                    prev_regions.insert(*addr, region.len());
+                }
+                _ => (),
+            }
+        }
+        for event in insn {
+            match event {
+                TraceEvent::RegisterSet { idx, value } => {
+                    let prev_value = prev_regs[idx];
+                    if let Some(expected) = regs.insert(*idx, *value) {
+                        assert_eq!(prev_value, expected, "x{idx}");
+                    }
+                }
+                TraceEvent::MemorySet { addr, region } => {
+                    assert_eq!(prev_regions[addr], region.len());
+                    if *addr == 0x08000224 && region[..] == 1337_u32.to_le_bytes() {
+                        stores += 1;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+    assert_eq!(stores, 1);
+}
+
+#[test]
+fn write_combiner() {
+    // The bytes of the events picked out of a trace, by address, and the number of events.
+    fn collect(
+        events: &[TraceEvent],
+        pick: impl Fn(&TraceEvent) -> Option<(u32, &[u8])>,
+    ) -> (BTreeMap<u32, u8>, usize) {
+        let mut bytes = BTreeMap::new();
+        let mut count = 0;
+        for (addr, region) in events.iter().filter_map(pick) {
+            count += 1;
+            for (offset, byte) in region.iter().enumerate() {
+                bytes.insert(addr + offset as u32, *byte);
+            }
+        }
+        (bytes, count)
+    }
+
+    let image = MemoryImage::new(&testutil::fill_memory(), PAGE_SIZE as u32).unwrap();
+    for prev_values in [false, true] {
+        let raw = Rc::new(RefCell::new(Vec::new()));
+        let combined = Rc::new(RefCell::new(Vec::new()));
+        let mut builder = ExecutorEnv::builder();
+        builder
+            .trace_callback({
+                let raw = raw.clone();
+                move |event: TraceEvent| Ok(raw.borrow_mut().push(event))
+            })
+            .trace_callback(WriteCombiner::new({
+                let combined = combined.clone();
+                move |event: TraceEvent| Ok(combined.borrow_mut().push(event))
+            }));
+        if prev_values {
+            builder.trace_prev_values();
+        }
+        let env = builder.build().unwrap();
+        ExecutorImpl::new(env, image.clone())
+            .unwrap()
+            .run()
+            .unwrap();
+        let raw = raw.take();
+        let combined = combined.take();
+
+        // Both traces write the same bytes, in an order of magnitude fewer events when combined.
+        let (expected, stores) = collect(&raw, |event| match event {
+            TraceEvent::MemorySet { addr, region } => Some((*addr, region.as_slice())),
+            _ => None,
+        });
+        let (written, regions) = collect(&combined, |event| match event {
+            TraceEvent::MemorySet { .. } => panic!("uncombined store"),
+            TraceEvent::MemoryRegionSet { addr, bytes } => Some((*addr, bytes.as_slice())),
+            _ => None,
+        });
+        assert_eq!(written, expected);
+        assert!(stores >= 1024);
+        assert!(
+            regions * 10 <= stores,
+            "{regions} regions for {stores} stores"
+        );
+
+        // And the same previous values, combined in the same way.
+        let (expected, prev_stores) = collect(&raw, |event| match event {
+            TraceEvent::MemoryPrev { addr, region } => Some((*addr, region.as_slice())),
+            _ => None,
+        });
+        let (written, prev_regions) = collect(&combined, |event| match event {
+            TraceEvent::MemoryPrev { addr, region } => Some((*addr, region.as_slice())),
+            TraceEvent::MemoryRegionPrev { addr, bytes } => Some((*addr, bytes.as_slice())),
+            _ => None,
+        });
+        assert_eq!(written, expected);
+        if prev_values {
+            assert_eq!(prev_stores, stores);
+            assert_eq!(prev_regions, regions);
+        } else {
+            assert_eq!(prev_stores, 0);
+            assert_eq!(prev_regions, 0);
+        }
+
+        // The previous values of a combined region are passed on just before it.
+        for (idx, event) in combined.iter().enumerate() {
+            if let TraceEvent::MemoryRegionSet { addr, bytes } = event {
+                let prev = idx.checked_sub(1).map(|idx| &combined[idx]);
+                match prev {
+                    Some(TraceEvent::MemoryRegionPrev {
+                        addr: prev_addr,
+                        bytes: prev_bytes,
+                    }) => {
+                        assert_eq!(prev_addr, addr);
+                        assert_eq!(prev_bytes.len(), bytes.len());
+                    }
+                    _ => assert!(!prev_values, "{prev:?} before {event:?}"),
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn unsupported_page_size() {
+    let program = Program::load_elf(MULTI_TEST_ELF, GUEST_MAX_MEM as u32).unwrap();
+    let image = MemoryImage::new(&program, 4 * PAGE_SIZE as u32).unwrap();
+    assert_eq!(image.page_size(), 4 * PAGE_SIZE as u32);
+
+    let env = ExecutorEnv::builder().build().unwrap();
+    let err = ExecutorImpl::new(env, image).err().unwrap();
+    assert!(err.to_string().contains("Unsupported page size"));
+}
+
+#[test]
+fn syscall_transcript() {
+    let slice = b"audit me";
+    let env = ExecutorEnv::builder()
+        .write_slice(&[slice.len() as u32])
+        .write_slice(slice)
+        .record_syscall_transcript()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, SLICE_IO_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let transcript = session.syscall_transcript().unwrap().clone();
+    assert!(!transcript.entries.is_empty());
+    transcript.verify().unwrap();
+
+    // Replay without any input; the guest is fed the recorded answers.
+    let replay = |transcript: SyscallTranscript| -> Result<Session> {
+        let env = ExecutorEnv::builder()
+            .replay_transcript(transcript)
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, SLICE_IO_ELF).unwrap().run()
+    };
+    let replayed = replay(transcript.clone()).unwrap();
+    assert_eq!(replayed.journal.unwrap().bytes, slice);
+
+    // A transcript missing its last entries is rejected.
+    let mut truncated = transcript.clone();
+    truncated.entries.pop();
+    assert!(replay(truncated).is_err());
+
+    // A tampered answer is rejected.
+    let mut tampered = transcript;
+    let entry = tampered
+        .entries
+        .iter_mut()
+        .find(|entry| !entry.to_guest.is_empty())
+        .unwrap();
+    entry.to_guest[0] ^= 1;
+    assert!(replay(tampered).is_err());
+}
+
+#[test]
+fn compare_sessions() {
+    let run = |slice: &[u8]| {
+        let env = ExecutorEnv::builder()
+            .write_slice(&[slice.len() as u32])
+            .write_slice(slice)
+            .record_syscall_transcript()
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, SLICE_IO_ELF)
+            .unwrap()
+            .run()
+            .unwrap()
+    };
+    let a = run(b"abc");
+    let diff = crate::compare_sessions(&a, &run(b"abc")).unwrap();
+    assert!(diff.is_empty(), "{diff}");
+
+    let diff = crate::compare_sessions(&a, &run(b"abd")).unwrap();
+    assert!(!diff.is_empty());
+    assert_eq!(diff.exit_code, None);
+    let journal = diff.journal.as_ref().unwrap();
+    assert_eq!(journal.offset, 2);
+    assert_eq!(
+        (journal.a.as_slice(), journal.b.as_slice()),
+        (&b"abc"[..], &b"abd"[..])
+    );
+    let segment = diff.segment.as_ref().unwrap();
+    assert_eq!(segment.index, 0);
+    let (segment_a, segment_b) = (segment.a.as_ref().unwrap(), segment.b.as_ref().unwrap());
+    assert_eq!(segment_a.pre_state, segment_b.pre_state);
+    assert_ne!(segment_a.post_state, segment_b.post_state);
+    let syscall = diff.syscall.as_ref().unwrap();
+    assert_eq!(
+        syscall.a.as_ref().unwrap().name,
+        syscall.b.as_ref().unwrap().name
+    );
+    assert!(diff.to_string().contains("journals differ at byte 2"));
+}
+
+#[test]
+fn syscall_transcript_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("transcript.bin");
+    let slice = b"replay me";
+    let env = ExecutorEnv::builder()
+        .write_slice(&[slice.len() as u32])
+        .write_slice(slice)
+        .record_syscall_transcript_to(&path)
+        .build()
+        .unwrap();
+    let recorded = ExecutorImpl::from_elf(env, SLICE_IO_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(
+        &SyscallTranscript::load(&path).unwrap(),
+        recorded.syscall_transcript().unwrap()
+    );
+
+    let env = ExecutorEnv::builder()
+        .replay_transcript_from(&path)
+        .unwrap()
+        .build()
+        .unwrap();
+    let replayed = ExecutorImpl::from_elf(env, SLICE_IO_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(replayed.journal.unwrap().bytes, slice);
+    assert_eq!(replayed.exit_code, recorded.exit_code);
+    assert_eq!(replayed.user_cycles, recorded.user_cycles);
+    assert_eq!(replayed.post_state, recorded.post_state);
+
+    assert!(ExecutorEnv::builder()
+        .replay_transcript_from(dir.path().join("missing.bin"))
+        .is_err());
+}
+
+#[test]
+fn syscall_transcript_file_failed_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("transcript.bin");
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::ReadFrames)
+        .unwrap()
+        .write_frame(&"seven".to_string())
+        .unwrap()
+        .write_frame(&7u32)
+        .unwrap()
+        .record_syscall_transcript_to(&path)
+        .build()
+        .unwrap();
+
+    // The syscalls answered before the guest panicked are saved.
+    ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .map(|_| ())
+        .unwrap_err();
+    let transcript = SyscallTranscript::load(&path).unwrap();
+    assert!(!transcript.entries.is_empty());
+    transcript.verify().unwrap();
+
+    // And replaying them fails the same run again.
+    let env = ExecutorEnv::builder()
+        .replay_transcript_from(&path)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert!(ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .is_err());
+}
+
+#[test]
+fn batch_executor() {
+    let batch = BatchExecutor::from_elf(MULTI_TEST_ELF).unwrap();
+    let sessions = batch
+        .run_all([1u64 << 14, 1 << 16, 1 << 15].map(|cycles| {
+            move || {
+                ExecutorEnv::builder()
+                    .write(&MultiTestSpec::BusyLoop { cycles })?
+                    .build()
+            }
+        }))
+        .unwrap();
+
+    assert_eq!(sessions.len(), 3);
+    for session in &sessions {
+        assert_eq!(session.exit_code, ExitCode::Halted(0));
+    }
+    // The sessions are returned in the order of their inputs.
+    assert!(sessions[0].user_cycles < sessions[2].user_cycles);
+    assert!(sessions[2].user_cycles < sessions[1].user_cycles);
+
+    let sessions = batch
+        .run_all([|| ExecutorEnv::builder().session_limit(Some(1)).build()])
+        .unwrap();
+    assert_eq!(sessions[0].exit_code, ExitCode::SessionLimit);
+}
+
+#[test]
+fn executor_pool() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut pool = ExecutorPool::new(2).unwrap();
+    pool.add_service(SYS_MULTI_TEST, {
+        let calls = calls.clone();
+        move |from_guest| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok(from_guest)
+        }
+    });
+    assert_eq!(pool.threads(), 2);
+
+    let results = pool.run_all(
+        MULTI_TEST_ELF,
+        [1u32, 2, 3].map(|count| {
+            move |env: &mut ExecutorEnvBuilder<'static>| {
+                env.write(&MultiTestSpec::Syscall { count })?;
+                Ok(())
+            }
+        }),
+    );
+    for result in results {
+        assert_eq!(result.unwrap().exit_code, ExitCode::Halted(0));
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 6);
+
+    // Sessions are isolated: the limit of one session does not apply to another.
+    let limited = pool.spawn(MULTI_TEST_ELF, |env| {
+        env.write(&MultiTestSpec::DoNothing)?.session_limit(Some(1));
+        Ok(())
+    });
+    let unlimited = pool.spawn(MULTI_TEST_ELF, |env| {
+        env.write(&MultiTestSpec::DoNothing)?;
+        Ok(())
+    });
+    assert_eq!(limited.join().unwrap().exit_code, ExitCode::SessionLimit);
+    assert_eq!(unlimited.join().unwrap().exit_code, ExitCode::Halted(0));
+}
+
+#[test]
+fn dual_executor() {
+    let data: Vec<u8> = (0..4096).map(|idx| idx as u8).collect();
+    let input = data.clone();
+    let mut dual = DualExecutor::new(
+        MULTI_TEST_ELF,
+        move |env| {
+            env.write(&MultiTestSpec::EchoStdout {
+                nbytes: 13,
+                fd: fileno::STDIN,
+            })?
+            .stdin(Cursor::new(input));
+            Ok(())
+        },
+        MULTI_TEST_ELF,
+        |env| {
+            env.write(&MultiTestSpec::EchoWords {
+                fd: 5,
+                nwords: 1024,
+            })?;
+            Ok(())
+        },
+    );
+    dual.pipe(DualGuest::First, fileno::STDOUT, 5);
+    let (first, second) = dual.run().unwrap();
+    assert_eq!(first.exit_code, ExitCode::Halted(0));
+    assert_eq!(second.exit_code, ExitCode::Halted(0));
+    assert_eq!(second.journal.unwrap().bytes, data);
+
+    // Guests that both wait to read from each other are stopped.
+    let read = |env: &mut ExecutorEnvBuilder<'static>| -> Result<()> {
+        env.write(&MultiTestSpec::EchoWords { fd: 5, nwords: 1 })?;
+        Ok(())
+    };
+    let mut dual = DualExecutor::new(MULTI_TEST_ELF, read, MULTI_TEST_ELF, read);
+    dual.pipe(DualGuest::First, 6, 5)
+        .pipe(DualGuest::Second, 6, 5);
+    let err = dual.run().unwrap_err();
+    assert!(format!("{err:#}").contains("Deadlock"), "{err:#}");
+}
+
+#[test]
+fn capture_statement() {
+    let stdin = b"some input".to_vec();
+    let args = vec!["a".to_string(), "b".to_string()];
+    let env_vars = HashMap::from([("KEY".to_string(), "value".to_string())]);
+    let build = |capture: bool| {
+        let mut builder = ExecutorEnv::builder();
+        builder
+            .write_slice(&stdin)
+            .args(&args)
+            .env_vars(env_vars.clone());
+        if capture {
+            builder.capture_statement();
+        }
+        builder.build().unwrap()
+    };
+    let expected = Statement {
+        stdin: stdin.clone(),
+        args: args.clone(),
+        env_vars: env_vars.clone().into_iter().collect(),
+    }
+    .digest();
+
+    // The guest hashes the inputs it reads, and checks them against the input digest.
+    let env = build(true);
+    assert_eq!(env.input_digest(), Some(expected));
+    let session = ExecutorImpl::from_elf(env, STATEMENT_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert_eq!(session.claim().unwrap().input.digest(), expected);
+    assert_eq!(session.journal.unwrap().bytes, expected.as_bytes());
+
+    // The journal binds the inputs even if the host does not set the input digest.
+    let session = ExecutorImpl::from_elf(build(false), STATEMENT_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.journal.unwrap().bytes, expected.as_bytes());
+
+    // An input digest that does not match the inputs read by the guest is caught by the guest.
+    let env = ExecutorEnv::builder()
+        .write_slice(&stdin)
+        .args(&args)
+        .env_vars(env_vars.clone())
+        .input_digest(Digest::from([1u32; 8]))
+        .build()
+        .unwrap();
+    let err = ExecutorImpl::from_elf(env, STATEMENT_ELF)
+        .unwrap()
+        .run()
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("do not match the input digest"),
+        "{err}"
+    );
+
+    // Data from other readers cannot be captured.
+    let err = ExecutorEnv::builder()
+        .stdin(Cursor::new(stdin))
+        .capture_statement()
+        .build()
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("Statement capture"));
+}
+
+#[test]
+fn syscall_manifest() {
+    let manifest = SyscallManifest::new([SYS_READ.as_str()]);
+    assert_eq!(
+        SyscallManifest::from_elf(SYSCALL_MANIFEST_ELF).unwrap(),
+        Some(manifest.clone())
+    );
+    let run = |log: bool| {
+        let env = ExecutorEnv::builder().write(&log).unwrap().build().unwrap();
+        ExecutorImpl::from_elf(env, SYSCALL_MANIFEST_ELF)
+            .unwrap()
+            .run()
+    };
+
+    // The guest commits the digest of its manifest, which the verifier checks in the journal.
+    let session = run(false).unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert_eq!(session.syscall_manifest, Some(manifest.clone()));
+    let journal = session.journal.unwrap().bytes;
+    assert_eq!(manifest.verify_journal(&journal).unwrap(), b"done");
+    assert!(SyscallManifest::default().verify_journal(&journal).is_err());
+
+    // A syscall outside of the manifest fails.
+    let err = run(true).unwrap_err();
+    assert!(
+        format!("{err:?}").contains("not in the syscall manifest"),
+        "{err:?}"
+    );
+
+    // The host can restrict a guest that does not declare a manifest, e.g. one that reads its
+    // input from stdin.
+    let run = |manifest: SyscallManifest| {
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::DoNothing)
+            .unwrap()
+            .syscall_manifest(manifest)
             .build()
             .unwrap();
-        ExecutorImpl::from_elf(env, STANDARD_LIB_ELF)
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap().run()
+    };
+    let err = run(SyscallManifest::default()).unwrap_err();
+    assert!(
+        format!("{err:?}").contains("not in the syscall manifest"),
+        "{err:?}"
+    );
+    let session = run(manifest.clone()).unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert_eq!(session.syscall_manifest, Some(manifest));
+}
+
+#[test]
+fn memoize() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::Memoize { n: 1000 })
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+    let (first, second, first_cycles, second_cycles): (Digest, Digest, u64, u64) =
+        session.journal.unwrap().decode().unwrap();
+    assert_eq!(first, second);
+    assert!(second_cycles < first_cycles / 10);
+}
+
+#[test]
+fn read_frames() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::ReadFrames)
+        .unwrap()
+        .write_frame(&7u32)
+        .unwrap()
+        .write_frame(&"seven".to_string())
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let (count, name): (u32, String) = session.journal.unwrap().decode().unwrap();
+    assert_eq!((count, name.as_str()), (7, "seven"));
+
+    // Frames written in the wrong order are reported, rather than decoded as the wrong type.
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::ReadFrames)
+        .unwrap()
+        .write_frame(&"seven".to_string())
+        .unwrap()
+        .write_frame(&7u32)
+        .unwrap()
+        .build()
+        .unwrap();
+    let err = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .map(|_| ())
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("input frame 0 was written as String, but read as u32"));
+
+    // As is input written without a frame.
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::ReadFrames)
+        .unwrap()
+        .write(&7u32)
+        .unwrap()
+        .build()
+        .unwrap();
+    let err = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .map(|_| ())
+        .unwrap_err();
+    assert!(err.to_string().contains("not written as a frame"));
+}
+
+#[test]
+fn named_stream() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::NamedStream)
+        .unwrap()
+        .named_stream("prices", [1u8, 2, 3].as_slice())
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let (fd, dup_fd, closed_std, closed, buf): (u32, u32, [u32; 4], [u32; 2], Vec<u8>) =
+        session.journal.unwrap().decode().unwrap();
+
+    // The standard descriptors are neither handed out nor closed.
+    assert!(fd > fileno::JOURNAL && dup_fd > fileno::JOURNAL);
+    assert_ne!(fd, dup_fd);
+    assert_eq!(closed_std, [fileno::INVALID; 4]);
+    assert_eq!(closed, [0, fileno::INVALID]);
+    assert_eq!(buf, [1, 2, 3]);
+}
+
+#[test]
+fn authenticated_store() {
+    let store = AuthenticatedStore::new();
+    store.insert(b"alice", &100u64.to_le_bytes()).unwrap();
+    store.insert(b"bob", &50u64.to_le_bytes()).unwrap();
+    store.insert(b"dave", &0u64.to_le_bytes()).unwrap();
+    let initial_root = store.root();
+
+    // The stores live outside the input, so runs using them must not be served from the cache.
+    let cache = ExecutionCache::new(4);
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::AuthenticatedStore { root: initial_root })
+        .unwrap()
+        .authenticated_store("accounts", store.clone())
+        .execution_cache(&cache)
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let root: Digest = session.journal.unwrap().decode().unwrap();
+    assert_eq!(root, store.root());
+    assert_eq!(store.get(b"alice"), Some(70u64.to_le_bytes().to_vec()));
+    assert_eq!(store.get(b"bob"), Some(80u64.to_le_bytes().to_vec()));
+    assert_eq!(store.get(b"dave"), None);
+
+    assert!(cache.is_empty());
+
+    // The guest rejects entries that do not match the root it was given, even when the rest of
+    // the request matches an earlier run.
+    let tampered = AuthenticatedStore::new();
+    tampered.insert(b"alice", &1000u64.to_le_bytes()).unwrap();
+    tampered.insert(b"bob", &50u64.to_le_bytes()).unwrap();
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::AuthenticatedStore { root: initial_root })
+        .unwrap()
+        .authenticated_store("accounts", tampered)
+        .execution_cache(&cache)
+        .build()
+        .unwrap();
+    let err = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("witness does not match"));
+}
+
+#[test]
+fn mul_div() {
+    let cases = vec![
+        (7, 1, 2),
+        (5, 1, 2),
+        (-5, 1, 2),
+        (-7, 1, 2),
+        (10, 10, 3),
+        (10i128.pow(30), 10i128.pow(20), 10i128.pow(18)),
+        (i128::MAX, i128::MAX, i128::MAX),
+        (i128::MIN, 1, 1),
+        (i128::MAX, 2, 1),
+        (1, 1, 0),
+    ];
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::MulDiv { cases })
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+    let (results, price): (Vec<Option<i128>>, String) = session.journal.unwrap().decode().unwrap();
+    assert_eq!(
+        results,
+        [
+            Some(4),
+            Some(2),
+            Some(-2),
+            Some(-4),
+            Some(33),
+            Some(10i128.pow(32)),
+            Some(i128::MAX),
+            Some(i128::MIN),
+            None,
+            None,
+        ]
+    );
+    // 10.05 * 0.50 = 5.025, which rounds to the even cent.
+    assert_eq!(price, "5.02");
+}
+
+#[test]
+fn fp_env() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::FpEnv)
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    let fp_env: FpEnv = session.journal.unwrap().decode().unwrap();
+    assert_eq!(fp_env, FpEnv::ZKVM);
+}
+
+#[test]
+fn memory_output() {
+    let len = 3 * PAGE_SIZE as u32 + 5;
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::MemoryOutput { len })
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Paused(0));
+
+    let expected: Vec<u8> = (0..len).map(|i| i as u8).collect();
+    assert_eq!(session.memory_output().unwrap(), expected);
+
+    // A halted session does not commit to its memory.
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert!(session.read_post_memory(MEMORY_OUTPUT, 8).is_err());
+}
+
+#[test]
+fn commit_fields() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::CommitFields)
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+    let fields = &session.committed_fields;
+    let names: Vec<&str> = fields
+        .fields
+        .iter()
+        .map(|field| field.name.as_str())
+        .collect();
+    assert_eq!(names, ["name", "age", "balance"]);
+
+    let journal = session.journal.unwrap().bytes;
+    let redacted = fields.redact_fields(&["age"]);
+    redacted.verify_journal(&journal).unwrap();
+    assert_eq!(
+        redacted.field_by_name("age"),
+        Some(&30u32.to_le_bytes()[..])
+    );
+    assert_eq!(redacted.field_by_name("name"), None);
+
+    // The journal holds only the root, and each name can be committed once.
+    for (spec, msg) in [
+        (MultiTestSpec::CommitDuplicateField, "duplicate field name"),
+        (
+            MultiTestSpec::CommitFieldAfterSlice,
+            "cannot be combined with other journal data",
+        ),
+    ] {
+        let env = ExecutorEnv::builder()
+            .write(&spec)
             .unwrap()
-            .run()
+            .build()
             .unwrap();
+        let err = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap_err();
+        assert!(err.to_string().contains(msg), "{err}");
     }
-    assert_eq!(from_utf8(&stdout).unwrap(), expected_stdout());
-    assert_eq!(from_utf8(&stderr).unwrap(), EXPECTED_STDERR);
 }
 
+#[cfg(feature = "journal-encryption")]
 #[test]
-fn environment() {
+fn commit_encrypted() {
+    use crate::{
+        encryption::{public_key, EncryptedJournal},
+        Journal,
+    };
+
+    let secret_key = [7u8; 32];
+    let data = b"for the recipient only".to_vec();
     let env = ExecutorEnv::builder()
-        .env_var("TEST_MODE", "ENV_VARS")
-        .env_var("ENV_VAR1", "val1")
-        .env_var("ENV_VAR2", "")
-        .read_fd(
-            fileno::STDIN,
-            Cursor::new(
-                r"ENV_VAR1
-ENV_VAR2
-ENV_VAR3",
-            ),
-        )
+        .write(&MultiTestSpec::CommitEncrypted { data: data.clone() })
+        .unwrap()
+        .journal_recipient(public_key(&secret_key))
         .build()
         .unwrap();
-    let mut exec = ExecutorImpl::from_elf(env, STANDARD_LIB_ELF).unwrap();
-    let session = exec.run().unwrap();
-    let actual = &session.journal.as_ref().unwrap().bytes;
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+
+    // The journal commits to the ciphertext, which only the recipient can decrypt.
+    let encrypted: EncryptedJournal = session.journal.unwrap().decode().unwrap();
+    assert_eq!(encrypted.recipient, public_key(&secret_key));
+    let plaintext = Journal::new(encrypted.decrypt(&secret_key).unwrap());
+    assert_eq!(plaintext.decode::<Vec<u8>>().unwrap(), data);
+    assert!(encrypted.decrypt(&[8u8; 32]).is_err());
+
+    // Without a recipient, the guest cannot encrypt its journal.
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::CommitEncrypted { data })
+        .unwrap()
+        .build()
+        .unwrap();
+    let err = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("none was set"), "{err:#}");
+}
+
+#[test]
+fn execution_cache() {
+    let cache = ExecutionCache::new(4);
+    let slice = b"cache me";
+    let run = |segment_limit_po2: u32| {
+        let env = ExecutorEnv::builder()
+            .write_slice(&[slice.len() as u32])
+            .write_slice(slice)
+            .segment_limit_po2(segment_limit_po2)
+            .execution_cache(&cache)
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, SLICE_IO_ELF)
+            .unwrap()
+            .run()
+            .unwrap()
+    };
+
+    let session = run(14);
+    assert_eq!(cache.len(), 1);
+    let cached = run(14);
+    assert_eq!(cache.len(), 1);
+    assert_eq!(cached.journal.unwrap().bytes, slice);
+    assert_eq!(cached.exit_code, session.exit_code);
+    assert_eq!(cached.user_cycles, session.user_cycles);
+    assert_eq!(cached.segments.len(), session.segments.len());
+    assert_eq!(
+        cached.segments[0]
+            .resolve()
+            .unwrap()
+            .inner
+            .pre_state
+            .digest(),
+        session.segments[0]
+            .resolve()
+            .unwrap()
+            .inner
+            .pre_state
+            .digest()
+    );
     assert_eq!(
-        from_utf8(actual).unwrap(),
-        r"ENV_VAR1=val1
-ENV_VAR2=
-!ENV_VAR3
-"
+        cached.claim().unwrap().digest(),
+        session.claim().unwrap().digest()
     );
-}
 
-#[test]
-fn args() {
-    let test_cases: [&[String]; 3] = [
-        &[String::default()],
-        &[
-            "grep".to_string(),
-            "-c".to_string(),
-            "foo bar".to_string(),
-            "-".to_string(),
-        ],
-        &[String::default()],
+    // A different environment is executed again.
+    run(15);
+    assert_eq!(cache.len(), 2);
+
+    let quotas = QuotaManager::new();
+    let run_with = |configure: &dyn Fn(&mut ExecutorEnvBuilder)| {
+        let mut builder = ExecutorEnv::builder();
+        builder
+            .write_slice(&[slice.len() as u32])
+            .write_slice(slice)
+            .segment_limit_po2(14)
+            .execution_cache(&cache);
+        configure(&mut builder);
+        let env = builder.build().unwrap();
+        ExecutorImpl::from_elf(env, SLICE_IO_ELF)
+            .unwrap()
+            .run()
+            .unwrap()
+    };
+
+    // Settings that change the result are part of the key.
+    run_with(&|builder| {
+        builder.journal_recipient([7; 32]);
+    });
+    assert_eq!(cache.len(), 3);
+
+    // Runs with effects beyond their result neither use nor fill the cache.
+    let bypass: [&dyn Fn(&mut ExecutorEnvBuilder); 4] = [
+        &|builder| {
+            builder.self_check(true);
+        },
+        &|builder| {
+            builder.tenant("a", &quotas);
+        },
+        &|builder| {
+            builder.watch(0..4, WatchKind::Write);
+        },
+        &|builder| {
+            builder.cycle_audit(true);
+        },
     ];
-    for args_arr in test_cases {
-        let env = ExecutorEnv::builder()
-            .env_var("TEST_MODE", "ARGS")
-            .args(&args_arr)
-            .build()
-            .unwrap();
-        let mut exec = ExecutorImpl::from_elf(env, STANDARD_LIB_ELF).unwrap();
-        let session = exec.run().unwrap();
-        let output: Vec<String> = session.journal.unwrap().decode().unwrap();
-        assert_eq!(
-            output,
-            args_arr
-                .into_iter()
-                .map(|s| s.to_string())
-                .collect::<Vec<String>>(),
-        );
+    for configure in bypass {
+        let len = cache.len();
+        let session = run_with(configure);
+        assert_eq!(session.journal.unwrap().bytes, slice);
+        assert_eq!(cache.len(), len);
     }
+
+    // A run with empty stdin is cached, but not one that reads stdin from a reader.
+    let cache = ExecutionCache::new(4);
+    let run_hello = |stdin: Option<&'static [u8]>| {
+        let mut builder = ExecutorEnv::builder();
+        builder.execution_cache(&cache);
+        if let Some(stdin) = stdin {
+            builder.stdin(stdin);
+        }
+        let env = builder.build().unwrap();
+        ExecutorImpl::from_elf(env, HELLO_COMMIT_ELF)
+            .unwrap()
+            .run()
+            .unwrap()
+    };
+    run_hello(None);
+    assert_eq!(cache.len(), 1);
+    run_hello(Some(b""));
+    assert_eq!(cache.len(), 1);
 }
 
 #[test]
-fn buf_read() {
-    // Host-provided input is 7 bytes, while the guest requests to read 9.
-    let input = b"1234567";
+fn self_check() {
+    let slice = b"check me";
     let env = ExecutorEnv::builder()
-        .env_var("TEST_MODE", "BUF_READ")
-        // Previously failed on anything > buf and not % 4 == 0
-        // https://github.com/risc0/risc0/pull/1557
-        .write(&9usize)
-        .unwrap()
-        .write_slice(input.as_slice())
+        .write_slice(&[slice.len() as u32])
+        .write_slice(slice)
+        .segment_limit_po2(14)
+        .self_check(true)
         .build()
         .unwrap();
-    let mut exec = ExecutorImpl::from_elf(env, STANDARD_LIB_ELF).unwrap();
-    let session = exec.run().unwrap();
-    let output = session.journal.unwrap().bytes;
-    assert_eq!(output, input);
+    let session = ExecutorImpl::from_elf(env, SLICE_IO_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.journal.unwrap().bytes, slice);
+    // The transcript used by the self-check is only exposed when requested.
+    assert!(session.syscall_transcript().is_none());
 }
 
 #[test]
-fn commit_hello_world() {
-    ExecutorImpl::from_elf(ExecutorEnv::default(), HELLO_COMMIT_ELF)
+fn self_check_nondeterministic_handler() {
+    // The re-run answers syscalls from the transcript of the first run, so a handler that gives a
+    // different answer every time is only called by the first run, and the session is consistent
+    // with the answers the guest actually received.
+    let count = 4;
+    let calls = AtomicUsize::new(0);
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::Syscall { count })
+        .unwrap()
+        .io_callback(SYS_MULTI_TEST, |_| {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            Ok(Bytes::from(format!("answer {call}")))
+        })
+        .self_check(true)
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
         .unwrap()
         .run()
         .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert_eq!(calls.load(Ordering::SeqCst), count as usize);
 }
 
 #[test]
-fn random() {
-    run_test(MultiTestSpec::DoRandom);
+fn self_check_divergence() {
+    let expected = RunOutcome {
+        exit_code: ExitCode::Halted(0),
+        pc: 0x1000,
+        merkle_root: Digest::from([1u32; 8]),
+        output_digest: Some(Digest::from([2u32; 8])),
+        user_cycles: 100,
+        total_cycles: 1 << 16,
+    };
+    check_outcomes(&expected, &expected, 0).unwrap();
+
+    let diverged: [(&str, fn(&mut RunOutcome)); 6] = [
+        ("exit code", |outcome| {
+            outcome.exit_code = ExitCode::Halted(1)
+        }),
+        ("pc 0x00001004 != 0x00001000", |outcome| outcome.pc += 4),
+        ("memory root", |outcome| outcome.merkle_root = Digest::ZERO),
+        ("journal digest", |outcome| outcome.output_digest = None),
+        ("user cycles 101 != 100", |outcome| outcome.user_cycles += 1),
+        ("total cycles", |outcome| outcome.total_cycles *= 2),
+    ];
+    for (diff, diverge) in diverged {
+        let mut actual = expected.clone();
+        diverge(&mut actual);
+        let err = check_outcomes(&expected, &actual, 0)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("Self-check failed"), "{err}");
+        assert!(err.contains(diff), "{err}");
+    }
+
+    // Syscalls the first run made but the re-run did not are a divergence too.
+    let err = check_outcomes(&expected, &expected, 2).unwrap_err();
+    assert!(err.to_string().contains("2 syscalls were not made"));
+
+    // Every difference is reported, not just the first.
+    let actual = RunOutcome {
+        pc: 0x2000,
+        user_cycles: 0,
+        ..expected.clone()
+    };
+    let err = check_outcomes(&expected, &actual, 1)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("pc") && err.contains("user cycles") && err.contains("1 syscalls"));
 }
 
 #[test]
-#[should_panic(expected = "WARNING: `getrandom()` called from guest.")]
-fn getrandom_panic() {
-    let env = ExecutorEnv::builder().build().unwrap();
-    let _session = ExecutorImpl::from_elf(env, RAND_ELF)
+fn segment_replay() {
+    let slice = b"replay me";
+    let env = ExecutorEnv::builder()
+        .write_slice(&[slice.len() as u32])
+        .write_slice(slice)
+        .segment_limit_po2(14)
+        .record_syscall_transcript()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, SLICE_IO_ELF)
         .unwrap()
         .run()
         .unwrap();
-}
+    assert!(session.segments.len() > 1);
 
-#[test]
-fn slice_io() {
-    let run = |slice: &[u8]| {
-        let env = ExecutorEnv::builder()
-            .write_slice(&[slice.len() as u32])
-            .write_slice(slice)
-            .build()
+    for (idx, segment_ref) in session.segments.iter().enumerate() {
+        let segment = segment_ref.resolve().unwrap();
+        let transcript = session.segment_transcript(idx as u32).unwrap();
+        assert_eq!(transcript.entries.len(), segment.inner.syscalls.len());
+
+        let recorded = segment.replay(None).unwrap();
+        assert!(recorded.matches_segment);
+        assert_eq!(recorded.exit_code, segment.inner.exit_code);
+        assert_eq!(recorded.post_state, segment.inner.post_state.digest());
+
+        let mut insns = 0;
+        let replayed = segment
+            .replay_with_hook(Some(&transcript), |event| {
+                if let TraceEvent::InstructionStart { .. } = event {
+                    insns += 1;
+                }
+                Ok(())
+            })
             .unwrap();
-        let mut exec = ExecutorImpl::from_elf(env, SLICE_IO_ELF).unwrap();
-        let session = exec.run().unwrap();
-        assert_eq!(session.journal.unwrap().bytes, slice);
-    };
+        assert_eq!(replayed, recorded);
+        assert!(insns > 0);
 
-    run(b"");
-    run(b"xyz");
-    run(b"0000");
+        // A transcript naming a different syscall is rejected.
+        if let Some(entry) = transcript.entries.first() {
+            let mut wrong = transcript.clone();
+            wrong.entries[0].name = format!("{}_other", entry.name);
+            assert!(segment.replay(Some(&wrong)).is_err());
+        }
+    }
+    assert!(session
+        .segment_transcript(session.segments.len() as u32)
+        .is_err());
 }
 
 // Check that a compliant host will return an error on panic.
@@ -905,6 +3580,22 @@ fn panic() {
     assert!(err.to_string().contains("MultiTestSpec::Panic invoked"));
 }
 
+#[test]
+fn panic_at() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::PanicAt)
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+    let err = exec.run().err().unwrap();
+    assert!(
+        err.to_string()
+            .contains("Guest panicked at src/bin/multi_test.rs:12:34"),
+        "{err}"
+    );
+}
+
 #[test]
 fn fault() {
     let env = ExecutorEnv::builder()
@@ -915,6 +3606,8 @@ fn fault() {
     let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
     let err = exec.run().err().unwrap();
     assert!(err.to_string().contains("StoreAccessFault"));
+    // The fault is reported along with the guest function that caused it.
+    assert!(err.to_string().contains("\n    at 0x"), "{err}");
 }
 
 #[test]
@@ -1012,8 +3705,194 @@ fn oom() {
         .build()
         .unwrap();
     let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
-    let err = exec.run().err().unwrap();
-    assert!(err.to_string().contains("Out of memory"), "{err:?}");
+    let err = exec.run().err().unwrap().to_string();
+    assert!(err.contains("Guest ran out of heap"), "{err}");
+    let requested = format!("requested {} bytes", SYSTEM.start());
+    assert!(err.contains(&requested), "{err}");
+
+    // The reported pc is in the code that allocated, not in the allocator.
+    let pc = err
+        .split("pc=0x")
+        .nth(1)
+        .unwrap()
+        .split(':')
+        .next()
+        .unwrap();
+    let pc = u32::from_str_radix(pc, 16).unwrap();
+    let symbolizer = Symbolizer::new(MULTI_TEST_ELF).unwrap();
+    let function = symbolizer.function_name(pc).unwrap();
+    assert!(!function.contains("sys_alloc"), "{function}");
+}
+
+#[test]
+fn tenant_quota() {
+    let quotas = QuotaManager::new();
+    let run = |spec: MultiTestSpec| {
+        let env = ExecutorEnv::builder()
+            .write(&spec)
+            .unwrap()
+            .tenant("a", &quotas)
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap().run()
+    };
+
+    // Sessions are charged for the cycles they execute whether they halt, fail, or stop at the
+    // session limit.
+    let session = run(MultiTestSpec::BusyLoop { cycles: 1 << 16 }).unwrap();
+    assert_eq!(quotas.usage("a").cycles, session.user_cycles);
+    run(MultiTestSpec::Panic).err().unwrap();
+    let failed = quotas.usage("a").cycles - session.user_cycles;
+    assert!(failed > 0);
+
+    let used = quotas.usage("a").cycles;
+    quotas.set_quota(
+        "a",
+        TenantQuota {
+            max_cycles: Some(used + 1000),
+            max_memory: None,
+        },
+    );
+    let session = run(MultiTestSpec::BusyLoop { cycles: 1 << 16 }).unwrap();
+    assert_eq!(session.exit_code, ExitCode::SessionLimit);
+    assert_eq!(quotas.usage("a").cycles, used + session.user_cycles);
+
+    // The memory quota covers the pages the guest touches while it runs, not just its image.
+    let program = Program::load_elf(MULTI_TEST_ELF, GUEST_MAX_MEM as u32).unwrap();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    quotas.reset_cycles("a");
+    quotas.set_quota(
+        "a",
+        TenantQuota {
+            max_cycles: None,
+            max_memory: Some(((image.pages.len() + 64) * PAGE_SIZE) as u64),
+        },
+    );
+    let err = run(MultiTestSpec::Recurse { depth: 1024 }).err().unwrap();
+    assert!(
+        format!("{err:?}").contains("Memory quota exceeded"),
+        "{err:?}"
+    );
+    assert_eq!(quotas.usage("a").memory, 0);
+}
+
+#[test]
+fn stack_size() {
+    fn recurse(depth: u32, stack_size: Option<u32>) -> Result<Session> {
+        let mut builder = ExecutorEnv::builder();
+        builder.write(&MultiTestSpec::Recurse { depth }).unwrap();
+        if let Some(stack_size) = stack_size {
+            builder.stack_size(stack_size);
+        }
+        ExecutorImpl::from_elf(builder.build().unwrap(), MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+    }
+
+    assert_eq!(
+        recurse(16, Some(64 * 1024)).unwrap().exit_code,
+        ExitCode::Halted(0)
+    );
+    let err = recurse(128, Some(64 * 1024)).err().unwrap();
+    assert!(err.to_string().contains("stack overflow at pc="), "{err:?}");
+    // Without a stack size, the stack may use all of guest memory below it.
+    assert_eq!(recurse(128, None).unwrap().exit_code, ExitCode::Halted(0));
+
+    let err = ExecutorEnv::builder()
+        .stack_size(STACK_TOP)
+        .build()
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("exceeds the maximum"), "{err:?}");
+}
+
+#[test]
+fn wall_clock_deadline() {
+    let spec = MultiTestSpec::ShaDigestIter {
+        data: vec![0; 32],
+        num_iter: 1000,
+    };
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(14)
+        .build()
+        .unwrap();
+    let expected = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+
+    // With a deadline that has already passed, each run stops at its first split outside a
+    // syscall.
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(14)
+        .wall_clock_deadline(Instant::now())
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+    let mut runs = 0;
+    let mut user_cycles = 0;
+    let session = loop {
+        let session = exec.run().unwrap();
+        runs += 1;
+        user_cycles += session.user_cycles;
+        if session.exit_code != ExitCode::SystemSplit {
+            break session;
+        }
+    };
+    assert!(runs > 1);
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert_eq!(session.journal, expected.journal);
+    assert_eq!(user_cycles, expected.user_cycles);
+}
+
+#[test]
+fn pause_at_cycle() {
+    let spec = MultiTestSpec::ShaDigestIter {
+        data: vec![0; 32],
+        num_iter: 1000,
+    };
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(14)
+        .build()
+        .unwrap();
+    let expected = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert!(expected.segments.len() > 2);
+
+    // Each run stops once it has executed two segments, unless it ends in a syscall.
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(14)
+        .pause_at_cycle(2 << 14)
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+    let mut runs = 0;
+    let mut user_cycles = 0;
+    let session = loop {
+        let session = exec.run().unwrap();
+        runs += 1;
+        user_cycles += session.user_cycles;
+        if session.exit_code != ExitCode::SystemSplit {
+            break session;
+        }
+        assert!(session.total_cycles >= 2 << 14);
+        assert!(session.segments.len() >= 2);
+        session.claim().unwrap();
+    };
+    assert!(runs > 1);
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert_eq!(session.journal, expected.journal);
+    assert_eq!(user_cycles, expected.user_cycles);
 }
 
 #[test]
@@ -1093,7 +3972,8 @@ fn post_state_digest_randomization() {
             let mut exec =
                 ExecutorImpl::from_elf(ExecutorEnv::default(), HELLO_COMMIT_ELF).unwrap();
             // Override the default randomness syscall using crate-internal API.
-            exec.syscall_table.with_syscall(SYS_RANDOM, RiggedRandom);
+            exec.syscall_table
+                .with_syscall(SYS_RANDOM, SyscallRing::Input, RiggedRandom);
 
             exec.run()
                 .unwrap()
@@ -1110,6 +3990,50 @@ fn post_state_digest_randomization() {
     assert_eq!(post_state_digests.len(), 1);
 }
 
+#[test]
+fn syscall_list() {
+    let env = ExecutorEnv::builder()
+        .io_callback(SYS_MULTI_TEST, |buf| Ok(buf))
+        .io_callback("test.checked", |buf| Ok(buf))
+        .syscall_ring("test.checked", SyscallRing::Checked)
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+    let describe = |exec: &ExecutorImpl, name: &str| {
+        let list = exec.syscall_table.list();
+        let syscall = list.iter().find(|syscall| syscall.name == name).unwrap();
+        (syscall.provenance, syscall.ring)
+    };
+
+    assert_eq!(
+        describe(&exec, SYS_READ.as_str()),
+        (SyscallProvenance::Builtin, SyscallRing::Input)
+    );
+    assert_eq!(
+        describe(&exec, SYS_WRITE.as_str()),
+        (SyscallProvenance::Builtin, SyscallRing::Output)
+    );
+    assert_eq!(
+        describe(&exec, SYS_CYCLE_COUNT.as_str()),
+        (SyscallProvenance::Builtin, SyscallRing::Input)
+    );
+    assert_eq!(
+        describe(&exec, SYS_MULTI_TEST.as_str()),
+        (SyscallProvenance::Env, SyscallRing::Input)
+    );
+    assert_eq!(
+        describe(&exec, "test.checked"),
+        (SyscallProvenance::Env, SyscallRing::Checked)
+    );
+
+    exec.syscall_table
+        .with_syscall(SYS_RANDOM, SyscallRing::Checked, SysCycleCount);
+    assert_eq!(
+        describe(&exec, SYS_RANDOM.as_str()),
+        (SyscallProvenance::Override, SyscallRing::Checked)
+    );
+}
+
 #[test]
 fn aligned_alloc() {
     run_test(MultiTestSpec::AlignedAlloc);
@@ -1154,7 +4078,7 @@ mod docker {
     use risc0_zkvm_methods::{multi_test::MultiTestSpec, MULTI_TEST_ELF};
     use risc0_zkvm_platform::WORD_SIZE;
 
-    use crate::{ExecutorEnv, ExecutorImpl, Session, TraceEvent};
+    use crate::{ExecutorEnv, ExecutorImpl, ExitCode, TraceEvent};
 
     #[test]
     fn trace() {
@@ -1228,7 +4152,7 @@ mod docker {
             loop_cycles: u64,
             segment_limit_po2: u32,
             session_count_limit: u64,
-        ) -> anyhow::Result<Session> {
+        ) -> ExitCode {
             let session_cycles = (1 << segment_limit_po2) * session_count_limit;
             let spec = MultiTestSpec::BusyLoop {
                 cycles: loop_cycles,
@@ -1240,27 +4164,53 @@ mod docker {
                 .session_limit(Some(session_cycles))
                 .build()
                 .unwrap();
-            ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap().run()
+            let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+                .unwrap()
+                .run()
+                .unwrap();
+            if session.exit_code == ExitCode::SessionLimit {
+                assert!(session.user_cycles >= session_cycles);
+                assert!(!session.segments.is_empty());
+                assert!(session.claim().is_err());
+            }
+            session.exit_code
         }
 
-        // This test should always fail if the last parameter is zero
-        let err = run_session(0, 16, 0).err().unwrap();
-        assert!(err.to_string().contains("Session limit exceeded"));
+        // This test should always stop at the limit if the last parameter is zero
+        assert_eq!(run_session(0, 16, 0), ExitCode::SessionLimit);
 
-        assert!(run_session(0, 16, 2).is_ok());
+        assert_eq!(run_session(0, 16, 2), ExitCode::Halted(0));
 
-        let err = run_session(1 << 16, 16, 1).err().unwrap();
-        assert!(err.to_string().contains("Session limit exceeded"));
+        assert_eq!(run_session(1 << 16, 16, 1), ExitCode::SessionLimit);
 
         // this should contain exactly 2 segments
-        assert!(run_session(1 << 16, 16, 2).is_ok());
+        assert_eq!(run_session(1 << 16, 16, 2), ExitCode::Halted(0));
 
         // it's ok to run with a limit that's higher than the actual count
-        assert!(run_session(1 << 16, 16, 10).is_ok());
+        assert_eq!(run_session(1 << 16, 16, 10), ExitCode::Halted(0));
 
-        let err = run_session(1 << 16, 15, 2).err().unwrap();
-        assert!(err.to_string().contains("Session limit exceeded"));
+        assert_eq!(run_session(1 << 16, 15, 2), ExitCode::SessionLimit);
 
-        assert!(run_session(1 << 16, 15, 17).is_ok());
+        assert_eq!(run_session(1 << 16, 15, 17), ExitCode::Halted(0));
     }
 }
+
+#[cfg(feature = "rv64im")]
+#[test]
+fn rv64im_journal() {
+    use risc0_circuit_rv32im::prove::emu::testutil::{rv64_hello, RV64_HELLO_JOURNAL};
+
+    let env = ExecutorEnv::builder().build().unwrap();
+    let session = crate::execute_rv64(env, &rv64_hello()).unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert_eq!(session.journal, RV64_HELLO_JOURNAL);
+    assert_eq!(session.user_cycles, 18);
+
+    let env = ExecutorEnv::builder()
+        .session_limit(Some(4))
+        .build()
+        .unwrap();
+    let session = crate::execute_rv64(env, &rv64_hello()).unwrap();
+    assert_eq!(session.exit_code, ExitCode::SessionLimit);
+    assert!(session.journal.is_empty());
+}
@@ -12,9 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub(crate) mod bundle;
 pub(crate) mod exec;
 #[cfg(feature = "prove")]
 pub(crate) mod prove;
 pub(crate) mod session;
+pub(crate) mod stream;
 #[cfg(test)]
 mod testutils;
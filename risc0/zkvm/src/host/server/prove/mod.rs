@@ -19,10 +19,13 @@ mod prover_impl;
 #[cfg(test)]
 mod tests;
 
-use std::rc::Rc;
+use std::{fmt, rc::Rc};
 
 use anyhow::{anyhow, bail, ensure, Result};
-use risc0_circuit_rv32im::prove::segment_prover;
+use risc0_circuit_rv32im::{
+    prove::{backend_segment_prover, segment_prover},
+    CIRCUIT_ID,
+};
 use risc0_core::field::baby_bear::{BabyBear, Elem, ExtElem};
 use risc0_zkp::hal::{CircuitHal, Hal};
 
@@ -35,9 +38,9 @@ use crate::{
         InnerReceipt, SegmentReceipt, SuccinctReceipt,
     },
     receipt_claim::Unknown,
-    sha::Digestible,
-    stark_to_snark, ExecutorEnv, ExecutorImpl, ProverOpts, Receipt, ReceiptClaim, ReceiptKind,
-    Segment, Session, VerifierContext,
+    sha::{Digest, Digestible},
+    stark_to_snark, ExecutorEnv, ExecutorImpl, InMemorySegmentRef, ProverOpts, Receipt,
+    ReceiptClaim, ReceiptKind, Segment, Session, SessionBundle, VerifierContext,
 };
 
 /// A ProverServer can execute a given ELF binary and produce a [ProveInfo] which contains a
@@ -49,21 +52,42 @@ pub trait ProverServer {
     }
 
     /// Prove the specified ELF binary using the specified [VerifierContext].
+    ///
+    /// The segments are saved to the [segment path][crate::ExecutorEnvBuilder::segment_path], or
+    /// handed to the prover in memory, as [InMemorySegmentRef]s, if the environment asks for
+    /// [segments_in_memory][crate::ExecutorEnvBuilder::segments_in_memory].
     fn prove_with_ctx(
         &self,
         env: ExecutorEnv<'_>,
         ctx: &VerifierContext,
         elf: &[u8],
     ) -> Result<ProveInfo> {
+        let in_memory = env.segments_in_memory;
         let mut exec = ExecutorImpl::from_elf(env, elf)?;
-        let session = exec.run()?;
+        let session = if in_memory {
+            exec.run_with_callback(|segment| Ok(Box::new(InMemorySegmentRef::new(segment))))?
+        } else {
+            exec.run()?
+        };
         self.prove_session(ctx, &session)
     }
 
     /// Prove the specified [Session].
     fn prove_session(&self, ctx: &VerifierContext, session: &Session) -> Result<ProveInfo>;
 
+    /// Prove the session saved in a [SessionBundle], reusing the segment receipts saved in it.
+    ///
+    /// Each segment receipt is saved to the bundle as soon as it is proven, so a prover process
+    /// that dies part way through can be restarted without proving those segments again. The
+    /// default implementation proves every segment, and saves nothing.
+    fn resume_proving(&self, ctx: &VerifierContext, bundle: &SessionBundle) -> Result<ProveInfo> {
+        self.prove_session(ctx, &bundle.session()?)
+    }
+
     /// Prove the specified [Segment].
+    ///
+    /// Fails with a [VersionMismatch] if the segment was executed with a different circuit than
+    /// the prover's.
     fn prove_segment(&self, ctx: &VerifierContext, segment: &Segment) -> Result<SegmentReceipt>;
 
     /// Lift a [SegmentReceipt] into a [SuccinctReceipt]
@@ -161,6 +185,7 @@ pub trait ProverServer {
     /// provided [ProverOpts]. If the receipt is already at least as compressed as the requested
     /// kind, this is a no-op.
     fn compress(&self, opts: &ProverOpts, receipt: &Receipt) -> Result<Receipt> {
+        let _span = tracing::info_span!("compress").entered();
         match &receipt.inner {
             InnerReceipt::Composite(inner) => match opts.receipt_kind {
                 ReceiptKind::Composite => Ok(receipt.clone()),
@@ -229,6 +254,45 @@ impl Session {
     }
 }
 
+/// The error returned when a [ProverServer] is asked to prove a [Segment] produced by an executor
+/// built with a different circuit, whose proof would fail verification.
+///
+/// Circuits are identified by their Poseidon2 control ID for the smallest segment size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionMismatch {
+    /// The circuit ID recorded in the segment.
+    pub segment: Digest,
+
+    /// The circuit ID of the prover.
+    pub prover: Digest,
+}
+
+impl VersionMismatch {
+    /// Check that `segment` was produced with the circuit of this prover.
+    pub(crate) fn check(segment: &Segment) -> Result<(), Self> {
+        let circuit_id = segment.circuit_id();
+        if circuit_id == CIRCUIT_ID {
+            return Ok(());
+        }
+        Err(Self {
+            segment: circuit_id,
+            prover: CIRCUIT_ID,
+        })
+    }
+}
+
+impl fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "segment was executed with circuit {}, but the prover has circuit {}",
+            self.segment, self.prover
+        )
+    }
+}
+
+impl std::error::Error for VersionMismatch {}
+
 /// Select a [ProverServer] based on the specified [ProverOpts] and currently
 /// compiled features.
 pub fn get_prover_server(opts: &ProverOpts) -> Result<Rc<dyn ProverServer>> {
@@ -237,6 +301,9 @@ pub fn get_prover_server(opts: &ProverOpts) -> Result<Rc<dyn ProverServer>> {
         return Ok(Rc::new(DevModeProver));
     }
 
-    let prover = segment_prover(&opts.hashfn)?;
+    let prover = match &opts.backend {
+        Some(backend) => backend_segment_prover(backend, &opts.hashfn)?,
+        None => segment_prover(&opts.hashfn)?,
+    };
     Ok(Rc::new(ProverImpl::new(opts.clone(), prover)))
 }
@@ -12,10 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use risc0_circuit_rv32im::prove::SegmentProver;
 
-use super::ProverServer;
+use super::{ProverServer, VersionMismatch};
 use crate::{
     host::{
         client::prove::ReceiptKind,
@@ -27,9 +27,42 @@ use crate::{
     },
     receipt_claim::{MaybePruned, Merge, Unknown},
     sha::Digestible,
-    CompositeReceipt, Output, ProverOpts, Receipt, ReceiptClaim, Segment, Session, VerifierContext,
+    CompositeReceipt, ExitCode, Output, ProverOpts, Receipt, ReceiptClaim, Segment, Session,
+    SessionBundle, SessionEvents, VerifierContext,
 };
 
+/// Check that `receipt`, saved in a [SessionBundle], is a valid receipt of `segment`: a stale
+/// receipt, or one saved from another session, proves a different claim.
+fn check_saved_receipt(
+    ctx: &VerifierContext,
+    segment: &Segment,
+    receipt: &SegmentReceipt,
+) -> Result<()> {
+    receipt.verify_integrity_with_context(ctx)?;
+    ensure!(
+        receipt.index == segment.index,
+        "receipt is of segment {}",
+        receipt.index
+    );
+    let claim = &receipt.claim;
+    ensure!(
+        claim.pre.digest() == segment.inner.pre_state.digest(),
+        "receipt does not start from the segment's state"
+    );
+    ensure!(
+        claim.exit_code == segment.inner.exit_code,
+        "receipt exits with {:?}, but the segment exits with {:?}",
+        claim.exit_code,
+        segment.inner.exit_code
+    );
+    let output: MaybePruned<Option<Output>> = segment.output.clone().into();
+    ensure!(
+        claim.output.digest() == output.digest(),
+        "receipt does not commit to the segment's output"
+    );
+    Ok(())
+}
+
 /// An implementation of a Prover that runs locally.
 pub struct ProverImpl {
     opts: ProverOpts,
@@ -44,28 +77,63 @@ impl ProverImpl {
             segment_prover,
         }
     }
-}
 
-impl ProverServer for ProverImpl {
-    fn prove_session(&self, ctx: &VerifierContext, session: &Session) -> Result<ProveInfo> {
-        tracing::debug!(
-            "prove_session: exit_code = {:?}, journal = {:?}, segments: {}",
-            session.exit_code,
-            session.journal.as_ref().map(hex::encode),
-            session.segments.len()
+    /// Prove each segment of `session`, calling `hooks` around the proving of each.
+    ///
+    /// With a `bundle`, the receipts saved in it are reused, and each new receipt is saved to it.
+    fn prove_segments(
+        &self,
+        ctx: &VerifierContext,
+        session: &Session,
+        hooks: &[Box<dyn SessionEvents>],
+        bundle: Option<&SessionBundle>,
+    ) -> Result<Vec<SegmentReceipt>> {
+        ensure!(
+            session.exit_code != ExitCode::SessionLimit,
+            "Session stopped at its session limit, which cannot be proven"
         );
         let mut segments = Vec::new();
-        for segment_ref in session.segments.iter() {
-            let segment = segment_ref.resolve()?;
-            for hook in &session.hooks {
+        for (idx, segment_ref) in session.segments.iter().enumerate() {
+            let segment = segment_ref.resolve_shared()?;
+            if let Some(receipt) = bundle
+                .map(|b| b.segment_receipt(idx))
+                .transpose()?
+                .flatten()
+            {
+                // A receipt that does not prove this segment is proven again rather than failing
+                // the session.
+                match check_saved_receipt(ctx, &segment, &receipt) {
+                    Ok(()) => {
+                        tracing::debug!("reusing the saved receipt of segment {idx}");
+                        segments.push(receipt);
+                        continue;
+                    }
+                    Err(err) => tracing::warn!("saved receipt of segment {idx} is invalid: {err}"),
+                }
+            }
+            for hook in hooks {
                 hook.on_pre_prove_segment(&segment);
             }
-            segments.push(self.prove_segment(ctx, &segment)?);
-            for hook in &session.hooks {
+            let receipt = self.prove_segment(ctx, &segment)?;
+            for hook in hooks {
                 hook.on_post_prove_segment(&segment);
             }
+            if let Some(bundle) = bundle {
+                bundle.save_segment_receipt(idx, &receipt)?;
+            }
+            segments.push(receipt);
         }
+        Ok(segments)
+    }
 
+    /// Assemble the receipts of the segments of `session` into a receipt for the session,
+    /// compressed to the requested [ReceiptKind].
+    fn finish(
+        &self,
+        ctx: &VerifierContext,
+        session: &Session,
+        mut segments: Vec<SegmentReceipt>,
+    ) -> Result<ProveInfo> {
         let (assumptions, session_assumption_receipts) = session
             .assumptions
             .iter()
@@ -163,8 +231,52 @@ impl ProverServer for ProverImpl {
             stats: session.stats(),
         })
     }
+}
+
+impl ProverServer for ProverImpl {
+    fn prove_session(&self, ctx: &VerifierContext, session: &Session) -> Result<ProveInfo> {
+        let _span = tracing::info_span!(
+            "prove_session",
+            session_id = %session.id,
+            segments = session.segments.len(),
+        )
+        .entered();
+        tracing::debug!(
+            "prove_session: exit_code = {:?}, journal = {:?}, segments: {}",
+            session.exit_code,
+            session.journal.as_ref().map(hex::encode),
+            session.segments.len()
+        );
+        let segments = self.prove_segments(ctx, session, &session.hooks, None)?;
+        self.finish(ctx, session, segments)
+    }
+
+    fn resume_proving(&self, ctx: &VerifierContext, bundle: &SessionBundle) -> Result<ProveInfo> {
+        let session = bundle.session()?;
+        let _span = tracing::info_span!(
+            "resume_proving",
+            session_id = %session.id,
+            segments = session.segments.len(),
+        )
+        .entered();
+        let segments = self.prove_segments(ctx, &session, bundle.hooks(), Some(bundle))?;
+        self.finish(ctx, &session, segments)
+    }
 
     fn prove_segment(&self, ctx: &VerifierContext, segment: &Segment) -> Result<SegmentReceipt> {
+        let _span = tracing::info_span!(
+            "prove_segment",
+            session_id = %segment.session_id,
+            index = segment.index,
+            po2 = segment.po2(),
+        )
+        .entered();
+        VersionMismatch::check(segment)?;
+        ensure!(
+            segment.inner.exit_code != ExitCode::SessionLimit,
+            "Segment {} stopped at its session limit, which cannot be proven",
+            segment.index
+        );
         let seal = self.segment_prover.prove_segment(&segment.inner)?;
 
         let mut claim = decode_receipt_claim_from_seal(&seal)?;
@@ -190,6 +302,7 @@ impl ProverServer for ProverImpl {
     }
 
     fn lift(&self, receipt: &SegmentReceipt) -> Result<SuccinctReceipt<ReceiptClaim>> {
+        let _span = tracing::info_span!("lift", index = receipt.index).entered();
         lift(receipt)
     }
 
@@ -198,6 +311,7 @@ impl ProverServer for ProverImpl {
         a: &SuccinctReceipt<ReceiptClaim>,
         b: &SuccinctReceipt<ReceiptClaim>,
     ) -> Result<SuccinctReceipt<ReceiptClaim>> {
+        let _span = tracing::info_span!("join").entered();
         join(a, b)
     }
 
@@ -206,6 +320,7 @@ impl ProverServer for ProverImpl {
         conditional: &SuccinctReceipt<ReceiptClaim>,
         assumption: &SuccinctReceipt<Unknown>,
     ) -> Result<SuccinctReceipt<ReceiptClaim>> {
+        let _span = tracing::info_span!("resolve").entered();
         resolve(conditional, assumption)
     }
 
@@ -213,6 +328,7 @@ impl ProverServer for ProverImpl {
         &self,
         a: &SuccinctReceipt<ReceiptClaim>,
     ) -> Result<SuccinctReceipt<ReceiptClaim>> {
+        let _span = tracing::info_span!("identity_p254").entered();
         identity_p254(a)
     }
 }
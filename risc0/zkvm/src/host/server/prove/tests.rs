@@ -12,19 +12,29 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::sync::Arc;
+
 use anyhow::Result;
 use risc0_binfmt::MemoryImage;
-use risc0_circuit_rv32im::prove::emu::testutil;
-use risc0_zkp::{core::digest::Digest, verify::VerificationError};
+use risc0_circuit_rv32im::{control_id::SHA256_CONTROL_IDS, prove::emu::testutil, CIRCUIT_ID};
+use risc0_zkp::{
+    core::digest::Digest,
+    verify::{seal::Reader, VerificationError},
+    QUERIES,
+};
 use risc0_zkvm_methods::{multi_test::MultiTestSpec, MULTI_TEST_ELF, MULTI_TEST_ID};
 use risc0_zkvm_platform::{memory, PAGE_SIZE, WORD_SIZE};
 use test_log::test;
 
-use super::get_prover_server;
+use super::{get_prover_server, VersionMismatch};
 use crate::{
     host::server::testutils,
+    predicate::{exit_halted, journal},
     serde::{from_slice, to_vec},
-    ExecutorEnv, ExecutorImpl, ExitCode, ProveInfo, ProverOpts, Receipt, Session, VerifierContext,
+    sha::Digestible,
+    ClaimPredicate, ExecutorEnv, ExecutorImpl, ExitCode, FileReceiptCache, InMemorySegmentRef,
+    InnerReceipt, LocalProver, ProveInfo, Prover, ProverOpts, Receipt, SegmentStreamReader,
+    SegmentStreamWriter, Session, SessionBundle, VerifierContext,
 };
 
 fn prove_session_fast(session: &Session) -> Receipt {
@@ -63,6 +73,117 @@ fn prove_nothing_succinct() {
         .unwrap(); // ensure that we got a succinct receipt.
 }
 
+#[test]
+fn prover_backend() {
+    use std::{rc::Rc, sync::Arc};
+
+    use risc0_circuit_rv32im::prove::{
+        hal::cpu::CpuCircuitHal, register_backend, segment_prover_with_hal, ProverBackend,
+        SegmentProver,
+    };
+    use risc0_zkp::{
+        core::hash::poseidon2::Poseidon2HashSuite, field::baby_bear::BabyBear, hal::cpu::CpuHal,
+    };
+
+    struct TestBackend;
+
+    impl ProverBackend for TestBackend {
+        fn segment_prover(&self, hashfn: &str) -> Result<Box<dyn SegmentProver>> {
+            anyhow::ensure!(hashfn == "poseidon2", "unsupported hashfn: {hashfn}");
+            let hal = Rc::new(CpuHal::<BabyBear>::new(Poseidon2HashSuite::new_suite()));
+            Ok(segment_prover_with_hal(hal, Rc::new(CpuCircuitHal::new())))
+        }
+    }
+
+    register_backend("test-backend", Arc::new(TestBackend));
+    let env = || {
+        ExecutorEnv::builder()
+            .write(&MultiTestSpec::DoNothing)
+            .unwrap()
+            .build()
+            .unwrap()
+    };
+
+    let opts = ProverOpts::composite().with_backend("test-backend");
+    let receipt = get_prover_server(&opts)
+        .unwrap()
+        .prove(env(), MULTI_TEST_ELF)
+        .unwrap()
+        .receipt;
+    receipt.verify(MULTI_TEST_ID).unwrap();
+
+    let opts = ProverOpts::composite().with_backend("missing-backend");
+    assert!(get_prover_server(&opts).is_err());
+}
+
+#[test]
+fn unknown_hash_suite() {
+    let mut receipt = prove_nothing("poseidon2").unwrap().receipt;
+    let InnerReceipt::Composite(ref mut composite) = receipt.inner else {
+        panic!("expected a composite receipt");
+    };
+    composite.segments[0].hashfn = "blake3".to_string();
+    assert_eq!(
+        receipt.verify(MULTI_TEST_ID).unwrap_err(),
+        VerificationError::UnknownHashSuite {
+            name: "blake3".to_string()
+        }
+    );
+}
+
+#[test]
+fn custom_hash_suite() {
+    use risc0_circuit_rv32im::prove::engine::loader::Loader;
+    use risc0_zkp::{
+        core::hash::{register_hash_suite, sha::Sha256HashSuite, HashSuite},
+        field::baby_bear::BabyBear,
+        hal::cpu::CpuHal,
+    };
+
+    fn custom_suite() -> HashSuite<BabyBear> {
+        HashSuite {
+            name: "custom-sha-256".into(),
+            ..Sha256HashSuite::new_suite()
+        }
+    }
+    register_hash_suite("custom-sha-256", custom_suite).unwrap();
+
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let po2 = session.segments[0].resolve().unwrap().po2();
+    let control_id = Loader::compute_control_id(&CpuHal::new(custom_suite()), po2);
+
+    // Registering a suite lets it be used for proving, but verifiers only accept it once it is
+    // added to their context.
+    let opts = ProverOpts::composite().with_hashfn("custom-sha-256".to_string());
+    let prover = get_prover_server(&opts).unwrap();
+    let err = prover
+        .prove_session(&VerifierContext::default(), &session)
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("custom-sha-256"), "{err:#}");
+
+    let ctx = VerifierContext::default().with_hash_suite(custom_suite(), [control_id]);
+    let receipt = prover.prove_session(&ctx, &session).unwrap().receipt;
+    assert_eq!(
+        receipt.inner.composite().unwrap().segments[0].hashfn,
+        "custom-sha-256"
+    );
+    receipt.verify_with_context(&ctx, MULTI_TEST_ID).unwrap();
+    assert_eq!(
+        receipt.verify(MULTI_TEST_ID).unwrap_err(),
+        VerificationError::UnknownHashSuite {
+            name: "custom-sha-256".to_string()
+        }
+    );
+}
+
 #[test]
 fn hashfn_poseidon2() {
     prove_nothing("poseidon2").unwrap();
@@ -83,6 +204,22 @@ fn hashfn_poseidon2() {
 //     prover.prove(env, MULTI_TEST_ELF).unwrap();
 // }
 
+#[test]
+fn seal_reader() {
+    let receipt = prove_nothing("poseidon2").unwrap().receipt;
+    let segment = &receipt.inner.composite().unwrap().segments[0];
+    let reader = segment.seal_reader().unwrap();
+    assert_eq!(reader.po2(), segment.po2().unwrap());
+    assert_eq!(reader.queries().len(), QUERIES);
+    assert_eq!(reader.code().rows, reader.data().rows);
+    assert!(reader.queries().iter().all(|query| {
+        query.fri.len() == reader.fri_layers().len() && query.check.row.len() == reader.check().cols
+    }));
+
+    let truncated = &segment.seal[..segment.seal.len() - 1];
+    assert!(Reader::new(&risc0_circuit_rv32im::CIRCUIT, truncated).is_err());
+}
+
 #[test]
 fn receipt_serde() {
     let receipt = prove_nothing("sha-256").unwrap().receipt;
@@ -105,6 +242,21 @@ fn check_image_id() {
     ));
 }
 
+#[test]
+fn verify_with_predicate() {
+    let receipt = prove_nothing("sha-256").unwrap().receipt;
+    let policy = ClaimPredicate::image(MULTI_TEST_ID).and(exit_halted(0));
+    receipt.verify_with_predicate(&policy).unwrap();
+
+    let policy = policy.and(journal(b"v1"));
+    assert_eq!(
+        receipt.verify_with_predicate(&policy).unwrap_err(),
+        VerificationError::ClaimPredicateFailed {
+            predicate: "journal(0x7631)".to_string()
+        }
+    );
+}
+
 #[test]
 fn sha_basics() {
     fn run_sha(msg: &str) -> String {
@@ -1031,3 +1183,288 @@ mod soundness {
         assert_eq!(security, 98.32892);
     }
 }
+
+#[test]
+fn circuit_id_mismatch() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let mut segment = session.segments[0].resolve().unwrap();
+    assert_eq!(segment.circuit_id(), CIRCUIT_ID);
+
+    let other = SHA256_CONTROL_IDS[0];
+    segment.inner.circuit_id = other;
+    let prover = get_prover_server(&ProverOpts::fast()).unwrap();
+    let err = prover
+        .prove_segment(&VerifierContext::default(), &segment)
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<VersionMismatch>(),
+        Some(&VersionMismatch {
+            segment: other,
+            prover: CIRCUIT_ID,
+        })
+    );
+}
+
+#[test]
+fn in_memory_segments() {
+    let program = testutil::simple_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let env = ExecutorEnv::builder()
+        .segment_limit_po2(14) // 16k cycles
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::new(env, image)
+        .unwrap()
+        .run_with_callback(|segment| Ok(Box::new(InMemorySegmentRef::new(segment))))
+        .unwrap();
+    assert_eq!(session.segments.len(), 2);
+
+    // The segment is shared rather than copied.
+    let segment = session.segments[0].resolve_shared().unwrap();
+    assert!(Arc::ptr_eq(
+        &segment,
+        &session.segments[0].resolve_shared().unwrap()
+    ));
+    assert_eq!(session.segments[0].stats(), Some(segment.stats()));
+
+    let receipt = prove_session_fast(&session);
+    receipt.verify_integrity().unwrap();
+    assert_eq!(
+        receipt.claim().unwrap().digest(),
+        session.claim().unwrap().digest()
+    );
+
+    // The prover keeps the segments in memory when the environment asks for it.
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .segments_in_memory(true)
+        .build()
+        .unwrap();
+    let prover = get_prover_server(&ProverOpts::fast()).unwrap();
+    let receipt = prover.prove(env, MULTI_TEST_ELF).unwrap().receipt;
+    receipt.verify(MULTI_TEST_ID).unwrap();
+}
+
+#[test]
+fn resume_proving() {
+    let program = testutil::simple_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let env = ExecutorEnv::builder()
+        .segment_limit_po2(14) // 16k cycles
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::new(env, image).unwrap().run().unwrap();
+    assert_eq!(session.segments.len(), 2);
+
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = SessionBundle::create(dir.path(), &session).unwrap();
+    let ctx = VerifierContext::default();
+    let prover = get_prover_server(&ProverOpts::fast()).unwrap();
+
+    // Prove only the first segment, as if the prover died before the second.
+    let segment = bundle.session().unwrap().segments[0].resolve().unwrap();
+    let first = prover.prove_segment(&ctx, &segment).unwrap();
+    bundle.save_segment_receipt(0, &first).unwrap();
+    assert_eq!(bundle.proven_segments(), vec![0]);
+
+    let bundle = SessionBundle::open(dir.path()).unwrap();
+    let receipt = prover.resume_proving(&ctx, &bundle).unwrap().receipt;
+    receipt.verify_integrity_with_context(&ctx).unwrap();
+    assert_eq!(
+        receipt.claim().unwrap().digest(),
+        session.claim().unwrap().digest()
+    );
+    assert_eq!(bundle.proven_segments(), vec![0, 1]);
+
+    // The saved receipt was reused rather than proven again.
+    let segments = &receipt.inner.composite().unwrap().segments;
+    assert_eq!(segments[0].seal, first.seal);
+}
+
+#[test]
+fn resume_proving_checks_saved_receipts() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::{Segment, SessionEvents};
+
+    struct Counter(Arc<AtomicUsize>);
+
+    impl SessionEvents for Counter {
+        fn on_post_prove_segment(&self, _: &Segment) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let ctx = VerifierContext::default();
+    let prover = get_prover_server(&ProverOpts::fast()).unwrap();
+    let run = |env: ExecutorEnv| ExecutorImpl::from_elf(env, MULTI_TEST_ELF)?.run();
+
+    // A valid receipt of the first segment of another session.
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let other = run(env).unwrap().segments[0].resolve().unwrap();
+    let foreign = prover.prove_segment(&ctx, &other).unwrap();
+
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles: 1 << 15 })
+        .unwrap()
+        .segment_limit_po2(14)
+        .build()
+        .unwrap();
+    let session = run(env).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let mut bundle = SessionBundle::create(dir.path(), &session).unwrap();
+    bundle.save_segment_receipt(0, &foreign).unwrap();
+    let proven = Arc::new(AtomicUsize::new(0));
+    bundle.add_hook(Counter(proven.clone()));
+
+    // The foreign receipt is proven again, with the hooks called for every segment.
+    let receipt = prover.resume_proving(&ctx, &bundle).unwrap().receipt;
+    assert_eq!(
+        receipt.claim().unwrap().digest(),
+        session.claim().unwrap().digest()
+    );
+    let segments = &receipt.inner.composite().unwrap().segments;
+    assert_ne!(segments[0].seal, foreign.seal);
+    assert_eq!(proven.load(Ordering::Relaxed), session.segments.len());
+
+    // A session stopped at its session limit cannot be resumed either.
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles: 1 << 20 })
+        .unwrap()
+        .segment_limit_po2(14)
+        .session_limit(Some(1 << 15))
+        .build()
+        .unwrap();
+    let session = run(env).unwrap();
+    assert_eq!(session.exit_code, ExitCode::SessionLimit);
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = SessionBundle::create(dir.path(), &session).unwrap();
+    let err = prover.resume_proving(&ctx, &bundle).unwrap_err();
+    assert!(err.to_string().contains("session limit"), "{err}");
+
+    // Nor can its last segment be proven on its own.
+    let last = session.segments.last().unwrap().resolve().unwrap();
+    let err = prover.prove_segment(&ctx, &last).unwrap_err();
+    assert!(err.to_string().contains("session limit"), "{err}");
+}
+
+#[test]
+fn receipt_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let prover = LocalProver::new("local").with_cache(FileReceiptCache::new(dir.path()).unwrap());
+    let env = || {
+        ExecutorEnv::builder()
+            .write(&MultiTestSpec::DoNothing)
+            .unwrap()
+            .build()
+            .unwrap()
+    };
+    let opts = ProverOpts::fast();
+
+    let first = prover
+        .prove_with_opts(env(), MULTI_TEST_ELF, &opts)
+        .unwrap()
+        .receipt;
+    first.verify(MULTI_TEST_ID).unwrap();
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    // The second request is served from the cache, and the cached receipt still verifies.
+    let second = prover
+        .prove_with_opts(env(), MULTI_TEST_ELF, &opts)
+        .unwrap()
+        .receipt;
+    second.verify(MULTI_TEST_ID).unwrap();
+    assert_eq!(second, first);
+
+    // Requests with a host-side writer are not cached, since the writer must observe the guest.
+    let mut out = Vec::new();
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .write_fd(3, &mut out)
+        .build()
+        .unwrap();
+    prover
+        .prove_with_opts(env, MULTI_TEST_ELF, &opts)
+        .unwrap()
+        .receipt
+        .verify(MULTI_TEST_ID)
+        .unwrap();
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn segment_stream() {
+    let program = testutil::simple_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let env = ExecutorEnv::builder()
+        .segment_limit_po2(14) // 16k cycles
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::new(env, image).unwrap().run().unwrap();
+    let ctx = VerifierContext::default();
+    let prover = get_prover_server(&ProverOpts::fast()).unwrap();
+    let proven: Vec<_> = session
+        .segments
+        .iter()
+        .map(|segment_ref| {
+            let segment = segment_ref.resolve().unwrap();
+            let receipt = prover.prove_segment(&ctx, &segment).unwrap();
+            (segment, receipt)
+        })
+        .collect();
+    assert_eq!(proven.len(), 2);
+
+    let write = |pairs: &[usize]| {
+        let mut writer = SegmentStreamWriter::new(Vec::new()).unwrap();
+        for &idx in pairs {
+            let (segment, receipt) = &proven[idx];
+            writer.write(segment, receipt).unwrap();
+        }
+        writer.finish().unwrap()
+    };
+    let image_id = session.pre_state.digest();
+    let read_from = |bytes: &[u8], image_id: Digest| -> Result<Vec<u32>> {
+        SegmentStreamReader::new(bytes, &ctx, image_id)?
+            .map(|item| item.map(|(segment, _)| segment.index))
+            .collect()
+    };
+    let read = |bytes: &[u8]| read_from(bytes, image_id);
+
+    let bytes = write(&[0, 1]);
+    assert_eq!(read(&bytes).unwrap(), vec![0, 1]);
+
+    // A truncated stream, one missing the final segment, and one out of order are all rejected.
+    assert!(read(&bytes[..bytes.len() - 1]).is_err());
+    assert!(read(&write(&[0])).is_err());
+    assert!(read(&write(&[1, 0])).is_err());
+
+    // So is a stream of another program.
+    let err = read_from(&bytes, Digest::ZERO).unwrap_err();
+    assert!(err.to_string().contains("image ID"), "{err}");
+
+    // A frame length prefix is not trusted: a huge one is rejected, and one longer than the data
+    // fails when the data runs out.
+    let header = &bytes[..12];
+    for (len, expected) in [(u64::MAX, "too large"), (1 << 27, "part way")] {
+        let mut stream = header.to_vec();
+        stream.push(1);
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&[0; 16]);
+        let err = read(&stream).unwrap_err();
+        assert!(err.to_string().contains(expected), "{err}");
+    }
+}
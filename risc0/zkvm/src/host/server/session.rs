@@ -15,18 +15,31 @@
 //! This module defines [Session] and [Segment] which provides a way to share
 //! execution traces between the execution phase and the proving phase.
 
-use std::{collections::BTreeSet, fs, path::PathBuf};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    ops::Range,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 
-use anyhow::{ensure, Result};
-use risc0_binfmt::{MemoryImage, SystemState};
+use anyhow::{bail, ensure, Context as _, Result};
+use risc0_binfmt::{MemoryImage, MemoryProof, SystemState};
 use risc0_circuit_rv32im::prove::segment::Segment as CircuitSegment;
+use risc0_zkvm_platform::{memory::MEMORY_OUTPUT, WORD_SIZE};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    host::{client::env::SegmentPath, prove_info::SessionStats},
-    sha::Digest,
-    Assumption, AssumptionReceipt, Assumptions, ExitCode, Journal, MaybePruned, Output,
-    ReceiptClaim,
+    host::{
+        client::{audit::NondeterminismReport, env::SegmentPath, transcript::SyscallTranscript},
+        prove_info::{AcceleratorStats, FdUsage, SegmentStats, SessionStats},
+    },
+    redaction::RedactableJournal,
+    sha::{Digest, Digestible},
+    Assumption, AssumptionReceipt, Assumptions, ExecutorEnv, ExecutorImpl, ExecutorState, ExitCode,
+    Journal, MaybePruned, Output, PerfWarning, ReceiptClaim, RunawayReport, SyscallManifest,
+    TaintedCommit,
 };
 
 #[derive(Clone, Default, Serialize, Deserialize, Debug)]
@@ -43,10 +56,18 @@ pub struct PageFaults {
 /// stored as a vector of [Segment]s.
 #[non_exhaustive]
 pub struct Session {
+    /// The ID of the session, recorded as the `session_id` field of the `tracing` spans emitted
+    /// while executing and proving it. See
+    /// [ExecutorEnvBuilder::session_id][crate::ExecutorEnvBuilder::session_id].
+    pub id: String,
+
     /// The constituent [Segment]s of the Session. The final [Segment] will have
     /// an [ExitCode] of [Halted](ExitCode::Halted), [Paused](ExitCode::Paused),
     /// or [SessionLimit](ExitCode::SessionLimit), and all other [Segment]s (if
-    /// any) will have [ExitCode::SystemSplit].
+    /// any) will have [ExitCode::SystemSplit]. A session stopped at its
+    /// [wall-clock deadline][crate::ExecutorEnvBuilder::wall_clock_deadline] or
+    /// [pause cycle][crate::ExecutorEnvBuilder::pause_at_cycle] ends with a
+    /// [Segment] that has [ExitCode::SystemSplit].
     pub segments: Vec<Box<dyn SegmentRef>>,
 
     /// The input digest.
@@ -80,6 +101,136 @@ pub struct Session {
 
     /// The system state of the final [MemoryImage] at the end of execution.
     pub post_state: SystemState,
+
+    /// The number of bytes written to stdout, if it was sent to [Sink::Counting][crate::Sink].
+    pub stdout_bytes: Option<u64>,
+
+    /// The number of bytes written to stderr, if it was sent to [Sink::Counting][crate::Sink].
+    pub stderr_bytes: Option<u64>,
+
+    /// The data transferred through each file descriptor with a quota, set with
+    /// [ExecutorEnvBuilder::fd_quota][crate::ExecutorEnvBuilder::fd_quota].
+    pub fd_usage: BTreeMap<u32, FdUsage>,
+
+    /// The number of cycles spent paging memory in and out, if known.
+    pub paging_cycles: Option<u64>,
+
+    /// The number of paging cycles saved by sharing page table pages between the pages accessed
+    /// in each segment, compared to paging the whole path to the root of each page on its own,
+    /// if known.
+    pub saved_paging_cycles: Option<u64>,
+
+    /// The number of times each kind of instruction was executed, by mnemonic, if counted with
+    /// [ExecutorEnvBuilder::insn_stats][crate::ExecutorEnvBuilder::insn_stats].
+    pub insn_counts: Option<BTreeMap<String, u64>>,
+
+    /// The fields committed by the guest with
+    /// [env::commit_field][crate::guest::env::commit_field], whose root is committed to the
+    /// journal when the guest halts.
+    pub committed_fields: RedactableJournal,
+
+    /// The syscalls the guest was allowed to make, if restricted with
+    /// [ExecutorEnvBuilder::syscall_manifest][crate::ExecutorEnvBuilder::syscall_manifest], or
+    /// if its ELF declares a manifest with [syscall_manifest!][crate::syscall_manifest].
+    pub syscall_manifest: Option<SyscallManifest>,
+
+    /// The patterns in the guest known to waste cycles, if looked for with
+    /// [ExecutorEnvBuilder::perf_warnings][crate::ExecutorEnvBuilder::perf_warnings].
+    pub perf_warnings: Vec<PerfWarning>,
+
+    /// The writes to the journal of data from the host that was not verified, if looked for
+    /// with [ExecutorEnvBuilder::taint_tracking][crate::ExecutorEnvBuilder::taint_tracking].
+    pub tainted_commits: Vec<TaintedCommit>,
+
+    pub(crate) syscall_transcript: Option<SyscallTranscript>,
+
+    pub(crate) nondeterminism_report: Option<NondeterminismReport>,
+
+    pub(crate) events: Vec<ExecutionEvent>,
+
+    pub(crate) accelerators: BTreeMap<String, AcceleratorStats>,
+
+    // The state of the executor when the session paused, for resume_with_syscalls.
+    pub(crate) executor_state: Option<ExecutorState>,
+}
+
+/// An event in the execution of a guest, recorded in [Session::events].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionEvent {
+    /// The number of user cycles executed before the event, counted from the start of the first
+    /// run of the executor, across pauses.
+    pub cycle: u64,
+
+    /// What happened.
+    pub kind: ExecutionEventKind,
+}
+
+/// The kinds of [ExecutionEvent].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum ExecutionEventKind {
+    /// A segment ended with `exit_code`, which is [ExitCode::SystemSplit] unless it was the last
+    /// segment of its run, e.g. [ExitCode::Paused] or [ExitCode::Halted].
+    Segment {
+        /// The index of the segment within its session.
+        index: u32,
+        /// The po2 of the segment.
+        po2: u32,
+        /// The exit code of the segment.
+        exit_code: ExitCode,
+    },
+
+    /// Execution resumed after a pause.
+    Resumed,
+
+    /// Execution stopped at the session limit.
+    SessionLimit,
+
+    /// Execution stopped at a breakpoint added with
+    /// [ExecutorImpl::add_breakpoint][crate::ExecutorImpl::add_breakpoint].
+    Breakpoint {
+        /// The address of the breakpoint.
+        pc: u32,
+    },
+
+    /// A syscall failed, which stops execution.
+    SyscallError {
+        /// The name of the syscall.
+        name: String,
+        /// The error returned by its handler.
+        error: String,
+    },
+
+    /// A [runaway heuristic][crate::ExecutorEnvBuilder::runaway_heuristics] triggered.
+    Runaway {
+        /// The state of the guest when the heuristic triggered.
+        report: RunawayReport,
+    },
+
+    /// Execution failed.
+    Failed {
+        /// The error that stopped execution.
+        error: String,
+    },
+}
+
+/// The progress of a run of the executor, passed to the callback set with
+/// [ExecutorEnvBuilder::progress_callback][crate::ExecutorEnvBuilder::progress_callback].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ExecutionProgress {
+    /// The user cycles executed so far, including by the earlier runs of a resumed session.
+    pub user_cycles: u64,
+
+    /// The total cycles of the segments ended so far in this run, including the overhead of continuations
+    /// and po2 padding.
+    pub total_cycles: u64,
+
+    /// The number of segments ended so far in this run.
+    pub segments: u32,
+
+    /// The wall time since the run started.
+    pub elapsed: Duration,
 }
 
 /// The execution trace of a portion of a program.
@@ -95,6 +246,10 @@ pub struct Segment {
     /// The index of this [Segment] within the [Session]
     pub index: u32,
 
+    /// The [ID][Session::id] of the [Session] this [Segment] belongs to.
+    #[serde(default)]
+    pub session_id: String,
+
     pub(crate) inner: CircuitSegment,
     pub(crate) output: Option<Output>,
 }
@@ -106,6 +261,50 @@ impl Segment {
     pub fn po2(&self) -> usize {
         self.inner.po2
     }
+
+    /// The identity of the circuit that this [Segment] was executed with: the Poseidon2 control
+    /// ID of its smallest segment size.
+    ///
+    /// A [ProverServer][crate::ProverServer] fails with a
+    /// [VersionMismatch][crate::VersionMismatch] when asked to prove a segment executed with a
+    /// different circuit.
+    pub fn circuit_id(&self) -> Digest {
+        self.inner.circuit_id
+    }
+
+    /// The cycle counts and paging of this [Segment].
+    pub fn stats(&self) -> SegmentStats {
+        let paging = &self.inner.paging;
+        SegmentStats {
+            index: self.index,
+            po2: self.inner.po2,
+            user_cycles: self.inner.insn_cycles as u64,
+            page_reads: paging.page_reads,
+            page_writes: paging.page_writes,
+            page_read_cycles: paging.read_cycles as u64,
+            page_write_cycles: paging.write_cycles as u64,
+        }
+    }
+
+    /// Produce a [MemoryProof] of the given range of guest memory at the start of this segment.
+    ///
+    /// The proof is checked against the image ID of the segment's pre-state. Only memory that is
+    /// paged in by this segment can be proven. To prove memory at the end of a segment, use the
+    /// next segment, or [Session::prove_memory] at the end of a session.
+    pub fn prove_memory(&self, range: Range<u32>) -> Result<MemoryProof> {
+        let proof = self.inner.partial_image.prove_memory(range.clone())?;
+        proof
+            .verify(&self.inner.pre_state.digest())
+            .with_context(|| format!("Memory range {range:x?} is not paged in by this segment"))?;
+        Ok(proof)
+    }
+}
+
+/// Generate a random ID for a [Session].
+pub(crate) fn new_session_id() -> String {
+    let mut id = [0u8; 16];
+    getrandom::getrandom(&mut id).expect("Failed to generate a session ID");
+    hex::encode(id)
 }
 
 /// A reference to a [Segment].
@@ -116,6 +315,19 @@ impl Segment {
 pub trait SegmentRef: Send {
     /// Resolve this reference into an actual [Segment].
     fn resolve(&self) -> Result<Segment>;
+
+    /// Resolve this reference into a shared [Segment].
+    ///
+    /// References that already hold the segment in memory, such as [InMemorySegmentRef], return
+    /// it without copying it.
+    fn resolve_shared(&self) -> Result<Arc<Segment>> {
+        self.resolve().map(Arc::new)
+    }
+
+    /// The [SegmentStats] of the referenced [Segment], if known without resolving it.
+    fn stats(&self) -> Option<SegmentStats> {
+        None
+    }
 }
 
 /// The Events of [Session]
@@ -145,6 +357,7 @@ impl Session {
         post_state: SystemState,
     ) -> Self {
         Self {
+            id: new_session_id(),
             segments,
             input,
             journal: journal.map(Journal::new),
@@ -156,9 +369,72 @@ impl Session {
             total_cycles,
             pre_state,
             post_state,
+            stdout_bytes: None,
+            stderr_bytes: None,
+            fd_usage: BTreeMap::new(),
+            paging_cycles: None,
+            saved_paging_cycles: None,
+            insn_counts: None,
+            committed_fields: RedactableJournal::default(),
+            syscall_manifest: None,
+            perf_warnings: Vec::new(),
+            tainted_commits: Vec::new(),
+            syscall_transcript: None,
+            nondeterminism_report: None,
+            events: Vec::new(),
+            accelerators: BTreeMap::new(),
+            executor_state: None,
         }
     }
 
+    /// The events of the execution of this session, in order, including the runs that came
+    /// before it if it was resumed from a pause.
+    ///
+    /// Errors that stop execution do not produce a session, and are only recorded in
+    /// [ExecutorImpl::events][crate::ExecutorImpl::events].
+    pub fn events(&self) -> &[ExecutionEvent] {
+        &self.events
+    }
+
+    /// The use of each accelerator by the guest, such as `sha` and `bigint`, by name.
+    ///
+    /// Accelerators the guest never invoked are absent, which can reveal a patched crate that is
+    /// not routing through its accelerator.
+    pub fn accelerator_stats(&self) -> &BTreeMap<String, AcceleratorStats> {
+        &self.accelerators
+    }
+
+    /// The syscalls answered by the host during this session, if recorded with
+    /// [ExecutorEnvBuilder::record_syscall_transcript][crate::ExecutorEnvBuilder::record_syscall_transcript].
+    pub fn syscall_transcript(&self) -> Option<&SyscallTranscript> {
+        self.syscall_transcript.as_ref()
+    }
+
+    /// The sources of nondeterminism consumed by the guest during this session, if audited with
+    /// [ExecutorEnvBuilder::audit_nondeterminism][crate::ExecutorEnvBuilder::audit_nondeterminism].
+    pub fn nondeterminism_report(&self) -> Option<&NondeterminismReport> {
+        self.nondeterminism_report.as_ref()
+    }
+
+    /// The code passed by the guest to [env::pause][crate::guest::env::pause], if this session
+    /// ended in a pause.
+    ///
+    /// The code is part of the [ExitCode] committed to by the [ReceiptClaim], so a verifier of
+    /// the receipt for this session can also rely on it. Guests can use distinct codes to tell
+    /// the host what they are pausing for, e.g. which kind of request the host should answer
+    /// before resuming.
+    pub fn pause_code(&self) -> Option<u32> {
+        self.exit_code.pause_code()
+    }
+
+    /// The [pause code][Session::pause_code] converted to a host-defined type, e.g. an enum of
+    /// the requests that the guest can make by pausing.
+    ///
+    /// Returns `None` if the session did not end in a pause.
+    pub fn pause_reason<T: TryFrom<u32>>(&self) -> Option<Result<T, T::Error>> {
+        self.pause_code().map(T::try_from)
+    }
+
     /// Add a hook to be called during the proving phase.
     pub fn add_hook<E: SessionEvents + 'static>(&mut self, hook: E) {
         self.hooks.push(Box::new(hook));
@@ -168,6 +444,10 @@ impl Session {
     /// [ReceiptClaim] is the claim that will be proven if this [Session]
     /// is passed to the [crate::Prover].
     pub fn claim(&self) -> Result<ReceiptClaim> {
+        ensure!(
+            self.exit_code != ExitCode::SessionLimit,
+            "Session stopped at its session limit, which cannot be proven"
+        );
         // Construct the Output struct for the session, checking internal consistency.
         // NOTE: The Session output is distinct from the final Segment output because in the
         // Session output any proven assumptions are not included.
@@ -213,6 +493,79 @@ impl Session {
         })
     }
 
+    /// Resume a paused [Session], using the host-side syscall handlers configured in `env`.
+    ///
+    /// Execution continues from the state of the executor when the session paused, as captured by
+    /// [ExecutorImpl::snapshot], so that interactive sessions can swap out their handlers without
+    /// restarting the whole execution. The input digest of this session and the assumptions the
+    /// guest used are carried over to `env`. The file descriptors and named streams of the guest
+    /// are taken from `env`, as they are when resuming with new handlers; use
+    /// [ExecutorImpl::update_syscalls] instead to keep all of the state of an existing executor.
+    pub fn resume_with_syscalls(&self, mut env: ExecutorEnv<'_>) -> Result<Session> {
+        let state = match (&self.exit_code, &self.executor_state) {
+            (ExitCode::Paused(_), Some(state)) => state,
+            (ExitCode::Paused(_), None) => bail!("Session does not hold the state of its executor"),
+            (exit_code, _) => bail!("Session with exit code {exit_code:?} cannot be resumed"),
+        };
+        match env.input_digest {
+            Some(digest) => ensure!(
+                digest == self.input,
+                "Input digest {digest} does not match the input digest {} of the session",
+                self.input
+            ),
+            None if self.input != Digest::ZERO => env.input_digest = Some(self.input),
+            None => (),
+        }
+        env.assumptions
+            .borrow_mut()
+            .cached
+            .extend(self.assumptions.iter().map(|(_, receipt)| receipt.clone()));
+
+        let mut exec = ExecutorImpl::new(env, state.image.clone())?;
+        exec.restore(state.clone())?;
+        exec.run()
+    }
+
+    /// Produce a [MemoryProof] of the given range of guest memory at the end of this session.
+    ///
+    /// The proof is checked against the image ID of the [post_image][Session::post_image], which
+    /// matches the post-state of a paused session.
+    pub fn prove_memory(&self, range: Range<u32>) -> Result<MemoryProof> {
+        self.post_image.prove_memory(range)
+    }
+
+    /// Read `len` bytes of guest memory at `addr` at the end of this session.
+    ///
+    /// The memory is checked with a [MemoryProof] against the post-state in the
+    /// [claim][Session::claim] of the session. A halted session does not commit to its memory, so
+    /// its memory cannot be read.
+    pub fn read_post_memory(&self, addr: u32, len: u32) -> Result<Vec<u8>> {
+        ensure!(
+            !matches!(self.exit_code, ExitCode::Halted(_)),
+            "Session with exit code {:?} does not commit to its post-state memory",
+            self.exit_code
+        );
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        let end = addr
+            .checked_add(len)
+            .with_context(|| format!("Memory range at 0x{addr:08x} overflows"))?;
+        let proof = self.prove_memory(addr..end)?;
+        proof.verify(&self.post_state.digest())?;
+        proof.read(addr..end)
+    }
+
+    /// Read the output left in guest memory by
+    /// [env::pause_with_memory_output][crate::guest::env::pause_with_memory_output].
+    pub fn memory_output(&self) -> Result<Vec<u8>> {
+        let descriptor = self.read_post_memory(MEMORY_OUTPUT, 2 * WORD_SIZE as u32)?;
+        let addr = u32::from_le_bytes(descriptor[..WORD_SIZE].try_into().unwrap());
+        let len = u32::from_le_bytes(descriptor[WORD_SIZE..].try_into().unwrap());
+        self.read_post_memory(addr, len)
+            .context("Failed to read the memory output of the guest")
+    }
+
     /// Log cycle information for this [Session].
     ///
     /// This logs the total and user cycles for this [Session] at the INFO level.
@@ -233,6 +586,12 @@ impl Session {
             segments: self.segments.len(),
             total_cycles: self.total_cycles,
             user_cycles: self.user_cycles,
+            stdout_bytes: self.stdout_bytes,
+            stderr_bytes: self.stderr_bytes,
+            fd_usage: self.fd_usage.clone(),
+            paging_cycles: self.paging_cycles,
+            saved_paging_cycles: self.saved_paging_cycles,
+            insn_counts: self.insn_counts.clone(),
         }
     }
 }
@@ -265,6 +624,10 @@ impl SegmentRef for SimpleSegmentRef {
     fn resolve(&self) -> Result<Segment> {
         Ok(self.segment.clone())
     }
+
+    fn stats(&self) -> Option<SegmentStats> {
+        Some(self.segment.stats())
+    }
 }
 
 impl SimpleSegmentRef {
@@ -274,6 +637,46 @@ impl SimpleSegmentRef {
     }
 }
 
+/// A [SegmentRef] that shares the [Segment] in memory, for handing segments to a prover in the
+/// same process without copying or serializing them.
+///
+/// Unlike a [SimpleSegmentRef], [resolve_shared][SegmentRef::resolve_shared] returns the segment
+/// itself, so the prover reads the segment the executor produced. Every segment of the
+/// [Session] is kept in memory until the session is dropped, so a [FileSegmentRef] is better
+/// suited to long sessions.
+#[derive(Clone)]
+pub struct InMemorySegmentRef {
+    segment: Arc<Segment>,
+}
+
+impl SegmentRef for InMemorySegmentRef {
+    fn resolve(&self) -> Result<Segment> {
+        Ok(self.segment.as_ref().clone())
+    }
+
+    fn resolve_shared(&self) -> Result<Arc<Segment>> {
+        Ok(self.segment.clone())
+    }
+
+    fn stats(&self) -> Option<SegmentStats> {
+        Some(self.segment.stats())
+    }
+}
+
+impl InMemorySegmentRef {
+    /// Construct an [InMemorySegmentRef] with the specified [Segment].
+    pub fn new(segment: impl Into<Arc<Segment>>) -> Self {
+        Self {
+            segment: segment.into(),
+        }
+    }
+
+    /// The referenced [Segment].
+    pub fn segment(&self) -> &Arc<Segment> {
+        &self.segment
+    }
+}
+
 /// A basic implementation of a [SegmentRef] that saves the segment to a file
 ///
 /// The [Segment] is stored in a user-specified file in this implementation,
@@ -284,6 +687,7 @@ impl SimpleSegmentRef {
 /// [1]: https://github.com/risc0/risc0/blob/main/examples/zkevm-demo/src/main.rs
 pub struct FileSegmentRef {
     path: PathBuf,
+    stats: SegmentStats,
     _dir: SegmentPath,
 }
 
@@ -293,6 +697,10 @@ impl SegmentRef for FileSegmentRef {
         let segment = bincode::deserialize(&contents)?;
         Ok(segment)
     }
+
+    fn stats(&self) -> Option<SegmentStats> {
+        Some(self.stats)
+    }
 }
 
 impl FileSegmentRef {
@@ -304,6 +712,7 @@ impl FileSegmentRef {
         fs::write(&path, bincode::serialize(&segment)?)?;
         Ok(Self {
             path,
+            stats: segment.stats(),
             _dir: dir.clone(),
         })
     }
@@ -0,0 +1,269 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A stream of segments, each followed by its receipt, for sending proven segments between
+//! machines, e.g. from a prover to an aggregator.
+//!
+//! The stream starts with a header, followed by frames of a one byte tag, a little-endian `u64`
+//! length, and a bincode payload. Each segment frame is followed by the frame of its receipt, and
+//! the stream is closed by an end frame, so a truncated stream is detected.
+
+use std::io::{Read, Write};
+
+use anyhow::{bail, ensure, Context as _, Result};
+use risc0_binfmt::ExitCode;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    sha::{Digest, Digestible},
+    Segment, SegmentReceipt, VerifierContext,
+};
+
+const MAGIC: &[u8; 8] = b"R0SEGSTM";
+const VERSION: u32 = 1;
+
+const TAG_END: u8 = 0;
+const TAG_SEGMENT: u8 = 1;
+const TAG_RECEIPT: u8 = 2;
+
+/// Frames larger than this are rejected. This is far larger than any segment or receipt, whose
+/// size is bounded by the pages that a segment can page in.
+const MAX_FRAME_LEN: u64 = 1 << 28;
+
+/// Writes segments and their receipts to a stream read by a [SegmentStreamReader].
+pub struct SegmentStreamWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> SegmentStreamWriter<W> {
+    /// Start a stream by writing its header to `writer`.
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        Ok(Self { writer })
+    }
+
+    /// Write a segment, followed by its receipt.
+    pub fn write(&mut self, segment: &Segment, receipt: &SegmentReceipt) -> Result<()> {
+        ensure!(
+            segment.index == receipt.index,
+            "receipt of segment {} written with segment {}",
+            receipt.index,
+            segment.index
+        );
+        ensure!(
+            segment.inner.exit_code != ExitCode::SessionLimit,
+            "Segment {} stopped at its session limit, which cannot be proven",
+            segment.index
+        );
+        self.write_frame(TAG_SEGMENT, segment)?;
+        self.write_frame(TAG_RECEIPT, receipt)?;
+        // Flush each pair, so the reader can check it while the next is being proven.
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Close the stream, and return the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.write_all(&[TAG_END])?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_frame(&mut self, tag: u8, payload: &impl Serialize) -> Result<()> {
+        let payload = bincode::serialize(payload)?;
+        self.writer.write_all(&[tag])?;
+        self.writer
+            .write_all(&(payload.len() as u64).to_le_bytes())?;
+        self.writer.write_all(&payload)?;
+        Ok(())
+    }
+}
+
+/// Reads segments and their receipts written by a [SegmentStreamWriter], verifying each receipt
+/// and its chaining to the previous one as it arrives.
+///
+/// A segment is only returned once its receipt has been verified, so a consumer can act on it
+/// before the rest of the stream has arrived. Reading fails if the stream is truncated, or if the
+/// segments do not form a single continuation from the expected image ID ending in a terminal
+/// exit code.
+pub struct SegmentStreamReader<'a, R: Read> {
+    reader: R,
+    ctx: &'a VerifierContext,
+    next_index: u32,
+    // The digest of the state that the next receipt must start from: the image ID, and then the
+    // post state of the last receipt.
+    expected_pre_state: Digest,
+    terminated: bool,
+    ended: bool,
+}
+
+impl<'a, R: Read> SegmentStreamReader<'a, R> {
+    /// Start reading a stream from `reader`, verifying receipts against `ctx`, of a session that
+    /// starts from the program with the given image ID.
+    pub fn new(
+        mut reader: R,
+        ctx: &'a VerifierContext,
+        image_id: impl Into<Digest>,
+    ) -> Result<Self> {
+        let mut magic = [0u8; 8];
+        reader
+            .read_exact(&mut magic)
+            .context("failed to read the segment stream header")?;
+        ensure!(&magic == MAGIC, "not a segment stream");
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        ensure!(
+            version == VERSION,
+            "unsupported segment stream version: {version}"
+        );
+        Ok(Self {
+            reader,
+            ctx,
+            next_index: 0,
+            expected_pre_state: image_id.into(),
+            terminated: false,
+            ended: false,
+        })
+    }
+
+    /// Read the next segment and its verified receipt, or `None` once the stream is closed.
+    pub fn next_segment(&mut self) -> Result<Option<(Segment, SegmentReceipt)>> {
+        if self.ended {
+            return Ok(None);
+        }
+        match self.read_tag()? {
+            TAG_END => {
+                ensure!(
+                    self.terminated,
+                    "segment stream closed before the final segment"
+                );
+                self.ended = true;
+                return Ok(None);
+            }
+            TAG_SEGMENT => (),
+            tag => bail!("expected a segment frame, found tag {tag}"),
+        }
+        let segment: Segment = self.read_payload()?;
+        match self.read_tag()? {
+            TAG_RECEIPT => (),
+            tag => bail!(
+                "expected the receipt of segment {}, found tag {tag}",
+                segment.index
+            ),
+        }
+        let receipt: SegmentReceipt = self.read_payload()?;
+        self.check(&segment, &receipt)?;
+        Ok(Some((segment, receipt)))
+    }
+
+    fn check(&mut self, segment: &Segment, receipt: &SegmentReceipt) -> Result<()> {
+        let index = self.next_index;
+        ensure!(
+            !self.terminated,
+            "segment {index} follows the final segment"
+        );
+        ensure!(
+            segment.index == index && receipt.index == index,
+            "expected segment {index}, found segment {} with the receipt of segment {}",
+            segment.index,
+            receipt.index
+        );
+        receipt
+            .verify_integrity_with_context(self.ctx)
+            .with_context(|| format!("receipt of segment {index} is invalid"))?;
+
+        let claim = &receipt.claim;
+        let pre_state = claim.pre.digest();
+        ensure!(
+            pre_state == segment.inner.pre_state.digest(),
+            "receipt of segment {index} does not start from the segment's state"
+        );
+        ensure!(
+            claim.exit_code == segment.inner.exit_code,
+            "receipt of segment {index} exits with {:?}, but the segment exits with {:?}",
+            claim.exit_code,
+            segment.inner.exit_code
+        );
+        if index == 0 {
+            ensure!(
+                pre_state == self.expected_pre_state,
+                "segment 0 does not start from image ID {}",
+                self.expected_pre_state
+            );
+        } else {
+            ensure!(
+                pre_state == self.expected_pre_state,
+                "segment {index} does not start where segment {} ended",
+                index - 1
+            );
+        }
+
+        if claim.exit_code == ExitCode::SystemSplit {
+            ensure!(
+                claim.output.is_none(),
+                "receipt of segment {index} has output before the final segment"
+            );
+            let post_state = claim
+                .post
+                .as_value()
+                .with_context(|| format!("receipt of segment {index} has a pruned post state"))?;
+            self.expected_pre_state = post_state.digest();
+        } else {
+            self.terminated = true;
+        }
+        self.next_index += 1;
+        Ok(())
+    }
+
+    fn read_tag(&mut self) -> Result<u8> {
+        let mut tag = [0u8];
+        self.reader
+            .read_exact(&mut tag)
+            .context("segment stream ended without being closed")?;
+        Ok(tag[0])
+    }
+
+    fn read_payload<T: DeserializeOwned>(&mut self) -> Result<T> {
+        let mut len = [0u8; 8];
+        self.reader.read_exact(&mut len)?;
+        let len = u64::from_le_bytes(len);
+        ensure!(
+            len <= MAX_FRAME_LEN,
+            "segment stream frame of {len} bytes is too large"
+        );
+        // Read the payload as it arrives, rather than allocating the length given up front.
+        let mut payload = Vec::new();
+        (&mut self.reader).take(len).read_to_end(&mut payload)?;
+        ensure!(
+            payload.len() as u64 == len,
+            "segment stream ended part way through a frame"
+        );
+        Ok(bincode::deserialize(&payload)?)
+    }
+}
+
+impl<'a, R: Read> Iterator for SegmentStreamReader<'a, R> {
+    type Item = Result<(Segment, SegmentReceipt)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.next_segment().transpose();
+        // Stop after an error, rather than reading from the middle of a frame.
+        if matches!(item, Some(Err(_))) {
+            self.ended = true;
+        }
+        item
+    }
+}
@@ -63,6 +63,31 @@ impl BigIntTestCase {
         bigint_to_arr(&z)
     }
 
+    /// The words committed by the `BigIntOps` test: `x mod n`, `x + y mod n`, `x - y mod n`, and
+    /// the 512-bit product `x * y`, where a modulus of zero means modulo 2^256.
+    pub fn expected_ops(&self) -> Vec<u32> {
+        let x = arr_to_bigint(self.x);
+        let y = arr_to_bigint(self.y);
+        let n = arr_to_bigint(self.modulus);
+        let (x_mod, sum, diff) = match Option::<NonZero<U256>>::from(NonZero::new(n)) {
+            Some(n_nonzero) => {
+                let x_mod = x.rem(&n_nonzero);
+                let y_mod = y.rem(&n_nonzero);
+                (x_mod, x_mod.add_mod(&y_mod, &n), x_mod.sub_mod(&y_mod, &n))
+            }
+            None => (x, x.wrapping_add(&y), x.wrapping_sub(&y)),
+        };
+        let (w_lo, w_hi) = x.mul_wide(&y);
+
+        let mut words = Vec::new();
+        words.extend(bigint_to_arr(&x_mod));
+        words.extend(bigint_to_arr(&sum));
+        words.extend(bigint_to_arr(&diff));
+        words.extend(bigint_to_arr(&w_lo));
+        words.extend(bigint_to_arr(&w_hi));
+        words
+    }
+
     // NOTE: Testing here could be significantly improved by creating a less uniform
     // test case generator. It is likely more important to test inputs of different
     // byte-lengths, with zero and 0xff bytes, and other boundary values than
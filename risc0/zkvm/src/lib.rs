@@ -54,8 +54,9 @@
 //! | client           | all except rv32im | std        | Enables the client API.                                                                                                                                      |
 //! | cuda             |                   | prove, std | Enables CUDA GPU acceleration for the prover. Requires CUDA toolkit to be installed.                                                                         |
 //! | disable-dev-mode | all except rv32im |            | Disables dev mode so that proving and verifying may not be faked. Used to prevent a misplaced `RISC0_DEV_MODE` from breaking security in production systems. |
+//! | executor         | all except rv32im | client     | Enables the in-process executor without the prover, for hosts that only execute guests. Using `LocalProver` without `prove` warns which feature is missing.  |
 //! | metal            | macos             | prove, std | Enables Metal GPU acceleration for the prover.                                                                                                               |
-//! | prove            | all except rv32im | std        | Enables the prover, incompatible within the zkvm guest.                                                                                                      |
+//! | prove            | all except rv32im | executor   | Enables the prover, incompatible within the zkvm guest.                                                                                                      |
 //! | std              | all               |            | Support for the Rust stdlib.                                                                                                                                 |
 //!
 //! [`cargo risczero` tool]: https://crates.io/crates/cargo-risczero
@@ -72,16 +73,28 @@
 
 extern crate alloc;
 
+pub mod authenticated;
+#[cfg(feature = "compat")]
+pub mod compat;
+#[cfg(feature = "journal-encryption")]
+pub mod encryption;
 pub mod guest;
 #[cfg(not(target_os = "zkvm"))]
 mod host;
+pub mod predicate;
 mod receipt;
 mod receipt_claim;
+pub mod redaction;
+pub mod schema;
 pub mod serde;
 pub mod sha;
 
+#[cfg(all(not(target_os = "zkvm"), feature = "executor"))]
+pub use host::bench;
+#[cfg(all(not(target_os = "zkvm"), feature = "otel-export"))]
+pub use host::otel;
 #[cfg(all(not(target_os = "zkvm"), feature = "prove"))]
-pub use host::recursion;
+pub use host::{inspect, recursion};
 
 pub use anyhow::Result;
 #[cfg(not(target_os = "zkvm"))]
@@ -90,9 +103,53 @@ pub use bytes::Bytes;
 pub use risc0_binfmt::{ExitCode, InvalidExitCodeError, SystemState};
 pub use risc0_zkvm_platform::{align_up, declare_syscall, memory::GUEST_MAX_MEM, PAGE_SIZE};
 
+#[cfg(all(not(target_os = "zkvm"), feature = "executor", not(feature = "prove")))]
+#[allow(deprecated)]
+pub use self::host::client::prove::prove_feature::LocalProver;
+#[cfg(all(not(target_os = "zkvm"), feature = "gdb"))]
+pub use self::host::server::exec::gdb::GdbStub;
+#[cfg(all(not(target_os = "zkvm"), feature = "rv64im"))]
+pub use self::host::server::exec::rv64::{execute_rv64, Rv64Session};
+#[cfg(all(not(target_os = "zkvm"), feature = "executor"))]
+pub use self::host::{
+    client::prove::local_exec::LocalExecutor,
+    server::{
+        bundle::SessionBundle,
+        exec::{
+            batch::BatchExecutor,
+            cache::ExecutionCache,
+            diff::{
+                compare_sessions, JournalDiff, SegmentDiff, SegmentSummary, SessionDiff,
+                SyscallDiff,
+            },
+            dual::{DualExecutor, DualGuest},
+            executor::ExecutorImpl,
+            manifest::SyscallManifest,
+            patches::{missing_patches, MissingPatch},
+            perf::PerfWarning,
+            pool::{ExecutorPool, SessionHandle},
+            replay::{audit_session, ReplayResult},
+            runaway::{RunawayHeuristics, RunawayKind, RunawayReport, TightLoop},
+            snapshot::ExecutorState,
+            step::{StepResult, Stepper},
+            symbolizer::{Frame, Symbolizer},
+            syscall::{SyscallDescriptor, SyscallProvenance, SyscallRing, SyscallTable},
+            taint::TaintedCommit,
+        },
+        session::{
+            ExecutionEvent, ExecutionEventKind, ExecutionProgress, FileSegmentRef,
+            InMemorySegmentRef, NullSegmentRef, Segment, SegmentRef, Session, SessionEvents,
+            SimpleSegmentRef,
+        },
+        stream::{SegmentStreamReader, SegmentStreamWriter},
+    },
+};
 pub use self::receipt_claim::{
-    Assumption, Assumptions, Input, MaybePruned, Output, PrunedValueError, ReceiptClaim,
+    compute_claim_digest, Assumption, Assumptions, Input, MaybePruned, Output, PrunedValueError,
+    ReceiptClaim, Statement,
 };
+#[cfg(all(not(target_os = "zkvm"), feature = "executor"))]
+pub use risc0_circuit_rv32im::prove::emu::cycle_audit::{CycleMismatch, InsnCycles};
 #[cfg(all(not(target_os = "zkvm"), feature = "prove",))]
 pub use {
     self::host::{
@@ -100,12 +157,8 @@ pub use {
         client::prove::local::LocalProver,
         recursion::RECURSION_PO2,
         server::{
-            exec::{compose::register_zkr, executor::ExecutorImpl},
-            prove::{get_prover_server, HalPair, ProverServer},
-            session::{
-                FileSegmentRef, NullSegmentRef, Segment, SegmentRef, Session, SessionEvents,
-                SimpleSegmentRef,
-            },
+            exec::compose::register_zkr,
+            prove::{get_prover_server, HalPair, ProverServer, VersionMismatch},
         },
     },
     risc0_circuit_rv32im::prove::engine::loader::Loader,
@@ -120,26 +173,40 @@ pub use {
             client::Client as ApiClient, Asset, AssetRequest, Connector, SegmentInfo, SessionInfo,
         },
         client::{
+            audit::{NondeterminismKind, NondeterminismReport, NondeterminismSource},
             env::{ExecutorEnv, ExecutorEnvBuilder},
+            posix_io::{FdQuota, FdQuotaExceeded},
             prove::{
-                bonsai::BonsaiProver, default_executor, default_prover, external::ExternalProver,
+                bonsai::BonsaiProver,
+                cache::{
+                    EvictionPolicy, FileReceiptCache, MaxBytes, MaxEntries, NoEviction,
+                    ReceiptCache, ReceiptCacheEntry, ReceiptCacheKey,
+                },
+                default_executor, default_prover,
+                external::ExternalProver,
                 Executor, Prover, ProverOpts, ReceiptKind,
             },
+            quota::{QuotaManager, TenantQuota, TenantUsage},
+            sink::{Sink, Tee},
+            transcript::{SyscallTranscript, TranscriptEntry},
         },
     },
-    risc0_circuit_rv32im::trace::{TraceCallback, TraceEvent},
+    risc0_circuit_rv32im::trace::{TraceCallback, TraceEvent, WatchHit, WatchKind, WriteCombiner},
 };
 #[cfg(not(target_os = "zkvm"))]
 pub use {
     self::host::{
-        prove_info::{ProveInfo, SessionStats},
+        prove_info::{AcceleratorStats, FdUsage, ProveInfo, SegmentStats, SessionStats},
         recursion::{ALLOWED_CONTROL_IDS, ALLOWED_CONTROL_ROOT},
     },
-    risc0_binfmt::compute_image_id,
+    risc0_binfmt::{compute_image_id, MemoryProof},
     risc0_circuit_rv32im::control_id::POSEIDON2_CONTROL_IDS,
     risc0_groth16::Seal as Groth16Seal,
 };
 
+pub use predicate::ClaimPredicate;
+#[cfg(feature = "legacy-receipts")]
+pub use receipt::legacy::{migrate_receipt, ReceiptVersion};
 pub use receipt::{
     AssumptionReceipt, CompositeReceipt, CompositeReceiptVerifierParameters, FakeReceipt,
     InnerAssumptionReceipt, InnerReceipt, Journal, Receipt, ReceiptMetadata, SegmentReceipt,
@@ -0,0 +1,232 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification policies expressed as data.
+//!
+//! A [ClaimPredicate] describes what a verifier requires of a receipt, such as the image it was
+//! proven from, a prefix of its journal, and its exit code. Predicates are built by combining
+//! the functions of this module, can be serialized to be stored alongside the code that applies
+//! them, and print as a readable expression, so a policy can be audited without reading the
+//! verifier. They are checked with [Receipt::verify_with_predicate][crate::Receipt::verify_with_predicate].
+//!
+//! ```rust
+//! use risc0_zkvm::{
+//!     predicate::{exit_halted, image, journal_prefix},
+//!     sha::Digest,
+//!     ReceiptClaim,
+//! };
+//!
+//! let image_id = Digest::from([1u32; 8]);
+//! let policy = image(image_id)
+//!     .and(journal_prefix(b"v1"))
+//!     .and(exit_halted(0));
+//!
+//! let claim = ReceiptClaim::ok(image_id, b"v1:data".to_vec());
+//! assert!(policy.check(&claim, b"v1:data").is_ok());
+//! assert_eq!(
+//!     policy.check(&claim, b"v2:data").unwrap_err().to_string(),
+//!     "journal_prefix(0x7631)"
+//! );
+//! ```
+
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{fmt, ops::Not};
+
+use risc0_binfmt::ExitCode;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    sha::{Digest, Digestible},
+    ReceiptClaim,
+};
+
+/// A condition on a [ReceiptClaim] and its journal.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum ClaimPredicate {
+    /// The guest was run from the image with this ID.
+    Image(Digest),
+    /// The journal starts with these bytes.
+    JournalPrefix(Vec<u8>),
+    /// The journal is exactly these bytes.
+    Journal(Vec<u8>),
+    /// The guest exited with this exit code.
+    ExitCode(ExitCode),
+    /// All of the predicates hold.
+    All(Vec<ClaimPredicate>),
+    /// At least one of the predicates holds.
+    Any(Vec<ClaimPredicate>),
+    /// The predicate does not hold.
+    Not(Box<ClaimPredicate>),
+}
+
+/// The guest was run from the image with ID `image_id`.
+pub fn image(image_id: impl Into<Digest>) -> ClaimPredicate {
+    ClaimPredicate::Image(image_id.into())
+}
+
+/// The journal starts with `prefix`.
+pub fn journal_prefix(prefix: impl AsRef<[u8]>) -> ClaimPredicate {
+    ClaimPredicate::JournalPrefix(prefix.as_ref().to_vec())
+}
+
+/// The journal is exactly `journal`.
+pub fn journal(journal: impl AsRef<[u8]>) -> ClaimPredicate {
+    ClaimPredicate::Journal(journal.as_ref().to_vec())
+}
+
+/// The guest exited with `exit_code`.
+pub fn exit_code(exit_code: ExitCode) -> ClaimPredicate {
+    ClaimPredicate::ExitCode(exit_code)
+}
+
+/// The guest halted with the user exit code `code`.
+pub fn exit_halted(code: u32) -> ClaimPredicate {
+    ClaimPredicate::ExitCode(ExitCode::Halted(code))
+}
+
+impl ClaimPredicate {
+    /// The guest was run from the image with ID `image_id`.
+    pub fn image(image_id: impl Into<Digest>) -> Self {
+        image(image_id)
+    }
+
+    /// This predicate and `other` both hold.
+    pub fn and(self, other: ClaimPredicate) -> Self {
+        match self {
+            Self::All(mut predicates) => {
+                predicates.push(other);
+                Self::All(predicates)
+            }
+            predicate => Self::All(vec![predicate, other]),
+        }
+    }
+
+    /// This predicate or `other` holds.
+    pub fn or(self, other: ClaimPredicate) -> Self {
+        match self {
+            Self::Any(mut predicates) => {
+                predicates.push(other);
+                Self::Any(predicates)
+            }
+            predicate => Self::Any(vec![predicate, other]),
+        }
+    }
+
+    /// Check that the predicate holds for `claim`, whose journal is `journal`, returning the part
+    /// of the predicate that does not hold otherwise.
+    ///
+    /// This does not check that `journal` is the journal committed to by `claim`, as
+    /// [Receipt::verify_with_predicate][crate::Receipt::verify_with_predicate] does.
+    pub fn check(&self, claim: &ReceiptClaim, journal: &[u8]) -> Result<(), &ClaimPredicate> {
+        let holds = match self {
+            Self::Image(image_id) => claim.pre.digest() == *image_id,
+            Self::JournalPrefix(prefix) => journal.starts_with(prefix),
+            Self::Journal(expected) => journal == expected.as_slice(),
+            Self::ExitCode(exit_code) => claim.exit_code == *exit_code,
+            Self::All(predicates) => {
+                return predicates
+                    .iter()
+                    .try_for_each(|predicate| predicate.check(claim, journal));
+            }
+            Self::Any(predicates) => predicates
+                .iter()
+                .any(|predicate| predicate.check(claim, journal).is_ok()),
+            Self::Not(predicate) => predicate.check(claim, journal).is_err(),
+        };
+        holds.then_some(()).ok_or(self)
+    }
+}
+
+impl Not for ClaimPredicate {
+    type Output = ClaimPredicate;
+
+    fn not(self) -> Self::Output {
+        Self::Not(Box::new(self))
+    }
+}
+
+impl fmt::Display for ClaimPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |f: &mut fmt::Formatter<'_>, predicates: &[ClaimPredicate], op: &str| {
+            write!(f, "(")?;
+            for (idx, predicate) in predicates.iter().enumerate() {
+                if idx > 0 {
+                    write!(f, " {op} ")?;
+                }
+                write!(f, "{predicate}")?;
+            }
+            write!(f, ")")
+        };
+        match self {
+            Self::Image(image_id) => write!(f, "image({image_id})"),
+            Self::JournalPrefix(prefix) => write!(f, "journal_prefix(0x{})", hex::encode(prefix)),
+            Self::Journal(journal) => write!(f, "journal(0x{})", hex::encode(journal)),
+            Self::ExitCode(exit_code) => write!(f, "exit_code({exit_code:?})"),
+            Self::All(predicates) => join(f, predicates, "and"),
+            Self::Any(predicates) => join(f, predicates, "or"),
+            Self::Not(predicate) => write!(f, "not {predicate}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, string::ToString};
+
+    use risc0_binfmt::ExitCode;
+
+    use super::{exit_code, exit_halted, image, journal, journal_prefix, ClaimPredicate};
+    use crate::{sha::Digest, ReceiptClaim};
+
+    #[test]
+    fn check() {
+        let image_id = Digest::from([1u32; 8]);
+        let claim = ReceiptClaim::ok(image_id, b"v1:data".to_vec());
+
+        let policy = ClaimPredicate::image(image_id)
+            .and(journal_prefix(b"v1"))
+            .and(exit_halted(0));
+        assert!(policy.check(&claim, b"v1:data").is_ok());
+        assert_eq!(
+            policy.check(&claim, b"v2:data"),
+            Err(&journal_prefix(b"v1"))
+        );
+
+        let other = Digest::from([2u32; 8]);
+        assert!(image(other).check(&claim, b"").is_err());
+        assert!(image(other).or(image(image_id)).check(&claim, b"").is_ok());
+        assert!((!image(other)).check(&claim, b"").is_ok());
+        assert!(journal(b"v1:data").check(&claim, b"v1:data").is_ok());
+        assert!(exit_code(ExitCode::Paused(0))
+            .check(&claim, b"v1:data")
+            .is_err());
+    }
+
+    #[test]
+    fn display() {
+        let policy = image(Digest::ZERO).and(journal_prefix(b"v1").or(!exit_halted(1)));
+        assert_eq!(
+            policy.to_string(),
+            format!(
+                "(image({}) and (journal_prefix(0x7631) or not exit_code(Halted(1))))",
+                Digest::ZERO
+            )
+        );
+
+        let encoded = crate::serde::to_vec(&policy).unwrap();
+        let decoded: ClaimPredicate = crate::serde::from_slice(&encoded).unwrap();
+        assert_eq!(decoded, policy);
+    }
+}
@@ -16,11 +16,20 @@
 
 pub(crate) mod composite;
 pub(crate) mod groth16;
+#[cfg(feature = "std")]
+pub(crate) mod json;
+#[cfg(feature = "legacy-receipts")]
+pub(crate) mod legacy;
 pub(crate) mod merkle;
 pub(crate) mod segment;
 pub(crate) mod succinct;
 
-use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
 use core::fmt::Debug;
 
 use anyhow::Result;
@@ -39,7 +48,9 @@ use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 // Make succinct receipt available through this `receipt` module.
 use crate::{
+    predicate::ClaimPredicate,
     receipt_claim::Unknown,
+    schema::{self, JournalSchema, SchemaError},
     serde::{from_slice, Error},
     sha::{Digestible, Sha256},
     Assumption, Assumptions, MaybePruned, Output, ReceiptClaim,
@@ -193,6 +204,37 @@ impl Receipt {
         Ok(())
     }
 
+    /// Verify this receipt, and that its claim and journal satisfy `predicate`.
+    ///
+    /// Unlike [Receipt::verify], this does not require the guest to have halted with exit code 0,
+    /// so the predicate should constrain the exit code, along with the image ID. On failure, the
+    /// error names the part of the predicate that does not hold.
+    pub fn verify_with_predicate(
+        &self,
+        predicate: &ClaimPredicate,
+    ) -> Result<(), VerificationError> {
+        self.verify_with_predicate_and_context(&VerifierContext::default(), predicate)
+    }
+
+    /// Verify this receipt with the given [VerifierContext], and that its claim and journal
+    /// satisfy `predicate`.
+    pub fn verify_with_predicate_and_context(
+        &self,
+        ctx: &VerifierContext,
+        predicate: &ClaimPredicate,
+    ) -> Result<(), VerificationError> {
+        self.verify_integrity_with_context(ctx)?;
+        let claim = self.claim()?;
+        let claim = claim
+            .as_value()
+            .map_err(|_| VerificationError::ReceiptFormatError)?;
+        predicate
+            .check(claim, &self.journal.bytes)
+            .map_err(|failed| VerificationError::ClaimPredicateFailed {
+                predicate: failed.to_string(),
+            })
+    }
+
     /// Verify the integrity of this receipt, ensuring the claim and journal
     /// are attested to by the seal.
     ///
@@ -255,6 +297,35 @@ impl Receipt {
     pub fn claim(&self) -> Result<MaybePruned<ReceiptClaim>, VerificationError> {
         self.inner.claim()
     }
+
+    /// Encode this receipt as canonical JSON.
+    ///
+    /// The canonical form has no insignificant whitespace, lists the fields of each struct in
+    /// declaration order, and encodes digests as lowercase hex strings. Encoding the same receipt
+    /// always yields the same text, and decoding it with [Receipt::from_json] yields a receipt
+    /// equal to this one, with an identical binary encoding.
+    #[cfg(feature = "std")]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        json::to_string(self)
+    }
+
+    /// Decode a receipt from JSON produced by [Receipt::to_json].
+    #[cfg(feature = "std")]
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Decrypt a journal committed with
+    /// [env::commit_encrypted][crate::guest::env::commit_encrypted], using the secret key of its
+    /// recipient.
+    ///
+    /// The decrypted journal can be decoded with [Journal::decode] as usual. This does not verify
+    /// the receipt.
+    #[cfg(feature = "journal-encryption")]
+    pub fn decrypt_journal(&self, secret_key: &[u8; 32]) -> Result<Journal> {
+        let encrypted: crate::encryption::EncryptedJournal = self.journal.decode()?;
+        Ok(Journal::new(encrypted.decrypt(secret_key)?))
+    }
 }
 
 /// A record of the public commitments for a proven zkVM execution.
@@ -278,6 +349,12 @@ impl Journal {
     pub fn decode<T: DeserializeOwned>(&self) -> Result<T, Error> {
         from_slice(&self.bytes)
     }
+
+    /// Decode the journal bytes committed with the [JournalSchema] `T`, checking that the guest
+    /// committed them with the same schema.
+    pub fn decode_schema<T: JournalSchema>(&self) -> Result<T, SchemaError> {
+        schema::decode(&self.bytes)
+    }
 }
 
 impl risc0_binfmt::Digestible for Journal {
@@ -677,6 +754,9 @@ impl VerifierContext {
     }
 
     /// Return the mapping of hash suites used in the default [VerifierContext].
+    ///
+    /// Only the built-in hash suites are included. A custom hash suite is accepted only by a
+    /// context it is added to with [VerifierContext::with_hash_suite].
     pub fn default_hash_suites() -> BTreeMap<String, HashSuite<BabyBear>> {
         BTreeMap::from([
             ("blake2b".into(), Blake2bCpuHashSuite::new_suite()),
@@ -691,6 +771,25 @@ impl VerifierContext {
         self
     }
 
+    /// Return [VerifierContext] accepting [SegmentReceipt]s proven with the given custom hash
+    /// suite, under its name.
+    ///
+    /// The control IDs of the rv32im circuit depend on the hash suite, so the control IDs of the
+    /// segment sizes to accept must be given as well. They are added to the
+    /// [SegmentReceiptVerifierParameters] of this context, and can be computed with
+    /// `risc0_circuit_rv32im::prove::engine::loader::Loader::compute_control_id`.
+    pub fn with_hash_suite(
+        mut self,
+        suite: HashSuite<BabyBear>,
+        control_ids: impl IntoIterator<Item = Digest>,
+    ) -> Self {
+        if let Some(params) = self.segment_verifier_parameters.as_mut() {
+            params.control_ids.extend(control_ids);
+        }
+        self.suites.insert(suite.name.clone(), suite);
+        self
+    }
+
     /// Return [VerifierContext] with the given [SegmentReceiptVerifierParameters] set.
     pub fn with_segment_verifier_parameters(
         mut self,
@@ -747,7 +846,7 @@ mod tests {
     use super::{FakeReceipt, InnerReceipt, Receipt};
     use crate::{
         sha::{Digest, DIGEST_BYTES},
-        MaybePruned,
+        MaybePruned, ReceiptClaim,
     };
     use risc0_zkp::verify::VerificationError;
 
@@ -790,4 +889,23 @@ mod tests {
             }
         );
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn json_roundtrip() {
+        let image_id = Digest::from([1u8; DIGEST_BYTES]);
+        let receipt = Receipt::new(
+            InnerReceipt::Fake(FakeReceipt::new(ReceiptClaim::ok(image_id, vec![1, 2, 3]))),
+            vec![1, 2, 3],
+        );
+
+        let json = receipt.to_json().unwrap();
+        assert!(json.contains(&format!("\"{image_id}\"")));
+        let decoded = Receipt::from_json(&json).unwrap();
+        assert_eq!(decoded.to_json().unwrap(), json);
+        assert_eq!(
+            crate::serde::to_vec(&decoded).unwrap(),
+            crate::serde::to_vec(&receipt).unwrap()
+        );
+    }
 }
@@ -0,0 +1,535 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Decoding of receipts serialized by earlier releases.
+//!
+//! Each supported release has a private copy of the receipt types as they were serialized by
+//! that release, which is decoded with bincode and converted to the current types. Seals are
+//! carried over unchanged. Fields that did not exist in a release are filled in with the values
+//! of the current release, e.g. the verifier parameters of receipts from before 1.0, so the seal
+//! of a migrated receipt may not verify if the circuit has changed since. The claim and journal
+//! are always preserved, and can be checked against the claim digest of the original receipt.
+//! Parts of a 0.21 claim that the current [ReceiptClaim] can't represent, such as a nonzero input
+//! digest or a list of assumed claims, are pruned to their original digests.
+
+use alloc::{string::String, vec::Vec};
+
+use anyhow::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    CompositeReceipt, CompositeReceiptVerifierParameters, FakeReceipt, Groth16Receipt,
+    Groth16ReceiptVerifierParameters, InnerAssumptionReceipt, InnerReceipt, Journal, Receipt,
+    ReceiptMetadata, SegmentReceipt, SegmentReceiptVerifierParameters, SuccinctReceipt,
+    SuccinctReceiptVerifierParameters,
+};
+use crate::{receipt::merkle::MerkleProof, sha::Digestible, ReceiptClaim};
+
+/// A release of `risc0-zkvm` whose serialized receipts can be read with [migrate_receipt].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReceiptVersion {
+    /// Releases 0.21.x.
+    V0_21,
+
+    /// Releases 1.0.x.
+    V1_0,
+}
+
+/// Decode a receipt serialized with bincode by the given release, and convert it to the current
+/// [Receipt] format.
+///
+/// The claim and journal of the receipt are preserved. Verifier parameters missing from older
+/// formats are set to those of the current release, so the seal may no longer verify.
+pub fn migrate_receipt(old_bytes: &[u8], from_version: ReceiptVersion) -> Result<Receipt> {
+    match from_version {
+        ReceiptVersion::V0_21 => {
+            let receipt: v0_21::Receipt =
+                bincode::deserialize(old_bytes).context("Failed to decode a 0.21 receipt")?;
+            Ok(Receipt::new(receipt.inner.into(), receipt.journal.bytes))
+        }
+        ReceiptVersion::V1_0 => {
+            let receipt: v1_0::Receipt =
+                bincode::deserialize(old_bytes).context("Failed to decode a 1.0 receipt")?;
+            Ok(Receipt {
+                inner: receipt.inner.into(),
+                journal: receipt.journal,
+                metadata: receipt.metadata,
+            })
+        }
+    }
+}
+
+mod v0_21 {
+    use risc0_binfmt::{tagged_list, ExitCode, SystemState};
+
+    use super::*;
+    use crate::{sha, sha::Digest, Assumptions, MaybePruned};
+
+    #[derive(Deserialize)]
+    pub(super) struct Receipt {
+        pub inner: InnerReceipt,
+        pub journal: Journal,
+    }
+
+    #[derive(Deserialize)]
+    pub(super) enum InnerReceipt {
+        Composite(CompositeReceipt),
+        Succinct(SuccinctReceipt),
+        Compact(CompactReceipt),
+        Fake { claim: ReceiptClaim },
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct CompositeReceipt {
+        segments: Vec<SegmentReceipt>,
+        assumptions: Vec<InnerReceipt>,
+        #[allow(dead_code)]
+        journal_digest: Option<crate::sha::Digest>,
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct SegmentReceipt {
+        seal: Vec<u32>,
+        index: u32,
+        hashfn: String,
+        claim: ReceiptClaim,
+    }
+
+    // The input was a bare digest, and assumptions were the claims themselves rather than
+    // [Assumption](crate::Assumption) values carrying a control root.
+    #[derive(Clone, Deserialize, Serialize)]
+    pub(super) struct ReceiptClaim {
+        pre: MaybePruned<SystemState>,
+        post: MaybePruned<SystemState>,
+        exit_code: ExitCode,
+        input: Digest,
+        output: MaybePruned<Option<Output>>,
+    }
+
+    #[derive(Clone, Deserialize, Serialize)]
+    pub(super) struct Output {
+        journal: MaybePruned<Vec<u8>>,
+        assumptions: MaybePruned<Vec<MaybePruned<ReceiptClaim>>>,
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct SuccinctReceipt {
+        seal: Vec<u32>,
+        control_id: crate::sha::Digest,
+        claim: ReceiptClaim,
+        hashfn: String,
+        control_inclusion_proof: MerkleProof,
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct CompactReceipt {
+        seal: Vec<u8>,
+        claim: ReceiptClaim,
+    }
+
+    impl From<InnerReceipt> for super::InnerReceipt {
+        fn from(value: InnerReceipt) -> Self {
+            match value {
+                InnerReceipt::Composite(inner) => Self::Composite(inner.into()),
+                InnerReceipt::Succinct(inner) => Self::Succinct(inner.into()),
+                InnerReceipt::Compact(inner) => Self::Groth16(Groth16Receipt {
+                    seal: inner.seal,
+                    claim: super::ReceiptClaim::from(inner.claim).into(),
+                    verifier_parameters: Groth16ReceiptVerifierParameters::default().digest(),
+                }),
+                InnerReceipt::Fake { claim } => {
+                    Self::Fake(FakeReceipt::new(super::ReceiptClaim::from(claim)))
+                }
+            }
+        }
+    }
+
+    // The converted claim has the same digest as the original. A non-empty assumptions list is
+    // pruned to its digest, which was the tagged list of the assumed claim digests.
+    impl From<ReceiptClaim> for super::ReceiptClaim {
+        fn from(value: ReceiptClaim) -> Self {
+            Self {
+                pre: value.pre,
+                post: value.post,
+                exit_code: value.exit_code,
+                input: if value.input == Digest::ZERO {
+                    MaybePruned::Value(None)
+                } else {
+                    MaybePruned::Pruned(value.input)
+                },
+                output: match value.output {
+                    MaybePruned::Value(output) => MaybePruned::Value(output.map(Into::into)),
+                    MaybePruned::Pruned(digest) => MaybePruned::Pruned(digest),
+                },
+            }
+        }
+    }
+
+    impl From<Output> for crate::Output {
+        fn from(value: Output) -> Self {
+            Self {
+                journal: value.journal,
+                assumptions: match value.assumptions {
+                    MaybePruned::Value(claims) if claims.is_empty() => {
+                        MaybePruned::Value(Assumptions::default())
+                    }
+                    MaybePruned::Value(claims) => {
+                        let digests: Vec<Digest> = claims
+                            .into_iter()
+                            .map(|claim| match claim {
+                                MaybePruned::Value(claim) => {
+                                    super::ReceiptClaim::from(claim).digest()
+                                }
+                                MaybePruned::Pruned(digest) => digest,
+                            })
+                            .collect();
+                        MaybePruned::Pruned(tagged_list::<sha::Impl>("risc0.Assumptions", &digests))
+                    }
+                    MaybePruned::Pruned(digest) => MaybePruned::Pruned(digest),
+                },
+            }
+        }
+    }
+
+    impl From<CompositeReceipt> for super::CompositeReceipt {
+        fn from(value: CompositeReceipt) -> Self {
+            let segment_params = SegmentReceiptVerifierParameters::default().digest();
+            Self {
+                segments: value
+                    .segments
+                    .into_iter()
+                    .map(|segment| super::SegmentReceipt {
+                        seal: segment.seal,
+                        index: segment.index,
+                        hashfn: segment.hashfn,
+                        verifier_parameters: segment_params,
+                        claim: segment.claim.into(),
+                    })
+                    .collect(),
+                assumption_receipts: value
+                    .assumptions
+                    .into_iter()
+                    .map(|inner| super::InnerReceipt::from(inner).into())
+                    .collect(),
+                verifier_parameters: CompositeReceiptVerifierParameters::default().digest(),
+            }
+        }
+    }
+
+    impl From<SuccinctReceipt> for super::SuccinctReceipt<super::ReceiptClaim> {
+        fn from(value: SuccinctReceipt) -> Self {
+            Self {
+                seal: value.seal,
+                control_id: value.control_id,
+                claim: super::ReceiptClaim::from(value.claim).into(),
+                hashfn: value.hashfn,
+                verifier_parameters: SuccinctReceiptVerifierParameters::default().digest(),
+                control_inclusion_proof: value.control_inclusion_proof,
+            }
+        }
+    }
+}
+
+mod v1_0 {
+    use super::*;
+
+    #[derive(Deserialize)]
+    pub(super) struct Receipt {
+        pub inner: InnerReceipt,
+        pub journal: Journal,
+        pub metadata: ReceiptMetadata,
+    }
+
+    #[derive(Deserialize)]
+    pub(super) enum InnerReceipt {
+        Composite(CompositeReceipt),
+        Succinct(super::SuccinctReceipt<ReceiptClaim>),
+        Groth16(super::Groth16Receipt<ReceiptClaim>),
+        Fake(super::FakeReceipt<ReceiptClaim>),
+    }
+
+    // Composite receipts carried their assumptions as receipts of the same type as the
+    // conditional receipt, before InnerAssumptionReceipt was introduced.
+    #[derive(Deserialize)]
+    pub(super) struct CompositeReceipt {
+        segments: Vec<super::SegmentReceipt>,
+        assumption_receipts: Vec<InnerReceipt>,
+        verifier_parameters: crate::sha::Digest,
+    }
+
+    impl From<InnerReceipt> for super::InnerReceipt {
+        fn from(value: InnerReceipt) -> Self {
+            match value {
+                InnerReceipt::Composite(inner) => Self::Composite(inner.into()),
+                InnerReceipt::Succinct(inner) => Self::Succinct(inner),
+                InnerReceipt::Groth16(inner) => Self::Groth16(inner),
+                InnerReceipt::Fake(inner) => Self::Fake(inner),
+            }
+        }
+    }
+
+    impl From<CompositeReceipt> for super::CompositeReceipt {
+        fn from(value: CompositeReceipt) -> Self {
+            Self {
+                segments: value.segments,
+                assumption_receipts: value
+                    .assumption_receipts
+                    .into_iter()
+                    .map(|inner| InnerAssumptionReceipt::from(super::InnerReceipt::from(inner)))
+                    .collect(),
+                verifier_parameters: value.verifier_parameters,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use risc0_binfmt::{tagged_list, ExitCode, SystemState};
+
+    use super::{migrate_receipt, ReceiptVersion};
+    use crate::{
+        receipt::{FakeReceipt, InnerAssumptionReceipt, InnerReceipt},
+        sha::{self, Digest, Digestible},
+        Assumptions, MaybePruned, Output, Receipt, ReceiptClaim,
+    };
+
+    // The fixtures below are written out field by field in the bincode layout of the old types,
+    // rather than serialized from the private copies of those types in this module, so that a
+    // mistake in a copy shows up as a decoding failure or a changed claim digest.
+    #[derive(Default)]
+    struct Fixture(Vec<u8>);
+
+    impl Fixture {
+        fn u8(&mut self, value: u8) -> &mut Self {
+            self.0.push(value);
+            self
+        }
+
+        fn u32(&mut self, value: u32) -> &mut Self {
+            self.0.extend(value.to_le_bytes());
+            self
+        }
+
+        fn len(&mut self, len: usize) -> &mut Self {
+            self.0.extend((len as u64).to_le_bytes());
+            self
+        }
+
+        fn digest(&mut self, digest: Digest) -> &mut Self {
+            self.0.extend(digest.as_bytes());
+            self
+        }
+
+        fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+            self.len(bytes.len());
+            self.0.extend(bytes);
+            self
+        }
+
+        fn words(&mut self, words: &[u32]) -> &mut Self {
+            self.len(words.len());
+            words.iter().for_each(|word| {
+                self.u32(*word);
+            });
+            self
+        }
+
+        // A 0.21 ReceiptClaim with a populated pre-state, a pruned post-state, a Halted(0) exit
+        // code and the given input digest, journal and pruned assumptions.
+        fn v0_21_claim(
+            &mut self,
+            input: Digest,
+            journal: &[u8],
+            assumptions: &[Digest],
+        ) -> &mut Self {
+            self.u32(0).u32(0x1000).digest(Digest::from([1; 8]));
+            self.u32(1).digest(Digest::from([2; 8]));
+            self.u32(0).u32(0);
+            self.digest(input);
+            self.u32(0).u8(1);
+            self.u32(0).bytes(journal);
+            self.u32(0).len(assumptions.len());
+            assumptions.iter().for_each(|digest| {
+                self.u32(1).digest(*digest);
+            });
+            self
+        }
+    }
+
+    // The current claim equivalent to [Fixture::v0_21_claim] without assumptions.
+    fn claim(input: MaybePruned<Option<crate::Input>>, journal: &[u8]) -> ReceiptClaim {
+        ReceiptClaim {
+            pre: MaybePruned::Value(SystemState {
+                pc: 0x1000,
+                merkle_root: Digest::from([1; 8]),
+            }),
+            post: MaybePruned::Pruned(Digest::from([2; 8])),
+            exit_code: ExitCode::Halted(0),
+            input,
+            output: MaybePruned::Value(Some(Output {
+                journal: MaybePruned::Value(journal.to_vec()),
+                assumptions: MaybePruned::Value(Assumptions::default()),
+            })),
+        }
+    }
+
+    #[test]
+    fn v1_0_fake() {
+        // A fake receipt has the same encoding in 1.0 as in the current release.
+        let claim = ReceiptClaim::ok(Digest::ZERO, b"journal".to_vec());
+        let receipt = Receipt::new(
+            InnerReceipt::Fake(FakeReceipt::new(claim)),
+            b"journal".to_vec(),
+        );
+        let bytes = bincode::serialize(&receipt).unwrap();
+        assert_eq!(
+            migrate_receipt(&bytes, ReceiptVersion::V1_0).unwrap(),
+            receipt
+        );
+    }
+
+    #[test]
+    fn v1_0_composite() {
+        // A composite receipt whose assumption is a fake receipt, encoded as a 1.0 InnerReceipt.
+        let assumption = ReceiptClaim::ok(Digest::from([3; 8]), b"assumed".to_vec());
+        let mut fixture = Fixture::default();
+        fixture.u32(0).len(1);
+        fixture.words(&[0xaa]).u32(0).bytes(b"poseidon2");
+        fixture.digest(Digest::from([4; 8]));
+        fixture
+            .0
+            .extend(bincode::serialize(&claim(MaybePruned::Value(None), b"journal")).unwrap());
+        fixture.len(1).u32(3).u32(0);
+        fixture.0.extend(bincode::serialize(&assumption).unwrap());
+        fixture.digest(Digest::from([5; 8]));
+        fixture.bytes(b"journal").digest(Digest::from([5; 8]));
+
+        let receipt = migrate_receipt(&fixture.0, ReceiptVersion::V1_0).unwrap();
+        let InnerReceipt::Composite(inner) = &receipt.inner else {
+            panic!("expected a composite receipt");
+        };
+        assert_eq!(inner.segments[0].hashfn, "poseidon2");
+        assert_eq!(inner.segments[0].verifier_parameters, Digest::from([4; 8]));
+        assert_eq!(
+            inner.segments[0].claim,
+            claim(MaybePruned::Value(None), b"journal")
+        );
+        assert_eq!(inner.verifier_parameters, Digest::from([5; 8]));
+        let [InnerAssumptionReceipt::Fake(fake)] = &inner.assumption_receipts[..] else {
+            panic!("expected a single fake assumption receipt");
+        };
+        assert_eq!(fake.claim.digest(), assumption.digest());
+        assert_eq!(receipt.journal.bytes, b"journal");
+        assert_eq!(receipt.metadata.verifier_parameters, Digest::from([5; 8]));
+    }
+
+    #[test]
+    fn v0_21_fake() {
+        let mut fixture = Fixture::default();
+        fixture.u32(3).v0_21_claim(Digest::ZERO, b"journal", &[]);
+        fixture.bytes(b"journal");
+
+        let receipt = migrate_receipt(&fixture.0, ReceiptVersion::V0_21).unwrap();
+        assert_eq!(receipt.journal.bytes, b"journal");
+        assert_eq!(
+            receipt.claim().unwrap(),
+            MaybePruned::Value(claim(MaybePruned::Value(None), b"journal"))
+        );
+        assert!(migrate_receipt(&fixture.0[1..], ReceiptVersion::V0_21).is_err());
+    }
+
+    #[test]
+    fn v0_21_composite() {
+        let mut fixture = Fixture::default();
+        fixture.u32(0).len(1);
+        fixture.words(&[1, 2, 3]).u32(0).bytes(b"poseidon2");
+        fixture.v0_21_claim(Digest::ZERO, b"journal", &[]);
+        fixture
+            .len(1)
+            .u32(3)
+            .v0_21_claim(Digest::ZERO, b"assumed", &[]);
+        fixture.u8(0);
+        fixture.bytes(b"journal");
+
+        let receipt = migrate_receipt(&fixture.0, ReceiptVersion::V0_21).unwrap();
+        let InnerReceipt::Composite(inner) = &receipt.inner else {
+            panic!("expected a composite receipt");
+        };
+        assert_eq!(inner.segments[0].seal, [1, 2, 3]);
+        assert_eq!(
+            inner.segments[0].claim,
+            claim(MaybePruned::Value(None), b"journal")
+        );
+        assert_eq!(
+            inner.assumption_receipts[0].claim_digest().unwrap(),
+            claim(MaybePruned::Value(None), b"assumed").digest()
+        );
+        assert_eq!(receipt.journal.bytes, b"journal");
+    }
+
+    #[test]
+    fn v0_21_succinct_preserves_claim_digest() {
+        // A nonzero input digest and a non-empty assumptions list have no exact equivalent in the
+        // current claim, and are carried over as pruned values with the same digests.
+        let input = Digest::from([6; 8]);
+        let assumptions = [Digest::from([7; 8]), Digest::from([8; 8])];
+        let mut fixture = Fixture::default();
+        fixture.u32(1).words(&[9; 4]).digest(Digest::from([10; 8]));
+        fixture.v0_21_claim(input, b"journal", &assumptions);
+        fixture
+            .bytes(b"poseidon2")
+            .u32(2)
+            .len(1)
+            .digest(Digest::from([11; 8]));
+        fixture.bytes(b"journal");
+
+        let receipt = migrate_receipt(&fixture.0, ReceiptVersion::V0_21).unwrap();
+        let InnerReceipt::Succinct(inner) = &receipt.inner else {
+            panic!("expected a succinct receipt");
+        };
+        assert_eq!(inner.seal, [9; 4]);
+        assert_eq!(inner.control_id, Digest::from([10; 8]));
+        assert_eq!(inner.control_inclusion_proof.index, 2);
+
+        let mut expected = claim(MaybePruned::Pruned(input), b"journal");
+        let MaybePruned::Value(Some(output)) = &mut expected.output else {
+            unreachable!()
+        };
+        output.assumptions =
+            MaybePruned::Pruned(tagged_list::<sha::Impl>("risc0.Assumptions", &assumptions));
+        assert_eq!(inner.claim.digest(), expected.digest());
+    }
+
+    #[test]
+    fn v0_21_compact() {
+        let mut fixture = Fixture::default();
+        fixture.u32(2).bytes(&[12; 256]);
+        fixture.v0_21_claim(Digest::ZERO, b"journal", &[]);
+        fixture.bytes(b"journal");
+
+        let receipt = migrate_receipt(&fixture.0, ReceiptVersion::V0_21).unwrap();
+        let InnerReceipt::Groth16(inner) = &receipt.inner else {
+            panic!("expected a Groth16 receipt");
+        };
+        assert_eq!(inner.seal, vec![12; 256]);
+        assert_eq!(
+            inner.claim.digest(),
+            claim(MaybePruned::Value(None), b"journal").digest()
+        );
+    }
+}
@@ -24,8 +24,9 @@ use risc0_circuit_rv32im::{
 use risc0_zkp::{
     adapter::{CircuitInfo as _, ProtocolInfo, PROOF_SYSTEM_INFO},
     core::{digest::Digest, hash::sha::Sha256},
+    field::baby_bear::BabyBear,
     layout::Buffer,
-    verify::VerificationError,
+    verify::{seal::Reader, VerificationError},
 };
 use serde::{Deserialize, Serialize};
 
@@ -103,10 +104,12 @@ impl SegmentReceipt {
                 },
             )
         };
-        let suite = ctx
-            .suites
-            .get(&self.hashfn)
-            .ok_or(VerificationError::InvalidHashSuite)?;
+        let suite =
+            ctx.suites
+                .get(&self.hashfn)
+                .ok_or_else(|| VerificationError::UnknownHashSuite {
+                    name: self.hashfn.clone(),
+                })?;
         risc0_zkp::verify::verify(&CIRCUIT, suite, &self.seal, check_code)?;
 
         // Receipt is consistent with the claim encoded on the seal. Now check against the
@@ -126,6 +129,21 @@ impl SegmentReceipt {
         Ok(())
     }
 
+    /// Return the power of two length of the segment proven by this receipt, as encoded on the
+    /// seal.
+    pub fn po2(&self) -> Result<u32, VerificationError> {
+        self.seal
+            .get(CircuitImpl::OUTPUT_SIZE)
+            .copied()
+            .ok_or(VerificationError::ReceiptFormatError)
+    }
+
+    /// Return a [Reader] over the structure of the seal, e.g. to implement an independent
+    /// verifier. The seal is not verified.
+    pub fn seal_reader(&self) -> Result<Reader<'_, BabyBear>, VerificationError> {
+        Reader::new(&CIRCUIT, &self.seal)
+    }
+
     /// Return the seal for this receipt, as a vector of bytes.
     pub fn get_seal_bytes(&self) -> Vec<u8> {
         self.seal.iter().flat_map(|x| x.to_le_bytes()).collect()
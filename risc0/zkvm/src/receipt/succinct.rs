@@ -110,10 +110,12 @@ where
             });
         }
 
-        let suite = ctx
-            .suites
-            .get(&self.hashfn)
-            .ok_or(VerificationError::InvalidHashSuite)?;
+        let suite =
+            ctx.suites
+                .get(&self.hashfn)
+                .ok_or_else(|| VerificationError::UnknownHashSuite {
+                    name: self.hashfn.clone(),
+                })?;
 
         let check_code = |_, control_id: &Digest| -> Result<(), VerificationError> {
             self.control_inclusion_proof
@@ -184,7 +186,7 @@ where
         self.seal.iter().flat_map(|x| x.to_le_bytes()).collect()
     }
 
-    #[cfg(feature = "prove")]
+    #[cfg(feature = "executor")]
     pub(crate) fn control_root(&self) -> anyhow::Result<Digest> {
         let hash_suite = risc0_zkp::core::hash::hash_suite_from_name(&self.hashfn)
             .ok_or_else(|| anyhow::anyhow!("unsupported hash function: {}", self.hashfn))?;
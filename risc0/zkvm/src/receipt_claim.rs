@@ -19,7 +19,11 @@
 //! information such as the exit code and the starting and ending system state (i.e. the state of
 //! memory).
 
-use alloc::{collections::VecDeque, vec::Vec};
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    string::String,
+    vec::Vec,
+};
 use core::{fmt, ops::Deref};
 
 use anyhow::{anyhow, ensure};
@@ -109,6 +113,20 @@ impl ReceiptClaim {
         }
     }
 
+    /// Encode this claim as canonical JSON, in the same form as [Receipt::to_json].
+    ///
+    /// [Receipt::to_json]: crate::Receipt::to_json
+    #[cfg(feature = "std")]
+    pub fn to_json(&self) -> Result<alloc::string::String, serde_json::Error> {
+        crate::receipt::json::to_string(self)
+    }
+
+    /// Decode a claim from JSON produced by [ReceiptClaim::to_json].
+    #[cfg(feature = "std")]
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
     /// Decode a [ReceiptClaim] from a list of [u32]'s
     pub fn decode(flat: &mut VecDeque<u32>) -> Result<Self, DecodeError> {
         let input = read_sha_halfs(flat)?;
@@ -162,6 +180,45 @@ impl Digestible for ReceiptClaim {
     }
 }
 
+/// Compute the digest of the [ReceiptClaim] for a guest execution that halted, without executing
+/// or proving it.
+///
+/// The result is the digest committed to by every receipt of an execution of the image with the
+/// given ID that halts with `exit_code`, writing `journal` and leaving the given assumptions
+/// unresolved. `input` is the digest of the committed input, or [Digest::ZERO] if the input was
+/// not committed. Applications can use this to precompute the claim they expect a receipt to
+/// prove, e.g. to match it against the claim digest checked by an on-chain verifier.
+///
+/// Only executions that halted can be described this way, since the post-state of any other
+/// execution depends on the contents of its memory.
+pub fn compute_claim_digest(
+    image_id: impl Into<Digest>,
+    journal: impl Into<MaybePruned<Vec<u8>>>,
+    exit_code: ExitCode,
+    input: Digest,
+    assumptions: impl Into<MaybePruned<Assumptions>>,
+) -> anyhow::Result<Digest> {
+    ensure!(
+        matches!(exit_code, ExitCode::Halted(_)),
+        "cannot compute the claim digest of an execution with exit code {exit_code:?}"
+    );
+    let claim = ReceiptClaim {
+        pre: MaybePruned::Pruned(image_id.into()),
+        post: MaybePruned::Value(SystemState {
+            pc: 0,
+            merkle_root: Digest::ZERO,
+        }),
+        exit_code,
+        input: MaybePruned::Pruned(input),
+        output: Some(Output {
+            journal: journal.into(),
+            assumptions: assumptions.into(),
+        })
+        .into(),
+    };
+    Ok(claim.digest::<sha::Impl>())
+}
+
 /// Error returned when decoding [ReceiptClaim] fails.
 #[derive(Debug, Copy, Clone)]
 pub enum DecodeError {
@@ -235,6 +292,60 @@ impl Digestible for Input {
     }
 }
 
+/// The data that a host provides to a guest, in a canonical form.
+///
+/// A guest reads its inputs as a [Statement] with
+/// [env::commit_statement][crate::guest::env::commit_statement], which hashes them in the guest
+/// and commits the digest to the journal. A verifier that knows the inputs can then recompute the
+/// digest and check it against the journal. When the environment is built with
+/// `ExecutorEnvBuilder::capture_statement`, the digest also becomes the input digest of the
+/// session, which is part of its [ReceiptClaim], and the guest checks that it matches.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Statement {
+    /// All data written to the guest's stdin.
+    pub stdin: Vec<u8>,
+
+    /// The arguments of the guest, in order.
+    pub args: Vec<String>,
+
+    /// The environment variables of the guest.
+    pub env_vars: BTreeMap<String, String>,
+}
+
+impl Digestible for Statement {
+    /// Hash the [Statement] to get a digest of the struct.
+    fn digest<S: Sha256>(&self) -> Digest {
+        let args: Vec<Digest> = self
+            .args
+            .iter()
+            .map(|arg| *S::hash_bytes(arg.as_bytes()))
+            .collect();
+        let env_vars: Vec<Digest> = self
+            .env_vars
+            .iter()
+            .map(|(name, value)| {
+                tagged_struct::<S>(
+                    "risc0.EnvVar",
+                    &[
+                        *S::hash_bytes(name.as_bytes()),
+                        *S::hash_bytes(value.as_bytes()),
+                    ],
+                    &[],
+                )
+            })
+            .collect();
+        tagged_struct::<S>(
+            "risc0.Statement",
+            &[
+                *S::hash_bytes(&self.stdin),
+                tagged_list::<S>("risc0.Args", &args),
+                tagged_list::<S>("risc0.EnvVars", &env_vars),
+            ],
+            &[],
+        )
+    }
+}
+
 /// Output field in the [ReceiptClaim], committing to a claimed journal and assumptions list.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
@@ -739,7 +850,10 @@ impl Merge for ReceiptClaim {
 mod tests {
     use hex::FromHex;
 
-    use super::{Assumptions, ExitCode, MaybePruned, Merge, Output, ReceiptClaim, SystemState};
+    use super::{
+        compute_claim_digest, Assumption, Assumptions, ExitCode, MaybePruned, Merge, Output,
+        ReceiptClaim, SystemState,
+    };
     use crate::sha::{Digest, Digestible};
 
     /// Testing utility for randomly pruning structs.
@@ -852,4 +966,54 @@ mod tests {
             assert_eq!(left.merge(&right).unwrap().digest(), claim.digest());
         }
     }
+
+    #[test]
+    fn claim_digest_matches_receipt_claim() {
+        let image_id = Digest::from([1, 2, 3, 4, 5, 6, 7, 8]);
+        let journal = b"journal".to_vec();
+
+        let digest = compute_claim_digest(
+            image_id,
+            journal.clone(),
+            ExitCode::Halted(0),
+            Digest::ZERO,
+            Assumptions::default(),
+        )
+        .unwrap();
+        assert_eq!(digest, ReceiptClaim::ok(image_id, journal.clone()).digest());
+
+        let assumption = Assumption {
+            claim: Digest::from([9; 8]),
+            control_root: Digest::ZERO,
+        };
+        let input = Digest::from([10; 8]);
+        let digest = compute_claim_digest(
+            image_id,
+            journal.clone(),
+            ExitCode::Halted(1),
+            input,
+            vec![assumption.clone()],
+        )
+        .unwrap();
+        let mut claim = ReceiptClaim::ok(image_id, journal.clone());
+        claim.exit_code = ExitCode::Halted(1);
+        claim.input = MaybePruned::Pruned(input);
+        claim
+            .output
+            .as_value_mut()
+            .unwrap()
+            .as_mut()
+            .unwrap()
+            .assumptions = vec![assumption].into();
+        assert_eq!(digest, claim.digest());
+
+        assert!(compute_claim_digest(
+            image_id,
+            journal,
+            ExitCode::Paused(0),
+            Digest::ZERO,
+            Assumptions::default()
+        )
+        .is_err());
+    }
 }
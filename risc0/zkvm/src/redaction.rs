@@ -0,0 +1,461 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Journals whose fields can be selectively revealed.
+//!
+//! A guest builds a [RedactableJournal] from a list of fields and commits only its
+//! [root][RedactableJournal::root] to the journal, using
+//! [env::commit_redactable][crate::guest::env::commit_redactable]. The fields themselves must be
+//! sent to the host separately, e.g. with [env::write][crate::guest::env::write].
+//!
+//! In either case, the journal holds only the root, and nothing else may be committed to it.
+//!
+//! Alternatively, the guest can commit named fields one at a time with
+//! [env::commit_field][crate::guest::env::commit_field], which salts each field, sends it to the
+//! host, and commits the root when the guest exits. The host finds the fields in
+//! `Session::committed_fields`, and can reveal them by name with
+//! [redact_fields][RedactableJournal::redact_fields].
+//!
+//! The host can then [redact][RedactableJournal::redact] the fields that should stay private,
+//! and hand the resulting [RedactedJournal] to a verifier alongside the receipt. The verifier
+//! checks the receipt, then checks with [RedactedJournal::verify_journal] that the redacted
+//! journal has the same root as the one committed by the guest, and reads the revealed fields.
+//!
+//! Each field is committed together with a salt, so that a redacted field cannot be recovered by
+//! guessing its contents. Salts should be 32 bytes of randomness that the verifier does not know.
+//!
+//! The data of the journal is the concatenation of the data of its fields, and each field is
+//! committed together with its offset in it, so the fields also mark out the byte ranges of the
+//! journal data that can be revealed. The host can reveal byte ranges with
+//! [redact_ranges][RedactableJournal::redact_ranges], and the verifier reads where each revealed
+//! field lies with [RedactedJournal::revealed_ranges].
+//!
+//! The root commits to the number of fields as well as to each field in order, and hashes
+//! leaves and interior nodes of the tree under different tags, so that a redacted journal
+//! cannot pass off an interior node as a field, or otherwise reveal a different list of fields
+//! under the same root.
+//!
+//! ```rust
+//! use risc0_zkvm::{redaction::RedactableJournal, sha::Digest};
+//!
+//! let mut journal = RedactableJournal::default();
+//! journal.push_named("name", Digest::from([1u32; 8]), b"alice".to_vec()).unwrap();
+//! journal.push_named("balance", Digest::from([2u32; 8]), b"1000".to_vec()).unwrap();
+//!
+//! // The guest commits the root, and the host reveals only the balance.
+//! let committed = journal.root();
+//! let redacted = journal.redact_fields(&["balance"]);
+//! redacted.verify_journal(committed.as_bytes()).unwrap();
+//! assert_eq!(redacted.field(0), None);
+//! assert_eq!(redacted.field_by_name("balance"), Some(&b"1000"[..]));
+//! ```
+
+use alloc::{string::String, vec::Vec};
+use core::ops::Range;
+
+use anyhow::{ensure, Result};
+use risc0_binfmt::tagged_struct;
+use serde::{Deserialize, Serialize};
+
+use crate::sha::{self, Digest, Sha256};
+
+/// A field of a [RedactableJournal], together with the salt it is committed with.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactableField {
+    /// The name of the field, which is empty for fields added with [RedactableJournal::push].
+    pub name: String,
+
+    /// Randomness that hides the data of the field when it is redacted.
+    pub salt: Digest,
+
+    /// The offset of the data of the field in the data of the journal, i.e. the concatenation of
+    /// the data of its fields in order.
+    pub offset: u32,
+
+    /// The data of the field.
+    pub data: Vec<u8>,
+}
+
+impl RedactableField {
+    /// The digest that commits to this field.
+    pub fn digest(&self) -> Digest {
+        let name_digest = *sha::Impl::hash_bytes(self.name.as_bytes());
+        let data_digest = *sha::Impl::hash_bytes(&self.data);
+        tagged_struct::<sha::Impl>(
+            "risc0.RedactableField",
+            &[name_digest, self.salt, data_digest],
+            &[self.offset],
+        )
+    }
+
+    /// The byte range of the data of the journal that this field holds.
+    pub fn range(&self) -> Range<usize> {
+        let start = self.offset as usize;
+        start..start + self.data.len()
+    }
+}
+
+/// A list of fields committed to by a Merkle tree, whose root is committed to the journal.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactableJournal {
+    /// The fields of the journal, in order.
+    pub fields: Vec<RedactableField>,
+}
+
+impl RedactableJournal {
+    /// Append an unnamed field with the given salt and data.
+    pub fn push(&mut self, salt: Digest, data: Vec<u8>) {
+        self.push_field("", salt, data);
+    }
+
+    /// Append a field with the given name, salt, and data.
+    ///
+    /// Fails if the journal already has a field with the same name, so that
+    /// [RedactedJournal::field_by_name] is unambiguous.
+    pub fn push_named(&mut self, name: &str, salt: Digest, data: Vec<u8>) -> Result<()> {
+        ensure!(
+            name.is_empty() || self.fields.iter().all(|field| field.name != name),
+            "duplicate field name {name:?}"
+        );
+        self.push_field(name, salt, data);
+        Ok(())
+    }
+
+    fn push_field(&mut self, name: &str, salt: Digest, data: Vec<u8>) {
+        let offset = self.fields.last().map_or(0, |field| field.range().end) as u32;
+        self.fields.push(RedactableField {
+            name: name.into(),
+            salt,
+            offset,
+            data,
+        });
+    }
+
+    /// The data of the journal, i.e. the concatenation of the data of its fields in order.
+    pub fn data(&self) -> Vec<u8> {
+        self.fields
+            .iter()
+            .flat_map(|field| field.data.iter().copied())
+            .collect()
+    }
+
+    /// The root of the Merkle tree over the fields, which commits to the number of fields.
+    pub fn root(&self) -> Digest {
+        merkle_root(self.fields.iter().map(RedactableField::digest).collect())
+    }
+
+    /// Produce a [RedactedJournal] that reveals only the fields at the given indices.
+    pub fn redact(&self, reveal: &[usize]) -> RedactedJournal {
+        let fields = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(idx, field)| {
+                if reveal.contains(&idx) {
+                    RedactedField::Revealed(field.clone())
+                } else {
+                    RedactedField::Redacted(field.digest())
+                }
+            })
+            .collect();
+        RedactedJournal { fields }
+    }
+
+    /// Produce a [RedactedJournal] that reveals only the fields with the given names.
+    pub fn redact_fields(&self, reveal: &[&str]) -> RedactedJournal {
+        let reveal: Vec<usize> = self
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| reveal.contains(&field.name.as_str()))
+            .map(|(idx, _)| idx)
+            .collect();
+        self.redact(&reveal)
+    }
+
+    /// Produce a [RedactedJournal] that reveals only the given byte ranges of the
+    /// [data][Self::data] of the journal.
+    ///
+    /// Fails if a range does not start and end at the boundaries of fields, since fields are
+    /// revealed or redacted as a whole.
+    pub fn redact_ranges(&self, reveal: &[Range<usize>]) -> Result<RedactedJournal> {
+        let is_boundary = |offset: usize| {
+            offset == 0 || self.fields.iter().any(|field| field.range().end == offset)
+        };
+        for range in reveal {
+            ensure!(
+                is_boundary(range.start) && is_boundary(range.end),
+                "byte range {range:?} does not start and end at the boundaries of fields"
+            );
+        }
+        let reveal: Vec<usize> = self
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| {
+                !field.data.is_empty()
+                    && reveal.iter().any(|range| {
+                        range.start <= field.range().start && field.range().end <= range.end
+                    })
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        Ok(self.redact(&reveal))
+    }
+}
+
+/// A field of a [RedactedJournal].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RedactedField {
+    /// A field whose data is revealed.
+    Revealed(RedactableField),
+
+    /// A field whose data is hidden, represented by its [RedactableField::digest].
+    Redacted(Digest),
+}
+
+impl RedactedField {
+    fn digest(&self) -> Digest {
+        match self {
+            Self::Revealed(field) => field.digest(),
+            Self::Redacted(digest) => *digest,
+        }
+    }
+}
+
+/// A [RedactableJournal] in which some fields are hidden.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactedJournal {
+    /// The fields of the journal, in order.
+    pub fields: Vec<RedactedField>,
+}
+
+impl RedactedJournal {
+    /// The root of the Merkle tree over the fields, which is equal to the root of the
+    /// [RedactableJournal] that this was produced from.
+    pub fn root(&self) -> Digest {
+        merkle_root(self.fields.iter().map(RedactedField::digest).collect())
+    }
+
+    /// Check that this is a redaction of the fields committed to by `journal`, i.e. the journal
+    /// bytes of a receipt for a guest that called
+    /// [env::commit_redactable][crate::guest::env::commit_redactable].
+    pub fn verify_journal(&self, journal: &[u8]) -> Result<()> {
+        ensure!(
+            journal == self.root().as_bytes(),
+            "redacted journal does not match the committed journal"
+        );
+        Ok(())
+    }
+
+    /// The data of the field at `idx`, or `None` if it is redacted or out of range.
+    pub fn field(&self, idx: usize) -> Option<&[u8]> {
+        match self.fields.get(idx)? {
+            RedactedField::Revealed(field) => Some(&field.data),
+            RedactedField::Redacted(_) => None,
+        }
+    }
+
+    /// The data of the revealed field named `name`, if any.
+    ///
+    /// The names of redacted fields are hidden along with their data. Names are unique, since
+    /// [RedactableJournal::push_named] and [env::commit_field][crate::guest::env::commit_field]
+    /// reject duplicates.
+    pub fn field_by_name(&self, name: &str) -> Option<&[u8]> {
+        self.fields.iter().find_map(|field| match field {
+            RedactedField::Revealed(field) if field.name == name => Some(field.data.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// The byte range of the data of the journal held by each revealed field, with its data, in
+    /// order.
+    pub fn revealed_ranges(&self) -> Vec<(Range<usize>, &[u8])> {
+        self.fields
+            .iter()
+            .filter_map(|field| match field {
+                RedactedField::Revealed(field) => Some((field.range(), field.data.as_slice())),
+                RedactedField::Redacted(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// Compute the root of a binary Merkle tree over the field digests in `leaves`, carrying an odd
+/// node at the end of a level up to the next level unchanged, and commit to the number of leaves.
+///
+/// Leaves are hashed under a different tag than interior nodes, so a digest given for a redacted
+/// field cannot stand in for a subtree.
+fn merkle_root(leaves: Vec<Digest>) -> Digest {
+    let count = leaves.len() as u32;
+    let mut nodes: Vec<Digest> = leaves
+        .iter()
+        .map(|leaf| tagged_struct::<sha::Impl>("risc0.RedactableLeaf", &[leaf], &[]))
+        .collect();
+    if nodes.is_empty() {
+        nodes.push(Digest::ZERO);
+    }
+    while nodes.len() > 1 {
+        nodes = nodes
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => {
+                    tagged_struct::<sha::Impl>("risc0.RedactableNode", &[left, right], &[])
+                }
+                [node] => *node,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    tagged_struct::<sha::Impl>("risc0.RedactableJournal", &[nodes[0]], &[count])
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use risc0_binfmt::tagged_struct;
+
+    use super::{RedactableJournal, RedactedField, RedactedJournal};
+    use crate::sha::{self, Digest};
+
+    #[test]
+    fn redact() {
+        let mut journal = RedactableJournal::default();
+        for i in 0..5u32 {
+            journal.push(Digest::from([i; 8]), i.to_le_bytes().to_vec());
+        }
+        let root = journal.root();
+
+        let redacted = journal.redact(&[0, 3]);
+        redacted.verify_journal(root.as_bytes()).unwrap();
+        assert_eq!(redacted.field(0), Some(&0u32.to_le_bytes()[..]));
+        assert_eq!(redacted.field(1), None);
+        assert_eq!(redacted.field(3), Some(&3u32.to_le_bytes()[..]));
+
+        // Tampering with a revealed field is detected.
+        let mut tampered = redacted.clone();
+        if let RedactedField::Revealed(field) = &mut tampered.fields[3] {
+            field.data[0] ^= 1;
+        }
+        assert!(tampered.verify_journal(root.as_bytes()).is_err());
+
+        // The salt hides the data of a redacted field.
+        let mut resalted = journal.clone();
+        resalted.fields[1].salt = Digest::ZERO;
+        assert_ne!(resalted.redact(&[]), journal.redact(&[]));
+    }
+
+    #[test]
+    fn redact_fields() {
+        let mut journal = RedactableJournal::default();
+        journal
+            .push_named("a", Digest::from([1u32; 8]), b"x".to_vec())
+            .unwrap();
+        journal
+            .push_named("b", Digest::from([2u32; 8]), b"y".to_vec())
+            .unwrap();
+        journal
+            .push_named("c", Digest::from([3u32; 8]), b"z".to_vec())
+            .unwrap();
+
+        // Names are unique, except for unnamed fields.
+        let err = journal
+            .push_named("b", Digest::ZERO, b"w".to_vec())
+            .unwrap_err();
+        assert!(err.to_string().contains("duplicate field name"), "{err}");
+
+        let redacted = journal.redact_fields(&["c", "a"]);
+        redacted.verify_journal(journal.root().as_bytes()).unwrap();
+        assert_eq!(redacted.field_by_name("a"), Some(&b"x"[..]));
+        assert_eq!(redacted.field_by_name("b"), None);
+        assert_eq!(redacted.field_by_name("c"), Some(&b"z"[..]));
+
+        // The name is part of the commitment.
+        let mut renamed = redacted.clone();
+        if let RedactedField::Revealed(field) = &mut renamed.fields[0] {
+            field.name = "b".into();
+        }
+        assert!(renamed.verify_journal(journal.root().as_bytes()).is_err());
+    }
+
+    #[test]
+    fn root_binds_fields() {
+        let mut journal = RedactableJournal::default();
+        for i in 0..4u32 {
+            journal.push(Digest::from([i; 8]), i.to_le_bytes().to_vec());
+        }
+        let root = journal.root();
+
+        // An interior node cannot stand in for the fields under it.
+        let leaf = |idx: usize| {
+            tagged_struct::<sha::Impl>("risc0.RedactableLeaf", &[journal.fields[idx].digest()], &[])
+        };
+        let node = |left: Digest, right: Digest| {
+            tagged_struct::<sha::Impl>("risc0.RedactableNode", &[left, right], &[])
+        };
+        let collapsed = RedactedJournal {
+            fields: vec![
+                RedactedField::Redacted(node(leaf(0), leaf(1))),
+                RedactedField::Redacted(node(leaf(2), leaf(3))),
+            ],
+        };
+        assert!(collapsed.verify_journal(root.as_bytes()).is_err());
+
+        // Nor can the fields be reordered or dropped.
+        let mut reordered = journal.redact(&[]);
+        reordered.fields.swap(0, 1);
+        assert!(reordered.verify_journal(root.as_bytes()).is_err());
+        let mut truncated = journal.redact(&[]);
+        truncated.fields.pop();
+        assert!(truncated.verify_journal(root.as_bytes()).is_err());
+
+        // The root of an empty journal still commits to its length.
+        assert_ne!(RedactableJournal::default().root(), Digest::ZERO);
+    }
+
+    #[test]
+    fn redact_ranges() {
+        let mut journal = RedactableJournal::default();
+        for data in [&b"head"[..], b"secret", b"tail"] {
+            journal.push(Digest::from([data.len() as u32; 8]), data.to_vec());
+        }
+        assert_eq!(journal.data(), b"headsecrettail");
+
+        let redacted = journal.redact_ranges(&[0..4, 10..14]).unwrap();
+        redacted.verify_journal(journal.root().as_bytes()).unwrap();
+        assert_eq!(
+            redacted.revealed_ranges(),
+            vec![(0..4, &b"head"[..]), (10..14, &b"tail"[..])]
+        );
+
+        // A range spanning several fields reveals all of them.
+        let redacted = journal.redact_ranges(&[4..14]).unwrap();
+        assert_eq!(
+            redacted.revealed_ranges(),
+            vec![(4..10, &b"secret"[..]), (10..14, &b"tail"[..])]
+        );
+
+        // Fields are revealed whole.
+        assert!(journal.redact_ranges(&[2..4]).is_err());
+        assert!(journal.redact_ranges(&[0..5]).is_err());
+
+        // The offset of a field is part of the commitment.
+        let mut moved = journal.redact_ranges(&[0..4]).unwrap();
+        if let RedactedField::Revealed(field) = &mut moved.fields[0] {
+            field.offset = 1;
+        }
+        assert!(moved.verify_journal(journal.root().as_bytes()).is_err());
+    }
+}
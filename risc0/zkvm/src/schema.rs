@@ -0,0 +1,315 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Journal layouts declared once and shared by the guest and the host.
+//!
+//! A struct declared with [journal_schema][crate::journal_schema], in a crate used by both the
+//! guest and the host, can be committed by the guest with its `commit` method and decoded from
+//! the journal by the host with its `decode` method, or [Journal::decode_schema]. The guest
+//! commits the [digest][JournalSchema::schema_digest] of the schema before the fields, so that a
+//! host decoding the journal with another schema, or another version of it, gets a
+//! [SchemaError::Mismatch] rather than misread fields.
+//!
+//! ```rust
+//! risc0_zkvm::journal_schema! {
+//!     version = 2;
+//!
+//!     /// The public output of the guest.
+//!     #[derive(Debug, PartialEq)]
+//!     pub struct Output {
+//!         pub image_hash: [u8; 32],
+//!         pub count: u32,
+//!     }
+//! }
+//!
+//! // In the guest:
+//! // Output { image_hash, count }.commit();
+//!
+//! // In the host:
+//! # let output = Output { image_hash: [1; 32], count: 3 };
+//! # let journal = risc0_zkvm::Journal::new(bytemuck::cast_slice(
+//! #     &risc0_zkvm::schema::encode(&output).unwrap(),
+//! # ).to_vec());
+//! let output = Output::decode(&journal.bytes).unwrap();
+//! assert_eq!(output.count, 3);
+//! ```
+//!
+//! [Journal::decode_schema]: crate::Journal::decode_schema
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    serde::{to_vec, Deserializer, Error},
+    sha::{Digest, Impl, Sha256},
+};
+
+/// A journal layout, implemented by the structs declared with
+/// [journal_schema][crate::journal_schema].
+pub trait JournalSchema: Sized {
+    /// The name of the schema, which is the name of the struct.
+    const NAME: &'static str;
+
+    /// The version of the schema.
+    const VERSION: u32;
+
+    /// The name and type of each field, in the order they are committed.
+    const FIELDS: &'static [(&'static str, &'static str)];
+
+    /// The digest identifying the schema, committed before its fields.
+    ///
+    /// The digest covers the name, version, and fields of the schema, so it changes whenever one
+    /// of them does.
+    fn schema_digest() -> Digest {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(Self::NAME.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(&Self::VERSION.to_le_bytes());
+        for (name, ty) in Self::FIELDS {
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.push(b':');
+            // Different compilers may stringify a type with different whitespace.
+            bytes.extend(ty.bytes().filter(|byte| !byte.is_ascii_whitespace()));
+            bytes.push(0);
+        }
+        *Impl::hash_bytes(&bytes)
+    }
+
+    #[doc(hidden)]
+    fn write_fields(&self, writer: &mut FieldWriter) -> Result<(), Error>;
+
+    #[doc(hidden)]
+    fn read_fields(reader: &mut FieldReader) -> Result<Self, SchemaError>;
+}
+
+/// An error decoding a journal with a [JournalSchema].
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum SchemaError {
+    /// The journal was committed with another schema, or another version of the schema.
+    Mismatch {
+        /// The name of the schema used to decode the journal.
+        name: &'static str,
+        /// The version of the schema used to decode the journal.
+        version: u32,
+        /// The digest of the schema used to decode the journal.
+        expected: Digest,
+        /// The digest committed at the start of the journal.
+        found: Digest,
+    },
+    /// The journal does not hold the fields of the schema.
+    Decode(Error),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mismatch {
+                name,
+                version,
+                expected,
+                found,
+            } => write!(
+                f,
+                "journal does not match schema {name} v{version} ({expected}): found {found}"
+            ),
+            Self::Decode(err) => write!(f, "failed to decode journal fields: {err}"),
+        }
+    }
+}
+
+impl serde::ser::StdError for SchemaError {}
+
+/// Encode `data` as it is committed to the journal: the digest of its schema, followed by each
+/// of its fields.
+pub fn encode<T: JournalSchema>(data: &T) -> Result<Vec<u32>, Error> {
+    let mut writer = FieldWriter {
+        words: to_vec(&T::schema_digest())?,
+    };
+    data.write_fields(&mut writer)?;
+    Ok(writer.words)
+}
+
+/// Decode a journal committed with the schema `T`.
+///
+/// Data committed after the fields of the schema, if any, is ignored.
+pub fn decode<T: JournalSchema>(journal: &[u8]) -> Result<T, SchemaError> {
+    let words = bytemuck::pod_collect_to_vec::<u8, u32>(journal);
+    let mut reader = FieldReader {
+        deserializer: Deserializer::new(words.as_slice()),
+    };
+    let found: Digest = reader.read()?;
+    let expected = T::schema_digest();
+    if found != expected {
+        return Err(SchemaError::Mismatch {
+            name: T::NAME,
+            version: T::VERSION,
+            expected,
+            found,
+        });
+    }
+    T::read_fields(&mut reader)
+}
+
+#[doc(hidden)]
+pub struct FieldWriter {
+    words: Vec<u32>,
+}
+
+impl FieldWriter {
+    pub fn write<T: Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.words.extend(to_vec(value)?);
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub struct FieldReader<'a> {
+    deserializer: Deserializer<'a, &'a [u32]>,
+}
+
+impl FieldReader<'_> {
+    pub fn read<T: DeserializeOwned>(&mut self) -> Result<T, SchemaError> {
+        T::deserialize(&mut self.deserializer).map_err(SchemaError::Decode)
+    }
+}
+
+/// Declare a struct committed to the journal by the guest and decoded by the host, with a
+/// [JournalSchema] derived from its name, version, and fields.
+///
+/// The struct gets a `commit` method, which commits it to the journal in the guest, and a
+/// `decode` function, which decodes it from the journal in the host. The version is given before
+/// the struct, as `version = N;`, and should be increased when the meaning of a field changes
+/// without its name or type changing. Every field must implement `Serialize` and
+/// `DeserializeOwned`. See [crate::schema] for an example.
+#[macro_export]
+macro_rules! journal_schema {
+    (
+        version = $version:literal;
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_attr:meta])*
+                $field_vis:vis $field:ident: $ty:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        $vis struct $name {
+            $(
+                $(#[$field_attr])*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        impl $name {
+            /// Commit to the journal the digest of the schema, followed by each field.
+            #[allow(dead_code)]
+            $vis fn commit(&self) {
+                $crate::guest::env::commit_schema(self)
+            }
+
+            /// Decode a journal committed with this schema.
+            #[allow(dead_code)]
+            $vis fn decode(
+                journal: &[u8],
+            ) -> ::core::result::Result<Self, $crate::schema::SchemaError> {
+                $crate::schema::decode(journal)
+            }
+        }
+
+        impl $crate::schema::JournalSchema for $name {
+            const NAME: &'static str = stringify!($name);
+            const VERSION: u32 = $version;
+            const FIELDS: &'static [(&'static str, &'static str)] =
+                &[$((stringify!($field), stringify!($ty))),*];
+
+            fn write_fields(
+                &self,
+                writer: &mut $crate::schema::FieldWriter,
+            ) -> ::core::result::Result<(), $crate::serde::Error> {
+                $(writer.write(&self.$field)?;)*
+                Ok(())
+            }
+
+            fn read_fields(
+                reader: &mut $crate::schema::FieldReader,
+            ) -> ::core::result::Result<Self, $crate::schema::SchemaError> {
+                Ok(Self {
+                    $($field: reader.read()?,)*
+                })
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec, vec::Vec};
+
+    use super::{decode, encode, JournalSchema, SchemaError};
+    use crate::serde::to_vec;
+
+    crate::journal_schema! {
+        version = 1;
+        #[derive(Debug, PartialEq)]
+        struct Output {
+            name: String,
+            values: Vec<u32>,
+            flag: bool,
+        }
+    }
+
+    crate::journal_schema! {
+        version = 2;
+        #[derive(Debug, PartialEq)]
+        struct OutputV2 {
+            name: String,
+            values: Vec<u32>,
+            flag: bool,
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let output = Output {
+            name: "alice".into(),
+            values: vec![1, 2, 3],
+            flag: true,
+        };
+        let words = encode(&output).unwrap();
+        assert_eq!(words[..8], to_vec(&Output::schema_digest()).unwrap());
+        let journal: &[u8] = bytemuck::cast_slice(&words);
+        assert_eq!(Output::decode(journal).unwrap(), output);
+
+        // Another version of the schema rejects the journal.
+        assert_ne!(Output::schema_digest(), OutputV2::schema_digest());
+        assert!(matches!(
+            decode::<OutputV2>(journal),
+            Err(SchemaError::Mismatch {
+                name: "OutputV2",
+                version: 2,
+                ..
+            })
+        ));
+
+        // A truncated journal does not hold every field.
+        assert!(matches!(
+            Output::decode(&journal[..journal.len() - 4]),
+            Err(SchemaError::Decode(_))
+        ));
+    }
+}
@@ -0,0 +1,182 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Framing of input written with `ExecutorEnvBuilder::write_frame` and read with
+//! `env::read_frame`.
+//!
+//! A frame is a header of [HEADER_WORDS] words: [FRAME_MARKER], the index of the frame, the length
+//! in bytes of the [tag][FrameTag] of its type, and the length in words of its payload. The header
+//! is followed by the tag, zero-padded to a whole number of words, and the payload, serialized with
+//! [to_vec].
+
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use risc0_zkvm_platform::WORD_SIZE;
+use serde::Serialize;
+
+use super::{to_vec, Result};
+
+/// The first word of every frame, which tells a frame apart from input written without one.
+pub(crate) const FRAME_MARKER: u32 = u32::from_le_bytes(*b"R0IF");
+
+/// The number of words in the header of a frame.
+pub(crate) const HEADER_WORDS: usize = 4;
+
+/// The tag that an input frame records for the type of its data, and that the guest checks when
+/// it reads the frame.
+///
+/// Tags are spelled out by each type rather than derived from its name at compile time, so that
+/// the host and the guest agree on them whatever toolchains they are built with. Types that
+/// serialize alike share a tag, e.g. `str` and `String` are both tagged `String`. Implement this
+/// for the types of your own frames, with the same tag in the host and the guest:
+///
+/// ```
+/// use risc0_zkvm::serde::FrameTag;
+///
+/// struct Input {
+///     values: Vec<u32>,
+/// }
+///
+/// impl FrameTag for Input {
+///     fn tag() -> String {
+///         "Input".to_string()
+///     }
+/// }
+/// ```
+pub trait FrameTag {
+    /// The tag of the type.
+    fn tag() -> String;
+}
+
+macro_rules! impl_frame_tag {
+    ($($ty:ty => $tag:literal),* $(,)?) => {
+        $(
+            impl FrameTag for $ty {
+                fn tag() -> String {
+                    $tag.to_string()
+                }
+            }
+        )*
+    };
+}
+
+impl_frame_tag!(
+    () => "()",
+    bool => "bool",
+    char => "char",
+    u8 => "u8",
+    u16 => "u16",
+    u32 => "u32",
+    u64 => "u64",
+    u128 => "u128",
+    usize => "usize",
+    i8 => "i8",
+    i16 => "i16",
+    i32 => "i32",
+    i64 => "i64",
+    i128 => "i128",
+    isize => "isize",
+    f32 => "f32",
+    f64 => "f64",
+    str => "String",
+    String => "String",
+);
+
+impl<T: FrameTag + ?Sized> FrameTag for &T {
+    fn tag() -> String {
+        T::tag()
+    }
+}
+
+impl<T: FrameTag + ?Sized> FrameTag for Box<T> {
+    fn tag() -> String {
+        T::tag()
+    }
+}
+
+impl<T: FrameTag> FrameTag for [T] {
+    fn tag() -> String {
+        format!("Vec<{}>", T::tag())
+    }
+}
+
+impl<T: FrameTag> FrameTag for Vec<T> {
+    fn tag() -> String {
+        format!("Vec<{}>", T::tag())
+    }
+}
+
+impl<T: FrameTag, const N: usize> FrameTag for [T; N] {
+    fn tag() -> String {
+        format!("[{}; {N}]", T::tag())
+    }
+}
+
+impl<T: FrameTag> FrameTag for Option<T> {
+    fn tag() -> String {
+        format!("Option<{}>", T::tag())
+    }
+}
+
+impl<T: FrameTag> FrameTag for BTreeSet<T> {
+    fn tag() -> String {
+        format!("BTreeSet<{}>", T::tag())
+    }
+}
+
+impl<K: FrameTag, V: FrameTag> FrameTag for BTreeMap<K, V> {
+    fn tag() -> String {
+        format!("BTreeMap<{}, {}>", K::tag(), V::tag())
+    }
+}
+
+macro_rules! impl_frame_tag_tuple {
+    ($($name:ident)+) => {
+        impl<$($name: FrameTag),+> FrameTag for ($($name,)+) {
+            fn tag() -> String {
+                let tags = [$($name::tag()),+];
+                format!("({})", tags.join(", "))
+            }
+        }
+    };
+}
+
+impl_frame_tag_tuple!(A);
+impl_frame_tag_tuple!(A B);
+impl_frame_tag_tuple!(A B C);
+impl_frame_tag_tuple!(A B C D);
+impl_frame_tag_tuple!(A B C D E);
+impl_frame_tag_tuple!(A B C D E F);
+
+/// Encode `data` as the frame at `index`.
+pub(crate) fn encode<T: Serialize + FrameTag + ?Sized>(index: u32, data: &T) -> Result<Vec<u32>> {
+    let name = T::tag();
+    let payload = to_vec(data)?;
+    let mut name_words = vec![0u32; name.len().div_ceil(WORD_SIZE)];
+    bytemuck::cast_slice_mut::<u32, u8>(&mut name_words)[..name.len()]
+        .copy_from_slice(name.as_bytes());
+
+    let mut words = Vec::with_capacity(HEADER_WORDS + name_words.len() + payload.len());
+    words.extend([FRAME_MARKER, index, name.len() as u32, payload.len() as u32]);
+    words.extend(name_words);
+    words.extend(payload);
+    Ok(words)
+}
@@ -40,10 +40,12 @@
 
 mod deserializer;
 mod err;
+pub(crate) mod frame;
 mod serializer;
 
 pub use deserializer::{from_slice, Deserializer, WordRead};
 pub use err::{Error, Result};
+pub use frame::FrameTag;
 pub use serializer::{to_vec, to_vec_with_capacity, Serializer, WordWrite};
 
 #[cfg(test)]
@@ -84,4 +86,25 @@ mod tests {
         let output: NaiveDate = from_slice(date_vec.as_slice()).unwrap();
         assert_eq!(input, output);
     }
+
+    #[test]
+    fn test_frame() {
+        use crate::serde::frame::{encode, FrameTag, FRAME_MARKER, HEADER_WORDS};
+
+        assert_eq!(u32::tag(), "u32");
+        assert_eq!(<&str>::tag(), String::tag());
+        assert_eq!(
+            <(Vec<[u8; 4]>, Option<String>)>::tag(),
+            "(Vec<[u8; 4]>, Option<String>)"
+        );
+
+        let words = encode(2, &(1u32, 2u64)).unwrap();
+        assert_eq!(words[..HEADER_WORDS], [FRAME_MARKER, 2, 10, 3]);
+        assert_eq!(
+            &bytemuck::cast_slice::<u32, u8>(&words[4..7])[..10],
+            b"(u32, u64)"
+        );
+        let output: (u32, u64) = from_slice(&words[7..]).unwrap();
+        assert_eq!(output, (1, 2));
+    }
 }
@@ -0,0 +1,92 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fs, path::Path};
+
+use clap::Parser;
+use risc0_zkvm::{get_prover_server, is_dev_mode, sha::Digest, ExecutorEnv, ProverOpts, VERSION};
+use risc0_zkvm_methods::{FIB_ELF, FIB_ID};
+
+const COMPAT_DIR: &str = "risc0/zkvm/src/compat";
+
+/// Prove the golden receipt of the current release for `risc0_zkvm::compat`.
+#[derive(Parser)]
+pub struct GenCompatReceipt;
+
+impl GenCompatReceipt {
+    pub fn run(&self) {
+        assert!(
+            !is_dev_mode(),
+            "Golden receipts must not be proven in dev mode"
+        );
+
+        let iterations = 100;
+        let env = ExecutorEnv::builder()
+            .write_slice(&[iterations])
+            .build()
+            .unwrap();
+        let receipt = get_prover_server(&ProverOpts::succinct())
+            .unwrap()
+            .prove(env, FIB_ELF)
+            .unwrap()
+            .receipt;
+
+        // Encoded as `risc0_zkvm::compat::Golden`.
+        let golden = (
+            Digest::from(FIB_ID),
+            receipt.journal.bytes.clone(),
+            bincode::serialize(&receipt).unwrap(),
+        );
+        let path = Path::new(COMPAT_DIR).join(format!("v{VERSION}.bin"));
+        fs::write(&path, bincode::serialize(&golden).unwrap()).unwrap();
+        println!("Wrote {}", path.display());
+
+        write_index();
+    }
+}
+
+/// Regenerate the list of golden files in the compat module, oldest release first.
+fn write_index() {
+    let mut releases: Vec<(Vec<u64>, String)> = fs::read_dir(COMPAT_DIR)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter_map(|name| {
+            let release = name.strip_prefix('v')?.strip_suffix(".bin")?.to_string();
+            let key = release.split('.').map(|part| part.parse().unwrap_or(0));
+            Some((key.collect(), release))
+        })
+        .collect();
+    releases.sort();
+
+    let entries: String = releases
+        .iter()
+        .map(|(_, release)| format!("    (\"{release}\", include_bytes!(\"v{release}.bin\")),\n"))
+        .collect();
+    let index = Path::new(COMPAT_DIR).join("golden.rs");
+    let header: String = fs::read_to_string(&index)
+        .unwrap()
+        .lines()
+        .take_while(|line| line.starts_with("//"))
+        .map(|line| format!("{line}\n"))
+        .collect();
+    fs::write(
+        index,
+        format!(
+            "{header}\n// Generated by `cargo xtask gen-compat-receipt`. Do not edit.\n\n\
+             /// The golden file of each release, oldest first.\n\
+             pub(super) const GOLDEN: &[(&str, &[u8])] = &[\n{entries}];\n"
+        ),
+    )
+    .unwrap();
+}
@@ -19,6 +19,8 @@ mod bootstrap_groth16;
 mod bootstrap_poseidon;
 mod bootstrap_protos;
 #[cfg(feature = "zkvm")]
+mod gen_compat_receipt;
+#[cfg(feature = "zkvm")]
 mod gen_receipt;
 mod install;
 
@@ -26,7 +28,10 @@ use clap::{Parser, Subcommand};
 use tracing_subscriber::{prelude::*, EnvFilter};
 
 #[cfg(feature = "zkvm")]
-use self::{bootstrap::Bootstrap, bootstrap_groth16::BootstrapGroth16, gen_receipt::GenReceipt};
+use self::{
+    bootstrap::Bootstrap, bootstrap_groth16::BootstrapGroth16,
+    gen_compat_receipt::GenCompatReceipt, gen_receipt::GenReceipt,
+};
 use self::{
     bootstrap_poseidon::BootstrapPoseidon, bootstrap_protos::BootstrapProtos, install::Install,
 };
@@ -46,6 +51,8 @@ enum Commands {
     BootstrapPoseidon(BootstrapPoseidon),
     BootstrapProtos(BootstrapProtos),
     #[cfg(feature = "zkvm")]
+    GenCompatReceipt(GenCompatReceipt),
+    #[cfg(feature = "zkvm")]
     GenReceipt(GenReceipt),
     Install(Install),
 }
@@ -61,6 +68,8 @@ impl Commands {
             Commands::BootstrapProtos(cmd) => cmd.run(),
             Commands::Install(cmd) => cmd.run(),
             #[cfg(feature = "zkvm")]
+            Commands::GenCompatReceipt(cmd) => cmd.run(),
+            #[cfg(feature = "zkvm")]
             Commands::GenReceipt(cmd) => cmd.run(),
         }
     }